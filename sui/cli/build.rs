@@ -0,0 +1,54 @@
+//! Embeds the build metadata printed by `version --verbose`.
+
+use std::process::Command;
+
+fn main() {
+    println!("cargo:rerun-if-changed=../../.git/HEAD");
+    println!("cargo:rerun-if-changed=../../.git/index");
+    println!("cargo:rerun-if-env-changed=SOURCE_DATE_EPOCH");
+
+    let git = |args: &[&str]| {
+        Command::new("git")
+            .args(args)
+            .output()
+            .ok()
+            .filter(|output| output.status.success())
+            .map(|output| {
+                String::from_utf8_lossy(&output.stdout).trim().to_owned()
+            })
+    };
+    let commit = match git(&["rev-parse", "HEAD"]) {
+        Some(commit)
+            if git(&["status", "--porcelain"])
+                .is_some_and(|s| !s.is_empty()) =>
+        {
+            format!("{commit}-dirty")
+        }
+        Some(commit) => commit,
+        None => "unknown".to_owned(),
+    };
+
+    // reproducible builds pin the timestamp
+    let timestamp = std::env::var("SOURCE_DATE_EPOCH").unwrap_or_else(|_| {
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|since| since.as_secs().to_string())
+            .unwrap_or_default()
+    });
+
+    let mut features: Vec<_> = std::env::vars()
+        .filter_map(|(key, _)| {
+            key.strip_prefix("CARGO_FEATURE_")
+                .map(|feature| feature.to_lowercase().replace('_', "-"))
+        })
+        .collect();
+    features.sort();
+
+    println!("cargo:rustc-env=ATOMA_CLI_GIT_COMMIT={commit}");
+    println!("cargo:rustc-env=ATOMA_CLI_BUILD_TIMESTAMP={timestamp}");
+    println!(
+        "cargo:rustc-env=ATOMA_CLI_TARGET={}",
+        std::env::var("TARGET").unwrap_or_default()
+    );
+    println!("cargo:rustc-env=ATOMA_CLI_FEATURES={}", features.join(","));
+}
@@ -0,0 +1,16 @@
+//! Aggregations over the events emitted by the contracts.
+//!
+//! Gateways use them for routing decisions and node operators for pricing.
+//! The events are read from the full node's event index, so how far back
+//! the history goes depends on its pruning configuration.
+
+mod dispute_rates;
+mod echelon_fees;
+mod events;
+mod settlement_latency;
+mod task_prices;
+
+pub(crate) use dispute_rates::command as dispute_rates;
+pub(crate) use echelon_fees::command as echelon_fees;
+pub(crate) use settlement_latency::command as settlement_latency;
+pub(crate) use task_prices::command as task_prices;
@@ -0,0 +1,77 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use super::events::{self, parse_u64};
+use crate::{prelude::*, DB_MODULE_NAME};
+
+const TRY_SETTLE_EVENT_NAME: &str = "StackTrySettleEvent";
+const DISPUTE_EVENT_NAME: &str = "StackAttestationDisputeEvent";
+
+#[derive(Default)]
+struct NodeDisputes {
+    settlements: u64,
+    disputed: u64,
+}
+
+/// Prints, per node, how many stack settlements it attempted and how many
+/// of them an attestation node disputed, highest dispute rate first.
+///
+/// A stack can be disputed by several attestation nodes, it's counted once.
+pub(crate) async fn command(
+    context: &mut Context,
+    node_small_id: Option<u64>,
+) -> Result<()> {
+    let mut nodes: BTreeMap<u64, NodeDisputes> = BTreeMap::new();
+    for event in
+        events::query(context, DB_MODULE_NAME, TRY_SETTLE_EVENT_NAME).await?
+    {
+        if let Some(node) = parse_u64(&event.json["selected_node_id"]["inner"])
+        {
+            nodes.entry(node).or_default().settlements += 1;
+        }
+    }
+
+    let mut disputed_stacks = BTreeSet::new();
+    for event in
+        events::query(context, DB_MODULE_NAME, DISPUTE_EVENT_NAME).await?
+    {
+        let json = &event.json;
+        let (Some(stack), Some(node)) = (
+            parse_u64(&json["stack_small_id"]["inner"]),
+            parse_u64(&json["original_node_id"]["inner"]),
+        ) else {
+            continue;
+        };
+        if disputed_stacks.insert(stack) {
+            nodes.entry(node).or_default().disputed += 1;
+        }
+    }
+
+    let mut rates: Vec<_> = nodes
+        .into_iter()
+        .filter(|(node, _)| node_small_id.map_or(true, |id| id == *node))
+        .map(|(node, disputes)| {
+            let permille = (disputes.disputed * 1_000)
+                .checked_div(disputes.settlements)
+                .unwrap_or_default();
+            (node, disputes, permille)
+        })
+        .collect();
+    rates.sort_by(|(a, _, a_permille), (b, _, b_permille)| {
+        b_permille.cmp(a_permille).then(a.cmp(b))
+    });
+
+    if rates.is_empty() {
+        println!("No settlements found");
+    }
+    for (node, disputes, permille) in rates {
+        println!(
+            "Node {node}: {} settlement(s), {} disputed, {}.{}%",
+            disputes.settlements,
+            disputes.disputed,
+            permille / 10,
+            permille % 10,
+        );
+    }
+
+    Ok(())
+}
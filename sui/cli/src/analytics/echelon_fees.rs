@@ -0,0 +1,82 @@
+use super::events::{self, parse_u64};
+use crate::{db, prelude::*, DB_MODULE_NAME};
+
+const FEE_UPDATED_EVENT_NAME: &str = "ModelEchelonFeeUpdatedEvent";
+
+/// Prints every change of the echelon fees, oldest first, and if a model
+/// is given also the current fees of its echelons.
+///
+/// Only changes made with `set_model_echelon_fee` are tracked, the fees an
+/// echelon was created with are not emitted.
+pub(crate) async fn command(
+    context: &mut Context,
+    model_name: Option<&str>,
+) -> Result<()> {
+    let changes =
+        events::query(context, DB_MODULE_NAME, FEE_UPDATED_EVENT_NAME).await?;
+
+    let mut printed = 0;
+    for event in changes {
+        let json = &event.json;
+        let Some(model) = json["model_name"].as_str() else {
+            continue;
+        };
+        if model_name.is_some_and(|model_name| model_name != model) {
+            continue;
+        }
+        let (
+            Some(echelon),
+            Some(old_input),
+            Some(old_output),
+            Some(new_input),
+            Some(new_output),
+        ) = (
+            parse_u64(&json["echelon_id"]["id"]),
+            parse_u64(&json["old_input_fee_per_token"]),
+            parse_u64(&json["old_output_fee_per_token"]),
+            parse_u64(&json["new_input_fee_per_token"]),
+            parse_u64(&json["new_output_fee_per_token"]),
+        )
+        else {
+            continue;
+        };
+
+        println!(
+            "{model} echelon {echelon} at {} ms: input {} -> {} ({}), \
+            output {} -> {} ({})",
+            event.timestamp_ms,
+            context.toma(old_input),
+            context.toma(new_input),
+            percent_change(old_input, new_input),
+            context.toma(old_output),
+            context.toma(new_output),
+            percent_change(old_output, new_output),
+        );
+        printed += 1;
+    }
+    if printed == 0 {
+        println!("No fee changes found");
+    }
+
+    if let Some(model_name) = model_name {
+        let (_, echelons) = db::load_model(context, model_name).await?;
+        for echelon in echelons {
+            println!(
+                "{model_name} echelon {} now: input {}, output {}",
+                echelon.id,
+                context.toma(echelon.input_fee_per_token),
+                context.toma(echelon.output_fee_per_token),
+            );
+        }
+    }
+
+    Ok(())
+}
+
+fn percent_change(old: u64, new: u64) -> String {
+    if old == 0 {
+        return "n/a".to_string();
+    }
+    let change = (new as i128 - old as i128) * 100 / old as i128;
+    format!("{change:+}%")
+}
@@ -0,0 +1,57 @@
+use move_core_types::language_storage::StructTag;
+use serde_json::Value;
+use sui_sdk::rpc_types::{EventFilter, Page};
+
+use crate::prelude::*;
+
+pub(super) struct Event {
+    /// When the checkpoint that includes the event was created.
+    pub(super) timestamp_ms: u64,
+    pub(super) json: Value,
+}
+
+/// All events of the given type, oldest first.
+pub(super) async fn query(
+    context: &mut Context,
+    module: &str,
+    event_name: &str,
+) -> Result<Vec<Event>> {
+    let filter = EventFilter::MoveEventType(StructTag {
+        address: context.unwrap_atoma_package_id().into(),
+        module: FromStr::from_str(module)?,
+        name: FromStr::from_str(event_name)?,
+        type_params: vec![],
+    });
+
+    let client = context.get_client().await?;
+    let mut cursor = None;
+    let mut events = vec![];
+    loop {
+        let Page {
+            data,
+            has_next_page,
+            next_cursor,
+        } = client
+            .event_api()
+            .query_events(filter.clone(), cursor, None, false)
+            .await?;
+        cursor = next_cursor;
+
+        events.extend(data.into_iter().map(|event| Event {
+            timestamp_ms: event.timestamp_ms.unwrap_or_default(),
+            json: event.parsed_json,
+        }));
+
+        if !has_next_page {
+            break;
+        }
+    }
+    debug!("Found {} {event_name} event(s)", events.len());
+
+    Ok(events)
+}
+
+/// u64s are strings in the event JSON.
+pub(super) fn parse_u64(value: &Value) -> Option<u64> {
+    value.as_str().and_then(|s| s.parse().ok())
+}
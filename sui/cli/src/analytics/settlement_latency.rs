@@ -0,0 +1,88 @@
+use std::collections::HashMap;
+
+use super::events;
+use crate::{prelude::*, GATE_MODULE_NAME, SETTLEMENT_MODULE_NAME};
+
+const PROMPT_EVENT_NAMES: [&str; 2] =
+    ["Text2TextPromptEvent", "Text2ImagePromptEvent"];
+/// Cancelled tickets that paid nodes were settled partially.
+const SETTLED_EVENT_NAMES: [&str; 2] = ["SettledEvent", "PromptCancelledEvent"];
+/// Upper bounds of the histogram buckets, the last bucket is unbounded.
+const HISTOGRAM_BOUNDS_MS: [u64; 5] = [1_000, 5_000, 15_000, 60_000, 300_000];
+
+/// Prints the distribution of the time between a prompt being submitted
+/// and its ticket being settled, either fully or partially.
+///
+/// The timestamps are those of the checkpoints that include the events, so
+/// the latencies are only as precise as the checkpoint interval.
+pub(crate) async fn command(context: &mut Context) -> Result<()> {
+    let mut submitted_at_ms = HashMap::new();
+    for event_name in PROMPT_EVENT_NAMES {
+        for event in
+            events::query(context, GATE_MODULE_NAME, event_name).await?
+        {
+            if let Some(ticket_id) = event.json["ticket_id"].as_str() {
+                submitted_at_ms
+                    .insert(ticket_id.to_string(), event.timestamp_ms);
+            }
+        }
+    }
+
+    let mut latencies_ms = vec![];
+    for event_name in SETTLED_EVENT_NAMES {
+        for event in
+            events::query(context, SETTLEMENT_MODULE_NAME, event_name).await?
+        {
+            if event.json["paid_nodes"]
+                .as_array()
+                .is_some_and(Vec::is_empty)
+            {
+                continue;
+            }
+            let Some(submitted_at_ms) = event.json["ticket_id"]
+                .as_str()
+                .and_then(|ticket_id| submitted_at_ms.remove(ticket_id))
+            else {
+                continue;
+            };
+            latencies_ms
+                .push(event.timestamp_ms.saturating_sub(submitted_at_ms));
+        }
+    }
+    latencies_ms.sort_unstable();
+
+    println!("Settled tickets: {}", latencies_ms.len());
+    println!("Not settled yet: {}", submitted_at_ms.len());
+    if latencies_ms.is_empty() {
+        return Ok(());
+    }
+
+    for (label, percentile) in [("p50", 50), ("p90", 90), ("p99", 99)] {
+        println!("{label}: {} ms", nearest_rank(&latencies_ms, percentile));
+    }
+    println!("min: {} ms", latencies_ms[0]);
+    println!("max: {} ms", latencies_ms[latencies_ms.len() - 1]);
+
+    let mut lower_bound_ms = 0;
+    for upper_bound_ms in HISTOGRAM_BOUNDS_MS {
+        let count = latencies_ms
+            .iter()
+            .filter(|ms| **ms >= lower_bound_ms && **ms < upper_bound_ms)
+            .count();
+        println!("[{lower_bound_ms}, {upper_bound_ms}) ms: {count}");
+        lower_bound_ms = upper_bound_ms;
+    }
+    let count = latencies_ms
+        .iter()
+        .filter(|ms| **ms >= lower_bound_ms)
+        .count();
+    println!("[{lower_bound_ms}, ...) ms: {count}");
+
+    Ok(())
+}
+
+/// The sorted values must not be empty.
+fn nearest_rank(sorted: &[u64], percentile: usize) -> u64 {
+    let rank = (percentile * sorted.len()).div_ceil(100).max(1);
+    sorted[rank - 1]
+}
@@ -0,0 +1,72 @@
+use std::collections::BTreeMap;
+
+use super::events::{self, parse_u64};
+use crate::{prelude::*, DB_MODULE_NAME};
+
+const STACK_CREATED_EVENT_NAME: &str = "StackCreatedEvent";
+
+#[derive(Default)]
+struct Bucket {
+    stacks: u64,
+    compute_units: u128,
+    /// Sum of price per one million compute units times compute units.
+    weighted_price: u128,
+}
+
+/// Prints the average price per one million compute units that users paid
+/// for the stacks of each task, per time bucket.
+///
+/// The average is weighted by the compute units of each stack so that a
+/// single small stack bought at an odd price doesn't skew it.
+pub(crate) async fn command(
+    context: &mut Context,
+    task_small_id: Option<u64>,
+    bucket_hours: u64,
+) -> Result<()> {
+    if bucket_hours == 0 {
+        anyhow::bail!("The bucket must be at least an hour long");
+    }
+    let bucket_ms = bucket_hours * 3_600_000;
+
+    let mut buckets: BTreeMap<(u64, u64), Bucket> = BTreeMap::new();
+    for event in
+        events::query(context, DB_MODULE_NAME, STACK_CREATED_EVENT_NAME).await?
+    {
+        let json = &event.json;
+        let (Some(task), Some(compute_units), Some(price)) = (
+            parse_u64(&json["task_small_id"]["inner"]),
+            parse_u64(&json["num_compute_units"]),
+            parse_u64(&json["price_per_one_million_compute_units"]),
+        ) else {
+            continue;
+        };
+        if task_small_id.is_some_and(|task_small_id| task_small_id != task) {
+            continue;
+        }
+
+        let bucket_start_ms = event.timestamp_ms / bucket_ms * bucket_ms;
+        let bucket = buckets.entry((task, bucket_start_ms)).or_default();
+        bucket.stacks += 1;
+        bucket.compute_units += compute_units as u128;
+        bucket.weighted_price += price as u128 * compute_units as u128;
+    }
+
+    if buckets.is_empty() {
+        println!("No stacks found");
+    }
+    for ((task, bucket_start_ms), bucket) in buckets {
+        let average_price = bucket
+            .weighted_price
+            .checked_div(bucket.compute_units)
+            .unwrap_or_default();
+        println!(
+            "Task {task} from {bucket_start_ms} ms: {} stack(s), {} compute \
+            units, {} per 1M compute units on average",
+            bucket.stacks,
+            bucket.compute_units,
+            context.toma(average_price),
+        );
+    }
+
+    Ok(())
+}
@@ -0,0 +1,9 @@
+//! Benchmarks of the RPC heavy commands.
+//!
+//! They track the number of RPC calls and the wall time, and can run against
+//! recorded RPC responses so that the results are comparable across runs.
+
+mod rpc_recorder;
+mod run;
+
+pub(crate) use run::{command as run, Gates, RpcFixtures, Target};
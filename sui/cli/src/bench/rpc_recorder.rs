@@ -0,0 +1,186 @@
+//! A JSON-RPC proxy that sits between the CLI and the fullnode.
+//!
+//! It counts the calls, and either records the responses of the fullnode or
+//! replays previously recorded ones so that benchmarks do not depend on the
+//! network or on the on-chain state.
+
+use std::{
+    collections::HashMap,
+    net::SocketAddr,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+};
+
+use serde_json::json;
+use tokio::{
+    io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader},
+    net::{TcpListener, TcpStream},
+    sync::Mutex,
+};
+
+use crate::prelude::*;
+
+pub(crate) enum Mode {
+    /// Forwards calls to the fullnode.
+    Forward { upstream: String },
+    /// Forwards calls to the fullnode and writes the responses into a file.
+    Record { upstream: String, out: PathBuf },
+    /// Answers calls from a file written in the record mode.
+    Replay { fixtures: PathBuf },
+}
+
+pub(crate) struct RpcRecorder {
+    pub(crate) url: String,
+    state: Arc<State>,
+}
+
+struct State {
+    upstream: Option<String>,
+    http: reqwest::Client,
+    calls: AtomicU64,
+    /// Responses keyed by method and params, without the request ID.
+    responses: Mutex<HashMap<String, serde_json::Value>>,
+}
+
+impl RpcRecorder {
+    pub(crate) async fn start(mode: &Mode) -> Result<Self> {
+        let (upstream, responses) = match mode {
+            Mode::Forward { upstream } | Mode::Record { upstream, .. } => {
+                (Some(upstream.clone()), HashMap::new())
+            }
+            Mode::Replay { fixtures } => (
+                None,
+                serde_json::from_str(&std::fs::read_to_string(fixtures)?)?,
+            ),
+        };
+
+        let listener =
+            TcpListener::bind(SocketAddr::from(([127, 0, 0, 1], 0))).await?;
+        let url = format!("http://{}", listener.local_addr()?);
+        let state = Arc::new(State {
+            upstream,
+            http: reqwest::Client::new(),
+            calls: AtomicU64::new(0),
+            responses: Mutex::new(responses),
+        });
+
+        let server_state = Arc::clone(&state);
+        tokio::spawn(async move {
+            loop {
+                let Ok((stream, _)) = listener.accept().await else {
+                    break;
+                };
+                let state = Arc::clone(&server_state);
+                tokio::spawn(async move {
+                    if let Err(err) = state.serve(stream).await {
+                        debug!("RPC recorder connection failed: {err}");
+                    }
+                });
+            }
+        });
+        debug!("RPC recorder listening on {url}");
+
+        Ok(Self { url, state })
+    }
+
+    pub(crate) fn calls(&self) -> u64 {
+        self.state.calls.load(Ordering::SeqCst)
+    }
+
+    pub(crate) async fn save(&self, out: &Path) -> Result<()> {
+        let responses = self.state.responses.lock().await;
+        std::fs::write(out, serde_json::to_string_pretty(&*responses)?)?;
+        println!("Recorded {} RPC response(s) to {out:?}", responses.len());
+        Ok(())
+    }
+}
+
+impl State {
+    /// The client keeps connections alive, so we serve requests until it
+    /// closes the connection.
+    async fn serve(&self, stream: TcpStream) -> Result<()> {
+        let mut stream = BufReader::new(stream);
+        loop {
+            let mut request_line = String::new();
+            if stream.read_line(&mut request_line).await? == 0 {
+                return Ok(());
+            }
+
+            let mut content_length = 0;
+            loop {
+                let mut line = String::new();
+                stream.read_line(&mut line).await?;
+                let line = line.trim_end();
+                if line.is_empty() {
+                    break;
+                }
+                if let Some((name, value)) = line.split_once(':') {
+                    if name.trim().eq_ignore_ascii_case("content-length") {
+                        content_length = value.trim().parse()?;
+                    }
+                }
+            }
+            let mut body = vec![0; content_length];
+            stream.read_exact(&mut body).await?;
+
+            let response = self.call(serde_json::from_slice(&body)?).await?;
+            let response = response.to_string();
+            let head = format!(
+                "HTTP/1.1 200 OK\r\n\
+                Content-Type: application/json\r\n\
+                Content-Length: {}\r\n\r\n",
+                response.len()
+            );
+            let stream = stream.get_mut();
+            stream.write_all(head.as_bytes()).await?;
+            stream.write_all(response.as_bytes()).await?;
+        }
+    }
+
+    async fn call(
+        &self,
+        request: serde_json::Value,
+    ) -> Result<serde_json::Value> {
+        self.calls.fetch_add(1, Ordering::SeqCst);
+        let key = format!("{} {}", request["method"], request["params"]);
+
+        let mut response = match &self.upstream {
+            Some(upstream) => {
+                let response: serde_json::Value = self
+                    .http
+                    .post(upstream)
+                    .json(&request)
+                    .send()
+                    .await?
+                    .json()
+                    .await?;
+                self.responses.lock().await.insert(key, response.clone());
+                response
+            }
+            None => self
+                .responses
+                .lock()
+                .await
+                .get(&key)
+                .cloned()
+                .unwrap_or_else(|| {
+                    error!("No recorded response for {key}");
+                    json!({
+                        "jsonrpc": "2.0",
+                        "error": {
+                            "code": -32603,
+                            "message": "No recorded response",
+                        },
+                    })
+                }),
+        };
+        if let Some(response) = response.as_object_mut() {
+            response.insert("id".to_owned(), request["id"].clone());
+        }
+
+        Ok(response)
+    }
+}
@@ -0,0 +1,164 @@
+use std::{
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+use clap::ValueEnum;
+use serde_json::json;
+
+use super::rpc_recorder::{Mode, RpcRecorder};
+use crate::{db, prelude::*, settle};
+
+const BENCH_ENV_ALIAS: &str = "bench";
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+pub(crate) enum Target {
+    /// `settle list-tickets`, first page only.
+    ListTickets,
+    /// Finding the db manager and node badges owned by the active address.
+    BadgeDiscovery,
+    /// `db list-tasks`, first page only.
+    ListTasks,
+    /// `db list-echelons`, requires a model name.
+    ListEchelons,
+}
+
+pub(crate) enum RpcFixtures {
+    /// Writes the fullnode responses into the file.
+    Record(PathBuf),
+    /// Answers RPC calls from a file written with `Record`.
+    Replay(PathBuf),
+}
+
+/// Fails the benchmark if exceeded.
+pub(crate) struct Gates {
+    pub(crate) max_rpc_calls: Option<u64>,
+    pub(crate) max_mean_wall_time: Option<Duration>,
+}
+
+/// 1. Point the wallet at an RPC recorder that forwards to the fullnode of
+///    the active env or replays recorded responses.
+/// 2. Run the target once to warm up the client, and then the given number of
+///    times while counting RPC calls and measuring wall time.
+/// 3. Check the gates and write the report.
+pub(crate) async fn command(
+    context: &mut Context,
+    target: Target,
+    model: Option<&str>,
+    iterations: u64,
+    rpc_fixtures: Option<RpcFixtures>,
+    gates: Gates,
+    report: Option<&Path>,
+) -> Result<()> {
+    if iterations == 0 {
+        anyhow::bail!("At least one iteration is required");
+    }
+
+    // 1.
+    let active_env = context.wallet.config.get_active_env()?.clone();
+    let upstream = active_env.rpc.clone();
+    let mode = match rpc_fixtures {
+        Some(RpcFixtures::Record(out)) => Mode::Record { upstream, out },
+        Some(RpcFixtures::Replay(fixtures)) => Mode::Replay { fixtures },
+        None => Mode::Forward { upstream },
+    };
+    let recorder = RpcRecorder::start(&mode).await?;
+
+    let mut bench_env = active_env;
+    bench_env.alias = BENCH_ENV_ALIAS.to_owned();
+    bench_env.rpc = recorder.url.clone();
+    bench_env.ws = None;
+    context.wallet.config.envs.push(bench_env);
+    context.wallet.config.active_env = Some(BENCH_ENV_ALIAS.to_owned());
+
+    // 2.
+    run_target(context, target, model).await?;
+
+    let mut rpc_calls = vec![];
+    let mut wall_times = vec![];
+    for _ in 0..iterations {
+        let calls_before = recorder.calls();
+        let started_at = Instant::now();
+        run_target(context, target, model).await?;
+        wall_times.push(started_at.elapsed());
+        rpc_calls.push(recorder.calls() - calls_before);
+    }
+
+    if let Mode::Record { out, .. } = &mode {
+        recorder.save(out).await?;
+    }
+
+    // 3.
+    let max_rpc_calls = *rpc_calls.iter().max().unwrap();
+    let mean_wall_time =
+        wall_times.iter().sum::<Duration>() / wall_times.len() as u32;
+    wall_times.sort();
+    let median_wall_time = wall_times[wall_times.len() / 2];
+    let max_wall_time = *wall_times.last().unwrap();
+
+    println!(
+        "{target:?}: {iterations} iteration(s), \
+        {max_rpc_calls} RPC call(s), \
+        wall time mean {mean_wall_time:?}, \
+        median {median_wall_time:?}, max {max_wall_time:?}"
+    );
+
+    if let Some(report) = report {
+        let report_json = json!({
+            "target": format!("{target:?}"),
+            "iterations": iterations,
+            "rpc_calls": rpc_calls,
+            "mean_wall_time_ms": mean_wall_time.as_millis() as u64,
+            "median_wall_time_ms": median_wall_time.as_millis() as u64,
+            "max_wall_time_ms": max_wall_time.as_millis() as u64,
+        });
+        std::fs::write(report, serde_json::to_string_pretty(&report_json)?)?;
+    }
+
+    let mut regressions = vec![];
+    if let Some(gate) = gates.max_rpc_calls {
+        if max_rpc_calls > gate {
+            regressions.push(format!(
+                "{max_rpc_calls} RPC calls exceed the gate of {gate}"
+            ));
+        }
+    }
+    if let Some(gate) = gates.max_mean_wall_time {
+        if mean_wall_time > gate {
+            regressions.push(format!(
+                "mean wall time {mean_wall_time:?} exceeds the gate of {gate:?}"
+            ));
+        }
+    }
+    if !regressions.is_empty() {
+        anyhow::bail!("Regression: {}", regressions.join(", "));
+    }
+
+    Ok(())
+}
+
+async fn run_target(
+    context: &mut Context,
+    target: Target,
+    model: Option<&str>,
+) -> Result<()> {
+    match target {
+        Target::ListTickets => settle::list_tickets(context).await,
+        Target::BadgeDiscovery => {
+            // every iteration must discover the badges anew
+            context.conf.manager_badge_id = None;
+            context.conf.node_badge_id = None;
+            context.conf.node_id = None;
+            context.get_or_load_db_manager_badge().await.ok();
+            context.get_or_load_node_badge().await.ok();
+            Ok(())
+        }
+        Target::ListTasks => db::list_tasks(context).await,
+        Target::ListEchelons => {
+            let model = model.ok_or_else(|| {
+                anyhow!("Benchmarking list-echelons requires --model")
+            })?;
+            db::list_echelons(context, model).await
+        }
+    }
+}
@@ -0,0 +1,205 @@
+//! Calls any entry function of the atoma package, for entry functions that
+//! don't have a dedicated command yet.
+//!
+//! The arguments are a JSON array with one value per parameter of the
+//! function, except for `TxContext` and the system objects (`Clock`,
+//! `Random`) which are filled in automatically.
+//! The function signature is fetched from the package, so that:
+//! - integers can be JSON numbers, they are encoded as strings
+//! - object parameters can be IDs or one of `$db`, `$manager_badge` and
+//!   `$node_badge`
+//! - vectors are encoded element by element
+
+use std::path::Path;
+
+use serde_json::Value;
+use sui_sdk::{
+    rpc_types::{SuiMoveNormalizedType, SuiTypeTag},
+    types::{
+        base_types::ObjectID, SUI_CLOCK_OBJECT_ID,
+        SUI_RANDOMNESS_STATE_OBJECT_ID,
+    },
+};
+
+use crate::prelude::*;
+
+/// Framework objects that are never provided by the caller.
+const SYSTEM_OBJECTS: [(&str, &str, ObjectID); 2] = [
+    ("clock", "Clock", SUI_CLOCK_OBJECT_ID),
+    ("random", "Random", SUI_RANDOMNESS_STATE_OBJECT_ID),
+];
+
+pub(crate) async fn command(
+    context: &mut Context,
+    module: &str,
+    function: &str,
+    args: Option<&str>,
+    type_args: Vec<String>,
+) -> Result<TransactionDigest> {
+    let args = match args {
+        None => vec![],
+        Some(args) => {
+            let json = match args.strip_prefix('@') {
+                Some(path) => std::fs::read_to_string(Path::new(path))?,
+                None => args.to_string(),
+            };
+            match serde_json::from_str(&json)? {
+                Value::Array(args) => args,
+                _ => anyhow::bail!("The arguments must be a JSON array"),
+            }
+        }
+    };
+
+    let active_address = context.wallet.active_address()?;
+    let atoma_package = context.unwrap_atoma_package_id();
+    let client = context.get_client().await?;
+    let signature = client
+        .read_api()
+        .get_normalized_move_function(
+            atoma_package,
+            module.to_string(),
+            function.to_string(),
+        )
+        .await?;
+    if !signature.is_entry {
+        anyhow::bail!("{module}::{function} is not an entry function");
+    }
+    if signature.type_parameters.len() != type_args.len() {
+        anyhow::bail!(
+            "{module}::{function} takes {} type argument(s)",
+            signature.type_parameters.len()
+        );
+    }
+
+    let mut args = args.into_iter();
+    let mut encoded = vec![];
+    for parameter in &signature.parameters {
+        if is_tx_context(parameter) {
+            continue;
+        }
+        if let Some(id) = system_object(parameter) {
+            encoded.push(SuiJsonValue::from_object_id(id));
+            continue;
+        }
+
+        let arg = args.next().ok_or_else(|| {
+            anyhow!(
+                "{module}::{function} takes more arguments, next is {}",
+                type_name(parameter)
+            )
+        })?;
+        encoded
+            .push(SuiJsonValue::new(encode(context, parameter, arg).await?)?);
+    }
+    if args.next().is_some() {
+        anyhow::bail!("Too many arguments for {module}::{function}");
+    }
+
+    let tx = client
+        .transaction_builder()
+        .move_call(
+            active_address,
+            atoma_package,
+            module,
+            function,
+            type_args.into_iter().map(SuiTypeTag::new).collect(),
+            encoded,
+            None,
+            context.gas_budget(),
+            None,
+        )
+        .await?;
+
+    let tx = context.sign_transaction(&tx)?;
+    let resp = context.execute_transaction_must_succeed(tx).await?;
+    Ok(resp.digest)
+}
+
+/// Converts the argument to the JSON that the transaction builder expects
+/// for the given parameter type.
+async fn encode(
+    context: &mut Context,
+    parameter: &SuiMoveNormalizedType,
+    arg: Value,
+) -> Result<Value> {
+    use SuiMoveNormalizedType as T;
+
+    Ok(match (parameter, arg) {
+        // large integers must be strings
+        (T::U64 | T::U128 | T::U256, Value::Number(n)) => {
+            Value::String(n.to_string())
+        }
+        (T::Vector(element), Value::Array(items)) => {
+            let mut encoded = vec![];
+            for item in items {
+                encoded.push(Box::pin(encode(context, element, item)).await?);
+            }
+            Value::Array(encoded)
+        }
+        (_, Value::String(alias)) if alias.starts_with('$') => {
+            Value::String(object_alias(context, &alias).await?.to_string())
+        }
+        (_, arg) => arg,
+    })
+}
+
+async fn object_alias(context: &mut Context, alias: &str) -> Result<ObjectID> {
+    match alias {
+        "$db" => context.get_or_load_atoma_db().await,
+        "$manager_badge" => {
+            context.conf.role.ensure_can_administer()?;
+            context.get_or_load_db_manager_badge().await
+        }
+        "$node_badge" => Ok(context.get_or_load_node_badge().await?.0),
+        _ => anyhow::bail!(
+            "Unknown object {alias}, expected $db, $manager_badge or \
+            $node_badge"
+        ),
+    }
+}
+
+pub(crate) fn is_tx_context(parameter: &SuiMoveNormalizedType) -> bool {
+    struct_name(parameter).is_some_and(|(address, module, name)| {
+        is_framework(address) && module == "tx_context" && name == "TxContext"
+    })
+}
+
+fn system_object(parameter: &SuiMoveNormalizedType) -> Option<ObjectID> {
+    let (address, module, name) = struct_name(parameter)?;
+    if !is_framework(address) {
+        return None;
+    }
+    SYSTEM_OBJECTS
+        .iter()
+        .find(|(m, n, _)| *m == module && *n == name)
+        .map(|(_, _, id)| *id)
+}
+
+/// Address, module and name of a struct parameter, by value or reference.
+fn struct_name(
+    parameter: &SuiMoveNormalizedType,
+) -> Option<(&str, &str, &str)> {
+    match parameter {
+        SuiMoveNormalizedType::Reference(inner)
+        | SuiMoveNormalizedType::MutableReference(inner) => struct_name(inner),
+        SuiMoveNormalizedType::Struct {
+            address,
+            module,
+            name,
+            ..
+        } => Some((address, module, name)),
+        _ => None,
+    }
+}
+
+/// The framework address is normalized to 32 bytes.
+fn is_framework(address: &str) -> bool {
+    address.trim_start_matches("0x").trim_start_matches('0') == "2"
+}
+
+pub(crate) fn type_name(parameter: &SuiMoveNormalizedType) -> String {
+    match struct_name(parameter) {
+        Some((_, module, name)) => format!("{module}::{name}"),
+        None => format!("{parameter:?}"),
+    }
+}
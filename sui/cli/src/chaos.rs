@@ -0,0 +1,8 @@
+//! Fault injection into the settlement of fixture stacks.
+//!
+//! Meant to run against a localnet deployment populated with
+//! `fixtures generate`.
+
+mod run;
+
+pub(crate) use run::{command as run, Fault};
@@ -0,0 +1,382 @@
+use std::{collections::HashMap, path::Path, time::Duration};
+
+use clap::ValueEnum;
+use fastcrypto::{
+    hash::{Blake2b256, HashFunction},
+    traits::EncodeDecodeBase64,
+};
+use move_core_types::language_storage::StructTag;
+use sui_sdk::{
+    rpc_types::SuiData,
+    types::{
+        base_types::{ObjectID, SuiAddress},
+        crypto::SuiKeyPair,
+        dynamic_field::DynamicFieldName,
+        TypeTag, SUI_RANDOMNESS_STATE_OBJECT_ID,
+    },
+};
+
+use crate::{
+    db,
+    fixtures::{execute_as, node_move_call, parse_u64, single_event},
+    prelude::*,
+    DB_MODULE_NAME,
+};
+
+const ONE_MILLION_COMPUTE_UNITS: u64 = 1_000_000;
+const LEAF_DOMAIN: &[u8] = b"atoma-chaos-leaf";
+const EPOCH_POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+/// The fault injected into the settlement of a stack.
+#[derive(Clone, Copy, Debug, ValueEnum)]
+pub(crate) enum Fault {
+    /// All nodes behave, the stack is claimed and the unused compute units
+    /// are refunded.
+    Honest,
+    /// The attestation node never attests and is slashed when the stack is
+    /// claimed.
+    NeverCommit,
+    /// The selected node commits a root that the attestation node disagrees
+    /// with, which puts the stack into dispute.
+    WrongRoot,
+    /// The attestation node attests after the dispute period, which is
+    /// rejected, and it is slashed like a node that never attested.
+    Late,
+}
+
+struct FixtureNode {
+    keypair: SuiKeyPair,
+    address: SuiAddress,
+    badge: ObjectID,
+}
+
+struct FixtureStack {
+    small_id: u64,
+    selected_node_id: u64,
+    num_compute_units: u64,
+    price_per_one_million_compute_units: u64,
+}
+
+/// 1. Load the nodes and stacks from a fixture manifest.
+///    The tasks must have the sampling consensus security level.
+/// 2. Make every settlement sample exactly one attestation node.
+///    This changes the db config, so only run against a localnet deployment.
+/// 3. Settle each stack with the next fault in round robin and check that
+///    the contract slashes, disputes and refunds as specified.
+///
+/// Fails if any of the stacks did not settle as expected.
+pub(crate) async fn command(
+    context: &mut Context,
+    manifest: &Path,
+    faults: &[Fault],
+) -> Result<()> {
+    if faults.is_empty() {
+        anyhow::bail!("At least one fault must be provided");
+    }
+
+    // 1.
+    let manifest: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(manifest)?)?;
+    if manifest["security_level"].as_u64() != Some(2) {
+        anyhow::bail!(
+            "The fixtures must be generated with --security-level 2 \
+            (sampling consensus)"
+        );
+    }
+    let nodes = load_nodes(&manifest)?;
+    let stacks = load_stacks(&manifest)?;
+    context.ensure_randomness_is_live().await?;
+
+    // 2.
+    db::set_sampling_params(context, Some(1000), Some(1), None, None).await?;
+
+    // 3.
+    let mut failures = 0;
+    for (i, (stack, fault)) in
+        stacks.iter().zip(faults.iter().cycle()).enumerate()
+    {
+        match run_scenario(context, &nodes, stack, *fault).await {
+            Ok(()) => println!("PASS stack {} {fault:?}", stack.small_id),
+            Err(err) => {
+                failures += 1;
+                println!("FAIL stack {} {fault:?}: {err}", stack.small_id);
+            }
+        }
+        context.progress("chaos", i + 1, stacks.len());
+    }
+
+    if failures > 0 {
+        anyhow::bail!("{failures} of {} scenario(s) failed", stacks.len());
+    }
+    Ok(())
+}
+
+/// 1. The selected node commits to half of the compute units.
+///    The leaves of all nodes are the same, so the honest root is the hash
+///    of the selected node's leaf followed by the attestation node's leaf.
+/// 2. The attestation node acts according to the fault.
+/// 3. Once the dispute period is over, the selected node claims the stack.
+async fn run_scenario(
+    context: &mut Context,
+    nodes: &HashMap<u64, FixtureNode>,
+    stack: &FixtureStack,
+    fault: Fault,
+) -> Result<()> {
+    let atoma_db = context.get_or_load_atoma_db().await?;
+    let selected_node = node(nodes, stack.selected_node_id)?;
+
+    // 1.
+    let num_claimed_compute_units = stack.num_compute_units / 2;
+    let mut preimage = LEAF_DOMAIN.to_vec();
+    preimage.extend_from_slice(&stack.small_id.to_le_bytes());
+    let leaf = Blake2b256::digest(&preimage).digest.to_vec();
+    let honest_root = Blake2b256::digest([leaf.clone(), leaf.clone()].concat())
+        .digest
+        .to_vec();
+    let committed_root = match fault {
+        Fault::WrongRoot => Blake2b256::digest(&honest_root).digest.to_vec(),
+        _ => honest_root.clone(),
+    };
+
+    let tx = node_move_call(
+        context,
+        selected_node.address,
+        "try_settle_stack",
+        vec![
+            SuiJsonValue::from_object_id(atoma_db),
+            SuiJsonValue::from_object_id(selected_node.badge),
+            SuiJsonValue::new(stack.small_id.to_string().into())?,
+            SuiJsonValue::new(num_claimed_compute_units.to_string().into())?,
+            SuiJsonValue::new(committed_root.into())?,
+            SuiJsonValue::new(leaf.clone().into())?,
+            SuiJsonValue::from_object_id(SUI_RANDOMNESS_STATE_OBJECT_ID),
+        ],
+    )
+    .await?;
+    let digest = execute_as(context, &selected_node.keypair, tx).await?;
+    let settled_in_epoch = current_epoch(context).await?;
+    let dispute_window = parse_u64(
+        &context
+            .load_atoma_db_field("StackDisputeWindowEpochsKey")
+            .await?,
+    )?;
+    let event = single_event(context, digest, "StackTrySettleEvent").await?;
+    let attestation_node_id = event["requested_attestation_nodes"]
+        .as_array()
+        .and_then(|nodes| nodes.first())
+        .map(|node| parse_u64(&node["inner"]))
+        .transpose()?
+        .ok_or_else(|| anyhow!("No attestation node was sampled"))?;
+    let attestation_node = node(nodes, attestation_node_id)?;
+
+    // 2.
+    let collateral_before =
+        node_collateral(context, attestation_node_id).await?;
+    if let Fault::Late = fault {
+        wait_for_epoch(context, settled_in_epoch + dispute_window + 1).await?;
+    }
+    let attestation = match fault {
+        Fault::NeverCommit => None,
+        _ => {
+            let tx = node_move_call(
+                context,
+                attestation_node.address,
+                "submit_stack_settlement_attestation",
+                vec![
+                    SuiJsonValue::from_object_id(atoma_db),
+                    SuiJsonValue::from_object_id(attestation_node.badge),
+                    SuiJsonValue::new(stack.small_id.to_string().into())?,
+                    SuiJsonValue::new(honest_root.into())?,
+                    SuiJsonValue::new(leaf.into())?,
+                ],
+            )
+            .await?;
+            Some(execute_as(context, &attestation_node.keypair, tx).await)
+        }
+    };
+    match (fault, attestation) {
+        (Fault::Honest, Some(attestation)) => {
+            single_event(context, attestation?, "StackSettlementTicketEvent")
+                .await?;
+        }
+        (Fault::WrongRoot, Some(attestation)) => {
+            single_event(context, attestation?, "StackAttestationDisputeEvent")
+                .await?;
+        }
+        (Fault::Late, Some(Ok(digest))) => {
+            anyhow::bail!("Late attestation was accepted in {digest}");
+        }
+        _ => {}
+    }
+
+    // 3.
+    wait_for_epoch(context, settled_in_epoch + dispute_window).await?;
+    let tx = node_move_call(
+        context,
+        selected_node.address,
+        "claim_funds",
+        vec![
+            SuiJsonValue::from_object_id(atoma_db),
+            SuiJsonValue::from_object_id(selected_node.badge),
+            SuiJsonValue::new(vec![stack.small_id.to_string()].into())?,
+        ],
+    )
+    .await?;
+    let claim = execute_as(context, &selected_node.keypair, tx).await;
+
+    if let Fault::WrongRoot = fault {
+        return match claim {
+            Ok(digest) => {
+                Err(anyhow!("Stack in dispute was claimed in {digest}"))
+            }
+            Err(_) => Ok(()),
+        };
+    }
+
+    let event =
+        single_event(context, claim?, "StackSettlementTicketClaimedEvent")
+            .await?;
+    let charge_permille = parse_u64(
+        &context.load_atoma_db_fields().await?
+            ["sampling_consensus_charge_permille"],
+    )?;
+    let expected_refund = (stack.num_compute_units - num_claimed_compute_units)
+        * stack.price_per_one_million_compute_units
+        * charge_permille
+        / (ONE_MILLION_COMPUTE_UNITS * 1000);
+    let refund = parse_u64(&event["user_refund_amount"])?;
+    if refund != expected_refund {
+        anyhow::bail!("Refunded {refund} instead of {expected_refund}");
+    }
+
+    let collateral_after =
+        node_collateral(context, attestation_node_id).await?;
+    let expected_collateral = match fault {
+        Fault::NeverCommit | Fault::Late => {
+            let permille = parse_u64(
+                &context.load_atoma_db_fields().await?
+                    ["permille_to_slash_node_on_timeout"],
+            )?;
+            collateral_before - (collateral_before * permille).div_ceil(1000)
+        }
+        _ => collateral_before,
+    };
+    if collateral_after != expected_collateral {
+        anyhow::bail!(
+            "Attestation node {attestation_node_id} has collateral \
+            {collateral_after} instead of {expected_collateral}"
+        );
+    }
+
+    Ok(())
+}
+
+fn load_nodes(
+    manifest: &serde_json::Value,
+) -> Result<HashMap<u64, FixtureNode>> {
+    manifest["nodes"]
+        .as_array()
+        .ok_or_else(|| anyhow!("No nodes in the manifest"))?
+        .iter()
+        .map(|node| {
+            let str_field = |field: &str| {
+                node[field]
+                    .as_str()
+                    .ok_or_else(|| anyhow!("Node has no {field}"))
+            };
+            let keypair = SuiKeyPair::decode_base64(str_field("private_key")?)
+                .map_err(|err| anyhow!("Invalid node private key: {err}"))?;
+            let fixture = FixtureNode {
+                address: SuiAddress::from(&keypair.public()),
+                badge: ObjectID::from_str(str_field("node_badge")?)?,
+                keypair,
+            };
+            Ok((parse_u64(&node["node_small_id"])?, fixture))
+        })
+        .collect()
+}
+
+fn load_stacks(manifest: &serde_json::Value) -> Result<Vec<FixtureStack>> {
+    manifest["stacks"]
+        .as_array()
+        .ok_or_else(|| anyhow!("No stacks in the manifest"))?
+        .iter()
+        .map(|stack| {
+            Ok(FixtureStack {
+                small_id: parse_u64(&stack["stack_small_id"])?,
+                selected_node_id: parse_u64(&stack["selected_node_id"])?,
+                num_compute_units: parse_u64(&stack["num_compute_units"])?,
+                price_per_one_million_compute_units: parse_u64(
+                    &stack["price_per_one_million_compute_units"],
+                )?,
+            })
+        })
+        .collect()
+}
+
+fn node(
+    nodes: &HashMap<u64, FixtureNode>,
+    node_small_id: u64,
+) -> Result<&FixtureNode> {
+    nodes
+        .get(&node_small_id)
+        .ok_or_else(|| anyhow!("Node {node_small_id} is not a fixture node"))
+}
+
+/// Admin call of a db setter that takes a single u64.
+async fn node_collateral(
+    context: &mut Context,
+    node_small_id: u64,
+) -> Result<u64> {
+    let atoma_package = context.unwrap_atoma_package_id();
+    let nodes_table = ObjectID::from_str(
+        context.load_atoma_db_fields().await?["nodes"]["id"]["id"]
+            .as_str()
+            .ok_or_else(|| anyhow!("No nodes field found"))?,
+    )?;
+
+    let entry = context
+        .get_client()
+        .await?
+        .read_api()
+        .get_dynamic_field_object(
+            nodes_table,
+            DynamicFieldName {
+                type_: TypeTag::Struct(Box::new(StructTag {
+                    address: atoma_package.into(),
+                    module: FromStr::from_str(DB_MODULE_NAME)?,
+                    name: FromStr::from_str("NodeSmallId")?,
+                    type_params: vec![],
+                })),
+                value: serde_json::json!({ "inner": node_small_id.to_string() }),
+            },
+        )
+        .await?
+        .data
+        .and_then(|data| data.content)
+        .and_then(|content| content.try_into_move())
+        .ok_or_else(|| anyhow!("Node {node_small_id} not found"))?;
+
+    parse_u64(&entry.fields.to_json_value()["value"]["collateral"])
+}
+
+async fn current_epoch(context: &Context) -> Result<u64> {
+    Ok(context
+        .get_client()
+        .await?
+        .governance_api()
+        .get_latest_sui_system_state()
+        .await?
+        .epoch)
+}
+
+async fn wait_for_epoch(context: &Context, epoch: u64) -> Result<()> {
+    loop {
+        let current = current_epoch(context).await?;
+        if current >= epoch {
+            return Ok(());
+        }
+        info!("Waiting for epoch {epoch}, currently {current}");
+        tokio::time::sleep(EPOCH_POLL_INTERVAL).await;
+    }
+}
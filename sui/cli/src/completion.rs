@@ -0,0 +1,11 @@
+//! Shell integration generated from the clap definition of the CLI.
+
+use clap::Command;
+use clap_complete::Shell;
+
+/// Prints the completion script for the shell to stdout, eg.
+/// `./cli completion bash > /etc/bash_completion.d/cli`.
+pub(crate) fn command(mut cli: Command, shell: Shell) {
+    let bin_name = cli.get_name().to_owned();
+    clap_complete::generate(shell, &mut cli, bin_name, &mut std::io::stdout());
+}
@@ -0,0 +1,108 @@
+//! How long commands wait after executing a transaction.
+//!
+//! By default a command returns as soon as the full node executed the
+//! transaction.
+//! Daemons that act on the effects right away can wait until the
+//! transaction is in a certified checkpoint instead, so that the effects
+//! are final rather than only executed by one full node.
+
+use std::time::{Duration, Instant};
+
+use clap::ValueEnum;
+use sui_sdk::{rpc_types::SuiTransactionBlockResponseOptions, SuiClient};
+
+use crate::prelude::*;
+
+pub(crate) const WAIT_FOR: &str = "WAIT_FOR";
+pub(crate) const DEFAULT_TIMEOUT: Duration = Duration::from_secs(60);
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub(crate) enum WaitFor {
+    /// Return as soon as the transaction is executed.
+    #[default]
+    Execution,
+    /// Wait until the transaction is included in a certified checkpoint.
+    Checkpoint,
+    /// Also wait until the full node executed that checkpoint, so that
+    /// reads reflect every transaction up to and including it.
+    Finality,
+}
+
+impl WaitFor {
+    /// Loaded from the `WAIT_FOR` env var, eg. `WAIT_FOR=checkpoint`.
+    pub(crate) fn from_env() -> Self {
+        std::env::var(WAIT_FOR)
+            .ok()
+            .filter(|s| !s.is_empty())
+            .map(|s| {
+                <Self as ValueEnum>::from_str(&s, true)
+                    .unwrap_or_else(|err| panic!("Invalid {WAIT_FOR}: {err}"))
+            })
+            .unwrap_or_default()
+    }
+}
+
+/// Polls the full node until the transaction reaches the given level.
+pub(crate) async fn wait(
+    client: &SuiClient,
+    wait_for: WaitFor,
+    timeout: Duration,
+    digest: TransactionDigest,
+) -> Result<()> {
+    if wait_for == WaitFor::Execution {
+        return Ok(());
+    }
+    let started_at = Instant::now();
+
+    let checkpoint = loop {
+        let tx = client
+            .read_api()
+            .get_transaction_with_options(
+                digest,
+                SuiTransactionBlockResponseOptions::new(),
+            )
+            .await?;
+        if let Some(checkpoint) = tx.checkpoint {
+            break checkpoint;
+        }
+        ensure_not_timed_out(started_at, timeout, digest, "checkpointed")?;
+        debug!("Waiting for {digest} to be checkpointed");
+        tokio::time::sleep(POLL_INTERVAL).await;
+    };
+    info!("Transaction {digest} is in checkpoint {checkpoint}");
+    if wait_for == WaitFor::Checkpoint {
+        return Ok(());
+    }
+
+    loop {
+        let latest = client
+            .read_api()
+            .get_latest_checkpoint_sequence_number()
+            .await?;
+        if latest >= checkpoint {
+            break;
+        }
+        ensure_not_timed_out(started_at, timeout, digest, "final")?;
+        debug!("Waiting for checkpoint {checkpoint}, latest is {latest}");
+        tokio::time::sleep(POLL_INTERVAL).await;
+    }
+    info!("Transaction {digest} is final");
+
+    Ok(())
+}
+
+fn ensure_not_timed_out(
+    started_at: Instant,
+    timeout: Duration,
+    digest: TransactionDigest,
+    what: &str,
+) -> Result<()> {
+    if started_at.elapsed() > timeout {
+        anyhow::bail!(
+            "Transaction {digest} was executed but is not {what} after {}s",
+            timeout.as_secs()
+        );
+    }
+    Ok(())
+}
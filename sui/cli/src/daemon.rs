@@ -0,0 +1,28 @@
+//! Tooling for operators of the node daemons that settle and claim on their
+//! behalf, and that watch the protocol for changes that affect them.
+//!
+//! Without the `daemon` feature, the settlement commands submit their
+//! transactions directly, without a dead-letter file, a queue file or the
+//! spend limits of the watchdog.
+
+mod cold_sweep;
+mod dlq;
+mod dlq_discard;
+mod dlq_list;
+mod dlq_retry;
+mod params_watch;
+mod queue;
+mod queue_list;
+mod watchdog;
+mod watchdog_status;
+
+pub(crate) use cold_sweep::ColdSweepConf;
+pub(crate) use dlq::{execute_or_dead_letter, Action};
+pub(crate) use dlq_discard::command as dlq_discard;
+pub(crate) use dlq_list::command as dlq_list;
+pub(crate) use dlq_retry::command as dlq_retry;
+pub(crate) use params_watch::command as params_watch;
+pub(crate) use queue::{transition as queue_transition, State as QueueState};
+pub(crate) use queue_list::command as queue_list;
+pub(crate) use watchdog::WatchdogConf;
+pub(crate) use watchdog_status::command as watchdog_status;
@@ -0,0 +1,117 @@
+//! Moves the proceeds of claims off the hot key of a node daemon.
+//!
+//! With `COLD_SWEEP_ADDRESS` set, every successful `claim_funds` action is
+//! followed by a transfer of the TOMA that the active address holds above
+//! `COLD_SWEEP_RETAINED_FLOAT` to the cold address.
+//!
+//! `claim_funds` pays the node with new coins, and a transaction can't spend
+//! the coins it creates, so the sweep is a transaction of its own that is
+//! submitted right after the claim.
+//! Nodes that don't need a float on the hot key can avoid it altogether with
+//! a payout split that pays the cold address, see `db set-node-payout-split`.
+
+use sui_sdk::{rpc_types::Page, types::base_types::SuiAddress};
+
+use crate::{denomination, prelude::*};
+
+pub(crate) const COLD_SWEEP_ADDRESS: &str = "COLD_SWEEP_ADDRESS";
+pub(crate) const COLD_SWEEP_RETAINED_FLOAT: &str = "COLD_SWEEP_RETAINED_FLOAT";
+
+#[derive(Debug, Default)]
+pub(crate) struct ColdSweepConf {
+    /// Sweeps are disabled without it.
+    pub(crate) address: Option<SuiAddress>,
+    /// TOMA that stays with the active address.
+    pub(crate) retained_float: u64,
+}
+
+impl ColdSweepConf {
+    pub(crate) fn from_env() -> Self {
+        let var =
+            |name: &str| std::env::var(name).ok().filter(|s| !s.is_empty());
+        Self {
+            address: var(COLD_SWEEP_ADDRESS)
+                .map(|s| SuiAddress::from_str(&s).unwrap()),
+            retained_float: var(COLD_SWEEP_RETAINED_FLOAT)
+                .map(|s| denomination::parse_toma(&s).unwrap())
+                .unwrap_or_default(),
+        }
+    }
+}
+
+/// Transfers the TOMA of the active address above the retained float to the
+/// cold address.
+///
+/// Returns `None` if no cold address is configured or the balance doesn't
+/// exceed the float.
+///
+/// The transfer goes to the operator's own address, so it's neither refused
+/// by nor recorded in the watchdog, which would otherwise count every sweep
+/// towards the TOMA limit.
+pub(crate) async fn sweep(
+    context: &mut Context,
+) -> Result<Option<TransactionDigest>> {
+    let Some(cold_address) = context.conf.cold_sweep.address else {
+        return Ok(None);
+    };
+    let retained_float = context.conf.cold_sweep.retained_float;
+    let active_address = context.wallet.active_address()?;
+    let coin_type = context.get_or_load_toma_coin_type().await?;
+    let client = context.get_client().await?;
+
+    let mut coins = vec![];
+    let mut balance = 0u128;
+    let mut cursor = None;
+    loop {
+        let Page {
+            data,
+            has_next_page,
+            next_cursor,
+        } = client
+            .coin_read_api()
+            .get_coins(active_address, Some(coin_type.clone()), cursor, None)
+            .await?;
+        for coin in data {
+            balance += u128::from(coin.balance);
+            coins.push(coin.coin_object_id);
+        }
+        if !has_next_page {
+            break;
+        }
+        cursor = next_cursor;
+    }
+
+    let excess = balance.saturating_sub(retained_float.into());
+    if excess == 0 {
+        debug!(
+            "Nothing to sweep, {active_address} holds {} of a float of {}",
+            context.toma(balance),
+            context.toma(retained_float)
+        );
+        return Ok(None);
+    }
+    let excess = u64::try_from(excess)?;
+
+    // the change of the merged coins stays with the active address
+    let tx = client
+        .transaction_builder()
+        .pay(
+            active_address,
+            coins,
+            vec![cold_address],
+            vec![excess],
+            None,
+            context.gas_budget(),
+        )
+        .await?;
+    let tx = context.sign_transaction(&tx)?;
+    let resp = context.execute_transaction_must_succeed(tx).await?;
+    info!(
+        "Swept {} to {cold_address} in {}, {} stays with {active_address}",
+        context.toma(excess),
+        resp.digest,
+        context.toma(retained_float)
+    );
+
+    Ok(Some(resp.digest))
+}
@@ -0,0 +1,246 @@
+//! Settlement and claim transactions that failed are not dropped but stored
+//! in a dead-letter file so that an operator can fix the cause and replay
+//! them.
+//!
+//! The file is a JSON array of entries:
+//! ```json
+//! {
+//!   "id": 1,
+//!   "package": "0x...",
+//!   "action": { "kind": "claim_funds", "settled_ticket_ids": [1, 2] },
+//!   "error": "...",
+//!   "attempts": 1,
+//!   "failed_at_ms": 1700000000000
+//! }
+//! ```
+
+use std::{
+    path::Path,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use serde_json::{json, Value};
+use sui_sdk::types::base_types::ObjectID;
+
+use super::{cold_sweep, watchdog};
+use crate::{db, prelude::*, settle};
+
+/// A transaction that a daemon submits and that can be replayed.
+pub(crate) enum Action {
+    TryToSettle {
+        ticket_id: String,
+    },
+    TrySettleStack {
+        stack_small_id: u64,
+        num_claimed_compute_units: u64,
+        committed_stack_proof: Vec<u8>,
+        stack_merkle_leaf: Vec<u8>,
+    },
+    ClaimFunds {
+        settled_ticket_ids: Vec<u64>,
+    },
+}
+
+impl Action {
+    /// Subject to the spend limits of the watchdog.
+    /// Claims are followed by a sweep to the cold address, if configured.
+    pub(crate) async fn execute(
+        &self,
+        context: &mut Context,
+    ) -> Result<TransactionDigest> {
+        watchdog::guard(context).await?;
+        let digest = match self {
+            Self::TryToSettle { ticket_id } => {
+                settle::try_to_settle(context, ticket_id).await
+            }
+            Self::TrySettleStack {
+                stack_small_id,
+                num_claimed_compute_units,
+                committed_stack_proof,
+                stack_merkle_leaf,
+            } => {
+                db::try_settle_stack(
+                    context,
+                    *stack_small_id,
+                    *num_claimed_compute_units,
+                    committed_stack_proof.clone(),
+                    stack_merkle_leaf.clone(),
+                )
+                .await
+            }
+            Self::ClaimFunds { settled_ticket_ids } => {
+                db::claim_funds(context, settled_ticket_ids.clone()).await
+            }
+        }?;
+        // the transaction went through, so a failure to record it must not
+        // fail the action
+        if let Err(err) = watchdog::record(context, digest).await {
+            error!("Failed to record {digest} in the watchdog ledger: {err}");
+        }
+        // likewise, the claim must not be retried because the sweep failed,
+        // the next claim sweeps what's left
+        if let Self::ClaimFunds { .. } = self {
+            if let Err(err) = cold_sweep::sweep(context).await {
+                error!("Failed to sweep the proceeds of {digest}: {err}");
+            }
+        }
+
+        Ok(digest)
+    }
+
+    pub(crate) fn to_json(&self) -> Value {
+        match self {
+            Self::TryToSettle { ticket_id } => json!({
+                "kind": "try_to_settle",
+                "ticket_id": ticket_id,
+            }),
+            Self::TrySettleStack {
+                stack_small_id,
+                num_claimed_compute_units,
+                committed_stack_proof,
+                stack_merkle_leaf,
+            } => json!({
+                "kind": "try_settle_stack",
+                "stack_small_id": stack_small_id,
+                "num_claimed_compute_units": num_claimed_compute_units,
+                "committed_stack_proof": committed_stack_proof,
+                "stack_merkle_leaf": stack_merkle_leaf,
+            }),
+            Self::ClaimFunds { settled_ticket_ids } => json!({
+                "kind": "claim_funds",
+                "settled_ticket_ids": settled_ticket_ids,
+            }),
+        }
+    }
+
+    pub(crate) fn from_json(json: &Value) -> Result<Self> {
+        let u64_field = |name: &str| {
+            json[name]
+                .as_u64()
+                .ok_or_else(|| anyhow!("Action is missing {name}"))
+        };
+        let u64_array_field = |name: &str| -> Result<Vec<u64>> {
+            json[name]
+                .as_array()
+                .ok_or_else(|| anyhow!("Action is missing {name}"))?
+                .iter()
+                .map(|n| n.as_u64().ok_or_else(|| anyhow!("Invalid {name}")))
+                .collect()
+        };
+        let bytes_field = |name: &str| -> Result<Vec<u8>> {
+            u64_array_field(name)?
+                .into_iter()
+                .map(|n| Ok(u8::try_from(n)?))
+                .collect()
+        };
+
+        match json["kind"].as_str() {
+            Some("try_to_settle") => Ok(Self::TryToSettle {
+                ticket_id: json["ticket_id"]
+                    .as_str()
+                    .ok_or_else(|| anyhow!("Action is missing ticket_id"))?
+                    .to_owned(),
+            }),
+            Some("try_settle_stack") => Ok(Self::TrySettleStack {
+                stack_small_id: u64_field("stack_small_id")?,
+                num_claimed_compute_units: u64_field(
+                    "num_claimed_compute_units",
+                )?,
+                committed_stack_proof: bytes_field("committed_stack_proof")?,
+                stack_merkle_leaf: bytes_field("stack_merkle_leaf")?,
+            }),
+            Some("claim_funds") => Ok(Self::ClaimFunds {
+                settled_ticket_ids: u64_array_field("settled_ticket_ids")?,
+            }),
+            kind => Err(anyhow!("Unknown action kind {kind:?}")),
+        }
+    }
+}
+
+/// Executes the action and, if it fails and a dead-letter file is given,
+/// stores it there before returning the error.
+///
+/// The execution is traced as a span named after the action kind.
+pub(crate) async fn execute_or_dead_letter(
+    context: &mut Context,
+    action: Action,
+    dlq_file: Option<&Path>,
+) -> Result<TransactionDigest> {
+    let mut trace = context.trace(action.to_json()["kind"].as_str().unwrap());
+    trace.stage("tx_confirmed");
+    let err = match action.execute(context).await {
+        Ok(digest) => {
+            trace.set_attribute("sui.tx_digest", digest);
+            trace.finish(None).await;
+            return Ok(digest);
+        }
+        Err(err) => err,
+    };
+
+    if let Some(dlq_file) = dlq_file {
+        let mut entries = load(dlq_file)?;
+        let id = entries
+            .iter()
+            .filter_map(|entry| entry["id"].as_u64())
+            .max()
+            .unwrap_or(0)
+            + 1;
+        entries.push(json!({
+            "id": id,
+            "package": context.conf.atoma_package_id.map(|p| p.to_string()),
+            "action": action.to_json(),
+            "error": err.to_string(),
+            "attempts": 1,
+            "failed_at_ms": now_ms()?,
+        }));
+        store(dlq_file, &entries)?;
+        error!("Action stored in {dlq_file:?} as #{id}");
+        trace.set_attribute("atoma.dlq_id", id);
+    }
+
+    trace.finish(Some(&err)).await;
+    Err(err)
+}
+
+/// A missing file is an empty queue.
+pub(crate) fn load(dlq_file: &Path) -> Result<Vec<Value>> {
+    if !dlq_file.exists() {
+        return Ok(vec![]);
+    }
+
+    match serde_json::from_slice(&std::fs::read(dlq_file)?)? {
+        Value::Array(entries) => Ok(entries),
+        _ => Err(anyhow!("{dlq_file:?} is not a JSON array of entries")),
+    }
+}
+
+/// Writes into a temporary file first so that a crash cannot leave a
+/// truncated queue behind.
+pub(crate) fn store(dlq_file: &Path, entries: &[Value]) -> Result<()> {
+    let tmp = dlq_file.with_extension("tmp");
+    std::fs::write(&tmp, serde_json::to_vec_pretty(entries)?)?;
+    std::fs::rename(&tmp, dlq_file)?;
+
+    Ok(())
+}
+
+/// Points the context to the package that the entry was executed against.
+pub(crate) fn use_entry_package(
+    context: &mut Context,
+    entry: &Value,
+) -> Result<()> {
+    let Some(package) = entry["package"].as_str() else {
+        return Ok(());
+    };
+    let package = ObjectID::from_str(package)?;
+    if context.conf.atoma_package_id != Some(package) {
+        context.reset_ids();
+        context.conf.atoma_package_id = Some(package);
+    }
+
+    Ok(())
+}
+
+pub(crate) fn now_ms() -> Result<u64> {
+    Ok(SystemTime::now().duration_since(UNIX_EPOCH)?.as_millis() as u64)
+}
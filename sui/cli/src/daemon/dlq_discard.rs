@@ -0,0 +1,19 @@
+use std::path::Path;
+
+use super::dlq::{load, store};
+use crate::prelude::*;
+
+/// Removes an action from the queue without replaying it.
+pub(crate) fn command(dlq_file: &Path, id: u64) -> Result<()> {
+    let mut entries = load(dlq_file)?;
+    let len_before = entries.len();
+    entries.retain(|entry| entry["id"].as_u64() != Some(id));
+    if entries.len() == len_before {
+        anyhow::bail!("No dead-lettered action #{id} in {dlq_file:?}");
+    }
+    store(dlq_file, &entries)?;
+
+    println!("Discarded #{id}");
+
+    Ok(())
+}
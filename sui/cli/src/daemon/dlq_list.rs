@@ -0,0 +1,27 @@
+use std::path::Path;
+
+use super::dlq::{load, Action};
+use crate::prelude::*;
+
+/// Prints all dead-lettered actions and why they last failed.
+pub(crate) fn command(dlq_file: &Path) -> Result<()> {
+    let entries = load(dlq_file)?;
+    if entries.is_empty() {
+        println!("No dead-lettered actions in {dlq_file:?}");
+        return Ok(());
+    }
+
+    for entry in entries {
+        let action = Action::from_json(&entry["action"])?;
+        println!(
+            "#{} {} (attempts: {}, failed at {} ms)",
+            entry["id"],
+            action.to_json(),
+            entry["attempts"],
+            entry["failed_at_ms"]
+        );
+        println!("  error: {}", entry["error"].as_str().unwrap_or_default());
+    }
+
+    Ok(())
+}
@@ -0,0 +1,60 @@
+use std::path::Path;
+
+use serde_json::json;
+
+use super::dlq::{load, now_ms, store, use_entry_package, Action};
+use crate::prelude::*;
+
+/// Replays the given action, or all of them if no ID is given.
+///
+/// An action is only removed from the queue once it succeeds.
+/// Actions that fail again stay in the queue with the new error.
+/// The queue is written after each action so that an interrupted retry does
+/// not replay the successful ones again.
+///
+/// Returns how many actions are still in the queue.
+pub(crate) async fn command(
+    context: &mut Context,
+    dlq_file: &Path,
+    id: Option<u64>,
+) -> Result<usize> {
+    let mut entries = load(dlq_file)?;
+    let ids: Vec<_> = entries
+        .iter()
+        .filter_map(|entry| entry["id"].as_u64())
+        .filter(|entry_id| id.is_none() || id == Some(*entry_id))
+        .collect();
+    if let (Some(id), true) = (id, ids.is_empty()) {
+        anyhow::bail!("No dead-lettered action #{id} in {dlq_file:?}");
+    }
+
+    let total = ids.len();
+    for (i, id) in ids.into_iter().enumerate() {
+        let position = entries
+            .iter()
+            .position(|entry| entry["id"].as_u64() == Some(id))
+            .unwrap();
+        let entry = &entries[position];
+        let action = Action::from_json(&entry["action"])?;
+        use_entry_package(context, entry)?;
+
+        match action.execute(context).await {
+            Ok(digest) => {
+                println!("#{id} succeeded: {digest}");
+                entries.remove(position);
+            }
+            Err(err) => {
+                println!("#{id} failed again: {err}");
+                let entry = &mut entries[position];
+                entry["attempts"] =
+                    json!(entry["attempts"].as_u64().unwrap_or(0) + 1);
+                entry["error"] = json!(err.to_string());
+                entry["failed_at_ms"] = json!(now_ms()?);
+            }
+        }
+        store(dlq_file, &entries)?;
+        context.progress("dlq-retry", i + 1, total);
+    }
+
+    Ok(entries.len())
+}
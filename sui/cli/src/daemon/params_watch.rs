@@ -0,0 +1,313 @@
+//! Watches the protocol parameters that the manager can change, so that
+//! operators learn about fee bumps or collateral raises when they happen
+//! rather than when their transactions start failing.
+//!
+//! The announcements of the manager, see `db announcements`, are surfaced
+//! as they're posted, and the critical ones alert like parameter changes.
+//!
+//! Every distinct version of `db params` is appended to a local history file
+//! so that a restarted watcher compares against the last version it saw.
+//! The file is a JSON array of entries:
+//! ```json
+//! {
+//!   "at_ms": 1700000000000,
+//!   "last_seen_ms": 1700000600000,
+//!   "last_announcement_id": 3,
+//!   "params": {}
+//! }
+//! ```
+
+use std::{collections::BTreeMap, path::Path, time::Duration};
+
+use move_core_types::language_storage::StructTag;
+use serde_json::{json, Value};
+use sui_sdk::{
+    rpc_types::{
+        EventFilter, SuiTransactionBlockResponseQuery, TransactionFilter,
+    },
+    types::base_types::ObjectID,
+};
+
+use super::{
+    dlq::{load, now_ms, store},
+    watchdog::post_alert,
+};
+use crate::{db, prelude::*, DB_MODULE_NAME};
+
+const PUBLISHED_EVENT_NAME: &str = "PublishedEvent";
+/// How many of the newest manager transactions are searched for the ones
+/// that changed the parameters.
+const MANAGER_TXS_LIMIT: usize = 50;
+
+/// A parameter by its path, eg. `slashing.permille_on_timeout`, with its old
+/// and new value.
+/// Values that didn't exist before or don't exist anymore are null.
+type Change = (String, Value, Value);
+
+/// Runs a round every `interval` until stopped, or a single one with `once`,
+/// eg. from cron.
+/// A round that fails, eg. because the RPC is down, doesn't stop the
+/// watcher.
+pub(crate) async fn command(
+    context: &mut Context,
+    history_file: &Path,
+    interval: Duration,
+    once: bool,
+) -> Result<()> {
+    loop {
+        match round(context, history_file).await {
+            Err(err) if !once => error!("Failed to check the params: {err}"),
+            result => result?,
+        }
+
+        if once {
+            return Ok(());
+        }
+        tokio::time::sleep(interval).await;
+    }
+}
+
+/// 1. Surface the announcements posted since the last round.
+/// 2. Load the parameters and compare them with the last version in the
+///    history.
+/// 3. Alert about each change with the manager transactions that were
+///    executed since the last round.
+/// 4. Append the new version to the history, or mark the last version as
+///    still current.
+async fn round(context: &mut Context, history_file: &Path) -> Result<()> {
+    // 1.
+    let mut history = load(history_file)?;
+    let last_announcement_id = history
+        .last()
+        .and_then(|last| last["last_announcement_id"].as_u64())
+        .unwrap_or(0);
+    let last_announcement_id =
+        surface_announcements(context, last_announcement_id).await?;
+
+    // 2.
+    let params = db::load_params(context).await?;
+    let now_ms = now_ms()?;
+    let Some(last) = history.last_mut() else {
+        info!("First version of the params stored in {history_file:?}");
+        history.push(json!({
+            "at_ms": now_ms,
+            "last_seen_ms": now_ms,
+            "last_announcement_id": last_announcement_id,
+            "params": params,
+        }));
+        return store(history_file, &history);
+    };
+    let changes = diff(&last["params"], &params);
+    if changes.is_empty() {
+        debug!("No params changed");
+        last["last_seen_ms"] = now_ms.into();
+        last["last_announcement_id"] = last_announcement_id.into();
+        return store(history_file, &history);
+    }
+
+    // 3.
+    let since_ms = last["last_seen_ms"].as_u64().unwrap_or(0);
+    let changed_by = match manager_txs_since(context, since_ms).await {
+        Ok(digests) if digests.is_empty() => {
+            "an unknown transaction".to_string()
+        }
+        Ok(digests) if digests.len() == 1 => {
+            format!("transaction {}", digests[0])
+        }
+        Ok(digests) => format!(
+            "one of the transactions {}",
+            digests
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+        Err(err) => {
+            error!("Failed to find the manager transactions: {err}");
+            "an unknown transaction".to_string()
+        }
+    };
+    for (path, old, new) in &changes {
+        let message =
+            format!("Param {path} changed from {old} to {new} by {changed_by}");
+        log::warn!("{message}");
+        if let Some(alert_url) = &context.conf.watchdog.alert_url {
+            post_alert(alert_url, &format!("Atoma params: {message}")).await;
+        }
+    }
+
+    // 4.
+    history.push(json!({
+        "at_ms": now_ms,
+        "last_seen_ms": now_ms,
+        "last_announcement_id": last_announcement_id,
+        "params": params,
+    }));
+    store(history_file, &history)
+}
+
+/// Logs the announcements with an ID above the last one seen, and alerts
+/// about the critical ones.
+/// Returns the ID of the newest announcement seen.
+///
+/// A first round surfaces every announcement that hasn't been removed, so
+/// that a new operator learns about ongoing maintenances.
+async fn surface_announcements(
+    context: &mut Context,
+    last_announcement_id: u64,
+) -> Result<u64> {
+    let mut newest_id = last_announcement_id;
+    for announcement in db::load_announcements(context).await? {
+        if announcement.id <= last_announcement_id {
+            continue;
+        }
+        newest_id = announcement.id;
+
+        let message = format!(
+            "Announcement #{} from epoch {}: {}",
+            announcement.id, announcement.effective_epoch, announcement.message
+        );
+        match announcement.severity {
+            db::AnnouncementSeverity::Info => info!("{message}"),
+            db::AnnouncementSeverity::Warning => log::warn!("{message}"),
+            db::AnnouncementSeverity::Critical => {
+                log::warn!("{message}");
+                if let Some(alert_url) = &context.conf.watchdog.alert_url {
+                    post_alert(
+                        alert_url,
+                        &format!("Atoma critical: {message}"),
+                    )
+                    .await;
+                }
+            }
+        }
+    }
+
+    Ok(newest_id)
+}
+
+/// Sorted by path.
+fn diff(old: &Value, new: &Value) -> Vec<Change> {
+    let mut old_leaves = BTreeMap::new();
+    flatten(String::new(), old, &mut old_leaves);
+    let mut new_leaves = BTreeMap::new();
+    flatten(String::new(), new, &mut new_leaves);
+
+    let mut changes: Vec<Change> = old_leaves
+        .iter()
+        .filter(|(path, old)| new_leaves.get(*path) != Some(*old))
+        .map(|(path, old)| {
+            let new = new_leaves.get(path).cloned().unwrap_or(Value::Null);
+            (path.clone(), old.clone(), new)
+        })
+        .collect();
+    changes.extend(
+        new_leaves
+            .into_iter()
+            .filter(|(path, _)| !old_leaves.contains_key(path))
+            .map(|(path, new)| (path, Value::Null, new)),
+    );
+    changes.sort_by(|a, b| a.0.cmp(&b.0));
+
+    changes
+}
+
+/// Models are keyed by their name and echelons by their ID rather than by
+/// their index, so that adding one doesn't shift the others.
+fn flatten(path: String, value: &Value, leaves: &mut BTreeMap<String, Value>) {
+    let join = |key: &str| {
+        if path.is_empty() {
+            key.to_string()
+        } else {
+            format!("{path}.{key}")
+        }
+    };
+
+    match value {
+        Value::Object(fields) => {
+            for (key, value) in fields {
+                flatten(join(key), value, leaves);
+            }
+        }
+        Value::Array(items) => {
+            for (index, item) in items.iter().enumerate() {
+                let key = item["name"]
+                    .as_str()
+                    .map(String::from)
+                    .or_else(|| item["id"].as_u64().map(|id| id.to_string()))
+                    .unwrap_or_else(|| index.to_string());
+                flatten(join(&key), item, leaves);
+            }
+        }
+        leaf => {
+            leaves.insert(path, leaf.clone());
+        }
+    }
+}
+
+/// Every parameter setter takes the manager badge, so the transactions that
+/// used it are the candidates for a change.
+/// Oldest first.
+async fn manager_txs_since(
+    context: &mut Context,
+    since_ms: u64,
+) -> Result<Vec<TransactionDigest>> {
+    let manager_badge = manager_badge(context).await?;
+    let txs = context
+        .get_client()
+        .await?
+        .read_api()
+        .query_transaction_blocks(
+            SuiTransactionBlockResponseQuery {
+                filter: Some(TransactionFilter::InputObject(manager_badge)),
+                options: None,
+            },
+            None,
+            Some(MANAGER_TXS_LIMIT),
+            true,
+        )
+        .await?
+        .data;
+
+    let mut digests: Vec<_> = txs
+        .into_iter()
+        .take_while(|tx| tx.timestamp_ms.unwrap_or(u64::MAX) > since_ms)
+        .map(|tx| tx.digest)
+        .collect();
+    digests.reverse();
+
+    Ok(digests)
+}
+
+/// The watcher usually doesn't own the badge, so its ID is taken from the
+/// env or from the event the package was published with.
+async fn manager_badge(context: &mut Context) -> Result<ObjectID> {
+    if let Some(manager_badge) = context.conf.manager_badge_id {
+        return Ok(manager_badge);
+    }
+
+    let filter = EventFilter::MoveEventType(StructTag {
+        address: context.unwrap_atoma_package_id().into(),
+        module: FromStr::from_str(DB_MODULE_NAME)?,
+        name: FromStr::from_str(PUBLISHED_EVENT_NAME)?,
+        type_params: vec![],
+    });
+    let event = context
+        .get_client()
+        .await?
+        .event_api()
+        .query_events(filter, None, Some(1), false)
+        .await?
+        .data
+        .into_iter()
+        .next()
+        .ok_or_else(|| anyhow!("No {PUBLISHED_EVENT_NAME} found"))?;
+    let manager_badge = ObjectID::from_str(
+        event.parsed_json["manager_badge"]
+            .as_str()
+            .ok_or_else(|| anyhow!("{PUBLISHED_EVENT_NAME} has no badge"))?,
+    )?;
+    context.conf.manager_badge_id = Some(manager_badge);
+
+    Ok(manager_badge)
+}
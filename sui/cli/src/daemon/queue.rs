@@ -0,0 +1,106 @@
+//! Prompts a node was sampled for are tracked in a local queue file so that
+//! a restarted daemon can resume those it received but didn't settle yet.
+//!
+//! Each ticket moves forward through the states
+//! received → inferring → committed → settled.
+//! A state can be skipped, eg. if the daemon crashed before recording it,
+//! but never entered again once left.
+//! Recording the current state again is a no-op, so that replaying a step
+//! after a crash is safe.
+//!
+//! The file is a JSON array of entries:
+//! ```json
+//! {
+//!   "ticket_id": "0x...",
+//!   "state": "inferring",
+//!   "received_at_ms": 1700000000000,
+//!   "updated_at_ms": 1700000001000
+//! }
+//! ```
+
+use std::path::Path;
+
+use clap::ValueEnum;
+use serde_json::json;
+
+use super::dlq::{load, now_ms, store};
+use crate::prelude::*;
+
+/// Settled tickets are kept this long for inspection.
+const SETTLED_RETENTION_MS: u64 = 7 * 24 * 60 * 60 * 1000;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, ValueEnum)]
+pub(crate) enum State {
+    Received,
+    Inferring,
+    Committed,
+    Settled,
+}
+
+impl State {
+    pub(crate) fn as_str(self) -> &'static str {
+        match self {
+            Self::Received => "received",
+            Self::Inferring => "inferring",
+            Self::Committed => "committed",
+            Self::Settled => "settled",
+        }
+    }
+
+    pub(crate) fn parse(state: &str) -> Result<Self> {
+        <Self as ValueEnum>::from_str(state, false)
+            .map_err(|_| anyhow!("Unknown queue state {state}"))
+    }
+}
+
+/// Moves the ticket to the state, adding it to the queue if it's not there
+/// yet.
+///
+/// The file is replaced atomically, so a crash leaves either the previous or
+/// the new state behind.
+pub(crate) fn transition(
+    queue_file: &Path,
+    ticket_id: &str,
+    to: State,
+) -> Result<()> {
+    let now_ms = now_ms()?;
+    let mut entries = load(queue_file)?;
+    entries.retain(|entry| {
+        entry["state"].as_str() != Some(State::Settled.as_str())
+            || entry["updated_at_ms"].as_u64().unwrap_or(0)
+                + SETTLED_RETENTION_MS
+                > now_ms
+    });
+
+    match entries
+        .iter_mut()
+        .find(|entry| entry["ticket_id"].as_str() == Some(ticket_id))
+    {
+        Some(entry) => {
+            let from =
+                State::parse(entry["state"].as_str().unwrap_or_default())?;
+            if to < from {
+                anyhow::bail!(
+                    "Ticket {ticket_id} is already {}, cannot move it back to {}",
+                    from.as_str(),
+                    to.as_str()
+                );
+            }
+            if to == from {
+                return Ok(());
+            }
+            entry["state"] = to.as_str().into();
+            entry["updated_at_ms"] = now_ms.into();
+        }
+        None => entries.push(json!({
+            "ticket_id": ticket_id,
+            "state": to.as_str(),
+            "received_at_ms": now_ms,
+            "updated_at_ms": now_ms,
+        })),
+    }
+    store(queue_file, &entries)?;
+    debug!("Ticket {ticket_id} is {} in {queue_file:?}", to.as_str());
+
+    Ok(())
+}
@@ -0,0 +1,39 @@
+use std::path::Path;
+
+use super::{
+    dlq::{load, now_ms},
+    queue::State,
+};
+use crate::prelude::*;
+
+/// Prints the queued tickets, oldest first.
+/// With `pending`, only those that are not settled yet, ie. the ones a
+/// restarted daemon has to resume.
+pub(crate) fn command(queue_file: &Path, pending: bool) -> Result<()> {
+    let mut entries = load(queue_file)?;
+    entries.retain(|entry| {
+        !pending || entry["state"].as_str() != Some(State::Settled.as_str())
+    });
+    if entries.is_empty() {
+        println!("No queued tickets in {queue_file:?}");
+        return Ok(());
+    }
+    entries.sort_by_key(|entry| entry["received_at_ms"].as_u64());
+
+    let now_ms = now_ms()?;
+    for entry in entries {
+        let state = State::parse(entry["state"].as_str().unwrap_or_default())?;
+        let age_secs = |field: &str| {
+            now_ms.saturating_sub(entry[field].as_u64().unwrap_or(0)) / 1000
+        };
+        println!(
+            "{} {} (received {}s ago, updated {}s ago)",
+            entry["ticket_id"].as_str().unwrap_or_default(),
+            state.as_str(),
+            age_secs("received_at_ms"),
+            age_secs("updated_at_ms")
+        );
+    }
+
+    Ok(())
+}
@@ -0,0 +1,215 @@
+//! Guardrails for daemons that hold hot keys.
+//!
+//! Every transaction of a daemon action is recorded in a local ledger with
+//! the gas it cost and the TOMA that left the active address.
+//! Before an action is executed, the spend of the last 24 hours is checked
+//! against the limits and the action is refused if it could exceed them.
+//!
+//! The ledger is a JSON array of entries:
+//! ```json
+//! { "digest": "...", "at_ms": 1700000000000, "gas_mist": 1000, "toma": 0 }
+//! ```
+
+use std::path::{Path, PathBuf};
+
+use serde_json::{json, Value};
+use sui_sdk::{
+    rpc_types::{
+        SuiTransactionBlockEffectsAPI, SuiTransactionBlockResponseOptions,
+    },
+    types::object::Owner,
+};
+
+use super::dlq::{load, now_ms, store};
+use crate::{denomination, prelude::*, toma::normalize_coin_type};
+
+pub(crate) const WATCHDOG_ALERT_URL: &str = "WATCHDOG_ALERT_URL";
+pub(crate) const WATCHDOG_FILE: &str = "WATCHDOG_FILE";
+pub(crate) const WATCHDOG_GAS_LIMIT: &str = "WATCHDOG_GAS_LIMIT";
+pub(crate) const WATCHDOG_TOMA_LIMIT: &str = "WATCHDOG_TOMA_LIMIT";
+const DEFAULT_WATCHDOG_FILE: &str = "atoma-watchdog.json";
+/// Limits apply to a rolling window of this length.
+pub(crate) const WINDOW_MS: u64 = 24 * 60 * 60 * 1000;
+
+#[derive(Debug, Default)]
+pub(crate) struct WatchdogConf {
+    pub(crate) file: Option<PathBuf>,
+    /// In MIST.
+    pub(crate) gas_limit: Option<u64>,
+    pub(crate) toma_limit: Option<u64>,
+    /// Refusals are POSTed as `{"text": "..."}` to this URL.
+    pub(crate) alert_url: Option<String>,
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct Spend {
+    pub(crate) gas_mist: u64,
+    pub(crate) toma: u64,
+    /// When the oldest entry in the window leaves it.
+    pub(crate) frees_up_at_ms: Option<u64>,
+}
+
+impl WatchdogConf {
+    pub(crate) fn from_env() -> Self {
+        let var =
+            |name: &str| std::env::var(name).ok().filter(|s| !s.is_empty());
+        Self {
+            file: var(WATCHDOG_FILE).map(PathBuf::from),
+            gas_limit: var(WATCHDOG_GAS_LIMIT).map(|s| s.parse().unwrap()),
+            toma_limit: var(WATCHDOG_TOMA_LIMIT)
+                .map(|s| denomination::parse_toma(&s).unwrap()),
+            alert_url: var(WATCHDOG_ALERT_URL),
+        }
+    }
+
+    /// Transactions are only tracked if there is a limit to enforce.
+    pub(crate) fn is_enabled(&self) -> bool {
+        self.gas_limit.is_some() || self.toma_limit.is_some()
+    }
+
+    pub(crate) fn file(&self) -> &Path {
+        self.file
+            .as_deref()
+            .unwrap_or_else(|| Path::new(DEFAULT_WATCHDOG_FILE))
+    }
+}
+
+/// Sums the entries of the ledger that are within the window.
+pub(crate) fn spent(ledger: &[Value], now_ms: u64) -> Spend {
+    let window_start = now_ms.saturating_sub(WINDOW_MS);
+    ledger
+        .iter()
+        .filter(|entry| entry["at_ms"].as_u64().unwrap_or(0) > window_start)
+        .fold(Spend::default(), |spend, entry| {
+            let at_ms = entry["at_ms"].as_u64().unwrap_or(0);
+            Spend {
+                gas_mist: spend.gas_mist
+                    + entry["gas_mist"].as_u64().unwrap_or(0),
+                toma: spend.toma + entry["toma"].as_u64().unwrap_or(0),
+                frees_up_at_ms: Some(
+                    spend
+                        .frees_up_at_ms
+                        .unwrap_or(u64::MAX)
+                        .min(at_ms + WINDOW_MS),
+                ),
+            }
+        })
+}
+
+/// Refuses the next transaction if it could exceed a limit.
+///
+/// We don't know the gas of a transaction before it's executed, so the whole
+/// gas budget counts.
+/// Likewise, we don't know how much TOMA it will spend, so it's refused once
+/// the TOMA limit has been reached.
+pub(crate) async fn guard(context: &Context) -> Result<()> {
+    let conf = &context.conf.watchdog;
+    if !conf.is_enabled() {
+        return Ok(());
+    }
+
+    let spend = spent(&load(conf.file())?, now_ms()?);
+    let refusal = match (conf.gas_limit, conf.toma_limit) {
+        (Some(gas_limit), _)
+            if spend.gas_mist + context.gas_budget() > gas_limit =>
+        {
+            format!(
+                "Gas spent in the last 24h is {} MIST, another {} MIST of \
+                gas budget would exceed the limit of {gas_limit} MIST",
+                spend.gas_mist,
+                context.gas_budget()
+            )
+        }
+        (_, Some(toma_limit)) if spend.toma >= toma_limit => format!(
+            "TOMA spent in the last 24h is {}, \
+            which reached the limit of {}",
+            context.toma(spend.toma),
+            context.toma(toma_limit)
+        ),
+        _ => return Ok(()),
+    };
+
+    alert(conf, &refusal).await;
+    Err(anyhow!("Watchdog refused the transaction: {refusal}"))
+}
+
+/// Adds the gas and TOMA outflow of the executed transaction to the ledger
+/// and drops the entries that left the window.
+pub(crate) async fn record(
+    context: &mut Context,
+    digest: TransactionDigest,
+) -> Result<()> {
+    if !context.conf.watchdog.is_enabled() {
+        return Ok(());
+    }
+
+    let active_address = context.wallet.active_address()?;
+    let toma_coin_type = context.get_or_load_toma_coin_type().await?;
+    let tx = context
+        .get_client()
+        .await?
+        .read_api()
+        .get_transaction_with_options(
+            digest,
+            SuiTransactionBlockResponseOptions::new()
+                .with_effects()
+                .with_balance_changes(),
+        )
+        .await?;
+
+    let gas_mist = tx
+        .effects
+        .ok_or_else(|| anyhow!("No effects for {digest}"))?
+        .gas_cost_summary()
+        .net_gas_usage()
+        .max(0) as u64;
+    let toma: i128 = tx
+        .balance_changes
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|change| {
+            change.owner == Owner::AddressOwner(active_address)
+                && normalize_coin_type(&change.coin_type.to_string()).ok()
+                    == Some(toma_coin_type.clone())
+        })
+        .map(|change| change.amount)
+        .sum();
+
+    let file = context.conf.watchdog.file();
+    let now_ms = now_ms()?;
+    let mut ledger = load(file)?;
+    ledger.retain(|entry| {
+        entry["at_ms"].as_u64().unwrap_or(0) > now_ms.saturating_sub(WINDOW_MS)
+    });
+    ledger.push(json!({
+        "digest": digest.to_string(),
+        "at_ms": now_ms,
+        "gas_mist": gas_mist,
+        // only outflows count towards the limit
+        "toma": (-toma).max(0) as u64,
+    }));
+    store(file, &ledger)?;
+
+    Ok(())
+}
+
+/// Alerts are best effort, the refusal is logged either way.
+async fn alert(conf: &WatchdogConf, message: &str) {
+    error!("{message}");
+    if let Some(alert_url) = &conf.alert_url {
+        post_alert(alert_url, &format!("Atoma watchdog: {message}")).await;
+    }
+}
+
+/// POSTs `{"text": "..."}`, which chat webhooks understand.
+pub(super) async fn post_alert(alert_url: &str, text: &str) {
+    let resp = reqwest::Client::new()
+        .post(alert_url)
+        .json(&json!({ "text": text }))
+        .send()
+        .await
+        .and_then(|resp| resp.error_for_status());
+    if let Err(err) = resp {
+        error!("Failed to send alert to {alert_url}: {err}");
+    }
+}
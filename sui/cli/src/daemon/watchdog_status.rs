@@ -0,0 +1,47 @@
+use super::{
+    dlq::{load, now_ms},
+    watchdog::spent,
+};
+use crate::prelude::*;
+
+/// Prints what the daemons spent in the last 24 hours against the limits.
+pub(crate) fn command(context: &Context) -> Result<()> {
+    let conf = &context.conf.watchdog;
+    if !conf.is_enabled() {
+        println!("Watchdog is disabled, no spend limit is set");
+        return Ok(());
+    }
+
+    let now_ms = now_ms()?;
+    let spend = spent(&load(conf.file())?, now_ms);
+    let usage =
+        |spent: u64, limit: Option<u64>, unit: &dyn Fn(u64) -> String| {
+            match limit {
+                Some(0) => format!("{} of {} (100%)", unit(spent), unit(0)),
+                Some(limit) => format!(
+                    "{} of {} ({}%)",
+                    unit(spent),
+                    unit(limit),
+                    spent * 100 / limit
+                ),
+                None => format!("{}, no limit", unit(spent)),
+            }
+        };
+
+    println!("Ledger: {:?}", conf.file());
+    let mist = |amount: u64| amount.to_string();
+    let toma = |amount: u64| context.toma(amount);
+    println!(
+        "Gas in MIST: {}",
+        usage(spend.gas_mist, conf.gas_limit, &mist)
+    );
+    println!("TOMA: {}", usage(spend.toma, conf.toma_limit, &toma));
+    if let Some(frees_up_at_ms) = spend.frees_up_at_ms {
+        println!(
+            "Oldest spend leaves the 24h window in {} min",
+            frees_up_at_ms.saturating_sub(now_ms) / 60_000
+        );
+    }
+
+    Ok(())
+}
@@ -7,46 +7,155 @@ mod acquire_new_stack_entry;
 mod add_model;
 mod add_model_echelon;
 mod add_node_to_model;
+mod announcements;
 mod claim_funds;
+mod claim_referral_fees;
 mod create_task_entry;
 mod deprecate_task;
 mod destroy_disabled_node;
+mod get_sampling_params;
+mod heartbeat;
+mod list_echelons;
+mod list_nodes;
+mod list_tasks;
+mod migrate;
+mod modality;
 mod new_network_key_rotation;
+mod node_info;
+mod node_versions;
+mod params;
+mod payout_split;
 mod permanently_disable_node;
+mod post_announcement;
 mod print_env;
+mod record_model_echelon_latency;
 mod register_node;
+mod remove_announcement;
 mod remove_deprecated_task;
 mod remove_node_from_model;
+mod remove_node_from_task_whitelist;
+mod remove_task_zk_verifier;
 mod rotate_node_public_key;
+mod set_model_echelon_settlement_timeout;
+mod set_node_payout_split;
+mod set_node_region;
+mod set_node_software_version;
 mod set_required_registration_collateral;
+mod set_sampling_params;
+mod set_stack_dispute_window;
+mod set_stack_gc_params;
+mod set_stale_node_sweep_params;
+mod set_task_min_node_version;
+mod set_task_min_price;
+mod set_task_zk_verifier;
+mod settlement_check;
+mod show_whitelist;
+mod snapshot;
+mod snapshot_diff;
+mod software_version;
 mod start_attestation_dispute;
 mod submit_stack_settlement_attestation;
 mod subscribe_node_to_task;
+mod sweep_stale_nodes;
+mod task_templates;
 mod try_settle_stack;
+mod try_settle_stack_with_zk_proof;
 mod unsubscribe_node_from_task;
 mod update_node_subscription;
+mod verify_vault;
 mod whitelist_nodes_for_task;
+mod zk_public_inputs;
 
 pub(crate) use acquire_new_stack_entry::command as acquire_new_stack_entry;
 pub(crate) use add_model::command as add_model;
 pub(crate) use add_model_echelon::command as add_model_echelon;
 pub(crate) use add_node_to_model::command as add_node_to_model;
+#[cfg(feature = "daemon")]
+pub(crate) use announcements::load as load_announcements;
+pub(crate) use announcements::{
+    command as announcements, Severity as AnnouncementSeverity,
+};
 pub(crate) use claim_funds::command as claim_funds;
-pub(crate) use create_task_entry::command as create_task_entry;
+pub(crate) use claim_referral_fees::command as claim_referral_fees;
+pub(crate) use create_task_entry::{
+    command as create_task_entry, TaskConstraints,
+};
 pub(crate) use deprecate_task::command as deprecate_task;
 pub(crate) use destroy_disabled_node::command as destroy_disabled_node;
+pub(crate) use get_sampling_params::{
+    command as get_sampling_params, load_task, task_security_level,
+    SamplingParams,
+};
+pub(crate) use heartbeat::command as heartbeat;
+pub(crate) use list_echelons::{
+    command as list_echelons, load_model, recommended_echelon, ModelEchelon,
+};
+pub(crate) use list_nodes::{
+    command as list_nodes, dynamic_field_values, parse_u64,
+};
+pub(crate) use list_tasks::command as list_tasks;
+pub(crate) use migrate::{
+    command as migrate, load_package, rewrite_env_file, type_origin,
+};
+pub(crate) use modality::Modality;
 pub(crate) use new_network_key_rotation::command as new_network_key_rotation;
+pub(crate) use node_info::{
+    command as node_info, decayed_reputation_score, load_node,
+    node_reputations, NodeReputation,
+};
+pub(crate) use node_versions::command as node_versions;
+pub(crate) use params::command as params;
+#[cfg(feature = "daemon")]
+pub(crate) use params::load as load_params;
+pub(crate) use payout_split::{
+    command as payout_split, validate_command as validate_payout_split,
+    PayoutShare,
+};
 pub(crate) use permanently_disable_node::command as permanently_disable_node;
+pub(crate) use post_announcement::command as post_announcement;
 pub(crate) use print_env::command as print_env;
+pub(crate) use record_model_echelon_latency::command as record_model_echelon_latency;
 pub(crate) use register_node::command as register_node;
+pub(crate) use remove_announcement::command as remove_announcement;
 pub(crate) use remove_deprecated_task::command as remove_deprecated_task;
 pub(crate) use remove_node_from_model::command as remove_node_from_model;
+pub(crate) use remove_node_from_task_whitelist::command as remove_node_from_task_whitelist;
+pub(crate) use remove_task_zk_verifier::command as remove_task_zk_verifier;
 pub(crate) use rotate_node_public_key::command as rotate_node_public_key;
+pub(crate) use set_model_echelon_settlement_timeout::command as set_model_echelon_settlement_timeout;
+pub(crate) use set_node_payout_split::command as set_node_payout_split;
+pub(crate) use set_node_region::command as set_node_region;
+pub(crate) use set_node_software_version::command as set_node_software_version;
 pub(crate) use set_required_registration_collateral::command as set_required_registration_collateral;
+pub(crate) use set_sampling_params::command as set_sampling_params;
+pub(crate) use set_stack_dispute_window::command as set_stack_dispute_window;
+pub(crate) use set_stack_gc_params::command as set_stack_gc_params;
+pub(crate) use set_stale_node_sweep_params::command as set_stale_node_sweep_params;
+pub(crate) use set_task_min_node_version::command as set_task_min_node_version;
+pub(crate) use set_task_min_price::{
+    command as set_task_min_price, task_min_prices,
+};
+pub(crate) use set_task_zk_verifier::{
+    command as set_task_zk_verifier, ZkCurve,
+};
+pub(crate) use settlement_check::{
+    command as check_settlement, Submission as SettlementSubmission,
+};
+pub(crate) use show_whitelist::command as show_whitelist;
+pub(crate) use snapshot::command as snapshot;
+pub(crate) use snapshot_diff::command as snapshot_diff;
+pub(crate) use software_version::SoftwareVersion;
 pub(crate) use start_attestation_dispute::command as start_attestation_dispute;
 pub(crate) use submit_stack_settlement_attestation::command as submit_stack_settlement_attestation;
 pub(crate) use subscribe_node_to_task::command as subscribe_node_to_task;
+pub(crate) use sweep_stale_nodes::command as sweep_stale_nodes;
+pub(crate) use task_templates::{
+    command as list_task_templates, expand as expand_task_template, TaskParams,
+};
 pub(crate) use try_settle_stack::command as try_settle_stack;
+pub(crate) use try_settle_stack_with_zk_proof::command as try_settle_stack_with_zk_proof;
 pub(crate) use unsubscribe_node_from_task::command as unsubscribe_node_from_task;
 pub(crate) use update_node_subscription::command as update_node_subscription;
+pub(crate) use verify_vault::command as verify_vault;
 pub(crate) use whitelist_nodes_for_task::command as whitelist_nodes_for_task;
+pub(crate) use zk_public_inputs::command as zk_public_inputs;
@@ -1,4 +1,4 @@
-use sui_sdk::types::SUI_RANDOMNESS_STATE_OBJECT_ID;
+use sui_sdk::types::{base_types::SuiAddress, SUI_RANDOMNESS_STATE_OBJECT_ID};
 
 use crate::{prelude::*, DB_MODULE_NAME};
 
@@ -9,18 +9,22 @@ pub(crate) async fn command(
     task_small_id: u64,
     num_compute_units: u64,
     price: u64,
+    referrer: Option<SuiAddress>,
+    referral_fee_share_bps: u64,
 ) -> Result<TransactionDigest> {
-    let active_address = context.wallet.active_address()?;
+    let sender = context.sender()?;
     let atoma_package = context.unwrap_atoma_package_id();
     let atoma_db = context.get_or_load_atoma_db().await?;
     let toma_wallet = context.get_or_load_toma_wallet().await?;
 
+    context.ensure_randomness_is_live().await?;
+
     let tx = context
         .get_client()
         .await?
         .transaction_builder()
         .move_call(
-            active_address,
+            sender,
             atoma_package,
             DB_MODULE_NAME,
             ENDPOINT_NAME,
@@ -31,6 +35,14 @@ pub(crate) async fn command(
                 SuiJsonValue::new(task_small_id.to_string().into())?,
                 SuiJsonValue::new(num_compute_units.to_string().into())?,
                 SuiJsonValue::new(price.to_string().into())?,
+                SuiJsonValue::new(
+                    referrer
+                        .into_iter()
+                        .map(|r| r.to_string())
+                        .collect::<Vec<_>>()
+                        .into(),
+                )?,
+                SuiJsonValue::new(referral_fee_share_bps.to_string().into())?,
                 SuiJsonValue::from_object_id(SUI_RANDOMNESS_STATE_OBJECT_ID),
             ],
             None,
@@ -39,7 +51,7 @@ pub(crate) async fn command(
         )
         .await?;
 
-    let tx = context.wallet.sign_transaction(&tx);
-    let resp = context.wallet.execute_transaction_must_succeed(tx).await;
+    let tx = context.sign_transaction(&tx)?;
+    let resp = context.execute_transaction_must_succeed(tx).await?;
     Ok(resp.digest)
 }
@@ -1,3 +1,4 @@
+use super::Modality;
 use crate::{prelude::*, DB_MODULE_NAME};
 
 const ENDPOINT_NAME: &str = "add_model_entry";
@@ -5,7 +6,7 @@ const ENDPOINT_NAME: &str = "add_model_entry";
 pub(crate) async fn command(
     context: &mut Context,
     model_name: &str,
-    modality: u64,
+    modality: Modality,
 ) -> Result<TransactionDigest> {
     let active_address = context.wallet.active_address()?;
     let atoma_package = context.unwrap_atoma_package_id();
@@ -26,7 +27,7 @@ pub(crate) async fn command(
                 SuiJsonValue::from_object_id(atoma_db),
                 SuiJsonValue::from_object_id(manager_badge),
                 SuiJsonValue::new(model_name.into())?,
-                SuiJsonValue::new(modality.to_string().into())?,
+                SuiJsonValue::new(u64::from(modality).to_string().into())?,
             ],
             None,
             context.gas_budget(),
@@ -34,7 +35,7 @@ pub(crate) async fn command(
         )
         .await?;
 
-    let tx = context.wallet.sign_transaction(&tx);
-    let resp = context.wallet.execute_transaction_must_succeed(tx).await;
+    let tx = context.sign_transaction(&tx)?;
+    let resp = context.execute_transaction_must_succeed(tx).await?;
     Ok(resp.digest)
 }
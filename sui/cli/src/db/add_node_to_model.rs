@@ -34,7 +34,7 @@ pub(crate) async fn command(
         )
         .await?;
 
-    let tx = context.wallet.sign_transaction(&tx);
-    let resp = context.wallet.execute_transaction_must_succeed(tx).await;
+    let tx = context.sign_transaction(&tx)?;
+    let resp = context.execute_transaction_must_succeed(tx).await?;
     Ok(resp.digest)
 }
@@ -0,0 +1,116 @@
+use clap::ValueEnum;
+use sui_sdk::types::base_types::ObjectID;
+
+use super::{dynamic_field_values, parse_u64};
+use crate::prelude::*;
+
+/// Must match the announcement severities in the db module.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub(crate) enum Severity {
+    Info,
+    Warning,
+    Critical,
+}
+
+impl Severity {
+    pub(crate) fn as_u8(self) -> u8 {
+        match self {
+            Self::Info => 0,
+            Self::Warning => 1,
+            Self::Critical => 2,
+        }
+    }
+
+    fn from_u8(severity: u8) -> Result<Self> {
+        Ok(match severity {
+            0 => Self::Info,
+            1 => Self::Warning,
+            2 => Self::Critical,
+            _ => anyhow::bail!("Unknown announcement severity {severity}"),
+        })
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct Announcement {
+    pub(crate) id: u64,
+    pub(crate) severity: Severity,
+    pub(crate) message: String,
+    pub(crate) effective_epoch: u64,
+    pub(crate) posted_at_epoch: u64,
+}
+
+/// Prints the announcements that haven't been removed, oldest first, and
+/// whether they're in effect in the current epoch.
+pub(crate) async fn command(context: &mut Context) -> Result<()> {
+    let announcements = load(context).await?;
+    if announcements.is_empty() {
+        println!("No announcements");
+        return Ok(());
+    }
+
+    let epoch = context
+        .get_client()
+        .await?
+        .governance_api()
+        .get_latest_sui_system_state()
+        .await?
+        .epoch;
+    for Announcement {
+        id,
+        severity,
+        message,
+        effective_epoch,
+        posted_at_epoch,
+    } in announcements
+    {
+        let effect = if effective_epoch <= epoch {
+            "in effect"
+        } else {
+            "upcoming"
+        };
+        println!(
+            "#{id} {severity:?}, {effect} from epoch {effective_epoch} \
+            (posted in epoch {posted_at_epoch})"
+        );
+        println!("  {message}");
+    }
+
+    Ok(())
+}
+
+/// Sorted by ID, which is the order they were posted in.
+pub(crate) async fn load(context: &mut Context) -> Result<Vec<Announcement>> {
+    let announcements = context.load_atoma_db_field("AnnouncementsKey").await?;
+    let announcements = ObjectID::from_str(
+        announcements["entries"]["id"]["id"]
+            .as_str()
+            .ok_or_else(|| anyhow!("No announcements table found"))?,
+    )?;
+    let client = context.get_client().await?;
+
+    let mut announcements = dynamic_field_values(&client, announcements)
+        .await?
+        .into_iter()
+        .map(|(key, announcement)| {
+            let announcement = &announcement["value"];
+            let severity = announcement["severity"]
+                .as_u64()
+                .and_then(|severity| u8::try_from(severity).ok())
+                .ok_or_else(|| anyhow!("Invalid announcement severity"))?;
+            Ok(Announcement {
+                id: parse_u64(&key)?,
+                severity: Severity::from_u8(severity)?,
+                message: announcement["message"]
+                    .as_str()
+                    .ok_or_else(|| anyhow!("Invalid announcement message"))?
+                    .to_string(),
+                effective_epoch: parse_u64(&announcement["effective_epoch"])?,
+                posted_at_epoch: parse_u64(&announcement["posted_at_epoch"])?,
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+    announcements.sort_by_key(|announcement| announcement.id);
+
+    Ok(announcements)
+}
@@ -1,3 +1,5 @@
+use sui_sdk::rpc_types::{SuiExecutionStatus, SuiTransactionBlockEffectsAPI};
+
 use crate::{prelude::*, DB_MODULE_NAME};
 
 const ENDPOINT_NAME: &str = "claim_funds";
@@ -32,7 +34,13 @@ pub(crate) async fn command(
         )
         .await?;
 
-    let tx = context.wallet.sign_transaction(&tx);
-    let resp = context.wallet.execute_transaction_must_succeed(tx).await;
+    let tx = context.sign_transaction(&tx)?;
+    // may fail so that the daemon can dead-letter the action
+    let resp = context.execute_transaction_may_fail(tx).await?;
+    if let SuiExecutionStatus::Failure { error } =
+        resp.effects.as_ref().unwrap().status()
+    {
+        anyhow::bail!("Transaction {} failed: {error}", resp.digest);
+    }
     Ok(resp.digest)
 }
@@ -0,0 +1,75 @@
+use sui_sdk::{
+    rpc_types::SuiData,
+    types::{base_types::ObjectID, dynamic_field::DynamicFieldName, TypeTag},
+};
+
+use crate::{prelude::*, DB_MODULE_NAME};
+
+const ENDPOINT_NAME: &str = "claim_referral_fees";
+
+/// 1. Look up how much the active address accrued as a referrer.
+/// 2. Claim it, unless there's nothing to claim.
+pub(crate) async fn command(
+    context: &mut Context,
+) -> Result<Option<TransactionDigest>> {
+    let active_address = context.wallet.active_address()?;
+    let atoma_package = context.unwrap_atoma_package_id();
+    let atoma_db = context.get_or_load_atoma_db().await?;
+
+    // 1.
+    let referrals = context.load_atoma_db_field("ReferralsKey").await?;
+    let referral_balances = ObjectID::from_str(
+        referrals["balances"]["id"]["id"]
+            .as_str()
+            .ok_or_else(|| anyhow!("No referral balances found"))?,
+    )?;
+    let balance = context
+        .get_client()
+        .await?
+        .read_api()
+        .get_dynamic_field_object(
+            referral_balances,
+            DynamicFieldName {
+                type_: TypeTag::Address,
+                value: serde_json::json!(active_address.to_string()),
+            },
+        )
+        .await?
+        .data
+        .and_then(|data| data.content)
+        .and_then(|content| content.try_into_move())
+        .and_then(|content| {
+            content.fields.to_json_value()["value"]
+                .as_str()?
+                .parse()
+                .ok()
+        })
+        .unwrap_or(0u64);
+    if balance == 0 {
+        info!("No referral fees to claim for {active_address}");
+        return Ok(None);
+    }
+    println!("Claiming {} in referral fees", context.toma(balance));
+
+    // 2.
+    let tx = context
+        .get_client()
+        .await?
+        .transaction_builder()
+        .move_call(
+            active_address,
+            atoma_package,
+            DB_MODULE_NAME,
+            ENDPOINT_NAME,
+            vec![],
+            vec![SuiJsonValue::from_object_id(atoma_db)],
+            None,
+            context.gas_budget(),
+            None,
+        )
+        .await?;
+
+    let tx = context.sign_transaction(&tx)?;
+    let resp = context.execute_transaction_must_succeed(tx).await?;
+    Ok(Some(resp.digest))
+}
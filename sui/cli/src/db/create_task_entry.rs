@@ -1,6 +1,32 @@
+use clap::Args;
+
 use crate::{prelude::*, DB_MODULE_NAME};
 
 const ENDPOINT_NAME: &str = "create_task_entry";
+const WITH_CONSTRAINTS_ENDPOINT_NAME: &str =
+    "create_task_with_constraints_entry";
+
+/// Bounds the worst-case cost of a single stack for the task.
+#[derive(Args, Default)]
+pub(crate) struct TaskConstraints {
+    /// Maximum number of input tokens a stack can be used for.
+    #[arg(long)]
+    pub(crate) max_input_tokens: Option<u64>,
+    /// Maximum number of output tokens a stack can be used for.
+    #[arg(long)]
+    pub(crate) max_output_tokens: Option<u64>,
+    /// Modalities the task's model may have, any if not provided.
+    #[arg(long)]
+    pub(crate) allowed_modalities: Vec<u64>,
+}
+
+impl TaskConstraints {
+    fn is_unbounded(&self) -> bool {
+        self.max_input_tokens.is_none()
+            && self.max_output_tokens.is_none()
+            && self.allowed_modalities.is_empty()
+    }
+}
 
 pub(crate) async fn command(
     context: &mut Context,
@@ -9,6 +35,7 @@ pub(crate) async fn command(
     security_level: Option<u16>,
     minimum_reputation_score: Option<u8>,
     is_public: bool,
+    constraints: TaskConstraints,
 ) -> Result<TransactionDigest> {
     let active_address = context.wallet.active_address()?;
     let atoma_package = context.unwrap_atoma_package_id();
@@ -20,6 +47,41 @@ pub(crate) async fn command(
     let minimum_reputation_score = minimum_reputation_score
         .map(|v| vec![v])
         .unwrap_or_default();
+    let mut args = vec![
+        SuiJsonValue::from_object_id(atoma_db),
+        SuiJsonValue::from_object_id(manager_badge),
+        SuiJsonValue::new(role.into())?,
+        SuiJsonValue::new(model_name.into())?,
+        SuiJsonValue::new(security_level.into())?,
+        SuiJsonValue::new(minimum_reputation_score.into())?,
+        SuiJsonValue::new(is_public.into())?,
+    ];
+    // tasks without constraints go through the original entry function
+    let endpoint = if constraints.is_unbounded() {
+        ENDPOINT_NAME
+    } else {
+        let TaskConstraints {
+            max_input_tokens,
+            max_output_tokens,
+            allowed_modalities,
+        } = constraints;
+        let max_input_tokens = max_input_tokens
+            .map(|v| vec![v.to_string()])
+            .unwrap_or_default();
+        let max_output_tokens = max_output_tokens
+            .map(|v| vec![v.to_string()])
+            .unwrap_or_default();
+        let allowed_modalities: Vec<_> = allowed_modalities
+            .into_iter()
+            .map(|v| v.to_string())
+            .collect();
+        args.extend([
+            SuiJsonValue::new(max_input_tokens.into())?,
+            SuiJsonValue::new(max_output_tokens.into())?,
+            SuiJsonValue::new(allowed_modalities.into())?,
+        ]);
+        WITH_CONSTRAINTS_ENDPOINT_NAME
+    };
 
     let tx = context
         .get_client()
@@ -29,24 +91,16 @@ pub(crate) async fn command(
             active_address,
             atoma_package,
             DB_MODULE_NAME,
-            ENDPOINT_NAME,
+            endpoint,
             vec![],
-            vec![
-                SuiJsonValue::from_object_id(atoma_db),
-                SuiJsonValue::from_object_id(manager_badge),
-                SuiJsonValue::new(role.into())?,
-                SuiJsonValue::new(model_name.into())?,
-                SuiJsonValue::new(security_level.into())?,
-                SuiJsonValue::new(minimum_reputation_score.into())?,
-                SuiJsonValue::new(is_public.into())?,
-            ],
+            args,
             None,
             context.gas_budget(),
             None,
         )
         .await?;
 
-    let tx = context.wallet.sign_transaction(&tx);
-    let resp = context.wallet.execute_transaction_must_succeed(tx).await;
+    let tx = context.sign_transaction(&tx)?;
+    let resp = context.execute_transaction_must_succeed(tx).await?;
     Ok(resp.digest)
 }
@@ -0,0 +1,143 @@
+use move_core_types::language_storage::StructTag;
+use sui_sdk::{
+    rpc_types::SuiData,
+    types::{base_types::ObjectID, dynamic_field::DynamicFieldName, TypeTag},
+};
+
+use crate::{prelude::*, DB_MODULE_NAME};
+
+const TASK_SMALL_ID_TYPE_NAME: &str = "TaskSmallId";
+/// Must match the on-chain security level.
+const SAMPLING_CONSENSUS: u64 = 2;
+const ONE_MILLION_COMPUTE_UNITS: u128 = 1_000_000;
+
+/// Parameters of the sampling consensus security level as stored in
+/// `AtomaDb`.
+pub(crate) struct SamplingParams {
+    pub(crate) cross_validation_probability_permille: u64,
+    pub(crate) cross_validation_extra_nodes_count: u64,
+    pub(crate) sampling_consensus_charge_permille: u64,
+    pub(crate) cross_validation_extra_nodes_charge_permille: u64,
+}
+
+impl SamplingParams {
+    pub(crate) async fn load(context: &mut Context) -> Result<Self> {
+        let fields = context.load_atoma_db_fields().await?;
+        let field = |name: &str| {
+            fields[name]
+                .as_str()
+                .and_then(|value| value.parse().ok())
+                .ok_or_else(|| anyhow!("No {name} field found"))
+        };
+
+        Ok(Self {
+            cross_validation_probability_permille: field(
+                "cross_validation_probability_permille",
+            )?,
+            cross_validation_extra_nodes_count: field(
+                "cross_validation_extra_nodes_count",
+            )?,
+            sampling_consensus_charge_permille: field(
+                "sampling_consensus_charge_permille",
+            )?,
+            cross_validation_extra_nodes_charge_permille: field(
+                "cross_validation_extra_nodes_charge_permille",
+            )?,
+        })
+    }
+
+    /// What the user pays when acquiring a stack of a task with the given
+    /// security level.
+    /// Must match `db::acquire_new_stack`.
+    pub(crate) fn stack_cost(
+        &self,
+        security_level: u64,
+        price_per_one_million_compute_units: u64,
+        num_compute_units: u64,
+    ) -> u64 {
+        let total_cost = price_per_one_million_compute_units as u128
+            * num_compute_units as u128
+            / ONE_MILLION_COMPUTE_UNITS;
+        if security_level == SAMPLING_CONSENSUS {
+            (total_cost
+                * (self.sampling_consensus_charge_permille
+                    + self.cross_validation_extra_nodes_charge_permille)
+                    as u128
+                / 1000) as u64
+        } else {
+            total_cost as u64
+        }
+    }
+}
+
+pub(crate) async fn command(context: &mut Context) -> Result<()> {
+    let params = SamplingParams::load(context).await?;
+
+    println!(
+        "Cross validation probability: {}‰",
+        params.cross_validation_probability_permille
+    );
+    println!(
+        "Cross validation extra nodes: {}",
+        params.cross_validation_extra_nodes_count
+    );
+    println!(
+        "Sampling consensus charge: {}‰",
+        params.sampling_consensus_charge_permille
+    );
+    println!(
+        "Cross validation extra nodes charge: {}‰",
+        params.cross_validation_extra_nodes_charge_permille
+    );
+
+    Ok(())
+}
+
+pub(crate) async fn task_security_level(
+    context: &mut Context,
+    task_small_id: u64,
+) -> Result<u64> {
+    load_task(context, task_small_id).await?["security_level"]["inner"]
+        .as_u64()
+        .ok_or_else(|| anyhow!("Task {task_small_id} has no security level"))
+}
+
+/// The fields of the task as JSON.
+pub(crate) async fn load_task(
+    context: &mut Context,
+    task_small_id: u64,
+) -> Result<serde_json::Value> {
+    let package = context.unwrap_atoma_package_id();
+    let tasks_id = ObjectID::from_str(
+        context.load_atoma_db_fields().await?["tasks"]["id"]["id"]
+            .as_str()
+            .ok_or_else(|| anyhow!("No tasks field found"))?,
+    )?;
+
+    // tasks are stored as dynamic object fields, so we get the task itself
+    let task = context
+        .get_client()
+        .await?
+        .read_api()
+        .get_dynamic_field_object(
+            tasks_id,
+            DynamicFieldName {
+                type_: TypeTag::Struct(Box::new(StructTag {
+                    address: package.into(),
+                    module: FromStr::from_str(DB_MODULE_NAME)?,
+                    name: FromStr::from_str(TASK_SMALL_ID_TYPE_NAME)?,
+                    type_params: vec![],
+                })),
+                value: serde_json::json!({
+                    "inner": task_small_id.to_string()
+                }),
+            },
+        )
+        .await?
+        .data
+        .and_then(|data| data.content)
+        .and_then(|content| content.try_into_move())
+        .ok_or_else(|| anyhow!("Task {task_small_id} not found"))?;
+
+    Ok(task.fields.to_json_value())
+}
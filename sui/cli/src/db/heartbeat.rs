@@ -0,0 +1,39 @@
+use crate::{prelude::*, DB_MODULE_NAME};
+
+const ENDPOINT_NAME: &str = "heartbeat";
+
+/// Must be sent at least once every `threshold_epochs` epochs of the
+/// stale node sweeps,
+/// otherwise anyone can sweep the node from its task subscriptions.
+pub(crate) async fn command(
+    context: &mut Context,
+) -> Result<TransactionDigest> {
+    let active_address = context.wallet.active_address()?;
+    let atoma_package = context.unwrap_atoma_package_id();
+    let atoma_db = context.get_or_load_atoma_db().await?;
+    let (node_badge, _) = context.get_or_load_node_badge().await?;
+
+    let tx = context
+        .get_client()
+        .await?
+        .transaction_builder()
+        .move_call(
+            active_address,
+            atoma_package,
+            DB_MODULE_NAME,
+            ENDPOINT_NAME,
+            vec![],
+            vec![
+                SuiJsonValue::from_object_id(atoma_db),
+                SuiJsonValue::from_object_id(node_badge),
+            ],
+            None,
+            context.gas_budget(),
+            None,
+        )
+        .await?;
+
+    let tx = context.sign_transaction(&tx)?;
+    let resp = context.execute_transaction_must_succeed(tx).await?;
+    Ok(resp.digest)
+}
@@ -0,0 +1,308 @@
+use std::collections::HashMap;
+
+use serde_json::Value;
+use sui_sdk::{
+    rpc_types::{Page, SuiData, SuiObjectDataOptions},
+    types::{base_types::ObjectID, dynamic_field::DynamicFieldName},
+};
+
+use super::{dynamic_field_values, parse_u64};
+use crate::{
+    prelude::*,
+    sampling::{latency_weighted_performance, priority_weighted_performance},
+    DynamicFieldNameExt,
+};
+
+/// Typed view of `db::ModelEchelon`.
+pub(crate) struct ModelEchelon {
+    pub(crate) id: u64,
+    settlement_timeout_ms: u64,
+    pub(crate) input_fee_per_token: u64,
+    pub(crate) output_fee_per_token: u64,
+    pub(crate) relative_performance: u64,
+    oracles_count: usize,
+    /// Median latency reported by an oracle, if measured yet.
+    /// Stored in the db under `ModelEchelonLatenciesKey`, see `load_model`.
+    pub(crate) latency_p50_ms: Option<u64>,
+    /// Length of the `nodes` table vec.
+    pub(crate) nodes_count: u64,
+    /// Root of the `nodes` table vec, keyed by the node's index.
+    pub(crate) nodes_table: ObjectID,
+}
+
+impl ModelEchelon {
+    /// u64 values are serialized as strings.
+    fn from_json(echelon: &Value) -> Result<Self> {
+        let u64_at = |value: &Value, name: &str| {
+            value
+                .as_str()
+                .and_then(|s| s.parse::<u64>().ok())
+                .ok_or_else(|| anyhow!("Echelon has invalid {name}"))
+        };
+
+        Ok(Self {
+            id: u64_at(&echelon["id"]["id"], "id")?,
+            settlement_timeout_ms: u64_at(
+                &echelon["settlement_timeout_ms"],
+                "settlement_timeout_ms",
+            )?,
+            input_fee_per_token: u64_at(
+                &echelon["input_fee_per_token"],
+                "input_fee_per_token",
+            )?,
+            output_fee_per_token: u64_at(
+                &echelon["output_fee_per_token"],
+                "output_fee_per_token",
+            )?,
+            relative_performance: u64_at(
+                &echelon["relative_performance"],
+                "relative_performance",
+            )?,
+            oracles_count: echelon["oracles"]["contents"]
+                .as_array()
+                .map_or(0, Vec::len),
+            latency_p50_ms: None,
+            nodes_count: u64_at(
+                &echelon["nodes"]["contents"]["size"],
+                "nodes size",
+            )?,
+            nodes_table: echelon["nodes"]["contents"]["id"]["id"]
+                .as_str()
+                .ok_or_else(|| anyhow!("Echelon has no nodes table"))
+                .and_then(|id| Ok(ObjectID::from_str(id)?))?,
+        })
+    }
+
+    /// The gate picks an echelon with probability proportional to its
+    /// relative performance times the number of its nodes.
+    /// Higher priorities weigh the relative performance more, and preferring
+    /// low latency divides the weight by the median latency.
+    /// Fails where the contract's u256 weight doesn't fit into u128.
+    pub(crate) fn selection_weight(
+        &self,
+        priority: u8,
+        prefer_low_latency: bool,
+    ) -> Result<u128> {
+        let weight =
+            priority_weighted_performance(self.relative_performance, priority)
+                .and_then(|weight| weight.checked_mul(self.nodes_count as u128))
+                .and_then(|weight| {
+                    if prefer_low_latency {
+                        latency_weighted_performance(
+                            weight,
+                            self.latency_p50_ms,
+                        )
+                    } else {
+                        Some(weight)
+                    }
+                });
+
+        weight.ok_or_else(|| {
+            anyhow!("The weight of echelon {} overflows u128", self.id)
+        })
+    }
+}
+
+/// 1. Load the model and parse its echelons.
+/// 2. Count the open tickets per echelon of the model.
+/// 3. Print the echelons with their share of the prompts that the gate would
+///    route to them, assuming no fee cap.
+pub(crate) async fn command(
+    context: &mut Context,
+    model_name: &str,
+) -> Result<()> {
+    // 1.
+    let (model, echelons) = load_model(context, model_name).await?;
+    let tickets_root = ObjectID::from_str(
+        context.load_atoma_db_fields().await?["tickets"]["id"]
+            .as_str()
+            .ok_or_else(|| anyhow!("No tickets field found"))?,
+    )?;
+    let client = context.get_client().await?;
+
+    // 2.
+    let mut open_tickets: HashMap<u64, usize> = HashMap::new();
+    let mut cursor = None;
+    loop {
+        let Page {
+            data,
+            has_next_page,
+            next_cursor,
+        } = client
+            .read_api()
+            .get_dynamic_fields(tickets_root, cursor, None)
+            .await?;
+        cursor = next_cursor;
+
+        let tickets_page = data.iter().map(|info| info.object_id).collect();
+        let tickets = client
+            .read_api()
+            .multi_get_object_with_options(
+                tickets_page,
+                SuiObjectDataOptions {
+                    show_content: true,
+                    ..Default::default()
+                },
+            )
+            .await?
+            .into_iter()
+            // ignore tickets that have been deleted between the calls
+            .filter_map(|ticket| {
+                Some(
+                    ticket
+                        .data?
+                        .content?
+                        .try_as_move()
+                        .cloned()?
+                        .fields
+                        .to_json_value(),
+                )
+            });
+
+        for ticket in tickets {
+            if ticket["model_name"].as_str() != Some(model_name) {
+                continue;
+            }
+            if let Some(echelon_id) = ticket["echelon_id"]["id"]
+                .as_str()
+                .and_then(|id| id.parse::<u64>().ok())
+            {
+                *open_tickets.entry(echelon_id).or_default() += 1;
+            }
+        }
+
+        if !has_next_page {
+            break;
+        }
+    }
+
+    // 3.
+    let weights = echelons
+        .iter()
+        .map(|echelon| echelon.selection_weight(0, false))
+        .collect::<Result<Vec<_>>>()?;
+    // only the shares are printed, so f64 is precise enough
+    let total_weight: f64 = weights.iter().map(|weight| *weight as f64).sum();
+    println!("Model: {model_name}");
+    if model["is_disabled"].as_bool() == Some(true) {
+        println!("[DISABLED]");
+    }
+    for (echelon, weight) in echelons.iter().zip(weights) {
+        let share = if total_weight == 0.0 {
+            0.0
+        } else {
+            weight as f64 * 100.0 / total_weight
+        };
+
+        println!("----------------------------");
+        println!("Echelon: {}", echelon.id);
+        println!(
+            "Nodes: {}, oracles: {}",
+            echelon.nodes_count, echelon.oracles_count
+        );
+        println!(
+            "Fee per input token: {}, fee per output token: {}",
+            context.toma(echelon.input_fee_per_token),
+            context.toma(echelon.output_fee_per_token)
+        );
+        println!(
+            "Relative performance: {}, selection share: {share:.2}%",
+            echelon.relative_performance
+        );
+        println!(
+            "Settlement timeout: {} ms, open tickets: {}",
+            echelon.settlement_timeout_ms,
+            open_tickets.get(&echelon.id).copied().unwrap_or(0)
+        );
+        match echelon.latency_p50_ms {
+            Some(latency_p50_ms) => {
+                println!("Median latency: {latency_p50_ms} ms")
+            }
+            None => println!("Median latency: not measured"),
+        }
+    }
+
+    Ok(())
+}
+
+/// The echelon where a new node can expect the most fees, ie. the one with
+/// the highest relative performance times fees.
+/// Since the gate picks an echelon by its relative performance times the
+/// number of its nodes, the expected share of a single node depends on the
+/// relative performance rather than on how crowded the echelon is.
+///
+/// The node must still have the hardware the echelon stands for.
+pub(crate) async fn recommended_echelon(
+    context: &mut Context,
+    model_name: &str,
+) -> Result<Option<u64>> {
+    let (_, echelons) = load_model(context, model_name).await?;
+
+    Ok(echelons
+        .iter()
+        .max_by_key(|echelon| {
+            echelon.relative_performance as u128
+                * (echelon.input_fee_per_token + echelon.output_fee_per_token)
+                    as u128
+        })
+        .map(|echelon| echelon.id))
+}
+
+/// The model fields and its parsed echelons.
+pub(crate) async fn load_model(
+    context: &mut Context,
+    model_name: &str,
+) -> Result<(Value, Vec<ModelEchelon>)> {
+    let models_root = ObjectID::from_str(
+        context.load_atoma_db_fields().await?["models"]["id"]["id"]
+            .as_str()
+            .ok_or_else(|| anyhow!("No models field found"))?,
+    )?;
+
+    let model = context
+        .get_client()
+        .await?
+        .read_api()
+        .get_dynamic_field_object(
+            models_root,
+            DynamicFieldName::ascii(model_name),
+        )
+        .await?
+        .data
+        .ok_or_else(|| anyhow!("Model {model_name} not found on Atoma"))?
+        .content
+        .unwrap()
+        .try_into_move()
+        .unwrap()
+        .fields
+        .to_json_value();
+    let mut echelons = model["echelons"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(ModelEchelon::from_json)
+        .collect::<Result<Vec<_>>>()?;
+
+    let latencies_table = ObjectID::from_str(
+        context
+            .load_atoma_db_field("ModelEchelonLatenciesKey")
+            .await?["id"]["id"]
+            .as_str()
+            .ok_or_else(|| anyhow!("No echelon latencies found"))?,
+    )?;
+    let client = context.get_client().await?;
+    for (key, latency) in dynamic_field_values(&client, latencies_table).await?
+    {
+        if key["model_name"].as_str() != Some(model_name) {
+            continue;
+        }
+        let echelon_id = parse_u64(&key["echelon_id"]["id"])?;
+        if let Some(echelon) =
+            echelons.iter_mut().find(|echelon| echelon.id == echelon_id)
+        {
+            echelon.latency_p50_ms = Some(parse_u64(&latency["value"])?);
+        }
+    }
+
+    Ok((model, echelons))
+}
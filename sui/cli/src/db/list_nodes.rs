@@ -0,0 +1,231 @@
+use std::{collections::BTreeMap, path::Path};
+
+use serde_json::Value;
+use sui_sdk::{
+    rpc_types::{Page, SuiData, SuiObjectDataOptions},
+    types::base_types::ObjectID,
+    SuiClient,
+};
+
+use super::{decayed_reputation_score, node_reputations, NodeReputation};
+use crate::prelude::*;
+
+const CSV_HEADER: &str =
+    "node_small_id,collateral,reputation,disabled_in_epoch,\
+    last_activity_epoch,subscribed_tasks";
+
+struct NodeRow {
+    collateral: u64,
+    reputation: u64,
+    disabled_in_epoch: Option<u64>,
+    last_activity_epoch: u64,
+    subscribed_tasks: Vec<u64>,
+}
+
+/// 1. Load all node entries.
+/// 2. Walk the subscribed nodes of every task to find each node's
+///    subscriptions, since nodes don't keep track of them.
+/// 3. Print a line per node or write them into a CSV file.
+///
+/// The reputation is the score with decay applied, see `node-info`.
+/// The last activity epoch is the latest epoch in which the node was paid a
+/// fee or refreshed its confidential compute key, as recorded in the node
+/// entry.
+pub(crate) async fn command(
+    context: &mut Context,
+    csv: Option<&Path>,
+) -> Result<()> {
+    let db_fields = context.load_atoma_db_fields().await?;
+    let table_id = |name: &str| {
+        db_fields[name]["id"]["id"]
+            .as_str()
+            .ok_or_else(|| anyhow!("No {name} field found"))
+            .and_then(|id| Ok(ObjectID::from_str(id)?))
+    };
+    let nodes_table = table_id("nodes")?;
+    let tasks_table = table_id("tasks")?;
+    let mut reputations = node_reputations(context).await?;
+
+    let client = context.get_client().await?;
+    let epoch = client
+        .governance_api()
+        .get_latest_sui_system_state()
+        .await?
+        .epoch;
+
+    // 1.
+    let mut nodes = BTreeMap::new();
+    for (key, node) in dynamic_field_values(&client, nodes_table).await? {
+        let node_small_id = parse_u64(&key["inner"])?;
+        let node = &node["value"];
+        let NodeReputation {
+            updated_at_epoch, ..
+        } = reputations
+            .remove(&node_small_id)
+            .unwrap_or_else(|| NodeReputation::fresh(epoch));
+        let reputation = decayed_reputation_score(
+            node["reputation_score"]["inner"]
+                .as_u64()
+                .unwrap_or_default(),
+            updated_at_epoch,
+            epoch,
+        );
+        let last_activity_epoch = parse_u64(&node["last_fee_epoch"])?.max(
+            parse_u64(&node["confidential_compute_last_updated_epoch"])
+                .unwrap_or_default(),
+        );
+        nodes.insert(
+            node_small_id,
+            NodeRow {
+                collateral: parse_u64(&node["collateral"])?,
+                reputation,
+                disabled_in_epoch: parse_u64(&node["was_disabled_in_epoch"])
+                    .ok(),
+                last_activity_epoch,
+                subscribed_tasks: vec![],
+            },
+        );
+    }
+    context.progress("nodes", 1, 2);
+
+    // 2.
+    for (key, task) in dynamic_field_values(&client, tasks_table).await? {
+        let task_small_id = parse_u64(&key["inner"])?;
+        let subscribed_nodes =
+            ObjectID::from_str(
+                task["subscribed_nodes"]["id"]["id"].as_str().ok_or_else(
+                    || anyhow!("Task {task_small_id} has no subscribed nodes"),
+                )?,
+            )?;
+        for node_small_id in dynamic_field_keys(&client, subscribed_nodes)
+            .await?
+            .iter()
+            .map(|key| parse_u64(&key["inner"]))
+        {
+            if let Some(node) = nodes.get_mut(&node_small_id?) {
+                node.subscribed_tasks.push(task_small_id);
+            }
+        }
+    }
+    context.progress("nodes", 2, 2);
+
+    // 3.
+    let tasks = |node: &NodeRow| {
+        node.subscribed_tasks
+            .iter()
+            .map(u64::to_string)
+            .collect::<Vec<_>>()
+    };
+    if let Some(csv) = csv {
+        let mut lines = vec![CSV_HEADER.to_owned()];
+        for (node_small_id, node) in &nodes {
+            lines.push(format!(
+                "{node_small_id},{},{},{},{},{}",
+                node.collateral,
+                node.reputation,
+                node.disabled_in_epoch
+                    .map(|epoch| epoch.to_string())
+                    .unwrap_or_default(),
+                node.last_activity_epoch,
+                tasks(node).join(" "),
+            ));
+        }
+        std::fs::write(csv, lines.join("\n") + "\n")?;
+        println!("Written {} node(s) to {csv:?}", nodes.len());
+    } else {
+        for (node_small_id, node) in &nodes {
+            println!("----------------------------");
+            if let Some(disabled_in_epoch) = node.disabled_in_epoch {
+                print!("[DISABLED IN EPOCH {disabled_in_epoch}] ");
+            }
+            println!("Node small ID: {node_small_id}");
+            println!(
+                "Collateral: {}, reputation: {}",
+                context.toma(node.collateral),
+                node.reputation
+            );
+            println!("Last activity in epoch: {}", node.last_activity_epoch);
+            println!("Subscribed tasks: {}", tasks(node).join(", "));
+        }
+    }
+
+    Ok(())
+}
+
+pub(crate) fn parse_u64(value: &Value) -> Result<u64> {
+    value
+        .as_str()
+        .and_then(|s| s.parse().ok())
+        .ok_or_else(|| anyhow!("Expected a u64 string, got {value}"))
+}
+
+pub(crate) async fn dynamic_field_keys(
+    client: &SuiClient,
+    root: ObjectID,
+) -> Result<Vec<Value>> {
+    let mut keys = vec![];
+    let mut cursor = None;
+    loop {
+        let Page {
+            data,
+            has_next_page,
+            next_cursor,
+        } = client
+            .read_api()
+            .get_dynamic_fields(root, cursor, None)
+            .await?;
+        keys.extend(data.into_iter().map(|info| info.name.value));
+
+        if !has_next_page {
+            return Ok(keys);
+        }
+        cursor = next_cursor;
+    }
+}
+
+/// Returns the key and the object of every dynamic field.
+/// For plain tables the value is under the `value` field of the object.
+pub(crate) async fn dynamic_field_values(
+    client: &SuiClient,
+    root: ObjectID,
+) -> Result<Vec<(Value, Value)>> {
+    let mut values = vec![];
+    let mut cursor = None;
+    loop {
+        let Page {
+            data,
+            has_next_page,
+            next_cursor,
+        } = client
+            .read_api()
+            .get_dynamic_fields(root, cursor, None)
+            .await?;
+
+        let objects = client
+            .read_api()
+            .multi_get_object_with_options(
+                data.iter().map(|info| info.object_id).collect(),
+                SuiObjectDataOptions {
+                    show_content: true,
+                    ..Default::default()
+                },
+            )
+            .await?;
+        // ignore entries that have been deleted between the calls
+        for (info, object) in data.into_iter().zip(objects) {
+            let Some(object) = object
+                .data
+                .and_then(|data| data.content)
+                .and_then(|content| content.try_as_move().cloned())
+            else {
+                continue;
+            };
+            values.push((info.name.value, object.fields.to_json_value()));
+        }
+
+        if !has_next_page {
+            return Ok(values);
+        }
+        cursor = next_cursor;
+    }
+}
@@ -0,0 +1,163 @@
+use std::collections::BTreeMap;
+
+use serde_json::Value;
+use sui_sdk::{
+    rpc_types::{Page, SuiData, SuiObjectDataOptions},
+    types::base_types::ObjectID,
+};
+
+use super::{dynamic_field_values, parse_u64, task_min_prices, Modality};
+use crate::{prelude::*, wait_for_user_confirm};
+
+pub(crate) async fn command(context: &mut Context) -> Result<()> {
+    let tasks_root = ObjectID::from_str(
+        context.load_atoma_db_fields().await?["tasks"]["id"]["id"]
+            .as_str()
+            .ok_or_else(|| anyhow!("No tasks field found"))?,
+    )?;
+    let min_prices = task_min_prices(context).await?;
+    let all_constraints = task_constraints(context).await?;
+
+    let mut cursor = None;
+
+    let client = context.get_client().await?;
+    loop {
+        let Page {
+            data,
+            has_next_page,
+            next_cursor,
+        } = client
+            .read_api()
+            .get_dynamic_fields(tasks_root, cursor, None)
+            .await?;
+        cursor = next_cursor;
+
+        let small_ids: Vec<_> = data
+            .iter()
+            .map(|info| info.name.value["inner"].as_str().unwrap().to_owned())
+            .collect();
+        let tasks_page = data.iter().map(|info| info.object_id).collect();
+        let tasks = client
+            .read_api()
+            .multi_get_object_with_options(
+                tasks_page,
+                SuiObjectDataOptions {
+                    show_content: true,
+                    ..Default::default()
+                },
+            )
+            .await?
+            .into_iter()
+            .map(|task| {
+                Some(
+                    task.data?
+                        .content?
+                        .try_as_move()
+                        .cloned()?
+                        .fields
+                        .to_json_value(),
+                )
+            });
+
+        // ignore tasks that have been removed between the calls
+        for (small_id, task) in small_ids.into_iter().zip(tasks) {
+            let Some(task) = task else { continue };
+
+            let role = task["role"]["inner"].as_u64().unwrap();
+            let model = task["model_name"].as_str().unwrap_or("-");
+            let security_level =
+                task["security_level"]["inner"].as_u64().unwrap();
+            let min_reputation =
+                task["minimum_reputation_score"]["inner"].as_u64().unwrap();
+            let is_public = task["is_public"].as_bool().unwrap();
+            let is_deprecated = task["is_deprecated"].as_bool().unwrap();
+            let constraints = small_id
+                .parse()
+                .ok()
+                .and_then(|small_id: u64| all_constraints.get(&small_id))
+                .unwrap_or(&Value::Null);
+            let limit = |field: &str| {
+                constraints[field]
+                    .as_str()
+                    .unwrap_or("unbounded")
+                    .to_owned()
+            };
+            let modalities = constraints["allowed_modalities"]
+                .as_array()
+                .into_iter()
+                .flatten()
+                .map(|m| {
+                    let m = m.as_str().unwrap();
+                    match m.parse::<u64>().map(Modality::try_from) {
+                        Ok(Ok(modality)) => modality.to_string(),
+                        _ => m.to_owned(),
+                    }
+                })
+                .collect::<Vec<_>>();
+
+            println!("----------------------------");
+            if is_deprecated {
+                print!("[DEPRECATED] ");
+            }
+            println!("Task small ID: {small_id}");
+            println!("Role: {role}, security level: {security_level}");
+            println!("Model: {model}");
+            println!(
+                "Public: {is_public}, minimum reputation: {min_reputation}"
+            );
+            if let Some(min_price) = small_id
+                .parse()
+                .ok()
+                .and_then(|small_id: u64| min_prices.get(&small_id))
+            {
+                println!(
+                    "Minimum price per one million compute units: {}",
+                    context.toma(*min_price)
+                );
+            }
+            println!(
+                "Max input tokens: {}, max output tokens: {}",
+                limit("max_input_tokens"),
+                limit("max_output_tokens"),
+            );
+            if modalities.is_empty() {
+                println!("Allowed modalities: any");
+            } else {
+                println!("Allowed modalities: {}", modalities.join(", "));
+            }
+        }
+
+        if has_next_page {
+            println!();
+            println!("Load next page? (Y/n)");
+            if !wait_for_user_confirm() {
+                break;
+            }
+            println!();
+        } else {
+            break;
+        }
+    }
+
+    Ok(())
+}
+
+/// Tasks that were created without constraints have no entry.
+async fn task_constraints(
+    context: &mut Context,
+) -> Result<BTreeMap<u64, Value>> {
+    let all_constraints = ObjectID::from_str(
+        context.load_atoma_db_field("TaskConstraintsKey").await?["id"]["id"]
+            .as_str()
+            .ok_or_else(|| anyhow!("No task constraints found"))?,
+    )?;
+    let client = context.get_client().await?;
+
+    dynamic_field_values(&client, all_constraints)
+        .await?
+        .into_iter()
+        .map(|(key, constraints)| {
+            Ok((parse_u64(&key["inner"])?, constraints["value"].clone()))
+        })
+        .collect()
+}
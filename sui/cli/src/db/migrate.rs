@@ -0,0 +1,234 @@
+use std::path::{Path, PathBuf};
+
+use sui_sdk::{
+    rpc_types::{SuiObjectDataOptions, SuiRawData, SuiRawMovePackage},
+    types::base_types::ObjectID,
+};
+
+use crate::{
+    dotenv_conf::{
+        ATOMA_DB_ID, ATOMA_PACKAGE_ID, FAUCET_ID, MANAGER_BADGE_ID,
+        NODE_BADGE_ID, NODE_ID, TOMA_PACKAGE_ID, TOMA_WALLET_ID,
+    },
+    prelude::*,
+    DB_MANAGER_TYPE_NAME, DB_MODULE_NAME, DB_NODE_TYPE_NAME, DB_TYPE_NAME,
+};
+
+const ENDPOINT_NAME: &str = "migrate";
+
+/// 1. Load both the configured and the new package and check that the new one
+///    is an upgrade of the configured one, ie. that the db types originate
+///    in the same package.
+/// 2. Re-resolve the AtomaDb, faucet and badge IDs.
+///    Objects keep the type of the package that defined it, so we look them
+///    up by the original package.
+/// 3. Rewrite the env file with the new IDs, keeping a backup of the old one.
+/// 4. If the active address holds the manager badge, add the state that was
+///    introduced after the db was published.
+///    The struct layouts can't change on upgrade, so that state lives in
+///    dynamic fields of the db that the db module's `migrate` adds once.
+pub(crate) async fn command(
+    context: &mut Context,
+    new_package: ObjectID,
+    env_file: &Path,
+) -> Result<()> {
+    let old_package = context.unwrap_atoma_package_id();
+
+    // 1.
+    let old = load_package(context, old_package).await?;
+    let new = load_package(context, new_package).await?;
+    println!(
+        "Migrating from {old_package} (v{}) to {new_package} (v{})",
+        old.version.value(),
+        new.version.value()
+    );
+    if new.version <= old.version {
+        anyhow::bail!("Package {new_package} is not newer than {old_package}");
+    }
+
+    let mut origin = None;
+    for type_name in [DB_TYPE_NAME, DB_MANAGER_TYPE_NAME, DB_NODE_TYPE_NAME] {
+        let old_origin = type_origin(&old, DB_MODULE_NAME, type_name)?;
+        let new_origin = type_origin(&new, DB_MODULE_NAME, type_name)?;
+        if old_origin != new_origin {
+            anyhow::bail!(
+                "{DB_MODULE_NAME}::{type_name} is defined by {new_origin} \
+                in the new package but by {old_origin} in the old one. \
+                Is {new_package} an upgrade of {old_package}?"
+            );
+        }
+        origin = Some(new_origin);
+    }
+    let origin = origin.unwrap();
+    debug!("Db types originate in package {origin}");
+
+    // 2.
+    context.reset_ids();
+    context.conf.atoma_package_id = Some(origin);
+    let atoma_db = context.get_or_load_atoma_db().await?;
+    let manager_badge = context.get_or_load_db_manager_badge().await.ok();
+    let node_badge = context.get_or_load_node_badge().await.ok();
+
+    context.conf.atoma_package_id = Some(new_package);
+    context.conf.toma_package_id = None;
+    let toma_package = context.get_or_load_toma_package_id().await?;
+    let faucet = context.get_or_load_faucet_id().await.ok();
+    let toma_wallet = context.get_or_load_toma_wallet().await.ok();
+
+    if manager_badge.is_none() && node_badge.is_none() {
+        info!("Active address owns neither manager nor node badge");
+    }
+
+    // 3.
+    let node_id = node_badge.map(|(_, node_id)| node_id.to_string());
+    let node_badge = node_badge.map(|(node_badge, _)| node_badge);
+    let updates = [
+        (ATOMA_PACKAGE_ID, Some(new_package.to_string())),
+        (ATOMA_DB_ID, Some(atoma_db.to_string())),
+        (TOMA_PACKAGE_ID, Some(toma_package.to_string())),
+        (FAUCET_ID, faucet.map(|id| id.to_string())),
+        (MANAGER_BADGE_ID, manager_badge.map(|id| id.to_string())),
+        (NODE_BADGE_ID, node_badge.map(|id| id.to_string())),
+        (NODE_ID, node_id),
+        (TOMA_WALLET_ID, toma_wallet.map(|id| id.to_string())),
+    ];
+    rewrite_env_file(env_file, &updates)?;
+
+    for (key, value) in updates {
+        println!("{key}={}", value.unwrap_or_default());
+    }
+    println!("Written to {env_file:?}");
+
+    // 4.
+    match manager_badge {
+        Some(manager_badge)
+            if context.conf.role.ensure_can_administer().is_ok() =>
+        {
+            let tx = context
+                .get_client()
+                .await?
+                .transaction_builder()
+                .move_call(
+                    context.wallet.active_address()?,
+                    new_package,
+                    DB_MODULE_NAME,
+                    ENDPOINT_NAME,
+                    vec![],
+                    vec![
+                        SuiJsonValue::from_object_id(atoma_db),
+                        SuiJsonValue::from_object_id(manager_badge),
+                    ],
+                    None,
+                    context.gas_budget(),
+                    None,
+                )
+                .await?;
+
+            let tx = context.sign_transaction(&tx)?;
+            let resp = context.execute_transaction_must_succeed(tx).await?;
+            println!("Migrated the db state in {}", resp.digest);
+        }
+        _ => info!(
+            "Only the manager can migrate the db state, \
+            new features are unavailable until they do"
+        ),
+    }
+
+    Ok(())
+}
+
+pub(crate) async fn load_package(
+    context: &Context,
+    package: ObjectID,
+) -> Result<SuiRawMovePackage> {
+    let data = context
+        .get_client()
+        .await?
+        .read_api()
+        .get_object_with_options(
+            package,
+            SuiObjectDataOptions {
+                show_bcs: true,
+                ..Default::default()
+            },
+        )
+        .await?
+        .data
+        .ok_or_else(|| anyhow!("Package {package} not found"))?;
+
+    match data.bcs {
+        Some(SuiRawData::Package(package)) => Ok(package),
+        _ => Err(anyhow!("Object {package} is not a package")),
+    }
+}
+
+/// Returns the package in which the given type was first defined.
+pub(crate) fn type_origin(
+    package: &SuiRawMovePackage,
+    module_name: &str,
+    type_name: &str,
+) -> Result<ObjectID> {
+    package
+        .type_origin_table
+        .iter()
+        .find(|origin| {
+            origin.module_name == module_name
+                && origin.datatype_name == type_name
+        })
+        .map(|origin| origin.package)
+        .ok_or_else(|| {
+            anyhow!(
+                "Package {} does not define {module_name}::{type_name}",
+                package.id
+            )
+        })
+}
+
+/// Replaces the values of the given keys in the env file and appends those
+/// that are missing.
+/// Keys with no value are emptied.
+pub(crate) fn rewrite_env_file(
+    env_file: &Path,
+    updates: &[(&str, Option<String>)],
+) -> Result<()> {
+    let original = if env_file.exists() {
+        let original = std::fs::read_to_string(env_file)?;
+        let backup = PathBuf::from(format!("{}.bak", env_file.display()));
+        std::fs::write(&backup, &original)?;
+        info!("Backed up {env_file:?} to {backup:?}");
+        original
+    } else {
+        String::new()
+    };
+
+    let value_of = |key: &str| {
+        updates
+            .iter()
+            .find(|(k, _)| *k == key)
+            .map(|(_, v)| v.clone().unwrap_or_default())
+    };
+
+    let mut written_keys = vec![];
+    let mut lines: Vec<_> = original
+        .lines()
+        .map(|line| {
+            let key = line.split('=').next().unwrap_or_default().trim();
+            match value_of(key) {
+                Some(value) => {
+                    written_keys.push(key.to_owned());
+                    format!("{key}={value}")
+                }
+                None => line.to_owned(),
+            }
+        })
+        .collect();
+    for (key, value) in updates {
+        if !written_keys.iter().any(|k| k == *key) {
+            lines.push(format!("{key}={}", value.clone().unwrap_or_default()));
+        }
+    }
+
+    std::fs::write(env_file, lines.join("\n") + "\n")?;
+
+    Ok(())
+}
@@ -0,0 +1,84 @@
+use std::fmt;
+
+use crate::prelude::*;
+
+/// Must match the modality constants in the db module.
+///
+/// Values below [`FIRST_CUSTOM_MODALITY`] that aren't listed are reserved
+/// for future protocol modalities and rejected by the contract.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Modality {
+    Text2Text,
+    Text2Image,
+    TextEmbedding,
+    Audio,
+    /// Defined by the deployment, at least [`FIRST_CUSTOM_MODALITY`].
+    Custom(u64),
+}
+
+const FIRST_CUSTOM_MODALITY: u64 = 1_000;
+
+impl TryFrom<u64> for Modality {
+    type Error = anyhow::Error;
+
+    fn try_from(value: u64) -> Result<Self> {
+        match value {
+            0 => Ok(Self::Text2Text),
+            1 => Ok(Self::Text2Image),
+            2 => Ok(Self::TextEmbedding),
+            3 => Ok(Self::Audio),
+            value if value >= FIRST_CUSTOM_MODALITY => Ok(Self::Custom(value)),
+            value => Err(anyhow!(
+                "Modality {value} is reserved for the protocol, \
+                custom modalities start at {FIRST_CUSTOM_MODALITY}"
+            )),
+        }
+    }
+}
+
+impl From<Modality> for u64 {
+    fn from(modality: Modality) -> Self {
+        match modality {
+            Modality::Text2Text => 0,
+            Modality::Text2Image => 1,
+            Modality::TextEmbedding => 2,
+            Modality::Audio => 3,
+            Modality::Custom(value) => value,
+        }
+    }
+}
+
+/// Either a name as printed by [`fmt::Display`] or a number.
+impl FromStr for Modality {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "text2text" => Ok(Self::Text2Text),
+            "text2image" => Ok(Self::Text2Image),
+            "text-embedding" => Ok(Self::TextEmbedding),
+            "audio" => Ok(Self::Audio),
+            s => s
+                .parse::<u64>()
+                .map_err(|_| {
+                    anyhow!(
+                        "Unknown modality {s}, expected text2text, \
+                        text2image, text-embedding, audio or a number"
+                    )
+                })?
+                .try_into(),
+        }
+    }
+}
+
+impl fmt::Display for Modality {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Text2Text => write!(f, "text2text"),
+            Self::Text2Image => write!(f, "text2image"),
+            Self::TextEmbedding => write!(f, "text-embedding"),
+            Self::Audio => write!(f, "audio"),
+            Self::Custom(value) => write!(f, "custom ({value})"),
+        }
+    }
+}
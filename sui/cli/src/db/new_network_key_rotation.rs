@@ -12,6 +12,8 @@ pub(crate) async fn command(
     let atoma_db = context.get_or_load_atoma_db().await?;
     let manager_badge = context.get_or_load_db_manager_badge().await?;
 
+    context.ensure_randomness_is_live().await?;
+
     let tx = context
         .get_client()
         .await?
@@ -33,7 +35,7 @@ pub(crate) async fn command(
         )
         .await?;
 
-    let tx = context.wallet.sign_transaction(&tx);
-    let resp = context.wallet.execute_transaction_must_succeed(tx).await;
+    let tx = context.sign_transaction(&tx)?;
+    let resp = context.execute_transaction_must_succeed(tx).await?;
     Ok(resp.digest)
 }
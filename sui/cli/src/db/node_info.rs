@@ -0,0 +1,201 @@
+use std::collections::BTreeMap;
+
+use move_core_types::language_storage::StructTag;
+use sui_sdk::{
+    rpc_types::SuiData,
+    types::{base_types::ObjectID, dynamic_field::DynamicFieldName, TypeTag},
+};
+
+use super::{dynamic_field_values, parse_u64};
+use crate::{prelude::*, DB_MODULE_NAME};
+
+/// Must match `REPUTATION_SCORE_START` in the db module.
+const REPUTATION_BASELINE: u64 = 50;
+/// Must match `REPUTATION_DECAY_EPOCHS_PER_POINT` in the db module.
+const REPUTATION_DECAY_EPOCHS_PER_POINT: u64 = 7;
+/// Must match `REPUTATION_RECOVERY_STREAK` in the db module.
+const REPUTATION_RECOVERY_STREAK: u64 = 10;
+/// How many upcoming decay steps to print.
+const TRAJECTORY_STEPS: u64 = 5;
+
+/// Must match `db::NodeReputation`.
+pub(crate) struct NodeReputation {
+    pub(crate) updated_at_epoch: u64,
+    pub(crate) dispute_free_settlements_streak: u64,
+}
+
+/// Prints the state of the node, by default the one owned by the active
+/// address.
+///
+/// The reputation score is stored as of the epoch it was last updated in,
+/// so we apply the decay the same way the contract does to get the current
+/// score.
+pub(crate) async fn command(
+    context: &mut Context,
+    node_small_id: Option<u64>,
+) -> Result<()> {
+    let node_small_id = match node_small_id {
+        Some(node_small_id) => node_small_id,
+        None => context.get_or_load_node_badge().await?.1,
+    };
+    let node = load_node(context, node_small_id).await?;
+    let epoch = context
+        .get_client()
+        .await?
+        .governance_api()
+        .get_latest_sui_system_state()
+        .await?
+        .epoch;
+
+    let field = |name: &str| {
+        node[name]
+            .as_str()
+            .and_then(|value| value.parse::<u64>().ok())
+            .ok_or_else(|| anyhow!("Node {node_small_id} has no {name}"))
+    };
+    let stored_score = node["reputation_score"]["inner"]
+        .as_u64()
+        .ok_or_else(|| anyhow!("Node {node_small_id} has no reputation"))?;
+    let NodeReputation {
+        updated_at_epoch,
+        dispute_free_settlements_streak: streak,
+    } = node_reputations(context)
+        .await?
+        .remove(&node_small_id)
+        .unwrap_or_else(|| NodeReputation::fresh(epoch));
+
+    println!("Node small ID: {node_small_id}");
+    if let Some(disabled_in_epoch) = node["was_disabled_in_epoch"].as_str() {
+        println!("Disabled in epoch: {disabled_in_epoch}");
+    }
+    println!("Collateral: {}", context.toma(field("collateral")?));
+    println!(
+        "Available fees: {}",
+        context.toma(field("available_fee_amount")?)
+    );
+    println!(
+        "Reputation: {} in epoch {epoch} \
+        ({stored_score} as of epoch {updated_at_epoch}, baseline \
+        {REPUTATION_BASELINE})",
+        decayed_reputation_score(stored_score, updated_at_epoch, epoch)
+    );
+    println!(
+        "Dispute-free settlements in a row: {streak}, \
+        {} more until the next recovery point",
+        REPUTATION_RECOVERY_STREAK - streak % REPUTATION_RECOVERY_STREAK
+    );
+
+    // the score changes at the end of each decay period until it reaches
+    // the baseline
+    let next_step = updated_at_epoch
+        + ((epoch - updated_at_epoch) / REPUTATION_DECAY_EPOCHS_PER_POINT + 1)
+            * REPUTATION_DECAY_EPOCHS_PER_POINT;
+    let mut trajectory = vec![];
+    for step in 0..TRAJECTORY_STEPS {
+        let at_epoch = next_step + step * REPUTATION_DECAY_EPOCHS_PER_POINT;
+        let score =
+            decayed_reputation_score(stored_score, updated_at_epoch, at_epoch);
+        trajectory.push(format!("{score} in epoch {at_epoch}"));
+        if score == REPUTATION_BASELINE {
+            break;
+        }
+    }
+    println!("Without settlements: {}", trajectory.join(", "));
+
+    Ok(())
+}
+
+/// The fields of the node entry as JSON.
+pub(crate) async fn load_node(
+    context: &mut Context,
+    node_small_id: u64,
+) -> Result<serde_json::Value> {
+    let atoma_package = context.unwrap_atoma_package_id();
+    let nodes_table = ObjectID::from_str(
+        context.load_atoma_db_fields().await?["nodes"]["id"]["id"]
+            .as_str()
+            .ok_or_else(|| anyhow!("No nodes field found"))?,
+    )?;
+
+    let node = context
+        .get_client()
+        .await?
+        .read_api()
+        .get_dynamic_field_object(
+            nodes_table,
+            DynamicFieldName {
+                type_: TypeTag::Struct(Box::new(StructTag {
+                    address: atoma_package.into(),
+                    module: FromStr::from_str(DB_MODULE_NAME)?,
+                    name: FromStr::from_str("NodeSmallId")?,
+                    type_params: vec![],
+                })),
+                value: serde_json::json!({ "inner": node_small_id.to_string() }),
+            },
+        )
+        .await?
+        .data
+        .and_then(|data| data.content)
+        .and_then(|content| content.try_into_move())
+        .ok_or_else(|| anyhow!("Node {node_small_id} not found"))?
+        .fields
+        .to_json_value()["value"]
+        .clone();
+
+    Ok(node)
+}
+
+/// The decay state of the nodes whose reputation changed since the db was
+/// migrated, see `NodeReputation::fresh` for the rest.
+pub(crate) async fn node_reputations(
+    context: &mut Context,
+) -> Result<BTreeMap<u64, NodeReputation>> {
+    let reputations_table = ObjectID::from_str(
+        context.load_atoma_db_field("NodeReputationsKey").await?["id"]["id"]
+            .as_str()
+            .ok_or_else(|| anyhow!("No node reputations found"))?,
+    )?;
+    let client = context.get_client().await?;
+
+    let mut reputations = BTreeMap::new();
+    for (key, reputation) in
+        dynamic_field_values(&client, reputations_table).await?
+    {
+        let reputation = &reputation["value"];
+        reputations.insert(
+            parse_u64(&key["inner"])?,
+            NodeReputation {
+                updated_at_epoch: parse_u64(&reputation["updated_at_epoch"])?,
+                dispute_free_settlements_streak: parse_u64(
+                    &reputation["dispute_free_settlements_streak"],
+                )?,
+            },
+        );
+    }
+
+    Ok(reputations)
+}
+
+impl NodeReputation {
+    /// Must match `db::node_reputation_or_fresh`.
+    pub(crate) fn fresh(epoch: u64) -> Self {
+        Self {
+            updated_at_epoch: epoch,
+            dispute_free_settlements_streak: 0,
+        }
+    }
+}
+
+/// Must match `db::decayed_reputation_score`.
+pub(crate) fn decayed_reputation_score(
+    score: u64,
+    updated_at_epoch: u64,
+    epoch: u64,
+) -> u64 {
+    let points = (epoch - updated_at_epoch) / REPUTATION_DECAY_EPOCHS_PER_POINT;
+    if score > REPUTATION_BASELINE {
+        score.saturating_sub(points).max(REPUTATION_BASELINE)
+    } else {
+        (score + points).min(REPUTATION_BASELINE)
+    }
+}
@@ -0,0 +1,120 @@
+use std::collections::BTreeMap;
+
+use serde_json::Value;
+use sui_sdk::types::base_types::ObjectID;
+
+use super::{
+    list_nodes::{dynamic_field_keys, dynamic_field_values, parse_u64},
+    SoftwareVersion,
+};
+use crate::prelude::*;
+
+/// 1. Load the versions that nodes reported, and the nodes themselves since
+///    not all of them reported one.
+/// 2. If a task is given, keep only the nodes subscribed to it and load its
+///    minimum version.
+/// 3. Print how many nodes run each version, newest first, so that the
+///    manager can tell whether a minimum version would leave the task
+///    without nodes.
+pub(crate) async fn command(
+    context: &mut Context,
+    task_small_id: Option<u64>,
+) -> Result<()> {
+    let db_fields = context.load_atoma_db_fields().await?;
+    let node_versions = context
+        .load_atoma_db_field("NodeSoftwareVersionsKey")
+        .await?;
+    let task_min_versions = context
+        .load_atoma_db_field("TaskMinNodeVersionsKey")
+        .await?;
+    let table_id = |table: &Value, name: &str| {
+        table["id"]["id"]
+            .as_str()
+            .ok_or_else(|| anyhow!("No {name} table found"))
+            .and_then(|id| Ok(ObjectID::from_str(id)?))
+    };
+    let client = context.get_client().await?;
+
+    // 1.
+    let mut versions = BTreeMap::new();
+    for (key, version) in
+        dynamic_field_values(&client, table_id(&node_versions, "versions")?)
+            .await?
+    {
+        versions.insert(
+            parse_u64(&key["inner"])?,
+            SoftwareVersion::from_json(&version["value"])?,
+        );
+    }
+    let mut nodes =
+        dynamic_field_keys(&client, table_id(&db_fields["nodes"], "nodes")?)
+            .await?
+            .iter()
+            .map(|key| parse_u64(&key["inner"]))
+            .collect::<Result<Vec<_>>>()?;
+
+    // 2.
+    let mut min_version = None;
+    if let Some(task_small_id) = task_small_id {
+        let task = super::load_task(context, task_small_id).await?;
+        let subscribed_nodes =
+            ObjectID::from_str(
+                task["subscribed_nodes"]["id"]["id"].as_str().ok_or_else(
+                    || anyhow!("Task {task_small_id} has no subscribed nodes"),
+                )?,
+            )?;
+        nodes = dynamic_field_keys(&client, subscribed_nodes)
+            .await?
+            .iter()
+            .map(|key| parse_u64(&key["inner"]))
+            .collect::<Result<Vec<_>>>()?;
+
+        let min_versions = table_id(&task_min_versions, "min versions")?;
+        min_version = dynamic_field_values(&client, min_versions)
+            .await?
+            .into_iter()
+            .find(|(key, _)| {
+                parse_u64(&key["inner"]).ok() == Some(task_small_id)
+            })
+            .map(|(_, version)| SoftwareVersion::from_json(&version["value"]))
+            .transpose()?;
+    }
+
+    // 3.
+    let mut distribution: BTreeMap<Option<SoftwareVersion>, Vec<u64>> =
+        BTreeMap::new();
+    for node_small_id in &nodes {
+        distribution
+            .entry(versions.get(node_small_id).copied())
+            .or_default()
+            .push(*node_small_id);
+    }
+    if let Some(min_version) = min_version {
+        let eligible = nodes
+            .iter()
+            .filter(|node| {
+                versions.get(node).is_some_and(|v| *v >= min_version)
+            })
+            .count();
+        println!(
+            "Minimum version {min_version}, met by {eligible} of {} node(s)",
+            nodes.len()
+        );
+    }
+    for (version, node_small_ids) in distribution.iter().rev() {
+        let version = version
+            .map(|version| version.to_string())
+            .unwrap_or_else(|| "not reported".to_string());
+        println!(
+            "{version}: {} node(s), {}",
+            node_small_ids.len(),
+            node_small_ids
+                .iter()
+                .map(u64::to_string)
+                .collect::<Vec<_>>()
+                .join(", ")
+        );
+    }
+
+    Ok(())
+}
@@ -0,0 +1,125 @@
+use serde_json::{json, Value};
+use sui_sdk::types::base_types::ObjectID;
+
+use super::list_nodes::dynamic_field_values;
+use crate::prelude::*;
+
+/// Prints all global protocol parameters as a single JSON document so that
+/// gateways can configure themselves from it.
+pub(crate) async fn command(context: &mut Context) -> Result<()> {
+    let params = load(context).await?;
+    println!("{}", serde_json::to_string_pretty(&params)?);
+
+    Ok(())
+}
+
+/// Unlike in the RPC responses, u64 values are JSON numbers.
+/// Models are sorted by name and their echelons carry the fees and the
+/// settlement timeout.
+pub(crate) async fn load(context: &mut Context) -> Result<Value> {
+    let atoma_package = context.unwrap_atoma_package_id();
+    let atoma_db = context.get_or_load_atoma_db().await?;
+    let db_fields = context.load_atoma_db_fields().await?;
+    let u64_at = |value: &Value, name: &str| {
+        value
+            .as_str()
+            .and_then(|s| s.parse::<u64>().ok())
+            .ok_or_else(|| anyhow!("Invalid {name}: {value}"))
+    };
+    let db_u64 = |name: &str| u64_at(&db_fields[name], name);
+    let stale_node_sweeps =
+        context.load_atoma_db_field("StaleNodeSweepsKey").await?;
+    let sweeps_u64 = |name: &str| u64_at(&stale_node_sweeps[name], name);
+    let stack_gc = context.load_atoma_db_field("StackGcKey").await?;
+    let gc_u64 = |name: &str| u64_at(&stack_gc[name], name);
+    let stack_dispute_window_epochs = context
+        .load_atoma_db_field("StackDisputeWindowEpochsKey")
+        .await?;
+
+    let models_root = ObjectID::from_str(
+        db_fields["models"]["id"]["id"]
+            .as_str()
+            .ok_or_else(|| anyhow!("No models field found"))?,
+    )?;
+    let client = context.get_client().await?;
+    let mut models = vec![];
+    for (_, model) in dynamic_field_values(&client, models_root).await? {
+        let echelons = model["echelons"]
+            .as_array()
+            .ok_or_else(|| anyhow!("Model has no echelons: {model}"))?
+            .iter()
+            .map(|echelon| {
+                Ok(json!({
+                    "id": u64_at(&echelon["id"]["id"], "echelon id")?,
+                    "input_fee_per_token": u64_at(
+                        &echelon["input_fee_per_token"],
+                        "input_fee_per_token",
+                    )?,
+                    "output_fee_per_token": u64_at(
+                        &echelon["output_fee_per_token"],
+                        "output_fee_per_token",
+                    )?,
+                    "relative_performance": u64_at(
+                        &echelon["relative_performance"],
+                        "relative_performance",
+                    )?,
+                    "settlement_timeout_ms": u64_at(
+                        &echelon["settlement_timeout_ms"],
+                        "settlement_timeout_ms",
+                    )?,
+                }))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        models.push(json!({
+            "name": model["name"],
+            "modality": u64_at(&model["modality"], "modality")?,
+            "is_disabled": model["is_disabled"],
+            "echelons": echelons,
+        }));
+    }
+    models.sort_by(|a, b| a["name"].as_str().cmp(&b["name"].as_str()));
+
+    Ok(json!({
+        "atoma_package": atoma_package.to_string(),
+        "atoma_db": atoma_db.to_string(),
+        "registration": {
+            "is_disabled": db_fields["is_registration_disabled"],
+            "collateral": db_u64("registration_collateral_in_protocol_token")?,
+        },
+        "slashing": {
+            "permille_on_timeout": db_u64("permille_to_slash_node_on_timeout")?,
+            "permille_for_oracle_on_dispute":
+                db_u64("permille_for_oracle_on_dispute")?,
+            "permille_for_honest_nodes_on_dispute":
+                db_u64("permille_for_honest_nodes_on_dispute")?,
+        },
+        "sampling_consensus": {
+            "cross_validation_probability_permille":
+                db_u64("cross_validation_probability_permille")?,
+            "cross_validation_extra_nodes_count":
+                db_u64("cross_validation_extra_nodes_count")?,
+            "sampling_consensus_charge_permille":
+                db_u64("sampling_consensus_charge_permille")?,
+            "cross_validation_extra_nodes_charge_permille":
+                db_u64("cross_validation_extra_nodes_charge_permille")?,
+        },
+        "settlement": {
+            "stack_dispute_window_epochs": u64_at(
+                &stack_dispute_window_epochs,
+                "stack_dispute_window_epochs",
+            )?,
+        },
+        "stale_nodes": {
+            "threshold_epochs": sweeps_u64("threshold_epochs")?,
+            "sweep_reward": sweeps_u64("reward")?,
+        },
+        "stack_gc": {
+            "min_age_epochs": gc_u64("min_age_epochs")?,
+            "bounty": gc_u64("bounty")?,
+        },
+        "confidential_compute": {
+            "key_rotation_counter": db_u64("key_rotation_counter")?,
+        },
+        "models": models,
+    }))
+}
@@ -0,0 +1,150 @@
+use std::fmt;
+
+use serde_json::Value;
+use sui_sdk::types::base_types::{ObjectID, SuiAddress};
+
+use super::list_nodes::{dynamic_field_values, parse_u64};
+use crate::prelude::*;
+
+/// Must match the sum that `set_node_payout_split` in the db module checks.
+const TOTAL_BPS: u64 = 10_000;
+/// Must match `MaxPayoutSplitRecipients` in the db module.
+const MAX_RECIPIENTS: usize = 16;
+
+/// Must match `PayoutShare` in the db module.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct PayoutShare {
+    pub(crate) recipient: SuiAddress,
+    pub(crate) bps: u64,
+}
+
+impl PayoutShare {
+    /// From the JSON of the Move struct.
+    fn from_json(value: &Value) -> Result<Self> {
+        Ok(Self {
+            recipient: SuiAddress::from_str(
+                value["recipient"]
+                    .as_str()
+                    .ok_or_else(|| anyhow!("Invalid payout share {value}"))?,
+            )?,
+            bps: parse_u64(&value["bps"])?,
+        })
+    }
+}
+
+/// Eg. `0x1234:6000` for 60% of the fees.
+impl FromStr for PayoutShare {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let (recipient, bps) = s
+            .split_once(':')
+            .ok_or_else(|| anyhow!("{s} is not an ADDRESS:BPS share"))?;
+        Ok(Self {
+            recipient: SuiAddress::from_str(recipient).map_err(|err| {
+                anyhow!("Invalid recipient {recipient}: {err}")
+            })?,
+            bps: bps
+                .parse()
+                .map_err(|err| anyhow!("Invalid share {bps}: {err}"))?,
+        })
+    }
+}
+
+impl fmt::Display for PayoutShare {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} {}.{:02}%",
+            self.recipient,
+            self.bps / 100,
+            self.bps % 100
+        )
+    }
+}
+
+/// Checks the split the same way the contract does, so that a split that
+/// would abort is caught before it's signed.
+/// An empty split is valid, it removes the split.
+pub(crate) fn validate(shares: &[PayoutShare]) -> Result<()> {
+    if shares.len() > MAX_RECIPIENTS {
+        anyhow::bail!(
+            "{} recipients, at most {MAX_RECIPIENTS} are allowed",
+            shares.len()
+        );
+    }
+    if let Some(share) = shares.iter().find(|share| share.bps == 0) {
+        anyhow::bail!("{} has no share", share.recipient);
+    }
+    let total_bps: u64 = shares.iter().map(|share| share.bps).sum();
+    if !shares.is_empty() && total_bps != TOTAL_BPS {
+        anyhow::bail!(
+            "The shares add up to {total_bps} bps rather than {TOTAL_BPS}"
+        );
+    }
+
+    Ok(())
+}
+
+/// Prints how the fees of the node, by default the one owned by the active
+/// address, are split.
+pub(crate) async fn command(
+    context: &mut Context,
+    node_small_id: Option<u64>,
+) -> Result<()> {
+    let node_small_id = match node_small_id {
+        Some(node_small_id) => node_small_id,
+        None => context.get_or_load_node_badge().await?.1,
+    };
+    let shares = load(context, node_small_id).await?;
+
+    if shares.is_empty() {
+        println!("Node {node_small_id} is paid alone");
+    } else {
+        println!("Node {node_small_id} is paid to:");
+        for share in &shares {
+            println!("  {share}");
+        }
+    }
+
+    Ok(())
+}
+
+/// Prints whether the split would be accepted by the contract.
+pub(crate) fn validate_command(shares: &[PayoutShare]) -> Result<()> {
+    validate(shares)?;
+    for share in shares {
+        println!("  {share}");
+    }
+    println!("Valid split among {} recipient(s)", shares.len());
+
+    Ok(())
+}
+
+/// Empty if the node has no split.
+pub(crate) async fn load(
+    context: &mut Context,
+    node_small_id: u64,
+) -> Result<Vec<PayoutShare>> {
+    let node_payout_splits = ObjectID::from_str(
+        context.load_atoma_db_field("NodePayoutSplitsKey").await?["id"]["id"]
+            .as_str()
+            .ok_or_else(|| anyhow!("No node payout splits found"))?,
+    )?;
+
+    let client = context.get_client().await?;
+    let Some((_, split)) = dynamic_field_values(&client, node_payout_splits)
+        .await?
+        .into_iter()
+        .find(|(key, _)| parse_u64(&key["inner"]).ok() == Some(node_small_id))
+    else {
+        return Ok(vec![]);
+    };
+
+    split["value"]
+        .as_array()
+        .ok_or_else(|| anyhow!("Invalid payout split of node {node_small_id}"))?
+        .iter()
+        .map(PayoutShare::from_json)
+        .collect()
+}
@@ -0,0 +1,43 @@
+use crate::{prelude::*, DB_MODULE_NAME};
+
+const ENDPOINT_NAME: &str = "record_model_echelon_latency";
+
+/// Only an oracle of the echelon can record its latency.
+pub(crate) async fn command(
+    context: &mut Context,
+    model_name: &str,
+    echelon: u64,
+    latency_p50_ms: u64,
+) -> Result<TransactionDigest> {
+    let active_address = context.wallet.active_address()?;
+    let atoma_package = context.unwrap_atoma_package_id();
+    let atoma_db = context.get_or_load_atoma_db().await?;
+    let (node_badge, _) = context.get_or_load_node_badge().await?;
+
+    let tx = context
+        .get_client()
+        .await?
+        .transaction_builder()
+        .move_call(
+            active_address,
+            atoma_package,
+            DB_MODULE_NAME,
+            ENDPOINT_NAME,
+            vec![],
+            vec![
+                SuiJsonValue::from_object_id(atoma_db),
+                SuiJsonValue::from_object_id(node_badge),
+                SuiJsonValue::new(model_name.into())?,
+                SuiJsonValue::new(echelon.to_string().into())?,
+                SuiJsonValue::new(latency_p50_ms.to_string().into())?,
+            ],
+            None,
+            context.gas_budget(),
+            None,
+        )
+        .await?;
+
+    let tx = context.sign_transaction(&tx)?;
+    let resp = context.execute_transaction_must_succeed(tx).await?;
+    Ok(resp.digest)
+}
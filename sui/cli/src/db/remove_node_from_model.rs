@@ -177,8 +177,8 @@ pub(crate) async fn command(
             )
             .await?;
 
-        let tx = context.wallet.sign_transaction(&tx);
-        let resp = context.wallet.execute_transaction_may_fail(tx).await?;
+        let tx = context.sign_transaction(&tx)?;
+        let resp = context.execute_transaction_may_fail(tx).await?;
         if let SuiExecutionStatus::Failure { error } =
             resp.effects.as_ref().unwrap().status()
         {
@@ -0,0 +1,56 @@
+use crate::{prelude::*, DB_MODULE_NAME};
+
+const ENDPOINT_NAME: &str = "set_model_echelon_settlement_timeout_ms";
+/// Must match `MinSettlementTimeoutMs` in the db module.
+const MIN_SETTLEMENT_TIMEOUT_MS: u64 = 1_000;
+/// Must match `MaxSettlementTimeoutMs` in the db module.
+const MAX_SETTLEMENT_TIMEOUT_MS: u64 = 3_600_000;
+
+/// The current timeouts are listed by `list-echelons`.
+pub(crate) async fn command(
+    context: &mut Context,
+    model_name: &str,
+    echelon: u64,
+    new_timeout_ms: u64,
+) -> Result<TransactionDigest> {
+    if !(MIN_SETTLEMENT_TIMEOUT_MS..=MAX_SETTLEMENT_TIMEOUT_MS)
+        .contains(&new_timeout_ms)
+    {
+        anyhow::bail!(
+            "Settlement timeout must be between {MIN_SETTLEMENT_TIMEOUT_MS} \
+            and {MAX_SETTLEMENT_TIMEOUT_MS} ms, got {new_timeout_ms} ms"
+        );
+    }
+
+    let active_address = context.wallet.active_address()?;
+    let atoma_package = context.unwrap_atoma_package_id();
+    let atoma_db = context.get_or_load_atoma_db().await?;
+    let manager_badge = context.get_or_load_db_manager_badge().await?;
+
+    let tx = context
+        .get_client()
+        .await?
+        .transaction_builder()
+        .move_call(
+            active_address,
+            atoma_package,
+            DB_MODULE_NAME,
+            ENDPOINT_NAME,
+            vec![],
+            vec![
+                SuiJsonValue::from_object_id(atoma_db),
+                SuiJsonValue::from_object_id(manager_badge),
+                SuiJsonValue::new(model_name.into())?,
+                SuiJsonValue::new(echelon.to_string().into())?,
+                SuiJsonValue::new(new_timeout_ms.to_string().into())?,
+            ],
+            None,
+            context.gas_budget(),
+            None,
+        )
+        .await?;
+
+    let tx = context.sign_transaction(&tx)?;
+    let resp = context.execute_transaction_must_succeed(tx).await?;
+    Ok(resp.digest)
+}
@@ -0,0 +1,49 @@
+use super::{payout_split::validate, PayoutShare};
+use crate::{prelude::*, DB_MODULE_NAME};
+
+const ENDPOINT_NAME: &str = "set_node_payout_split";
+
+/// Replaces the split of the node's fees, no shares remove it.
+pub(crate) async fn command(
+    context: &mut Context,
+    shares: Vec<PayoutShare>,
+) -> Result<TransactionDigest> {
+    validate(&shares)?;
+
+    let active_address = context.wallet.active_address()?;
+    let atoma_package = context.unwrap_atoma_package_id();
+    let atoma_db = context.get_or_load_atoma_db().await?;
+    let (node_badge, _) = context.get_or_load_node_badge().await?;
+
+    let recipients: Vec<_> = shares
+        .iter()
+        .map(|share| share.recipient.to_string())
+        .collect();
+    let shares_bps: Vec<_> =
+        shares.iter().map(|share| share.bps.to_string()).collect();
+    let tx = context
+        .get_client()
+        .await?
+        .transaction_builder()
+        .move_call(
+            active_address,
+            atoma_package,
+            DB_MODULE_NAME,
+            ENDPOINT_NAME,
+            vec![],
+            vec![
+                SuiJsonValue::from_object_id(atoma_db),
+                SuiJsonValue::from_object_id(node_badge),
+                SuiJsonValue::new(recipients.into())?,
+                SuiJsonValue::new(shares_bps.into())?,
+            ],
+            None,
+            context.gas_budget(),
+            None,
+        )
+        .await?;
+
+    let tx = context.sign_transaction(&tx)?;
+    let resp = context.execute_transaction_must_succeed(tx).await?;
+    Ok(resp.digest)
+}
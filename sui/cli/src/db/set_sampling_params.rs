@@ -0,0 +1,86 @@
+use super::SamplingParams;
+use crate::{prelude::*, DB_MODULE_NAME};
+
+const ENDPOINT_NAME: &str = "set_sampling_consensus_params";
+
+/// Parameters that are not given keep their current on-chain value.
+pub(crate) async fn command(
+    context: &mut Context,
+    cross_validation_probability_permille: Option<u64>,
+    cross_validation_extra_nodes_count: Option<u64>,
+    sampling_consensus_charge_permille: Option<u64>,
+    cross_validation_extra_nodes_charge_permille: Option<u64>,
+) -> Result<TransactionDigest> {
+    let current = SamplingParams::load(context).await?;
+    let params = SamplingParams {
+        cross_validation_probability_permille:
+            cross_validation_probability_permille
+                .unwrap_or(current.cross_validation_probability_permille),
+        cross_validation_extra_nodes_count: cross_validation_extra_nodes_count
+            .unwrap_or(current.cross_validation_extra_nodes_count),
+        sampling_consensus_charge_permille: sampling_consensus_charge_permille
+            .unwrap_or(current.sampling_consensus_charge_permille),
+        cross_validation_extra_nodes_charge_permille:
+            cross_validation_extra_nodes_charge_permille
+                .unwrap_or(current.cross_validation_extra_nodes_charge_permille),
+    };
+    if params.cross_validation_probability_permille > 1000 {
+        anyhow::bail!("Cross validation probability cannot exceed 1000‰");
+    }
+    if params.cross_validation_extra_nodes_count == 0 {
+        anyhow::bail!("At least one cross validation extra node is required");
+    }
+
+    let active_address = context.wallet.active_address()?;
+    let atoma_package = context.unwrap_atoma_package_id();
+    let atoma_db = context.get_or_load_atoma_db().await?;
+    let manager_badge = context.get_or_load_db_manager_badge().await?;
+
+    let tx = context
+        .get_client()
+        .await?
+        .transaction_builder()
+        .move_call(
+            active_address,
+            atoma_package,
+            DB_MODULE_NAME,
+            ENDPOINT_NAME,
+            vec![],
+            vec![
+                SuiJsonValue::from_object_id(atoma_db),
+                SuiJsonValue::from_object_id(manager_badge),
+                SuiJsonValue::new(
+                    params
+                        .cross_validation_probability_permille
+                        .to_string()
+                        .into(),
+                )?,
+                SuiJsonValue::new(
+                    params
+                        .cross_validation_extra_nodes_count
+                        .to_string()
+                        .into(),
+                )?,
+                SuiJsonValue::new(
+                    params
+                        .sampling_consensus_charge_permille
+                        .to_string()
+                        .into(),
+                )?,
+                SuiJsonValue::new(
+                    params
+                        .cross_validation_extra_nodes_charge_permille
+                        .to_string()
+                        .into(),
+                )?,
+            ],
+            None,
+            context.gas_budget(),
+            None,
+        )
+        .await?;
+
+    let tx = context.sign_transaction(&tx)?;
+    let resp = context.execute_transaction_must_succeed(tx).await?;
+    Ok(resp.digest)
+}
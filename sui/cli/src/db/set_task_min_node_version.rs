@@ -0,0 +1,44 @@
+use super::SoftwareVersion;
+use crate::{prelude::*, DB_MODULE_NAME};
+
+const ENDPOINT_NAME: &str = "set_task_min_node_version";
+
+/// A version of 0.0.0 removes the minimum.
+pub(crate) async fn command(
+    context: &mut Context,
+    task_small_id: u64,
+    min_version: SoftwareVersion,
+) -> Result<TransactionDigest> {
+    let active_address = context.wallet.active_address()?;
+    let atoma_package = context.unwrap_atoma_package_id();
+    let atoma_db = context.get_or_load_atoma_db().await?;
+    let manager_badge = context.get_or_load_db_manager_badge().await?;
+
+    let tx = context
+        .get_client()
+        .await?
+        .transaction_builder()
+        .move_call(
+            active_address,
+            atoma_package,
+            DB_MODULE_NAME,
+            ENDPOINT_NAME,
+            vec![],
+            vec![
+                SuiJsonValue::from_object_id(atoma_db),
+                SuiJsonValue::from_object_id(manager_badge),
+                SuiJsonValue::new(task_small_id.to_string().into())?,
+                SuiJsonValue::new(min_version.major.into())?,
+                SuiJsonValue::new(min_version.minor.into())?,
+                SuiJsonValue::new(min_version.patch.into())?,
+            ],
+            None,
+            context.gas_budget(),
+            None,
+        )
+        .await?;
+
+    let tx = context.sign_transaction(&tx)?;
+    let resp = context.execute_transaction_must_succeed(tx).await?;
+    Ok(resp.digest)
+}
@@ -0,0 +1,69 @@
+use std::collections::BTreeMap;
+
+use sui_sdk::types::base_types::ObjectID;
+
+use super::{dynamic_field_values, parse_u64};
+use crate::{prelude::*, DB_MODULE_NAME};
+
+const ENDPOINT_NAME: &str = "set_task_min_price";
+
+/// A price of 0 removes the minimum.
+pub(crate) async fn command(
+    context: &mut Context,
+    task_small_id: u64,
+    min_price_per_one_million_compute_units: u64,
+) -> Result<TransactionDigest> {
+    let active_address = context.wallet.active_address()?;
+    let atoma_package = context.unwrap_atoma_package_id();
+    let atoma_db = context.get_or_load_atoma_db().await?;
+    let manager_badge = context.get_or_load_db_manager_badge().await?;
+
+    let tx = context
+        .get_client()
+        .await?
+        .transaction_builder()
+        .move_call(
+            active_address,
+            atoma_package,
+            DB_MODULE_NAME,
+            ENDPOINT_NAME,
+            vec![],
+            vec![
+                SuiJsonValue::from_object_id(atoma_db),
+                SuiJsonValue::from_object_id(manager_badge),
+                SuiJsonValue::new(task_small_id.to_string().into())?,
+                SuiJsonValue::new(
+                    min_price_per_one_million_compute_units.to_string().into(),
+                )?,
+            ],
+            None,
+            context.gas_budget(),
+            None,
+        )
+        .await?;
+
+    let tx = context.sign_transaction(&tx)?;
+    let resp = context.execute_transaction_must_succeed(tx).await?;
+    Ok(resp.digest)
+}
+
+/// The minimum price per one million compute units of the tasks that have
+/// one, by task small ID.
+pub(crate) async fn task_min_prices(
+    context: &mut Context,
+) -> Result<BTreeMap<u64, u64>> {
+    let min_prices = ObjectID::from_str(
+        context.load_atoma_db_field("TaskMinPricesKey").await?["id"]["id"]
+            .as_str()
+            .ok_or_else(|| anyhow!("No task min prices table found"))?,
+    )?;
+    let client = context.get_client().await?;
+
+    dynamic_field_values(&client, min_prices)
+        .await?
+        .into_iter()
+        .map(|(key, min_price)| {
+            Ok((parse_u64(&key["inner"])?, parse_u64(&min_price["value"])?))
+        })
+        .collect()
+}
@@ -0,0 +1,60 @@
+use std::path::Path;
+
+use clap::ValueEnum;
+
+use crate::{prelude::*, DB_MODULE_NAME};
+
+const ENDPOINT_NAME: &str = "set_task_zk_verifier";
+
+/// Must match the on-chain curve IDs.
+#[derive(Clone, Copy, Debug, ValueEnum)]
+pub(crate) enum ZkCurve {
+    Bn254 = 0,
+    Bls12381 = 1,
+}
+
+/// The verifying key file holds the arkworks compressed bytes of the Groth16
+/// verifying key as written by the prover.
+pub(crate) async fn command(
+    context: &mut Context,
+    task_small_id: u64,
+    curve: ZkCurve,
+    verifying_key: &Path,
+) -> Result<TransactionDigest> {
+    let verifying_key = std::fs::read(verifying_key)?;
+    if verifying_key.is_empty() {
+        anyhow::bail!("The verifying key file is empty");
+    }
+
+    let active_address = context.wallet.active_address()?;
+    let atoma_package = context.unwrap_atoma_package_id();
+    let atoma_db = context.get_or_load_atoma_db().await?;
+    let manager_badge = context.get_or_load_db_manager_badge().await?;
+
+    let tx = context
+        .get_client()
+        .await?
+        .transaction_builder()
+        .move_call(
+            active_address,
+            atoma_package,
+            DB_MODULE_NAME,
+            ENDPOINT_NAME,
+            vec![],
+            vec![
+                SuiJsonValue::from_object_id(atoma_db),
+                SuiJsonValue::from_object_id(manager_badge),
+                SuiJsonValue::new(task_small_id.to_string().into())?,
+                SuiJsonValue::new((curve as u8).into())?,
+                SuiJsonValue::new(verifying_key.into())?,
+            ],
+            None,
+            context.gas_budget(),
+            None,
+        )
+        .await?;
+
+    let tx = context.sign_transaction(&tx)?;
+    let resp = context.execute_transaction_must_succeed(tx).await?;
+    Ok(resp.digest)
+}
@@ -0,0 +1,331 @@
+//! Checks the arguments of `try-settle-stack` and
+//! `submit-stack-settlement-attestation` against the on-chain layout of the
+//! stack before anything is signed, so that nodes learn why their bytes
+//! would be rejected rather than getting an abort code.
+//!
+//! The settlement ticket of a stack keeps the merkle leaves of the nodes
+//! that processed it in one vector of 32 byte leaves:
+//! - position 0 is the leaf of the selected node, written when it tries to
+//!   settle the stack
+//! - position `i + 1` is the leaf of the `i`-th requested attestation node,
+//!   written when it submits its attestation
+//!
+//! Once all attestation nodes submitted, the blake2b256 hash of the vector
+//! must equal the committed stack proof of the selected node.
+
+use regex::Regex;
+use serde_json::Value;
+use sui_sdk::{
+    rpc_types::{SuiExecutionStatus, SuiTransactionBlockEffectsAPI},
+    types::transaction::TransactionDataAPI,
+};
+
+use super::list_nodes::parse_u64;
+use crate::{
+    prelude::*,
+    stack::{load_settlement_ticket, load_stack},
+    DB_MODULE_NAME,
+};
+
+/// Must match `BYTES_PER_HASH_COMMITMENT` in the db module.
+const BYTES_PER_HASH_COMMITMENT: usize = 32;
+/// Must match `EBase` in the db module.
+const DB_EBASE: u64 = 312012_000;
+
+/// What the node is about to submit.
+pub(crate) enum Submission {
+    /// The selected node settles the stack with `try_settle_stack`.
+    TrySettle { num_claimed_compute_units: u64 },
+    /// A requested attestation node submits its leaf with
+    /// `submit_stack_settlement_attestation`.
+    Attestation,
+}
+
+/// 1. Load the stack and its settlement ticket, and derive the position of
+///    the node's leaf.
+/// 2. Check the bytes against the layout and explain every mismatch.
+/// 3. Dev-inspect the call to catch anything the checks missed.
+pub(crate) async fn command(
+    context: &mut Context,
+    stack_small_id: u64,
+    submission: &Submission,
+    committed_stack_proof: &[u8],
+    stack_merkle_leaf: &[u8],
+) -> Result<()> {
+    let (_, node_small_id) = context.get_or_load_node_badge().await?;
+
+    // 1.
+    let stack = load_stack(context, stack_small_id).await?;
+    let stack = &stack["value"];
+    let ticket = load_settlement_ticket(context, stack_small_id).await?;
+    let selected_node_id = parse_u64(&stack["selected_node_id"]["inner"])?;
+
+    let mut problems = vec![];
+    let position = match submission {
+        Submission::TrySettle {
+            num_claimed_compute_units,
+        } => {
+            if selected_node_id != node_small_id {
+                problems.push(format!(
+                    "Node {node_small_id} was not selected for stack \
+                    {stack_small_id}, node {selected_node_id} was"
+                ));
+            }
+            if ticket.is_some() {
+                problems.push(format!(
+                    "Stack {stack_small_id} already has a settlement ticket"
+                ));
+            }
+            let num_compute_units = parse_u64(&stack["num_compute_units"])?;
+            if *num_claimed_compute_units > num_compute_units {
+                problems.push(format!(
+                    "Claimed {num_claimed_compute_units} compute units but \
+                    stack {stack_small_id} has {num_compute_units}"
+                ));
+            }
+            println!(
+                "Node {node_small_id} writes leaf 0 of stack \
+                {stack_small_id}, ie. bytes 0..{BYTES_PER_HASH_COMMITMENT}"
+            );
+            println!(
+                "Attestation nodes, if any, are sampled when the ticket is \
+                created and write the leaves after it"
+            );
+            Some(0)
+        }
+        Submission::Attestation => match &ticket {
+            None => {
+                problems.push(format!(
+                    "Stack {stack_small_id} has no settlement ticket yet, \
+                    node {selected_node_id} must try to settle it first"
+                ));
+                None
+            }
+            Some(ticket) => {
+                attestation_position(
+                    context,
+                    ticket,
+                    stack_small_id,
+                    node_small_id,
+                    committed_stack_proof,
+                    &mut problems,
+                )
+                .await?
+            }
+        },
+    };
+
+    // 2.
+    problems.extend(length_problem(
+        "committed stack proof",
+        committed_stack_proof,
+    ));
+    problems.extend(length_problem("stack merkle leaf", stack_merkle_leaf));
+    if !problems.is_empty() {
+        anyhow::bail!(
+            "The settlement of stack {stack_small_id} would fail:\n- {}",
+            problems.join("\n- ")
+        );
+    }
+
+    // 3.
+    inspect(
+        context,
+        stack_small_id,
+        submission,
+        committed_stack_proof,
+        stack_merkle_leaf,
+    )
+    .await?;
+    if let Some(position) = position {
+        println!("Leaf {position} of stack {stack_small_id} checks out");
+    }
+
+    Ok(())
+}
+
+/// The position of the node's leaf, `None` if the node can't attest.
+async fn attestation_position(
+    context: &mut Context,
+    ticket: &Value,
+    stack_small_id: u64,
+    node_small_id: u64,
+    committed_stack_proof: &[u8],
+    problems: &mut Vec<String>,
+) -> Result<Option<usize>> {
+    let node_ids = |name: &str| {
+        ticket[name]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .map(|node| parse_u64(&node["inner"]))
+            .collect::<Result<Vec<_>>>()
+    };
+    let attestation_nodes = node_ids("requested_attestation_nodes")?;
+    let already_attested_nodes = node_ids("already_attested_nodes")?;
+
+    let Some(index) = attestation_nodes
+        .iter()
+        .position(|node| *node == node_small_id)
+    else {
+        problems.push(if attestation_nodes.is_empty() {
+            format!("Stack {stack_small_id} requested no attestations")
+        } else {
+            format!(
+                "Node {node_small_id} was not requested to attest stack \
+                {stack_small_id}, nodes {attestation_nodes:?} were"
+            )
+        });
+        return Ok(None);
+    };
+    let position = index + 1;
+    println!(
+        "Node {node_small_id} writes leaf {position} of {} of stack \
+        {stack_small_id}, ie. bytes {}..{}",
+        attestation_nodes.len() + 1,
+        position * BYTES_PER_HASH_COMMITMENT,
+        (position + 1) * BYTES_PER_HASH_COMMITMENT,
+    );
+
+    if already_attested_nodes.contains(&node_small_id) {
+        problems.push(format!(
+            "Node {node_small_id} already attested stack {stack_small_id}"
+        ));
+    }
+    let epoch = context
+        .get_client()
+        .await?
+        .governance_api()
+        .get_latest_sui_system_state()
+        .await?
+        .epoch;
+    let dispute_settled_at_epoch =
+        parse_u64(&ticket["dispute_settled_at_epoch"])?;
+    if dispute_settled_at_epoch < epoch {
+        problems.push(format!(
+            "The dispute period of stack {stack_small_id} ended in epoch \
+            {dispute_settled_at_epoch}"
+        ));
+    }
+    let ticket_proof = ticket["committed_stack_proof"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .map(|byte| byte.as_u64().map(|byte| byte as u8))
+        .collect::<Option<Vec<_>>>()
+        .ok_or_else(|| anyhow!("Invalid committed stack proof in ticket"))?;
+    if ticket_proof != committed_stack_proof {
+        problems.push(
+            "The committed stack proof differs from the one of the selected \
+            node, which would start an attestation dispute, use \
+            start-attestation-dispute if that's intended"
+                .to_string(),
+        );
+    }
+
+    Ok(Some(position))
+}
+
+fn length_problem(name: &str, bytes: &[u8]) -> Option<String> {
+    if bytes.len() == BYTES_PER_HASH_COMMITMENT {
+        return None;
+    }
+
+    let hint = if bytes.len() == 2 * BYTES_PER_HASH_COMMITMENT
+        && bytes.iter().all(u8::is_ascii_hexdigit)
+    {
+        ", it looks like the hex text of the hash rather than its bytes"
+    } else {
+        ""
+    };
+    Some(format!(
+        "The {name} has {} bytes, it must have {BYTES_PER_HASH_COMMITMENT}{hint}",
+        bytes.len()
+    ))
+}
+
+async fn inspect(
+    context: &mut Context,
+    stack_small_id: u64,
+    submission: &Submission,
+    committed_stack_proof: &[u8],
+    stack_merkle_leaf: &[u8],
+) -> Result<()> {
+    let active_address = context.wallet.active_address()?;
+    let atoma_package = context.unwrap_atoma_package_id();
+    let atoma_db = context.get_or_load_atoma_db().await?;
+    let (node_badge, _) = context.get_or_load_node_badge().await?;
+
+    let mut args = vec![
+        SuiJsonValue::from_object_id(atoma_db),
+        SuiJsonValue::from_object_id(node_badge),
+        SuiJsonValue::new(stack_small_id.to_string().into())?,
+    ];
+    let endpoint_name = match submission {
+        Submission::TrySettle {
+            num_claimed_compute_units,
+        } => {
+            args.push(SuiJsonValue::new(
+                num_claimed_compute_units.to_string().into(),
+            )?);
+            "try_settle_stack"
+        }
+        Submission::Attestation => "submit_stack_settlement_attestation",
+    };
+    args.push(SuiJsonValue::new(committed_stack_proof.to_vec().into())?);
+    args.push(SuiJsonValue::new(stack_merkle_leaf.to_vec().into())?);
+
+    let client = context.get_client().await?;
+    let tx = client
+        .transaction_builder()
+        .move_call(
+            active_address,
+            atoma_package,
+            DB_MODULE_NAME,
+            endpoint_name,
+            vec![],
+            args,
+            None,
+            context.gas_budget(),
+            None,
+        )
+        .await?;
+    let inspection = client
+        .read_api()
+        .dev_inspect_transaction_block(
+            active_address,
+            tx.kind().clone(),
+            None,
+            None,
+            None,
+        )
+        .await?;
+    if let SuiExecutionStatus::Failure { error } = inspection.effects.status() {
+        return Err(abort_error(error, stack_small_id));
+    }
+
+    Ok(())
+}
+
+/// Turns an abort of the endpoint into a reason.
+fn abort_error(error: &str, stack_small_id: u64) -> anyhow::Error {
+    let code = Regex::new(r"MoveAbort\(.*, (\d+)\)")
+        .unwrap()
+        .captures(error)
+        .and_then(|captures| captures[1].parse::<u64>().ok());
+
+    // offsets of the db module error codes
+    let reason = match code.and_then(|code| code.checked_sub(DB_EBASE)) {
+        Some(26) => format!(
+            "The task of stack {stack_small_id} doesn't use sampling \
+            consensus, so nobody attests it"
+        ),
+        Some(62) => format!(
+            "The task of stack {stack_small_id} requires a zk proof, use \
+            try-settle-stack-with-zk-proof"
+        ),
+        _ => return anyhow!("Settlement would fail: {error}"),
+    };
+
+    anyhow!("Settlement would fail: {reason}")
+}
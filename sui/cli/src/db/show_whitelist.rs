@@ -0,0 +1,54 @@
+use std::collections::BTreeSet;
+
+use sui_sdk::types::base_types::ObjectID;
+
+use super::{
+    list_nodes::{dynamic_field_keys, parse_u64},
+    load_task,
+};
+use crate::prelude::*;
+
+/// Prints the nodes whitelisted for a private task and whether each of them
+/// is currently subscribed to it.
+pub(crate) async fn command(
+    context: &mut Context,
+    task_small_id: u64,
+) -> Result<()> {
+    let task = load_task(context, task_small_id).await?;
+    if task["is_public"].as_bool().unwrap_or_default() {
+        println!("Task {task_small_id} is public, any node can subscribe");
+        return Ok(());
+    }
+
+    let subscribed_nodes = ObjectID::from_str(
+        task["subscribed_nodes"]["id"]["id"]
+            .as_str()
+            .ok_or_else(|| {
+                anyhow!("Task {task_small_id} has no subscribed nodes")
+            })?,
+    )?;
+    let client = context.get_client().await?;
+    let subscribed = dynamic_field_keys(&client, subscribed_nodes)
+        .await?
+        .iter()
+        .map(|key| parse_u64(&key["inner"]))
+        .collect::<Result<BTreeSet<_>>>()?;
+
+    let whitelisted = task["whitelisted_nodes"]
+        .as_array()
+        .ok_or_else(|| anyhow!("Task {task_small_id} has no whitelist"))?;
+    if whitelisted.is_empty() {
+        println!("No nodes are whitelisted for task {task_small_id}");
+    }
+    for node in whitelisted {
+        let node_small_id = parse_u64(&node["inner"])?;
+        let status = if subscribed.contains(&node_small_id) {
+            "subscribed"
+        } else {
+            "not subscribed"
+        };
+        println!("Node {node_small_id}: {status}");
+    }
+
+    Ok(())
+}
@@ -0,0 +1,203 @@
+use std::{path::Path, time::Duration};
+
+use serde_json::{json, Map, Value};
+use sui_sdk::{
+    rpc_types::{Page, SuiData, SuiObjectDataOptions},
+    types::base_types::ObjectID,
+};
+
+use crate::prelude::*;
+
+/// Collections stored in tables of the AtomaDb object.
+/// The tuple is `(collection name, path to the table's UID, is object table)`.
+///
+/// Values of object tables are the objects themselves, values of plain tables
+/// are wrapped in a dynamic field object.
+const DB_COLLECTIONS: &[(&str, &[&str], bool)] = &[
+    ("models", &["models", "id", "id"], true),
+    ("tasks", &["tasks", "id", "id"], true),
+    ("nodes", &["nodes", "id", "id"], false),
+    ("stacks", &["stacks", "id", "id"], false),
+    (
+        "stack_settlement_tickets",
+        &["stack_settlement_tickets", "id", "id"],
+        true,
+    ),
+    ("tickets", &["tickets", "id"], true),
+];
+
+/// Walks all the tables of the AtomaDb and writes them into a JSON file.
+///
+/// The file is rewritten after each page so that a failed or interrupted
+/// export can be continued with `resume`.
+/// Echelon node lists are discovered from the models once those are exported.
+///
+/// The file has the following format:
+/// ```json
+/// {
+///   "atoma_db": "0x...",
+///   "collections": {
+///     "<name>": {
+///       "root": "0x...",
+///       "is_object_table": true,
+///       "cursor": null,
+///       "done": false,
+///       "entries": { "<key as json>": { ... } }
+///     }
+///   }
+/// }
+/// ```
+pub(crate) async fn command(
+    context: &mut Context,
+    out: &Path,
+    resume: bool,
+    page_delay: Duration,
+) -> Result<()> {
+    let atoma_db = context.get_or_load_atoma_db().await?;
+
+    let mut snapshot = if resume && out.exists() {
+        let snapshot: Value = serde_json::from_slice(&std::fs::read(out)?)?;
+        if snapshot["atoma_db"].as_str() != Some(&atoma_db.to_string()) {
+            anyhow::bail!("Snapshot in {out:?} is of a different AtomaDb");
+        }
+        info!("Resuming snapshot from {out:?}");
+        snapshot
+    } else {
+        let db_fields = context.load_atoma_db_fields().await?;
+        let mut collections = Map::new();
+        for (name, path, is_object_table) in DB_COLLECTIONS {
+            let root = path
+                .iter()
+                .fold(&db_fields, |value, key| &value[key])
+                .as_str()
+                .ok_or_else(|| anyhow!("No {name} field found"))?;
+            collections.insert(
+                name.to_string(),
+                new_collection(root, *is_object_table),
+            );
+        }
+        json!({
+            "atoma_db": atoma_db.to_string(),
+            "collections": collections,
+        })
+    };
+
+    let client = context.get_client().await?;
+    loop {
+        let collections = snapshot["collections"].as_object_mut().unwrap();
+        let Some((name, collection)) = collections
+            .iter_mut()
+            .find(|(_, collection)| collection["done"] == false)
+        else {
+            break;
+        };
+        let name = name.clone();
+        let root = ObjectID::from_str(collection["root"].as_str().unwrap())?;
+        let is_object_table = collection["is_object_table"] == true;
+        let cursor = collection["cursor"]
+            .as_str()
+            .map(ObjectID::from_str)
+            .transpose()?;
+
+        let Page {
+            data,
+            has_next_page,
+            next_cursor,
+        } = client
+            .read_api()
+            .get_dynamic_fields(root, cursor, None)
+            .await?;
+
+        let keys: Vec<_> = data
+            .iter()
+            .map(|info| info.name.value.to_string())
+            .collect();
+        let objects = client
+            .read_api()
+            .multi_get_object_with_options(
+                data.iter().map(|info| info.object_id).collect(),
+                SuiObjectDataOptions {
+                    show_content: true,
+                    ..Default::default()
+                },
+            )
+            .await?
+            .into_iter()
+            .map(|object| {
+                Some(
+                    object
+                        .data?
+                        .content?
+                        .try_as_move()
+                        .cloned()?
+                        .fields
+                        .to_json_value(),
+                )
+            });
+
+        let entries = collection["entries"].as_object_mut().unwrap();
+        // ignore entries that have been deleted between the calls
+        for (key, object) in keys.into_iter().zip(objects) {
+            let Some(object) = object else { continue };
+            let value = if is_object_table {
+                object
+            } else {
+                object["value"].clone()
+            };
+            entries.insert(key, value);
+        }
+        debug!("Exported {} entries of {name}", entries.len());
+
+        collection["cursor"] = json!(next_cursor.map(|c| c.to_string()));
+        if !has_next_page {
+            collection["done"] = json!(true);
+            info!("Exported {name}");
+
+            if name == "models" {
+                let echelon_collections = echelon_node_collections(collection);
+                collections.extend(echelon_collections);
+            }
+        }
+        let done = collections
+            .values()
+            .filter(|collection| collection["done"] == true)
+            .count();
+        context.progress("snapshot", done, collections.len());
+
+        std::fs::write(out, serde_json::to_vec_pretty(&snapshot)?)?;
+        tokio::time::sleep(page_delay).await;
+    }
+
+    println!("Snapshot written to {out:?}");
+
+    Ok(())
+}
+
+fn new_collection(root: &str, is_object_table: bool) -> Value {
+    json!({
+        "root": root,
+        "is_object_table": is_object_table,
+        "cursor": null,
+        "done": false,
+        "entries": {},
+    })
+}
+
+/// Each model echelon keeps its nodes in a table vec.
+fn echelon_node_collections(models: &Value) -> Map<String, Value> {
+    let mut collections = Map::new();
+    for model in models["entries"].as_object().unwrap().values() {
+        let model_name = model["name"].as_str().unwrap();
+        for echelon in model["echelons"].as_array().unwrap() {
+            let echelon_id = echelon["id"]["id"].as_str().unwrap();
+            let root =
+                echelon["nodes"]["contents"]["id"]["id"].as_str().unwrap();
+            collections.insert(
+                format!("echelon_nodes/{model_name}/{echelon_id}"),
+                new_collection(root, false),
+            );
+        }
+    }
+
+    collections
+}
@@ -0,0 +1,68 @@
+use std::{collections::BTreeSet, path::Path};
+
+use serde_json::Value;
+
+use crate::prelude::*;
+
+/// Compares two snapshots created by the `snapshot` command and prints which
+/// entries were added, removed or changed in each collection.
+///
+/// Returns the total number of differences.
+pub(crate) fn command(old: &Path, new: &Path) -> Result<usize> {
+    let old: Value = serde_json::from_slice(&std::fs::read(old)?)?;
+    let new: Value = serde_json::from_slice(&std::fs::read(new)?)?;
+
+    if old["atoma_db"] != new["atoma_db"] {
+        println!(
+            "Snapshots are of different AtomaDb objects: {} vs {}",
+            old["atoma_db"], new["atoma_db"]
+        );
+    }
+
+    let collections = |snapshot: &Value| -> BTreeSet<String> {
+        snapshot["collections"]
+            .as_object()
+            .map(|c| c.keys().cloned().collect())
+            .unwrap_or_default()
+    };
+
+    let mut differences = 0;
+    for name in collections(&old).union(&collections(&new)) {
+        let old_collection = &old["collections"][name];
+        let new_collection = &new["collections"][name];
+        for (label, snapshot) in
+            [("old", &old_collection), ("new", &new_collection)]
+        {
+            if snapshot.is_object() && snapshot["done"] != true {
+                println!("[INCOMPLETE] {name} in the {label} snapshot");
+            }
+        }
+
+        let empty = serde_json::Map::new();
+        let old_entries =
+            old_collection["entries"].as_object().unwrap_or(&empty);
+        let new_entries =
+            new_collection["entries"].as_object().unwrap_or(&empty);
+
+        for (key, old_value) in old_entries {
+            match new_entries.get(key) {
+                None => println!("[REMOVED] {name} {key}"),
+                Some(new_value) if new_value != old_value => {
+                    println!("[CHANGED] {name} {key}");
+                    println!("  old: {old_value}");
+                    println!("  new: {new_value}");
+                }
+                Some(_) => continue,
+            }
+            differences += 1;
+        }
+        for key in new_entries.keys() {
+            if !old_entries.contains_key(key) {
+                println!("[ADDED] {name} {key}");
+                differences += 1;
+            }
+        }
+    }
+
+    Ok(differences)
+}
@@ -0,0 +1,60 @@
+use std::fmt;
+
+use serde_json::Value;
+
+use crate::prelude::*;
+
+/// Must match `SoftwareVersion` in the db module.
+///
+/// Ordered by major, then minor, then patch.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) struct SoftwareVersion {
+    pub(crate) major: u16,
+    pub(crate) minor: u16,
+    pub(crate) patch: u16,
+}
+
+impl SoftwareVersion {
+    /// From the JSON of the Move struct.
+    pub(crate) fn from_json(value: &Value) -> Result<Self> {
+        let part = |name: &str| {
+            value[name]
+                .as_u64()
+                .and_then(|part| u16::try_from(part).ok())
+                .ok_or_else(|| anyhow!("Invalid software version {value}"))
+        };
+        Ok(Self {
+            major: part("major")?,
+            minor: part("minor")?,
+            patch: part("patch")?,
+        })
+    }
+}
+
+/// Eg. `1.4.2`, a leading `v` is accepted.
+impl FromStr for SoftwareVersion {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let parts = s
+            .trim_start_matches('v')
+            .split('.')
+            .map(|part| part.parse::<u16>())
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|_| anyhow!("{s} is not a version"))?;
+        let [major, minor, patch] = parts[..] else {
+            anyhow::bail!("{s} is not a major.minor.patch version");
+        };
+        Ok(Self {
+            major,
+            minor,
+            patch,
+        })
+    }
+}
+
+impl fmt::Display for SoftwareVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)
+    }
+}
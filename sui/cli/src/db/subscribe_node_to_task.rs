@@ -1,7 +1,29 @@
+use move_core_types::language_storage::StructTag;
+use regex::Regex;
+use serde_json::Value;
+use sui_sdk::{
+    rpc_types::{SuiExecutionStatus, SuiTransactionBlockEffectsAPI},
+    types::{
+        base_types::ObjectID, dynamic_field::DynamicFieldName,
+        transaction::TransactionDataAPI, TypeTag,
+    },
+};
+
+use super::{
+    decayed_reputation_score, load_node, load_task, node_reputations,
+    task_min_prices, NodeReputation,
+};
 use crate::{prelude::*, DB_MODULE_NAME};
 
-const ENDPOINT_NAME: &str = "subscribe_node_to_task";
+const ENDPOINT_NAME: &str = "subscribe_node_to_task_entry";
+/// Must match `EBase` in the db module.
+const DB_EBASE: u64 = 312012_000;
 
+/// 1. Check locally the conditions under which the endpoint aborts, so that
+///    the node operator gets a reason rather than an abort code.
+/// 2. Dev-inspect the call to catch anything the checks missed, eg. a task
+///    deprecated in the meantime.
+/// 3. Subscribe.
 pub(crate) async fn command(
     context: &mut Context,
     task_small_id: u64,
@@ -10,7 +32,66 @@ pub(crate) async fn command(
     let active_address = context.wallet.active_address()?;
     let atoma_package = context.unwrap_atoma_package_id();
     let atoma_db = context.get_or_load_atoma_db().await?;
-    let (node_badge, _) = context.get_or_load_node_badge().await?;
+    let (node_badge, node_small_id) = context.get_or_load_node_badge().await?;
+
+    // 1.
+    if price_per_one_million_compute_units == 0 {
+        anyhow::bail!("Price per one million compute units must not be zero");
+    }
+    let task = load_task(context, task_small_id).await?;
+    let min_price = task_min_prices(context)
+        .await?
+        .get(&task_small_id)
+        .copied()
+        .unwrap_or_default();
+    if price_per_one_million_compute_units < min_price {
+        anyhow::bail!(
+            "Task {task_small_id} requires a price of at least {} per one \
+            million compute units, got {}",
+            context.toma(min_price),
+            context.toma(price_per_one_million_compute_units)
+        );
+    }
+    if task["is_deprecated"].as_bool() == Some(true) {
+        anyhow::bail!(
+            "Task {task_small_id} was deprecated in epoch {}, \
+            it no longer accepts subscriptions",
+            task["deprecated_at_epoch"].as_str().unwrap_or("?")
+        );
+    }
+    if task["is_public"].as_bool() == Some(false) {
+        let whitelisted = task["whitelisted_nodes"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .any(|node| {
+                node["inner"].as_str() == Some(&node_small_id.to_string())
+            });
+        if !whitelisted {
+            anyhow::bail!(
+                "Task {task_small_id} is private and node {node_small_id} \
+                is not whitelisted for it, ask the task owner to whitelist it"
+            );
+        }
+    }
+    let minimum_score = task["minimum_reputation_score"]["inner"]
+        .as_u64()
+        .ok_or_else(|| {
+            anyhow!("Task {task_small_id} has no minimum reputation score")
+        })?;
+    let score = current_reputation_score(context, node_small_id).await?;
+    if score < minimum_score {
+        anyhow::bail!(
+            "Node {node_small_id} has a reputation score of {score}, \
+            task {task_small_id} requires at least {minimum_score}"
+        );
+    }
+    if is_subscribed(context, &task, node_small_id).await? {
+        anyhow::bail!(
+            "Node {node_small_id} is already subscribed to task \
+            {task_small_id}, use update-node-subscription to change the price"
+        );
+    }
 
     let tx = context
         .get_client()
@@ -36,7 +117,128 @@ pub(crate) async fn command(
         )
         .await?;
 
-    let tx = context.wallet.sign_transaction(&tx);
-    let resp = context.wallet.execute_transaction_must_succeed(tx).await;
+    // 2.
+    let inspection = context
+        .get_client()
+        .await?
+        .read_api()
+        .dev_inspect_transaction_block(
+            active_address,
+            tx.kind().clone(),
+            None,
+            None,
+            None,
+        )
+        .await?;
+    if let SuiExecutionStatus::Failure { error } = inspection.effects.status() {
+        return Err(abort_error(error, task_small_id, node_small_id));
+    }
+
+    // 3.
+    let tx = context.sign_transaction(&tx)?;
+    let resp = context.execute_transaction_must_succeed(tx).await?;
     Ok(resp.digest)
 }
+
+/// Must match `node_meets_task_requirements` in the db module.
+async fn current_reputation_score(
+    context: &mut Context,
+    node_small_id: u64,
+) -> Result<u64> {
+    let node = load_node(context, node_small_id).await?;
+    let stored_score = node["reputation_score"]["inner"]
+        .as_u64()
+        .ok_or_else(|| anyhow!("Node {node_small_id} has no reputation"))?;
+    let epoch = context
+        .get_client()
+        .await?
+        .governance_api()
+        .get_latest_sui_system_state()
+        .await?
+        .epoch;
+    let NodeReputation {
+        updated_at_epoch, ..
+    } = node_reputations(context)
+        .await?
+        .remove(&node_small_id)
+        .unwrap_or_else(|| NodeReputation::fresh(epoch));
+
+    Ok(decayed_reputation_score(
+        stored_score,
+        updated_at_epoch,
+        epoch,
+    ))
+}
+
+/// Whether the node has an entry in the task's `subscribed_nodes` table.
+async fn is_subscribed(
+    context: &mut Context,
+    task: &Value,
+    node_small_id: u64,
+) -> Result<bool> {
+    let subscribed_nodes = ObjectID::from_str(
+        task["subscribed_nodes"]["id"]["id"]
+            .as_str()
+            .ok_or_else(|| anyhow!("Task has no subscribed nodes table"))?,
+    )?;
+    let atoma_package = context.unwrap_atoma_package_id();
+
+    let entry = context
+        .get_client()
+        .await?
+        .read_api()
+        .get_dynamic_field_object(
+            subscribed_nodes,
+            DynamicFieldName {
+                type_: TypeTag::Struct(Box::new(StructTag {
+                    address: atoma_package.into(),
+                    module: FromStr::from_str(DB_MODULE_NAME)?,
+                    name: FromStr::from_str("NodeSmallId")?,
+                    type_params: vec![],
+                })),
+                value: serde_json::json!({ "inner": node_small_id.to_string() }),
+            },
+        )
+        .await?;
+
+    Ok(entry.data.is_some())
+}
+
+/// Turns an abort of the endpoint into the reason the checks above would
+/// have given.
+fn abort_error(
+    error: &str,
+    task_small_id: u64,
+    node_small_id: u64,
+) -> anyhow::Error {
+    let code = Regex::new(r"MoveAbort\(.*, (\d+)\)")
+        .unwrap()
+        .captures(error)
+        .and_then(|captures| captures[1].parse::<u64>().ok());
+
+    // offsets of the db module error codes
+    let reason = match code.and_then(|code| code.checked_sub(DB_EBASE)) {
+        Some(15) => format!("Task {task_small_id} is deprecated"),
+        Some(16) => format!(
+            "Node {node_small_id} is already subscribed to task \
+            {task_small_id}"
+        ),
+        Some(17) => format!("Task {task_small_id} does not exist"),
+        Some(37) => {
+            "Price per one million compute units must not be zero".to_owned()
+        }
+        Some(38) => format!(
+            "Node {node_small_id} does not meet the minimum reputation score \
+            of task {task_small_id}"
+        ),
+        Some(45) => format!(
+            "Node {node_small_id} is not whitelisted for task {task_small_id}"
+        ),
+        Some(91) => format!(
+            "The price is below the minimum price of task {task_small_id}"
+        ),
+        _ => return anyhow!("Subscription would fail: {error}"),
+    };
+
+    anyhow!("Subscription would fail: {reason}")
+}
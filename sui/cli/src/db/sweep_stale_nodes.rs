@@ -0,0 +1,166 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use sui_sdk::types::base_types::ObjectID;
+
+use super::list_nodes::{dynamic_field_keys, dynamic_field_values, parse_u64};
+use crate::{prelude::*, DB_MODULE_NAME};
+
+const ENDPOINT_NAME: &str = "sweep_stale_node";
+
+/// 1. Find when each node sent its last heartbeat, nodes without one count
+///    as if they sent it when the db was migrated.
+/// 2. Walk the subscriptions of every task to find the stale ones, that is
+///    of nodes without a heartbeat for more than `threshold_epochs` epochs or
+///    of nodes that no longer exist, skipping those already swept since the
+///    node's last heartbeat.
+/// 3. Sweep them one by one unless it's a dry run.
+///
+/// The sender is paid a reward from the communal treasury for the first
+/// sweep of each node since its last heartbeat, unless the sender registered
+/// the node or sent that heartbeat.
+pub(crate) async fn command(
+    context: &mut Context,
+    dry_run: bool,
+) -> Result<()> {
+    let db_fields = context.load_atoma_db_fields().await?;
+    let table_id = |name: &str| {
+        db_fields[name]["id"]["id"]
+            .as_str()
+            .ok_or_else(|| anyhow!("No {name} field found"))
+            .and_then(|id| Ok(ObjectID::from_str(id)?))
+    };
+    let nodes_table = table_id("nodes")?;
+    let tasks_table = table_id("tasks")?;
+    let sweeps = context.load_atoma_db_field("StaleNodeSweepsKey").await?;
+    let threshold_epochs = parse_u64(&sweeps["threshold_epochs"])?;
+    let reward = parse_u64(&sweeps["reward"])?;
+    let migrated_at_epoch = parse_u64(&sweeps["migrated_at_epoch"])?;
+    let sweeps_table_id = |name: &str| {
+        sweeps[name]["id"]["id"]
+            .as_str()
+            .ok_or_else(|| anyhow!("No {name} sweeps field found"))
+            .and_then(|id| Ok(ObjectID::from_str(id)?))
+    };
+    let heartbeats_table = sweeps_table_id("last_heartbeat_epochs")?;
+    let swept_table = sweeps_table_id("swept")?;
+
+    let client = context.get_client().await?;
+    let epoch = client
+        .governance_api()
+        .get_latest_sui_system_state()
+        .await?
+        .epoch;
+
+    // 1.
+    let mut last_heartbeats = BTreeMap::new();
+    for key in dynamic_field_keys(&client, nodes_table).await? {
+        last_heartbeats.insert(parse_u64(&key["inner"])?, migrated_at_epoch);
+    }
+    for (key, last) in dynamic_field_values(&client, heartbeats_table).await? {
+        let node_small_id = parse_u64(&key["inner"])?;
+        if let Some(last_heartbeat) = last_heartbeats.get_mut(&node_small_id) {
+            *last_heartbeat = parse_u64(&last["value"])?;
+        }
+    }
+    let mut swept = BTreeMap::new();
+    for (key, swept_at) in dynamic_field_values(&client, swept_table).await? {
+        swept.insert(
+            (
+                parse_u64(&key["task_small_id"]["inner"])?,
+                parse_u64(&key["node_small_id"]["inner"])?,
+            ),
+            parse_u64(&swept_at["value"])?,
+        );
+    }
+
+    // 2.
+    let mut stale = vec![];
+    for (key, task) in dynamic_field_values(&client, tasks_table).await? {
+        let task_small_id = parse_u64(&key["inner"])?;
+        let subscribed_nodes =
+            ObjectID::from_str(
+                task["subscribed_nodes"]["id"]["id"].as_str().ok_or_else(
+                    || anyhow!("Task {task_small_id} has no subscribed nodes"),
+                )?,
+            )?;
+        for key in dynamic_field_keys(&client, subscribed_nodes).await? {
+            let node_small_id = parse_u64(&key["inner"])?;
+            let last = last_heartbeats.get(&node_small_id).copied();
+            if matches!(last, Some(last) if epoch <= last + threshold_epochs) {
+                continue;
+            }
+            // must match the `ENodeAlreadySwept` check of the endpoint, nodes
+            // that no longer exist count as without a heartbeat
+            if swept.get(&(task_small_id, node_small_id))
+                == Some(&last.unwrap_or(migrated_at_epoch))
+            {
+                continue;
+            }
+            stale.push((task_small_id, node_small_id, last));
+        }
+    }
+
+    if stale.is_empty() {
+        println!("No stale nodes, threshold is {threshold_epochs} epochs");
+        return Ok(());
+    }
+
+    // 3.
+    let active_address = context.wallet.active_address()?;
+    let atoma_package = context.unwrap_atoma_package_id();
+    let atoma_db = context.get_or_load_atoma_db().await?;
+    for (i, (task_small_id, node_small_id, last)) in stale.iter().enumerate() {
+        match last {
+            Some(last) => println!(
+                "Node {node_small_id} in task {task_small_id}: \
+                last heartbeat in epoch {last}, now {epoch}"
+            ),
+            None => println!(
+                "Node {node_small_id} in task {task_small_id}: \
+                no longer exists"
+            ),
+        }
+        if dry_run {
+            continue;
+        }
+
+        let tx = client
+            .transaction_builder()
+            .move_call(
+                active_address,
+                atoma_package,
+                DB_MODULE_NAME,
+                ENDPOINT_NAME,
+                vec![],
+                vec![
+                    SuiJsonValue::from_object_id(atoma_db),
+                    SuiJsonValue::new(task_small_id.to_string().into())?,
+                    SuiJsonValue::new(node_small_id.to_string().into())?,
+                ],
+                None,
+                context.gas_budget(),
+                None,
+            )
+            .await?;
+
+        let tx = context.sign_transaction(&tx)?;
+        let resp = context.execute_transaction_must_succeed(tx).await?;
+        println!("Swept in {}", resp.digest);
+        context.progress("sweep", i + 1, stale.len());
+    }
+
+    if dry_run {
+        let nodes: BTreeSet<_> = stale
+            .iter()
+            .map(|(_, node_small_id, _)| node_small_id)
+            .collect();
+        println!(
+            "{} stale subscription(s) of {} node(s), the reward is up to \
+            {reward} per node",
+            stale.len(),
+            nodes.len()
+        );
+    }
+
+    Ok(())
+}
@@ -0,0 +1,212 @@
+//! Named sets of task parameters for common workloads.
+//!
+//! The bundled templates are in `task_templates.json`.
+//! Users can add their own or override the bundled ones by name in a JSON
+//! file of the same format:
+//! ```json
+//! {
+//!   "my-template": {
+//!     "description": "...",
+//!     "role": 0,
+//!     "model_name": "llama",
+//!     "security_level": 1,
+//!     "minimum_reputation_score": 60,
+//!     "is_public": true,
+//!     "max_input_tokens": 4096,
+//!     "max_output_tokens": 4096,
+//!     "allowed_modalities": [0]
+//!   }
+//! }
+//! ```
+//! All fields but `role` are optional.
+
+use std::{collections::BTreeMap, path::Path};
+
+use serde_json::Value;
+
+use super::TaskConstraints;
+use crate::prelude::*;
+
+const BUNDLED_TEMPLATES: &str = include_str!("task_templates.json");
+
+/// Parameters of `create_task_entry`.
+#[derive(Default)]
+pub(crate) struct TaskParams {
+    pub(crate) role: Option<u16>,
+    pub(crate) model_name: Option<String>,
+    pub(crate) security_level: Option<u16>,
+    pub(crate) minimum_reputation_score: Option<u8>,
+    pub(crate) is_public: bool,
+    pub(crate) constraints: TaskConstraints,
+}
+
+/// Expands the template into the full parameter set and prints it.
+///
+/// The parameters given explicitly take precedence over the template.
+/// Since `is_public` is a flag, the task is public if either says so.
+pub(crate) fn expand(
+    template: &str,
+    templates_file: &Path,
+    explicit: TaskParams,
+) -> Result<TaskParams> {
+    let templates = load(templates_file)?;
+    let template_params = templates.get(template).ok_or_else(|| {
+        anyhow!(
+            "No task template {template}, available: {}",
+            templates.keys().cloned().collect::<Vec<_>>().join(", ")
+        )
+    })?;
+    let TaskParams {
+        role,
+        model_name,
+        security_level,
+        minimum_reputation_score,
+        is_public,
+        constraints,
+    } = parse(template, template_params)?;
+
+    let params = TaskParams {
+        role: explicit.role.or(role),
+        model_name: explicit.model_name.or(model_name),
+        security_level: explicit.security_level.or(security_level),
+        minimum_reputation_score: explicit
+            .minimum_reputation_score
+            .or(minimum_reputation_score),
+        is_public: explicit.is_public || is_public,
+        constraints: TaskConstraints {
+            max_input_tokens: explicit
+                .constraints
+                .max_input_tokens
+                .or(constraints.max_input_tokens),
+            max_output_tokens: explicit
+                .constraints
+                .max_output_tokens
+                .or(constraints.max_output_tokens),
+            allowed_modalities: if explicit
+                .constraints
+                .allowed_modalities
+                .is_empty()
+            {
+                constraints.allowed_modalities
+            } else {
+                explicit.constraints.allowed_modalities
+            },
+        },
+    };
+
+    println!("Template {template} expands to:");
+    print(&params);
+
+    Ok(params)
+}
+
+/// Prints all templates, bundled and from the file, with their parameters.
+pub(crate) fn command(templates_file: &Path) -> Result<()> {
+    for (name, template) in load(templates_file)? {
+        println!("----------------------------");
+        println!(
+            "{name}: {}",
+            template["description"].as_str().unwrap_or_default()
+        );
+        print(&parse(&name, &template)?);
+    }
+
+    Ok(())
+}
+
+/// The bundled templates and, if the file exists, the user's templates.
+fn load(templates_file: &Path) -> Result<BTreeMap<String, Value>> {
+    let mut templates = as_templates(serde_json::from_str(BUNDLED_TEMPLATES)?)
+        .ok_or_else(|| anyhow!("Invalid bundled task templates"))?;
+    if templates_file.exists() {
+        let user_templates = as_templates(serde_json::from_slice(
+            &std::fs::read(templates_file)?,
+        )?)
+        .ok_or_else(|| {
+            anyhow!("{templates_file:?} is not a task templates file")
+        })?;
+        templates.extend(user_templates);
+    }
+
+    Ok(templates)
+}
+
+fn as_templates(json: Value) -> Option<BTreeMap<String, Value>> {
+    match json {
+        Value::Object(templates) => Some(templates.into_iter().collect()),
+        _ => None,
+    }
+}
+
+fn parse(name: &str, template: &Value) -> Result<TaskParams> {
+    let u64_field = |field: &str| -> Result<Option<u64>> {
+        match &template[field] {
+            Value::Null => Ok(None),
+            value => value.as_u64().map(Some).ok_or_else(|| {
+                anyhow!("Template {name} has an invalid {field}: {value}")
+            }),
+        }
+    };
+
+    let role = u64_field("role")?
+        .ok_or_else(|| anyhow!("Template {name} has no role"))?;
+    let allowed_modalities = match &template["allowed_modalities"] {
+        Value::Null => vec![],
+        Value::Array(modalities) => modalities
+            .iter()
+            .map(|modality| {
+                modality.as_u64().ok_or_else(|| {
+                    anyhow!("Template {name} has an invalid modality")
+                })
+            })
+            .collect::<Result<_>>()?,
+        value => anyhow::bail!(
+            "Template {name} has invalid allowed_modalities: {value}"
+        ),
+    };
+
+    Ok(TaskParams {
+        role: Some(u16::try_from(role)?),
+        model_name: template["model_name"].as_str().map(ToOwned::to_owned),
+        security_level: u64_field("security_level")?
+            .map(u16::try_from)
+            .transpose()?,
+        minimum_reputation_score: u64_field("minimum_reputation_score")?
+            .map(u8::try_from)
+            .transpose()?,
+        is_public: template["is_public"].as_bool().unwrap_or(false),
+        constraints: TaskConstraints {
+            max_input_tokens: u64_field("max_input_tokens")?,
+            max_output_tokens: u64_field("max_output_tokens")?,
+            allowed_modalities,
+        },
+    })
+}
+
+fn print(params: &TaskParams) {
+    let or_none =
+        |value: Option<String>| value.unwrap_or_else(|| "-".to_owned());
+    println!("  role: {}", or_none(params.role.map(|v| v.to_string())));
+    println!("  model name: {}", or_none(params.model_name.clone()));
+    println!(
+        "  security level: {}",
+        or_none(params.security_level.map(|v| v.to_string()))
+    );
+    println!(
+        "  minimum reputation score: {}",
+        or_none(params.minimum_reputation_score.map(|v| v.to_string()))
+    );
+    println!("  is public: {}", params.is_public);
+    println!(
+        "  max input tokens: {}",
+        or_none(params.constraints.max_input_tokens.map(|v| v.to_string()))
+    );
+    println!(
+        "  max output tokens: {}",
+        or_none(params.constraints.max_output_tokens.map(|v| v.to_string()))
+    );
+    println!(
+        "  allowed modalities: {:?}",
+        params.constraints.allowed_modalities
+    );
+}
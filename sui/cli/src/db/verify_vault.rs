@@ -0,0 +1,124 @@
+use std::collections::BTreeMap;
+
+use sui_sdk::types::base_types::ObjectID;
+
+use super::{
+    list_nodes::{dynamic_field_values, parse_u64},
+    task_security_level, SamplingParams,
+};
+use crate::prelude::*;
+
+/// Checks that the fee treasury holds at least what the protocol owes:
+/// - fees attributed to nodes that they have not claimed yet
+/// - fees accrued by referrers that they have not claimed yet
+/// - what users paid for stacks that were not claimed yet
+/// - what users paid for prompts whose tickets are not settled yet
+///
+/// The treasury can hold a bit more than that, eg. rounding dust of fee
+/// splits, but never less.
+/// A deficit means an accounting bug and the command fails.
+///
+/// The tables are read page by page, so the check is only exact if no
+/// transaction touched the db in the meantime.
+pub(crate) async fn command(context: &mut Context) -> Result<()> {
+    let db_fields = context.load_atoma_db_fields().await?;
+    let root = |path: &[&str]| {
+        path.iter()
+            .fold(&db_fields, |value, key| &value[key])
+            .as_str()
+            .ok_or_else(|| anyhow!("No {} field found", path[0]))
+            .and_then(|id| Ok(ObjectID::from_str(id)?))
+    };
+    let nodes_table = root(&["nodes", "id", "id"])?;
+    let stacks_table = root(&["stacks", "id", "id"])?;
+    let tickets = root(&["tickets", "id"])?;
+    let fee_treasury = parse_u64(&db_fields["fee_treasury"])?;
+    let params = SamplingParams::load(context).await?;
+    let referrals = context.load_atoma_db_field("ReferralsKey").await?;
+    let referrals_table = ObjectID::from_str(
+        referrals["balances"]["id"]["id"]
+            .as_str()
+            .ok_or_else(|| anyhow!("No referral balances found"))?,
+    )?;
+
+    let client = context.get_client().await?;
+
+    let mut nodes_owed = 0u128;
+    for (_, node) in dynamic_field_values(&client, nodes_table).await? {
+        let node = &node["value"];
+        nodes_owed += parse_u64(&node["available_fee_amount"])? as u128
+            + parse_u64(&node["last_fee_epoch_amount"])? as u128;
+    }
+    context.progress("vault", 1, 4);
+
+    let mut referrers_owed = 0u128;
+    for (_, balance) in dynamic_field_values(&client, referrals_table).await? {
+        referrers_owed += parse_u64(&balance["value"])? as u128;
+    }
+    context.progress("vault", 2, 4);
+
+    let mut stacks_locked = 0u128;
+    let mut security_levels = BTreeMap::new();
+    for (key, stack) in dynamic_field_values(&client, stacks_table).await? {
+        let stack = &stack["value"];
+        if stack["is_claimed"].as_bool().unwrap_or_default() {
+            continue;
+        }
+        let task_small_id = parse_u64(&stack["task_small_id"]["inner"])?;
+        let security_level = match security_levels.get(&task_small_id) {
+            Some(security_level) => *security_level,
+            None => {
+                let security_level =
+                    task_security_level(context, task_small_id).await?;
+                security_levels.insert(task_small_id, security_level);
+                security_level
+            }
+        };
+        let cost = params.stack_cost(
+            security_level,
+            parse_u64(&stack["price_per_one_million_compute_units"])?,
+            parse_u64(&stack["num_compute_units"])?,
+        );
+        trace!("Stack {} locks {cost}", key["inner"]);
+        stacks_locked += cost as u128;
+    }
+    context.progress("vault", 3, 4);
+
+    let mut tickets_locked = 0u128;
+    for (key, ticket) in dynamic_field_values(&client, tickets).await? {
+        // tickets are stored as dynamic object fields
+        let Ok(collected_fee) =
+            parse_u64(&ticket["collected_fee_in_protocol_token"])
+        else {
+            continue;
+        };
+        trace!("Ticket {key} locks {collected_fee}");
+        tickets_locked += collected_fee as u128;
+    }
+    context.progress("vault", 4, 4);
+
+    let owed = nodes_owed + referrers_owed + stacks_locked + tickets_locked;
+    println!("Fee treasury: {}", context.toma(fee_treasury));
+    println!("Owed to nodes: {}", context.toma(nodes_owed));
+    println!("Owed to referrers: {}", context.toma(referrers_owed));
+    println!(
+        "Locked in unclaimed stacks: {}",
+        context.toma(stacks_locked)
+    );
+    println!(
+        "Locked in unsettled tickets: {}",
+        context.toma(tickets_locked)
+    );
+    println!("Total owed: {}", context.toma(owed));
+
+    let fee_treasury = fee_treasury as u128;
+    if fee_treasury < owed {
+        anyhow::bail!(
+            "Discrepancy: the fee treasury is short of {}",
+            context.toma(owed - fee_treasury)
+        );
+    }
+    println!("Surplus: {}", context.toma(fee_treasury - owed));
+
+    Ok(())
+}
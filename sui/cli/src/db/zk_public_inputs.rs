@@ -0,0 +1,63 @@
+use std::path::Path;
+
+use fastcrypto::encoding::{Encoding, Hex};
+
+use crate::prelude::*;
+
+/// Each public input is a scalar encoded as 32 little-endian bytes.
+const BYTES_PER_ZK_SCALAR: usize = 32;
+const NUM_PUBLIC_INPUTS: usize = 3;
+
+/// Prints the public inputs that the external prover must prove the
+/// settlement of a stack against, one hex encoded scalar per line.
+///
+/// The prover usually wants them as a single file, in which case they are
+/// written to `out` concatenated.
+pub(crate) fn command(
+    stack_small_id: u64,
+    num_claimed_compute_units: u64,
+    committed_stack_proof: &[u8],
+    out: Option<&Path>,
+) -> Result<()> {
+    let inputs = zk_settlement_public_inputs(
+        stack_small_id,
+        num_claimed_compute_units,
+        committed_stack_proof,
+    );
+    for scalar in inputs.chunks(BYTES_PER_ZK_SCALAR) {
+        println!("{}", Hex::encode(scalar));
+    }
+
+    if let Some(out) = out {
+        std::fs::write(out, &inputs)?;
+        info!("Written public inputs to {out:?}");
+    }
+
+    Ok(())
+}
+
+/// Must match `db::zk_settlement_public_inputs` on-chain:
+/// the stack small ID, the number of claimed compute units and the first 31
+/// bytes of the committed stack proof, each zero-padded to a little-endian
+/// scalar.
+pub(crate) fn zk_settlement_public_inputs(
+    stack_small_id: u64,
+    num_claimed_compute_units: u64,
+    committed_stack_proof: &[u8],
+) -> Vec<u8> {
+    let commitment = &committed_stack_proof
+        [..committed_stack_proof.len().min(BYTES_PER_ZK_SCALAR - 1)];
+
+    let mut inputs =
+        Vec::with_capacity(NUM_PUBLIC_INPUTS * BYTES_PER_ZK_SCALAR);
+    for le_bytes in [
+        &stack_small_id.to_le_bytes()[..],
+        &num_claimed_compute_units.to_le_bytes()[..],
+        commitment,
+    ] {
+        let mut scalar = [0; BYTES_PER_ZK_SCALAR];
+        scalar[..le_bytes.len()].copy_from_slice(le_bytes);
+        inputs.extend_from_slice(&scalar);
+    }
+    inputs
+}
@@ -0,0 +1,5 @@
+//! Commands for inspecting what's on-chain in the encodings that nodes see.
+
+mod decode_envelope;
+
+pub(crate) use decode_envelope::command as decode_envelope;
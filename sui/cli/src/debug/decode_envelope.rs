@@ -0,0 +1,48 @@
+use fastcrypto::encoding::{Encoding, Hex};
+use sui_sdk::types::base_types::ObjectID;
+
+use crate::{gate::prompt_event, prelude::*, protocol_encoding};
+
+/// Decodes the output destination of a text to text prompt, read from the
+/// event its ticket was created with, or of the given hex encoded bytes.
+/// The prompt itself is printed as text.
+pub(crate) async fn command(
+    context: &mut Context,
+    ticket_id: Option<&str>,
+    hex: Option<&str>,
+) -> Result<()> {
+    let output_destination = match (ticket_id, hex) {
+        (_, Some(hex)) => Hex::decode(hex.trim_start_matches("0x"))
+            .map_err(|err| anyhow!("Invalid hex: {err}"))?,
+        (Some(ticket_id), None) => {
+            let event =
+                prompt_event(context, ObjectID::from_str(ticket_id)?).await?;
+            let prompt = bytes_field(&event["params"]["prompt"])?;
+            println!("Prompt: {}", String::from_utf8_lossy(&prompt));
+            bytes_field(&event["output_destination"])?
+        }
+        (None, None) => anyhow::bail!("Provide a ticket ID or hex bytes"),
+    };
+
+    let envelope = protocol_encoding::decode(&output_destination)?;
+    println!("Envelope version: {}", envelope.version);
+    match envelope.payload {
+        Some(payload) => println!("Output destination: {payload}"),
+        None => println!("Output destination: none"),
+    }
+
+    Ok(())
+}
+
+/// `vector<u8>` fields are JSON arrays of numbers.
+fn bytes_field(value: &serde_json::Value) -> Result<Vec<u8>> {
+    value
+        .as_array()
+        .and_then(|items| {
+            items
+                .iter()
+                .map(|item| item.as_u64().and_then(|b| u8::try_from(b).ok()))
+                .collect()
+        })
+        .ok_or_else(|| anyhow!("Expected bytes, got {value}"))
+}
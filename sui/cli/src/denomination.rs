@@ -0,0 +1,69 @@
+//! TOMA amounts are stored on-chain in the smallest unit, with 9 decimals,
+//! which makes raw amounts easy to misread by orders of magnitude.
+//!
+//! Amount flags accept `1.5 TOMA`, `1500 mTOMA` or a raw integer in the
+//! smallest unit, and amounts are printed in TOMA unless `--raw` is set.
+
+use crate::prelude::*;
+
+/// Must match `toma::DECIMALS`.
+pub(crate) const TOMA_DECIMALS: u32 = 9;
+
+/// Units that amounts can be given in, with their decimals.
+const UNITS: [(&str, u32); 2] = [("TOMA", TOMA_DECIMALS), ("mTOMA", 6)];
+
+/// Parses an amount into the smallest unit.
+/// Without a unit, the amount is already in the smallest unit.
+pub(crate) fn parse_toma(amount: &str) -> Result<u64> {
+    let amount = amount.trim();
+    let (number, unit) = match amount.find(|c: char| c.is_ascii_alphabetic()) {
+        Some(unit_start) => {
+            (amount[..unit_start].trim(), amount[unit_start..].trim())
+        }
+        None => (amount, ""),
+    };
+    let decimals = if unit.is_empty() {
+        0
+    } else {
+        UNITS
+            .iter()
+            .find(|(name, _)| *name == unit)
+            .map(|(_, decimals)| *decimals)
+            .ok_or_else(|| {
+                anyhow!("Unknown unit {unit}, expected TOMA or mTOMA")
+            })?
+    };
+
+    let (whole, fraction) = number.split_once('.').unwrap_or((number, ""));
+    if whole.is_empty() && fraction.is_empty() {
+        anyhow::bail!("{amount} is not an amount");
+    }
+    if fraction.len() > decimals as usize {
+        anyhow::bail!("{amount} has more than {decimals} decimal(s)");
+    }
+    let digits =
+        format!("{whole}{fraction:0<width$}", width = decimals as usize);
+    if !digits.chars().all(|c| c.is_ascii_digit()) {
+        anyhow::bail!("{amount} is not an amount");
+    }
+
+    digits
+        .parse()
+        .map_err(|_| anyhow!("{amount} does not fit into u64"))
+}
+
+/// Prints the amount in TOMA, or as is if `raw`.
+pub(crate) fn format_toma(amount: u128, raw: bool) -> String {
+    if raw {
+        return amount.to_string();
+    }
+
+    let unit = 10u128.pow(TOMA_DECIMALS);
+    let (whole, fraction) = (amount / unit, amount % unit);
+    if fraction == 0 {
+        return format!("{whole} TOMA");
+    }
+    let fraction =
+        format!("{fraction:0>width$}", width = TOMA_DECIMALS as usize);
+    format!("{whole}.{} TOMA", fraction.trim_end_matches('0'))
+}
@@ -1,21 +1,42 @@
 use core::panic;
-use std::path::{Path, PathBuf};
+use std::{
+    path::{Path, PathBuf},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
 
 use sui_sdk::{
     rpc_types::{
         Page, SuiData, SuiObjectDataFilter, SuiObjectDataOptions,
         SuiObjectResponseQuery, SuiParsedData, SuiTransactionBlockEffects,
-        SuiTransactionBlockResponseOptions, SuiTransactionBlockResponseQuery,
-        TransactionFilter,
+        SuiTransactionBlockResponse, SuiTransactionBlockResponseOptions,
+        SuiTransactionBlockResponseQuery, TransactionFilter,
+    },
+    types::{
+        base_types::{ObjectID, ObjectType, SuiAddress},
+        transaction::{Transaction, TransactionData},
+        SUI_RANDOMNESS_STATE_OBJECT_ID,
     },
-    types::base_types::{ObjectID, ObjectType, SuiAddress},
     SuiClient,
 };
 
+#[cfg(feature = "gateway")]
+use sui_sdk::types::transaction::TransactionDataAPI;
+
+#[cfg(feature = "daemon")]
+use crate::daemon::{ColdSweepConf, WatchdogConf};
+#[cfg(feature = "gateway")]
+use crate::zklogin::ZkLoginConf;
 use crate::{
-    prelude::*, DB_MANAGER_TYPE_NAME, DB_MODULE_NAME, DB_NODE_TYPE_NAME,
+    confirmation::{self, WaitFor},
+    db, denomination,
+    prelude::*,
+    progress::{self, ProgressFormat},
+    role::Role,
+    telemetry::{self, Trace},
+    timeouts::{self, Timeouts},
+    toma, DB_MANAGER_TYPE_NAME, DB_MODULE_NAME, DB_NODE_TYPE_NAME,
     DB_TASK_TYPE_NAME, DB_TYPE_NAME, FAUCET_TYPE_NAME, SETTLEMENT_MODULE_NAME,
-    SETTLEMENT_TICKET_TYPE_NAME, TOMA_COIN_MODULE_NAME,
+    SETTLEMENT_TICKET_TYPE_NAME, TOMA_COIN_MODULE_NAME, TOMA_COIN_TYPE_NAME,
 };
 
 const ATOMA_DB_OBJECT_ID: &str =
@@ -32,6 +53,14 @@ pub(crate) const TASK_SMALL_ID: &str = "TASK_SMALL_ID";
 pub(crate) const TOMA_PACKAGE_ID: &str = "TOMA_PACKAGE_ID";
 pub(crate) const TOMA_WALLET_ID: &str = "TOMA_WALLET_ID";
 pub(crate) const WALLET_PATH: &str = "WALLET_PATH";
+/// Randomness is updated several times a second, so this only trips if the
+/// network stopped producing it.
+const RANDOMNESS_MAX_AGE_MS: u64 = 10 * 60 * 1000;
+/// Coins that this process just created can take a few seconds to show up
+/// in the full node's coin index.
+const COIN_DISCOVERY_TIMEOUT_AFTER_MINT: Duration = Duration::from_secs(30);
+const COIN_DISCOVERY_MIN_BACKOFF: Duration = Duration::from_millis(250);
+const COIN_DISCOVERY_MAX_BACKOFF: Duration = Duration::from_secs(4);
 
 pub(crate) struct Context {
     pub(crate) conf: DotenvConf,
@@ -51,7 +80,33 @@ pub(crate) struct DotenvConf {
     pub(crate) node_id: Option<u64>,
     pub(crate) faucet_id: Option<ObjectID>,
     pub(crate) toma_wallet_id: Option<ObjectID>,
+    /// See `get_or_load_toma_coin_type`.
+    pub(crate) toma_coin_type: Option<String>,
+    /// Coin types of older TOMA generations, ie. of TOMA packages that were
+    /// published anew rather than upgraded.
+    /// Their coins are reported and can be migrated, but not paid with.
+    pub(crate) toma_legacy_coin_types: Vec<String>,
     pub(crate) gas_budget: Option<u64>,
+    pub(crate) progress: ProgressFormat,
+    pub(crate) otlp_endpoint: Option<String>,
+    #[cfg(feature = "daemon")]
+    pub(crate) watchdog: WatchdogConf,
+    #[cfg(feature = "daemon")]
+    pub(crate) cold_sweep: ColdSweepConf,
+    pub(crate) wait_for: WaitFor,
+    pub(crate) wait_timeout: Duration,
+    pub(crate) timeouts: Timeouts,
+    pub(crate) raw_amounts: bool,
+    /// How long to retry finding a TOMA coin for, see
+    /// `get_or_load_toma_wallet`.
+    pub(crate) wait_for_coin: Duration,
+    /// Set once this process created TOMA coins, so that finding them is
+    /// retried even without `wait_for_coin`.
+    pub(crate) toma_coins_created: bool,
+    pub(crate) role: Role,
+    /// Signs as a zkLogin address instead of the active address if set.
+    #[cfg(feature = "gateway")]
+    pub(crate) zklogin: Option<ZkLoginConf>,
 }
 
 impl DotenvConf {
@@ -98,10 +153,29 @@ impl DotenvConf {
                 .ok()
                 .filter(|s| !s.is_empty())
                 .map(|s| ObjectID::from_str(&s).unwrap()),
+            toma_coin_type: None,
+            toma_legacy_coin_types: toma::legacy_coin_types_from_env(),
             gas_budget: std::env::var(GAS_BUDGET)
                 .ok()
                 .filter(|s| !s.is_empty())
                 .map(|s| s.parse().unwrap()),
+            progress: ProgressFormat::default(),
+            otlp_endpoint: std::env::var(telemetry::OTLP_ENDPOINT)
+                .ok()
+                .filter(|s| !s.is_empty()),
+            #[cfg(feature = "daemon")]
+            watchdog: WatchdogConf::from_env(),
+            #[cfg(feature = "daemon")]
+            cold_sweep: ColdSweepConf::from_env(),
+            wait_for: WaitFor::from_env(),
+            wait_timeout: confirmation::DEFAULT_TIMEOUT,
+            timeouts: Timeouts::from_env(),
+            raw_amounts: false,
+            wait_for_coin: Duration::ZERO,
+            toma_coins_created: false,
+            role: Role::from_env(),
+            #[cfg(feature = "gateway")]
+            zklogin: ZkLoginConf::from_env(),
         }
     }
 }
@@ -111,6 +185,173 @@ impl Context {
         self.wallet.get_client().await
     }
 
+    /// The zkLogin address if configured, otherwise the active address.
+    /// End user transactions, ie. prompts and stacks, are sent from it.
+    pub(crate) fn sender(&self) -> Result<SuiAddress> {
+        #[cfg(feature = "gateway")]
+        if let Some(zklogin) = &self.conf.zklogin {
+            return Ok(zklogin.address());
+        }
+        self.wallet.active_address()
+    }
+
+    /// Signs with zkLogin if the transaction is sent from the zkLogin
+    /// address, otherwise with the key of the active address.
+    /// Refuses to sign in read-only mode.
+    pub(crate) fn sign_transaction(
+        &self,
+        tx: &TransactionData,
+    ) -> Result<Transaction> {
+        self.conf.role.ensure_can_sign()?;
+        #[cfg(feature = "gateway")]
+        if let Some(zklogin) = &self.conf.zklogin {
+            if tx.sender() == zklogin.address() {
+                return Ok(zklogin.sign_transaction(tx));
+            }
+        }
+        Ok(self.wallet.sign_transaction(tx))
+    }
+
+    /// Executes the transaction, panics if it fails, and waits for the
+    /// confirmation level set with `--wait-for`.
+    pub(crate) async fn execute_transaction_must_succeed(
+        &self,
+        tx: Transaction,
+    ) -> Result<SuiTransactionBlockResponse> {
+        let resp = match self.conf.timeouts.execute {
+            Some(timeout) => {
+                let resp = timeouts::execute(&self.wallet, timeout, tx).await?;
+                assert!(resp.status_ok().unwrap());
+                resp
+            }
+            None => self.wallet.execute_transaction_must_succeed(tx).await,
+        };
+        self.wait_for_confirmation(resp.digest).await?;
+        Ok(resp)
+    }
+
+    /// Like [`Self::execute_transaction_must_succeed`] but a failed
+    /// execution is returned rather than a panic.
+    pub(crate) async fn execute_transaction_may_fail(
+        &self,
+        tx: Transaction,
+    ) -> Result<SuiTransactionBlockResponse> {
+        let resp = match self.conf.timeouts.execute {
+            Some(timeout) => {
+                timeouts::execute(&self.wallet, timeout, tx).await?
+            }
+            None => self.wallet.execute_transaction_may_fail(tx).await?,
+        };
+        self.wait_for_confirmation(resp.digest).await?;
+        Ok(resp)
+    }
+
+    async fn wait_for_confirmation(
+        &self,
+        digest: TransactionDigest,
+    ) -> Result<()> {
+        confirmation::wait(
+            &self.get_client().await?,
+            self.conf.wait_for,
+            self.conf.wait_timeout,
+            digest,
+        )
+        .await
+    }
+
+    /// Reports that `done` out of `total` steps of a long running command's
+    /// phase are completed.
+    pub(crate) fn progress(&self, phase: &str, done: usize, total: usize) {
+        progress::report(self.conf.progress, phase, done, total);
+    }
+
+    /// Starts a trace of a pipeline stage that is exported to the OTLP
+    /// endpoint once finished.
+    pub(crate) fn trace(&self, name: &str) -> Trace {
+        Trace::start(self.conf.otlp_endpoint.as_deref(), name)
+    }
+
+    /// Fails early, rather than with an execution abort, on networks that
+    /// don't have the `Random` object or where it's no longer updated.
+    ///
+    /// The state of `Random` lives in the only dynamic field of its
+    /// versioned wrapper, which every new randomness round modifies.
+    pub(crate) async fn ensure_randomness_is_live(&self) -> Result<()> {
+        let client = self.get_client().await?;
+        let random = client
+            .read_api()
+            .get_object_with_options(
+                SUI_RANDOMNESS_STATE_OBJECT_ID,
+                SuiObjectDataOptions {
+                    show_content: true,
+                    ..Default::default()
+                },
+            )
+            .await?
+            .data
+            .and_then(|data| data.content)
+            .and_then(|content| content.try_into_move())
+            .ok_or_else(|| {
+                anyhow!(
+                    "Randomness is not enabled on this network, \
+                    {SUI_RANDOMNESS_STATE_OBJECT_ID} does not exist"
+                )
+            })?
+            .fields
+            .to_json_value();
+        let versioned = ObjectID::from_str(
+            random["inner"]["id"]["id"]
+                .as_str()
+                .ok_or_else(|| anyhow!("Random has no inner state"))?,
+        )?;
+
+        let inner = client
+            .read_api()
+            .get_dynamic_fields(versioned, None, Some(1))
+            .await?
+            .data
+            .into_iter()
+            .next()
+            .ok_or_else(|| anyhow!("Randomness state is not initialized"))?;
+        let last_update = client
+            .read_api()
+            .get_object_with_options(
+                inner.object_id,
+                SuiObjectDataOptions {
+                    show_previous_transaction: true,
+                    ..Default::default()
+                },
+            )
+            .await?
+            .data
+            .and_then(|data| data.previous_transaction)
+            .ok_or_else(|| anyhow!("Randomness state has no last update"))?;
+        let updated_at_ms = client
+            .read_api()
+            .get_transaction_with_options(
+                last_update,
+                SuiTransactionBlockResponseOptions::new(),
+            )
+            .await?
+            .timestamp_ms
+            .ok_or_else(|| {
+                anyhow!("Randomness update {last_update} has no timestamp")
+            })?;
+
+        let now_ms =
+            SystemTime::now().duration_since(UNIX_EPOCH)?.as_millis() as u64;
+        let age_ms = now_ms.saturating_sub(updated_at_ms);
+        if age_ms > RANDOMNESS_MAX_AGE_MS {
+            anyhow::bail!(
+                "Randomness was last updated {} s ago in {last_update}, \
+                the network doesn't seem to produce it anymore",
+                age_ms / 1000
+            );
+        }
+
+        Ok(())
+    }
+
     pub(crate) fn with_optional_atoma_package_id(
         mut self,
         package_id: Option<String>,
@@ -148,13 +389,14 @@ impl Context {
     }
 
     /// Removes all the IDs that have been loaded so far from the config.
-    fn reset_ids(&mut self) {
+    pub(crate) fn reset_ids(&mut self) {
         self.conf.atoma_db_id = None;
         self.conf.faucet_id = None;
         self.conf.manager_badge_id = None;
         self.conf.node_badge_id = None;
         self.conf.node_id = None;
         self.conf.toma_wallet_id = None;
+        self.conf.toma_coin_type = None;
     }
 
     /// Package of the Atoma network.
@@ -179,6 +421,31 @@ impl Context {
         }
     }
 
+    /// Types are named after the package that first defined them, so after
+    /// an upgrade of the TOMA package the coin type still names the
+    /// original package rather than `TOMA_PACKAGE_ID`.
+    pub(crate) async fn get_or_load_toma_coin_type(
+        &mut self,
+    ) -> Result<String> {
+        if let Some(coin_type) = &self.conf.toma_coin_type {
+            return Ok(coin_type.clone());
+        }
+
+        let toma_package = self.get_or_load_toma_package_id().await?;
+        let package = db::load_package(self, toma_package).await?;
+        let origin = db::type_origin(
+            &package,
+            TOMA_COIN_MODULE_NAME,
+            TOMA_COIN_TYPE_NAME,
+        )?;
+        let coin_type = toma::normalize_coin_type(&format!(
+            "{origin}::{TOMA_COIN_MODULE_NAME}::{TOMA_COIN_TYPE_NAME}"
+        ))?;
+        self.conf.toma_coin_type = Some(coin_type.clone());
+
+        Ok(coin_type)
+    }
+
     /// Some CLI calls don't require a package ID to be provided, because it can
     /// be found from the context. For example, ticket ID is enough to find
     /// the package ID.
@@ -200,6 +467,11 @@ impl Context {
         }
     }
 
+    /// Formats a TOMA amount as set by `--raw`.
+    pub(crate) fn toma(&self, amount: impl Into<u128>) -> String {
+        denomination::format_toma(amount.into(), self.conf.raw_amounts)
+    }
+
     pub(crate) fn gas_budget(&self) -> u64 {
         self.conf.gas_budget.unwrap_or(10_000_000)
     }
@@ -298,25 +570,65 @@ impl Context {
         }
     }
 
+    /// The full node's coin index lags behind, so a coin that was just
+    /// minted or transferred might not be found right away.
+    /// Finding one is retried with a backoff for up to `wait_for_coin`, or
+    /// longer if this process created TOMA coins.
     pub(crate) async fn get_or_load_toma_wallet(&mut self) -> Result<ObjectID> {
         if let Some(toma_wallet_id) = self.conf.toma_wallet_id {
-            Ok(toma_wallet_id)
+            return Ok(toma_wallet_id);
+        }
+
+        let coin_type = self.get_or_load_toma_coin_type().await?;
+        let client = self.get_client().await?;
+        let owner = self.sender()?;
+        let timeout = if self.conf.toma_coins_created {
+            self.conf
+                .wait_for_coin
+                .max(COIN_DISCOVERY_TIMEOUT_AFTER_MINT)
         } else {
-            let toma_package_id = self.get_or_load_toma_package_id().await?;
-            let active_address = self.wallet.active_address()?;
-            let toma_wallet = find_toma_token_wallet(
-                &self.get_client().await?,
-                toma_package_id,
-                active_address,
-            )
-            .await;
-            if let Ok(toma_wallet) = toma_wallet {
-                self.conf.toma_wallet_id = Some(toma_wallet);
-                Ok(toma_wallet)
-            } else {
-                anyhow::bail!("No TOMA wallet found")
+            self.conf.wait_for_coin
+        };
+        let started_at = Instant::now();
+        let mut backoff = COIN_DISCOVERY_MIN_BACKOFF;
+        let toma_wallet = loop {
+            match find_toma_token_wallet(&client, &coin_type, owner).await? {
+                Some(toma_wallet) => break toma_wallet,
+                None if started_at.elapsed() + backoff <= timeout => {
+                    debug!(
+                        "No TOMA coins for {owner} yet, retry in {backoff:?}"
+                    );
+                    tokio::time::sleep(backoff).await;
+                    backoff = (backoff * 2).min(COIN_DISCOVERY_MAX_BACKOFF);
+                }
+                None if !self.conf.toma_legacy_coin_types.is_empty() => {
+                    anyhow::bail!(
+                        "No {coin_type} coins for {owner}. \
+                        Coins of older TOMA generations can't pay, check \
+                        whether it holds any with `toma balances` and swap \
+                        them with `toma migrate-coins`."
+                    )
+                }
+                None => anyhow::bail!(
+                    "No TOMA coins for {owner}. \
+                    Have you just received them? \
+                    It may take a few seconds for cache to refresh. \
+                    Double check that your address owns TOMA coins and try \
+                    again, or wait for them with --wait-for-coin."
+                ),
             }
-        }
+        };
+        self.conf.toma_wallet_id = Some(toma_wallet);
+
+        Ok(toma_wallet)
+    }
+
+    /// Called after a transaction created TOMA coins for the sender, so that
+    /// finding them is retried while the coin index catches up.
+    /// The largest coin might have changed too.
+    pub(crate) fn note_toma_coins_created(&mut self) {
+        self.conf.toma_coins_created = true;
+        self.conf.toma_wallet_id = None;
     }
 
     pub(crate) async fn ticket_package_and_fields(
@@ -397,6 +709,62 @@ impl Context {
 
         Ok(atoma.fields.to_json_value())
     }
+
+    /// The state that was added to the db after it was published lives in
+    /// dynamic fields of the db, keyed by a struct of the db module, eg.
+    /// `TaskConstraintsKey`.
+    pub(crate) async fn load_atoma_db_field(
+        &mut self,
+        key_type_name: &str,
+    ) -> Result<serde_json::Value> {
+        let atoma_id = self.get_or_load_atoma_db().await?;
+        let key_type_suffix = format!("::{DB_MODULE_NAME}::{key_type_name}");
+        let client = self.get_client().await?;
+
+        let mut cursor = None;
+        let field = loop {
+            let Page {
+                data,
+                has_next_page,
+                next_cursor,
+            } = client
+                .read_api()
+                .get_dynamic_fields(atoma_id, cursor, None)
+                .await?;
+            if let Some(info) = data.into_iter().find(|info| {
+                info.name.type_.to_string().ends_with(&key_type_suffix)
+            }) {
+                break info.object_id;
+            }
+            if !has_next_page {
+                return Err(anyhow!(
+                    "AtomaDb has no {key_type_name} field, the manager has to \
+                    migrate the db after upgrading the package"
+                ));
+            }
+            cursor = next_cursor;
+        };
+
+        let SuiParsedData::MoveObject(field) = client
+            .read_api()
+            .get_object_with_options(
+                field,
+                SuiObjectDataOptions {
+                    show_content: true,
+                    ..Default::default()
+                },
+            )
+            .await?
+            .data
+            .ok_or_else(|| anyhow!("Cannot fetch {key_type_name} data"))?
+            .content
+            .ok_or_else(|| anyhow!("{key_type_name} has no content"))?
+        else {
+            return Err(anyhow!("{key_type_name} must be a Move object"));
+        };
+
+        Ok(field.fields.to_json_value()["value"].take())
+    }
 }
 
 /// Returns the ID of the node badge and the small ID of the node.
@@ -520,32 +888,20 @@ async fn get_task_badge(
         })
 }
 
+/// The largest TOMA coin of the owner, if the full node knows of any.
 async fn find_toma_token_wallet(
     client: &SuiClient,
-    toma_package: ObjectID,
-    active_address: SuiAddress,
-) -> Result<ObjectID> {
+    coin_type: &str,
+    owner: SuiAddress,
+) -> Result<Option<ObjectID>> {
     let Page { data: coins, .. } = client
         .coin_read_api()
-        .get_coins(
-            active_address,
-            Some(format!("{toma_package}::toma::TOMA")),
-            None,
-            None,
-        )
+        .get_coins(owner, Some(coin_type.to_string()), None, None)
         .await?;
-    coins
+    Ok(coins
         .into_iter()
         .max_by_key(|coin| coin.balance)
-        .map(|coin| coin.coin_object_id)
-        .ok_or_else(|| {
-            anyhow::anyhow!(
-                "No TOMA coins for {active_address}. \
-                Have you just received them? \
-                It may take a few seconds for cache to refresh. \
-                Double check that your address owns TOMA coins and try again."
-            )
-        })
+        .map(|coin| coin.coin_object_id))
 }
 
 async fn get_atoma_db(
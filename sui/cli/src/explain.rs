@@ -0,0 +1,266 @@
+//! Post-mortem of a transaction that touched the Atoma packages, eg. one
+//! that a user reports as failed, in a form support can read without the
+//! raw JSON of an explorer.
+//!
+//! Abort codes are named after the error constants of the Move sources the
+//! CLI was built with, so a package upgraded since might name them
+//! differently.
+
+use std::collections::BTreeMap;
+
+use regex::Regex;
+use sui_sdk::{
+    rpc_types::{
+        SuiArgument, SuiCallArg, SuiCommand, SuiExecutionStatus,
+        SuiMoveNormalizedType, SuiTransactionBlockDataAPI,
+        SuiTransactionBlockEffectsAPI, SuiTransactionBlockKind,
+        SuiTransactionBlockResponseOptions,
+    },
+    types::{base_types::ObjectID, TypeTag},
+};
+
+use crate::{
+    call::{is_tx_context, type_name},
+    prelude::*,
+};
+
+/// Each module numbers its errors from its own `EBase`.
+const MODULE_SOURCES: [(&str, &str); 3] = [
+    ("db", include_str!("../../packages/atoma/sources/db.move")),
+    (
+        "gate",
+        include_str!("../../packages/atoma/sources/gate.move"),
+    ),
+    (
+        "settlement",
+        include_str!("../../packages/atoma/sources/settlement.move"),
+    ),
+];
+
+/// 1. Load the transaction with its effects, events and object changes.
+/// 2. Print the Move calls with their arguments, next to the parameter types
+///    of the called functions.
+/// 3. Print the created, mutated and deleted objects, and the events.
+/// 4. Print the gas and balance changes, and the abort reason if it failed.
+pub(crate) async fn command(
+    context: &mut Context,
+    digest: TransactionDigest,
+) -> Result<()> {
+    // 1.
+    let client = context.get_client().await?;
+    let resp = client
+        .read_api()
+        .get_transaction_with_options(
+            digest,
+            SuiTransactionBlockResponseOptions::full_content(),
+        )
+        .await?;
+    let tx = resp
+        .transaction
+        .as_ref()
+        .ok_or_else(|| anyhow!("Transaction {digest} has no input"))?;
+    let effects = resp
+        .effects
+        .as_ref()
+        .ok_or_else(|| anyhow!("Transaction {digest} has no effects"))?;
+
+    println!("Transaction {digest}");
+    println!("Sender: {}", tx.data.sender());
+    if let Some(checkpoint) = resp.checkpoint {
+        println!("Checkpoint: {checkpoint}");
+    }
+    if let Some(timestamp_ms) = resp.timestamp_ms {
+        println!("Timestamp: {timestamp_ms} ms");
+    }
+
+    // 2.
+    let SuiTransactionBlockKind::ProgrammableTransaction(ptb) =
+        tx.data.transaction()
+    else {
+        println!("Not a programmable transaction: {}", tx.data.transaction());
+        return Ok(());
+    };
+    println!("\nCommands:");
+    let mut signatures = BTreeMap::new();
+    for (index, command) in ptb.commands.iter().enumerate() {
+        let SuiCommand::MoveCall(call) = command else {
+            println!("{index}. {command}");
+            continue;
+        };
+        let is_atoma = context.conf.atoma_package_id == Some(call.package)
+            || MODULE_SOURCES.iter().any(|(name, _)| *name == call.module);
+        println!(
+            "{index}. {}::{}::{}{}",
+            call.package,
+            call.module,
+            call.function,
+            if is_atoma { " (atoma)" } else { "" }
+        );
+        if !call.type_arguments.is_empty() {
+            println!("   type arguments: {}", call.type_arguments.join(", "));
+        }
+
+        let key = (call.package, call.module.clone(), call.function.clone());
+        if !signatures.contains_key(&key) {
+            // eg. a package that was upgraded since, the arguments are then
+            // printed without types
+            let parameters =
+                load_parameters(context, &key).await.unwrap_or_else(|err| {
+                    debug!("No signature of {}: {err}", call.function);
+                    vec![]
+                });
+            signatures.insert(key.clone(), parameters);
+        }
+        let parameters = &signatures[&key];
+        for (position, argument) in call.arguments.iter().enumerate() {
+            let parameter = parameters
+                .get(position)
+                .map(type_name)
+                .unwrap_or_else(|| "?".to_string());
+            println!(
+                "   {parameter} = {}",
+                argument_value(argument, &ptb.inputs)
+            );
+        }
+    }
+
+    // 3.
+    println!("\nObjects:");
+    for change in resp.object_changes.iter().flatten() {
+        let change = serde_json::to_value(change)?;
+        let id = change["objectId"]
+            .as_str()
+            .or_else(|| change["packageId"].as_str())
+            .unwrap_or("?");
+        let object_type = change["objectType"].as_str().unwrap_or("package");
+        println!(
+            "{} {id} {}",
+            change["type"].as_str().unwrap_or("?"),
+            short_type(object_type)
+        );
+    }
+    println!("\nEvents:");
+    for event in resp.events.iter().flat_map(|events| &events.data) {
+        println!(
+            "{}::{} {}",
+            event.type_.module, event.type_.name, event.parsed_json
+        );
+    }
+
+    // 4.
+    let gas = effects.gas_cost_summary();
+    println!("\nFees:");
+    println!(
+        "gas {} MIST (computation {}, storage {}, rebate {})",
+        gas.net_gas_usage(),
+        gas.computation_cost,
+        gas.storage_cost,
+        gas.storage_rebate
+    );
+    for change in resp.balance_changes.iter().flatten() {
+        let coin = match &change.coin_type {
+            TypeTag::Struct(coin) => coin.name.to_string(),
+            coin_type => coin_type.to_string(),
+        };
+        println!("{} {:+} {coin}", change.owner, change.amount);
+    }
+
+    match effects.status() {
+        SuiExecutionStatus::Success => println!("\nStatus: success"),
+        SuiExecutionStatus::Failure { error } => {
+            println!("\nStatus: failed");
+            println!("{}", abort_reason(error));
+        }
+    }
+
+    Ok(())
+}
+
+/// The parameters of the called function, without `TxContext`.
+async fn load_parameters(
+    context: &Context,
+    (package, module, function): &(ObjectID, String, String),
+) -> Result<Vec<SuiMoveNormalizedType>> {
+    let signature = context
+        .get_client()
+        .await?
+        .read_api()
+        .get_normalized_move_function(
+            *package,
+            module.clone(),
+            function.clone(),
+        )
+        .await?;
+
+    Ok(signature
+        .parameters
+        .into_iter()
+        .filter(|parameter| !is_tx_context(parameter))
+        .collect())
+}
+
+fn argument_value(argument: &SuiArgument, inputs: &[SuiCallArg]) -> String {
+    let SuiArgument::Input(index) = argument else {
+        // results of previous commands and the gas coin
+        return argument.to_string();
+    };
+    match inputs.get(*index as usize) {
+        Some(input) => match (input.pure(), input.object()) {
+            (Some(value), _) => value.to_json_value().to_string(),
+            (_, Some(id)) => format!("object {id}"),
+            _ => argument.to_string(),
+        },
+        None => argument.to_string(),
+    }
+}
+
+/// `0x..::db::AtomaDb` as `db::AtomaDb`, type arguments included.
+fn short_type(object_type: &str) -> String {
+    Regex::new(r"0x[0-9a-f]+::")
+        .unwrap()
+        .replace_all(object_type, "")
+        .into_owned()
+}
+
+/// Names the error constant of Move aborts in the Atoma modules, otherwise
+/// returns the error as is.
+fn abort_reason(error: &str) -> String {
+    let code = Regex::new(r"MoveAbort\(.*, (\d+)\)")
+        .unwrap()
+        .captures(error)
+        .and_then(|captures| captures[1].parse::<u64>().ok());
+    let function = Regex::new(r#"function_name: Some\("(\w+)"\)"#)
+        .unwrap()
+        .captures(error)
+        .map(|captures| captures[1].to_string());
+
+    match code.and_then(error_constant) {
+        Some((module, name)) => format!(
+            "Aborted with {module}::{name} ({}) in {}",
+            code.unwrap_or_default(),
+            function.as_deref().unwrap_or("an unknown function")
+        ),
+        None => error.to_string(),
+    }
+}
+
+/// The module and name of the error constant with the code.
+fn error_constant(code: u64) -> Option<(&'static str, String)> {
+    let ebase = Regex::new(r"const EBase: u64 = ([\d_]+);").unwrap();
+    let constant = Regex::new(r"const (E\w+): u64 = EBase \+ (\d+);").unwrap();
+
+    MODULE_SOURCES.iter().find_map(|(module, source)| {
+        let base: u64 = ebase
+            .captures(source)?
+            .get(1)?
+            .as_str()
+            .replace('_', "")
+            .parse()
+            .ok()?;
+        let offset = code.checked_sub(base)?;
+        constant
+            .captures_iter(source)
+            .find(|captures| captures[2].parse::<u64>().ok() == Some(offset))
+            .map(|captures| (*module, captures[1].to_string()))
+    })
+}
@@ -0,0 +1,8 @@
+//! Populates a devnet deployment with synthetic nodes, models, tasks and
+//! stacks for tests and demos.
+
+mod generate;
+mod node;
+
+pub(crate) use generate::{command as generate, FixtureSizes};
+pub(crate) use node::{execute_as, node_move_call, parse_u64, single_event};
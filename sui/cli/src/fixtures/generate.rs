@@ -0,0 +1,289 @@
+use std::path::Path;
+
+use fastcrypto::{
+    ed25519::{Ed25519KeyPair, Ed25519PrivateKey},
+    hash::{Blake2b256, HashFunction},
+    traits::{EncodeDecodeBase64, ToFromBytes},
+};
+use serde_json::json;
+use sui_sdk::types::{
+    base_types::{ObjectID, SuiAddress},
+    crypto::SuiKeyPair,
+};
+
+use super::{execute_as, node_move_call, parse_u64, single_event};
+use crate::{db, prelude::*};
+
+/// Node keypairs are derived from the seed with this domain so that the same
+/// seed always yields the same node addresses.
+const KEYPAIR_DOMAIN: &[u8] = b"atoma-fixture-node";
+const ECHELON: u64 = 1;
+const FEE_PER_TOKEN: u64 = 1;
+const RELATIVE_PERFORMANCE: u64 = 100;
+const TASK_ROLE_CHAT_COMPLETION: u16 = 0;
+const TEXT2TEXT_MODALITY: u64 = 0;
+const PRICE_PER_ONE_MILLION_COMPUTE_UNITS: u64 = 1_000;
+const STACK_COMPUTE_UNITS: u64 = 1_000_000;
+
+pub(crate) struct FixtureSizes {
+    pub(crate) nodes: u64,
+    pub(crate) models: u64,
+    pub(crate) tasks: u64,
+    pub(crate) stacks: u64,
+}
+
+/// 1. Create the models, each with a single echelon, and a task per model in
+///    round robin.
+/// 2. Derive the node keypairs from the seed and fund them with SUI for gas
+///    from the active address in a single transaction.
+/// 3. Each node registers, joins the echelon of its model (round robin) and
+///    subscribes to the tasks of that model.
+/// 4. The active address acquires stacks for the tasks in round robin.
+///    It pays from the wallet configured in `TOMA_WALLET_ID`.
+/// 5. Write the manifest.
+///
+/// Models are named after the seed so that a package can hold fixtures of
+/// several seeds.
+/// Running the same seed twice against the same package fails because the
+/// models already exist.
+pub(crate) async fn command(
+    context: &mut Context,
+    FixtureSizes {
+        nodes,
+        models,
+        tasks,
+        stacks,
+    }: FixtureSizes,
+    security_level: Option<u16>,
+    seed: u64,
+    gas_per_node: u64,
+    out: &Path,
+) -> Result<()> {
+    if models == 0 || tasks < models || nodes < models {
+        anyhow::bail!(
+            "Every model needs at least one task and one node, \
+            got {models} model(s), {tasks} task(s) and {nodes} node(s)"
+        );
+    }
+    let atoma_package = context.unwrap_atoma_package_id();
+    let atoma_db = context.get_or_load_atoma_db().await?;
+
+    // 1.
+    let model_names: Vec<_> = (0..models)
+        .map(|i| format!("fixture-{seed}-model-{i}"))
+        .collect();
+    for (i, model_name) in model_names.iter().enumerate() {
+        db::add_model(context, model_name, TEXT2TEXT_MODALITY).await?;
+        db::add_model_echelon(
+            context,
+            model_name,
+            ECHELON,
+            FEE_PER_TOKEN,
+            FEE_PER_TOKEN,
+            RELATIVE_PERFORMANCE,
+        )
+        .await?;
+        info!("Created model {model_name}");
+        context.progress("models", i + 1, model_names.len());
+    }
+
+    let mut task_fixtures = vec![];
+    for i in 0..tasks {
+        let model_name = &model_names[(i % models) as usize];
+        let digest = db::create_task_entry(
+            context,
+            TASK_ROLE_CHAT_COMPLETION,
+            Some(model_name.clone()),
+            security_level,
+            None,
+            true,
+            Default::default(),
+        )
+        .await?;
+        let event =
+            single_event(context, digest, "TaskRegisteredEvent").await?;
+        let task_small_id = parse_u64(&event["task_small_id"]["inner"])?;
+        info!("Created task {task_small_id} for model {model_name}");
+        task_fixtures.push((task_small_id, model_name));
+        context.progress("tasks", task_fixtures.len(), tasks as usize);
+    }
+
+    // 2.
+    let keypairs: Vec<_> = (0..nodes)
+        .map(|i| node_keypair(seed, i))
+        .collect::<Result<_>>()?;
+    let addresses: Vec<_> = keypairs
+        .iter()
+        .map(|keypair| SuiAddress::from(&keypair.public()))
+        .collect();
+    fund_nodes(context, &addresses, gas_per_node).await?;
+    context.progress("fund", 1, 1);
+
+    // 3.
+    let mut node_fixtures = vec![];
+    for (i, (keypair, address)) in keypairs.iter().zip(&addresses).enumerate() {
+        let model_name = &model_names[i % models as usize];
+
+        let tx = node_move_call(
+            context,
+            *address,
+            "register_node_entry",
+            vec![SuiJsonValue::from_object_id(atoma_db)],
+        )
+        .await?;
+        let digest = execute_as(context, keypair, tx).await?;
+        let event =
+            single_event(context, digest, "NodeRegisteredEvent").await?;
+        let node_badge = ObjectID::from_str(
+            event["badge_id"]
+                .as_str()
+                .ok_or_else(|| anyhow!("No badge ID in {event}"))?,
+        )?;
+        let node_small_id = parse_u64(&event["node_small_id"]["inner"])?;
+
+        let tx = node_move_call(
+            context,
+            *address,
+            "add_node_to_model",
+            vec![
+                SuiJsonValue::from_object_id(atoma_db),
+                SuiJsonValue::from_object_id(node_badge),
+                SuiJsonValue::new(model_name.as_str().into())?,
+                SuiJsonValue::new(ECHELON.to_string().into())?,
+            ],
+        )
+        .await?;
+        execute_as(context, keypair, tx).await?;
+
+        let mut subscribed_tasks = vec![];
+        for (task_small_id, _) in task_fixtures
+            .iter()
+            .filter(|(_, task_model)| *task_model == model_name)
+        {
+            let tx = node_move_call(
+                context,
+                *address,
+                "subscribe_node_to_task",
+                vec![
+                    SuiJsonValue::from_object_id(atoma_db),
+                    SuiJsonValue::from_object_id(node_badge),
+                    SuiJsonValue::new(task_small_id.to_string().into())?,
+                    SuiJsonValue::new(
+                        PRICE_PER_ONE_MILLION_COMPUTE_UNITS.to_string().into(),
+                    )?,
+                ],
+            )
+            .await?;
+            execute_as(context, keypair, tx).await?;
+            subscribed_tasks.push(task_small_id.to_string());
+        }
+
+        info!("Registered node {node_small_id} at {address}");
+        node_fixtures.push(json!({
+            "address": address.to_string(),
+            "private_key": keypair.encode_base64(),
+            "node_badge": node_badge.to_string(),
+            "node_small_id": node_small_id.to_string(),
+            "model": model_name,
+            "echelon": ECHELON.to_string(),
+            "tasks": subscribed_tasks,
+        }));
+        context.progress("nodes", node_fixtures.len(), keypairs.len());
+    }
+
+    // 4.
+    let mut stack_fixtures = vec![];
+    for i in 0..stacks {
+        let (task_small_id, _) = task_fixtures[(i % tasks) as usize];
+        let digest = db::acquire_new_stack_entry(
+            context,
+            task_small_id,
+            STACK_COMPUTE_UNITS,
+            PRICE_PER_ONE_MILLION_COMPUTE_UNITS,
+            None,
+            0,
+        )
+        .await?;
+        let event = single_event(context, digest, "StackCreatedEvent").await?;
+        let stack_small_id = parse_u64(&event["stack_small_id"]["inner"])?;
+        info!("Acquired stack {stack_small_id} for task {task_small_id}");
+        stack_fixtures.push(json!({
+            "stack_id": event["stack_id"],
+            "stack_small_id": stack_small_id.to_string(),
+            "task_small_id": task_small_id.to_string(),
+            "selected_node_id": event["selected_node_id"]["inner"],
+            "num_compute_units": STACK_COMPUTE_UNITS.to_string(),
+            "price_per_one_million_compute_units":
+                PRICE_PER_ONE_MILLION_COMPUTE_UNITS.to_string(),
+        }));
+        context.progress("stacks", stack_fixtures.len(), stacks as usize);
+    }
+
+    // 5.
+    let manifest = json!({
+        "seed": seed.to_string(),
+        "security_level": security_level,
+        "atoma_package": atoma_package.to_string(),
+        "atoma_db": atoma_db.to_string(),
+        "models": model_names.iter().map(|model_name| json!({
+            "name": model_name,
+            "echelon": ECHELON.to_string(),
+        })).collect::<Vec<_>>(),
+        "tasks": task_fixtures.iter().map(|(task_small_id, model_name)| json!({
+            "task_small_id": task_small_id.to_string(),
+            "model": model_name,
+        })).collect::<Vec<_>>(),
+        "nodes": node_fixtures,
+        "stacks": stack_fixtures,
+    });
+    std::fs::write(out, serde_json::to_string_pretty(&manifest)?)?;
+    println!("Written fixture manifest to {out:?}");
+
+    Ok(())
+}
+
+/// The keypair of the `index`-th node is derived from
+/// `blake2b256(KEYPAIR_DOMAIN || seed || index)`, numbers little-endian.
+fn node_keypair(seed: u64, index: u64) -> Result<SuiKeyPair> {
+    let mut preimage = KEYPAIR_DOMAIN.to_vec();
+    preimage.extend_from_slice(&seed.to_le_bytes());
+    preimage.extend_from_slice(&index.to_le_bytes());
+    let secret = Blake2b256::digest(&preimage).digest;
+
+    let private_key = Ed25519PrivateKey::from_bytes(&secret)
+        .map_err(|err| anyhow!("Invalid node private key: {err}"))?;
+    Ok(SuiKeyPair::Ed25519(Ed25519KeyPair::from(private_key)))
+}
+
+/// Sends each node `gas_per_node` MIST from the active address.
+async fn fund_nodes(
+    context: &mut Context,
+    nodes: &[SuiAddress],
+    gas_per_node: u64,
+) -> Result<()> {
+    let active_address = context.wallet.active_address()?;
+    let client = context.get_client().await?;
+
+    let total = gas_per_node * nodes.len() as u64 + context.gas_budget();
+    let coins = client
+        .coin_read_api()
+        .select_coins(active_address, None, total as u128, vec![])
+        .await?;
+
+    let tx = client
+        .transaction_builder()
+        .pay_sui(
+            active_address,
+            coins.into_iter().map(|coin| coin.coin_object_id).collect(),
+            nodes.to_vec(),
+            vec![gas_per_node; nodes.len()],
+            context.gas_budget(),
+        )
+        .await?;
+
+    let tx = context.sign_transaction(&tx)?;
+    let resp = context.execute_transaction_must_succeed(tx).await?;
+    info!("Funded {} node(s) in {}", nodes.len(), resp.digest);
+
+    Ok(())
+}
@@ -0,0 +1,99 @@
+//! Transactions sent by fixture nodes, whose keys are not in the wallet.
+
+use sui_sdk::{
+    rpc_types::{
+        SuiExecutionStatus, SuiTransactionBlockEffectsAPI,
+        SuiTransactionBlockResponseOptions,
+    },
+    types::{
+        base_types::SuiAddress,
+        crypto::SuiKeyPair,
+        transaction::{Transaction, TransactionData},
+    },
+};
+
+use crate::{prelude::*, DB_MODULE_NAME};
+
+/// Builds a db call sent by the given node.
+pub(crate) async fn node_move_call(
+    context: &mut Context,
+    node: SuiAddress,
+    endpoint_name: &str,
+    args: Vec<SuiJsonValue>,
+) -> Result<TransactionData> {
+    let atoma_package = context.unwrap_atoma_package_id();
+
+    context
+        .get_client()
+        .await?
+        .transaction_builder()
+        .move_call(
+            node,
+            atoma_package,
+            DB_MODULE_NAME,
+            endpoint_name,
+            vec![],
+            args,
+            None,
+            context.gas_budget(),
+            None,
+        )
+        .await
+}
+
+/// The synthetic nodes are not in the wallet's keystore, so we sign with
+/// their keypairs directly.
+pub(crate) async fn execute_as(
+    context: &Context,
+    keypair: &SuiKeyPair,
+    tx: TransactionData,
+) -> Result<TransactionDigest> {
+    let tx = Transaction::from_data_and_signer(tx, vec![keypair]);
+    let resp = context.execute_transaction_may_fail(tx).await?;
+    if let SuiExecutionStatus::Failure { error } =
+        resp.effects.as_ref().unwrap().status()
+    {
+        anyhow::bail!("Transaction {} failed: {error}", resp.digest);
+    }
+    Ok(resp.digest)
+}
+
+/// Returns the fields of the only db event of the given name emitted by the
+/// transaction.
+pub(crate) async fn single_event(
+    context: &Context,
+    digest: TransactionDigest,
+    event_name: &str,
+) -> Result<serde_json::Value> {
+    let mut events: Vec<_> = context
+        .get_client()
+        .await?
+        .read_api()
+        .get_transaction_with_options(
+            digest,
+            SuiTransactionBlockResponseOptions::new().with_events(),
+        )
+        .await?
+        .events
+        .map(|events| events.data)
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|event| {
+            event.type_.module.as_str() == DB_MODULE_NAME
+                && event.type_.name.as_str() == event_name
+        })
+        .map(|event| event.parsed_json)
+        .collect();
+
+    match events.len() {
+        1 => Ok(events.remove(0)),
+        n => Err(anyhow!("Expected one {event_name} in {digest}, found {n}")),
+    }
+}
+
+pub(crate) fn parse_u64(value: &serde_json::Value) -> Result<u64> {
+    value
+        .as_str()
+        .and_then(|s| s.parse().ok())
+        .ok_or_else(|| anyhow!("Expected a u64 string, got {value}"))
+}
@@ -1,8 +1,18 @@
 //! Commands related to the gate module.
 //! Mainly concerned with submitting prompts.
 
+mod cancel_prompt;
+mod preview_sampling;
+mod priority;
+mod resubmit;
 mod submit_generate_nft_prompt;
 mod submit_tell_me_a_joke_prompt;
+mod text2text_options;
 
+pub(crate) use cancel_prompt::command as cancel_prompt;
+pub(crate) use preview_sampling::command as preview_sampling;
+pub(crate) use priority::Priority;
+pub(crate) use resubmit::{command as resubmit, prompt_event};
 pub(crate) use submit_generate_nft_prompt::command as submit_generate_nft_prompt;
 pub(crate) use submit_tell_me_a_joke_prompt::command as submit_tell_me_a_joke_prompt;
+pub(crate) use text2text_options::Text2TextOptions;
@@ -0,0 +1,134 @@
+use sui_sdk::{
+    rpc_types::{
+        Page, SuiData, SuiExecutionStatus, SuiObjectDataOptions,
+        SuiTransactionBlockEffectsAPI,
+    },
+    types::base_types::ObjectID,
+};
+
+use crate::{prelude::*, SETTLEMENT_MODULE_NAME};
+
+const ENDPOINT_NAME: &str = "cancel_prompt";
+/// 312012_200 + 9 is the error code as per the contract
+const TICKET_NOT_CANCELLABLE_ERROR: &str =
+    "function_name: Some(\"cancel_prompt\") }, 312012209)";
+
+/// 1. Go through all open tickets and find those that were paid by the active
+///    address and that not every node has committed to yet.
+/// 2. Try to cancel each of them, skipping those that have not timed out yet.
+///    The nodes that committed are paid, the others are slashed.
+pub(crate) async fn command(
+    context: &mut Context,
+) -> Result<Vec<TransactionDigest>> {
+    let sender = context.sender()?;
+    let atoma_package = context.unwrap_atoma_package_id();
+    let atoma_db = context.get_or_load_atoma_db().await?;
+    let tickets_root = ObjectID::from_str(
+        context.load_atoma_db_fields().await?["tickets"]["id"]
+            .as_str()
+            .ok_or_else(|| anyhow!("No tickets field found"))?,
+    )?;
+
+    // 1.
+    let mut stale_tickets = vec![];
+    let mut cursor = None;
+    let client = context.get_client().await?;
+    loop {
+        let Page {
+            data,
+            has_next_page,
+            next_cursor,
+        } = client
+            .read_api()
+            .get_dynamic_fields(tickets_root, cursor, None)
+            .await?;
+        cursor = next_cursor;
+
+        let tickets_page = data.iter().map(|info| info.object_id).collect();
+        let tickets = client
+            .read_api()
+            .multi_get_object_with_options(
+                tickets_page,
+                SuiObjectDataOptions {
+                    show_content: true,
+                    ..Default::default()
+                },
+            )
+            .await?
+            .into_iter()
+            // ignore tickets that have been deleted between the calls
+            .filter_map(|ticket| {
+                Some(
+                    ticket
+                        .data?
+                        .content?
+                        .try_as_move()
+                        .cloned()?
+                        .fields
+                        .to_json_value(),
+                )
+            });
+
+        for ticket in tickets {
+            let is_payer =
+                ticket["payer"].as_str() == Some(sender.to_string().as_str());
+            let is_being_disputed =
+                ticket["is_being_disputed"].as_bool().unwrap();
+            let all_count = ticket["all"].as_array().unwrap().len();
+            let completed_count = ticket["completed"].as_array().unwrap().len();
+
+            if is_payer && completed_count < all_count && !is_being_disputed {
+                let id = ticket["id"]["id"].as_str().unwrap();
+                stale_tickets.push(ObjectID::from_str(id)?);
+            }
+        }
+
+        if !has_next_page {
+            break;
+        }
+    }
+    info!(
+        "Found {} ticket(s) with missing commitments",
+        stale_tickets.len()
+    );
+
+    // 2.
+    let mut digests = vec![];
+    for ticket_id in stale_tickets {
+        let tx = context
+            .get_client()
+            .await?
+            .transaction_builder()
+            .move_call(
+                sender,
+                atoma_package,
+                SETTLEMENT_MODULE_NAME,
+                ENDPOINT_NAME,
+                vec![],
+                vec![
+                    SuiJsonValue::from_object_id(atoma_db),
+                    SuiJsonValue::from_object_id(ticket_id),
+                ],
+                None,
+                context.gas_budget(),
+                None,
+            )
+            .await?;
+
+        let tx = context.sign_transaction(&tx)?;
+        let resp = context.execute_transaction_may_fail(tx).await?;
+        if let SuiExecutionStatus::Failure { error } =
+            resp.effects.as_ref().unwrap().status()
+        {
+            if error.contains(TICKET_NOT_CANCELLABLE_ERROR) {
+                info!("Ticket {ticket_id} has not timed out yet, skipping");
+            } else {
+                error!("Failed to cancel ticket {ticket_id}: {error}");
+            }
+        } else {
+            digests.push(resp.digest);
+        }
+    }
+
+    Ok(digests)
+}
@@ -0,0 +1,125 @@
+use serde_json::json;
+use sui_sdk::{
+    rpc_types::SuiData,
+    types::{dynamic_field::DynamicFieldName, TypeTag},
+};
+
+use super::Priority;
+use crate::{db, prelude::*, sampling};
+
+/// 1. Filter the echelons of the model like the gate does.
+/// 2. Pick the echelon with the given random number.
+/// 3. Pick a node from each chunk of the echelon with the given node random
+///    numbers, if any.
+///
+/// The prediction assumes that none of the picked nodes are disabled,
+/// otherwise the contract removes them and samples again.
+pub(crate) async fn command(
+    context: &mut Context,
+    model_name: &str,
+    random: &str,
+    node_randoms: &[u64],
+    nodes_to_sample: u64,
+    max_fee_per_token: u64,
+    priority: Priority,
+    prefer_low_latency: bool,
+) -> Result<()> {
+    let random = parse_u256(random)?;
+    let (_, echelons) = db::load_model(context, model_name).await?;
+
+    // 1.
+    let multiplier = priority.fee_multiplier_percent();
+    let eligible: Vec<_> = echelons
+        .iter()
+        .filter(|echelon| {
+            echelon.input_fee_per_token * multiplier / 100 <= max_fee_per_token
+                && echelon.output_fee_per_token * multiplier / 100
+                    <= max_fee_per_token
+                && echelon.nodes_count >= nodes_to_sample
+        })
+        .collect();
+    if eligible.is_empty() {
+        anyhow::bail!(
+            "No echelon of {model_name} charges at most {} \
+            per token with priority {priority:?} and has \
+            {nodes_to_sample} node(s)",
+            context.toma(max_fee_per_token)
+        );
+    }
+
+    // 2.
+    let weights = eligible
+        .iter()
+        .map(|echelon| {
+            echelon.selection_weight(priority.as_u8(), prefer_low_latency)
+        })
+        .collect::<Result<Vec<_>>>()?;
+    let echelon = eligible[sampling::pick_weighted_index(&weights, &random)?];
+    println!(
+        "Echelon {} out of {} eligible, {} node(s)",
+        echelon.id,
+        eligible.len(),
+        echelon.nodes_count
+    );
+
+    // 3.
+    let chunks_count =
+        sampling::chunks_count(echelon.nodes_count, nodes_to_sample);
+    if node_randoms.is_empty() {
+        return Ok(());
+    }
+    if node_randoms.len() as u64 != chunks_count {
+        anyhow::bail!(
+            "Expected {chunks_count} node random number(s), one per chunk"
+        );
+    }
+    let client = context.get_client().await?;
+    for (chunk, node_random) in node_randoms.iter().enumerate() {
+        let index = sampling::unique_sample_node_index(
+            echelon.nodes_count,
+            nodes_to_sample,
+            chunk as u64,
+            *node_random,
+        );
+        let node_small_id = client
+            .read_api()
+            .get_dynamic_field_object(
+                echelon.nodes_table,
+                DynamicFieldName {
+                    type_: TypeTag::U64,
+                    value: json!(index.to_string()),
+                },
+            )
+            .await?
+            .data
+            .and_then(|data| data.content)
+            .and_then(|content| content.try_into_move())
+            .map(|object| object.fields.to_json_value())
+            .and_then(|field| {
+                field["value"]["inner"].as_str().map(String::from)
+            })
+            .ok_or_else(|| anyhow!("No node at index {index}"))?;
+        println!("Chunk {chunk}: node {node_small_id} at index {index}");
+    }
+
+    Ok(())
+}
+
+/// Hex, optionally 0x prefixed, of at most 32 bytes.
+fn parse_u256(random: &str) -> Result<[u8; 32]> {
+    let hex = random.trim_start_matches("0x");
+    if hex.is_empty()
+        || hex.len() > 64
+        || !hex.chars().all(|c| c.is_ascii_hexdigit())
+    {
+        anyhow::bail!("{random} is not a hex u256");
+    }
+
+    let padded = format!("{hex:0>64}");
+    let mut bytes = [0u8; 32];
+    for (i, byte) in bytes.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&padded[i * 2..i * 2 + 2], 16)?;
+    }
+
+    Ok(bytes)
+}
@@ -0,0 +1,46 @@
+use clap::ValueEnum;
+
+use crate::prelude::*;
+
+/// Must match the priority levels in the gate module.
+#[derive(Clone, Copy, Debug, Default, ValueEnum)]
+pub(crate) enum Priority {
+    #[default]
+    Normal,
+    High,
+    Urgent,
+}
+
+impl Priority {
+    pub(crate) fn as_u8(self) -> u8 {
+        match self {
+            Self::Normal => 0,
+            Self::High => 1,
+            Self::Urgent => 2,
+        }
+    }
+
+    /// Must match `gate::priority_fee_multiplier_percent`.
+    pub(crate) fn fee_multiplier_percent(self) -> u64 {
+        match self {
+            Self::Normal => 100,
+            Self::High => 150,
+            Self::Urgent => 200,
+        }
+    }
+
+    /// The max fee is compared against the echelon fee with the premium, so
+    /// a higher priority narrows down the echelons the prompt can go to.
+    pub(crate) fn print_cost_impact(
+        self,
+        context: &Context,
+        max_fee_per_token: u64,
+    ) {
+        let multiplier = self.fee_multiplier_percent();
+        println!(
+            "Priority {self:?}: paying {multiplier}% of the echelon fees, \
+            eligible echelons charge at most {} per token",
+            context.toma(max_fee_per_token * 100 / multiplier)
+        );
+    }
+}
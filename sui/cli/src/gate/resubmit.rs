@@ -0,0 +1,254 @@
+//! Resubmits a text to text prompt whose ticket timed out or was refunded.
+//!
+//! The ticket is deleted once it's settled or cancelled, so the parameters
+//! are read from the `Text2TextPromptEvent` it was created with, and the
+//! priority from the `PromptPreferencesEvent` emitted with it.
+//!
+//! New tickets are linked to the ones they replace in a local JSON array of
+//! entries:
+//! ```json
+//! {
+//!   "old_ticket_id": "0x...",
+//!   "new_ticket_id": "0x...",
+//!   "digest": "...",
+//!   "resubmitted_at_ms": 1700000000000
+//! }
+//! ```
+
+use std::{
+    path::Path,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use move_core_types::language_storage::StructTag;
+use serde_json::{json, Value};
+use sui_sdk::{
+    rpc_types::{EventFilter, Page, SuiData, SuiObjectDataOptions},
+    types::{base_types::ObjectID, SUI_RANDOMNESS_STATE_OBJECT_ID},
+};
+
+use crate::{prelude::*, GATE_MODULE_NAME, PROMPTS_MODULE_NAME};
+
+const ENDPOINT_NAME: &str = "send_prompt";
+const PROMPT_EVENT_NAME: &str = "Text2TextPromptEvent";
+const PREFERENCES_EVENT_NAME: &str = "PromptPreferencesEvent";
+
+/// 1. Find the events the ticket was created with.
+/// 2. Take the fee cap from the arguments, or from the fees the ticket
+///    charges if it still exists, and bump it.
+/// 3. Submit the same parameters with the same priority and latency
+///    preference.
+/// 4. Link the new ticket to the old one.
+///
+/// Returns the ID of the new ticket.
+pub(crate) async fn command(
+    context: &mut Context,
+    ticket_id: &str,
+    max_fee_per_token: Option<u64>,
+    fee_bump_percent: u64,
+    links_file: &Path,
+) -> Result<ObjectID> {
+    let ticket_id = ObjectID::from_str(ticket_id)?;
+    let sender = context.sender()?;
+    let atoma_package = context.unwrap_atoma_package_id();
+    let atoma_db = context.get_or_load_atoma_db().await?;
+    let toma_wallet = context.get_or_load_toma_wallet().await?;
+
+    // 1.
+    let event = prompt_event(context, ticket_id).await?;
+    let params = &event["params"];
+    // prompts submitted before the event existed had normal priority and no
+    // latency preference
+    let preferences =
+        ticket_event(context, PREFERENCES_EVENT_NAME, ticket_id).await?;
+    let priority = preferences
+        .as_ref()
+        .map_or(0.into(), |preferences| preferences["priority"].clone());
+    let prefer_low_latency = preferences
+        .as_ref()
+        .and_then(|preferences| preferences["prefer_low_latency"].as_bool())
+        .unwrap_or(false);
+
+    // 2.
+    let max_fee_per_token = match max_fee_per_token {
+        Some(max_fee_per_token) => max_fee_per_token,
+        None => ticket_fee_per_token(context, ticket_id).await?,
+    };
+    let max_fee_per_token = max_fee_per_token * (100 + fee_bump_percent) / 100;
+    println!(
+        "Resubmitting ticket {ticket_id} of model {} \
+        with max fee per token {}",
+        params["model"],
+        context.toma(max_fee_per_token)
+    );
+
+    // 3.
+    context.ensure_randomness_is_live().await?;
+
+    let tx = context
+        .get_client()
+        .await?
+        .transaction_builder()
+        .move_call(
+            sender,
+            atoma_package,
+            PROMPTS_MODULE_NAME,
+            ENDPOINT_NAME,
+            vec![],
+            vec![
+                SuiJsonValue::from_object_id(atoma_db),
+                SuiJsonValue::from_object_id(toma_wallet),
+                SuiJsonValue::new(params["model"].clone())?,
+                SuiJsonValue::new(event["output_destination"].clone())?,
+                SuiJsonValue::new(params["pre_prompt_tokens"].clone())?,
+                SuiJsonValue::new(params["prepend_output_with_input"].clone())?,
+                SuiJsonValue::new(max_fee_per_token.to_string().into())?,
+                SuiJsonValue::new(params["prompt"].clone())?,
+                SuiJsonValue::new(params["should_stream_output"].clone())?,
+                SuiJsonValue::new(params["max_tokens"].clone())?,
+                SuiJsonValue::new(params["repeat_last_n"].clone())?,
+                SuiJsonValue::new(params["repeat_penalty"].clone())?,
+                SuiJsonValue::new(params["temperature"].clone())?,
+                SuiJsonValue::new(params["top_k"].clone())?,
+                SuiJsonValue::new(params["top_p"].clone())?,
+                // let the network decide how many nodes to sample
+                SuiJsonValue::new(json!([]))?,
+                SuiJsonValue::new(priority)?,
+                SuiJsonValue::new(prefer_low_latency.into())?,
+                SuiJsonValue::from_object_id(SUI_RANDOMNESS_STATE_OBJECT_ID),
+            ],
+            None,
+            context.gas_budget(),
+            None,
+        )
+        .await?;
+
+    let tx = context.sign_transaction(&tx)?;
+    let resp = context.execute_transaction_must_succeed(tx).await?;
+    let new_ticket_id = resp
+        .events
+        .iter()
+        .flat_map(|events| &events.data)
+        .find(|event| event.type_.name.as_str() == PROMPT_EVENT_NAME)
+        .and_then(|event| event.parsed_json["ticket_id"].as_str())
+        .map(ObjectID::from_str)
+        .transpose()?
+        .ok_or_else(|| anyhow!("No {PROMPT_EVENT_NAME} in {}", resp.digest))?;
+
+    // 4.
+    let mut links = if links_file.exists() {
+        match serde_json::from_slice(&std::fs::read(links_file)?)? {
+            Value::Array(links) => links,
+            _ => anyhow::bail!("{links_file:?} is not a JSON array of entries"),
+        }
+    } else {
+        vec![]
+    };
+    links.push(json!({
+        "old_ticket_id": ticket_id.to_string(),
+        "new_ticket_id": new_ticket_id.to_string(),
+        "digest": resp.digest.to_string(),
+        "resubmitted_at_ms":
+            SystemTime::now().duration_since(UNIX_EPOCH)?.as_millis() as u64,
+    }));
+    std::fs::write(links_file, serde_json::to_vec_pretty(&links)?)?;
+    info!("Linked {ticket_id} to {new_ticket_id} in {links_file:?}");
+
+    Ok(new_ticket_id)
+}
+
+pub(crate) async fn prompt_event(
+    context: &mut Context,
+    ticket_id: ObjectID,
+) -> Result<Value> {
+    ticket_event(context, PROMPT_EVENT_NAME, ticket_id)
+        .await?
+        .ok_or_else(|| {
+            anyhow!(
+                "No {PROMPT_EVENT_NAME} for ticket {ticket_id}, \
+                only text to text prompts can be resubmitted"
+            )
+        })
+}
+
+/// Finds the gate event of the given name that mentions the ticket.
+///
+/// Events are scanned newest first since a ticket being resubmitted is
+/// usually recent.
+async fn ticket_event(
+    context: &mut Context,
+    event_name: &str,
+    ticket_id: ObjectID,
+) -> Result<Option<Value>> {
+    let filter = EventFilter::MoveEventType(StructTag {
+        address: context.unwrap_atoma_package_id().into(),
+        module: FromStr::from_str(GATE_MODULE_NAME)?,
+        name: FromStr::from_str(event_name)?,
+        type_params: vec![],
+    });
+    let ticket_id = ticket_id.to_string();
+
+    let client = context.get_client().await?;
+    let mut cursor = None;
+    loop {
+        let Page {
+            data,
+            has_next_page,
+            next_cursor,
+        } = client
+            .event_api()
+            .query_events(filter.clone(), cursor, None, true)
+            .await?;
+        cursor = next_cursor;
+
+        if let Some(event) = data.into_iter().find(|event| {
+            event.parsed_json["ticket_id"].as_str() == Some(ticket_id.as_str())
+        }) {
+            return Ok(Some(event.parsed_json));
+        }
+
+        if !has_next_page {
+            return Ok(None);
+        }
+    }
+}
+
+/// The higher of the input and output fee the ticket charges, which already
+/// includes the priority premium.
+async fn ticket_fee_per_token(
+    context: &mut Context,
+    ticket_id: ObjectID,
+) -> Result<u64> {
+    let ticket = context
+        .get_client()
+        .await?
+        .read_api()
+        .get_object_with_options(
+            ticket_id,
+            SuiObjectDataOptions {
+                show_content: true,
+                ..Default::default()
+            },
+        )
+        .await?
+        .data
+        .and_then(|data| data.content)
+        .and_then(|content| content.try_into_move())
+        .ok_or_else(|| {
+            anyhow!(
+                "Ticket {ticket_id} no longer exists, \
+                pass --max-fee-per-token"
+            )
+        })?
+        .fields
+        .to_json_value();
+
+    let fee = |name: &str| {
+        ticket[name]
+            .as_str()
+            .and_then(|fee| fee.parse::<u64>().ok())
+            .ok_or_else(|| anyhow!("Ticket {ticket_id} has no {name}"))
+    };
+
+    Ok(fee("input_fee_per_token")?.max(fee("output_fee_per_token")?))
+}
@@ -1,15 +1,20 @@
 use sui_sdk::types::SUI_RANDOMNESS_STATE_OBJECT_ID;
 
+use super::Priority;
 use crate::{prelude::*, PROMPTS_MODULE_NAME};
 
-const ENDPOINT_NAME: &str = "generate_nft";
+const ENDPOINT_NAME: &str = "send_image_prompt";
+const PROMPT: &str = "A pixel art Colosseum";
+const UNCOND_PROMPT: &str = "";
 
 pub(crate) async fn command(
     context: &mut Context,
     model_name: &str,
     max_fee_per_token: u64,
+    priority: Priority,
+    prefer_low_latency: bool,
 ) -> Result<TransactionDigest> {
-    let active_address = context.wallet.active_address()?;
+    let sender = context.sender()?;
     let atoma_package = context.unwrap_atoma_package_id();
     let atoma_db = context.get_or_load_atoma_db().await?;
     let toma_wallet = context.get_or_load_toma_wallet().await?;
@@ -17,12 +22,16 @@ pub(crate) async fn command(
     // TODO: agree with nodes on some output destination format
     let output_destination: Vec<u8> = vec![];
 
+    priority.print_cost_impact(context, max_fee_per_token);
+
+    context.ensure_randomness_is_live().await?;
+
     let tx = context
         .get_client()
         .await?
         .transaction_builder()
         .move_call(
-            active_address,
+            sender,
             atoma_package,
             PROMPTS_MODULE_NAME,
             ENDPOINT_NAME,
@@ -32,7 +41,14 @@ pub(crate) async fn command(
                 SuiJsonValue::from_object_id(toma_wallet),
                 SuiJsonValue::new(model_name.into())?,
                 SuiJsonValue::new(output_destination.into())?,
+                // max fee per input token
+                SuiJsonValue::new(max_fee_per_token.to_string().into())?,
+                // max fee per output pixel
                 SuiJsonValue::new(max_fee_per_token.to_string().into())?,
+                SuiJsonValue::new(PROMPT.into())?,
+                SuiJsonValue::new(UNCOND_PROMPT.into())?,
+                SuiJsonValue::new(priority.as_u8().into())?,
+                SuiJsonValue::new(prefer_low_latency.into())?,
                 SuiJsonValue::from_object_id(SUI_RANDOMNESS_STATE_OBJECT_ID),
             ],
             None,
@@ -41,7 +57,7 @@ pub(crate) async fn command(
         )
         .await?;
 
-    let tx = context.wallet.sign_transaction(&tx);
-    let resp = context.wallet.execute_transaction_must_succeed(tx).await;
+    let tx = context.sign_transaction(&tx)?;
+    let resp = context.execute_transaction_must_succeed(tx).await?;
     Ok(resp.digest)
 }
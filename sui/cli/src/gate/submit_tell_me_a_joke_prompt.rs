@@ -1,28 +1,52 @@
+use serde_json::json;
 use sui_sdk::types::SUI_RANDOMNESS_STATE_OBJECT_ID;
 
-use crate::{prelude::*, PROMPTS_MODULE_NAME};
+use super::{Priority, Text2TextOptions};
+use crate::{
+    prelude::*,
+    protocol_encoding::{self, FIREBASE_OUTPUT_DESTINATION},
+    PROMPTS_MODULE_NAME,
+};
 
-const ENDPOINT_NAME: &str = "tell_me_a_joke";
+const ENDPOINT_NAME: &str = "send_prompt";
+const PROMPT: &str = "Tell me a joke please";
+const MAX_TOKENS: u64 = 256;
 
+/// With `explain_fees`, only prints how the prompt would be charged.
 pub(crate) async fn command(
     context: &mut Context,
     model_name: &str,
     max_fee_per_token: u64,
-) -> Result<TransactionDigest> {
-    let active_address = context.wallet.active_address()?;
+    priority: Priority,
+    prefer_low_latency: bool,
+    options: Text2TextOptions,
+    explain_fees: bool,
+) -> Result<Option<TransactionDigest>> {
+    if explain_fees {
+        priority.print_cost_impact(context, max_fee_per_token);
+        options.explain_fees(context, PROMPT, MAX_TOKENS, max_fee_per_token);
+        return Ok(None);
+    }
+
+    let sender = context.sender()?;
     let atoma_package = context.unwrap_atoma_package_id();
     let atoma_db = context.get_or_load_atoma_db().await?;
     let toma_wallet = context.get_or_load_toma_wallet().await?;
 
-    // TODO: agree with nodes on some output destination format
-    let output_destination: Vec<u8> = rmp_serde::to_vec("Firebase")?;
+    let output_destination = protocol_encoding::encode_output_destination(
+        FIREBASE_OUTPUT_DESTINATION,
+    )?;
+
+    priority.print_cost_impact(context, max_fee_per_token);
+
+    context.ensure_randomness_is_live().await?;
 
     let tx = context
         .get_client()
         .await?
         .transaction_builder()
         .move_call(
-            active_address,
+            sender,
             atoma_package,
             PROMPTS_MODULE_NAME,
             ENDPOINT_NAME,
@@ -32,7 +56,25 @@ pub(crate) async fn command(
                 SuiJsonValue::from_object_id(toma_wallet),
                 SuiJsonValue::new(model_name.into())?,
                 SuiJsonValue::new(output_destination.into())?,
+                SuiJsonValue::new(json!(options.pre_prompt_tokens))?,
+                SuiJsonValue::new(options.prepend_output_with_input.into())?,
                 SuiJsonValue::new(max_fee_per_token.to_string().into())?,
+                SuiJsonValue::new(PROMPT.into())?,
+                // should stream output
+                SuiJsonValue::new(false.into())?,
+                SuiJsonValue::new(MAX_TOKENS.to_string().into())?,
+                // repeat last n
+                SuiJsonValue::new("64".into())?,
+                // floats are passed as their bits
+                SuiJsonValue::new(1.1_f32.to_bits().into())?,
+                SuiJsonValue::new(0.7_f32.to_bits().into())?,
+                // top k
+                SuiJsonValue::new("40".into())?,
+                SuiJsonValue::new(0.9_f32.to_bits().into())?,
+                // let the network decide how many nodes to sample
+                SuiJsonValue::new(json!([]))?,
+                SuiJsonValue::new(priority.as_u8().into())?,
+                SuiJsonValue::new(prefer_low_latency.into())?,
                 SuiJsonValue::from_object_id(SUI_RANDOMNESS_STATE_OBJECT_ID),
             ],
             None,
@@ -41,7 +83,7 @@ pub(crate) async fn command(
         )
         .await?;
 
-    let tx = context.wallet.sign_transaction(&tx);
-    let resp = context.wallet.execute_transaction_must_succeed(tx).await;
-    Ok(resp.digest)
+    let tx = context.sign_transaction(&tx)?;
+    let resp = context.execute_transaction_must_succeed(tx).await?;
+    Ok(Some(resp.digest))
 }
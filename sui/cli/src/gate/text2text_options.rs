@@ -0,0 +1,98 @@
+use std::path::Path;
+
+use serde_json::Value;
+
+use crate::prelude::*;
+
+/// How the prompt is presented to the model and echoed back, see
+/// `gate::Text2TextPromptParams`.
+#[derive(Debug, Default)]
+pub(crate) struct Text2TextOptions {
+    /// Token IDs of the model's tokenizer that precede the prompt.
+    pub(crate) pre_prompt_tokens: Vec<u32>,
+    /// Whether the output starts with the input.
+    pub(crate) prepend_output_with_input: bool,
+}
+
+impl Text2TextOptions {
+    /// The file is a JSON array of token IDs, eg. `[1, 518, 25580, 29962]`.
+    pub(crate) fn new(
+        pre_prompt_tokens_file: Option<&Path>,
+        prepend_output_with_input: bool,
+    ) -> Result<Self> {
+        let pre_prompt_tokens = match pre_prompt_tokens_file {
+            None => vec![],
+            Some(file) => {
+                let json: Value = serde_json::from_slice(&std::fs::read(file)?)
+                    .map_err(|err| anyhow!("{file:?} is not JSON: {err}"))?;
+                json.as_array()
+                    .ok_or_else(|| {
+                        anyhow!("{file:?} must be a JSON array of token IDs")
+                    })?
+                    .iter()
+                    .map(|token| {
+                        token
+                            .as_u64()
+                            .and_then(|token| u32::try_from(token).ok())
+                            .ok_or_else(|| {
+                                anyhow!(
+                                    "Token ID {token} in {file:?} is not a u32"
+                                )
+                            })
+                    })
+                    .collect::<Result<_>>()?
+            }
+        };
+
+        Ok(Self {
+            pre_prompt_tokens,
+            prepend_output_with_input,
+        })
+    }
+
+    /// Prints how the options affect what the prompt is charged.
+    ///
+    /// Must match `gate::submit_text2text_prompt_with_priority`.
+    pub(crate) fn explain_fees(
+        &self,
+        context: &Context,
+        prompt: &str,
+        max_tokens: u64,
+        max_fee_per_token: u64,
+    ) {
+        let pre_prompt_tokens = self.pre_prompt_tokens.len() as u64;
+        // the contract can't tokenize, so it counts a token per byte
+        let input_tokens = pre_prompt_tokens + prompt.len() as u64;
+
+        println!(
+            "Input tokens escrowed: {input_tokens} \
+            ({pre_prompt_tokens} pre-prompt tokens + {} prompt bytes)",
+            prompt.len()
+        );
+        println!("Output tokens escrowed: {max_tokens} (max tokens)");
+        println!(
+            "Escrow per sampled node: at most {} \
+            ({} max fee per token), \
+            plus the amortized cross validation fee",
+            context.toma(max_fee_per_token * (input_tokens + max_tokens)),
+            context.toma(max_fee_per_token)
+        );
+        println!(
+            "At settlement the nodes agree on the actual token counts and \
+            the difference to the escrow is refunded."
+        );
+        if self.prepend_output_with_input {
+            println!(
+                "Output is prepended with input: the echoed pre-prompt and \
+                prompt are part of the output, so they are counted as output \
+                tokens on top of being counted as input tokens, and they use \
+                up part of the {max_tokens} max tokens."
+            );
+        } else {
+            println!(
+                "Output is not prepended with input: only generated tokens \
+                are counted as output tokens."
+            );
+        }
+    }
+}
@@ -0,0 +1,248 @@
+//! Interactive onboarding of a new node operator.
+//!
+//! Every step checks whether it's already done, eg. whether the active
+//! address owns a node badge, so the wizard can be run again after it was
+//! interrupted.
+
+use std::{
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+use serde_json::json;
+use sui_sdk::types::base_types::ObjectID;
+
+use crate::{
+    db, denomination,
+    dotenv_conf::{
+        ATOMA_DB_ID, ATOMA_PACKAGE_ID, NODE_BADGE_ID, NODE_ID, TOMA_PACKAGE_ID,
+        TOMA_WALLET_ID, WALLET_PATH,
+    },
+    prelude::*,
+    toma,
+};
+
+/// Envs whose faucets hand out SUI for gas.
+const SUI_FAUCETS: &[(&str, &str)] = &[
+    ("devnet", "https://faucet.devnet.sui.io/gas"),
+    ("localnet", "http://127.0.0.1:9123/gas"),
+];
+const DEFAULT_TOMA_AMOUNT: u64 = 1_000_000;
+const DEFAULT_PRICE_PER_ONE_MILLION_COMPUTE_UNITS: u64 = 1_000;
+
+/// Runs before the wallet is loaded.
+/// If there's no wallet at the path, offers to create one with the `sui`
+/// binary, which asks for the full node to connect to and generates a key.
+pub(crate) fn ensure_wallet(wallet_path: &Path) -> Result<()> {
+    if wallet_path.exists() {
+        println!("Using the wallet at {wallet_path:?}");
+        return Ok(());
+    }
+
+    if !confirm(&format!(
+        "No wallet at {wallet_path:?}, create one with the sui CLI?"
+    ))? {
+        anyhow::bail!("A wallet is required, pass one with --wallet");
+    }
+    let status = std::process::Command::new("sui")
+        .arg("client")
+        .arg("--client.config")
+        .arg(wallet_path)
+        .arg("active-address")
+        .status()
+        .map_err(|err| {
+            anyhow!("Cannot run the sui CLI, is it installed? {err}")
+        })?;
+    if !status.success() || !wallet_path.exists() {
+        anyhow::bail!("The sui CLI did not create a wallet at {wallet_path:?}");
+    }
+
+    Ok(())
+}
+
+/// 1. Pick the Atoma package.
+/// 2. Fund the active address with SUI and TOMA on devnet and localnet.
+/// 3. Register a node.
+/// 4. Join a model in the recommended echelon.
+/// 5. Subscribe to a first task.
+/// 6. Write the IDs into the env file of the active env.
+pub(crate) async fn command(
+    context: Context,
+    package: Option<String>,
+) -> Result<()> {
+    let active_address = context.wallet.active_address()?;
+    let active_env = context.wallet.config.active_env.clone();
+    println!("Active address: {active_address}");
+    println!("Active env: {}", active_env.as_deref().unwrap_or("-"));
+
+    // 1.
+    let package = match package
+        .or_else(|| context.conf.atoma_package_id.map(|p| p.to_string()))
+    {
+        Some(package) => package,
+        None => ask("Atoma package ID", None)?,
+    };
+    let context = &mut context.with_optional_atoma_package_id(Some(package));
+    let atoma_db = context.get_or_load_atoma_db().await?;
+    println!("Atoma db: {atoma_db}");
+
+    // 2.
+    let faucet_url = active_env.as_deref().and_then(|env| {
+        SUI_FAUCETS
+            .iter()
+            .find(|(name, _)| *name == env)
+            .map(|(_, url)| *url)
+    });
+    let sui_balance = context
+        .get_client()
+        .await?
+        .coin_read_api()
+        .get_balance(active_address, None)
+        .await?
+        .total_balance;
+    println!("SUI balance: {sui_balance} MIST");
+    if let Some(faucet_url) = faucet_url {
+        if sui_balance == 0 && confirm("Request SUI from the faucet?")? {
+            reqwest::Client::new()
+                .post(faucet_url)
+                .json(&json!({
+                    "FixedAmountRequest": {
+                        "recipient": active_address.to_string(),
+                    },
+                }))
+                .send()
+                .await?
+                .error_for_status()?;
+            println!("Requested SUI, it can take a few seconds to arrive");
+        }
+        if context.get_or_load_toma_wallet().await.is_err()
+            && confirm("Mint TOMA from the faucet?")?
+        {
+            let amount = denomination::parse_toma(&ask(
+                "Amount of TOMA",
+                Some(&context.toma(DEFAULT_TOMA_AMOUNT)),
+            )?)?;
+            let digest = toma::faucet(context, amount).await?;
+            println!("Minted {} in {digest}", context.toma(amount));
+        }
+    } else if sui_balance == 0 {
+        println!("The address has no SUI to pay for gas, fund it first");
+    }
+
+    // 3.
+    let node_small_id = match context.get_or_load_node_badge().await {
+        Ok((node_badge, node_small_id)) => {
+            println!("Node {node_small_id} already registered ({node_badge})");
+            node_small_id
+        }
+        Err(_) => {
+            if !confirm("Register a node?")? {
+                return write_env(context, active_env.as_deref());
+            }
+            let digest = db::register_node(context).await?;
+            println!("Registered in {digest}");
+            context.get_or_load_node_badge().await?.1
+        }
+    };
+
+    // 4.
+    if confirm(&format!("Add node {node_small_id} to a model?"))? {
+        let model = ask("Model name", None)?;
+        db::list_echelons(context, &model).await?;
+        let recommended = db::recommended_echelon(context, &model).await?;
+        if let Some(echelon) = recommended {
+            println!(
+                "Echelon {echelon} pays the most per node, \
+                join it if your hardware matches it"
+            );
+        }
+        let echelon = ask(
+            "Echelon ID",
+            recommended.map(|echelon| echelon.to_string()).as_deref(),
+        )?
+        .parse()?;
+        let digest = db::add_node_to_model(context, &model, echelon).await?;
+        println!("Joined echelon {echelon} of {model} in {digest}");
+    }
+
+    // 5.
+    if confirm("Subscribe to a task?")? {
+        db::list_tasks(context).await?;
+        let task_small_id = ask("Task small ID", None)?.parse()?;
+        let price = ask(
+            "Price per one million compute units",
+            Some(&DEFAULT_PRICE_PER_ONE_MILLION_COMPUTE_UNITS.to_string()),
+        )?
+        .parse()?;
+        let digest =
+            db::subscribe_node_to_task(context, task_small_id, price).await?;
+        println!("Subscribed to task {task_small_id} in {digest}");
+    }
+
+    // 6.
+    write_env(context, active_env.as_deref())
+}
+
+/// The env file of the active env is loaded on every run, so the other
+/// commands pick up the configuration.
+fn write_env(context: &Context, active_env: Option<&str>) -> Result<()> {
+    let env_file = match active_env {
+        Some(active_env) => PathBuf::from(format!(".env.{active_env}")),
+        None => PathBuf::from(".env"),
+    };
+    let id = |id: Option<ObjectID>| id.map(|id| id.to_string());
+    let updates = [
+        (
+            WALLET_PATH,
+            Some(context.unwrap_wallet_path().display().to_string()),
+        ),
+        (ATOMA_PACKAGE_ID, id(context.conf.atoma_package_id)),
+        (TOMA_PACKAGE_ID, id(context.conf.toma_package_id)),
+        (ATOMA_DB_ID, id(context.conf.atoma_db_id)),
+        (NODE_BADGE_ID, id(context.conf.node_badge_id)),
+        (NODE_ID, context.conf.node_id.map(|id| id.to_string())),
+        (TOMA_WALLET_ID, id(context.conf.toma_wallet_id)),
+    ];
+    // don't empty what we didn't learn
+    let updates: Vec<_> = updates
+        .into_iter()
+        .filter(|(_, value)| value.is_some())
+        .collect();
+    db::rewrite_env_file(&env_file, &updates)?;
+    println!("Configuration written to {env_file:?}");
+
+    Ok(())
+}
+
+/// Reads a line from stdin, falling back to the default on an empty line.
+fn ask(question: &str, default: Option<&str>) -> Result<String> {
+    loop {
+        match default {
+            Some(default) => print!("{question} [{default}]: "),
+            None => print!("{question}: "),
+        }
+        std::io::stdout().flush()?;
+
+        let mut answer = String::new();
+        if std::io::stdin().read_line(&mut answer)? == 0 {
+            anyhow::bail!("stdin is closed");
+        }
+        match (answer.trim(), default) {
+            ("", Some(default)) => return Ok(default.to_owned()),
+            ("", None) => continue,
+            (answer, _) => return Ok(answer.to_owned()),
+        }
+    }
+}
+
+/// Line based unlike [`crate::wait_for_user_confirm`] so that it can be
+/// mixed with [`ask`].
+fn confirm(question: &str) -> Result<bool> {
+    loop {
+        match ask(&format!("{question} (y/n)"), None)?.as_str() {
+            "y" | "Y" => return Ok(true),
+            "n" | "N" => return Ok(false),
+            _ => println!("y/n only please."),
+        }
+    }
+}
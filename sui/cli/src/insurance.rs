@@ -0,0 +1,19 @@
+//! Opt-in insurance pool for stacks.
+//!
+//! Insured nodes pay a premium out of their fee for every stack they claim.
+//! Owners of stacks served by insured nodes file claims, which the manager
+//! pays out of the pool or rejects.
+
+mod file_claim;
+mod join;
+mod leave;
+mod resolve_claim;
+mod set_params;
+mod status;
+
+pub(crate) use file_claim::command as file_claim;
+pub(crate) use join::command as join;
+pub(crate) use leave::command as leave;
+pub(crate) use resolve_claim::command as resolve_claim;
+pub(crate) use set_params::command as set_params;
+pub(crate) use status::command as status;
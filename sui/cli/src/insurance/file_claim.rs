@@ -0,0 +1,43 @@
+use crate::{prelude::*, DB_MODULE_NAME};
+
+const ENDPOINT_NAME: &str = "file_insurance_claim";
+
+/// The active address must own the stack.
+pub(crate) async fn command(
+    context: &mut Context,
+    stack_small_id: u64,
+    amount: u64,
+) -> Result<TransactionDigest> {
+    if amount == 0 {
+        anyhow::bail!("The claimed amount must not be zero");
+    }
+
+    let active_address = context.wallet.active_address()?;
+    let atoma_package = context.unwrap_atoma_package_id();
+    let atoma_db = context.get_or_load_atoma_db().await?;
+
+    let tx = context
+        .get_client()
+        .await?
+        .transaction_builder()
+        .move_call(
+            active_address,
+            atoma_package,
+            DB_MODULE_NAME,
+            ENDPOINT_NAME,
+            vec![],
+            vec![
+                SuiJsonValue::from_object_id(atoma_db),
+                SuiJsonValue::new(stack_small_id.to_string().into())?,
+                SuiJsonValue::new(amount.to_string().into())?,
+            ],
+            None,
+            context.gas_budget(),
+            None,
+        )
+        .await?;
+
+    let tx = context.sign_transaction(&tx)?;
+    let resp = context.execute_transaction_must_succeed(tx).await?;
+    Ok(resp.digest)
+}
@@ -0,0 +1,46 @@
+use crate::{prelude::*, DB_MODULE_NAME};
+
+const PAY_ENDPOINT_NAME: &str = "pay_insurance_claim";
+const REJECT_ENDPOINT_NAME: &str = "reject_insurance_claim";
+
+/// Admin command that pays the claim filed for the stack, or rejects it.
+pub(crate) async fn command(
+    context: &mut Context,
+    stack_small_id: u64,
+    approve: bool,
+) -> Result<TransactionDigest> {
+    let active_address = context.wallet.active_address()?;
+    let atoma_package = context.unwrap_atoma_package_id();
+    let atoma_db = context.get_or_load_atoma_db().await?;
+    let manager_badge = context.get_or_load_db_manager_badge().await?;
+
+    let endpoint_name = if approve {
+        PAY_ENDPOINT_NAME
+    } else {
+        REJECT_ENDPOINT_NAME
+    };
+    let tx = context
+        .get_client()
+        .await?
+        .transaction_builder()
+        .move_call(
+            active_address,
+            atoma_package,
+            DB_MODULE_NAME,
+            endpoint_name,
+            vec![],
+            vec![
+                SuiJsonValue::from_object_id(atoma_db),
+                SuiJsonValue::from_object_id(manager_badge),
+                SuiJsonValue::new(stack_small_id.to_string().into())?,
+            ],
+            None,
+            context.gas_budget(),
+            None,
+        )
+        .await?;
+
+    let tx = context.sign_transaction(&tx)?;
+    let resp = context.execute_transaction_must_succeed(tx).await?;
+    Ok(resp.digest)
+}
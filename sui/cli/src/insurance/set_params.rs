@@ -0,0 +1,46 @@
+use crate::{prelude::*, DB_MODULE_NAME};
+
+const ENDPOINT_NAME: &str = "set_insurance_params";
+
+pub(crate) async fn command(
+    context: &mut Context,
+    premium_permille: u64,
+    payout_permille: u64,
+    max_payout_per_claim: u64,
+) -> Result<TransactionDigest> {
+    if premium_permille > 1000 || payout_permille > 1000 {
+        anyhow::bail!("Permilles must be at most 1000");
+    }
+
+    let active_address = context.wallet.active_address()?;
+    let atoma_package = context.unwrap_atoma_package_id();
+    let atoma_db = context.get_or_load_atoma_db().await?;
+    let manager_badge = context.get_or_load_db_manager_badge().await?;
+
+    let tx = context
+        .get_client()
+        .await?
+        .transaction_builder()
+        .move_call(
+            active_address,
+            atoma_package,
+            DB_MODULE_NAME,
+            ENDPOINT_NAME,
+            vec![],
+            vec![
+                SuiJsonValue::from_object_id(atoma_db),
+                SuiJsonValue::from_object_id(manager_badge),
+                SuiJsonValue::new(premium_permille.to_string().into())?,
+                SuiJsonValue::new(payout_permille.to_string().into())?,
+                SuiJsonValue::new(max_payout_per_claim.to_string().into())?,
+            ],
+            None,
+            context.gas_budget(),
+            None,
+        )
+        .await?;
+
+    let tx = context.sign_transaction(&tx)?;
+    let resp = context.execute_transaction_must_succeed(tx).await?;
+    Ok(resp.digest)
+}
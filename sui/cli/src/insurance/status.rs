@@ -0,0 +1,79 @@
+use move_core_types::language_storage::StructTag;
+use sui_sdk::{
+    rpc_types::SuiData,
+    types::{base_types::ObjectID, dynamic_field::DynamicFieldName, TypeTag},
+};
+
+use crate::{prelude::*, DB_MODULE_NAME};
+
+/// Prints the pool balance and the payout rules, and whether the node is
+/// insured if one is given.
+pub(crate) async fn command(
+    context: &mut Context,
+    node_small_id: Option<u64>,
+) -> Result<()> {
+    let insurance = context.load_atoma_db_field("InsurancePoolKey").await?;
+    let field = |name: &str| {
+        insurance[name]
+            .as_str()
+            .and_then(|s| s.parse::<u64>().ok())
+            .ok_or_else(|| anyhow!("No {name} insurance field found"))
+    };
+    let pool = field("balance")?;
+    let premium_permille = field("premium_permille")?;
+    let payout_permille = field("payout_permille")?;
+    let max_payout_per_claim = field("max_payout_per_claim")?;
+    let insured_nodes = ObjectID::from_str(
+        insurance["members"]["id"]["id"]
+            .as_str()
+            .ok_or_else(|| anyhow!("No insurance members field found"))?,
+    )?;
+
+    println!("Pool: {}", context.toma(pool));
+    println!("Premium: {premium_permille}‰ of the node fee per stack");
+    println!(
+        "Payout: {payout_permille}‰ of the claim, at most {} per claim",
+        context.toma(max_payout_per_claim)
+    );
+
+    let Some(node_small_id) = node_small_id else {
+        return Ok(());
+    };
+    let atoma_package = context.unwrap_atoma_package_id();
+    let membership = context
+        .get_client()
+        .await?
+        .read_api()
+        .get_dynamic_field_object(
+            insured_nodes,
+            DynamicFieldName {
+                type_: TypeTag::Struct(Box::new(StructTag {
+                    address: atoma_package.into(),
+                    module: FromStr::from_str(DB_MODULE_NAME)?,
+                    name: FromStr::from_str("NodeSmallId")?,
+                    type_params: vec![],
+                })),
+                value: serde_json::json!({ "inner": node_small_id.to_string() }),
+            },
+        )
+        .await?
+        .data
+        .and_then(|data| data.content)
+        .and_then(|content| content.try_into_move())
+        .map(|membership| membership.fields.to_json_value()["value"].clone());
+    match membership {
+        Some(membership) => println!(
+            "Node {node_small_id}: insured since epoch {}, paid {} in premiums",
+            membership["joined_in_epoch"].as_str().unwrap_or("?"),
+            context.toma(
+                membership["premiums_paid"]
+                    .as_str()
+                    .and_then(|s| s.parse::<u64>().ok())
+                    .unwrap_or_default()
+            ),
+        ),
+        None => println!("Node {node_small_id}: not insured"),
+    }
+
+    Ok(())
+}
@@ -0,0 +1,6 @@
+//! The encodings that submitters and nodes share with the contracts, without
+//! the Sui client and the runtime of the binary, so that nodes and gateways
+//! can depend on them with `default-features = false`.
+
+pub mod protocol_encoding;
+pub mod sampling;
@@ -1,13 +1,45 @@
+mod analytics;
+mod bench;
+mod call;
+mod chaos;
+mod completion;
+mod confirmation;
+#[cfg(feature = "daemon")]
+mod daemon;
 mod db;
+mod debug;
+mod denomination;
 mod dotenv_conf;
+mod explain;
+mod fixtures;
 mod gate;
+mod init;
+mod insurance;
+mod manpage;
+mod offer;
 mod prelude;
+mod progress;
+mod quote;
+mod receipts;
+mod refunds;
+mod release;
+mod role;
+mod secrets;
 mod settle;
+mod signed_terms;
+mod stack;
+mod telemetry;
+mod timeouts;
 mod toma;
+#[cfg(feature = "gateway")]
+mod zklogin;
 
-use std::{io::Read, path::PathBuf, str::FromStr};
+use std::{
+    io::Read, net::SocketAddr, path::PathBuf, str::FromStr, time::Duration,
+};
 
-use clap::{Parser, Subcommand};
+use clap::{CommandFactory, Parser, Subcommand};
+use cli::{protocol_encoding, sampling};
 use dotenv_conf::WALLET_PATH;
 use dotenvy::dotenv;
 use env_home::env_home_dir;
@@ -15,7 +47,9 @@ use move_core_types::{
     account_address::AccountAddress, language_storage::StructTag,
 };
 use sui_sdk::types::{
-    base_types::ObjectID, dynamic_field::DynamicFieldName, TypeTag,
+    base_types::{ObjectID, SuiAddress},
+    dynamic_field::DynamicFieldName,
+    TypeTag,
 };
 
 use crate::{dotenv_conf::DotenvConf, prelude::*};
@@ -25,11 +59,22 @@ const DB_MODULE_NAME: &str = "db";
 const DB_NODE_TYPE_NAME: &str = "NodeBadge";
 const DB_TASK_TYPE_NAME: &str = "TaskBadge";
 const DB_TYPE_NAME: &str = "AtomaDb";
+#[cfg(feature = "daemon")]
+const DEFAULT_DLQ_FILE: &str = "atoma-dlq.json";
+const DEFAULT_OFFER_BOOK_FILE: &str = "atoma-offers.jsonl";
+#[cfg(feature = "daemon")]
+const DEFAULT_QUEUE_FILE: &str = "atoma-queue.json";
+#[cfg(feature = "daemon")]
+const DEFAULT_PARAMS_HISTORY_FILE: &str = "atoma-params-history.json";
+const DEFAULT_RESUBMISSIONS_FILE: &str = "atoma-resubmissions.json";
+const DEFAULT_TASK_TEMPLATES_FILE: &str = "atoma-task-templates.json";
 const FAUCET_TYPE_NAME: &str = "Faucet";
+const GATE_MODULE_NAME: &str = "gate";
 const PROMPTS_MODULE_NAME: &str = "prompts";
 const SETTLEMENT_MODULE_NAME: &str = "settlement";
 const SETTLEMENT_TICKET_TYPE_NAME: &str = "SettlementTicket";
 const TOMA_COIN_MODULE_NAME: &str = "toma";
+const TOMA_COIN_TYPE_NAME: &str = "TOMA";
 
 #[derive(Parser)]
 #[command(version, about, long_about = None)]
@@ -46,42 +91,400 @@ struct Cli {
     /// If neither is provided, the CLI will fail.
     #[arg(short, long)]
     wallet: Option<PathBuf>,
+    /// Long running commands can report their progress as line-delimited
+    /// JSON on stderr.
+    #[arg(long, value_enum, default_value_t)]
+    progress: progress::ProgressFormat,
+    /// How long commands wait after executing a transaction.
+    /// Loaded from WAIT_FOR env var if not provided, defaults to execution.
+    #[arg(long, value_enum)]
+    wait_for: Option<confirmation::WaitFor>,
+    /// Fails the command if the transaction doesn't reach the --wait-for
+    /// level in time.
+    #[arg(long, default_value_t = confirmation::DEFAULT_TIMEOUT.as_secs())]
+    wait_timeout_secs: u64,
+    /// Prints TOMA amounts in the smallest unit instead of in TOMA.
+    #[arg(long)]
+    raw: bool,
+    /// Retries finding a TOMA coin of the sender for up to this many seconds,
+    /// 30 if given without a value, eg. right after a faucet mint.
+    #[arg(
+        long,
+        value_name = "SECS",
+        num_args = 0..=1,
+        default_missing_value = "30"
+    )]
+    wait_for_coin: Option<u64>,
+    /// Fails requests to the full node and transaction executions that
+    /// take longer than this many seconds.
+    /// Loaded per operation from the RPC_TIMEOUT_SECS and
+    /// EXECUTE_TIMEOUT_SECS env vars if not provided.
+    #[arg(long, value_name = "SECS")]
+    timeout: Option<u64>,
+    /// Refuses to sign transactions and messages, for credentials that only
+    /// inspect the network.
+    /// Takes precedence over the ROLE env var.
+    #[arg(long)]
+    read_only: bool,
 }
 
 #[derive(Subcommand)]
 enum Cmds {
+    /// Operations that require the manager badge.
+    /// Refused unless the role is admin, see the ROLE env var.
+    #[command(subcommand)]
+    Admin(AdminCmds),
+    /// Historical aggregations over the events emitted by the contracts.
+    #[command(subcommand)]
+    Analytics(AnalyticsCmds),
+    /// Benchmarks of RPC heavy commands with regression gates.
+    #[command(subcommand)]
+    Bench(BenchCmds),
+    /// Calls any entry function of the atoma package.
+    ///
+    /// Meant for entry functions that don't have a dedicated command yet.
+    /// The arguments are encoded based on the function signature.
+    Call {
+        #[arg(short, long)]
+        package: Option<String>,
+        #[arg(short, long)]
+        module: String,
+        #[arg(short, long)]
+        function: String,
+        /// A JSON array with an argument per parameter, or @path to a file
+        /// with it.
+        /// `TxContext`, `Clock` and `Random` are filled in automatically,
+        /// and `$db`, `$manager_badge` and `$node_badge` are replaced by the
+        /// IDs of those objects.
+        #[arg(short, long)]
+        args: Option<String>,
+        #[arg(long = "type-arg")]
+        type_args: Vec<String>,
+    },
+    /// Fault injection against fixture nodes on localnet.
+    #[command(subcommand)]
+    Chaos(ChaosCmds),
+    /// Prints the completion script for a shell.
+    Completion {
+        #[arg(value_enum)]
+        shell: clap_complete::Shell,
+    },
+    /// Operations for node daemon operators.
+    #[cfg(feature = "daemon")]
+    #[command(subcommand)]
+    Daemon(DaemonCmds),
     /// Queries and operations related to the database.
     #[command(subcommand)]
     Db(DbCmds),
+    /// Inspects the encodings of what prompts carry for the nodes.
+    #[command(subcommand)]
+    Debug(DebugCmds),
+    /// Explains what a transaction did: its calls with decoded arguments,
+    /// the objects and events, the fees, and why it aborted if it failed.
+    /// Support can ask users to run it on the digest of a failed command.
+    Explain { digest: TransactionDigest },
+    /// Synthetic network state for tests and demos.
+    #[command(subcommand)]
+    Fixtures(FixturesCmds),
     /// Queries and operations related to submitting a prompt.
     #[command(subcommand)]
     Gate(GateCmds),
+    /// Walks a new node operator through the wallet, funding, registration,
+    /// model and task setup and writes the resulting env file.
+    Init {
+        #[arg(short, long)]
+        package: Option<String>,
+    },
+    /// Opt-in insurance pool that covers users of insured nodes.
+    #[command(subcommand)]
+    Insurance(InsuranceCmds),
+    /// Prints the man page, or writes the pages of all subcommands into a
+    /// directory.
+    Manpage {
+        #[arg(long)]
+        out_dir: Option<PathBuf>,
+    },
+    /// Signed stack offers that nodes post to an order book.
+    #[command(subcommand)]
+    Offer(OfferCmds),
+    /// Signed price quotes for stacks.
+    #[command(subcommand)]
+    Quote(QuoteCmds),
+    /// Receipts that nodes sign for each request served with a stack.
+    #[command(subcommand)]
+    Receipts(ReceiptsCmds),
+    /// Escrow that prompt submitters recover from timed out tickets.
+    #[command(subcommand)]
+    Refunds(RefundsCmds),
+    /// Signatures of released binaries.
+    #[command(subcommand)]
+    Release(ReleaseCmds),
     /// Queries and operations related to settling tickets.
     #[command(subcommand)]
     Settle(SettlementCmds),
+    /// Off-chain operations on stacks of pre-paid compute units.
+    #[command(subcommand)]
+    Stack(StackCmds),
     /// TOMA coin package related commands.
     #[command(subcommand)]
     Toma(TomaCmds),
+    /// Prints the version, with --verbose also the commit, build timestamp,
+    /// target and features of this build.
+    Version {
+        #[arg(short, long)]
+        verbose: bool,
+    },
 }
 
 #[derive(Subcommand)]
-enum DbCmds {
-    /// Admin command to add a new model to the database.
+enum AnalyticsCmds {
+    /// Average price per compute unit of the stacks bought for each task
+    /// over time.
+    TaskPrices {
+        #[arg(short, long)]
+        package: Option<String>,
+        /// Only this task, otherwise all tasks.
+        #[arg(short, long)]
+        task_small_id: Option<u64>,
+        #[arg(long, default_value_t = 24)]
+        bucket_hours: u64,
+    },
+    /// Changes of the echelon fees over time.
+    EchelonFees {
+        #[arg(short, long)]
+        package: Option<String>,
+        /// Only this model, and also prints its current fees.
+        #[arg(short, long)]
+        model: Option<String>,
+    },
+    /// Distribution of the time from prompt submission to settlement.
+    SettlementLatency {
+        #[arg(short, long)]
+        package: Option<String>,
+    },
+    /// How often the stack settlements of each node are disputed.
+    DisputeRates {
+        #[arg(short, long)]
+        package: Option<String>,
+        /// Only this node, otherwise all nodes.
+        #[arg(short, long)]
+        node_small_id: Option<u64>,
+    },
+}
+
+#[derive(Subcommand)]
+enum BenchCmds {
+    /// Runs a command repeatedly and reports its RPC calls and wall time.
+    /// Fails if any of the gates is exceeded.
+    Run {
+        #[arg(short, long)]
+        package: Option<String>,
+        #[arg(short, long, value_enum)]
+        target: bench::Target,
+        /// Required by the list-echelons target.
+        #[arg(short, long)]
+        model: Option<String>,
+        #[arg(short, long, default_value_t = 10)]
+        iterations: u64,
+        /// Records the RPC responses into this file.
+        #[arg(long, conflicts_with = "replay")]
+        record: Option<PathBuf>,
+        /// Answers the RPC calls from a file written with --record instead
+        /// of the fullnode.
+        #[arg(long)]
+        replay: Option<PathBuf>,
+        #[arg(long)]
+        max_rpc_calls: Option<u64>,
+        #[arg(long)]
+        max_mean_wall_time_ms: Option<u64>,
+        /// Writes the results as JSON into this file.
+        #[arg(long)]
+        report: Option<PathBuf>,
+    },
+}
+
+#[derive(Subcommand)]
+enum ChaosCmds {
+    /// Settles the fixture stacks with injected faults and fails unless the
+    /// contract slashes, disputes and refunds as specified.
+    /// Changes the cross validation config of the db.
+    Run {
+        #[arg(short, long)]
+        package: Option<String>,
+        /// Manifest written by `fixtures generate` with sampling consensus
+        /// tasks.
+        #[arg(short, long, default_value = "fixtures.json")]
+        manifest: PathBuf,
+        /// Assigned to the stacks in round robin.
+        #[arg(
+            long,
+            value_enum,
+            value_delimiter = ',',
+            default_values_t = [
+                chaos::Fault::Honest,
+                chaos::Fault::NeverCommit,
+                chaos::Fault::WrongRoot,
+                chaos::Fault::Late,
+            ],
+        )]
+        faults: Vec<chaos::Fault>,
+    },
+}
+
+#[cfg(feature = "daemon")]
+#[derive(Subcommand)]
+enum DaemonCmds {
+    /// Settlement and claim transactions that failed permanently.
+    #[command(subcommand)]
+    Dlq(DlqCmds),
+    /// Periodically compares the protocol parameters with the last version
+    /// seen and alerts about every change, see `db params`.
+    /// New announcements are surfaced too, the critical ones as alerts.
+    /// Alerts are logged and POSTed to `WATCHDOG_ALERT_URL` if set.
+    ParamsWatch {
+        #[arg(short, long)]
+        package: Option<String>,
+        /// Every distinct version of the params is appended to this file.
+        #[arg(long, default_value = DEFAULT_PARAMS_HISTORY_FILE)]
+        history_file: PathBuf,
+        #[arg(long, default_value_t = 300)]
+        interval_secs: u64,
+        /// Checks once and exits, eg. when run from cron.
+        #[arg(long)]
+        once: bool,
+    },
+    /// Prompts the node was sampled for and how far it got with them.
+    #[command(subcommand)]
+    Queue(QueueCmds),
+    /// Spend limits of the daemon actions, configured with the `WATCHDOG_*`
+    /// env vars.
+    #[command(subcommand)]
+    Watchdog(WatchdogCmds),
+}
+
+#[cfg(feature = "daemon")]
+#[derive(Subcommand)]
+enum DlqCmds {
+    /// Lists the dead-lettered actions and their last error.
+    List {
+        #[arg(long, default_value = DEFAULT_DLQ_FILE)]
+        dlq_file: PathBuf,
+    },
+    /// Replays a dead-lettered action, or all of them.
+    /// Actions are removed from the file once they succeed.
+    Retry {
+        #[arg(long, default_value = DEFAULT_DLQ_FILE)]
+        dlq_file: PathBuf,
+        /// If not provided, all actions are retried.
+        #[arg(long)]
+        id: Option<u64>,
+    },
+    /// Removes an action from the file without replaying it.
+    Discard {
+        #[arg(long, default_value = DEFAULT_DLQ_FILE)]
+        dlq_file: PathBuf,
+        #[arg(long)]
+        id: u64,
+    },
+}
+
+#[cfg(feature = "daemon")]
+#[derive(Subcommand)]
+enum QueueCmds {
+    /// Lists the queued tickets and their state.
+    List {
+        #[arg(long, default_value = DEFAULT_QUEUE_FILE)]
+        queue_file: PathBuf,
+        /// Only the tickets that are not settled yet.
+        #[arg(long)]
+        pending: bool,
+    },
+    /// Moves a ticket forward to the state, adding it to the queue if needed.
+    Record {
+        #[arg(long, default_value = DEFAULT_QUEUE_FILE)]
+        queue_file: PathBuf,
+        #[arg(short, long)]
+        ticket_id: String,
+        #[arg(short, long, value_enum)]
+        state: daemon::QueueState,
+    },
+}
+
+#[cfg(feature = "daemon")]
+#[derive(Subcommand)]
+enum WatchdogCmds {
+    /// Prints the gas and TOMA spent in the last 24 hours against the limits.
+    Status,
+}
+
+#[derive(Subcommand)]
+enum DebugCmds {
+    /// Decodes the versioned output destination envelope of a text to text
+    /// prompt, or of hex encoded bytes.
+    DecodeEnvelope {
+        #[arg(short, long)]
+        package: Option<String>,
+        #[arg(short, long, required_unless_present = "hex")]
+        ticket_id: Option<String>,
+        #[arg(long, conflicts_with = "ticket_id")]
+        hex: Option<String>,
+    },
+}
+
+#[derive(Subcommand)]
+enum FixturesCmds {
+    /// Admin command that registers synthetic nodes with keypairs derived
+    /// from the seed, creates models, echelons, tasks and open stacks, and
+    /// writes their IDs and the node keys into a manifest file.
+    Generate {
+        #[arg(short, long)]
+        package: Option<String>,
+        #[arg(long, default_value_t = 10)]
+        nodes: u64,
+        #[arg(long, default_value_t = 2)]
+        models: u64,
+        #[arg(long, default_value_t = 3)]
+        tasks: u64,
+        #[arg(long, default_value_t = 3)]
+        stacks: u64,
+        /// Security level of the tasks.
+        /// Use 2 (sampling consensus) for fixtures that the chaos harness
+        /// runs against.
+        #[arg(long)]
+        security_level: Option<u16>,
+        /// The same seed yields the same node keypairs and model names.
+        #[arg(long, default_value_t = 0)]
+        seed: u64,
+        /// How much SUI (in MIST) each node gets for gas.
+        #[arg(long, default_value_t = 100_000_000)]
+        gas_per_node: u64,
+        #[arg(short, long, default_value = "fixtures.json")]
+        out: PathBuf,
+    },
+}
+
+#[derive(Subcommand)]
+enum AdminCmds {
+    /// Adds a new model to the database.
     AddModel {
         #[arg(short, long)]
         package: Option<String>,
         #[arg(short, long)]
         name: String,
         /// Provide if the model is text2text such as llama.
-        /// Exclusive with text2image.
-        #[arg(long, action)]
+        /// Shorthand for `--modality text2text`.
+        #[arg(long, action, conflicts_with_all = ["text2image", "modality"])]
         text2text: bool,
         /// Provide if the model is text2image such as stable diffusion.
-        /// Exclusive with text2text.
-        #[arg(long, action)]
+        /// Shorthand for `--modality text2image`.
+        #[arg(long, action, conflicts_with = "modality")]
         text2image: bool,
+        /// One of text2text, text2image, text-embedding, audio, or a custom
+        /// modality number starting at 1000.
+        #[arg(long)]
+        modality: Option<db::Modality>,
     },
-    /// Admin command to add a new echelon to a model.
+    /// Adds a new echelon to a model.
     AddModelEchelon {
         #[arg(short, long)]
         package: Option<String>,
@@ -92,81 +495,46 @@ enum DbCmds {
         #[arg(short, long)]
         echelon: u64,
         /// The fee per token that the model charges for input tokens.
-        #[arg(short, long)]
+        #[arg(short, long, value_parser = denomination::parse_toma)]
         input_fee_per_token: u64,
         /// Defaults to input_fee_per_token, which is ideal for text to text
         /// models.
-        #[arg(short, long)]
+        #[arg(short, long, value_parser = denomination::parse_toma)]
         output_fee_per_token: Option<u64>,
         /// The relative performance of the model to other models.
         /// This is relevant for load balancing.
         #[arg(short, long)]
         relative_performance: u64,
     },
-    /// Admin command to set the required registration collateral for TOMA.
-    /// Nodes will lock this many TOMA tokens.
-    SetRequiredRegistrationTomaCollateral {
-        #[arg(short, long)]
-        package: Option<String>,
-        #[arg(short, long)]
-        new_amount: u64,
-    },
-    /// First thing to do as a node.
-    /// It will create a `NodeBadge` object for the node.
-    RegisterNode {
-        #[arg(short, long)]
-        package: Option<String>,
-    },
-    /// Node can join a model to receive prompts.
-    AddNodeToModel {
+    /// Changes how long the sampled nodes of an echelon have to commit
+    /// before their tickets can be settled without them.
+    SetEchelonSettlementTimeout {
         #[arg(short, long)]
         package: Option<String>,
-        /// Must match an existing model name.
         #[arg(short, long)]
         model: String,
-        /// Must match an existing echelon ID.
-        /// Echelons group nodes with similar HW and SW.
-        /// See the protocol documentation for the list of echelons.
         #[arg(short, long)]
         echelon: u64,
-    },
-    /// Prints env vars in .env format that contain some important IDs for
-    /// the network.
-    PrintEnv {
-        #[arg(short, long)]
-        package: Option<String>,
-    },
-    /// A node can stop receiving prompts from a model.
-    /// There exist a rare scenario where this transaction must be retried due
-    /// to concurrent modification.
-    /// You will be informed if this happens and asked for retry.
-    RemoveNodeFromModel {
-        #[arg(short, long)]
-        package: Option<String>,
-        #[arg(short, long)]
-        model: String,
-    },
-    /// Stop participating in the network.
-    /// This cannot be undone and if a node wants to join again, they must
-    /// generate a new `NodeBadge`.
-    PermanentlyDisableNode {
-        #[arg(short, long)]
-        package: Option<String>,
-    },
-    /// On the second epoch from now (tops 2 days), the node can be destroyed
-    /// and the collateral will be returned.
-    DestroyDisabledNode {
+        /// Between 1 second and 1 hour.
         #[arg(short, long)]
-        package: Option<String>,
+        timeout_ms: u64,
     },
     /// Create a new task entry in the database
+    #[command(alias = "create-task")]
     CreateTaskEntry {
         /// Optional package ID. If not provided, the default from the environment will be used.
         #[arg(short, long)]
         package: Option<String>,
+        /// Named set of parameters, see `list-task-templates`.
+        /// Parameters given explicitly take precedence over the template.
+        #[arg(long)]
+        template: Option<String>,
+        /// Templates that extend or override the bundled ones.
+        #[arg(long, default_value = DEFAULT_TASK_TEMPLATES_FILE)]
+        templates_file: PathBuf,
         /// The role ID for this task
-        #[arg(short, long)]
-        role: u16,
+        #[arg(short, long, required_unless_present = "template")]
+        role: Option<u16>,
         /// Optional model name for this task
         #[arg(short, long)]
         model_name: Option<String>,
@@ -179,670 +547,2866 @@ enum DbCmds {
         /// Whether this task is public
         #[arg(short, long)]
         is_public: bool,
+        #[command(flatten)]
+        constraints: db::TaskConstraints,
     },
-    /// Deprecates a task in the database.
-    /// This command marks a task as deprecated, preventing new subscriptions.
-    /// Existing subscriptions and ongoing work are not affected.
-    DeprecateTask {
+    /// Whitelist nodes for a task.
+    WhitelistNodesForTask {
         /// Optional package ID. If not provided, the default from the environment will be used.
         #[arg(short, long)]
         package: Option<String>,
-        /// The ObjectID of the TaskBadge for the task to be deprecated.
-        #[arg(short, long)]
-        task_badge: String,
-    },
-    /// Removes a deprecated task from the database.
-    /// This command completely removes a task that has been previously deprecated.
-    /// It should only be used when you're certain the task is no longer needed.
-    RemoveDeprecatedTask {
-        /// Optional package ID. If not provided, the default from the environment will be used.
+        /// The small ID of the task to whitelist nodes for.
         #[arg(short, long)]
-        package: Option<String>,
-        /// The ObjectID of the TaskBadge for the deprecated task to be removed.
+        task_small_id: u64,
+        /// The nodes to whitelist.
         #[arg(short, long)]
-        task_badge: String,
+        nodes_small_ids: Vec<u64>,
     },
-    /// Command to subscribe a node to a specific task in the Atoma network.
-    SubscribeNodeToTask {
-        /// Optional package ID. If not provided, the default from the environment will be used.
-        #[arg(short = 'a', long)]
+    /// Removes nodes from the whitelist of a private task.
+    /// Removed nodes that are subscribed to the task are unsubscribed.
+    RemoveNodeFromTaskWhitelist {
+        #[arg(short, long)]
         package: Option<String>,
-        /// The small ID of the task to subscribe to. This is a unique identifier for the task.
         #[arg(short, long)]
         task_small_id: u64,
-        /// The price per compute unit that the node is willing to charge for this task.
-        /// This value is in the smallest unit of the network's native currency.
-        #[arg(short = 'p', long)]
-        price_per_one_million_compute_units: u64,
+        /// The nodes to remove from the whitelist.
+        #[arg(short, long)]
+        nodes_small_ids: Vec<u64>,
     },
-    /// Update the price per one million compute units for a node's subscription to a task.
-    UpdateNodeSubscription {
-        /// Optional package ID. If not provided, the default from the environment will be used.
-        #[arg(short = 'a', long)]
+    /// Requires stacks of the task to be settled with a Groth16 proof, which
+    /// is verified against the given key.
+    SetTaskZkVerifier {
+        #[arg(short, long)]
         package: Option<String>,
-        /// The small ID of the task to update the subscription for.
         #[arg(short, long)]
         task_small_id: u64,
-        /// The new price per one million compute units for the node.
+        #[arg(short, long, value_enum)]
+        curve: db::ZkCurve,
+        /// File with the arkworks compressed bytes of the verifying key.
         #[arg(short, long)]
-        price_per_one_million_compute_units: u64,
+        verifying_key: PathBuf,
     },
-    /// Unsubscribe a node from a specific task in the Atoma network.
-    /// This command removes a node's subscription to a task, preventing it from receiving further work for that task.
-    UnsubscribeNodeFromTask {
-        /// Optional package ID. If not provided, the default from the environment will be used.
+    /// Stacks of the task are settled with attestations again.
+    RemoveTaskZkVerifier {
         #[arg(short, long)]
         package: Option<String>,
-        /// The small ID of the task to unsubscribe from. This is a unique identifier for the task.
         #[arg(short, long)]
         task_small_id: u64,
     },
-    /// Acquires a new stack entry for a specific task.
-    /// This command allows a node to request a new stack entry, which represents
-    /// a unit of work to be performed for a given task.
-    AcquireNewStackEntry {
-        /// Optional package ID. If not provided, the default from the environment will be used.
+    /// Only nodes that reported at least this version are sampled for the
+    /// stacks of the task.
+    SetTaskMinNodeVersion {
         #[arg(short, long)]
         package: Option<String>,
-        /// The small ID of the task for which to acquire a new stack entry.
-        /// This ID uniquely identifies the task within the Atoma network.
         #[arg(short, long)]
         task_small_id: u64,
-        /// The number of compute units requested for this stack entry.
-        /// This represents the amount of computational work the node is willing to perform.
-        #[arg(short, long)]
-        num_compute_units: u64,
-        /// The price (in smallest unit of the network's native currency) that the node
-        /// is charging for the requested compute units.
-        /// This should be calculated based on the task's requirements and the node's pricing strategy.
+        /// Eg. `1.4.0`, or `0.0.0` to remove the minimum.
         #[arg(short, long)]
-        price: u64,
+        min_version: db::SoftwareVersion,
     },
-    /// Attempts to settle a stack entry in the Atoma network.
-    /// This command is used by nodes to finalize their work on a stack entry
-    /// and claim the associated rewards.
-    TrySettleStack {
-        /// Optional package ID. If not provided, the default from the environment will be used.
+    /// Nodes must ask at least this price per one million compute units to
+    /// subscribe to the task or update their subscription.
+    SetTaskMinPrice {
         #[arg(short, long)]
         package: Option<String>,
-        /// The small ID of the stack entry to be settled.
-        /// This is a unique identifier for the stack within the Atoma network.
         #[arg(short, long)]
-        stack_small_id: u64,
-        /// The number of compute units claimed by the node for this stack entry.
-        /// This should reflect the actual work performed by the node.
+        task_small_id: u64,
+        /// In TOMA, eg. `0.5 TOMA`, or in the smallest unit without a unit.
+        /// 0 removes the minimum.
+        #[arg(short, long, value_parser = denomination::parse_toma)]
+        min_price_per_one_million_compute_units: u64,
+    },
+    /// Posts a notice for nodes and gateways, see `db announcements`.
+    /// `daemon params-watch` alerts operators about critical ones.
+    PostAnnouncement {
         #[arg(short, long)]
-        num_claimed_compute_units: u64,
-        /// The committed stack proof, which is used to verify the node's work.
-        /// This is typically a cryptographic proof or hash of the work performed.
+        package: Option<String>,
+        #[arg(short, long, value_enum, default_value_t = db::AnnouncementSeverity::Info)]
+        severity: db::AnnouncementSeverity,
+        /// At most 1024 bytes.
         #[arg(short, long)]
-        committed_stack_proof: Vec<u8>,
-        /// The Merkle leaf for this stack entry.
-        /// This is part of the Merkle tree structure used for efficient verification
-        /// of the stack's state within the larger Atoma database.
+        message: String,
+        /// From when the notice applies, eg. when a maintenance starts.
         #[arg(short, long)]
-        stack_merkle_leaf: Vec<u8>,
+        effective_epoch: u64,
     },
-    /// Submits a stack settlement attestation for a completed task.
-    /// This command is used by nodes to provide proof of work completion
-    /// and initiate the settlement process for a stack entry.
-    SubmitStackSettlementAttestation {
-        /// Optional package ID. If not provided, the default from the environment will be used.
+    /// Removes a notice, eg. once a maintenance is over.
+    RemoveAnnouncement {
         #[arg(short, long)]
         package: Option<String>,
-        /// The small ID of the stack entry for which the attestation is being submitted.
-        /// This is a unique identifier for the stack within the Atoma network.
-        #[arg(short, long)]
-        stack_small_id: u64,
-        /// The committed stack proof, which is used to verify the node's work.
-        /// This is typically a cryptographic proof or hash of the work performed.
-        #[arg(short, long)]
-        committed_stack_proof: Vec<u8>,
-        /// The Merkle leaf for this stack entry.
-        /// This is part of the Merkle tree structure used for efficient verification
-        /// of the stack's state within the larger Atoma database.
         #[arg(short, long)]
-        stack_merkle_leaf: Vec<u8>,
+        announcement_id: u64,
     },
-    /// Claims funds for settled tickets in the Atoma network.
-    /// This command allows a node to claim the rewards for successfully completed and settled tasks.
-    ClaimFunds {
-        /// Optional package ID. If not provided, the default from the environment will be used.
+    /// Sets the parameters of the sampling consensus security level.
+    /// Parameters that are not given keep their current value.
+    SetSamplingParams {
         #[arg(short, long)]
         package: Option<String>,
-        /// A list of settled stack small IDs for which the node can claim funds.
-        /// Each ID represents a completed and settled stack that the node has performed.
-        #[arg(short, long)]
-        settled_ticket_ids: Vec<u64>,
+        /// Probability in permille that a stack settlement samples
+        /// attestation nodes.
+        #[arg(long)]
+        cross_validation_probability_permille: Option<u64>,
+        /// How many attestation nodes are sampled.
+        #[arg(long)]
+        cross_validation_extra_nodes_count: Option<u64>,
+        /// Share of the stack price in permille that the user pays for the
+        /// selected node.
+        #[arg(long)]
+        sampling_consensus_charge_permille: Option<u64>,
+        /// Share of the stack price in permille that the user pays on top
+        /// for the attestation nodes.
+        #[arg(long)]
+        cross_validation_extra_nodes_charge_permille: Option<u64>,
     },
-    /// Start an attestation dispute for a stack entry.
-    /// This can only be done by a selected attestation node,
-    /// and should only be done when the attestation node disagrees with the committed stack proof,
-    /// by the original selected node.
-    StartAttestationDispute {
-        /// Optional package ID. If not provided, the default from the environment will be used.
+    /// Sets the required registration collateral for TOMA.
+    /// Nodes will lock this many TOMA tokens.
+    SetRequiredRegistrationTomaCollateral {
         #[arg(short, long)]
         package: Option<String>,
-        /// The small ID of the stack entry for which the attestation is being disputed.
-        /// This is a unique identifier for the stack within the Atoma network.
-        #[arg(short, long)]
-        stack_small_id: u64,
-        /// The commitment to the stack entry that is being disputed.
-        /// This is typically a cryptographic proof or hash of the work performed.  
+        #[arg(short, long, value_parser = denomination::parse_toma)]
+        new_amount: u64,
+    },
+    /// Configures when nodes without a heartbeat can be swept from their
+    /// task subscriptions.
+    SetStaleNodeSweepParams {
         #[arg(short, long)]
-        attestation_commitment: Vec<u8>,
+        package: Option<String>,
+        /// How many epochs without a heartbeat make a node stale.
+        #[arg(long)]
+        threshold_epochs: u64,
+        /// Paid to the sweeper from the communal treasury for every sweep.
+        #[arg(long, value_parser = denomination::parse_toma)]
+        reward: u64,
     },
-    NewNetworkKeyRotation {
+    /// Configures when claimed stacks can be removed with `settle gc`.
+    SetStackGcParams {
         #[arg(short, long)]
         package: Option<String>,
+        /// How many epochs after the claim a stack can be removed.
+        #[arg(long)]
+        min_age_epochs: u64,
+        /// Paid to the collector from the communal treasury per stack.
+        #[arg(long, value_parser = denomination::parse_toma)]
+        bounty: u64,
     },
-    /// Rotates the node's public key commitment and tee attestation bytes.
-    RotateNodePublicKey {
+    /// Sets how many epochs after a stack is settled the attestation nodes
+    /// can dispute it, before the node can claim its funds.
+    SetStackDisputeWindow {
         #[arg(short, long)]
         package: Option<String>,
-        /// The new public key commitment.
         #[arg(short, long)]
-        public_key_commitment: Vec<u8>,
-        /// The new TEE evidence bytes (including the remote attestation bytes and the certificate chain bytes, in the case of Nvidia devices).
+        epochs: u64,
+    },
+    NewNetworkKeyRotation {
         #[arg(short, long)]
-        evidence_bytes: Vec<u8>,
-        /// The key rotation counter.
+        package: Option<String>,
+    },
+    /// Pays an insurance claim out of the pool.
+    PayInsuranceClaim {
         #[arg(short, long)]
-        key_rotation_counter: u64,
-        /// The device type.
+        package: Option<String>,
         #[arg(short, long)]
-        device_type: u16,
+        stack_small_id: u64,
     },
-    /// Whitelist nodes for a task.
-    WhitelistNodesForTask {
-        /// Optional package ID. If not provided, the default from the environment will be used.
+    /// Rejects an insurance claim.
+    RejectInsuranceClaim {
         #[arg(short, long)]
         package: Option<String>,
-        /// The small ID of the task to whitelist nodes for.
         #[arg(short, long)]
-        task_small_id: u64,
-        /// The nodes to whitelist.
+        stack_small_id: u64,
+    },
+    /// Sets the premium and payout rules of the insurance pool.
+    SetInsuranceParams {
         #[arg(short, long)]
-        nodes_small_ids: Vec<u64>,
+        package: Option<String>,
+        /// Of the node fee per stack, in ‰.
+        #[arg(long)]
+        premium_permille: u64,
+        /// Of the claimed amount, in ‰.
+        #[arg(long)]
+        payout_permille: u64,
+        #[arg(long, value_parser = denomination::parse_toma)]
+        max_payout_per_claim: u64,
     },
 }
 
 #[derive(Subcommand)]
-enum GateCmds {
-    /// Submits an example prompt to the network.
-    SubmitTellMeAJokePrompt {
+enum DbCmds {
+    /// First thing to do as a node.
+    /// It will create a `NodeBadge` object for the node.
+    RegisterNode {
+        #[arg(short, long)]
+        package: Option<String>,
+    },
+    /// Node can join a model to receive prompts.
+    AddNodeToModel {
         #[arg(short, long)]
         package: Option<String>,
+        /// Must match an existing model name.
         #[arg(short, long)]
         model: String,
-        #[arg(long, default_value_t = 1_000)]
-        max_fee_per_token: u64,
+        /// Must match an existing echelon ID.
+        /// Echelons group nodes with similar HW and SW.
+        /// See the protocol documentation for the list of echelons.
+        #[arg(short, long)]
+        echelon: u64,
     },
-    /// Submits an example prompt to the network.
-    SubmitGenerateNftPrompt {
+    /// Prints env vars in .env format that contain some important IDs for
+    /// the network.
+    PrintEnv {
+        #[arg(short, long)]
+        package: Option<String>,
+    },
+    /// A node can stop receiving prompts from a model.
+    /// There exist a rare scenario where this transaction must be retried due
+    /// to concurrent modification.
+    /// You will be informed if this happens and asked for retry.
+    RemoveNodeFromModel {
         #[arg(short, long)]
         package: Option<String>,
         #[arg(short, long)]
         model: String,
-        #[arg(long, default_value_t = 1_000)]
-        max_fee_per_token: u64,
     },
-}
-
-#[derive(Subcommand)]
-enum SettlementCmds {
-    /// Lists all open tickets.
-    ListTickets {
+    /// Stop participating in the network.
+    /// This cannot be undone and if a node wants to join again, they must
+    /// generate a new `NodeBadge`.
+    PermanentlyDisableNode {
         #[arg(short, long)]
         package: Option<String>,
     },
-    /// Submit a commitment to settle a ticket.
-    /// This can be only used for text to text models.
-    SubmitCommitment {
+    /// On the second epoch from now (tops 2 days), the node can be destroyed
+    /// and the collateral will be returned.
+    DestroyDisabledNode {
         #[arg(short, long)]
-        ticket_id: String,
+        package: Option<String>,
+    },
+    /// Lists the bundled task templates and those in the templates file.
+    ListTaskTemplates {
+        #[arg(long, default_value = DEFAULT_TASK_TEMPLATES_FILE)]
+        templates_file: PathBuf,
+    },
+    /// Lists the echelons of a model with their node counts, fees, relative
+    /// performance and open tickets.
+    ListEchelons {
         #[arg(short, long)]
-        output: String,
+        package: Option<String>,
+        #[arg(short, long)]
+        model: String,
     },
-    /// Try to settle a ticket.
-    /// This might be necessary to handle node timeouts.
-    TryToSettle {
+    /// Lists all tasks along with their constraints.
+    ListTasks {
         #[arg(short, long)]
-        ticket_id: String,
+        package: Option<String>,
     },
-}
-
-#[derive(Subcommand)]
-enum TomaCmds {
-    /// Admin command to mint TOMA tokens.
-    Faucet {
-        /// If not provided, we take the value from the env vars.
+    /// Lists all registered nodes with their collateral, reputation,
+    /// subscriptions and last activity epoch.
+    ListNodes {
+        #[arg(short, long)]
+        package: Option<String>,
+        /// Writes the nodes into this CSV file instead of printing them.
         #[arg(long)]
-        toma_package: Option<String>,
+        csv: Option<PathBuf>,
+    },
+    /// Tells clients which region the node runs in, eg. `eu-west`.
+    SetNodeRegion {
         #[arg(short, long)]
-        amount: u64,
+        package: Option<String>,
+        /// At most 32 ASCII characters.
+        #[arg(short, long)]
+        region: String,
     },
-}
-
-#[tokio::main]
-async fn main() -> Result<()> {
-    dotenv().ok();
-    env_logger::init();
-
-    let cli = Cli::parse();
-
-    let wallet_path = cli
-        .wallet
-        .clone()
-        .or_else(|| std::env::var(WALLET_PATH).ok().map(PathBuf::from))
-        .or_else(|| {
-            // let's try the default path
-            //
-            // TODO: will work badly on windows so if anyone is using windows
-            // insert a match statement here and provide the default path
-            Some(PathBuf::from(format!(
-                "{}/.sui/sui_config/client.yaml",
-                env_home_dir()?.display()
-            )))
-        })
-        .expect("Wallet path must be provided");
-
-    let wallet = {
-        if !wallet_path.exists() {
-            return Err(anyhow::anyhow!(
-                "Wallet does not exist at {wallet_path:?}"
-            ));
-        }
-
-        let mut wallet = WalletContext::new(&wallet_path, None, None)?;
-        let active_address = wallet.active_address()?;
-        info!("Active address: {active_address}");
-        wallet
-    };
-
-    if let Some(active_env) = wallet.config.active_env.as_ref() {
-        // loads env specific for the current active environment
-        dotenvy::from_filename_override(format!(".env.{active_env}")).ok();
-    }
-
-    let mut dotenv_conf = DotenvConf::from_env();
-    dotenv_conf.wallet_path = Some(wallet_path);
-
-    if cli.gas_budget.is_some() {
-        dotenv_conf.gas_budget = cli.gas_budget;
-    }
-
-    let mut context = Context {
-        conf: dotenv_conf,
-        wallet,
-    };
-
-    match cli.command {
-        Some(Cmds::Db(DbCmds::CreateTaskEntry {
-            package,
-            role,
-            model_name,
-            security_level,
-            minimum_reputation_score,
-            is_public,
-        })) => {
-            let digest = db::create_task_entry(
-                &mut context.with_optional_atoma_package_id(package),
-                role,
-                model_name,
-                security_level,
-                minimum_reputation_score,
-                is_public,
+    /// Tells the network which version of the node software runs, which
+    /// tasks can require a minimum of.
+    SetNodeSoftwareVersion {
+        #[arg(short, long)]
+        package: Option<String>,
+        /// Eg. `1.4.2`.
+        #[arg(short, long)]
+        software_version: db::SoftwareVersion,
+    },
+    /// Splits the fees of the node among the members of its cooperative
+    /// whenever it's paid out.
+    SetNodePayoutSplit {
+        #[arg(short, long)]
+        package: Option<String>,
+        /// Eg. `0x12..:6000,0x34..:4000`, in basis points that add up to
+        /// 10000.
+        /// No shares remove the split.
+        #[arg(short, long, value_delimiter = ',')]
+        shares: Vec<db::PayoutShare>,
+    },
+    /// How the fees of a node are split.
+    PayoutSplit {
+        #[arg(short, long)]
+        package: Option<String>,
+        /// Defaults to the node owned by the active address.
+        #[arg(short, long)]
+        node_small_id: Option<u64>,
+    },
+    /// Checks a split locally before it's set, eg. that the shares add up
+    /// to 100%.
+    ValidatePayoutSplit {
+        #[arg(short, long, value_delimiter = ',')]
+        shares: Vec<db::PayoutShare>,
+    },
+    /// How many nodes run each version of the node software.
+    NodeVersions {
+        #[arg(short, long)]
+        package: Option<String>,
+        /// Only counts the nodes subscribed to the task, and how many of
+        /// them meet its minimum version.
+        #[arg(short, long)]
+        task_small_id: Option<u64>,
+    },
+    /// Oracle nodes of an echelon report its median latency, which prompts
+    /// that prefer low latency are weighted by.
+    RecordEchelonLatency {
+        #[arg(short, long)]
+        package: Option<String>,
+        #[arg(short, long)]
+        model: String,
+        #[arg(short, long)]
+        echelon: u64,
+        #[arg(long)]
+        latency_p50_ms: u64,
+    },
+    /// Tells the network that the node is online.
+    /// Nodes that don't send one for a while can be swept from their task
+    /// subscriptions.
+    Heartbeat {
+        #[arg(short, long)]
+        package: Option<String>,
+    },
+    /// Removes nodes without a recent heartbeat from the tasks they are
+    /// subscribed to, for a reward per sweep.
+    SweepStaleNodes {
+        #[arg(short, long)]
+        package: Option<String>,
+        /// Only prints the stale subscriptions.
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Checks that the fee treasury holds at least what is owed to nodes
+    /// and referrers and locked in open stacks and tickets.
+    /// Fails on a deficit, so it can be run periodically as an alert.
+    VerifyVault {
+        #[arg(short, long)]
+        package: Option<String>,
+    },
+    /// Prints all global protocol parameters, including the fees and
+    /// settlement timeouts of every model echelon, as one JSON document.
+    Params {
+        #[arg(short, long)]
+        package: Option<String>,
+    },
+    /// Notices of the manager about maintenances, upgrades or parameter
+    /// changes, oldest first.
+    Announcements {
+        #[arg(short, long)]
+        package: Option<String>,
+    },
+    /// Prints the collateral, fees and reputation of a node, including how
+    /// its reputation score decays over the coming epochs.
+    NodeInfo {
+        #[arg(short, long)]
+        package: Option<String>,
+        /// Defaults to the node owned by the active address.
+        #[arg(short, long)]
+        node_small_id: Option<u64>,
+    },
+    /// Exports models, echelons, tasks, nodes, stacks and tickets into a JSON
+    /// file for audits and migrations.
+    Snapshot {
+        #[arg(short, long)]
+        package: Option<String>,
+        /// Where to write the snapshot.
+        #[arg(short, long)]
+        out: PathBuf,
+        /// Continue an interrupted export that was written to the same file.
+        #[arg(short, long)]
+        resume: bool,
+        /// How long to wait between fetching pages to not hit RPC limits.
+        #[arg(long, default_value_t = 100)]
+        page_delay_ms: u64,
+    },
+    /// After a package upgrade, re-resolves the db, faucet and badge IDs
+    /// against the new package and rewrites the env file.
+    Migrate {
+        /// The package to migrate from.
+        /// If not provided, the default from the environment will be used.
+        #[arg(short, long)]
+        package: Option<String>,
+        /// The upgraded package.
+        #[arg(short, long)]
+        new_package: String,
+        /// Defaults to the env file of the active wallet environment.
+        #[arg(short, long)]
+        env_file: Option<PathBuf>,
+    },
+    /// Compares two snapshots and prints added, removed and changed entries.
+    SnapshotDiff {
+        #[arg(long)]
+        old: PathBuf,
+        #[arg(long)]
+        new: PathBuf,
+    },
+    /// Deprecates a task in the database.
+    /// This command marks a task as deprecated, preventing new subscriptions.
+    /// Existing subscriptions and ongoing work are not affected.
+    DeprecateTask {
+        /// Optional package ID. If not provided, the default from the environment will be used.
+        #[arg(short, long)]
+        package: Option<String>,
+        /// The ObjectID of the TaskBadge for the task to be deprecated.
+        #[arg(short, long)]
+        task_badge: String,
+    },
+    /// Removes a deprecated task from the database.
+    /// This command completely removes a task that has been previously deprecated.
+    /// It should only be used when you're certain the task is no longer needed.
+    RemoveDeprecatedTask {
+        /// Optional package ID. If not provided, the default from the environment will be used.
+        #[arg(short, long)]
+        package: Option<String>,
+        /// The ObjectID of the TaskBadge for the deprecated task to be removed.
+        #[arg(short, long)]
+        task_badge: String,
+    },
+    /// Command to subscribe a node to a specific task in the Atoma network.
+    SubscribeNodeToTask {
+        /// Optional package ID. If not provided, the default from the environment will be used.
+        #[arg(short = 'a', long)]
+        package: Option<String>,
+        /// The small ID of the task to subscribe to. This is a unique identifier for the task.
+        #[arg(short, long)]
+        task_small_id: u64,
+        /// The price per compute unit that the node is willing to charge for this task.
+        /// In TOMA, eg. `0.5 TOMA`, or in the smallest unit without a unit.
+        #[arg(short = 'p', long, value_parser = denomination::parse_toma)]
+        price_per_one_million_compute_units: u64,
+    },
+    /// Update the price per one million compute units for a node's subscription to a task.
+    UpdateNodeSubscription {
+        /// Optional package ID. If not provided, the default from the environment will be used.
+        #[arg(short = 'a', long)]
+        package: Option<String>,
+        /// The small ID of the task to update the subscription for.
+        #[arg(short, long)]
+        task_small_id: u64,
+        /// The new price per one million compute units for the node.
+        #[arg(short, long, value_parser = denomination::parse_toma)]
+        price_per_one_million_compute_units: u64,
+    },
+    /// Unsubscribe a node from a specific task in the Atoma network.
+    /// This command removes a node's subscription to a task, preventing it from receiving further work for that task.
+    UnsubscribeNodeFromTask {
+        /// Optional package ID. If not provided, the default from the environment will be used.
+        #[arg(short, long)]
+        package: Option<String>,
+        /// The small ID of the task to unsubscribe from. This is a unique identifier for the task.
+        #[arg(short, long)]
+        task_small_id: u64,
+    },
+    /// Acquires a new stack entry for a specific task.
+    /// This command allows a node to request a new stack entry, which represents
+    /// a unit of work to be performed for a given task.
+    AcquireNewStackEntry {
+        /// Optional package ID. If not provided, the default from the environment will be used.
+        #[arg(short, long)]
+        package: Option<String>,
+        /// The small ID of the task for which to acquire a new stack entry.
+        /// This ID uniquely identifies the task within the Atoma network.
+        #[arg(short, long)]
+        task_small_id: u64,
+        /// The number of compute units requested for this stack entry.
+        /// This represents the amount of computational work the node is willing to perform.
+        #[arg(short, long)]
+        num_compute_units: u64,
+        /// The price (eg. `1.5 TOMA`, or in the smallest unit without a unit) that the node
+        /// is charging for the requested compute units.
+        /// This should be calculated based on the task's requirements and the node's pricing strategy.
+        #[arg(short, long, value_parser = denomination::parse_toma)]
+        price: u64,
+        /// Address that brought the user to the network, eg. a gateway.
+        #[arg(long)]
+        referrer: Option<String>,
+        /// Share of the node's fee for the stack that goes to the referrer.
+        #[arg(long, default_value_t = 0, requires = "referrer")]
+        referral_fee_share_bps: u64,
+    },
+    /// Attempts to settle a stack entry in the Atoma network.
+    /// This command is used by nodes to finalize their work on a stack entry
+    /// and claim the associated rewards.
+    TrySettleStack {
+        /// Optional package ID. If not provided, the default from the environment will be used.
+        #[arg(short, long)]
+        package: Option<String>,
+        /// The small ID of the stack entry to be settled.
+        /// This is a unique identifier for the stack within the Atoma network.
+        #[arg(short, long)]
+        stack_small_id: u64,
+        /// The number of compute units claimed by the node for this stack entry.
+        /// This should reflect the actual work performed by the node.
+        #[arg(short, long)]
+        num_claimed_compute_units: u64,
+        /// The committed stack proof, which is used to verify the node's work.
+        /// This is typically a cryptographic proof or hash of the work performed.
+        #[arg(short, long)]
+        committed_stack_proof: Vec<u8>,
+        /// The Merkle leaf for this stack entry.
+        /// This is part of the Merkle tree structure used for efficient verification
+        /// of the stack's state within the larger Atoma database.
+        #[arg(short, long)]
+        stack_merkle_leaf: Vec<u8>,
+        /// If the transaction fails, store it in this dead-letter file so
+        /// that it can be replayed with `daemon dlq retry`.
+        #[cfg(feature = "daemon")]
+        #[arg(long)]
+        dlq_file: Option<PathBuf>,
+        /// Checks the arguments against the stack's on-chain layout before
+        /// submitting, and explains any mismatch.
+        #[arg(long)]
+        check: bool,
+    },
+    /// Submits a stack settlement attestation for a completed task.
+    /// This command is used by nodes to provide proof of work completion
+    /// and initiate the settlement process for a stack entry.
+    SubmitStackSettlementAttestation {
+        /// Optional package ID. If not provided, the default from the environment will be used.
+        #[arg(short, long)]
+        package: Option<String>,
+        /// The small ID of the stack entry for which the attestation is being submitted.
+        /// This is a unique identifier for the stack within the Atoma network.
+        #[arg(short, long)]
+        stack_small_id: u64,
+        /// The committed stack proof, which is used to verify the node's work.
+        /// This is typically a cryptographic proof or hash of the work performed.
+        #[arg(short, long)]
+        committed_stack_proof: Vec<u8>,
+        /// The Merkle leaf for this stack entry.
+        /// This is part of the Merkle tree structure used for efficient verification
+        /// of the stack's state within the larger Atoma database.
+        #[arg(short, long)]
+        stack_merkle_leaf: Vec<u8>,
+        /// Checks the arguments against the stack's on-chain layout before
+        /// submitting, and explains any mismatch.
+        #[arg(long)]
+        check: bool,
+    },
+    /// Prints the parameters of the sampling consensus security level.
+    GetSamplingParams {
+        #[arg(short, long)]
+        package: Option<String>,
+    },
+    /// Prints the public inputs that an external prover must prove the
+    /// settlement of a stack against.
+    ZkPublicInputs {
+        #[arg(short, long)]
+        stack_small_id: u64,
+        #[arg(short, long)]
+        num_claimed_compute_units: u64,
+        #[arg(short, long)]
+        committed_stack_proof: Vec<u8>,
+        /// Also write the concatenated inputs into this file.
+        #[arg(short, long)]
+        out: Option<PathBuf>,
+    },
+    /// Like `try-settle-stack` but for tasks with a zk verifier.
+    /// The proof replaces attestations by other nodes.
+    TrySettleStackWithZkProof {
+        #[arg(short, long)]
+        package: Option<String>,
+        #[arg(short, long)]
+        stack_small_id: u64,
+        #[arg(short, long)]
+        num_claimed_compute_units: u64,
+        #[arg(short, long)]
+        committed_stack_proof: Vec<u8>,
+        #[arg(long)]
+        stack_merkle_leaf: Vec<u8>,
+        /// File with the arkworks compressed bytes of the Groth16 proof.
+        #[arg(long)]
+        proof: PathBuf,
+    },
+    /// Claims the fees accrued by the active address as a referrer of stacks.
+    ClaimReferralFees {
+        #[arg(short, long)]
+        package: Option<String>,
+    },
+    /// Claims funds for settled tickets in the Atoma network.
+    /// This command allows a node to claim the rewards for successfully completed and settled tasks.
+    ClaimFunds {
+        /// Optional package ID. If not provided, the default from the environment will be used.
+        #[arg(short, long)]
+        package: Option<String>,
+        /// A list of settled stack small IDs for which the node can claim funds.
+        /// Each ID represents a completed and settled stack that the node has performed.
+        #[arg(short, long)]
+        settled_ticket_ids: Vec<u64>,
+        /// If the transaction fails, store it in this dead-letter file so
+        /// that it can be replayed with `daemon dlq retry`.
+        #[cfg(feature = "daemon")]
+        #[arg(long)]
+        dlq_file: Option<PathBuf>,
+    },
+    /// Start an attestation dispute for a stack entry.
+    /// This can only be done by a selected attestation node,
+    /// and should only be done when the attestation node disagrees with the committed stack proof,
+    /// by the original selected node.
+    StartAttestationDispute {
+        /// Optional package ID. If not provided, the default from the environment will be used.
+        #[arg(short, long)]
+        package: Option<String>,
+        /// The small ID of the stack entry for which the attestation is being disputed.
+        /// This is a unique identifier for the stack within the Atoma network.
+        #[arg(short, long)]
+        stack_small_id: u64,
+        /// The commitment to the stack entry that is being disputed.
+        /// This is typically a cryptographic proof or hash of the work performed.  
+        #[arg(short, long)]
+        attestation_commitment: Vec<u8>,
+    },
+    /// Rotates the node's public key commitment and tee attestation bytes.
+    RotateNodePublicKey {
+        #[arg(short, long)]
+        package: Option<String>,
+        /// The new public key commitment.
+        #[arg(short, long)]
+        public_key_commitment: Vec<u8>,
+        /// The new TEE evidence bytes (including the remote attestation bytes and the certificate chain bytes, in the case of Nvidia devices).
+        #[arg(short, long)]
+        evidence_bytes: Vec<u8>,
+        /// The key rotation counter.
+        #[arg(short, long)]
+        key_rotation_counter: u64,
+        /// The device type.
+        #[arg(short, long)]
+        device_type: u16,
+    },
+    /// Lists the nodes whitelisted for a private task and whether they are
+    /// subscribed to it.
+    ShowWhitelist {
+        #[arg(short, long)]
+        package: Option<String>,
+        #[arg(short, long)]
+        task_small_id: u64,
+    },
+}
+
+#[derive(Subcommand)]
+enum GateCmds {
+    /// Submits an example prompt to the network.
+    SubmitTellMeAJokePrompt {
+        #[arg(short, long)]
+        package: Option<String>,
+        #[arg(short, long)]
+        model: String,
+        #[arg(long, default_value_t = 1_000, value_parser = denomination::parse_toma)]
+        max_fee_per_token: u64,
+        /// Higher priority multiplies the fee and favors faster echelons.
+        #[arg(long, value_enum, default_value_t)]
+        priority: gate::Priority,
+        /// Favors echelons with a lower median latency, as reported by
+        /// their oracles.
+        #[arg(long)]
+        prefer_low_latency: bool,
+        /// JSON array of token IDs that precede the prompt.
+        #[arg(long)]
+        pre_prompt_tokens_file: Option<PathBuf>,
+        /// The output starts with the pre-prompt and prompt, which affects
+        /// how many output tokens are billed, see --explain-fees.
+        #[arg(long)]
+        prepend_output_with_input: bool,
+        /// Prints how the prompt would be charged instead of submitting it.
+        #[arg(long)]
+        explain_fees: bool,
+    },
+    /// Submits an example prompt to the network.
+    SubmitGenerateNftPrompt {
+        #[arg(short, long)]
+        package: Option<String>,
+        #[arg(short, long)]
+        model: String,
+        #[arg(long, default_value_t = 1_000, value_parser = denomination::parse_toma)]
+        max_fee_per_token: u64,
+        /// Higher priority multiplies the fee and favors faster echelons.
+        #[arg(long, value_enum, default_value_t)]
+        priority: gate::Priority,
+        /// Favors echelons with a lower median latency, as reported by
+        /// their oracles.
+        #[arg(long)]
+        prefer_low_latency: bool,
+    },
+    /// Cancels all prompts paid by the active address that timed out before
+    /// every node committed to them.
+    /// The committed nodes are paid and the rest of the escrow is refunded.
+    CancelPrompt {
+        #[arg(short, long)]
+        package: Option<String>,
+    },
+    /// Predicts which echelon and nodes the gate would sample for the given
+    /// random numbers, eg. to analyze a dispute.
+    PreviewSampling {
+        #[arg(short, long)]
+        package: Option<String>,
+        #[arg(short, long)]
+        model: String,
+        /// The u256 the echelon is picked with, as hex.
+        #[arg(long)]
+        random: String,
+        /// The u64 a node is picked with, one per chunk of the echelon.
+        /// If not provided, only the echelon is predicted.
+        #[arg(long = "node-random")]
+        node_randoms: Vec<u64>,
+        #[arg(long, default_value_t = 1)]
+        nodes_to_sample: u64,
+        #[arg(long, default_value_t = 1_000, value_parser = denomination::parse_toma)]
+        max_fee_per_token: u64,
+        #[arg(long, value_enum, default_value_t)]
+        priority: gate::Priority,
+        #[arg(long)]
+        prefer_low_latency: bool,
+    },
+    /// Submits a text to text prompt again with the parameters and priority
+    /// of a ticket that timed out or was refunded.
+    Resubmit {
+        #[arg(short, long)]
+        package: Option<String>,
+        #[arg(short, long)]
+        ticket_id: String,
+        /// Required if the ticket no longer exists, otherwise defaults to the
+        /// fee the ticket charges.
+        #[arg(long, value_parser = denomination::parse_toma)]
+        max_fee_per_token: Option<u64>,
+        /// Raises the max fee per token by this percentage.
+        #[arg(long, default_value_t = 0)]
+        fee_bump_percent: u64,
+        /// Where the new ticket is linked to the old one.
+        #[arg(long, default_value = DEFAULT_RESUBMISSIONS_FILE)]
+        links_file: PathBuf,
+    },
+}
+
+#[derive(Subcommand)]
+enum SettlementCmds {
+    /// Lists all open tickets.
+    ListTickets {
+        #[arg(short, long)]
+        package: Option<String>,
+    },
+    /// Submit a commitment to settle a ticket.
+    /// This can be only used for text to text models.
+    SubmitCommitment {
+        #[arg(short, long)]
+        ticket_id: String,
+        #[arg(short, long, required_unless_present = "segments")]
+        output: Option<String>,
+        /// If the prompt output was streamed, a JSONL file with one JSON
+        /// string per streamed segment.
+        #[arg(short, long, conflicts_with = "output")]
+        segments: Option<PathBuf>,
+        /// Marks the ticket as committed in this queue file.
+        #[cfg(feature = "daemon")]
+        #[arg(long)]
+        queue_file: Option<PathBuf>,
+    },
+    /// Settles a timed out ticket with the nodes that committed and refunds
+    /// the share of the missing ones.
+    /// Prints what would be paid and refunded before settling.
+    Partial {
+        #[arg(short, long)]
+        ticket_id: String,
+        /// Settles without asking for confirmation.
+        #[arg(short, long)]
+        yes: bool,
+    },
+    /// Prints the rolling commitment after each segment of a streamed output.
+    StreamCommitment {
+        /// A JSONL file with one JSON string per streamed segment.
+        #[arg(short, long)]
+        segments: PathBuf,
+    },
+    /// Try to settle a ticket.
+    /// This might be necessary to handle node timeouts.
+    TryToSettle {
+        #[arg(short, long)]
+        ticket_id: String,
+        /// If the transaction fails, store it in this dead-letter file so
+        /// that it can be replayed with `daemon dlq retry`.
+        #[cfg(feature = "daemon")]
+        #[arg(long)]
+        dlq_file: Option<PathBuf>,
+        /// Marks the ticket as settled in this queue file.
+        #[cfg(feature = "daemon")]
+        #[arg(long)]
+        queue_file: Option<PathBuf>,
+    },
+    /// Removes stacks that were claimed long enough ago for a bounty from
+    /// the communal treasury, if it's worth more than the gas.
+    Gc {
+        #[arg(short, long)]
+        package: Option<String>,
+        /// What one SUI is worth in TOMA, to compare the gas with the
+        /// bounty.
+        #[arg(long, value_parser = denomination::parse_toma)]
+        sui_price: u64,
+        /// How many stacks are collected in one transaction at most.
+        #[arg(long, default_value_t = 100)]
+        max_stacks: usize,
+        /// Keeps collecting every this many seconds instead of once.
+        #[arg(long)]
+        interval_secs: Option<u64>,
+    },
+}
+
+#[derive(Subcommand)]
+enum RefundsCmds {
+    /// Recovers the escrow of every ticket paid by the active address that
+    /// timed out: those no node committed to are cancelled, the others
+    /// settled partially.
+    /// The calls are batched and the total TOMA recovered is reported.
+    Sweep {
+        #[arg(short, long)]
+        package: Option<String>,
+        /// How many tickets are refunded in one transaction at most.
+        #[arg(long, default_value_t = 50)]
+        max_tickets: usize,
+        /// Keeps sweeping every this many seconds instead of once.
+        #[arg(long)]
+        interval_secs: Option<u64>,
+    },
+}
+
+#[derive(Subcommand)]
+enum InsuranceCmds {
+    /// Insures the node owned by the active address.
+    /// From then on, it pays a premium for every stack it claims.
+    Join {
+        #[arg(short, long)]
+        package: Option<String>,
+    },
+    /// The premiums paid so far stay in the pool.
+    Leave {
+        #[arg(short, long)]
+        package: Option<String>,
+    },
+    /// Prints the pool balance and payout rules.
+    Status {
+        #[arg(short, long)]
+        package: Option<String>,
+        /// Also prints whether this node is insured.
+        #[arg(short, long)]
+        node_small_id: Option<u64>,
+    },
+    /// Files a claim for a stack owned by the active address whose node is
+    /// insured.
+    FileClaim {
+        #[arg(short, long)]
+        package: Option<String>,
+        #[arg(short, long)]
+        stack_small_id: u64,
+        /// At most what the stack cost.
+        #[arg(short, long, value_parser = denomination::parse_toma)]
+        amount: u64,
+    },
+}
+
+#[derive(Subcommand)]
+enum OfferCmds {
+    /// Registers the Ed25519 key of the active address as the key that the
+    /// node owned by it signs offers with.
+    SetKey {
+        #[arg(short, long)]
+        package: Option<String>,
+    },
+    /// Signs an offer with the key of the node owned by the active address
+    /// and appends it to the order book.
+    Post {
+        #[arg(short, long)]
+        package: Option<String>,
+        #[arg(short, long)]
+        task_small_id: u64,
+        #[arg(long, value_parser = denomination::parse_toma)]
+        price_per_one_million_compute_units: u64,
+        #[arg(short, long)]
+        num_compute_units: u64,
+        /// How long the offer can be accepted for.
+        #[arg(long, default_value_t = 3600)]
+        expires_in_secs: u64,
+        /// Each offer of a node can be accepted once per nonce.
+        /// Defaults to the current time in ms.
+        #[arg(long)]
+        nonce: Option<u64>,
+        #[arg(long, default_value = DEFAULT_OFFER_BOOK_FILE)]
+        book: PathBuf,
+    },
+    /// Prints the open offers in the order book, cheapest first.
+    List {
+        #[arg(short, long)]
+        task_small_id: Option<u64>,
+        #[arg(long, default_value = DEFAULT_OFFER_BOOK_FILE)]
+        book: PathBuf,
+    },
+    /// Acquires a stack with an offer from the order book, the cheapest open
+    /// one for the task unless a node and nonce are given.
+    Accept {
+        #[arg(short, long)]
+        package: Option<String>,
+        #[arg(short, long)]
+        task_small_id: u64,
+        #[arg(long, requires = "nonce")]
+        node_small_id: Option<u64>,
+        #[arg(long, requires = "node_small_id")]
+        nonce: Option<u64>,
+        #[arg(long, default_value = DEFAULT_OFFER_BOOK_FILE)]
+        book: PathBuf,
+    },
+    /// Cancels an offer of the node owned by the active address, even if
+    /// it's still in the order book.
+    Cancel {
+        #[arg(short, long)]
+        package: Option<String>,
+        #[arg(long)]
+        nonce: u64,
+    },
+}
+
+#[derive(Subcommand)]
+enum QuoteCmds {
+    /// Signs a quote for a stack with the key of the node owned by the active
+    /// address, which must be the key registered with `offer set-key`.
+    Issue {
+        #[arg(short, long)]
+        package: Option<String>,
+        #[arg(short, long)]
+        task_small_id: u64,
+        #[arg(long, value_parser = denomination::parse_toma)]
+        price_per_one_million_compute_units: u64,
+        #[arg(short, long)]
+        num_compute_units: u64,
+        /// How long the quote can be accepted for.
+        #[arg(long, default_value_t = 300)]
+        expires_in_secs: u64,
+        /// The JSON file to write the signed quote into.
+        #[arg(short, long)]
+        out: PathBuf,
+    },
+    /// Checks that a quote has not expired and that it was signed by the
+    /// owner of the quoting node, and prints what accepting it costs.
+    Verify {
+        #[arg(short, long)]
+        package: Option<String>,
+        #[arg(short, long)]
+        quote: PathBuf,
+    },
+    /// Verifies a quote and acquires a stack from the quoting node at the
+    /// quoted price.
+    Accept {
+        #[arg(short, long)]
+        package: Option<String>,
+        #[arg(short, long)]
+        quote: PathBuf,
+    },
+    /// Prints what a stack costs, including the sampling consensus charges
+    /// of the task's security level.
+    Estimate {
+        #[arg(short, long)]
+        package: Option<String>,
+        #[arg(short, long)]
+        task_small_id: u64,
+        #[arg(long, value_parser = denomination::parse_toma)]
+        price_per_one_million_compute_units: u64,
+        #[arg(short, long)]
+        num_compute_units: u64,
+    },
+}
+
+#[derive(Subcommand)]
+enum ReceiptsCmds {
+    /// Signs a receipt for a request served with a stack, with the key of
+    /// the node owned by the active address.
+    /// Prints the receipt as a line of JSON.
+    Sign {
+        #[arg(short, long)]
+        package: Option<String>,
+        #[arg(short, long)]
+        stack_small_id: u64,
+        /// The request body as signed by the stack owner.
+        #[arg(short, long)]
+        payload: String,
+        /// Charged for this request.
+        #[arg(short, long)]
+        compute_units: u64,
+        /// Charged for the stack so far, including this request.
+        #[arg(short, long)]
+        running_total: u64,
+    },
+    /// Checks the receipts of a stack before disputing its settlement.
+    ///
+    /// The signatures, the running totals and duplicates are checked, and
+    /// the total is compared with what the node settled on-chain.
+    Verify {
+        #[arg(short, long)]
+        package: Option<String>,
+        #[arg(short, long)]
+        stack_small_id: u64,
+        /// A JSONL file with a receipt per line, as printed by `sign`.
+        #[arg(short, long)]
+        receipts: PathBuf,
+    },
+}
+
+#[derive(Subcommand)]
+enum StackCmds {
+    /// Signs an inference request for a stack owned by the active address.
+    /// Prints the base64 encoded signature.
+    SignRequest {
+        #[arg(short, long)]
+        stack_small_id: u64,
+        /// Must be unique per request so that it cannot be replayed.
+        #[arg(short, long)]
+        nonce: u64,
+        /// The request body as sent to the node.
+        #[arg(short, long)]
+        payload: String,
+    },
+    /// Checks that a request was signed by the owner of the stack.
+    VerifyRequest {
+        #[arg(long)]
+        package: Option<String>,
+        #[arg(short, long)]
+        stack_small_id: u64,
+        #[arg(short, long)]
+        nonce: u64,
+        #[arg(short, long)]
+        payload: String,
+        /// Base64 encoded signature as printed by `sign-request`.
+        #[arg(long)]
+        signature: String,
+    },
+    /// Compares the node's local usage log against the compute units the
+    /// node settled and claimed on-chain, per stack.
+    ///
+    /// The log is a JSONL file where each line has `stack_small_id` and
+    /// `compute_units` fields.
+    Reconcile {
+        #[arg(short, long)]
+        package: Option<String>,
+        #[arg(short, long)]
+        usage_log: PathBuf,
+    },
+    /// Compute units consumed versus purchased, effective cost per request
+    /// and projected exhaustion of a stack.
+    Usage {
+        #[arg(short, long)]
+        package: Option<String>,
+        #[arg(short, long)]
+        stack_small_id: u64,
+        /// A JSONL file where each line has `stack_small_id`,
+        /// `compute_units` and optionally `timestamp_ms` fields.
+        #[arg(short, long)]
+        request_log: Option<PathBuf>,
+    },
+    /// Terminates a stack before it's exhausted with the consent of both
+    /// its owner and its node.
+    #[command(subcommand)]
+    CloseEarly(CloseEarlyCmds),
+}
+
+#[derive(Subcommand)]
+enum CloseEarlyCmds {
+    /// Proposes to settle the consumed compute units of the stack now and
+    /// refund the rest, replacing any pending proposal.
+    Propose {
+        #[arg(short, long)]
+        package: Option<String>,
+        #[arg(short, long)]
+        stack_small_id: u64,
+        #[arg(short, long)]
+        num_consumed_compute_units: u64,
+        /// Proposes as the node of the stack instead of its owner.
+        #[arg(long)]
+        as_node: bool,
+    },
+    /// Accepts the other party's proposal, which closes the stack.
+    Accept {
+        #[arg(short, long)]
+        package: Option<String>,
+        #[arg(short, long)]
+        stack_small_id: u64,
+        /// Must match the proposal.
+        #[arg(short, long)]
+        num_consumed_compute_units: u64,
+        /// Accepts as the node of the stack instead of its owner.
+        #[arg(long)]
+        as_node: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum ReleaseCmds {
+    /// Signs a binary with the key of the active address.
+    Sign {
+        #[arg(short, long)]
+        binary: PathBuf,
+    },
+    /// Checks that a binary was signed by the release key.
+    /// Doesn't need a wallet.
+    Verify {
+        #[arg(short, long)]
+        binary: PathBuf,
+        /// Base64 encoded signature as printed by `release sign`.
+        #[arg(long)]
+        signature: String,
+        /// Address of the release key.
+        #[arg(long)]
+        signer: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum TomaCmds {
+    /// Balance of the sender in the current TOMA and in the older
+    /// generations of `TOMA_LEGACY_COIN_TYPES`.
+    Balances {
+        /// If not provided, we take the value from the env vars.
+        #[arg(long)]
+        toma_package: Option<String>,
+    },
+    /// Admin command that opens a one to one swap of an older TOMA
+    /// generation for TOMA, funded by the active address.
+    CreateMigration {
+        /// If not provided, we take the value from the env vars.
+        #[arg(long)]
+        toma_package: Option<String>,
+        /// The publisher object of the TOMA package.
+        #[arg(long)]
+        publisher: ObjectID,
+        /// Eg. `0x12..::toma::TOMA`.
+        #[arg(long)]
+        old_coin_type: String,
+        /// How much TOMA the swap is funded with.
+        #[arg(short, long, value_parser = denomination::parse_toma)]
+        amount: u64,
+    },
+    /// Admin command to mint TOMA tokens.
+    Faucet {
+        /// If not provided, we take the value from the env vars.
+        #[arg(long)]
+        toma_package: Option<String>,
+        #[arg(short, long, value_parser = denomination::parse_toma)]
+        amount: u64,
+    },
+    /// Serves an HTTP faucet that drips TOMA to API key holders.
+    /// The active address signs the faucet transactions and pays for gas.
+    FaucetServer {
+        /// If not provided, we take the value from the env vars.
+        #[arg(long)]
+        toma_package: Option<String>,
+        #[arg(long, default_value = "127.0.0.1:8080")]
+        listen: SocketAddr,
+        /// How much TOMA every drip mints.
+        #[arg(short, long, value_parser = denomination::parse_toma)]
+        amount: u64,
+        /// File with one API key per line.
+        #[arg(long)]
+        api_keys_file: PathBuf,
+        /// How long an address or API key has to wait between drips.
+        #[arg(long, default_value_t = 24 * 60 * 60)]
+        cooldown_secs: u64,
+        /// Most drips handed out in a 24 hour window.
+        #[arg(long, default_value_t = 1_000)]
+        daily_cap: u64,
+    },
+    /// Swaps the coins of the sender in the older generations of
+    /// `TOMA_LEGACY_COIN_TYPES` for TOMA.
+    MigrateCoins {
+        /// If not provided, we take the value from the env vars.
+        #[arg(long)]
+        toma_package: Option<String>,
+    },
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    dotenv().ok();
+    env_logger::init();
+
+    let cli = Cli::parse();
+
+    // these don't need a wallet
+    match &cli.command {
+        Some(Cmds::Completion { shell }) => {
+            completion::command(Cli::command(), *shell);
+            return Ok(());
+        }
+        Some(Cmds::Manpage { out_dir }) => {
+            return manpage::command(Cli::command(), out_dir.as_deref());
+        }
+        Some(Cmds::Version { verbose }) => {
+            release::version(*verbose);
+            return Ok(());
+        }
+        Some(Cmds::Release(ReleaseCmds::Verify {
+            binary,
+            signature,
+            signer,
+        })) => {
+            release::verify(binary, signature, SuiAddress::from_str(signer)?)?;
+            println!("{binary:?} was signed by {signer}");
+            return Ok(());
+        }
+        _ => {}
+    }
+
+    let wallet_path = cli
+        .wallet
+        .clone()
+        .or_else(|| std::env::var(WALLET_PATH).ok().map(PathBuf::from))
+        .or_else(|| {
+            // let's try the default path
+            //
+            // TODO: will work badly on windows so if anyone is using windows
+            // insert a match statement here and provide the default path
+            Some(PathBuf::from(format!(
+                "{}/.sui/sui_config/client.yaml",
+                env_home_dir()?.display()
+            )))
+        })
+        .expect("Wallet path must be provided");
+
+    if let Some(Cmds::Init { .. }) = &cli.command {
+        init::ensure_wallet(&wallet_path)?;
+    }
+
+    let mut wallet = {
+        if !wallet_path.exists() {
+            return Err(anyhow::anyhow!(
+                "Wallet does not exist at {wallet_path:?}"
+            ));
+        }
+
+        WalletContext::new(&wallet_path, None, None)?
+    };
+
+    if let Some(active_env) = wallet.config.active_env.as_ref() {
+        // loads env specific for the current active environment
+        dotenvy::from_filename_override(format!(".env.{active_env}")).ok();
+    }
+
+    let mut dotenv_conf = DotenvConf::from_env();
+    if let Some(timeout) = cli.timeout {
+        dotenv_conf.timeouts =
+            timeouts::Timeouts::all(Duration::from_secs(timeout));
+    }
+    if let Some(rpc_timeout) = dotenv_conf.timeouts.rpc {
+        // the env of the active environment is only known once the wallet
+        // is loaded, and the client is created lazily so it's not too late
+        wallet = WalletContext::new(&wallet_path, Some(rpc_timeout), None)?;
+    }
+    dotenv_conf.wallet_path = Some(wallet_path);
+    // the profile of the active environment selects the secrets
+    if let Some(key) = secrets::from_env(secrets::WALLET_KEY_SECRET).await? {
+        secrets::use_wallet_key(&mut wallet, &key)?;
+    }
+    let active_address = wallet.active_address()?;
+    info!("Active address: {active_address}");
+    #[cfg(feature = "daemon")]
+    if let Some(alert_url) =
+        secrets::from_env(secrets::WATCHDOG_ALERT_URL_SECRET).await?
+    {
+        dotenv_conf.watchdog.alert_url = Some(alert_url);
+    }
+
+    if cli.gas_budget.is_some() {
+        dotenv_conf.gas_budget = cli.gas_budget;
+    }
+    dotenv_conf.progress = cli.progress;
+    if let Some(wait_for) = cli.wait_for {
+        dotenv_conf.wait_for = wait_for;
+    }
+    dotenv_conf.wait_timeout = Duration::from_secs(cli.wait_timeout_secs);
+    dotenv_conf.raw_amounts = cli.raw;
+    if let Some(wait_for_coin) = cli.wait_for_coin {
+        dotenv_conf.wait_for_coin = Duration::from_secs(wait_for_coin);
+    }
+    if cli.read_only {
+        dotenv_conf.role = role::Role::ReadOnly;
+    }
+    if let Some(Cmds::Admin(_)) = &cli.command {
+        dotenv_conf.role.ensure_can_administer()?;
+    }
+
+    let mut context = Context {
+        conf: dotenv_conf,
+        wallet,
+    };
+
+    match cli.command {
+        Some(Cmds::Explain { digest }) => {
+            explain::command(&mut context, digest).await?;
+        }
+        Some(Cmds::Analytics(AnalyticsCmds::TaskPrices {
+            package,
+            task_small_id,
+            bucket_hours,
+        })) => {
+            analytics::task_prices(
+                &mut context.with_optional_atoma_package_id(package),
+                task_small_id,
+                bucket_hours,
+            )
+            .await?;
+        }
+        Some(Cmds::Analytics(AnalyticsCmds::EchelonFees {
+            package,
+            model,
+        })) => {
+            analytics::echelon_fees(
+                &mut context.with_optional_atoma_package_id(package),
+                model.as_deref(),
+            )
+            .await?;
+        }
+        Some(Cmds::Analytics(AnalyticsCmds::SettlementLatency { package })) => {
+            analytics::settlement_latency(
+                &mut context.with_optional_atoma_package_id(package),
+            )
+            .await?;
+        }
+        Some(Cmds::Analytics(AnalyticsCmds::DisputeRates {
+            package,
+            node_small_id,
+        })) => {
+            analytics::dispute_rates(
+                &mut context.with_optional_atoma_package_id(package),
+                node_small_id,
+            )
+            .await?;
+        }
+        Some(Cmds::Bench(BenchCmds::Run {
+            package,
+            target,
+            model,
+            iterations,
+            record,
+            replay,
+            max_rpc_calls,
+            max_mean_wall_time_ms,
+            report,
+        })) => {
+            let rpc_fixtures = record
+                .map(bench::RpcFixtures::Record)
+                .or(replay.map(bench::RpcFixtures::Replay));
+            bench::run(
+                &mut context.with_optional_atoma_package_id(package),
+                target,
+                model.as_deref(),
+                iterations,
+                rpc_fixtures,
+                bench::Gates {
+                    max_rpc_calls,
+                    max_mean_wall_time: max_mean_wall_time_ms
+                        .map(Duration::from_millis),
+                },
+                report.as_deref(),
+            )
+            .await?;
+        }
+        Some(Cmds::Chaos(ChaosCmds::Run {
+            package,
+            manifest,
+            faults,
+        })) => {
+            chaos::run(
+                &mut context.with_optional_atoma_package_id(package),
+                &manifest,
+                &faults,
+            )
+            .await?;
+        }
+        #[cfg(feature = "daemon")]
+        Some(Cmds::Daemon(DaemonCmds::Dlq(DlqCmds::List { dlq_file }))) => {
+            daemon::dlq_list(&dlq_file)?;
+        }
+        #[cfg(feature = "daemon")]
+        Some(Cmds::Daemon(DaemonCmds::Dlq(DlqCmds::Retry {
+            dlq_file,
+            id,
+        }))) => {
+            let remaining =
+                daemon::dlq_retry(&mut context, &dlq_file, id).await?;
+
+            println!("{remaining} action(s) left in {dlq_file:?}");
+        }
+        #[cfg(feature = "daemon")]
+        Some(Cmds::Daemon(DaemonCmds::Dlq(DlqCmds::Discard {
+            dlq_file,
+            id,
+        }))) => {
+            daemon::dlq_discard(&dlq_file, id)?;
+        }
+        #[cfg(feature = "daemon")]
+        Some(Cmds::Daemon(DaemonCmds::ParamsWatch {
+            package,
+            history_file,
+            interval_secs,
+            once,
+        })) => {
+            daemon::params_watch(
+                &mut context.with_optional_atoma_package_id(package),
+                &history_file,
+                Duration::from_secs(interval_secs),
+                once,
+            )
+            .await?;
+        }
+        #[cfg(feature = "daemon")]
+        Some(Cmds::Daemon(DaemonCmds::Queue(QueueCmds::List {
+            queue_file,
+            pending,
+        }))) => {
+            daemon::queue_list(&queue_file, pending)?;
+        }
+        #[cfg(feature = "daemon")]
+        Some(Cmds::Daemon(DaemonCmds::Queue(QueueCmds::Record {
+            queue_file,
+            ticket_id,
+            state,
+        }))) => {
+            daemon::queue_transition(&queue_file, &ticket_id, state)?;
+        }
+        #[cfg(feature = "daemon")]
+        Some(Cmds::Daemon(DaemonCmds::Watchdog(WatchdogCmds::Status))) => {
+            daemon::watchdog_status(&context)?;
+        }
+        Some(Cmds::Fixtures(FixturesCmds::Generate {
+            package,
+            nodes,
+            models,
+            tasks,
+            stacks,
+            security_level,
+            seed,
+            gas_per_node,
+            out,
+        })) => {
+            fixtures::generate(
+                &mut context.with_optional_atoma_package_id(package),
+                fixtures::FixtureSizes {
+                    nodes,
+                    models,
+                    tasks,
+                    stacks,
+                },
+                security_level,
+                seed,
+                gas_per_node,
+                &out,
+            )
+            .await?;
+        }
+        Some(Cmds::Admin(AdminCmds::CreateTaskEntry {
+            package,
+            template,
+            templates_file,
+            role,
+            model_name,
+            security_level,
+            minimum_reputation_score,
+            is_public,
+            constraints,
+        })) => {
+            let mut params = db::TaskParams {
+                role,
+                model_name,
+                security_level,
+                minimum_reputation_score,
+                is_public,
+                constraints,
+            };
+            if let Some(template) = template {
+                params = db::expand_task_template(
+                    &template,
+                    &templates_file,
+                    params,
+                )?;
+            }
+            let db::TaskParams {
+                role,
+                model_name,
+                security_level,
+                minimum_reputation_score,
+                is_public,
+                constraints,
+            } = params;
+
+            let digest = db::create_task_entry(
+                &mut context.with_optional_atoma_package_id(package),
+                role.ok_or_else(|| anyhow!("Task role is required"))?,
+                model_name,
+                security_level,
+                minimum_reputation_score,
+                is_public,
+                constraints,
+            )
+            .await?;
+
+            println!("{digest}");
+        }
+        Some(Cmds::Db(DbCmds::ListTaskTemplates { templates_file })) => {
+            db::list_task_templates(&templates_file)?;
+        }
+        Some(Cmds::Db(DbCmds::ListEchelons { package, model })) => {
+            db::list_echelons(
+                &mut context.with_optional_atoma_package_id(package),
+                &model,
+            )
+            .await?;
+        }
+        Some(Cmds::Admin(AdminCmds::SetEchelonSettlementTimeout {
+            package,
+            model,
+            echelon,
+            timeout_ms,
+        })) => {
+            let digest = db::set_model_echelon_settlement_timeout(
+                &mut context.with_optional_atoma_package_id(package),
+                &model,
+                echelon,
+                timeout_ms,
+            )
+            .await?;
+
+            println!("{digest}");
+        }
+        Some(Cmds::Db(DbCmds::ListTasks { package })) => {
+            db::list_tasks(
+                &mut context.with_optional_atoma_package_id(package),
+            )
+            .await?;
+        }
+        Some(Cmds::Db(DbCmds::ListNodes { package, csv })) => {
+            db::list_nodes(
+                &mut context.with_optional_atoma_package_id(package),
+                csv.as_deref(),
+            )
+            .await?;
+        }
+        Some(Cmds::Db(DbCmds::SetNodeRegion { package, region })) => {
+            let digest = db::set_node_region(
+                &mut context.with_optional_atoma_package_id(package),
+                &region,
+            )
+            .await?;
+
+            println!("{digest}");
+        }
+        Some(Cmds::Db(DbCmds::SetNodeSoftwareVersion {
+            package,
+            software_version,
+        })) => {
+            let digest = db::set_node_software_version(
+                &mut context.with_optional_atoma_package_id(package),
+                software_version,
+            )
+            .await?;
+
+            println!("{digest}");
+        }
+        Some(Cmds::Db(DbCmds::SetNodePayoutSplit { package, shares })) => {
+            let digest = db::set_node_payout_split(
+                &mut context.with_optional_atoma_package_id(package),
+                shares,
+            )
+            .await?;
+
+            println!("{digest}");
+        }
+        Some(Cmds::Db(DbCmds::PayoutSplit {
+            package,
+            node_small_id,
+        })) => {
+            db::payout_split(
+                &mut context.with_optional_atoma_package_id(package),
+                node_small_id,
+            )
+            .await?;
+        }
+        Some(Cmds::Db(DbCmds::ValidatePayoutSplit { shares })) => {
+            db::validate_payout_split(&shares)?;
+        }
+        Some(Cmds::Db(DbCmds::NodeVersions {
+            package,
+            task_small_id,
+        })) => {
+            db::node_versions(
+                &mut context.with_optional_atoma_package_id(package),
+                task_small_id,
+            )
+            .await?;
+        }
+        Some(Cmds::Db(DbCmds::RecordEchelonLatency {
+            package,
+            model,
+            echelon,
+            latency_p50_ms,
+        })) => {
+            let digest = db::record_model_echelon_latency(
+                &mut context.with_optional_atoma_package_id(package),
+                &model,
+                echelon,
+                latency_p50_ms,
+            )
+            .await?;
+
+            println!("{digest}");
+        }
+        Some(Cmds::Db(DbCmds::Heartbeat { package })) => {
+            let digest = db::heartbeat(
+                &mut context.with_optional_atoma_package_id(package),
+            )
+            .await?;
+
+            println!("{digest}");
+        }
+        Some(Cmds::Db(DbCmds::SweepStaleNodes { package, dry_run })) => {
+            db::sweep_stale_nodes(
+                &mut context.with_optional_atoma_package_id(package),
+                dry_run,
+            )
+            .await?;
+        }
+        Some(Cmds::Db(DbCmds::VerifyVault { package })) => {
+            db::verify_vault(
+                &mut context.with_optional_atoma_package_id(package),
+            )
+            .await?;
+        }
+        Some(Cmds::Debug(DebugCmds::DecodeEnvelope {
+            package,
+            ticket_id,
+            hex,
+        })) => {
+            debug::decode_envelope(
+                &mut context.with_optional_atoma_package_id(package),
+                ticket_id.as_deref(),
+                hex.as_deref(),
+            )
+            .await?;
+        }
+        Some(Cmds::Db(DbCmds::Params { package })) => {
+            db::params(&mut context.with_optional_atoma_package_id(package))
+                .await?;
+        }
+        Some(Cmds::Db(DbCmds::Announcements { package })) => {
+            db::announcements(
+                &mut context.with_optional_atoma_package_id(package),
+            )
+            .await?;
+        }
+        Some(Cmds::Db(DbCmds::NodeInfo {
+            package,
+            node_small_id,
+        })) => {
+            db::node_info(
+                &mut context.with_optional_atoma_package_id(package),
+                node_small_id,
+            )
+            .await?;
+        }
+        Some(Cmds::Db(DbCmds::Snapshot {
+            package,
+            out,
+            resume,
+            page_delay_ms,
+        })) => {
+            db::snapshot(
+                &mut context.with_optional_atoma_package_id(package),
+                &out,
+                resume,
+                Duration::from_millis(page_delay_ms),
+            )
+            .await?;
+        }
+        Some(Cmds::Db(DbCmds::Migrate {
+            package,
+            new_package,
+            env_file,
+        })) => {
+            let env_file = env_file.unwrap_or_else(|| {
+                match context.wallet.config.active_env.as_ref() {
+                    Some(active_env) => format!(".env.{active_env}").into(),
+                    None => ".env".into(),
+                }
+            });
+            db::migrate(
+                &mut context.with_optional_atoma_package_id(package),
+                ObjectID::from_str(&new_package)?,
+                &env_file,
+            )
+            .await?;
+        }
+        Some(Cmds::Db(DbCmds::SnapshotDiff { old, new })) => {
+            let differences = db::snapshot_diff(&old, &new)?;
+            println!("{differences} difference(s)");
+        }
+        Some(Cmds::Admin(AdminCmds::WhitelistNodesForTask {
+            package,
+            task_small_id,
+            nodes_small_ids,
+        })) => {
+            let digest = db::whitelist_nodes_for_task(
+                &mut context.with_optional_atoma_package_id(package),
+                task_small_id,
+                nodes_small_ids,
+            )
+            .await?;
+
+            println!("{digest}");
+        }
+        Some(Cmds::Admin(AdminCmds::RemoveNodeFromTaskWhitelist {
+            package,
+            task_small_id,
+            nodes_small_ids,
+        })) => {
+            let digest = db::remove_node_from_task_whitelist(
+                &mut context.with_optional_atoma_package_id(package),
+                task_small_id,
+                nodes_small_ids,
+            )
+            .await?;
+
+            println!("{digest}");
+        }
+        Some(Cmds::Db(DbCmds::ShowWhitelist {
+            package,
+            task_small_id,
+        })) => {
+            db::show_whitelist(
+                &mut context.with_optional_atoma_package_id(package),
+                task_small_id,
+            )
+            .await?;
+        }
+        Some(Cmds::Db(DbCmds::DeprecateTask {
+            package,
+            task_badge,
+        })) => {
+            let digest = db::deprecate_task(
+                &mut context.with_optional_atoma_package_id(package),
+                ObjectID::from_str(&task_badge)?,
+            )
+            .await?;
+
+            println!("{digest}");
+        }
+        Some(Cmds::Db(DbCmds::RemoveDeprecatedTask {
+            package,
+            task_badge,
+        })) => {
+            let digest = db::remove_deprecated_task(
+                &mut context.with_optional_atoma_package_id(package),
+                ObjectID::from_str(&task_badge)?,
+            )
+            .await?;
+
+            println!("{digest}");
+        }
+        Some(Cmds::Db(DbCmds::SubscribeNodeToTask {
+            package,
+            task_small_id,
+            price_per_one_million_compute_units,
+        })) => {
+            let digest = db::subscribe_node_to_task(
+                &mut context.with_optional_atoma_package_id(package),
+                task_small_id,
+                price_per_one_million_compute_units,
+            )
+            .await?;
+
+            println!("{digest}");
+        }
+        Some(Cmds::Db(DbCmds::UpdateNodeSubscription {
+            package,
+            task_small_id,
+            price_per_one_million_compute_units,
+        })) => {
+            let digest = db::update_node_subscription(
+                &mut context.with_optional_atoma_package_id(package),
+                task_small_id,
+                price_per_one_million_compute_units,
+            )
+            .await?;
+
+            println!("{digest}");
+        }
+        Some(Cmds::Db(DbCmds::UnsubscribeNodeFromTask {
+            package,
+            task_small_id,
+        })) => {
+            let digest = db::unsubscribe_node_from_task(
+                &mut context.with_optional_atoma_package_id(package),
+                task_small_id,
+            )
+            .await?;
+
+            println!("{digest}");
+        }
+        Some(Cmds::Db(DbCmds::AcquireNewStackEntry {
+            package,
+            task_small_id,
+            num_compute_units,
+            price,
+            referrer,
+            referral_fee_share_bps,
+        })) => {
+            let digest = db::acquire_new_stack_entry(
+                &mut context.with_optional_atoma_package_id(package),
+                task_small_id,
+                num_compute_units,
+                price,
+                referrer.as_deref().map(FromStr::from_str).transpose()?,
+                referral_fee_share_bps,
+            )
+            .await?;
+
+            println!("{digest}");
+        }
+        Some(Cmds::Admin(AdminCmds::SetSamplingParams {
+            package,
+            cross_validation_probability_permille,
+            cross_validation_extra_nodes_count,
+            sampling_consensus_charge_permille,
+            cross_validation_extra_nodes_charge_permille,
+        })) => {
+            let digest = db::set_sampling_params(
+                &mut context.with_optional_atoma_package_id(package),
+                cross_validation_probability_permille,
+                cross_validation_extra_nodes_count,
+                sampling_consensus_charge_permille,
+                cross_validation_extra_nodes_charge_permille,
+            )
+            .await?;
+
+            println!("{digest}");
+        }
+        Some(Cmds::Db(DbCmds::GetSamplingParams { package })) => {
+            db::get_sampling_params(
+                &mut context.with_optional_atoma_package_id(package),
+            )
+            .await?;
+        }
+        Some(Cmds::Admin(AdminCmds::SetTaskZkVerifier {
+            package,
+            task_small_id,
+            curve,
+            verifying_key,
+        })) => {
+            let digest = db::set_task_zk_verifier(
+                &mut context.with_optional_atoma_package_id(package),
+                task_small_id,
+                curve,
+                &verifying_key,
+            )
+            .await?;
+
+            println!("{digest}");
+        }
+        Some(Cmds::Admin(AdminCmds::RemoveTaskZkVerifier {
+            package,
+            task_small_id,
+        })) => {
+            let digest = db::remove_task_zk_verifier(
+                &mut context.with_optional_atoma_package_id(package),
+                task_small_id,
+            )
+            .await?;
+
+            println!("{digest}");
+        }
+        Some(Cmds::Admin(AdminCmds::SetTaskMinNodeVersion {
+            package,
+            task_small_id,
+            min_version,
+        })) => {
+            let digest = db::set_task_min_node_version(
+                &mut context.with_optional_atoma_package_id(package),
+                task_small_id,
+                min_version,
+            )
+            .await?;
+
+            println!("{digest}");
+        }
+        Some(Cmds::Admin(AdminCmds::SetTaskMinPrice {
+            package,
+            task_small_id,
+            min_price_per_one_million_compute_units,
+        })) => {
+            let digest = db::set_task_min_price(
+                &mut context.with_optional_atoma_package_id(package),
+                task_small_id,
+                min_price_per_one_million_compute_units,
+            )
+            .await?;
+
+            println!("{digest}");
+        }
+        Some(Cmds::Admin(AdminCmds::PostAnnouncement {
+            package,
+            severity,
+            message,
+            effective_epoch,
+        })) => {
+            let digest = db::post_announcement(
+                &mut context.with_optional_atoma_package_id(package),
+                severity,
+                &message,
+                effective_epoch,
+            )
+            .await?;
+
+            println!("{digest}");
+        }
+        Some(Cmds::Admin(AdminCmds::RemoveAnnouncement {
+            package,
+            announcement_id,
+        })) => {
+            let digest = db::remove_announcement(
+                &mut context.with_optional_atoma_package_id(package),
+                announcement_id,
+            )
+            .await?;
+
+            println!("{digest}");
+        }
+        Some(Cmds::Db(DbCmds::ZkPublicInputs {
+            stack_small_id,
+            num_claimed_compute_units,
+            committed_stack_proof,
+            out,
+        })) => {
+            db::zk_public_inputs(
+                stack_small_id,
+                num_claimed_compute_units,
+                &committed_stack_proof,
+                out.as_deref(),
+            )?;
+        }
+        Some(Cmds::Db(DbCmds::TrySettleStackWithZkProof {
+            package,
+            stack_small_id,
+            num_claimed_compute_units,
+            committed_stack_proof,
+            stack_merkle_leaf,
+            proof,
+        })) => {
+            let digest = db::try_settle_stack_with_zk_proof(
+                &mut context.with_optional_atoma_package_id(package),
+                stack_small_id,
+                num_claimed_compute_units,
+                committed_stack_proof,
+                stack_merkle_leaf,
+                &proof,
+            )
+            .await?;
+
+            println!("{digest}");
+        }
+        Some(Cmds::Db(DbCmds::ClaimReferralFees { package })) => {
+            let digest = db::claim_referral_fees(
+                &mut context.with_optional_atoma_package_id(package),
+            )
+            .await?;
+
+            if let Some(digest) = digest {
+                println!("{digest}");
+            }
+        }
+        Some(Cmds::Db(DbCmds::TrySettleStack {
+            package,
+            stack_small_id,
+            num_claimed_compute_units,
+            committed_stack_proof,
+            stack_merkle_leaf,
+            #[cfg(feature = "daemon")]
+            dlq_file,
+            check,
+        })) => {
+            let context = &mut context.with_optional_atoma_package_id(package);
+            if check {
+                db::check_settlement(
+                    context,
+                    stack_small_id,
+                    &db::SettlementSubmission::TrySettle {
+                        num_claimed_compute_units,
+                    },
+                    &committed_stack_proof,
+                    &stack_merkle_leaf,
+                )
+                .await?;
+            }
+            #[cfg(feature = "daemon")]
+            let digest = daemon::execute_or_dead_letter(
+                context,
+                daemon::Action::TrySettleStack {
+                    stack_small_id,
+                    num_claimed_compute_units,
+                    committed_stack_proof,
+                    stack_merkle_leaf,
+                },
+                dlq_file.as_deref(),
+            )
+            .await?;
+            #[cfg(not(feature = "daemon"))]
+            let digest = db::try_settle_stack(
+                context,
+                stack_small_id,
+                num_claimed_compute_units,
+                committed_stack_proof,
+                stack_merkle_leaf,
+            )
+            .await?;
+
+            println!("{digest}");
+        }
+        Some(Cmds::Db(DbCmds::ClaimFunds {
+            package,
+            settled_ticket_ids,
+            #[cfg(feature = "daemon")]
+            dlq_file,
+        })) => {
+            let context = &mut context.with_optional_atoma_package_id(package);
+            #[cfg(feature = "daemon")]
+            let digest = daemon::execute_or_dead_letter(
+                context,
+                daemon::Action::ClaimFunds { settled_ticket_ids },
+                dlq_file.as_deref(),
+            )
+            .await?;
+            #[cfg(not(feature = "daemon"))]
+            let digest = db::claim_funds(context, settled_ticket_ids).await?;
+
+            println!("{digest}");
+        }
+        Some(Cmds::Db(DbCmds::SubmitStackSettlementAttestation {
+            package,
+            stack_small_id,
+            committed_stack_proof,
+            stack_merkle_leaf,
+            check,
+        })) => {
+            let context = &mut context.with_optional_atoma_package_id(package);
+            if check {
+                db::check_settlement(
+                    context,
+                    stack_small_id,
+                    &db::SettlementSubmission::Attestation,
+                    &committed_stack_proof,
+                    &stack_merkle_leaf,
+                )
+                .await?;
+            }
+            let digest = db::submit_stack_settlement_attestation(
+                context,
+                stack_small_id,
+                committed_stack_proof,
+                stack_merkle_leaf,
+            )
+            .await?;
+
+            println!("{digest}");
+        }
+        Some(Cmds::Db(DbCmds::StartAttestationDispute {
+            package,
+            stack_small_id,
+            attestation_commitment,
+        })) => {
+            let digest = db::start_attestation_dispute(
+                &mut context.with_optional_atoma_package_id(package),
+                stack_small_id,
+                attestation_commitment,
+            )
+            .await?;
+
+            println!("{digest}");
+        }
+        Some(Cmds::Db(DbCmds::RotateNodePublicKey {
+            package,
+            public_key_commitment,
+            evidence_bytes,
+            key_rotation_counter,
+            device_type,
+        })) => {
+            let digest = db::rotate_node_public_key(
+                &mut context.with_optional_atoma_package_id(package),
+                public_key_commitment,
+                evidence_bytes,
+                key_rotation_counter,
+                device_type,
+            )
+            .await?;
+
+            println!("{digest}");
+        }
+        Some(Cmds::Admin(AdminCmds::NewNetworkKeyRotation { package })) => {
+            let digest = db::new_network_key_rotation(
+                &mut context.with_optional_atoma_package_id(package),
+            )
+            .await?;
+
+            println!("{digest}");
+        }
+        Some(Cmds::Db(DbCmds::PrintEnv { package })) => {
+            db::print_env(&mut context.with_optional_atoma_package_id(package))
+                .await?;
+        }
+        Some(Cmds::Admin(AdminCmds::AddModel {
+            package,
+            name,
+            text2text,
+            text2image,
+            modality,
+        })) => {
+            let modality = match (text2text, text2image, modality) {
+                (true, false, None) => db::Modality::Text2Text,
+                (false, true, None) => db::Modality::Text2Image,
+                (false, false, Some(modality)) => modality,
+                _ => {
+                    return Err(anyhow::anyhow!(
+                        "Exactly one of --text2text, --text2image or \
+                        --modality must be set"
+                    ))
+                }
+            };
+            let digest = db::add_model(
+                &mut context.with_optional_atoma_package_id(package),
+                &name,
+                modality,
+            )
+            .await?;
+
+            println!("{digest}");
+        }
+        Some(Cmds::Admin(AdminCmds::AddModelEchelon {
+            package,
+            model,
+            echelon,
+            input_fee_per_token,
+            output_fee_per_token,
+            relative_performance,
+        })) => {
+            let digest = db::add_model_echelon(
+                &mut context.with_optional_atoma_package_id(package),
+                &model,
+                echelon,
+                input_fee_per_token,
+                output_fee_per_token.unwrap_or(input_fee_per_token),
+                relative_performance,
+            )
+            .await?;
+
+            println!("{digest}");
+        }
+        Some(Cmds::Admin(
+            AdminCmds::SetRequiredRegistrationTomaCollateral {
+                package,
+                new_amount,
+            },
+        )) => {
+            let digest = db::set_required_registration_collateral(
+                &mut context.with_optional_atoma_package_id(package),
+                new_amount,
+            )
+            .await?;
+
+            println!("{digest}");
+        }
+        Some(Cmds::Admin(AdminCmds::SetStaleNodeSweepParams {
+            package,
+            threshold_epochs,
+            reward,
+        })) => {
+            let digest = db::set_stale_node_sweep_params(
+                &mut context.with_optional_atoma_package_id(package),
+                threshold_epochs,
+                reward,
+            )
+            .await?;
+
+            println!("{digest}");
+        }
+        Some(Cmds::Admin(AdminCmds::SetStackGcParams {
+            package,
+            min_age_epochs,
+            bounty,
+        })) => {
+            let digest = db::set_stack_gc_params(
+                &mut context.with_optional_atoma_package_id(package),
+                min_age_epochs,
+                bounty,
+            )
+            .await?;
+
+            println!("{digest}");
+        }
+        Some(Cmds::Admin(AdminCmds::SetStackDisputeWindow {
+            package,
+            epochs,
+        })) => {
+            let digest = db::set_stack_dispute_window(
+                &mut context.with_optional_atoma_package_id(package),
+                epochs,
+            )
+            .await?;
+
+            println!("{digest}");
+        }
+        Some(Cmds::Db(DbCmds::RegisterNode { package })) => {
+            let digest = db::register_node(
+                &mut context.with_optional_atoma_package_id(package),
+            )
+            .await?;
+
+            println!("{digest}");
+        }
+        Some(Cmds::Db(DbCmds::AddNodeToModel {
+            package,
+            model,
+            echelon,
+        })) => {
+            let digest = db::add_node_to_model(
+                &mut context.with_optional_atoma_package_id(package),
+                &model,
+                echelon,
+            )
+            .await?;
+
+            println!("{digest}");
+        }
+        Some(Cmds::Db(DbCmds::RemoveNodeFromModel { package, model })) => {
+            let digest = db::remove_node_from_model(
+                &mut context.with_optional_atoma_package_id(package),
+                &model,
+            )
+            .await?;
+
+            println!("{digest}");
+        }
+        Some(Cmds::Db(DbCmds::PermanentlyDisableNode { package })) => {
+            db::permanently_disable_node(
+                &mut context.with_optional_atoma_package_id(package),
+            )
+            .await?;
+        }
+        Some(Cmds::Db(DbCmds::DestroyDisabledNode { package })) => {
+            db::destroy_disabled_node(
+                &mut context.with_optional_atoma_package_id(package),
+            )
+            .await?;
+        }
+        Some(Cmds::Gate(GateCmds::SubmitTellMeAJokePrompt {
+            package,
+            model,
+            max_fee_per_token,
+            priority,
+            prefer_low_latency,
+            pre_prompt_tokens_file,
+            prepend_output_with_input,
+            explain_fees,
+        })) => {
+            let options = gate::Text2TextOptions::new(
+                pre_prompt_tokens_file.as_deref(),
+                prepend_output_with_input,
+            )?;
+            let digest = gate::submit_tell_me_a_joke_prompt(
+                &mut context.with_optional_atoma_package_id(package),
+                &model,
+                max_fee_per_token,
+                priority,
+                prefer_low_latency,
+                options,
+                explain_fees,
+            )
+            .await?;
+
+            if let Some(digest) = digest {
+                println!("{digest}");
+            }
+        }
+        Some(Cmds::Gate(GateCmds::SubmitGenerateNftPrompt {
+            package,
+            model,
+            max_fee_per_token,
+            priority,
+            prefer_low_latency,
+        })) => {
+            let digest = gate::submit_generate_nft_prompt(
+                &mut context.with_optional_atoma_package_id(package),
+                &model,
+                max_fee_per_token,
+                priority,
+                prefer_low_latency,
+            )
+            .await?;
+
+            println!("{digest}");
+        }
+        Some(Cmds::Gate(GateCmds::PreviewSampling {
+            package,
+            model,
+            random,
+            node_randoms,
+            nodes_to_sample,
+            max_fee_per_token,
+            priority,
+            prefer_low_latency,
+        })) => {
+            gate::preview_sampling(
+                &mut context.with_optional_atoma_package_id(package),
+                &model,
+                &random,
+                &node_randoms,
+                nodes_to_sample,
+                max_fee_per_token,
+                priority,
+                prefer_low_latency,
+            )
+            .await?;
+        }
+        Some(Cmds::Gate(GateCmds::Resubmit {
+            package,
+            ticket_id,
+            max_fee_per_token,
+            fee_bump_percent,
+            links_file,
+        })) => {
+            let new_ticket_id = gate::resubmit(
+                &mut context.with_optional_atoma_package_id(package),
+                &ticket_id,
+                max_fee_per_token,
+                fee_bump_percent,
+                &links_file,
+            )
+            .await?;
+
+            println!("{new_ticket_id}");
+        }
+        Some(Cmds::Gate(GateCmds::CancelPrompt { package })) => {
+            let digests = gate::cancel_prompt(
+                &mut context.with_optional_atoma_package_id(package),
+            )
+            .await?;
+
+            for digest in digests {
+                println!("{digest}");
+            }
+        }
+        Some(Cmds::Settle(SettlementCmds::ListTickets { package })) => {
+            settle::list_tickets(
+                &mut context.with_optional_atoma_package_id(package),
+            )
+            .await?;
+        }
+        Some(Cmds::Settle(SettlementCmds::SubmitCommitment {
+            ticket_id,
+            output,
+            segments,
+            #[cfg(feature = "daemon")]
+            queue_file,
+        })) => {
+            let digest = settle::submit_commitment(
+                &mut context,
+                &ticket_id,
+                output.as_deref(),
+                segments.as_deref(),
+            )
+            .await?;
+            #[cfg(feature = "daemon")]
+            if let Some(queue_file) = queue_file {
+                daemon::queue_transition(
+                    &queue_file,
+                    &ticket_id,
+                    daemon::QueueState::Committed,
+                )?;
+            }
+
+            println!("{digest}");
+        }
+        Some(Cmds::Settle(SettlementCmds::Gc {
+            package,
+            sui_price,
+            max_stacks,
+            interval_secs,
+        })) => {
+            settle::gc(
+                &mut context.with_optional_atoma_package_id(package),
+                sui_price,
+                max_stacks,
+                interval_secs.map(Duration::from_secs),
+            )
+            .await?;
+        }
+        Some(Cmds::Refunds(RefundsCmds::Sweep {
+            package,
+            max_tickets,
+            interval_secs,
+        })) => {
+            refunds::sweep(
+                &mut context.with_optional_atoma_package_id(package),
+                max_tickets,
+                interval_secs.map(Duration::from_secs),
+            )
+            .await?;
+        }
+        Some(Cmds::Settle(SettlementCmds::Partial { ticket_id, yes })) => {
+            if let Some(digest) =
+                settle::settle_partially(&mut context, &ticket_id, yes).await?
+            {
+                println!("{digest}");
+            }
+        }
+        Some(Cmds::Settle(SettlementCmds::StreamCommitment { segments })) => {
+            settle::stream_commitment(&segments)?;
+        }
+        Some(Cmds::Settle(SettlementCmds::TryToSettle {
+            ticket_id,
+            #[cfg(feature = "daemon")]
+            dlq_file,
+            #[cfg(feature = "daemon")]
+            queue_file,
+        })) => {
+            #[cfg(feature = "daemon")]
+            let digest = daemon::execute_or_dead_letter(
+                &mut context,
+                daemon::Action::TryToSettle {
+                    ticket_id: ticket_id.clone(),
+                },
+                dlq_file.as_deref(),
+            )
+            .await?;
+            #[cfg(not(feature = "daemon"))]
+            let digest =
+                settle::try_to_settle(&mut context, &ticket_id).await?;
+            #[cfg(feature = "daemon")]
+            if let Some(queue_file) = queue_file {
+                daemon::queue_transition(
+                    &queue_file,
+                    &ticket_id,
+                    daemon::QueueState::Settled,
+                )?;
+            }
+
+            println!("{digest}");
+        }
+        Some(Cmds::Stack(StackCmds::SignRequest {
+            stack_small_id,
+            nonce,
+            payload,
+        })) => {
+            let signature = stack::sign_request(
+                &mut context,
+                stack_small_id,
+                nonce,
+                &payload,
             )
             .await?;
 
-            println!("{digest}");
+            println!("{signature}");
         }
-        Some(Cmds::Db(DbCmds::WhitelistNodesForTask {
+        Some(Cmds::Stack(StackCmds::VerifyRequest {
             package,
-            task_small_id,
-            nodes_small_ids,
+            stack_small_id,
+            nonce,
+            payload,
+            signature,
         })) => {
-            let digest = db::whitelist_nodes_for_task(
+            let owner = stack::verify_request(
                 &mut context.with_optional_atoma_package_id(package),
-                task_small_id,
-                nodes_small_ids,
+                stack_small_id,
+                nonce,
+                &payload,
+                &signature,
             )
             .await?;
 
-            println!("{digest}");
+            println!("Request signed by stack owner {owner}");
         }
-        Some(Cmds::Db(DbCmds::DeprecateTask {
-            package,
-            task_badge,
-        })) => {
-            let digest = db::deprecate_task(
+        Some(Cmds::Stack(StackCmds::Reconcile { package, usage_log })) => {
+            let mismatches = stack::reconcile(
                 &mut context.with_optional_atoma_package_id(package),
-                ObjectID::from_str(&task_badge)?,
+                &usage_log,
             )
             .await?;
 
-            println!("{digest}");
+            if mismatches > 0 {
+                anyhow::bail!("{mismatches} stack(s) are not reconciled");
+            }
         }
-        Some(Cmds::Db(DbCmds::RemoveDeprecatedTask {
+        Some(Cmds::Stack(StackCmds::Usage {
             package,
-            task_badge,
+            stack_small_id,
+            request_log,
         })) => {
-            let digest = db::remove_deprecated_task(
+            stack::usage(
                 &mut context.with_optional_atoma_package_id(package),
-                ObjectID::from_str(&task_badge)?,
+                stack_small_id,
+                request_log.as_deref(),
+            )
+            .await?;
+        }
+        Some(Cmds::Stack(StackCmds::CloseEarly(CloseEarlyCmds::Propose {
+            package,
+            stack_small_id,
+            num_consumed_compute_units,
+            as_node,
+        }))) => {
+            let digest = stack::propose_close_early(
+                &mut context.with_optional_atoma_package_id(package),
+                stack_small_id,
+                num_consumed_compute_units,
+                as_node,
             )
             .await?;
 
             println!("{digest}");
         }
-        Some(Cmds::Db(DbCmds::SubscribeNodeToTask {
+        Some(Cmds::Stack(StackCmds::CloseEarly(CloseEarlyCmds::Accept {
             package,
-            task_small_id,
-            price_per_one_million_compute_units,
-        })) => {
-            let digest = db::subscribe_node_to_task(
+            stack_small_id,
+            num_consumed_compute_units,
+            as_node,
+        }))) => {
+            let digest = stack::accept_close_early(
                 &mut context.with_optional_atoma_package_id(package),
-                task_small_id,
-                price_per_one_million_compute_units,
+                stack_small_id,
+                num_consumed_compute_units,
+                as_node,
             )
             .await?;
 
             println!("{digest}");
         }
-        Some(Cmds::Db(DbCmds::UpdateNodeSubscription {
+        Some(Cmds::Receipts(ReceiptsCmds::Sign {
             package,
-            task_small_id,
-            price_per_one_million_compute_units,
+            stack_small_id,
+            payload,
+            compute_units,
+            running_total,
         })) => {
-            let digest = db::update_node_subscription(
+            receipts::sign(
                 &mut context.with_optional_atoma_package_id(package),
-                task_small_id,
-                price_per_one_million_compute_units,
+                stack_small_id,
+                &payload,
+                compute_units,
+                running_total,
             )
             .await?;
-
-            println!("{digest}");
         }
-        Some(Cmds::Db(DbCmds::UnsubscribeNodeFromTask {
+        Some(Cmds::Receipts(ReceiptsCmds::Verify {
             package,
-            task_small_id,
+            stack_small_id,
+            receipts,
         })) => {
-            let digest = db::unsubscribe_node_from_task(
+            let problems = receipts::verify(
+                &mut context.with_optional_atoma_package_id(package),
+                stack_small_id,
+                &receipts,
+            )
+            .await?;
+
+            if problems > 0 {
+                anyhow::bail!("{problems} problem(s) with the receipts");
+            }
+        }
+        Some(Cmds::Offer(OfferCmds::SetKey { package })) => {
+            let digest = offer::set_key(
                 &mut context.with_optional_atoma_package_id(package),
-                task_small_id,
             )
             .await?;
 
             println!("{digest}");
         }
-        Some(Cmds::Db(DbCmds::AcquireNewStackEntry {
+        Some(Cmds::Offer(OfferCmds::Post {
             package,
             task_small_id,
+            price_per_one_million_compute_units,
             num_compute_units,
-            price,
+            expires_in_secs,
+            nonce,
+            book,
         })) => {
-            let digest = db::acquire_new_stack_entry(
+            offer::post(
                 &mut context.with_optional_atoma_package_id(package),
                 task_small_id,
+                price_per_one_million_compute_units,
                 num_compute_units,
-                price,
+                Duration::from_secs(expires_in_secs),
+                nonce,
+                &book,
             )
             .await?;
-
-            println!("{digest}");
         }
-        Some(Cmds::Db(DbCmds::TrySettleStack {
+        Some(Cmds::Offer(OfferCmds::List {
+            task_small_id,
+            book,
+        })) => {
+            offer::list(&context, &book, task_small_id)?;
+        }
+        Some(Cmds::Offer(OfferCmds::Accept {
             package,
-            stack_small_id,
-            num_claimed_compute_units,
-            committed_stack_proof,
-            stack_merkle_leaf,
+            task_small_id,
+            node_small_id,
+            nonce,
+            book,
         })) => {
-            let digest = db::try_settle_stack(
+            let digest = offer::accept(
                 &mut context.with_optional_atoma_package_id(package),
-                stack_small_id,
-                num_claimed_compute_units,
-                committed_stack_proof,
-                stack_merkle_leaf,
+                &book,
+                task_small_id,
+                node_small_id.zip(nonce),
             )
             .await?;
 
             println!("{digest}");
         }
-        Some(Cmds::Db(DbCmds::ClaimFunds {
-            package,
-            settled_ticket_ids,
-        })) => {
-            let digest = db::claim_funds(
+        Some(Cmds::Offer(OfferCmds::Cancel { package, nonce })) => {
+            let digest = offer::cancel(
                 &mut context.with_optional_atoma_package_id(package),
-                settled_ticket_ids,
+                nonce,
             )
             .await?;
 
             println!("{digest}");
         }
-        Some(Cmds::Db(DbCmds::SubmitStackSettlementAttestation {
+        Some(Cmds::Quote(QuoteCmds::Issue {
             package,
-            stack_small_id,
-            committed_stack_proof,
-            stack_merkle_leaf,
+            task_small_id,
+            price_per_one_million_compute_units,
+            num_compute_units,
+            expires_in_secs,
+            out,
         })) => {
-            let digest = db::submit_stack_settlement_attestation(
+            quote::issue(
                 &mut context.with_optional_atoma_package_id(package),
-                stack_small_id,
-                committed_stack_proof,
-                stack_merkle_leaf,
+                task_small_id,
+                price_per_one_million_compute_units,
+                num_compute_units,
+                Duration::from_secs(expires_in_secs),
+                &out,
             )
             .await?;
-
-            println!("{digest}");
         }
-        Some(Cmds::Db(DbCmds::StartAttestationDispute {
-            package,
-            stack_small_id,
-            attestation_commitment,
-        })) => {
-            let digest = db::start_attestation_dispute(
+        Some(Cmds::Quote(QuoteCmds::Verify { package, quote })) => {
+            let signed_quote = quote::verify(
                 &mut context.with_optional_atoma_package_id(package),
-                stack_small_id,
-                attestation_commitment,
+                &quote,
             )
             .await?;
 
-            println!("{digest}");
+            println!(
+                "Quote signed by {} for node {}",
+                signed_quote.signer, signed_quote.quote.node_small_id
+            );
         }
-        Some(Cmds::Db(DbCmds::RotateNodePublicKey {
+        Some(Cmds::Quote(QuoteCmds::Estimate {
             package,
-            public_key_commitment,
-            evidence_bytes,
-            key_rotation_counter,
-            device_type,
+            task_small_id,
+            price_per_one_million_compute_units,
+            num_compute_units,
         })) => {
-            let digest = db::rotate_node_public_key(
+            quote::estimate(
                 &mut context.with_optional_atoma_package_id(package),
-                public_key_commitment,
-                evidence_bytes,
-                key_rotation_counter,
-                device_type,
+                task_small_id,
+                price_per_one_million_compute_units,
+                num_compute_units,
             )
             .await?;
-
-            println!("{digest}");
         }
-        Some(Cmds::Db(DbCmds::NewNetworkKeyRotation { package })) => {
-            let digest = db::new_network_key_rotation(
+        Some(Cmds::Quote(QuoteCmds::Accept { package, quote })) => {
+            let digest = quote::accept(
                 &mut context.with_optional_atoma_package_id(package),
+                &quote,
             )
             .await?;
 
             println!("{digest}");
         }
-        Some(Cmds::Db(DbCmds::PrintEnv { package })) => {
-            db::print_env(&mut context.with_optional_atoma_package_id(package))
-                .await?;
+        Some(Cmds::Toma(TomaCmds::Balances { toma_package })) => {
+            toma::balances(
+                &mut context.with_optional_toma_package_id(toma_package),
+            )
+            .await?;
         }
-        Some(Cmds::Db(DbCmds::AddModel {
-            package,
-            name,
-            text2text,
-            text2image,
+        Some(Cmds::Toma(TomaCmds::CreateMigration {
+            toma_package,
+            publisher,
+            old_coin_type,
+            amount,
         })) => {
-            let digest = db::add_model(
-                &mut context.with_optional_atoma_package_id(package),
-                &name,
-                match (text2text, text2image) {
-                    // this is defined in the gate module
-                    (true, false) => 0,
-                    (false, true) => 1,
-                    _ => {
-                        return Err(anyhow::anyhow!(
-                        "Exactly one of --text2text or --text2image must be set"
-                    ))
-                    }
-                },
+            let migration = toma::create_migration(
+                &mut context.with_optional_toma_package_id(toma_package),
+                publisher,
+                &old_coin_type,
+                amount,
             )
             .await?;
 
-            println!("{digest}");
+            println!("{migration}");
         }
-        Some(Cmds::Db(DbCmds::AddModelEchelon {
-            package,
-            model,
-            echelon,
-            input_fee_per_token,
-            output_fee_per_token,
-            relative_performance,
-        })) => {
-            let digest = db::add_model_echelon(
-                &mut context.with_optional_atoma_package_id(package),
-                &model,
-                echelon,
-                input_fee_per_token,
-                output_fee_per_token.unwrap_or(input_fee_per_token),
-                relative_performance,
+        Some(Cmds::Toma(TomaCmds::MigrateCoins { toma_package })) => {
+            toma::migrate_coins(
+                &mut context.with_optional_toma_package_id(toma_package),
             )
             .await?;
-
-            println!("{digest}");
         }
-        Some(Cmds::Db(DbCmds::SetRequiredRegistrationTomaCollateral {
-            package,
-            new_amount,
+        Some(Cmds::Toma(TomaCmds::Faucet {
+            toma_package,
+            amount,
         })) => {
-            let digest = db::set_required_registration_collateral(
-                &mut context.with_optional_atoma_package_id(package),
-                new_amount,
+            let digest = toma::faucet(
+                &mut context.with_optional_toma_package_id(toma_package),
+                amount,
             )
             .await?;
 
             println!("{digest}");
         }
-        Some(Cmds::Db(DbCmds::RegisterNode { package })) => {
-            let digest = db::register_node(
-                &mut context.with_optional_atoma_package_id(package),
+        Some(Cmds::Toma(TomaCmds::FaucetServer {
+            toma_package,
+            listen,
+            amount,
+            api_keys_file,
+            cooldown_secs,
+            daily_cap,
+        })) => {
+            toma::faucet_server(
+                context.with_optional_toma_package_id(toma_package),
+                listen,
+                amount,
+                &api_keys_file,
+                toma::DripLimits {
+                    cooldown: Duration::from_secs(cooldown_secs),
+                    daily_cap,
+                },
             )
             .await?;
-
-            println!("{digest}");
         }
-        Some(Cmds::Db(DbCmds::AddNodeToModel {
+        Some(Cmds::Call {
             package,
-            model,
-            echelon,
-        })) => {
-            let digest = db::add_node_to_model(
+            module,
+            function,
+            args,
+            type_args,
+        }) => {
+            let digest = call::command(
                 &mut context.with_optional_atoma_package_id(package),
-                &model,
-                echelon,
+                &module,
+                &function,
+                args.as_deref(),
+                type_args,
             )
             .await?;
 
             println!("{digest}");
         }
-        Some(Cmds::Db(DbCmds::RemoveNodeFromModel { package, model })) => {
-            let digest = db::remove_node_from_model(
+        Some(Cmds::Insurance(InsuranceCmds::Join { package })) => {
+            let digest = insurance::join(
                 &mut context.with_optional_atoma_package_id(package),
-                &model,
             )
             .await?;
 
             println!("{digest}");
         }
-        Some(Cmds::Db(DbCmds::PermanentlyDisableNode { package })) => {
-            db::permanently_disable_node(
+        Some(Cmds::Insurance(InsuranceCmds::Leave { package })) => {
+            let digest = insurance::leave(
                 &mut context.with_optional_atoma_package_id(package),
             )
             .await?;
+
+            println!("{digest}");
         }
-        Some(Cmds::Db(DbCmds::DestroyDisabledNode { package })) => {
-            db::destroy_disabled_node(
+        Some(Cmds::Insurance(InsuranceCmds::Status {
+            package,
+            node_small_id,
+        })) => {
+            insurance::status(
                 &mut context.with_optional_atoma_package_id(package),
+                node_small_id,
             )
             .await?;
         }
-        Some(Cmds::Gate(GateCmds::SubmitTellMeAJokePrompt {
+        Some(Cmds::Insurance(InsuranceCmds::FileClaim {
             package,
-            model,
-            max_fee_per_token,
+            stack_small_id,
+            amount,
         })) => {
-            let digest = gate::submit_tell_me_a_joke_prompt(
+            let digest = insurance::file_claim(
                 &mut context.with_optional_atoma_package_id(package),
-                &model,
-                max_fee_per_token,
+                stack_small_id,
+                amount,
             )
             .await?;
 
             println!("{digest}");
         }
-        Some(Cmds::Gate(GateCmds::SubmitGenerateNftPrompt {
+        Some(Cmds::Admin(AdminCmds::PayInsuranceClaim {
             package,
-            model,
-            max_fee_per_token,
+            stack_small_id,
         })) => {
-            let digest = gate::submit_generate_nft_prompt(
+            let digest = insurance::resolve_claim(
                 &mut context.with_optional_atoma_package_id(package),
-                &model,
-                max_fee_per_token,
+                stack_small_id,
+                true,
             )
             .await?;
 
             println!("{digest}");
         }
-        Some(Cmds::Settle(SettlementCmds::ListTickets { package })) => {
-            settle::list_tickets(
+        Some(Cmds::Admin(AdminCmds::RejectInsuranceClaim {
+            package,
+            stack_small_id,
+        })) => {
+            let digest = insurance::resolve_claim(
                 &mut context.with_optional_atoma_package_id(package),
+                stack_small_id,
+                false,
             )
             .await?;
-        }
-        Some(Cmds::Settle(SettlementCmds::SubmitCommitment {
-            ticket_id,
-            output,
-        })) => {
-            let digest =
-                settle::submit_commitment(&mut context, &ticket_id, &output)
-                    .await?;
-
-            println!("{digest}");
-        }
-        Some(Cmds::Settle(SettlementCmds::TryToSettle { ticket_id })) => {
-            let digest =
-                settle::try_to_settle(&mut context, &ticket_id).await?;
 
             println!("{digest}");
         }
-        Some(Cmds::Toma(TomaCmds::Faucet {
-            toma_package,
-            amount,
+        Some(Cmds::Admin(AdminCmds::SetInsuranceParams {
+            package,
+            premium_permille,
+            payout_permille,
+            max_payout_per_claim,
         })) => {
-            let digest = toma::faucet(
-                &mut context.with_optional_toma_package_id(toma_package),
-                amount,
+            let digest = insurance::set_params(
+                &mut context.with_optional_atoma_package_id(package),
+                premium_permille,
+                payout_permille,
+                max_payout_per_claim,
             )
             .await?;
 
             println!("{digest}");
         }
+        Some(Cmds::Init { package }) => {
+            init::command(context, package).await?;
+        }
+        Some(Cmds::Release(ReleaseCmds::Sign { binary })) => {
+            println!("{}", release::sign(&mut context, &binary)?);
+        }
+        Some(Cmds::Completion { .. } | Cmds::Manpage { .. }) => {
+            unreachable!("Handled before the wallet is loaded")
+        }
+        Some(
+            Cmds::Release(ReleaseCmds::Verify { .. }) | Cmds::Version { .. },
+        ) => {
+            unreachable!("Handled before the wallet is loaded")
+        }
         None => {}
     }
 
@@ -853,7 +3417,10 @@ async fn main() -> Result<()> {
 fn wait_for_user_confirm() -> bool {
     loop {
         let mut input = [0];
-        let _ = std::io::stdin().read(&mut input);
+        if !matches!(std::io::stdin().read(&mut input), Ok(1)) {
+            // stdin is closed, eg. when the command is benchmarked
+            return false;
+        }
         match input[0] as char {
             'y' | 'Y' => return true,
             'n' | 'N' => return false,
@@ -0,0 +1,42 @@
+//! Man pages generated from the clap definition of the CLI.
+
+use std::path::Path;
+
+use clap::Command;
+use clap_mangen::Man;
+
+use crate::prelude::*;
+
+/// Without an output directory, prints the page of the top level command to
+/// stdout.
+/// Otherwise writes a page for every command and subcommand into the
+/// directory, named after the command path, eg. `cli-db-list-nodes.1`.
+pub(crate) fn command(mut cli: Command, out_dir: Option<&Path>) -> Result<()> {
+    // sets the display names of the subcommands to their full path
+    cli.build();
+
+    let Some(out_dir) = out_dir else {
+        Man::new(cli).render(&mut std::io::stdout())?;
+        return Ok(());
+    };
+
+    std::fs::create_dir_all(out_dir)?;
+    let pages = write_pages(&cli, out_dir)?;
+    println!("Written {pages} man page(s) to {out_dir:?}");
+
+    Ok(())
+}
+
+fn write_pages(command: &Command, out_dir: &Path) -> Result<usize> {
+    let man = Man::new(command.clone());
+    let mut page = vec![];
+    man.render(&mut page)?;
+    std::fs::write(out_dir.join(man.get_filename()), page)?;
+
+    let mut pages = 1;
+    for subcommand in command.get_subcommands().filter(|c| !c.is_hide_set()) {
+        pages += write_pages(subcommand, out_dir)?;
+    }
+
+    Ok(pages)
+}
@@ -0,0 +1,20 @@
+//! Stack offers that nodes sign off-chain and post to an order book, and
+//! that users accept on-chain with the offering node assigned to the stack.
+//!
+//! The order book is a JSON lines file of signed offers that can be shared
+//! between nodes and users, eg. on a file server.
+//! Offers are single use on-chain, so the book doesn't need to be trusted
+//! beyond its availability.
+
+mod accept;
+mod cancel;
+mod list;
+mod post;
+mod set_key;
+mod signed_offer;
+
+pub(crate) use accept::command as accept;
+pub(crate) use cancel::command as cancel;
+pub(crate) use list::command as list;
+pub(crate) use post::command as post;
+pub(crate) use set_key::command as set_key;
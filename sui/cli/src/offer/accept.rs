@@ -0,0 +1,96 @@
+use std::path::Path;
+
+use sui_sdk::types::{crypto::SuiSignature, SUI_CLOCK_OBJECT_ID};
+
+use super::list::open_offers;
+use crate::{prelude::*, quote::verify_node_badge_owner, DB_MODULE_NAME};
+
+const ENDPOINT_NAME: &str = "acquire_new_stack_with_offer_entry";
+
+/// 1. Pick the offer of the node with the given nonce from the order book,
+///    or the cheapest open offer for the task.
+/// 2. Check that the offer was signed for the configured deployment and
+///    that the signer owns the badge of the node that the offer is for.
+/// 3. Acquire a stack with the offering node, which checks the signature
+///    against the offer key of the node and consumes the offer.
+pub(crate) async fn command(
+    context: &mut Context,
+    book: &Path,
+    task_small_id: u64,
+    node_and_nonce: Option<(u64, u64)>,
+) -> Result<TransactionDigest> {
+    // 1.
+    let signed_offer = open_offers(book, Some(task_small_id))?
+        .into_iter()
+        .find(|signed_offer| {
+            node_and_nonce.map_or(true, |(node_small_id, nonce)| {
+                signed_offer.offer.node_small_id == node_small_id
+                    && signed_offer.offer.nonce == nonce
+            })
+        })
+        .ok_or_else(|| {
+            anyhow!("No matching open offer for task {task_small_id}")
+        })?;
+    let offer = &signed_offer.offer;
+
+    // 2.
+    offer.deployment.ensure_configured(context).await?;
+    verify_node_badge_owner(
+        context,
+        offer.node_badge,
+        signed_offer.signer,
+        offer.node_small_id,
+    )
+    .await?;
+    info!(
+        "Accepting offer {} of node {} at {} per 1M",
+        offer.nonce,
+        offer.node_small_id,
+        context.toma(offer.price_per_one_million_compute_units),
+    );
+
+    // 3.
+    let sender = context.sender()?;
+    let atoma_package = context.unwrap_atoma_package_id();
+    let atoma_db = context.get_or_load_atoma_db().await?;
+    let toma_wallet = context.get_or_load_toma_wallet().await?;
+
+    let tx = context
+        .get_client()
+        .await?
+        .transaction_builder()
+        .move_call(
+            sender,
+            atoma_package,
+            DB_MODULE_NAME,
+            ENDPOINT_NAME,
+            vec![],
+            vec![
+                SuiJsonValue::from_object_id(atoma_db),
+                SuiJsonValue::from_object_id(toma_wallet),
+                SuiJsonValue::new(offer.task_small_id.to_string().into())?,
+                SuiJsonValue::new(offer.num_compute_units.to_string().into())?,
+                SuiJsonValue::new(
+                    offer
+                        .price_per_one_million_compute_units
+                        .to_string()
+                        .into(),
+                )?,
+                SuiJsonValue::new(offer.node_small_id.to_string().into())?,
+                SuiJsonValue::new(offer.expires_at_ms.to_string().into())?,
+                SuiJsonValue::new(offer.nonce.to_string().into())?,
+                SuiJsonValue::new(
+                    signed_offer.signature.signature_bytes().to_vec().into(),
+                )?,
+                SuiJsonValue::from_object_id(SUI_CLOCK_OBJECT_ID),
+            ],
+            None,
+            context.gas_budget(),
+            None,
+        )
+        .await?;
+
+    let tx = context.sign_transaction(&tx)?;
+    let resp = context.execute_transaction_must_succeed(tx).await?;
+    Ok(resp.digest)
+}
@@ -0,0 +1,77 @@
+use std::{
+    path::Path,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use super::signed_offer::{load_book, SignedOffer};
+use crate::prelude::*;
+
+/// Prints the offers in the order book that can still be accepted, cheapest
+/// first.
+///
+/// Expired offers and offers with an invalid signature are skipped.
+/// Offers that were accepted or cancelled already are only rejected
+/// on-chain.
+pub(crate) fn command(
+    context: &Context,
+    book: &Path,
+    task_small_id: Option<u64>,
+) -> Result<()> {
+    let offers = open_offers(book, task_small_id)?;
+    if offers.is_empty() {
+        println!("No open offers in {book:?}");
+        return Ok(());
+    }
+
+    for signed_offer in offers {
+        let offer = &signed_offer.offer;
+        println!(
+            "Node {} nonce {}: task {}, {} compute units at {} per 1M, \
+            expires at {} ms",
+            offer.node_small_id,
+            offer.nonce,
+            offer.task_small_id,
+            offer.num_compute_units,
+            context.toma(offer.price_per_one_million_compute_units),
+            offer.expires_at_ms,
+        );
+    }
+
+    Ok(())
+}
+
+/// Sorted by price, then by expiry so that offers that expire sooner are
+/// taken first.
+pub(crate) fn open_offers(
+    book: &Path,
+    task_small_id: Option<u64>,
+) -> Result<Vec<SignedOffer>> {
+    let now_ms = SystemTime::now().duration_since(UNIX_EPOCH)?.as_millis();
+    let mut offers: Vec<_> = load_book(book)?
+        .into_iter()
+        .filter(|signed_offer| {
+            let offer = &signed_offer.offer;
+            task_small_id.map_or(true, |id| id == offer.task_small_id)
+                && offer.expires_at_ms as u128 >= now_ms
+        })
+        .filter(|signed_offer| match signed_offer.verify_signature() {
+            Ok(()) => true,
+            Err(err) => {
+                log::warn!(
+                    "Skipping offer {} of node {}: {err}",
+                    signed_offer.offer.nonce,
+                    signed_offer.offer.node_small_id
+                );
+                false
+            }
+        })
+        .collect();
+    offers.sort_by_key(|signed_offer| {
+        (
+            signed_offer.offer.price_per_one_million_compute_units,
+            signed_offer.offer.expires_at_ms,
+        )
+    });
+
+    Ok(offers)
+}
@@ -0,0 +1,51 @@
+use std::{
+    path::Path,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use super::signed_offer::{post_to_book, Offer};
+use crate::{prelude::*, signed_terms::Deployment};
+
+/// Signs an offer with the key of the active address, which must own the
+/// node badge and be registered as the offer key of the node, and appends it
+/// to the order book.
+///
+/// Without a nonce, the current time in ms is used, which is unique as long
+/// as offers are not posted in the same millisecond.
+pub(crate) async fn command(
+    context: &mut Context,
+    task_small_id: u64,
+    price_per_one_million_compute_units: u64,
+    num_compute_units: u64,
+    valid_for: Duration,
+    nonce: Option<u64>,
+    book: &Path,
+) -> Result<()> {
+    context.conf.role.ensure_can_sign()?;
+    let active_address = context.wallet.active_address()?;
+    let (node_badge, node_small_id) = context.get_or_load_node_badge().await?;
+    let now = SystemTime::now().duration_since(UNIX_EPOCH)?;
+    let expires_at_ms = (now + valid_for).as_millis() as u64;
+    let nonce = nonce.unwrap_or(now.as_millis() as u64);
+
+    let signed_offer = Offer {
+        deployment: Deployment::load(context).await?,
+        node_small_id,
+        node_badge,
+        task_small_id,
+        price_per_one_million_compute_units,
+        num_compute_units,
+        expires_at_ms,
+        nonce,
+    }
+    .sign(&context.wallet, active_address)?;
+
+    post_to_book(book, &signed_offer)?;
+    println!(
+        "Offer {nonce} of node {node_small_id} for {num_compute_units} \
+        compute units at {} per 1M posted to {book:?}",
+        context.toma(price_per_one_million_compute_units),
+    );
+
+    Ok(())
+}
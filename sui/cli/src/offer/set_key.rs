@@ -0,0 +1,60 @@
+use sui_keys::keystore::AccountKeystore;
+use sui_sdk::types::crypto::SignatureScheme;
+
+use crate::{prelude::*, DB_MODULE_NAME};
+
+const ENDPOINT_NAME: &str = "set_node_offer_key";
+
+/// Registers the Ed25519 public key of the active address as the key that
+/// the node signs its offers with.
+///
+/// The badge can be moved to another address, so the key is registered
+/// on-chain rather than derived from the badge owner.
+pub(crate) async fn command(
+    context: &mut Context,
+) -> Result<TransactionDigest> {
+    context.conf.role.ensure_can_sign()?;
+    let active_address = context.wallet.active_address()?;
+    let public_key = context
+        .wallet
+        .config
+        .keystore
+        .get_key(&active_address)?
+        .public();
+    if public_key.scheme() != SignatureScheme::ED25519 {
+        anyhow::bail!(
+            "Offers are signed with an Ed25519 key, the key of \
+            {active_address} is {:?}",
+            public_key.scheme()
+        );
+    }
+
+    let atoma_package = context.unwrap_atoma_package_id();
+    let atoma_db = context.get_or_load_atoma_db().await?;
+    let (node_badge, _) = context.get_or_load_node_badge().await?;
+
+    let tx = context
+        .get_client()
+        .await?
+        .transaction_builder()
+        .move_call(
+            active_address,
+            atoma_package,
+            DB_MODULE_NAME,
+            ENDPOINT_NAME,
+            vec![],
+            vec![
+                SuiJsonValue::from_object_id(atoma_db),
+                SuiJsonValue::from_object_id(node_badge),
+                SuiJsonValue::new(public_key.as_ref().to_vec().into())?,
+            ],
+            None,
+            context.gas_budget(),
+            None,
+        )
+        .await?;
+
+    let tx = context.sign_transaction(&tx)?;
+    let resp = context.execute_transaction_must_succeed(tx).await?;
+    Ok(resp.digest)
+}
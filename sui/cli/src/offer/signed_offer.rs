@@ -0,0 +1,185 @@
+//! An offer is a node's commitment to process a number of compute units for
+//! a task at a given price until some time.
+//!
+//! The offer is identified by the hash of the signed terms
+//! `node_small_id || task_small_id || price || compute_units ||
+//! expires_at_ms || nonce`, see the `signed_terms` module.
+//! This must match `db::offer_hash`, which the Move tests pin: with package
+//! `0x0` and the db of the test scenario, node 1, task 1, price 1000, 100
+//! compute units, expiry 10000 and nonce 7 hash to
+//! `c4c058bd32062f86b8963d144fe419552bd0310256d4e4edfbc180b87403d221`.
+
+use std::path::Path;
+
+use fastcrypto::{
+    encoding::{Encoding, Hex},
+    traits::EncodeDecodeBase64,
+};
+use serde_json::json;
+use sui_sdk::types::{
+    base_types::{ObjectID, SuiAddress},
+    crypto::Signature,
+};
+
+use crate::{
+    prelude::*,
+    signed_terms::{self, Deployment},
+};
+
+/// Must match `OFFER_DOMAIN` in the db module.
+const DOMAIN: &[u8] = b"atoma-offer";
+
+pub(crate) struct Offer {
+    pub(crate) deployment: Deployment,
+    pub(crate) node_small_id: u64,
+    pub(crate) node_badge: ObjectID,
+    pub(crate) task_small_id: u64,
+    pub(crate) price_per_one_million_compute_units: u64,
+    pub(crate) num_compute_units: u64,
+    pub(crate) expires_at_ms: u64,
+    pub(crate) nonce: u64,
+}
+
+pub(crate) struct SignedOffer {
+    pub(crate) offer: Offer,
+    pub(crate) signer: SuiAddress,
+    pub(crate) signature: Signature,
+}
+
+impl Offer {
+    pub(crate) fn hash(&self) -> [u8; 32] {
+        signed_terms::hash(
+            DOMAIN,
+            &self.deployment,
+            &[
+                self.node_small_id,
+                self.task_small_id,
+                self.price_per_one_million_compute_units,
+                self.num_compute_units,
+                self.expires_at_ms,
+                self.nonce,
+            ],
+        )
+    }
+
+    pub(crate) fn sign(
+        self,
+        wallet: &WalletContext,
+        signer: SuiAddress,
+    ) -> Result<SignedOffer> {
+        let signature =
+            signed_terms::sign(&wallet.config.keystore, signer, self.hash())?;
+
+        Ok(SignedOffer {
+            offer: self,
+            signer,
+            signature,
+        })
+    }
+}
+
+impl SignedOffer {
+    /// Fails if the offer was not signed by the signer.
+    ///
+    /// It's up to the caller to check that the signer owns the node badge
+    /// and that its key is the offer key of the node.
+    pub(crate) fn verify_signature(&self) -> Result<()> {
+        signed_terms::verify(self.offer.hash(), self.signer, &self.signature)
+            .map_err(|err| anyhow!("Offer: {err}"))
+    }
+
+    pub(crate) fn to_json(&self) -> serde_json::Value {
+        let Offer {
+            deployment,
+            node_small_id,
+            node_badge,
+            task_small_id,
+            price_per_one_million_compute_units,
+            num_compute_units,
+            expires_at_ms,
+            nonce,
+        } = &self.offer;
+
+        json!({
+            "package": deployment.package.to_string(),
+            "db": deployment.db.to_string(),
+            "node_small_id": node_small_id,
+            "node_badge": node_badge.to_string(),
+            "task_small_id": task_small_id,
+            "price_per_one_million_compute_units":
+                price_per_one_million_compute_units,
+            "num_compute_units": num_compute_units,
+            "expires_at_ms": expires_at_ms,
+            "nonce": nonce,
+            "hash": Hex::encode(self.offer.hash()),
+            "signer": self.signer.to_string(),
+            "signature": self.signature.encode_base64(),
+        })
+    }
+
+    /// The hash is informative only, we always recompute it.
+    pub(crate) fn from_json(json: &serde_json::Value) -> Result<Self> {
+        let u64_field = |name: &str| {
+            json[name]
+                .as_u64()
+                .ok_or_else(|| anyhow!("Offer is missing {name}"))
+        };
+        let str_field = |name: &str| {
+            json[name]
+                .as_str()
+                .ok_or_else(|| anyhow!("Offer is missing {name}"))
+        };
+
+        Ok(Self {
+            offer: Offer {
+                deployment: Deployment {
+                    package: ObjectID::from_str(str_field("package")?)?,
+                    db: ObjectID::from_str(str_field("db")?)?,
+                },
+                node_small_id: u64_field("node_small_id")?,
+                node_badge: ObjectID::from_str(str_field("node_badge")?)?,
+                task_small_id: u64_field("task_small_id")?,
+                price_per_one_million_compute_units: u64_field(
+                    "price_per_one_million_compute_units",
+                )?,
+                num_compute_units: u64_field("num_compute_units")?,
+                expires_at_ms: u64_field("expires_at_ms")?,
+                nonce: u64_field("nonce")?,
+            },
+            signer: SuiAddress::from_str(str_field("signer")?)?,
+            signature: Signature::decode_base64(str_field("signature")?)
+                .map_err(|err| anyhow!("Cannot decode signature: {err}"))?,
+        })
+    }
+}
+
+/// All offers in the order book, in the order they were posted.
+/// A missing book is empty.
+pub(crate) fn load_book(book: &Path) -> Result<Vec<SignedOffer>> {
+    if !book.exists() {
+        return Ok(vec![]);
+    }
+
+    std::fs::read_to_string(book)?
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(line_index, line)| {
+            SignedOffer::from_json(&serde_json::from_str(line)?)
+                .map_err(|err| anyhow!("Line {}: {err}", line_index + 1))
+        })
+        .collect()
+}
+
+/// Appends the offer as a line to the order book.
+pub(crate) fn post_to_book(book: &Path, offer: &SignedOffer) -> Result<()> {
+    use std::io::Write;
+
+    let mut file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(book)?;
+    writeln!(file, "{}", offer.to_json())?;
+
+    Ok(())
+}
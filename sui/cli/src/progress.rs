@@ -0,0 +1,36 @@
+//! Progress of long running commands.
+//!
+//! With the JSON format, each step is reported as a line of JSON on stderr,
+//! eg. `{"phase":"nodes","pct":40}`, so that wrappers can render progress
+//! without parsing the human readable output on stdout.
+
+use std::io::Write;
+
+use clap::ValueEnum;
+use serde_json::json;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub(crate) enum ProgressFormat {
+    /// Progress is only visible in the command's usual output.
+    #[default]
+    Human,
+    /// Line-delimited JSON progress events on stderr.
+    Json,
+}
+
+/// `done` out of `total` steps of the phase are completed.
+pub(crate) fn report(
+    format: ProgressFormat,
+    phase: &str,
+    done: usize,
+    total: usize,
+) {
+    if format != ProgressFormat::Json {
+        return;
+    }
+
+    let pct = if total == 0 { 100 } else { done * 100 / total };
+    let event = json!({ "phase": phase, "pct": pct.min(100) });
+    // progress is best effort, a closed stderr must not fail the command
+    let _ = writeln!(std::io::stderr().lock(), "{event}");
+}
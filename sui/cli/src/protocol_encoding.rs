@@ -0,0 +1,158 @@
+//! The encoding of the opaque bytes that prompts carry for the nodes, such as
+//! the output destination.
+//! The contract stores them as is, so this is a wire protocol between the
+//! submitters and the nodes.
+//!
+//! An envelope is a version byte followed by the MessagePack (rmp) encoded
+//! payload:
+//! - version 1: the payload is the MessagePack string of the destination,
+//!   eg. `Firebase`
+//! - version 0: bytes without a version byte, as submitted before envelopes
+//!   were versioned, are decoded as the payload itself
+//!
+//! Empty bytes mean no output destination.
+//!
+//! Versions are below `0x80` so that they can't be mistaken for the first
+//! byte of a legacy MessagePack string.
+//!
+//! Version 1 is a breaking change for nodes that only decode version 0, they
+//! must upgrade before prompts are submitted with this version of the CLI.
+//!
+//! The tests pin golden vectors, so that a change of the encoding is caught
+//! before it reaches the nodes.
+
+use anyhow::{anyhow, Result};
+use serde_json::Value;
+
+/// Bump when the payload changes in a way that nodes can't decode.
+pub const ENVELOPE_VERSION: u8 = 1;
+/// Bytes submitted before envelopes were versioned.
+pub const LEGACY_ENVELOPE_VERSION: u8 = 0;
+
+/// The destination that the CLI submits prompts with.
+pub const FIREBASE_OUTPUT_DESTINATION: &str = "Firebase";
+
+#[derive(Debug, PartialEq)]
+pub struct Envelope {
+    pub version: u8,
+    /// None if there are no bytes at all.
+    pub payload: Option<Value>,
+}
+
+pub fn encode_output_destination(destination: &str) -> Result<Vec<u8>> {
+    let mut bytes = vec![ENVELOPE_VERSION];
+    bytes.extend(rmp_serde::to_vec(destination)?);
+    Ok(bytes)
+}
+
+pub fn decode(bytes: &[u8]) -> Result<Envelope> {
+    let (version, payload) = match bytes.split_first() {
+        None => {
+            return Ok(Envelope {
+                version: ENVELOPE_VERSION,
+                payload: None,
+            })
+        }
+        Some((&ENVELOPE_VERSION, payload)) => (ENVELOPE_VERSION, payload),
+        // positive fixints, which no legacy payload started with
+        Some((&version, _)) if version < 0x80 => {
+            anyhow::bail!("Unknown envelope version {version}")
+        }
+        Some(_) => (LEGACY_ENVELOPE_VERSION, bytes),
+    };
+    let payload = rmp_serde::from_slice(payload).map_err(|err| {
+        anyhow!("Cannot decode a version {version} envelope: {err}")
+    })?;
+
+    Ok(Envelope {
+        version,
+        payload: Some(payload),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use fastcrypto::encoding::{Encoding, Hex};
+
+    use super::*;
+
+    /// Payload, version and hex encoded envelope.
+    /// Nodes can pin the same vectors.
+    const GOLDEN_VECTORS: [(&str, u8, &str); 4] = [
+        (FIREBASE_OUTPUT_DESTINATION, 1, "01a84669726562617365"),
+        (FIREBASE_OUTPUT_DESTINATION, 0, "a84669726562617365"),
+        // longer than 31 bytes is no longer a fixstr
+        (
+            "https://example.com/atoma/outputs/1",
+            1,
+            "01d92368747470733a2f2f6578616d706c652e636f6d2f61746f6d612f6f7574707574732f31",
+        ),
+        ("", 1, "01a0"),
+    ];
+
+    fn envelope(version: u8, payload: &str) -> Envelope {
+        Envelope {
+            version,
+            payload: Some(Value::String(payload.to_string())),
+        }
+    }
+
+    #[test]
+    fn golden_vectors_decode_to_their_payload() {
+        for (payload, version, hex) in GOLDEN_VECTORS {
+            let bytes = Hex::decode(hex).unwrap();
+            assert_eq!(decode(&bytes).unwrap(), envelope(version, payload));
+        }
+    }
+
+    #[test]
+    fn current_version_encodes_to_golden_vectors() {
+        for (payload, version, hex) in GOLDEN_VECTORS {
+            if version == ENVELOPE_VERSION {
+                let encoded = encode_output_destination(payload).unwrap();
+                assert_eq!(Hex::encode(encoded), hex, "{payload:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn output_destination_round_trips() {
+        for destination in [
+            FIREBASE_OUTPUT_DESTINATION,
+            "",
+            "ünïcödé",
+            "x".repeat(300).as_str(),
+        ] {
+            let encoded = encode_output_destination(destination).unwrap();
+            assert_eq!(
+                decode(&encoded).unwrap(),
+                envelope(ENVELOPE_VERSION, destination)
+            );
+        }
+    }
+
+    #[test]
+    fn unversioned_bytes_decode_as_legacy_envelope() {
+        let legacy = rmp_serde::to_vec(FIREBASE_OUTPUT_DESTINATION).unwrap();
+        assert_eq!(
+            decode(&legacy).unwrap(),
+            envelope(LEGACY_ENVELOPE_VERSION, FIREBASE_OUTPUT_DESTINATION)
+        );
+    }
+
+    #[test]
+    fn empty_bytes_have_no_payload() {
+        assert_eq!(
+            decode(&[]).unwrap(),
+            Envelope {
+                version: ENVELOPE_VERSION,
+                payload: None,
+            }
+        );
+    }
+
+    #[test]
+    fn unknown_version_is_rejected() {
+        assert!(decode(&[2, 0xa0]).is_err());
+    }
+}
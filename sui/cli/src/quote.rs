@@ -0,0 +1,13 @@
+//! Price quotes that nodes sign off-chain and users accept when acquiring a
+//! stack.
+
+mod accept;
+mod estimate;
+mod issue;
+mod signed_quote;
+mod verify;
+
+pub(crate) use accept::command as accept;
+pub(crate) use estimate::command as estimate;
+pub(crate) use issue::command as issue;
+pub(crate) use verify::{command as verify, verify_node_badge_owner};
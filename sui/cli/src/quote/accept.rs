@@ -0,0 +1,65 @@
+use std::path::Path;
+
+use sui_sdk::types::{crypto::SuiSignature, SUI_CLOCK_OBJECT_ID};
+
+use crate::{prelude::*, DB_MODULE_NAME};
+
+const ENDPOINT_NAME: &str = "acquire_new_stack_with_quote_entry";
+
+/// Verifies the quote and acquires a stack from the quoting node at the
+/// quoted price.
+///
+/// The quote hash is recomputed on-chain, the signature is checked against
+/// the key the node registered with `offer set-key`, and the hash is emitted
+/// with the new stack so that the stack can be matched with the signed
+/// quote.
+pub(crate) async fn command(
+    context: &mut Context,
+    quote: &Path,
+) -> Result<TransactionDigest> {
+    let signed_quote = super::verify(context, quote).await?;
+    let quote = &signed_quote.quote;
+
+    let sender = context.sender()?;
+    let atoma_package = context.unwrap_atoma_package_id();
+    let atoma_db = context.get_or_load_atoma_db().await?;
+    let toma_wallet = context.get_or_load_toma_wallet().await?;
+
+    let tx = context
+        .get_client()
+        .await?
+        .transaction_builder()
+        .move_call(
+            sender,
+            atoma_package,
+            DB_MODULE_NAME,
+            ENDPOINT_NAME,
+            vec![],
+            vec![
+                SuiJsonValue::from_object_id(atoma_db),
+                SuiJsonValue::from_object_id(toma_wallet),
+                SuiJsonValue::new(quote.task_small_id.to_string().into())?,
+                SuiJsonValue::new(quote.num_compute_units.to_string().into())?,
+                SuiJsonValue::new(
+                    quote
+                        .price_per_one_million_compute_units
+                        .to_string()
+                        .into(),
+                )?,
+                SuiJsonValue::new(quote.node_small_id.to_string().into())?,
+                SuiJsonValue::new(quote.expires_at_ms.to_string().into())?,
+                SuiJsonValue::new(
+                    signed_quote.signature.signature_bytes().to_vec().into(),
+                )?,
+                SuiJsonValue::from_object_id(SUI_CLOCK_OBJECT_ID),
+            ],
+            None,
+            context.gas_budget(),
+            None,
+        )
+        .await?;
+
+    let tx = context.sign_transaction(&tx)?;
+    let resp = context.execute_transaction_must_succeed(tx).await?;
+    Ok(resp.digest)
+}
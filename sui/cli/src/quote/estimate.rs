@@ -0,0 +1,46 @@
+use crate::{db, prelude::*};
+
+/// Must match the on-chain security level.
+const NO_SECURITY: u64 = 0;
+
+/// Prints what a stack of the task costs the user at the given price,
+/// including the sampling consensus charges.
+pub(crate) async fn command(
+    context: &mut Context,
+    task_small_id: u64,
+    price_per_one_million_compute_units: u64,
+    num_compute_units: u64,
+) -> Result<u64> {
+    let security_level =
+        db::task_security_level(context, task_small_id).await?;
+    let params = db::SamplingParams::load(context).await?;
+    let cost = params.stack_cost(
+        security_level,
+        price_per_one_million_compute_units,
+        num_compute_units,
+    );
+    let base_cost = params.stack_cost(
+        NO_SECURITY,
+        price_per_one_million_compute_units,
+        num_compute_units,
+    );
+
+    println!(
+        "{num_compute_units} compute unit(s) of task {task_small_id} \
+        at {} per million cost {}",
+        context.toma(price_per_one_million_compute_units),
+        context.toma(cost),
+    );
+    if cost != base_cost {
+        println!(
+            "Includes sampling consensus charges of {}‰ and {}‰ \
+            for {} attestation node(s) sampled with {}‰ probability",
+            params.sampling_consensus_charge_permille,
+            params.cross_validation_extra_nodes_charge_permille,
+            params.cross_validation_extra_nodes_count,
+            params.cross_validation_probability_permille,
+        );
+    }
+
+    Ok(cost)
+}
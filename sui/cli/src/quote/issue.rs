@@ -0,0 +1,49 @@
+use std::{
+    path::Path,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use super::signed_quote::Quote;
+use crate::{prelude::*, signed_terms::Deployment};
+
+/// Signs a quote with the key of the active address, which must own the node
+/// badge, and writes it into the given file.
+pub(crate) async fn command(
+    context: &mut Context,
+    task_small_id: u64,
+    price_per_one_million_compute_units: u64,
+    num_compute_units: u64,
+    valid_for: Duration,
+    out: &Path,
+) -> Result<()> {
+    context.conf.role.ensure_can_sign()?;
+    let active_address = context.wallet.active_address()?;
+    let (node_badge, node_small_id) = context.get_or_load_node_badge().await?;
+    let expires_at_ms = (SystemTime::now().duration_since(UNIX_EPOCH)?
+        + valid_for)
+        .as_millis() as u64;
+
+    let signed_quote = Quote {
+        deployment: Deployment::load(context).await?,
+        node_small_id,
+        node_badge,
+        task_small_id,
+        price_per_one_million_compute_units,
+        num_compute_units,
+        expires_at_ms,
+    }
+    .sign(&context.wallet, active_address)?;
+
+    let json = signed_quote.to_json();
+    std::fs::write(out, serde_json::to_vec_pretty(&json)?)?;
+    println!("Quote {} written to {out:?}", json["hash"]);
+    super::estimate(
+        context,
+        task_small_id,
+        price_per_one_million_compute_units,
+        num_compute_units,
+    )
+    .await?;
+
+    Ok(())
+}
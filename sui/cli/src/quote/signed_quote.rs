@@ -0,0 +1,149 @@
+//! A quote is a node's offer to process a number of compute units for a task
+//! at a given price until some time.
+//!
+//! The quote is identified by the hash of the signed terms
+//! `node_small_id || task_small_id || price || compute_units ||
+//! expires_at_ms`, see the `signed_terms` module.
+//! This must match `db::quote_hash` so that the hash recorded on-chain when
+//! the quote is accepted can be matched with the signed quote.
+
+use std::path::Path;
+
+use fastcrypto::{
+    encoding::{Encoding, Hex},
+    traits::EncodeDecodeBase64,
+};
+use serde_json::json;
+use sui_sdk::types::{
+    base_types::{ObjectID, SuiAddress},
+    crypto::Signature,
+};
+
+use crate::{
+    prelude::*,
+    signed_terms::{self, Deployment},
+};
+
+/// Must match `QUOTE_DOMAIN` in the db module.
+const DOMAIN: &[u8] = b"atoma-quote";
+
+pub(crate) struct Quote {
+    pub(crate) deployment: Deployment,
+    pub(crate) node_small_id: u64,
+    pub(crate) node_badge: ObjectID,
+    pub(crate) task_small_id: u64,
+    pub(crate) price_per_one_million_compute_units: u64,
+    pub(crate) num_compute_units: u64,
+    pub(crate) expires_at_ms: u64,
+}
+
+pub(crate) struct SignedQuote {
+    pub(crate) quote: Quote,
+    pub(crate) signer: SuiAddress,
+    pub(crate) signature: Signature,
+}
+
+impl Quote {
+    pub(crate) fn hash(&self) -> [u8; 32] {
+        signed_terms::hash(
+            DOMAIN,
+            &self.deployment,
+            &[
+                self.node_small_id,
+                self.task_small_id,
+                self.price_per_one_million_compute_units,
+                self.num_compute_units,
+                self.expires_at_ms,
+            ],
+        )
+    }
+
+    pub(crate) fn sign(
+        self,
+        wallet: &WalletContext,
+        signer: SuiAddress,
+    ) -> Result<SignedQuote> {
+        let signature =
+            signed_terms::sign(&wallet.config.keystore, signer, self.hash())?;
+
+        Ok(SignedQuote {
+            quote: self,
+            signer,
+            signature,
+        })
+    }
+}
+
+impl SignedQuote {
+    /// Fails if the quote was not signed by the signer.
+    ///
+    /// It's up to the caller to check that the signer owns the node badge
+    /// and that its key is the offer key of the node.
+    pub(crate) fn verify_signature(&self) -> Result<()> {
+        signed_terms::verify(self.quote.hash(), self.signer, &self.signature)
+            .map_err(|err| anyhow!("Quote: {err}"))
+    }
+
+    pub(crate) fn to_json(&self) -> serde_json::Value {
+        let Quote {
+            deployment,
+            node_small_id,
+            node_badge,
+            task_small_id,
+            price_per_one_million_compute_units,
+            num_compute_units,
+            expires_at_ms,
+        } = &self.quote;
+
+        json!({
+            "package": deployment.package.to_string(),
+            "db": deployment.db.to_string(),
+            "node_small_id": node_small_id,
+            "node_badge": node_badge.to_string(),
+            "task_small_id": task_small_id,
+            "price_per_one_million_compute_units":
+                price_per_one_million_compute_units,
+            "num_compute_units": num_compute_units,
+            "expires_at_ms": expires_at_ms,
+            "hash": Hex::encode(self.quote.hash()),
+            "signer": self.signer.to_string(),
+            "signature": self.signature.encode_base64(),
+        })
+    }
+
+    /// The hash in the file is informative only, we always recompute it.
+    pub(crate) fn from_file(path: &Path) -> Result<Self> {
+        let json: serde_json::Value =
+            serde_json::from_slice(&std::fs::read(path)?)?;
+        let u64_field = |name: &str| {
+            json[name]
+                .as_u64()
+                .ok_or_else(|| anyhow!("Quote is missing {name}"))
+        };
+        let str_field = |name: &str| {
+            json[name]
+                .as_str()
+                .ok_or_else(|| anyhow!("Quote is missing {name}"))
+        };
+
+        Ok(Self {
+            quote: Quote {
+                deployment: Deployment {
+                    package: ObjectID::from_str(str_field("package")?)?,
+                    db: ObjectID::from_str(str_field("db")?)?,
+                },
+                node_small_id: u64_field("node_small_id")?,
+                node_badge: ObjectID::from_str(str_field("node_badge")?)?,
+                task_small_id: u64_field("task_small_id")?,
+                price_per_one_million_compute_units: u64_field(
+                    "price_per_one_million_compute_units",
+                )?,
+                num_compute_units: u64_field("num_compute_units")?,
+                expires_at_ms: u64_field("expires_at_ms")?,
+            },
+            signer: SuiAddress::from_str(str_field("signer")?)?,
+            signature: Signature::decode_base64(str_field("signature")?)
+                .map_err(|err| anyhow!("Cannot decode signature: {err}"))?,
+        })
+    }
+}
@@ -0,0 +1,115 @@
+use std::{
+    path::Path,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use sui_sdk::{
+    rpc_types::{SuiData, SuiObjectDataOptions},
+    types::{
+        base_types::{ObjectID, ObjectType, SuiAddress},
+        object::Owner,
+    },
+};
+
+use super::signed_quote::SignedQuote;
+use crate::{prelude::*, DB_MODULE_NAME, DB_NODE_TYPE_NAME};
+
+/// 1. Check that the quote has not expired yet and that it was signed for
+///    the configured deployment.
+/// 2. Check the signature.
+/// 3. Check that the signer owns the badge of the node that the quote is for.
+/// 4. Print what accepting the quote costs.
+pub(crate) async fn command(
+    context: &mut Context,
+    quote: &Path,
+) -> Result<SignedQuote> {
+    let signed_quote = SignedQuote::from_file(quote)?;
+    let quote = &signed_quote.quote;
+
+    // 1.
+    let now_ms = SystemTime::now().duration_since(UNIX_EPOCH)?.as_millis();
+    if now_ms > quote.expires_at_ms as u128 {
+        anyhow::bail!("Quote expired at {} ms", quote.expires_at_ms);
+    }
+    quote.deployment.ensure_configured(context).await?;
+
+    // 2.
+    signed_quote.verify_signature()?;
+
+    // 3.
+    verify_node_badge_owner(
+        context,
+        quote.node_badge,
+        signed_quote.signer,
+        quote.node_small_id,
+    )
+    .await?;
+
+    // 4.
+    super::estimate(
+        context,
+        quote.task_small_id,
+        quote.price_per_one_million_compute_units,
+        quote.num_compute_units,
+    )
+    .await?;
+
+    Ok(signed_quote)
+}
+
+/// Fails unless the badge is a node badge of the given node owned by the
+/// signer.
+pub(crate) async fn verify_node_badge_owner(
+    context: &mut Context,
+    node_badge: ObjectID,
+    signer: SuiAddress,
+    node_small_id: u64,
+) -> Result<()> {
+    let badge = context
+        .get_client()
+        .await?
+        .read_api()
+        .get_object_with_options(
+            node_badge,
+            SuiObjectDataOptions {
+                show_type: true,
+                show_owner: true,
+                show_content: true,
+                ..Default::default()
+            },
+        )
+        .await?
+        .data
+        .ok_or_else(|| anyhow!("Node badge {node_badge} not found"))?;
+
+    let is_node_badge = matches!(
+        &badge.type_,
+        Some(ObjectType::Struct(type_))
+            if type_.module().as_str() == DB_MODULE_NAME
+                && type_.name().as_str() == DB_NODE_TYPE_NAME
+    );
+    if !is_node_badge {
+        anyhow::bail!("Object {node_badge} is not a node badge");
+    }
+    if badge.owner != Some(Owner::AddressOwner(signer)) {
+        anyhow::bail!(
+            "Node badge {node_badge} is not owned by the signer {signer}"
+        );
+    }
+    let small_id = badge
+        .content
+        .and_then(|content| content.try_as_move().cloned())
+        .and_then(|content| {
+            content.fields.to_json_value()["small_id"]["inner"]
+                .as_str()?
+                .parse::<u64>()
+                .ok()
+        });
+    if small_id != Some(node_small_id) {
+        anyhow::bail!(
+            "Node badge {node_badge} does not belong to node {node_small_id}"
+        );
+    }
+
+    Ok(())
+}
@@ -0,0 +1,13 @@
+//! Receipts that nodes sign off-chain for each request they serve with a
+//! stack.
+//!
+//! A user who collected the receipts of a stack can check that what the node
+//! settled on-chain matches what it charged, without any on-chain data about
+//! individual requests.
+
+mod receipt;
+mod sign;
+mod verify;
+
+pub(crate) use sign::command as sign;
+pub(crate) use verify::command as verify;
@@ -0,0 +1,154 @@
+//! A receipt states that a node charged a number of compute units of a stack
+//! for a request, and how many it charged for the stack in total so far.
+//!
+//! The signed message is the Blake2b-256 hash of
+//! `DOMAIN || node_small_id || stack_small_id || H(payload) ||
+//! compute_units || running_total`, all numbers little-endian, where
+//! `H(payload)` is the Blake2b-256 hash of the request payload.
+//! It's wrapped as a personal message so that a wallet can never be tricked
+//! into signing a transaction instead.
+
+use fastcrypto::{
+    encoding::{Encoding, Hex},
+    hash::{Blake2b256, HashFunction},
+    traits::EncodeDecodeBase64,
+};
+use serde_json::json;
+use shared_crypto::intent::{Intent, IntentMessage, PersonalMessage};
+use sui_keys::keystore::AccountKeystore;
+use sui_sdk::types::{
+    base_types::{ObjectID, SuiAddress},
+    crypto::{Signature, SuiSignature},
+};
+
+use crate::prelude::*;
+
+/// Prevents a receipt signature to be reused in some other context.
+const DOMAIN: &[u8] = b"atoma-receipt";
+
+pub(crate) struct Receipt {
+    pub(crate) node_small_id: u64,
+    pub(crate) node_badge: ObjectID,
+    pub(crate) stack_small_id: u64,
+    pub(crate) request_hash: [u8; 32],
+    pub(crate) compute_units: u64,
+    pub(crate) running_total: u64,
+}
+
+pub(crate) struct SignedReceipt {
+    pub(crate) receipt: Receipt,
+    pub(crate) signer: SuiAddress,
+    pub(crate) signature: Signature,
+}
+
+impl Receipt {
+    pub(crate) fn hash(&self) -> [u8; 32] {
+        Blake2b256::digest(self.message()).digest
+    }
+
+    pub(crate) fn sign(
+        self,
+        wallet: &WalletContext,
+        signer: SuiAddress,
+    ) -> Result<SignedReceipt> {
+        let signature = wallet.config.keystore.sign_secure(
+            &signer,
+            &PersonalMessage {
+                message: self.hash().to_vec(),
+            },
+            Intent::personal_message(),
+        )?;
+
+        Ok(SignedReceipt {
+            receipt: self,
+            signer,
+            signature,
+        })
+    }
+
+    fn message(&self) -> Vec<u8> {
+        let mut message = DOMAIN.to_vec();
+        message.extend_from_slice(&self.node_small_id.to_le_bytes());
+        message.extend_from_slice(&self.stack_small_id.to_le_bytes());
+        message.extend_from_slice(&self.request_hash);
+        message.extend_from_slice(&self.compute_units.to_le_bytes());
+        message.extend_from_slice(&self.running_total.to_le_bytes());
+        message
+    }
+}
+
+impl SignedReceipt {
+    /// Fails if the receipt was not signed by the signer.
+    ///
+    /// It's up to the caller to check that the signer owns the node badge.
+    pub(crate) fn verify_signature(&self) -> Result<()> {
+        let message = IntentMessage::new(
+            Intent::personal_message(),
+            PersonalMessage {
+                message: self.receipt.hash().to_vec(),
+            },
+        );
+        self.signature
+            .verify_secure(&message, self.signer, self.signature.scheme())
+            .map_err(|err| anyhow!("Invalid receipt signature: {err}"))
+    }
+
+    pub(crate) fn to_json(&self) -> serde_json::Value {
+        let Receipt {
+            node_small_id,
+            node_badge,
+            stack_small_id,
+            request_hash,
+            compute_units,
+            running_total,
+        } = &self.receipt;
+
+        json!({
+            "node_small_id": node_small_id,
+            "node_badge": node_badge.to_string(),
+            "stack_small_id": stack_small_id,
+            "request_hash": Hex::encode(request_hash),
+            "compute_units": compute_units,
+            "running_total": running_total,
+            "signer": self.signer.to_string(),
+            "signature": self.signature.encode_base64(),
+        })
+    }
+
+    pub(crate) fn from_json(json: &serde_json::Value) -> Result<Self> {
+        let u64_field = |name: &str| {
+            json[name]
+                .as_u64()
+                .ok_or_else(|| anyhow!("Receipt is missing {name}"))
+        };
+        let str_field = |name: &str| {
+            json[name]
+                .as_str()
+                .ok_or_else(|| anyhow!("Receipt is missing {name}"))
+        };
+
+        let request_hash = Hex::decode(str_field("request_hash")?)
+            .map_err(|err| anyhow!("Cannot decode request hash: {err}"))?
+            .try_into()
+            .map_err(|_| anyhow!("Request hash must be 32 bytes"))?;
+
+        Ok(Self {
+            receipt: Receipt {
+                node_small_id: u64_field("node_small_id")?,
+                node_badge: ObjectID::from_str(str_field("node_badge")?)?,
+                stack_small_id: u64_field("stack_small_id")?,
+                request_hash,
+                compute_units: u64_field("compute_units")?,
+                running_total: u64_field("running_total")?,
+            },
+            signer: SuiAddress::from_str(str_field("signer")?)?,
+            signature: Signature::decode_base64(str_field("signature")?)
+                .map_err(|err| anyhow!("Cannot decode signature: {err}"))?,
+        })
+    }
+}
+
+/// Identifies the request in a receipt without revealing its payload.
+pub(crate) fn request_hash(payload: &[u8]) -> [u8; 32] {
+    Blake2b256::digest(payload).digest
+}
@@ -0,0 +1,38 @@
+use super::receipt::{request_hash, Receipt};
+use crate::prelude::*;
+
+/// Signs a receipt for a request with the key of the active address, which
+/// must own the node badge, and prints it as a line of JSON.
+///
+/// Nodes append the lines to the response or to a file that they hand over
+/// to the stack owner.
+pub(crate) async fn command(
+    context: &mut Context,
+    stack_small_id: u64,
+    payload: &str,
+    compute_units: u64,
+    running_total: u64,
+) -> Result<()> {
+    if running_total < compute_units {
+        anyhow::bail!(
+            "The running total must include the compute units of this request"
+        );
+    }
+    context.conf.role.ensure_can_sign()?;
+    let active_address = context.wallet.active_address()?;
+    let (node_badge, node_small_id) = context.get_or_load_node_badge().await?;
+
+    let signed_receipt = Receipt {
+        node_small_id,
+        node_badge,
+        stack_small_id,
+        request_hash: request_hash(payload.as_bytes()),
+        compute_units,
+        running_total,
+    }
+    .sign(&context.wallet, active_address)?;
+
+    println!("{}", signed_receipt.to_json());
+
+    Ok(())
+}
@@ -0,0 +1,116 @@
+use std::{collections::HashSet, path::Path};
+
+use fastcrypto::encoding::{Encoding, Hex};
+
+use super::receipt::SignedReceipt;
+use crate::{prelude::*, quote, stack};
+
+/// 1. Parse the receipts of the stack from the JSONL file, in the order the
+///    node issued them.
+/// 2. Check that each is signed by the owner of the badge of the node that
+///    the stack is served by.
+/// 3. Check that the running totals add up and no request is charged twice.
+/// 4. Compare the total with what the node settled on-chain, if anything.
+///
+/// Returns how many problems were found.
+pub(crate) async fn command(
+    context: &mut Context,
+    stack_small_id: u64,
+    receipts: &Path,
+) -> Result<usize> {
+    // 1.
+    let mut signed_receipts = vec![];
+    for (line_index, line) in std::fs::read_to_string(receipts)?
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+    {
+        let signed_receipt =
+            SignedReceipt::from_json(&serde_json::from_str(line)?)
+                .map_err(|err| anyhow!("Line {}: {err}", line_index + 1))?;
+        if signed_receipt.receipt.stack_small_id == stack_small_id {
+            signed_receipts.push(signed_receipt);
+        }
+    }
+    let Some(first) = signed_receipts.first() else {
+        anyhow::bail!("No receipts for stack {stack_small_id}");
+    };
+    info!("Loaded {} receipt(s)", signed_receipts.len());
+
+    // 2.
+    let stack = stack::load_stack(context, stack_small_id).await?;
+    let selected_node_id = stack["value"]["selected_node_id"]["inner"]
+        .as_str()
+        .and_then(|s| s.parse::<u64>().ok())
+        .ok_or_else(|| anyhow!("Stack {stack_small_id} has no node"))?;
+    let (node_badge, signer) = (first.receipt.node_badge, first.signer);
+    quote::verify_node_badge_owner(
+        context,
+        node_badge,
+        signer,
+        selected_node_id,
+    )
+    .await?;
+
+    // 3.
+    let mut problems = 0;
+    let mut total = 0u64;
+    let mut request_hashes = HashSet::new();
+    for (index, signed_receipt) in signed_receipts.iter().enumerate() {
+        let receipt = &signed_receipt.receipt;
+        let request = Hex::encode(receipt.request_hash);
+        let mut flag = |problem: String| {
+            problems += 1;
+            println!(
+                "[INVALID] Receipt {index} for request {request}: {problem}"
+            );
+        };
+
+        if let Err(err) = signed_receipt.verify_signature() {
+            flag(err.to_string());
+            continue;
+        }
+        if signed_receipt.signer != signer
+            || receipt.node_badge != node_badge
+            || receipt.node_small_id != selected_node_id
+        {
+            flag("signed by a different node".to_string());
+            continue;
+        }
+        if !request_hashes.insert(receipt.request_hash) {
+            flag("charged twice".to_string());
+        }
+        total += receipt.compute_units;
+        if receipt.running_total != total {
+            flag(format!(
+                "running total is {} but the receipts add up to {total}",
+                receipt.running_total
+            ));
+            // continue from what the node claims to detect later gaps only
+            total = receipt.running_total;
+        }
+    }
+    println!(
+        "{} receipt(s) for {total} compute units signed by node \
+        {selected_node_id}",
+        signed_receipts.len()
+    );
+
+    // 4.
+    match stack::settled_compute_units(context, stack_small_id).await? {
+        Some(settled) if settled > total => {
+            problems += 1;
+            println!(
+                "[OVERCHARGED] The node settled {settled} compute units, \
+                {} more than the receipts prove",
+                settled - total
+            );
+        }
+        Some(settled) => {
+            println!("The node settled {settled} compute units");
+        }
+        None => println!("The stack is not settled yet"),
+    }
+
+    Ok(problems)
+}
@@ -0,0 +1,6 @@
+//! Escrow that prompt submitters recover from tickets that timed out, see
+//! `cancel_prompt` in the settlement module.
+
+mod sweep;
+
+pub(crate) use sweep::command as sweep;
@@ -0,0 +1,240 @@
+use std::time::Duration;
+
+use sui_sdk::{
+    rpc_types::{
+        MoveCallParams, Page, RPCTransactionRequestParams, SuiData,
+        SuiExecutionStatus, SuiObjectDataOptions,
+        SuiTransactionBlockEffectsAPI,
+    },
+    types::base_types::ObjectID,
+};
+
+use crate::{
+    db::parse_u64,
+    prelude::*,
+    settle::{partial_settlement, timed_out},
+    SETTLEMENT_MODULE_NAME,
+};
+
+const ENDPOINT_NAME: &str = "cancel_prompt";
+const PROMPT_CANCELLED_EVENT_NAME: &str = "PromptCancelledEvent";
+
+/// A timed out ticket of the active address.
+struct Refundable {
+    ticket_id: ObjectID,
+    expected_refund: u64,
+}
+
+/// Runs a round every `interval` until stopped, or a single one without it.
+/// A round that fails, eg. because the RPC is down, doesn't stop the
+/// daemon.
+pub(crate) async fn command(
+    context: &mut Context,
+    max_tickets: usize,
+    interval: Option<Duration>,
+) -> Result<()> {
+    loop {
+        match round(context, max_tickets).await {
+            Err(err) if interval.is_some() => {
+                error!("Failed to sweep refunds: {err}")
+            }
+            result => result?,
+        }
+
+        let Some(interval) = interval else {
+            return Ok(());
+        };
+        tokio::time::sleep(interval).await;
+    }
+}
+
+/// 1. Find the tickets paid by the active address that timed out and that
+///    not every node committed to.
+///    Cancelling settles those with a commitment partially.
+/// 2. Refund them in batches of at most `max_tickets` calls per transaction.
+///    A batch that fails, eg. because a ticket was settled in the meantime,
+///    is logged and the sweep goes on with the next one.
+/// 3. Report the refunds from the emitted events.
+async fn round(context: &mut Context, max_tickets: usize) -> Result<()> {
+    // 1.
+    let refundable = find_refundable(context).await?;
+    if refundable.is_empty() {
+        println!("No tickets to refund");
+        return Ok(());
+    }
+    info!("Found {} refundable ticket(s)", refundable.len());
+
+    // 2.
+    let sender = context.sender()?;
+    let atoma_package = context.unwrap_atoma_package_id();
+    let atoma_db = context.get_or_load_atoma_db().await?;
+    let mut refunded_tickets = 0;
+    let mut recovered = 0u64;
+    for batch in refundable.chunks(max_tickets.max(1)) {
+        let calls = batch
+            .iter()
+            .map(|refundable| {
+                RPCTransactionRequestParams::MoveCallRequestParams(
+                    MoveCallParams {
+                        package_object_id: atoma_package,
+                        module: SETTLEMENT_MODULE_NAME.to_string(),
+                        function: ENDPOINT_NAME.to_string(),
+                        type_arguments: vec![],
+                        arguments: vec![
+                            SuiJsonValue::from_object_id(atoma_db),
+                            SuiJsonValue::from_object_id(refundable.ticket_id),
+                        ],
+                    },
+                )
+            })
+            .collect();
+        let tx = context
+            .get_client()
+            .await?
+            .transaction_builder()
+            .batch_transaction(sender, calls, None, context.gas_budget())
+            .await?;
+
+        let tx = context.sign_transaction(&tx)?;
+        let resp = context.execute_transaction_may_fail(tx).await?;
+        if let SuiExecutionStatus::Failure { error } =
+            resp.effects.as_ref().unwrap().status()
+        {
+            error!(
+                "Failed to refund {} ticket(s) in {}: {error}",
+                batch.len(),
+                resp.digest
+            );
+            continue;
+        }
+
+        // 3.
+        let mut batch_recovered = 0;
+        for event in resp.events.iter().flat_map(|events| &events.data) {
+            if event.type_.name.as_str() == PROMPT_CANCELLED_EVENT_NAME {
+                batch_recovered +=
+                    parse_u64(&event.parsed_json["refunded_amount"])?;
+            }
+        }
+        let expected: u64 = batch
+            .iter()
+            .map(|refundable| refundable.expected_refund)
+            .sum();
+        if batch_recovered != expected {
+            log::warn!(
+                "Expected {} but recovered {} in {}",
+                context.toma(expected),
+                context.toma(batch_recovered),
+                resp.digest
+            );
+        }
+        info!(
+            "Refunded {} ticket(s) for {} in {}",
+            batch.len(),
+            context.toma(batch_recovered),
+            resp.digest
+        );
+        refunded_tickets += batch.len();
+        recovered += batch_recovered;
+    }
+
+    println!(
+        "Refunded {refunded_tickets} of {} ticket(s), recovered {}",
+        refundable.len(),
+        context.toma(recovered)
+    );
+
+    Ok(())
+}
+
+/// Mirrors the checks of the `cancel_prompt` endpoint, so that one ticket
+/// doesn't fail its whole batch.
+async fn find_refundable(context: &mut Context) -> Result<Vec<Refundable>> {
+    let sender = context.sender()?;
+    let tickets_root = ObjectID::from_str(
+        context.load_atoma_db_fields().await?["tickets"]["id"]
+            .as_str()
+            .ok_or_else(|| anyhow!("No tickets field found"))?,
+    )?;
+    let client = context.get_client().await?;
+    let system_state = client
+        .governance_api()
+        .get_latest_sui_system_state()
+        .await?;
+
+    let mut refundable = vec![];
+    let mut cursor = None;
+    loop {
+        let Page {
+            data,
+            has_next_page,
+            next_cursor,
+        } = client
+            .read_api()
+            .get_dynamic_fields(tickets_root, cursor, None)
+            .await?;
+        cursor = next_cursor;
+
+        let tickets_page = data.iter().map(|info| info.object_id).collect();
+        let tickets = client
+            .read_api()
+            .multi_get_object_with_options(
+                tickets_page,
+                SuiObjectDataOptions {
+                    show_content: true,
+                    ..Default::default()
+                },
+            )
+            .await?
+            .into_iter()
+            // ignore tickets that have been deleted between the calls
+            .filter_map(|ticket| {
+                Some(
+                    ticket
+                        .data?
+                        .content?
+                        .try_as_move()
+                        .cloned()?
+                        .fields
+                        .to_json_value(),
+                )
+            });
+
+        for ticket in tickets {
+            let is_payer =
+                ticket["payer"].as_str() == Some(sender.to_string().as_str());
+            let is_being_disputed =
+                ticket["is_being_disputed"].as_bool().unwrap_or(true);
+            let all_count = ticket["all"].as_array().map_or(0, Vec::len);
+            let completed_count =
+                ticket["completed"].as_array().map_or(0, Vec::len);
+            if !is_payer
+                || is_being_disputed
+                || completed_count >= all_count
+                || !timed_out(
+                    &ticket["timeout"],
+                    system_state.epoch,
+                    system_state.epoch_start_timestamp_ms,
+                )?
+            {
+                continue;
+            }
+
+            let ticket_id = ObjectID::from_str(
+                ticket["id"]["id"]
+                    .as_str()
+                    .ok_or_else(|| anyhow!("Ticket has no ID"))?,
+            )?;
+            refundable.push(Refundable {
+                ticket_id,
+                expected_refund: partial_settlement(&ticket)?.1,
+            });
+        }
+
+        if !has_next_page {
+            break;
+        }
+    }
+
+    Ok(refundable)
+}
@@ -0,0 +1,84 @@
+//! Which build of the CLI is running, and whether a downloaded binary is
+//! the one a release key signed.
+//!
+//! The signed message is `DOMAIN || H(binary)` where `H` is Blake2b-256,
+//! wrapped as a personal message like the stack requests.
+
+use std::path::Path;
+
+use fastcrypto::{
+    hash::{Blake2b256, HashFunction},
+    traits::EncodeDecodeBase64,
+};
+use shared_crypto::intent::{Intent, IntentMessage, PersonalMessage};
+use sui_keys::keystore::AccountKeystore;
+use sui_sdk::types::{
+    base_types::SuiAddress,
+    crypto::{Signature, SuiSignature},
+};
+
+use crate::prelude::*;
+
+/// Prevents a release signature to be reused in some other context.
+const DOMAIN: &[u8] = b"atoma-cli-release";
+
+/// Set by the build script.
+const GIT_COMMIT: &str = env!("ATOMA_CLI_GIT_COMMIT");
+const BUILD_TIMESTAMP: &str = env!("ATOMA_CLI_BUILD_TIMESTAMP");
+const TARGET: &str = env!("ATOMA_CLI_TARGET");
+const FEATURES: &str = env!("ATOMA_CLI_FEATURES");
+
+pub(crate) fn version(verbose: bool) {
+    println!("{} {}", env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION"));
+    if !verbose {
+        return;
+    }
+
+    println!("commit: {GIT_COMMIT}");
+    println!("build timestamp: {BUILD_TIMESTAMP}");
+    println!("target: {TARGET}");
+    println!(
+        "features: {}",
+        if FEATURES.is_empty() { "-" } else { FEATURES }
+    );
+}
+
+/// Signs the binary with the key of the active address.
+pub(crate) fn sign(context: &mut Context, binary: &Path) -> Result<String> {
+    context.conf.role.ensure_can_sign()?;
+    let signer = context.wallet.active_address()?;
+    let signature = context.wallet.config.keystore.sign_secure(
+        &signer,
+        &message(binary)?,
+        Intent::personal_message(),
+    )?;
+    info!("Signed {binary:?} with {signer}");
+
+    Ok(signature.encode_base64())
+}
+
+/// Fails unless the signature was produced by `expected_signer` for exactly
+/// this binary.
+pub(crate) fn verify(
+    binary: &Path,
+    signature: &str,
+    expected_signer: SuiAddress,
+) -> Result<()> {
+    let signature = Signature::decode_base64(signature)
+        .map_err(|err| anyhow!("Cannot decode signature: {err}"))?;
+    let message =
+        IntentMessage::new(Intent::personal_message(), message(binary)?);
+    signature
+        .verify_secure(&message, expected_signer, signature.scheme())
+        .map_err(|err| {
+            anyhow!("{binary:?} was not signed by {expected_signer}: {err}")
+        })
+}
+
+fn message(binary: &Path) -> Result<PersonalMessage> {
+    let mut message = DOMAIN.to_vec();
+    message
+        .extend_from_slice(Blake2b256::digest(std::fs::read(binary)?).as_ref());
+
+    Ok(PersonalMessage { message })
+}
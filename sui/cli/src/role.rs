@@ -0,0 +1,52 @@
+//! What the CLI may do with the wallet.
+//!
+//! Operations teams hand out credentials that can inspect the network but
+//! not sign, and keep the commands that require the manager badge, which
+//! are under the `admin` namespace, to the people who run the protocol.
+
+use clap::ValueEnum;
+
+use crate::prelude::*;
+
+pub(crate) const ROLE: &str = "ROLE";
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub(crate) enum Role {
+    /// Everything, including the `admin` commands.
+    #[default]
+    Admin,
+    /// Signs transactions and messages, except for the `admin` commands.
+    Operator,
+    /// Only queries, refuses to sign anything.
+    ReadOnly,
+}
+
+impl Role {
+    /// Loaded from the `ROLE` env var, eg. `ROLE=read-only`.
+    pub(crate) fn from_env() -> Self {
+        std::env::var(ROLE)
+            .ok()
+            .filter(|s| !s.is_empty())
+            .map(|s| {
+                <Self as ValueEnum>::from_str(&s, true)
+                    .unwrap_or_else(|err| panic!("Invalid {ROLE}: {err}"))
+            })
+            .unwrap_or_default()
+    }
+
+    pub(crate) fn ensure_can_sign(self) -> Result<()> {
+        if self == Self::ReadOnly {
+            anyhow::bail!("Refusing to sign in read-only mode");
+        }
+        Ok(())
+    }
+
+    pub(crate) fn ensure_can_administer(self) -> Result<()> {
+        if self != Self::Admin {
+            anyhow::bail!(
+                "The admin commands require the admin role, set {ROLE}=admin"
+            );
+        }
+        Ok(())
+    }
+}
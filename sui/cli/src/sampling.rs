@@ -0,0 +1,181 @@
+//! Mirrors the pure parts of the on-chain sampling so that we can predict
+//! which echelon and nodes given random numbers select.
+//!
+//! Must match `gate::pick_weighted_index` and `db::unique_sample_node_index`.
+//! The Move tests pin these vectors:
+//! - weights `[10, 1, 35]` with randoms `0, 9, 10, 11, 45, 46, 2^256 - 1`
+//!   pick `0, 0, 1, 2, 2, 0, 0`
+//! - 10 nodes, 3 to sample: chunk 0 with random 5 is index 8, chunk 1 with
+//!   random 5 is index 3, chunk 2 with randoms 2 and 3 are indices 0 and 2
+//! - 2 nodes, 3 to sample: chunks 0 and 1 are indices 1 and 0
+//! - performance 1000 with latencies 500 ms and unmeasured weighs 2_000_000
+//!   and 1_000_000, performance 1 with latency 2_000_000 ms weighs 1
+//!
+//! The tests pin the same vectors.
+//!
+//! The contract weighs in u256, the weights here are u128 and None where
+//! they wouldn't fit, rather than wrapping around or panicking.
+
+use anyhow::{anyhow, Result};
+
+/// Must match `gate::priority_weighted_performance`.
+/// Only urgent priority can overflow, with a relative performance above
+/// about 2^42.7.
+pub fn priority_weighted_performance(
+    relative_performance: u64,
+    priority: u8,
+) -> Option<u128> {
+    (0..priority).try_fold(relative_performance as u128, |weighted, _| {
+        weighted.checked_mul(relative_performance as u128)
+    })
+}
+
+/// Must match `gate::UnmeasuredLatencyP50Ms`.
+const UNMEASURED_LATENCY_P50_MS: u64 = 1_000;
+/// Must match `gate::LatencyWeightScaleMs`.
+const LATENCY_WEIGHT_SCALE_MS: u128 = 1_000_000;
+
+/// Must match `gate::latency_weighted_performance`.
+pub fn latency_weighted_performance(
+    performance: u128,
+    latency_p50_ms: Option<u64>,
+) -> Option<u128> {
+    let latency_p50_ms =
+        latency_p50_ms.unwrap_or(UNMEASURED_LATENCY_P50_MS).max(1);
+    let scaled = performance.checked_mul(LATENCY_WEIGHT_SCALE_MS)?;
+    Some((scaled / latency_p50_ms as u128).max(1))
+}
+
+/// Index into `weights` picked by the big-endian u256 `random`.
+///
+/// The sum of the weights must fit into 120 bits, which is plenty for
+/// relative performances times node counts.
+pub fn pick_weighted_index(
+    weights: &[u128],
+    random: &[u8; 32],
+) -> Result<usize> {
+    let total_weight = weights
+        .iter()
+        .try_fold(0u128, |total, weight| total.checked_add(*weight))
+        .filter(|total| *total > 0 && *total < 1 << 120)
+        .ok_or_else(|| anyhow!("Total weight must be in (0, 2^120)"))?;
+
+    // random % total, a byte at a time so that it fits into u128
+    let remainder = random.iter().fold(0u128, |remainder, byte| {
+        (remainder * 256 + *byte as u128) % total_weight
+    });
+    let goal = 1 + remainder;
+
+    let mut remaining_weight = total_weight;
+    for (index, weight) in weights.iter().enumerate().rev() {
+        remaining_weight -= weight;
+        if goal > remaining_weight {
+            return Ok(index);
+        }
+    }
+
+    unreachable!("The last weight brings the remaining weight to zero")
+}
+
+/// Index into the echelon's nodes, before any are removed, picked in the
+/// given chunk by `random`.
+pub fn unique_sample_node_index(
+    total_echelon_nodes: u64,
+    how_many_nodes_to_sample: u64,
+    chunk: u64,
+    random: u64,
+) -> u64 {
+    assert!(how_many_nodes_to_sample > 0, "Cannot sample zero nodes");
+    let base_nodes_per_chunk = total_echelon_nodes / how_many_nodes_to_sample;
+    let chunks_with_extra_node = total_echelon_nodes % how_many_nodes_to_sample;
+
+    let (from_node_index, nodes_to_pick_from) =
+        if chunk < chunks_with_extra_node {
+            (chunk * (base_nodes_per_chunk + 1), base_nodes_per_chunk + 1)
+        } else {
+            (
+                chunk * base_nodes_per_chunk + chunks_with_extra_node,
+                base_nodes_per_chunk,
+            )
+        };
+    let node_index = from_node_index + random % nodes_to_pick_from;
+
+    // sampled from the end of the echelon
+    total_echelon_nodes - 1 - node_index
+}
+
+/// How many nodes `db::sample_unique_nodes` draws a random number for.
+pub fn chunks_count(
+    total_echelon_nodes: u64,
+    how_many_nodes_to_sample: u64,
+) -> u64 {
+    total_echelon_nodes.min(how_many_nodes_to_sample)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Big-endian u256.
+    fn random(n: u64) -> [u8; 32] {
+        let mut random = [0; 32];
+        random[24..].copy_from_slice(&n.to_be_bytes());
+        random
+    }
+
+    #[test]
+    fn weighted_index_vectors() {
+        let weights = [10, 1, 35];
+        for (n, index) in [(0, 0), (9, 0), (10, 1), (11, 2), (45, 2), (46, 0)] {
+            assert_eq!(
+                pick_weighted_index(&weights, &random(n)).unwrap(),
+                index,
+                "{n}"
+            );
+        }
+        // 2^256 - 1
+        assert_eq!(pick_weighted_index(&weights, &[0xff; 32]).unwrap(), 0);
+    }
+
+    #[test]
+    fn weights_must_sum_to_less_than_2_pow_120() {
+        assert!(pick_weighted_index(&[0, 0], &random(0)).is_err());
+        assert!(pick_weighted_index(&[1 << 119, 1 << 119], &random(0)).is_err());
+    }
+
+    #[test]
+    fn unique_sample_vectors() {
+        assert_eq!(chunks_count(10, 3), 3);
+        assert_eq!(unique_sample_node_index(10, 3, 0, 5), 8);
+        assert_eq!(unique_sample_node_index(10, 3, 1, 5), 3);
+        assert_eq!(unique_sample_node_index(10, 3, 2, 2), 0);
+        assert_eq!(unique_sample_node_index(10, 3, 2, 3), 2);
+
+        assert_eq!(chunks_count(2, 3), 2);
+        assert_eq!(unique_sample_node_index(2, 3, 0, 7), 1);
+        assert_eq!(unique_sample_node_index(2, 3, 1, 7), 0);
+    }
+
+    #[test]
+    fn latency_vectors() {
+        assert_eq!(
+            latency_weighted_performance(1000, Some(500)),
+            Some(2_000_000)
+        );
+        assert_eq!(latency_weighted_performance(1000, None), Some(1_000_000));
+        assert_eq!(latency_weighted_performance(1, Some(2_000_000)), Some(1));
+        assert_eq!(latency_weighted_performance(u128::MAX, Some(1)), None);
+    }
+
+    #[test]
+    fn priority_weights_overflow_to_none() {
+        assert_eq!(priority_weighted_performance(1000, 0), Some(1000));
+        assert_eq!(priority_weighted_performance(1000, 2), Some(1_000_000_000));
+        assert_eq!(
+            priority_weighted_performance(u64::MAX, 1),
+            Some(u64::MAX as u128 * u64::MAX as u128)
+        );
+        // the contract's u256 holds this
+        assert_eq!(priority_weighted_performance(1 << 43, 2), None);
+    }
+}
@@ -0,0 +1,222 @@
+//! Secrets that daemons get from the secret manager of their deployment, eg.
+//! on k8s or Nomad, rather than from files next to the wallet.
+//!
+//! The env profile, ie. `.env.{active_env}`, refers to a secret with one of:
+//! - `env:NAME` reads the env var `NAME`, eg. one that k8s injected
+//! - `file:PATH` reads a file, eg. a mounted k8s secret
+//! - `exec:COMMAND` runs the command with `sh -c` and reads its output, eg.
+//!   `exec:vault kv get -field=key secret/atoma`
+//! - `http:URL` GETs the URL with the token of `SECRETS_HTTP_TOKEN`, itself a
+//!   reference of one of the kinds above, as a bearer token.
+//!   A fragment with a JSON pointer picks a field of a JSON response, eg.
+//!   `http:https://vault:8200/v1/secret/data/atoma#/data/data/key`.
+//!   The URL must be `https` unless its host is loopback, since the token
+//!   would otherwise be sent in the clear.
+//!
+//! These secrets can be referred to:
+//! - `WALLET_KEY_SECRET`, the private key that signs instead of the active
+//!   address of the keystore, as `suiprivkey...` or base64 flagged bytes
+//! - `WATCHDOG_ALERT_URL_SECRET`, the webhook URL that alerts are sent to,
+//!   which usually embeds its credentials
+//!
+//! Resolved values are never logged.
+
+use std::{net::IpAddr, path::PathBuf, process::Command};
+
+use sui_keys::keystore::{AccountKeystore, InMemKeystore, Keystore};
+use sui_sdk::types::{
+    base_types::SuiAddress,
+    crypto::{EncodeDecodeBase64, SuiKeyPair},
+};
+
+use crate::prelude::*;
+
+pub(crate) const WALLET_KEY_SECRET: &str = "WALLET_KEY_SECRET";
+#[cfg(feature = "daemon")]
+pub(crate) const WATCHDOG_ALERT_URL_SECRET: &str = "WATCHDOG_ALERT_URL_SECRET";
+pub(crate) const SECRETS_HTTP_TOKEN: &str = "SECRETS_HTTP_TOKEN";
+/// Bech32 encoded private keys, as exported by `sui keytool`.
+const BECH32_KEY_PREFIX: &str = "suiprivkey";
+
+/// Where a secret is read from.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) enum SecretSource {
+    Env(String),
+    File(PathBuf),
+    Exec(String),
+    /// The URL and the JSON pointer of its fragment, if any.
+    Http(String, Option<String>),
+}
+
+/// Eg. `file:/var/run/secrets/atoma/wallet-key`.
+impl FromStr for SecretSource {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let (kind, reference) = s.split_once(':').ok_or_else(|| {
+            anyhow!("{s} is not a KIND:REFERENCE secret, eg. env:NAME")
+        })?;
+        if reference.is_empty() {
+            anyhow::bail!("The {kind} secret has no reference");
+        }
+        Ok(match kind {
+            "env" => Self::Env(reference.to_string()),
+            "file" => Self::File(PathBuf::from(reference)),
+            "exec" => Self::Exec(reference.to_string()),
+            "http" => {
+                let (url, pointer) = match reference.split_once('#') {
+                    Some((url, pointer)) => (url, Some(pointer.to_string())),
+                    None => (reference, None),
+                };
+                ensure_https_unless_loopback(url)?;
+                Self::Http(url.to_string(), pointer)
+            }
+            _ => anyhow::bail!(
+                "Unknown secret kind {kind}, expected env, file, exec or http"
+            ),
+        })
+    }
+}
+
+impl SecretSource {
+    /// The value with surrounding whitespace removed, eg. the trailing new
+    /// line of files and command outputs.
+    pub(crate) async fn resolve(&self) -> Result<String> {
+        let value = match self {
+            Self::Http(url, pointer) => http(url, pointer.as_deref()).await?,
+            _ => self.read_local()?,
+        };
+        let value = value.trim();
+        if value.is_empty() {
+            anyhow::bail!("The secret of {self:?} is empty");
+        }
+
+        Ok(value.to_string())
+    }
+
+    /// Secrets that are read without a request.
+    fn read_local(&self) -> Result<String> {
+        Ok(match self {
+            Self::Env(name) => std::env::var(name)
+                .map_err(|err| anyhow!("Cannot read env var {name}: {err}"))?,
+            Self::File(path) => std::fs::read_to_string(path)
+                .map_err(|err| anyhow!("Cannot read {path:?}: {err}"))?,
+            Self::Exec(command) => exec(command)?,
+            Self::Http(url, _) => {
+                anyhow::bail!("{url} would have to be requested")
+            }
+        })
+    }
+}
+
+/// Resolves the secret that the env var refers to, if set.
+pub(crate) async fn from_env(name: &str) -> Result<Option<String>> {
+    let Some(reference) = std::env::var(name).ok().filter(|s| !s.is_empty())
+    else {
+        return Ok(None);
+    };
+    let source = SecretSource::from_str(&reference)
+        .map_err(|err| anyhow!("Invalid {name}: {err}"))?;
+    let value = source
+        .resolve()
+        .await
+        .map_err(|err| anyhow!("Cannot resolve {name}: {err}"))?;
+    debug!("Resolved {name} from {source:?}");
+
+    Ok(Some(value))
+}
+
+/// Replaces the keystore of the wallet with one that only holds the key, so
+/// that nothing is written to disk, and makes its address the active one.
+pub(crate) fn use_wallet_key(
+    wallet: &mut WalletContext,
+    key: &str,
+) -> Result<()> {
+    let keypair = if key.starts_with(BECH32_KEY_PREFIX) {
+        SuiKeyPair::decode(key)
+    } else {
+        SuiKeyPair::decode_base64(key)
+    }
+    .map_err(|_| anyhow!("{WALLET_KEY_SECRET} is not a private key"))?;
+    let address = SuiAddress::from(&keypair.public());
+
+    let mut keystore = InMemKeystore::default();
+    keystore.add_key(None, keypair)?;
+    wallet.config.keystore = Keystore::InMem(keystore);
+    wallet.config.active_address = Some(address);
+
+    Ok(())
+}
+
+fn exec(command: &str) -> Result<String> {
+    let output = Command::new("sh").arg("-c").arg(command).output()?;
+    if !output.status.success() {
+        // the command's own output is left out since it could be the secret
+        anyhow::bail!("`{command}` exited with {}", output.status);
+    }
+
+    Ok(String::from_utf8(output.stdout)?)
+}
+
+async fn http(url: &str, pointer: Option<&str>) -> Result<String> {
+    let loopback = ensure_https_unless_loopback(url)?;
+    // redirects must not take the token off https either
+    let mut request = reqwest::Client::builder()
+        .https_only(!loopback)
+        .build()?
+        .get(url);
+    if let Some(token) = token()? {
+        request = request.bearer_auth(token);
+    }
+    let body = request.send().await?.error_for_status()?.text().await?;
+
+    let Some(pointer) = pointer else {
+        return Ok(body);
+    };
+    let json: serde_json::Value = serde_json::from_str(&body)
+        .map_err(|err| anyhow!("{url} did not return JSON: {err}"))?;
+    match json.pointer(pointer) {
+        Some(serde_json::Value::String(value)) => Ok(value.clone()),
+        Some(_) => anyhow::bail!("{pointer} of {url} is not a string"),
+        None => anyhow::bail!("{url} has no {pointer}"),
+    }
+}
+
+/// Fails if the URL is not `https` and its host is not loopback, otherwise
+/// returns whether it is loopback.
+fn ensure_https_unless_loopback(url: &str) -> Result<bool> {
+    let parsed = reqwest::Url::parse(url)
+        .map_err(|err| anyhow!("{url} is not a URL: {err}"))?;
+    let host = parsed
+        .host_str()
+        .ok_or_else(|| anyhow!("{url} has no host"))?;
+    // IPv6 hosts are bracketed
+    let loopback = host == "localhost"
+        || host
+            .trim_start_matches('[')
+            .trim_end_matches(']')
+            .parse::<IpAddr>()
+            .is_ok_and(|ip| ip.is_loopback());
+    if parsed.scheme() != "https" && !loopback {
+        anyhow::bail!(
+            "{url} must be https, the token would be sent in the clear"
+        );
+    }
+
+    Ok(loopback)
+}
+
+/// The token can't be fetched over HTTP itself.
+fn token() -> Result<Option<String>> {
+    let Some(reference) = std::env::var(SECRETS_HTTP_TOKEN)
+        .ok()
+        .filter(|s| !s.is_empty())
+    else {
+        return Ok(None);
+    };
+    let token = SecretSource::from_str(&reference)
+        .and_then(|source| source.read_local())
+        .map_err(|err| anyhow!("Invalid {SECRETS_HTTP_TOKEN}: {err}"))?;
+
+    Ok(Some(token.trim().to_string()))
+}
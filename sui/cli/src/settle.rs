@@ -1,7 +1,16 @@
+mod commitment;
+mod gc;
 mod list_tickets;
+mod settle_partially;
+mod stream_commitment;
 mod submit_commitment;
 mod try_to_settle;
 
+pub(crate) use gc::command as gc;
 pub(crate) use list_tickets::command as list_tickets;
+pub(crate) use settle_partially::{
+    command as settle_partially, partial_settlement, timed_out,
+};
+pub(crate) use stream_commitment::command as stream_commitment;
 pub(crate) use submit_commitment::command as submit_commitment;
 pub(crate) use try_to_settle::command as try_to_settle;
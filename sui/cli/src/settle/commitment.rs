@@ -0,0 +1,46 @@
+//! How nodes commit to the output of a prompt.
+//!
+//! See the `SettlementTicket` docs in the settlement module for the on-chain
+//! side.
+
+use fastcrypto::hash::{Blake2b256, HashFunction};
+
+/// Must match `settlement::empty_stream_commitment`.
+pub(crate) const EMPTY_STREAM_COMMITMENT: [u8; 32] = [0; 32];
+
+/// Must match `settlement::roll_stream_commitment`.
+pub(crate) fn roll_stream_commitment(
+    previous: &[u8; 32],
+    segment: &[u8],
+) -> [u8; 32] {
+    Blake2b256::digest([previous.as_slice(), segment].concat()).digest
+}
+
+/// Rolls all the segments into a single commitment, ie. what the node
+/// commits to in place of the output if the output was streamed.
+pub(crate) fn stream_commitment<'a>(
+    segments: impl IntoIterator<Item = &'a [u8]>,
+) -> [u8; 32] {
+    segments
+        .into_iter()
+        .fold(EMPTY_STREAM_COMMITMENT, |commitment, segment| {
+            roll_stream_commitment(&commitment, segment)
+        })
+}
+
+/// Returns the concatenated 32 byte leaves `H(output, n)` for each sampled
+/// node `n`.
+/// The merkle root is the hash of the returned buffer.
+pub(crate) fn merkle_leaves(
+    committed_output: &[u8],
+    sampled_nodes_count: usize,
+) -> Vec<u8> {
+    (0..sampled_nodes_count)
+        .flat_map(|n| {
+            let n = n.to_le_bytes();
+            Blake2b256::digest([committed_output, n.as_slice()].concat())
+                .digest
+                .into_iter()
+        })
+        .collect()
+}
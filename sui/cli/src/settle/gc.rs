@@ -0,0 +1,153 @@
+use std::time::Duration;
+
+use sui_sdk::{
+    rpc_types::{SuiExecutionStatus, SuiTransactionBlockEffectsAPI},
+    types::{base_types::ObjectID, transaction::TransactionDataAPI},
+};
+
+use crate::{
+    db::{dynamic_field_values, parse_u64},
+    prelude::*,
+    DB_MODULE_NAME,
+};
+
+const ENDPOINT_NAME: &str = "collect_claimed_stacks";
+/// SUI has 9 decimals.
+const MIST_PER_SUI: u128 = 1_000_000_000;
+
+/// Runs a round every `interval` until stopped, or a single one without it.
+/// A round that fails, eg. because another collector was faster, doesn't
+/// stop the daemon.
+pub(crate) async fn command(
+    context: &mut Context,
+    sui_price: u64,
+    max_stacks: usize,
+    interval: Option<Duration>,
+) -> Result<()> {
+    loop {
+        match round(context, sui_price, max_stacks).await {
+            Err(err) if interval.is_some() => {
+                error!("Failed to collect stacks: {err}")
+            }
+            result => result?,
+        }
+
+        let Some(interval) = interval else {
+            return Ok(());
+        };
+        tokio::time::sleep(interval).await;
+    }
+}
+
+/// 1. Find the stacks that were claimed more than `min_age_epochs` epochs
+///    ago.
+/// 2. Dry run the collection of at most `max_stacks` of them to learn the
+///    gas cost.
+/// 3. Collect them if the bounty is worth more than the gas, with one SUI
+///    worth `sui_price` TOMA.
+async fn round(
+    context: &mut Context,
+    sui_price: u64,
+    max_stacks: usize,
+) -> Result<()> {
+    // 1.
+    let db_fields = context.load_atoma_db_fields().await?;
+    let stack_gc = context.load_atoma_db_field("StackGcKey").await?;
+    let claimed_stack_epochs = ObjectID::from_str(
+        stack_gc["claimed_stack_epochs"]["id"]["id"]
+            .as_str()
+            .ok_or_else(|| anyhow!("No claimed_stack_epochs field found"))?,
+    )?;
+    let min_age_epochs = parse_u64(&stack_gc["min_age_epochs"])?;
+    let bounty_per_stack = parse_u64(&stack_gc["bounty"])?;
+    let communal_treasury = parse_u64(&db_fields["communal_treasury"])?;
+
+    let client = context.get_client().await?;
+    let epoch = client
+        .governance_api()
+        .get_latest_sui_system_state()
+        .await?
+        .epoch;
+    let mut collectable = vec![];
+    for (key, claimed) in
+        dynamic_field_values(&client, claimed_stack_epochs).await?
+    {
+        if epoch > parse_u64(&claimed["value"])? + min_age_epochs {
+            collectable.push(parse_u64(&key["inner"])?);
+        }
+    }
+    if collectable.is_empty() {
+        println!("No stacks to collect");
+        return Ok(());
+    }
+    collectable.sort_unstable();
+    collectable.truncate(max_stacks);
+
+    // 2.
+    let active_address = context.wallet.active_address()?;
+    let atoma_package = context.unwrap_atoma_package_id();
+    let atoma_db = context.get_or_load_atoma_db().await?;
+    let tx = client
+        .transaction_builder()
+        .move_call(
+            active_address,
+            atoma_package,
+            DB_MODULE_NAME,
+            ENDPOINT_NAME,
+            vec![],
+            vec![
+                SuiJsonValue::from_object_id(atoma_db),
+                SuiJsonValue::new(
+                    collectable
+                        .iter()
+                        .map(ToString::to_string)
+                        .collect::<Vec<_>>()
+                        .into(),
+                )?,
+            ],
+            None,
+            context.gas_budget(),
+            None,
+        )
+        .await?;
+    let inspection = client
+        .read_api()
+        .dev_inspect_transaction_block(
+            active_address,
+            tx.kind().clone(),
+            None,
+            None,
+            None,
+        )
+        .await?;
+    if let SuiExecutionStatus::Failure { error } = inspection.effects.status() {
+        anyhow::bail!("Collecting the stacks would fail: {error}");
+    }
+    let gas_mist =
+        inspection.effects.gas_cost_summary().net_gas_usage().max(0) as u128;
+
+    // 3.
+    let bounty = (bounty_per_stack as u128 * collectable.len() as u128)
+        .min(communal_treasury as u128);
+    let gas_cost = gas_mist * sui_price as u128 / MIST_PER_SUI;
+    if bounty <= gas_cost {
+        println!(
+            "{} stack(s) to collect for {}, not worth the gas of {}",
+            collectable.len(),
+            context.toma(bounty),
+            context.toma(gas_cost),
+        );
+        return Ok(());
+    }
+
+    let tx = context.sign_transaction(&tx)?;
+    let resp = context.execute_transaction_must_succeed(tx).await?;
+    println!(
+        "Collected {} stack(s) in {}, the bounty exceeds the gas by {}",
+        collectable.len(),
+        resp.digest,
+        context.toma(bounty - gas_cost),
+    );
+
+    Ok(())
+}
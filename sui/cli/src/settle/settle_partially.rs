@@ -0,0 +1,204 @@
+use serde_json::Value;
+
+use crate::{prelude::*, wait_for_user_confirm, SETTLEMENT_MODULE_NAME};
+
+/// Cancelling a ticket that some nodes committed to settles it partially.
+const ENDPOINT_NAME: &str = "cancel_prompt";
+/// Must match `MaxTicketTimeouts` in the settlement module.
+const MAX_TICKET_TIMEOUTS: u64 = 3;
+
+/// 1. Print which of the sampled nodes committed to the ticket.
+/// 2. Check that the ticket can be settled partially and print what each
+///    committed node would be paid and how much would be refunded.
+/// 3. Unless `yes`, ask for confirmation and settle.
+///
+/// Returns `None` if the user didn't confirm.
+pub(crate) async fn command(
+    context: &mut Context,
+    ticket_id: &str,
+    yes: bool,
+) -> Result<Option<TransactionDigest>> {
+    let ticket_id = FromStr::from_str(ticket_id)?;
+    let (package, ticket) =
+        context.ticket_package_and_fields(ticket_id).await?;
+    let active_address = context.wallet.active_address()?;
+
+    // 1.
+    let node_ids = |field: &str| -> Result<Vec<u64>> {
+        ticket[field]
+            .as_array()
+            .ok_or_else(|| anyhow!("Ticket has no {field}"))?
+            .iter()
+            .map(|node| u64_field(&node["inner"], "node ID"))
+            .collect()
+    };
+    let all = node_ids("all")?;
+    let completed = node_ids("completed")?;
+    let bitmap: String = all
+        .iter()
+        .map(|node_id| {
+            if completed.contains(node_id) {
+                '1'
+            } else {
+                '0'
+            }
+        })
+        .collect();
+    println!("Completion bitmap: {bitmap}");
+    for node_id in &all {
+        let status = if completed.contains(node_id) {
+            "committed"
+        } else {
+            "missing"
+        };
+        println!("  node {node_id}: {status}");
+    }
+
+    // 2.
+    if ticket["payer"].as_str() != Some(active_address.to_string().as_str()) {
+        anyhow::bail!(
+            "Only the payer {} can settle the ticket",
+            ticket["payer"]
+        );
+    }
+    if ticket["is_being_disputed"].as_bool() == Some(true) {
+        anyhow::bail!("Ticket is being disputed, an oracle must settle it");
+    }
+    if completed.is_empty() {
+        anyhow::bail!("No node committed, cancel the ticket instead");
+    }
+    if completed.len() == all.len() {
+        anyhow::bail!("All nodes committed, use try-to-settle instead");
+    }
+    if !did_timeout(context, &ticket["timeout"]).await? {
+        anyhow::bail!("Ticket has not timed out yet");
+    }
+
+    let collected_fee =
+        u64_field(&ticket["collected_fee_in_protocol_token"], "collected fee")?;
+    let (fee_per_node, refunded_amount) = partial_settlement(&ticket)?;
+    println!(
+        "{} committed node(s) would be paid {} each, \
+        {} missing node(s) would be slashed and {} of the \
+        collected {} would be refunded",
+        completed.len(),
+        context.toma(fee_per_node),
+        all.len() - completed.len(),
+        context.toma(refunded_amount),
+        context.toma(collected_fee)
+    );
+
+    // 3.
+    if !yes {
+        println!("Settle the ticket? (y/n)");
+        if !wait_for_user_confirm() {
+            return Ok(None);
+        }
+    }
+
+    let atoma_db = context.get_or_load_atoma_db().await?;
+    let tx = context
+        .get_client()
+        .await?
+        .transaction_builder()
+        .move_call(
+            active_address,
+            package,
+            SETTLEMENT_MODULE_NAME,
+            ENDPOINT_NAME,
+            vec![],
+            vec![
+                SuiJsonValue::from_object_id(atoma_db),
+                SuiJsonValue::from_object_id(ticket_id),
+            ],
+            None,
+            context.gas_budget(),
+            None,
+        )
+        .await?;
+
+    let tx = context.sign_transaction(&tx)?;
+    let resp = context.execute_transaction_must_succeed(tx).await?;
+    Ok(Some(resp.digest))
+}
+
+/// What each committed node would be paid and how much would be refunded
+/// to the payer, mirrors `close_timed_out_ticket` in the settlement module.
+pub(crate) fn partial_settlement(ticket: &Value) -> Result<(u64, u64)> {
+    let nodes_count = |field: &str| {
+        ticket[field]
+            .as_array()
+            .map(|nodes| nodes.len() as u64)
+            .ok_or_else(|| anyhow!("Ticket has no {field}"))
+    };
+    let completed_count = nodes_count("completed")?;
+    if completed_count == 0 {
+        return Ok((0, collected_fee(ticket)?));
+    }
+
+    let collected_fee = collected_fee(ticket)?;
+    let exact_fee_per_node =
+        u64_field(&ticket["input_fee_per_token"], "input fee per token")?
+            * u64_field(&ticket["input_tokens_count"], "input tokens count")?
+            + u64_field(
+                &ticket["output_fee_per_token"],
+                "output fee per token",
+            )? * u64_field(
+                &ticket["output_tokens_count"],
+                "output tokens count",
+            )?;
+    let fee_per_node = exact_fee_per_node.min(collected_fee / completed_count);
+    let refunded_amount = collected_fee - fee_per_node * completed_count;
+
+    Ok((fee_per_node, refunded_amount))
+}
+
+fn collected_fee(ticket: &Value) -> Result<u64> {
+    u64_field(&ticket["collected_fee_in_protocol_token"], "collected fee")
+}
+
+async fn did_timeout(context: &Context, timeout: &Value) -> Result<bool> {
+    let system_state = context
+        .get_client()
+        .await?
+        .governance_api()
+        .get_latest_sui_system_state()
+        .await?;
+
+    timed_out(
+        timeout,
+        system_state.epoch,
+        system_state.epoch_start_timestamp_ms,
+    )
+}
+
+/// Must match `did_timeout` in the settlement module.
+pub(crate) fn timed_out(
+    timeout: &Value,
+    epoch: u64,
+    epoch_timestamp_ms: u64,
+) -> Result<bool> {
+    let timed_out_count = u64_field(&timeout["timed_out_count"], "timeout")?;
+    let timeout_ms = u64_field(&timeout["timeout_ms"], "timeout")?;
+    let started_in_epoch = u64_field(&timeout["started_in_epoch"], "timeout")?;
+    let started_at_epoch_timestamp_ms =
+        u64_field(&timeout["started_at_epoch_timestamp_ms"], "timeout")?;
+
+    Ok(if timed_out_count >= MAX_TICKET_TIMEOUTS {
+        true
+    } else if epoch == started_in_epoch {
+        epoch_timestamp_ms.saturating_sub(started_at_epoch_timestamp_ms)
+            > timeout_ms
+    } else if epoch == started_in_epoch + 1 {
+        epoch_timestamp_ms > timeout_ms
+    } else {
+        true
+    })
+}
+
+fn u64_field(value: &Value, name: &str) -> Result<u64> {
+    value
+        .as_str()
+        .and_then(|s| s.parse().ok())
+        .ok_or_else(|| anyhow!("Ticket has no valid {name}: {value}"))
+}
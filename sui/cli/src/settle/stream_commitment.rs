@@ -0,0 +1,37 @@
+use std::path::Path;
+
+use fastcrypto::encoding::{Encoding, Hex};
+
+use super::commitment::{roll_stream_commitment, EMPTY_STREAM_COMMITMENT};
+use crate::prelude::*;
+
+/// Reads the segments of a streamed output and prints the rolling commitment
+/// after each of them.
+///
+/// The last printed commitment is what the node commits to.
+pub(crate) fn command(segments: &Path) -> Result<()> {
+    let segments = read_segments(segments)?;
+
+    let mut commitment = EMPTY_STREAM_COMMITMENT;
+    for (index, segment) in segments.iter().enumerate() {
+        commitment = roll_stream_commitment(&commitment, segment.as_bytes());
+        println!("{index}\t{}", Hex::encode(commitment));
+    }
+
+    Ok(())
+}
+
+/// The segments file is JSONL where each line is a JSON string with one
+/// segment in the order it was streamed.
+pub(crate) fn read_segments(segments: &Path) -> Result<Vec<String>> {
+    std::fs::read_to_string(segments)?
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(index, line)| {
+            serde_json::from_str::<String>(line).map_err(|err| {
+                anyhow!("Line {} is not a JSON string: {err}", index + 1)
+            })
+        })
+        .collect()
+}
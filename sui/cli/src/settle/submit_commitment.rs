@@ -1,17 +1,50 @@
+use std::path::Path;
+
 use fastcrypto::hash::{Blake2b256, HashFunction};
 use sui_sdk::{
     rpc_types::SuiData,
     types::{dynamic_field::DynamicFieldName, SUI_RANDOMNESS_STATE_OBJECT_ID},
 };
 
-use crate::{prelude::*, DynamicFieldNameExt, SETTLEMENT_MODULE_NAME};
+use super::{
+    commitment::{merkle_leaves, stream_commitment},
+    stream_commitment::read_segments,
+};
+use crate::{
+    prelude::*, telemetry::Trace, DynamicFieldNameExt, SETTLEMENT_MODULE_NAME,
+};
 
 const ENDPOINT_NAME: &str = "submit_commitment";
 
+/// If the prompt asked for the output to be streamed, the node must provide
+/// the segments as they were streamed so that it commits to the stream
+/// commitment rather than to the final text.
+///
+/// Traced with the `commitment` and `tx_confirmed` stages.
 pub(crate) async fn command(
     context: &mut Context,
     ticket_id: &str,
-    prompt_output: &str,
+    prompt_output: Option<&str>,
+    segments: Option<&Path>,
+) -> Result<TransactionDigest> {
+    let mut trace = context.trace(ENDPOINT_NAME);
+    trace.set_attribute("atoma.ticket_id", ticket_id);
+    let result =
+        submit(context, &mut trace, ticket_id, prompt_output, segments).await;
+    if let Ok(digest) = &result {
+        trace.set_attribute("sui.tx_digest", digest);
+    }
+    trace.finish(result.as_ref().err()).await;
+
+    result
+}
+
+async fn submit(
+    context: &mut Context,
+    trace: &mut Trace,
+    ticket_id: &str,
+    prompt_output: Option<&str>,
+    segments: Option<&Path>,
 ) -> Result<TransactionDigest> {
     let active_address = context.wallet.active_address()?;
     let (node_badge, node_id) = context.get_or_load_node_badge().await?;
@@ -20,6 +53,46 @@ pub(crate) async fn command(
     let (package, ticket) =
         context.ticket_package_and_fields(ticket_id).await?;
 
+    let params = context
+        .get_client()
+        .await?
+        .read_api()
+        .get_dynamic_field_object(ticket_id, DynamicFieldName::ascii("params"))
+        .await?
+        .data
+        .ok_or_else(|| anyhow!("Ticket params not found"))?
+        .content
+        .unwrap()
+        .try_into_move()
+        .unwrap()
+        .fields
+        .to_json_value();
+    // only text prompts can be streamed
+    let should_stream_output =
+        params["value"]["should_stream_output"].as_bool() == Some(true);
+
+    trace.stage("commitment");
+    let (prompt_output, committed_output) =
+        match (should_stream_output, prompt_output, segments) {
+            (true, _, Some(segments)) => {
+                let segments = read_segments(segments)?;
+                let commitment =
+                    stream_commitment(segments.iter().map(|s| s.as_bytes()));
+                (segments.concat(), commitment.to_vec())
+            }
+            (true, _, None) => anyhow::bail!(
+                "Ticket {ticket_id} is for a streamed output, \
+                provide the streamed segments"
+            ),
+            (false, Some(output), None) => {
+                (output.to_owned(), output.as_bytes().to_vec())
+            }
+            (false, _, _) => anyhow::bail!(
+                "Ticket {ticket_id} is not for a streamed output, \
+                provide the output only"
+            ),
+        };
+
     let all = ticket["all"].as_array().unwrap();
     let chunk_position = all
         .iter()
@@ -34,18 +107,7 @@ pub(crate) async fn command(
     // TODO: use the same implementation as the node (if sampled nodes don't
     // divide the output evenly, the last chunk must be smaller)
 
-    let merkle_leaves: Vec<u8> = (0..sampled_nodes_count)
-        .flat_map(|n| {
-            let n = n.to_le_bytes();
-            Blake2b256::digest({
-                let output = prompt_output.as_bytes();
-                [output, n.as_slice()].concat()
-            })
-            .digest
-            .into_iter()
-        })
-        .collect();
-
+    let merkle_leaves = merkle_leaves(&committed_output, sampled_nodes_count);
     let merkle_root = Blake2b256::digest(&merkle_leaves).digest;
     let chunk_hash =
         merkle_leaves[chunk_position * 32..(chunk_position + 1) * 32].to_vec();
@@ -53,30 +115,12 @@ pub(crate) async fn command(
     // TODO: use tokenizer or whatever implementation will the Atoma node use
 
     let output_tokens_count = prompt_output.len();
-    let input_tokens_count = {
-        let object_content = context
-            .get_client()
-            .await?
-            .read_api()
-            .get_dynamic_field_object(
-                ticket_id,
-                DynamicFieldName::ascii("params"),
-            )
-            .await?
-            .data
-            .ok_or_else(|| anyhow!("Ticket params not found"))?
-            .content
-            .unwrap();
-        let json = object_content
-            .try_into_move()
-            .unwrap()
-            .fields
-            .to_json_value();
-        let prompt_str = json["value"]["prompt"].as_str().unwrap();
-        prompt_str.len()
-    };
+    let input_tokens_count = params["value"]["prompt"].as_str().unwrap().len();
 
+    trace.stage("tx_confirmed");
     let atoma_db = context.get_or_load_atoma_db().await?;
+    context.ensure_randomness_is_live().await?;
+
     let tx = context
         .get_client()
         .await?
@@ -103,7 +147,7 @@ pub(crate) async fn command(
         )
         .await?;
 
-    let tx = context.wallet.sign_transaction(&tx);
-    let resp = context.wallet.execute_transaction_must_succeed(tx).await;
+    let tx = context.sign_transaction(&tx)?;
+    let resp = context.execute_transaction_must_succeed(tx).await?;
     Ok(resp.digest)
 }
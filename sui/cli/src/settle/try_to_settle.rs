@@ -1,4 +1,7 @@
-use sui_sdk::types::SUI_RANDOMNESS_STATE_OBJECT_ID;
+use sui_sdk::{
+    rpc_types::{SuiExecutionStatus, SuiTransactionBlockEffectsAPI},
+    types::SUI_RANDOMNESS_STATE_OBJECT_ID,
+};
 
 use crate::{prelude::*, SETTLEMENT_MODULE_NAME};
 
@@ -13,6 +16,8 @@ pub(crate) async fn command(
     let active_address = context.wallet.active_address()?;
 
     let atoma_db = context.get_or_load_atoma_db().await?;
+    context.ensure_randomness_is_live().await?;
+
     let tx = context
         .get_client()
         .await?
@@ -34,7 +39,13 @@ pub(crate) async fn command(
         )
         .await?;
 
-    let tx = context.wallet.sign_transaction(&tx);
-    let resp = context.wallet.execute_transaction_must_succeed(tx).await;
+    let tx = context.sign_transaction(&tx)?;
+    // may fail so that the daemon can dead-letter the action
+    let resp = context.execute_transaction_may_fail(tx).await?;
+    if let SuiExecutionStatus::Failure { error } =
+        resp.effects.as_ref().unwrap().status()
+    {
+        anyhow::bail!("Transaction {} failed: {error}", resp.digest);
+    }
     Ok(resp.digest)
 }
@@ -0,0 +1,193 @@
+//! Terms that a node signs off-chain and that a user accepts on-chain, ie.
+//! price quotes and stack offers.
+//!
+//! The terms are identified by the Blake2b-256 hash of
+//! `domain || package || db || terms`, all numbers little-endian.
+//! The package is the one that first defined the db, so its ID stays the
+//! same across upgrades, and together with the db ID it keeps signed terms
+//! from being replayed against another deployment.
+//! This must match `db::signed_terms_hash`.
+
+use fastcrypto::hash::{Blake2b256, HashFunction};
+use shared_crypto::intent::{Intent, IntentMessage, PersonalMessage};
+use sui_keys::keystore::{AccountKeystore, Keystore};
+use sui_sdk::types::{
+    base_types::{ObjectID, SuiAddress},
+    crypto::{Signature, SignatureScheme, SuiSignature},
+};
+
+use crate::{db, prelude::*, DB_MODULE_NAME, DB_TYPE_NAME};
+
+/// The contracts that signed terms can be accepted with.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Deployment {
+    pub(crate) package: ObjectID,
+    pub(crate) db: ObjectID,
+}
+
+impl Deployment {
+    /// The deployment that the context is configured with.
+    pub(crate) async fn load(context: &mut Context) -> Result<Self> {
+        let package = context.unwrap_atoma_package_id();
+        let package = db::type_origin(
+            &db::load_package(context, package).await?,
+            DB_MODULE_NAME,
+            DB_TYPE_NAME,
+        )?;
+        let db = context.get_or_load_atoma_db().await?;
+
+        Ok(Self { package, db })
+    }
+
+    /// Fails if the terms were signed for another deployment than the one
+    /// the context is configured with, they would be rejected on-chain.
+    pub(crate) async fn ensure_configured(
+        &self,
+        context: &mut Context,
+    ) -> Result<()> {
+        let configured = Self::load(context).await?;
+        if *self != configured {
+            anyhow::bail!(
+                "Signed for package {} and db {}, but the configured \
+                package originates in {} with db {}",
+                self.package,
+                self.db,
+                configured.package,
+                configured.db
+            );
+        }
+
+        Ok(())
+    }
+}
+
+pub(crate) fn hash(
+    domain: &[u8],
+    deployment: &Deployment,
+    terms: &[u64],
+) -> [u8; 32] {
+    let mut message = domain.to_vec();
+    message.extend_from_slice(deployment.package.as_ref());
+    message.extend_from_slice(deployment.db.as_ref());
+    for term in terms {
+        message.extend_from_slice(&term.to_le_bytes());
+    }
+    Blake2b256::digest(message).digest
+}
+
+/// Signs the hash of the terms as a personal message so that a wallet can
+/// never be tricked into signing a transaction instead.
+///
+/// The contract only verifies Ed25519 signatures.
+pub(crate) fn sign(
+    keystore: &Keystore,
+    signer: SuiAddress,
+    hash: [u8; 32],
+) -> Result<Signature> {
+    let signature = keystore.sign_secure(
+        &signer,
+        &PersonalMessage {
+            message: hash.to_vec(),
+        },
+        Intent::personal_message(),
+    )?;
+    if signature.scheme() != SignatureScheme::ED25519 {
+        anyhow::bail!("Terms must be signed with an Ed25519 key");
+    }
+
+    Ok(signature)
+}
+
+/// Fails if the hash of the terms was not signed by the signer.
+///
+/// It's up to the caller to check that the signer owns the node badge and
+/// that its key is the offer key of the node.
+pub(crate) fn verify(
+    hash: [u8; 32],
+    signer: SuiAddress,
+    signature: &Signature,
+) -> Result<()> {
+    let message = IntentMessage::new(
+        Intent::personal_message(),
+        PersonalMessage {
+            message: hash.to_vec(),
+        },
+    );
+    signature
+        .verify_secure(&message, signer, signature.scheme())
+        .map_err(|err| anyhow!("Invalid signature: {err}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use fastcrypto::{
+        ed25519::{Ed25519KeyPair, Ed25519PrivateKey},
+        encoding::{Encoding, Hex},
+        traits::ToFromBytes,
+    };
+    use sui_keys::keystore::InMemKeystore;
+    use sui_sdk::types::crypto::SuiKeyPair;
+
+    use super::*;
+
+    /// The vectors of `test_offer_hash_vector` and `OFFER_SIGNATURE` in the
+    /// Move tests, which run with the package at `0x0`.
+    const OFFER_DOMAIN: &[u8] = b"atoma-offer";
+    const OFFER_TERMS: [u64; 6] = [1, 1, 1000, 100, 10_000, 7];
+    const OFFER_HASH: &str =
+        "c4c058bd32062f86b8963d144fe419552bd0310256d4e4edfbc180b87403d221";
+    const OFFER_PUBLIC_KEY: &str =
+        "ea4a6c63e29c520abef5507b132ec5f9954776aebebe7b92421eea691446d22c";
+    const OFFER_SIGNATURE: &str = "fc10b389f416dfc5ccf23dc0b98d2041035400a927a3ed237844a74296f746cadddd16c496994129d5aca16a35b55f6730584fe4a7b6fddb818137f151e51d06";
+    const TEST_DB_ID: &str =
+        "0x034401905bebdf8c04f3cd5f04f442a39372c8dc321c29edfb4f9cb30b23ab96";
+
+    fn deployment() -> Deployment {
+        Deployment {
+            package: ObjectID::ZERO,
+            db: ObjectID::from_hex_literal(TEST_DB_ID).unwrap(),
+        }
+    }
+
+    fn offer_hash() -> [u8; 32] {
+        hash(OFFER_DOMAIN, &deployment(), &OFFER_TERMS)
+    }
+
+    /// The key of the Move tests, derived from the seed `[7; 32]`.
+    fn offer_keystore() -> (Keystore, SuiAddress) {
+        let private_key = Ed25519PrivateKey::from_bytes(&[7; 32]).unwrap();
+        let keypair = SuiKeyPair::Ed25519(Ed25519KeyPair::from(private_key));
+        let signer = SuiAddress::from(&keypair.public());
+        let mut keystore = InMemKeystore::default();
+        keystore.add_key(None, keypair).unwrap();
+
+        (Keystore::InMem(keystore), signer)
+    }
+
+    #[test]
+    fn offer_hash_matches_move_vector() {
+        assert_eq!(Hex::encode(offer_hash()), OFFER_HASH);
+    }
+
+    #[test]
+    fn offer_signature_matches_move_vector() {
+        let (keystore, signer) = offer_keystore();
+        let signature = sign(&keystore, signer, offer_hash()).unwrap();
+
+        assert_eq!(Hex::encode(signature.signature_bytes()), OFFER_SIGNATURE);
+        assert_eq!(Hex::encode(signature.public_key_bytes()), OFFER_PUBLIC_KEY);
+        verify(offer_hash(), signer, &signature).unwrap();
+    }
+
+    #[test]
+    fn signature_of_other_terms_is_rejected() {
+        let (keystore, signer) = offer_keystore();
+        let signature = sign(&keystore, signer, offer_hash()).unwrap();
+
+        let mut terms = OFFER_TERMS;
+        terms[5] += 1;
+        let other_hash = hash(OFFER_DOMAIN, &deployment(), &terms);
+        assert!(verify(other_hash, signer, &signature).is_err());
+        assert!(verify(offer_hash(), SuiAddress::ZERO, &signature).is_err());
+    }
+}
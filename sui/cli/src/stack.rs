@@ -0,0 +1,21 @@
+//! Commands related to stacks, ie. compute units paid up-front by users.
+//!
+//! Since the payment happens on-chain before any inference, the requests
+//! themselves are sent off-chain and must be signed by the stack owner.
+
+mod close_early;
+mod reconcile;
+mod request_signature;
+mod sign_request;
+mod usage;
+mod verify_request;
+
+pub(crate) use close_early::{
+    accept as accept_close_early, propose as propose_close_early,
+};
+pub(crate) use reconcile::command as reconcile;
+pub(crate) use sign_request::command as sign_request;
+pub(crate) use usage::{
+    command as usage, load_settlement_ticket, settled_compute_units,
+};
+pub(crate) use verify_request::{command as verify_request, load_stack};
@@ -0,0 +1,104 @@
+//! Cooperative termination of a stack before it's exhausted, eg. when the
+//! model is deprecated or the node is leaving the network.
+//!
+//! Either the owner or the node proposes the number of compute units that
+//! were consumed, and the stack is settled and the rest refunded once the
+//! other party accepts the same number.
+
+use crate::{prelude::*, DB_MODULE_NAME};
+
+const PROPOSE_ENDPOINT_NAME: &str = "propose_stack_close";
+const PROPOSE_AS_NODE_ENDPOINT_NAME: &str = "propose_stack_close_as_node";
+const ACCEPT_ENDPOINT_NAME: &str = "accept_stack_close";
+const ACCEPT_AS_NODE_ENDPOINT_NAME: &str = "accept_stack_close_as_node";
+
+/// Replaces any pending proposal, eg. to counter the other party's.
+pub(crate) async fn propose(
+    context: &mut Context,
+    stack_small_id: u64,
+    num_consumed_compute_units: u64,
+    as_node: bool,
+) -> Result<TransactionDigest> {
+    let endpoint = if as_node {
+        PROPOSE_AS_NODE_ENDPOINT_NAME
+    } else {
+        PROPOSE_ENDPOINT_NAME
+    };
+    call(
+        context,
+        endpoint,
+        stack_small_id,
+        num_consumed_compute_units,
+        as_node,
+    )
+    .await
+}
+
+/// The number of compute units must match the other party's proposal.
+pub(crate) async fn accept(
+    context: &mut Context,
+    stack_small_id: u64,
+    num_consumed_compute_units: u64,
+    as_node: bool,
+) -> Result<TransactionDigest> {
+    let endpoint = if as_node {
+        ACCEPT_AS_NODE_ENDPOINT_NAME
+    } else {
+        ACCEPT_ENDPOINT_NAME
+    };
+    call(
+        context,
+        endpoint,
+        stack_small_id,
+        num_consumed_compute_units,
+        as_node,
+    )
+    .await
+}
+
+/// The node signs with the active address that owns the node badge, the
+/// owner with the zkLogin address if configured.
+async fn call(
+    context: &mut Context,
+    endpoint: &str,
+    stack_small_id: u64,
+    num_consumed_compute_units: u64,
+    as_node: bool,
+) -> Result<TransactionDigest> {
+    let atoma_package = context.unwrap_atoma_package_id();
+    let atoma_db = context.get_or_load_atoma_db().await?;
+
+    let mut args = vec![SuiJsonValue::from_object_id(atoma_db)];
+    let sender = if as_node {
+        let (node_badge, _) = context.get_or_load_node_badge().await?;
+        args.push(SuiJsonValue::from_object_id(node_badge));
+        context.wallet.active_address()?
+    } else {
+        context.sender()?
+    };
+    args.push(SuiJsonValue::new(stack_small_id.to_string().into())?);
+    args.push(SuiJsonValue::new(
+        num_consumed_compute_units.to_string().into(),
+    )?);
+
+    let tx = context
+        .get_client()
+        .await?
+        .transaction_builder()
+        .move_call(
+            sender,
+            atoma_package,
+            DB_MODULE_NAME,
+            endpoint,
+            vec![],
+            args,
+            None,
+            context.gas_budget(),
+            None,
+        )
+        .await?;
+
+    let tx = context.sign_transaction(&tx)?;
+    let resp = context.execute_transaction_must_succeed(tx).await?;
+    Ok(resp.digest)
+}
@@ -0,0 +1,161 @@
+use std::{collections::BTreeMap, path::Path};
+
+use move_core_types::language_storage::StructTag;
+use sui_sdk::{
+    rpc_types::{EventFilter, Page},
+    types::base_types::ObjectID,
+};
+
+use crate::{prelude::*, DB_MODULE_NAME};
+
+/// Emitted when a stack is settled, carries the compute units the node
+/// committed to.
+const SETTLED_EVENT_NAME: &str = "StackSettlementTicketEvent";
+/// Emitted when the funds for a settled stack are claimed.
+const SETTLED_CLAIMED_EVENT_NAME: &str = "StackSettlementTicketClaimedEvent";
+/// Emitted when the funds for a confidential compute stack are claimed.
+const CLAIMED_EVENT_NAME: &str = "ClaimedStackEvent";
+/// Emitted when a stack is closed early with the consent of both parties.
+const CLOSED_EARLY_EVENT_NAME: &str = "StackClosedEarlyEvent";
+
+#[derive(Default)]
+struct StackUsage {
+    logged: u64,
+    settled: Option<u64>,
+    claimed: Option<u64>,
+}
+
+/// 1. Sum the compute units per stack from the node's usage log.
+///    Each line of the log is a JSON object with `stack_small_id` and
+///    `compute_units` fields.
+/// 2. Collect the settled and claimed compute units of this node from the
+///    on-chain events.
+/// 3. Print a line per stack and flag those where the amounts disagree.
+///
+/// Returns how many stacks are not reconciled.
+pub(crate) async fn command(
+    context: &mut Context,
+    usage_log: &Path,
+) -> Result<usize> {
+    let (_, node_id) = context.get_or_load_node_badge().await?;
+    let package = context.unwrap_atoma_package_id();
+
+    // 1.
+    let mut stacks: BTreeMap<u64, StackUsage> = BTreeMap::new();
+    for (line_index, line) in std::fs::read_to_string(usage_log)?
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+    {
+        let entry: serde_json::Value = serde_json::from_str(line)?;
+        let field = |name: &str| {
+            entry[name].as_u64().ok_or_else(|| {
+                anyhow!("Line {}: missing u64 field {name}", line_index + 1)
+            })
+        };
+        stacks.entry(field("stack_small_id")?).or_default().logged +=
+            field("compute_units")?;
+    }
+    info!("Loaded usage of {} stack(s) from the log", stacks.len());
+
+    // 2.
+    for (event_name, is_claim) in [
+        (SETTLED_EVENT_NAME, false),
+        (SETTLED_CLAIMED_EVENT_NAME, true),
+        (CLAIMED_EVENT_NAME, true),
+        (CLOSED_EARLY_EVENT_NAME, true),
+    ] {
+        for (stack_small_id, units) in
+            node_events(context, package, event_name, node_id).await?
+        {
+            let usage = stacks.entry(stack_small_id).or_default();
+            if is_claim {
+                usage.claimed = Some(units);
+            } else {
+                usage.settled = Some(units);
+            }
+        }
+    }
+
+    // 3.
+    let mut mismatches = 0;
+    for (stack_small_id, usage) in &stacks {
+        let on_chain = usage.claimed.or(usage.settled);
+        let flag = match on_chain {
+            None => "[NOT SETTLED] ",
+            Some(units) if units < usage.logged => "[UNDER-CLAIM] ",
+            Some(units) if units > usage.logged => "[OVER-CLAIM] ",
+            Some(_) => "",
+        };
+        if !flag.is_empty() {
+            mismatches += 1;
+        }
+
+        let fmt = |units: Option<u64>| {
+            units.map_or_else(|| "-".to_string(), |u| u.to_string())
+        };
+        println!(
+            "{flag}Stack {stack_small_id}: logged {}, settled {}, claimed {}",
+            usage.logged,
+            fmt(usage.settled),
+            fmt(usage.claimed),
+        );
+    }
+
+    Ok(mismatches)
+}
+
+/// Returns `(stack_small_id, num_claimed_compute_units)` of all events of the
+/// given type that were emitted for the given node.
+async fn node_events(
+    context: &mut Context,
+    package: ObjectID,
+    event_name: &str,
+    node_id: u64,
+) -> Result<Vec<(u64, u64)>> {
+    let filter = EventFilter::MoveEventType(StructTag {
+        address: package.into(),
+        module: FromStr::from_str(DB_MODULE_NAME)?,
+        name: FromStr::from_str(event_name)?,
+        type_params: vec![],
+    });
+    let parse_u64 = |value: &serde_json::Value| {
+        value.as_str().and_then(|s| s.parse::<u64>().ok())
+    };
+
+    let client = context.get_client().await?;
+    let mut cursor = None;
+    let mut events = vec![];
+    loop {
+        let Page {
+            data,
+            has_next_page,
+            next_cursor,
+        } = client
+            .event_api()
+            .query_events(filter.clone(), cursor, None, false)
+            .await?;
+        cursor = next_cursor;
+
+        events.extend(data.into_iter().filter_map(|event| {
+            let json = event.parsed_json;
+            if parse_u64(&json["selected_node_id"]["inner"])? != node_id {
+                return None;
+            }
+            Some((
+                parse_u64(&json["stack_small_id"]["inner"])?,
+                parse_u64(&json["num_claimed_compute_units"])?,
+            ))
+        }));
+
+        if !has_next_page {
+            break;
+        }
+    }
+    debug!(
+        "Found {} {event_name} event(s) for node {node_id}",
+        events.len()
+    );
+
+    Ok(events)
+}
@@ -0,0 +1,70 @@
+//! Binds an off-chain inference request to a stack.
+//!
+//! The signed message is
+//! `DOMAIN || stack_small_id (LE) || nonce (LE) || H(payload)` where `H` is
+//! Blake2b-256.
+//! It's wrapped as a personal message so that a wallet can never be tricked
+//! into signing a transaction instead.
+
+use fastcrypto::hash::{Blake2b256, HashFunction};
+use shared_crypto::intent::{Intent, IntentMessage, PersonalMessage};
+use sui_keys::keystore::AccountKeystore;
+use sui_sdk::types::{
+    base_types::SuiAddress,
+    crypto::{Signature, SuiSignature},
+};
+
+use crate::prelude::*;
+
+/// Prevents a signature for a request to be reused in some other context.
+const DOMAIN: &[u8] = b"atoma-stack-request";
+
+pub(crate) fn request_message(
+    stack_small_id: u64,
+    nonce: u64,
+    payload: &[u8],
+) -> PersonalMessage {
+    let mut message = DOMAIN.to_vec();
+    message.extend_from_slice(&stack_small_id.to_le_bytes());
+    message.extend_from_slice(&nonce.to_le_bytes());
+    message.extend_from_slice(Blake2b256::digest(payload).as_ref());
+
+    PersonalMessage { message }
+}
+
+/// Signs the request with the key of the given address from the wallet
+/// keystore.
+pub(crate) fn sign(
+    wallet: &WalletContext,
+    signer: SuiAddress,
+    stack_small_id: u64,
+    nonce: u64,
+    payload: &[u8],
+) -> Result<Signature> {
+    let message = request_message(stack_small_id, nonce, payload);
+    let signature = wallet.config.keystore.sign_secure(
+        &signer,
+        &message,
+        Intent::personal_message(),
+    )?;
+
+    Ok(signature)
+}
+
+/// Fails if the signature was not produced by `expected_signer` for exactly
+/// this stack, nonce and payload.
+pub(crate) fn verify(
+    signature: &Signature,
+    expected_signer: SuiAddress,
+    stack_small_id: u64,
+    nonce: u64,
+    payload: &[u8],
+) -> Result<()> {
+    let message = IntentMessage::new(
+        Intent::personal_message(),
+        request_message(stack_small_id, nonce, payload),
+    );
+    signature
+        .verify_secure(&message, expected_signer, signature.scheme())
+        .map_err(|err| anyhow!("Invalid request signature: {err}"))
+}
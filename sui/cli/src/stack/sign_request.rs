@@ -0,0 +1,24 @@
+use fastcrypto::traits::EncodeDecodeBase64;
+
+use super::request_signature;
+use crate::prelude::*;
+
+/// Returns the base64 encoded signature of the request by the active address.
+pub(crate) async fn command(
+    context: &mut Context,
+    stack_small_id: u64,
+    nonce: u64,
+    payload: &str,
+) -> Result<String> {
+    context.conf.role.ensure_can_sign()?;
+    let active_address = context.wallet.active_address()?;
+    let signature = request_signature::sign(
+        &context.wallet,
+        active_address,
+        stack_small_id,
+        nonce,
+        payload.as_bytes(),
+    )?;
+
+    Ok(signature.encode_base64())
+}
@@ -0,0 +1,263 @@
+use std::{
+    path::Path,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use move_core_types::language_storage::StructTag;
+use sui_sdk::{
+    rpc_types::{EventFilter, Page, SuiData},
+    types::base_types::ObjectID,
+};
+
+use super::verify_request::{load_stack, stack_small_id_field_name};
+use crate::{prelude::*, DB_MODULE_NAME};
+
+const STACK_CREATED_EVENT_NAME: &str = "StackCreatedEvent";
+
+/// Requests sent with the stack according to the local request log.
+#[derive(Default)]
+struct LoggedUsage {
+    requests: u64,
+    compute_units: u64,
+    first_request_ms: Option<u64>,
+}
+
+/// 1. Load what was purchased from the stack and what the node claimed from
+///    its settlement ticket, if it's settled already.
+/// 2. Sum the requests and compute units of the stack from the local
+///    request log, if provided.
+///    It has the format of the usage log of `stack reconcile`, and each line
+///    can also have a `timestamp_ms` field.
+/// 3. Print the consumption, the effective cost per request and when the
+///    stack runs out at the current burn rate.
+///
+/// The burn rate is measured from the first logged request, or from the
+/// creation of the stack if the log has no timestamps.
+pub(crate) async fn command(
+    context: &mut Context,
+    stack_small_id: u64,
+    request_log: Option<&Path>,
+) -> Result<()> {
+    // 1.
+    let stack = load_stack(context, stack_small_id).await?;
+    let stack = &stack["value"];
+    let parse_u64 = |value: &serde_json::Value, name: &str| {
+        value
+            .as_str()
+            .and_then(|s| s.parse::<u64>().ok())
+            .ok_or_else(|| anyhow!("Stack {stack_small_id} has no {name}"))
+    };
+    let purchased = parse_u64(&stack["num_compute_units"], "compute units")?;
+    let price =
+        parse_u64(&stack["price_per_one_million_compute_units"], "price")?;
+    let claimed = settled_compute_units(context, stack_small_id).await?;
+
+    // 2.
+    let logged = match request_log {
+        Some(request_log) => Some(logged_usage(request_log, stack_small_id)?),
+        None => None,
+    };
+
+    // 3.
+    let cost =
+        |compute_units: u64| compute_units as u128 * price as u128 / 1_000_000;
+    println!(
+        "Purchased: {purchased} compute units for {} at {} per 1M",
+        context.toma(cost(purchased)),
+        context.toma(price),
+    );
+    let consumed = match (claimed, &logged) {
+        (Some(claimed), _) => {
+            println!("Consumed: {claimed} compute units, settled on-chain");
+            claimed
+        }
+        (None, Some(logged)) => {
+            println!(
+                "Consumed: {} compute units according to the request log, \
+                not settled yet",
+                logged.compute_units
+            );
+            logged.compute_units
+        }
+        (None, None) => {
+            println!(
+                "Consumed: unknown until the stack is settled, \
+                provide a request log"
+            );
+            return Ok(());
+        }
+    };
+    let remaining = purchased.saturating_sub(consumed);
+    println!(
+        "Remaining: {remaining} compute units ({}%)",
+        remaining as u128 * 100 / (purchased as u128).max(1)
+    );
+
+    if let Some(logged) = &logged {
+        if logged.requests > 0 {
+            println!(
+                "Requests: {}, on average {} compute units costing {} each",
+                logged.requests,
+                logged.compute_units / logged.requests,
+                context
+                    .toma(cost(logged.compute_units) / logged.requests as u128),
+            );
+        }
+    }
+
+    if claimed.is_some() {
+        // the node claims once, nothing burns after the settlement
+        return Ok(());
+    }
+    let since_ms = match logged.as_ref().and_then(|l| l.first_request_ms) {
+        Some(first_request_ms) => first_request_ms,
+        None => created_at_ms(context, stack_small_id).await?,
+    };
+    let now_ms =
+        SystemTime::now().duration_since(UNIX_EPOCH)?.as_millis() as u64;
+    let elapsed_ms = now_ms.saturating_sub(since_ms);
+    if consumed == 0 || elapsed_ms == 0 {
+        println!("Burn rate: nothing consumed yet");
+        return Ok(());
+    }
+    let per_hour = consumed as u128 * 3_600_000 / elapsed_ms as u128;
+    let exhausted_in_ms =
+        remaining as u128 * elapsed_ms as u128 / consumed as u128;
+    println!("Burn rate: {per_hour} compute units per hour");
+    println!(
+        "Projected exhaustion: in {} hour(s), at {} ms",
+        exhausted_in_ms / 3_600_000,
+        now_ms as u128 + exhausted_in_ms,
+    );
+
+    Ok(())
+}
+
+/// What the node claimed in the settlement ticket of the stack.
+pub(crate) async fn settled_compute_units(
+    context: &mut Context,
+    stack_small_id: u64,
+) -> Result<Option<u64>> {
+    let ticket = load_settlement_ticket(context, stack_small_id).await?;
+
+    Ok(ticket.and_then(|ticket| {
+        ticket["num_claimed_compute_units"]
+            .as_str()
+            .and_then(|s| s.parse().ok())
+    }))
+}
+
+/// The fields of the settlement ticket of the stack, if it was settled.
+pub(crate) async fn load_settlement_ticket(
+    context: &mut Context,
+    stack_small_id: u64,
+) -> Result<Option<serde_json::Value>> {
+    let package = context.unwrap_atoma_package_id();
+    let tickets_id = ObjectID::from_str(
+        context.load_atoma_db_fields().await?["stack_settlement_tickets"]["id"]
+            ["id"]
+            .as_str()
+            .ok_or_else(|| {
+                anyhow!("No stack_settlement_tickets field found")
+            })?,
+    )?;
+
+    // tickets are stored as dynamic object fields, so we get the ticket itself
+    let ticket = context
+        .get_client()
+        .await?
+        .read_api()
+        .get_dynamic_field_object(
+            tickets_id,
+            stack_small_id_field_name(package, stack_small_id)?,
+        )
+        .await?
+        .data
+        .and_then(|data| data.content)
+        .and_then(|content| content.try_into_move())
+        .map(|ticket| ticket.fields.to_json_value());
+
+    Ok(ticket)
+}
+
+fn logged_usage(
+    request_log: &Path,
+    stack_small_id: u64,
+) -> Result<LoggedUsage> {
+    let mut usage = LoggedUsage::default();
+    for (line_index, line) in std::fs::read_to_string(request_log)?
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+    {
+        let entry: serde_json::Value = serde_json::from_str(line)?;
+        let field = |name: &str| {
+            entry[name].as_u64().ok_or_else(|| {
+                anyhow!("Line {}: missing u64 field {name}", line_index + 1)
+            })
+        };
+        if field("stack_small_id")? != stack_small_id {
+            continue;
+        }
+
+        usage.requests += 1;
+        usage.compute_units += field("compute_units")?;
+        if let Some(timestamp_ms) = entry["timestamp_ms"].as_u64() {
+            usage.first_request_ms = Some(
+                usage
+                    .first_request_ms
+                    .map_or(timestamp_ms, |first| first.min(timestamp_ms)),
+            );
+        }
+    }
+    debug!(
+        "Logged {} request(s) for stack {stack_small_id}",
+        usage.requests
+    );
+
+    Ok(usage)
+}
+
+/// Events are scanned newest first since the stacks that are still burning
+/// are usually recent.
+async fn created_at_ms(
+    context: &mut Context,
+    stack_small_id: u64,
+) -> Result<u64> {
+    let filter = EventFilter::MoveEventType(StructTag {
+        address: context.unwrap_atoma_package_id().into(),
+        module: FromStr::from_str(DB_MODULE_NAME)?,
+        name: FromStr::from_str(STACK_CREATED_EVENT_NAME)?,
+        type_params: vec![],
+    });
+    let stack_small_id_str = stack_small_id.to_string();
+
+    let client = context.get_client().await?;
+    let mut cursor = None;
+    loop {
+        let Page {
+            data,
+            has_next_page,
+            next_cursor,
+        } = client
+            .event_api()
+            .query_events(filter.clone(), cursor, None, true)
+            .await?;
+        cursor = next_cursor;
+
+        if let Some(event) = data.into_iter().find(|event| {
+            event.parsed_json["stack_small_id"]["inner"].as_str()
+                == Some(stack_small_id_str.as_str())
+        }) {
+            return event.timestamp_ms.ok_or_else(|| {
+                anyhow!("{STACK_CREATED_EVENT_NAME} has no timestamp")
+            });
+        }
+
+        if !has_next_page {
+            anyhow::bail!(
+                "No {STACK_CREATED_EVENT_NAME} for stack {stack_small_id}"
+            );
+        }
+    }
+}
@@ -0,0 +1,96 @@
+use fastcrypto::traits::EncodeDecodeBase64;
+use move_core_types::language_storage::StructTag;
+use sui_sdk::{
+    rpc_types::SuiData,
+    types::{
+        base_types::{ObjectID, SuiAddress},
+        crypto::Signature,
+        dynamic_field::DynamicFieldName,
+        TypeTag,
+    },
+};
+
+use super::request_signature;
+use crate::{prelude::*, DB_MODULE_NAME};
+
+const STACK_SMALL_ID_TYPE_NAME: &str = "StackSmallId";
+
+/// 1. Find the owner of the stack in the db
+/// 2. Check that the signature was produced by the owner for this request
+pub(crate) async fn command(
+    context: &mut Context,
+    stack_small_id: u64,
+    nonce: u64,
+    payload: &str,
+    signature: &str,
+) -> Result<SuiAddress> {
+    let signature = Signature::decode_base64(signature)
+        .map_err(|err| anyhow!("Cannot decode signature: {err}"))?;
+
+    // 1.
+    let stack = load_stack(context, stack_small_id).await?;
+    let owner = SuiAddress::from_str(
+        stack["value"]["owner"]
+            .as_str()
+            .ok_or_else(|| anyhow!("Stack {stack_small_id} has no owner"))?,
+    )?;
+
+    // 2.
+    request_signature::verify(
+        &signature,
+        owner,
+        stack_small_id,
+        nonce,
+        payload.as_bytes(),
+    )?;
+
+    Ok(owner)
+}
+
+/// The stack as stored in the db, the fields are under `value`.
+pub(crate) async fn load_stack(
+    context: &mut Context,
+    stack_small_id: u64,
+) -> Result<serde_json::Value> {
+    let package = context.unwrap_atoma_package_id();
+    let stacks_id = ObjectID::from_str(
+        context.load_atoma_db_fields().await?["stacks"]["id"]["id"]
+            .as_str()
+            .ok_or_else(|| anyhow!("No stacks field found"))?,
+    )?;
+    let stack = context
+        .get_client()
+        .await?
+        .read_api()
+        .get_dynamic_field_object(
+            stacks_id,
+            stack_small_id_field_name(package, stack_small_id)?,
+        )
+        .await?
+        .data
+        .ok_or_else(|| anyhow!("Stack {stack_small_id} not found"))?
+        .content
+        .ok_or_else(|| anyhow!("Stack {stack_small_id} has no content"))?
+        .try_into_move()
+        .ok_or_else(|| anyhow!("Stack {stack_small_id} is not a Move object"))?
+        .fields
+        .to_json_value();
+
+    Ok(stack)
+}
+
+/// Key of the stack in the db tables keyed by `StackSmallId`.
+pub(crate) fn stack_small_id_field_name(
+    package: ObjectID,
+    stack_small_id: u64,
+) -> Result<DynamicFieldName> {
+    Ok(DynamicFieldName {
+        type_: TypeTag::Struct(Box::new(StructTag {
+            address: package.into(),
+            module: FromStr::from_str(DB_MODULE_NAME)?,
+            name: FromStr::from_str(STACK_SMALL_ID_TYPE_NAME)?,
+            type_params: vec![],
+        })),
+        value: serde_json::json!({ "inner": stack_small_id.to_string() }),
+    })
+}
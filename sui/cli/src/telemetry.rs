@@ -0,0 +1,209 @@
+//! OpenTelemetry spans of the settlement pipeline.
+//!
+//! A command that daemons run for a pipeline stage records a root span with
+//! a child span per step, eg. `commitment` and `tx_confirmed`, and exports
+//! them with OTLP over HTTP/JSON to `{OTEL_EXPORTER_OTLP_ENDPOINT}/v1/traces`.
+//! Without the endpoint the spans are dropped.
+//!
+//! The daemon that received the event and ran the inference can pass its span
+//! in the W3C `TRACEPARENT` env var, eg.
+//! `00-{32 hex trace id}-{16 hex span id}-01`, so that the spans of the
+//! command join the trace of the whole pipeline.
+
+use std::{
+    sync::atomic::{AtomicU64, Ordering},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use fastcrypto::{
+    encoding::{Encoding, Hex},
+    hash::{Blake2b256, HashFunction},
+};
+use serde_json::{json, Value};
+
+use crate::prelude::*;
+
+pub(crate) const OTLP_ENDPOINT: &str = "OTEL_EXPORTER_OTLP_ENDPOINT";
+pub(crate) const TRACEPARENT: &str = "TRACEPARENT";
+const SERVICE_NAME: &str = "atoma-cli";
+const SPAN_KIND_INTERNAL: u64 = 1;
+const STATUS_CODE_OK: u64 = 1;
+const STATUS_CODE_ERROR: u64 = 2;
+
+struct Span {
+    span_id: [u8; 8],
+    parent_span_id: Option<[u8; 8]>,
+    name: String,
+    start_ns: u128,
+    end_ns: u128,
+    attributes: Vec<(String, String)>,
+}
+
+pub(crate) struct Trace {
+    endpoint: Option<String>,
+    trace_id: [u8; 16],
+    root: Span,
+    stages: Vec<Span>,
+}
+
+impl Trace {
+    /// Starts the root span, the child of the `TRACEPARENT` span if given.
+    ///
+    /// See also [`Context::trace`].
+    pub(crate) fn start(endpoint: Option<&str>, name: &str) -> Self {
+        let (trace_id, parent_span_id) = std::env::var(TRACEPARENT)
+            .ok()
+            .and_then(|traceparent| parse_traceparent(&traceparent))
+            .map(|(trace_id, span_id)| (trace_id, Some(span_id)))
+            .unwrap_or_else(|| {
+                let mut trace_id = [0; 16];
+                trace_id.copy_from_slice(&random_id()[..16]);
+                (trace_id, None)
+            });
+
+        Self {
+            endpoint: endpoint
+                .map(|endpoint| endpoint.trim_end_matches('/'))
+                .map(ToOwned::to_owned),
+            trace_id,
+            root: Span::start(name, parent_span_id),
+            stages: vec![],
+        }
+    }
+
+    /// Ends the current stage, if any, and starts the next one.
+    pub(crate) fn stage(&mut self, name: &str) {
+        self.end_stage();
+        self.stages.push(Span::start(name, Some(self.root.span_id)));
+    }
+
+    /// Attributes describe the whole pipeline stage, so they are set on the
+    /// root span.
+    pub(crate) fn set_attribute(&mut self, key: &str, value: impl ToString) {
+        self.root
+            .attributes
+            .push((key.to_owned(), value.to_string()));
+    }
+
+    /// Ends all spans and exports them.
+    ///
+    /// Exporting is best effort, the command must not fail because the
+    /// collector is unreachable.
+    pub(crate) async fn finish(mut self, error: Option<&anyhow::Error>) {
+        self.end_stage();
+        self.root.end_ns = now_ns();
+        let Some(endpoint) = &self.endpoint else {
+            return;
+        };
+
+        let status = match error {
+            Some(err) => json!({
+                "code": STATUS_CODE_ERROR,
+                "message": err.to_string(),
+            }),
+            None => json!({ "code": STATUS_CODE_OK }),
+        };
+        let mut spans = vec![self.root.to_otlp(&self.trace_id, status)];
+        spans.extend(self.stages.iter().map(|span| {
+            span.to_otlp(&self.trace_id, json!({ "code": STATUS_CODE_OK }))
+        }));
+        let body = json!({
+            "resourceSpans": [{
+                "resource": {
+                    "attributes": [attribute("service.name", SERVICE_NAME)],
+                },
+                "scopeSpans": [{
+                    "scope": { "name": SERVICE_NAME },
+                    "spans": spans,
+                }],
+            }],
+        });
+
+        let resp = reqwest::Client::new()
+            .post(format!("{endpoint}/v1/traces"))
+            .json(&body)
+            .send()
+            .await
+            .and_then(|resp| resp.error_for_status());
+        match resp {
+            Ok(_) => debug!("Exported {} span(s) to {endpoint}", spans.len()),
+            Err(err) => error!("Failed to export spans to {endpoint}: {err}"),
+        }
+    }
+
+    fn end_stage(&mut self) {
+        if let Some(stage) = self.stages.last_mut() {
+            if stage.end_ns == 0 {
+                stage.end_ns = now_ns();
+            }
+        }
+    }
+}
+
+impl Span {
+    fn start(name: &str, parent_span_id: Option<[u8; 8]>) -> Self {
+        let mut span_id = [0; 8];
+        span_id.copy_from_slice(&random_id()[..8]);
+        Self {
+            span_id,
+            parent_span_id,
+            name: name.to_owned(),
+            start_ns: now_ns(),
+            end_ns: 0,
+            attributes: vec![],
+        }
+    }
+
+    /// IDs are hex encoded and timestamps are strings in OTLP/JSON.
+    fn to_otlp(&self, trace_id: &[u8; 16], status: Value) -> Value {
+        json!({
+            "traceId": Hex::encode(trace_id),
+            "spanId": Hex::encode(self.span_id),
+            "parentSpanId": self.parent_span_id.map(Hex::encode)
+                .unwrap_or_default(),
+            "name": self.name,
+            "kind": SPAN_KIND_INTERNAL,
+            "startTimeUnixNano": self.start_ns.to_string(),
+            "endTimeUnixNano": self.end_ns.to_string(),
+            "attributes": self.attributes.iter()
+                .map(|(key, value)| attribute(key, value))
+                .collect::<Vec<_>>(),
+            "status": status,
+        })
+    }
+}
+
+fn attribute(key: &str, value: &str) -> Value {
+    json!({ "key": key, "value": { "stringValue": value } })
+}
+
+/// Only version `00` is defined, see https://www.w3.org/TR/trace-context.
+fn parse_traceparent(traceparent: &str) -> Option<([u8; 16], [u8; 8])> {
+    let mut parts = traceparent.trim().split('-');
+    if parts.next()? != "00" {
+        return None;
+    }
+    let trace_id = Hex::decode(parts.next()?).ok()?.try_into().ok()?;
+    let span_id = Hex::decode(parts.next()?).ok()?.try_into().ok()?;
+
+    Some((trace_id, span_id))
+}
+
+/// IDs only need to be unique, not unpredictable.
+fn random_id() -> [u8; 32] {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+
+    let mut preimage = now_ns().to_le_bytes().to_vec();
+    preimage.extend_from_slice(&std::process::id().to_le_bytes());
+    preimage.extend_from_slice(
+        &COUNTER.fetch_add(1, Ordering::Relaxed).to_le_bytes(),
+    );
+    Blake2b256::digest(&preimage).digest
+}
+
+fn now_ns() -> u128 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|since| since.as_nanos())
+        .unwrap_or_default()
+}
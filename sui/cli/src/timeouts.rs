@@ -0,0 +1,89 @@
+//! How long operations may take before the command gives up on them, so
+//! that a hanging full node doesn't leave daemons stuck forever.
+//!
+//! - `RPC_TIMEOUT_SECS` applies to every request to the full node
+//! - `EXECUTE_TIMEOUT_SECS` applies to executing a signed transaction
+//!
+//! The global `--timeout` flag sets both.
+//! Without either, operations wait as long as the full node takes.
+//!
+//! A signed transaction whose execution timed out might still be executed.
+//! It's resubmitted as is rather than signed anew, since executing the same
+//! transaction twice returns the effects of the first execution, whereas a
+//! newly signed one could execute too.
+
+use std::time::Duration;
+
+use sui_sdk::{
+    rpc_types::SuiTransactionBlockResponse, types::transaction::Transaction,
+    wallet_context::WalletContext,
+};
+
+use crate::prelude::*;
+
+pub(crate) const RPC_TIMEOUT_SECS: &str = "RPC_TIMEOUT_SECS";
+pub(crate) const EXECUTE_TIMEOUT_SECS: &str = "EXECUTE_TIMEOUT_SECS";
+/// How many times a signed transaction is submitted before the command
+/// gives up on it.
+const EXECUTE_ATTEMPTS: u32 = 3;
+
+#[derive(Clone, Copy, Debug, Default)]
+pub(crate) struct Timeouts {
+    pub(crate) rpc: Option<Duration>,
+    pub(crate) execute: Option<Duration>,
+}
+
+impl Timeouts {
+    pub(crate) fn from_env() -> Self {
+        let secs = |name: &str| {
+            std::env::var(name).ok().filter(|s| !s.is_empty()).map(|s| {
+                Duration::from_secs(
+                    s.parse()
+                        .unwrap_or_else(|err| panic!("Invalid {name}: {err}")),
+                )
+            })
+        };
+        Self {
+            rpc: secs(RPC_TIMEOUT_SECS),
+            execute: secs(EXECUTE_TIMEOUT_SECS),
+        }
+    }
+
+    /// `--timeout` takes precedence over the env vars.
+    pub(crate) fn all(timeout: Duration) -> Self {
+        Self {
+            rpc: Some(timeout),
+            execute: Some(timeout),
+        }
+    }
+}
+
+/// Submits the same signed transaction up to [`EXECUTE_ATTEMPTS`] times if
+/// it doesn't execute in time.
+pub(crate) async fn execute(
+    wallet: &WalletContext,
+    timeout: Duration,
+    tx: Transaction,
+) -> Result<SuiTransactionBlockResponse> {
+    let digest = *tx.digest();
+    for attempt in 1..=EXECUTE_ATTEMPTS {
+        match tokio::time::timeout(
+            timeout,
+            wallet.execute_transaction_may_fail(tx.clone()),
+        )
+        .await
+        {
+            Ok(resp) => return resp,
+            Err(_) => log::warn!(
+                "Executing {digest} timed out after {}s, \
+                attempt {attempt} of {EXECUTE_ATTEMPTS}",
+                timeout.as_secs()
+            ),
+        }
+    }
+
+    anyhow::bail!(
+        "Transaction {digest} did not execute in time, it might still \
+        execute so check it before signing another one"
+    )
+}
@@ -1,3 +1,16 @@
+mod balances;
+mod coin_type;
+mod create_migration;
 mod faucet;
+mod faucet_server;
+mod migrate_coins;
 
-pub(crate) use faucet::command as faucet;
+pub(crate) use balances::command as balances;
+pub(crate) use coin_type::{
+    legacy_from_env as legacy_coin_types_from_env,
+    normalize as normalize_coin_type,
+};
+pub(crate) use create_migration::command as create_migration;
+pub(crate) use faucet::{command as faucet, to as faucet_to};
+pub(crate) use faucet_server::{command as faucet_server, DripLimits};
+pub(crate) use migrate_coins::command as migrate_coins;
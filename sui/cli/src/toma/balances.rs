@@ -0,0 +1,31 @@
+use crate::prelude::*;
+
+/// Prints the balance of the sender in every generation of TOMA, the
+/// current one that pays for prompts and stacks and the older ones of
+/// `TOMA_LEGACY_COIN_TYPES`.
+pub(crate) async fn command(context: &mut Context) -> Result<()> {
+    let owner = context.sender()?;
+    let current = context.get_or_load_toma_coin_type().await?;
+    let client = context.get_client().await?;
+
+    let generations = std::iter::once(("current", current)).chain(
+        context
+            .conf
+            .toma_legacy_coin_types
+            .iter()
+            .map(|coin_type| ("legacy", coin_type.clone())),
+    );
+    for (generation, coin_type) in generations {
+        let balance = client
+            .coin_read_api()
+            .get_balance(owner, Some(coin_type.clone()))
+            .await?;
+        println!(
+            "{generation} {coin_type}: {} in {} coin(s)",
+            context.toma(balance.total_balance),
+            balance.coin_object_count
+        );
+    }
+
+    Ok(())
+}
@@ -0,0 +1,39 @@
+use crate::prelude::*;
+
+/// Env var with the coin types of older TOMA generations, comma separated,
+/// eg. `0x12..::toma::TOMA`.
+pub(crate) const TOMA_LEGACY_COIN_TYPES: &str = "TOMA_LEGACY_COIN_TYPES";
+
+/// Writes the address of the coin type with all its 64 hex digits and the
+/// `0x` prefix, so that types compare equal however they were written, eg.
+/// by `std::type_name` which leaves out the prefix.
+pub(crate) fn normalize(coin_type: &str) -> Result<String> {
+    let mut parts = coin_type.trim().splitn(3, "::");
+    let (Some(address), Some(module), Some(name)) =
+        (parts.next(), parts.next(), parts.next())
+    else {
+        anyhow::bail!("{coin_type} is not a coin type, eg. 0x2::sui::SUI");
+    };
+    let address = address.trim_start_matches("0x");
+    if address.is_empty()
+        || address.len() > 64
+        || !address.chars().all(|c| c.is_ascii_hexdigit())
+    {
+        anyhow::bail!("{coin_type} has an invalid address");
+    }
+
+    Ok(format!(
+        "0x{:0>64}::{module}::{name}",
+        address.to_lowercase()
+    ))
+}
+
+/// Reads `TOMA_LEGACY_COIN_TYPES`.
+pub(crate) fn legacy_from_env() -> Vec<String> {
+    std::env::var(TOMA_LEGACY_COIN_TYPES)
+        .unwrap_or_default()
+        .split(',')
+        .filter(|coin_type| !coin_type.trim().is_empty())
+        .map(|coin_type| normalize(coin_type).unwrap())
+        .collect()
+}
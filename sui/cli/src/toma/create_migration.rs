@@ -0,0 +1,67 @@
+use sui_sdk::{rpc_types::SuiTypeTag, types::base_types::ObjectID};
+
+use super::coin_type::normalize;
+use crate::{prelude::*, TOMA_COIN_MODULE_NAME};
+
+const ENDPOINT_NAME: &str = "create_migration";
+const MIGRATION_CREATED_EVENT_NAME: &str = "MigrationCreatedEvent";
+
+/// Opens a migration from the older coin type, funded with `amount` of the
+/// TOMA of the active address.
+///
+/// Returns the ID of the migration.
+pub(crate) async fn command(
+    context: &mut Context,
+    publisher: ObjectID,
+    old_coin_type: &str,
+    amount: u64,
+) -> Result<ObjectID> {
+    let old_coin_type = normalize(old_coin_type)?;
+    let active_address = context.wallet.active_address()?;
+    let toma_package = context.get_or_load_toma_package_id().await?;
+    let toma_wallet = context.get_or_load_toma_wallet().await?;
+    let client = context.get_client().await?;
+    let old_metadata = client
+        .coin_read_api()
+        .get_coin_metadata(old_coin_type.clone())
+        .await?
+        .and_then(|metadata| metadata.id)
+        .ok_or_else(|| anyhow!("No coin metadata for {old_coin_type}"))?;
+
+    let tx = client
+        .transaction_builder()
+        .move_call(
+            active_address,
+            toma_package,
+            TOMA_COIN_MODULE_NAME,
+            ENDPOINT_NAME,
+            vec![SuiTypeTag::new(old_coin_type.clone())],
+            vec![
+                SuiJsonValue::from_object_id(publisher),
+                SuiJsonValue::from_object_id(old_metadata),
+                SuiJsonValue::from_object_id(toma_wallet),
+                SuiJsonValue::new(amount.to_string().into())?,
+            ],
+            None,
+            context.gas_budget(),
+            None,
+        )
+        .await?;
+
+    let tx = context.sign_transaction(&tx)?;
+    let resp = context.execute_transaction_must_succeed(tx).await?;
+    let migration = resp
+        .events
+        .iter()
+        .flat_map(|events| &events.data)
+        .find(|event| event.type_.name.as_str() == MIGRATION_CREATED_EVENT_NAME)
+        .and_then(|event| event.parsed_json["migration"].as_str())
+        .ok_or_else(|| anyhow!("No {MIGRATION_CREATED_EVENT_NAME} emitted"))?;
+    info!(
+        "Opened migration from {old_coin_type} with {} in reserve in {}",
+        context.toma(amount),
+        resp.digest
+    );
+
+    Ok(ObjectID::from_str(migration)?)
+}
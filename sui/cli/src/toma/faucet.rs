@@ -1,12 +1,15 @@
-use sui_sdk::types::base_types::ObjectID;
+use sui_sdk::types::base_types::{ObjectID, SuiAddress};
 
 use crate::{prelude::*, TOMA_COIN_MODULE_NAME};
 
 const ENDPOINT_NAME: &str = "faucet";
+const TO_ENDPOINT_NAME: &str = "faucet_to";
 const FAUCET_OBJECT_ID: &str =
     "0xfdddd6fb95509ea36f44f06d0d0a2f5868dac2bda1423d204bdc9f458115ff75";
 
 /// If Toma package ID is not provided, we use the env vars.
+///
+/// With `--wait-for-coin`, returns once the minted coin can be found.
 pub(crate) async fn command(
     context: &mut Context,
     amount: u64,
@@ -36,7 +39,50 @@ pub(crate) async fn command(
         )
         .await?;
 
-    let tx = context.wallet.sign_transaction(&tx);
-    let resp = context.wallet.execute_transaction_must_succeed(tx).await;
+    let tx = context.sign_transaction(&tx)?;
+    let resp = context.execute_transaction_must_succeed(tx).await?;
+    context.note_toma_coins_created();
+
+    // scripts can wait until the next command finds the coin
+    if !context.conf.wait_for_coin.is_zero() {
+        context.get_or_load_toma_wallet().await?;
+    }
+
+    Ok(resp.digest)
+}
+
+/// Mints the amount straight to the recipient in a single transaction.
+pub(crate) async fn to(
+    context: &mut Context,
+    amount: u64,
+    recipient: SuiAddress,
+) -> Result<TransactionDigest> {
+    let active_address = context.wallet.active_address()?;
+    let sui = context.get_client().await?;
+    let toma_package = context.get_or_load_toma_package_id().await?;
+
+    let tx = sui
+        .transaction_builder()
+        .move_call(
+            active_address,
+            toma_package,
+            TOMA_COIN_MODULE_NAME,
+            TO_ENDPOINT_NAME,
+            vec![],
+            vec![
+                SuiJsonValue::from_object_id(ObjectID::from_str(
+                    FAUCET_OBJECT_ID,
+                )?),
+                SuiJsonValue::new(amount.to_string().into())?,
+                SuiJsonValue::new(recipient.to_string().into())?,
+            ],
+            None,
+            context.gas_budget(),
+            None,
+        )
+        .await?;
+
+    let tx = context.sign_transaction(&tx)?;
+    let resp = context.execute_transaction_must_succeed(tx).await?;
     Ok(resp.digest)
 }
@@ -0,0 +1,338 @@
+use std::{
+    collections::{HashMap, HashSet},
+    net::SocketAddr,
+    path::Path,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use serde_json::json;
+use sui_sdk::types::base_types::SuiAddress;
+use tokio::{
+    io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader},
+    net::{TcpListener, TcpStream},
+    sync::Mutex,
+};
+
+use crate::prelude::*;
+
+const API_KEY_HEADER: &str = "x-api-key";
+const DRIP_PATH: &str = "/drip";
+const MAX_BODY_LEN: usize = 4 * 1024;
+/// Longer request and header lines are rejected.
+const MAX_LINE_LEN: u64 = 8 * 1024;
+/// How long a client has to send the whole request.
+const READ_TIMEOUT: Duration = Duration::from_secs(10);
+/// How long a client has to take the whole response.
+const WRITE_TIMEOUT: Duration = Duration::from_secs(10);
+const DAY: Duration = Duration::from_secs(24 * 60 * 60);
+
+pub(crate) struct DripLimits {
+    /// How long an address or an API key has to wait between two drips.
+    pub(crate) cooldown: Duration,
+    /// How many drips the server hands out in a 24 hour window.
+    pub(crate) daily_cap: u64,
+}
+
+struct Server {
+    /// The faucet transactions are signed by the active address of this
+    /// wallet, so it must be funded with SUI for gas.
+    context: Mutex<Context>,
+    api_keys: HashSet<String>,
+    amount: u64,
+    limits: DripLimits,
+    drips: Mutex<Drips>,
+}
+
+/// A request to `POST /drip` with a body of at most `MAX_BODY_LEN` bytes.
+struct DripRequest {
+    api_key: Option<String>,
+    body: Vec<u8>,
+}
+
+/// The status and the JSON body of an error response.
+type Rejection = (u16, serde_json::Value);
+
+#[derive(Default)]
+struct Drips {
+    /// Keyed by both recipient addresses and API keys, only those still in
+    /// their cooldown.
+    last_drip_at: HashMap<String, Instant>,
+    window_started_at: Option<Instant>,
+    in_window: u64,
+}
+
+/// Serves `POST /drip` with a JSON body `{"address": "0x..."}` and the API
+/// key in the `x-api-key` header.
+///
+/// Each drip mints `amount` TOMA straight to the requested address, in a
+/// transaction signed by the active address.
+/// Drips are served one at a time because they are signed by the same
+/// wallet.
+pub(crate) async fn command(
+    context: Context,
+    listen: SocketAddr,
+    amount: u64,
+    api_keys_file: &Path,
+    limits: DripLimits,
+) -> Result<()> {
+    let api_keys: HashSet<_> = std::fs::read_to_string(api_keys_file)?
+        .lines()
+        .map(str::trim)
+        .filter(|key| !key.is_empty() && !key.starts_with('#'))
+        .map(ToOwned::to_owned)
+        .collect();
+    if api_keys.is_empty() {
+        anyhow::bail!("No API keys found in {api_keys_file:?}");
+    }
+
+    let server = Arc::new(Server {
+        context: Mutex::new(context),
+        api_keys,
+        amount,
+        limits,
+        drips: Mutex::default(),
+    });
+
+    let listener = TcpListener::bind(listen).await?;
+    println!("Faucet listening on {listen}");
+
+    loop {
+        let (stream, peer) = listener.accept().await?;
+        let server = Arc::clone(&server);
+        tokio::spawn(async move {
+            if let Err(err) = server.handle(stream).await {
+                debug!("Connection from {peer} failed: {err}");
+            }
+        });
+    }
+}
+
+impl Server {
+    async fn handle(&self, stream: TcpStream) -> Result<()> {
+        let mut stream = BufReader::new(stream);
+
+        // the drip itself is not covered by the timeout, since cancelling it
+        // halfway would leave the limits reserved
+        let request =
+            tokio::time::timeout(READ_TIMEOUT, read_request(&mut stream))
+                .await
+                .unwrap_or_else(|_| {
+                    Err((408, json!({ "error": "Request timed out" })))
+                });
+        let (status, body) = match request {
+            Ok(DripRequest { api_key, body }) => {
+                self.drip(api_key, &body).await
+            }
+            Err(rejection) => rejection,
+        };
+
+        let body = body.to_string();
+        let response = format!(
+            "HTTP/1.1 {status} {}\r\n\
+            Content-Type: application/json\r\n\
+            Content-Length: {}\r\n\
+            Connection: close\r\n\r\n{body}",
+            reason_phrase(status),
+            body.len()
+        );
+        tokio::time::timeout(
+            WRITE_TIMEOUT,
+            stream.get_mut().write_all(response.as_bytes()),
+        )
+        .await
+        .map_err(|_| anyhow!("Response timed out"))??;
+
+        Ok(())
+    }
+
+    async fn drip(
+        &self,
+        api_key: Option<String>,
+        body: &[u8],
+    ) -> (u16, serde_json::Value) {
+        let Some(api_key) = api_key.filter(|key| self.api_keys.contains(key))
+        else {
+            return (401, json!({ "error": "Missing or unknown API key" }));
+        };
+
+        let recipient = serde_json::from_slice::<serde_json::Value>(body)
+            .ok()
+            .and_then(|body| {
+                SuiAddress::from_str(body["address"].as_str()?).ok()
+            });
+        let Some(recipient) = recipient else {
+            return (
+                400,
+                json!({ "error": "Expected {\"address\": \"0x...\"}" }),
+            );
+        };
+
+        // reserve the drip before sending any transaction so that concurrent
+        // requests cannot go over the limits
+        if let Err(retry_after) = self.reserve(&api_key, recipient).await {
+            return (
+                429,
+                json!({
+                    "error": "Drip limit reached",
+                    "retry_after_secs": retry_after.as_secs(),
+                }),
+            );
+        }
+
+        match self.mint_to(recipient).await {
+            Ok(digest) => {
+                info!(
+                    "Dripped {} TOMA to {recipient} in {digest}",
+                    self.amount
+                );
+                (
+                    200,
+                    json!({
+                        "amount": self.amount.to_string(),
+                        "digest": digest.to_string(),
+                    }),
+                )
+            }
+            Err(err) => {
+                error!("Drip to {recipient} failed: {err}");
+                self.release(&api_key, recipient).await;
+                (500, json!({ "error": "Faucet transaction failed" }))
+            }
+        }
+    }
+
+    /// Returns how long the caller has to wait if a limit is reached.
+    async fn reserve(
+        &self,
+        api_key: &str,
+        recipient: SuiAddress,
+    ) -> Result<(), Duration> {
+        let mut drips = self.drips.lock().await;
+        let now = Instant::now();
+
+        let window_started_at = *drips.window_started_at.get_or_insert(now);
+        if now.duration_since(window_started_at) >= DAY {
+            drips.window_started_at = Some(now);
+            drips.in_window = 0;
+        } else if drips.in_window >= self.limits.daily_cap {
+            return Err(DAY - now.duration_since(window_started_at));
+        }
+
+        // keys past their cooldown limit nothing, so they are dropped rather
+        // than kept for every address that ever asked
+        let cooldown = self.limits.cooldown;
+        drips
+            .last_drip_at
+            .retain(|_, last| now.duration_since(*last) < cooldown);
+
+        let keys = [api_key.to_owned(), recipient.to_string()];
+        for key in &keys {
+            if let Some(last) = drips.last_drip_at.get(key) {
+                let elapsed = now.duration_since(*last);
+                if elapsed < self.limits.cooldown {
+                    return Err(self.limits.cooldown - elapsed);
+                }
+            }
+        }
+
+        for key in keys {
+            drips.last_drip_at.insert(key, now);
+        }
+        drips.in_window += 1;
+
+        Ok(())
+    }
+
+    /// Gives the drip back if the transaction failed.
+    async fn release(&self, api_key: &str, recipient: SuiAddress) {
+        let mut drips = self.drips.lock().await;
+        drips.last_drip_at.remove(api_key);
+        drips.last_drip_at.remove(&recipient.to_string());
+        drips.in_window = drips.in_window.saturating_sub(1);
+    }
+
+    async fn mint_to(
+        &self,
+        recipient: SuiAddress,
+    ) -> Result<TransactionDigest> {
+        let mut context = self.context.lock().await;
+        super::faucet_to(&mut context, self.amount, recipient).await
+    }
+}
+
+/// Reads the request line, the headers and the body, rejecting anything but
+/// a well-formed `POST /drip`.
+async fn read_request(
+    stream: &mut BufReader<TcpStream>,
+) -> Result<DripRequest, Rejection> {
+    let request_line = read_line(stream).await?;
+    let mut parts = request_line.split_whitespace();
+    let method = parts.next().unwrap_or_default();
+    let path = parts.next().unwrap_or_default();
+    if method != "POST" || path != DRIP_PATH {
+        return Err((404, json!({ "error": format!("Use POST {DRIP_PATH}") })));
+    }
+
+    let mut api_key = None;
+    let mut content_length = 0;
+    loop {
+        let line = read_line(stream).await?;
+        if line.is_empty() {
+            break;
+        }
+        let Some((name, value)) = line.split_once(':') else {
+            continue;
+        };
+        let value = value.trim();
+        match name.trim().to_ascii_lowercase().as_str() {
+            API_KEY_HEADER => api_key = Some(value.to_owned()),
+            "content-length" => {
+                content_length = value
+                    .parse()
+                    .map_err(|_| bad_request("Invalid Content-Length"))?
+            }
+            _ => {}
+        }
+    }
+
+    if content_length > MAX_BODY_LEN {
+        return Err((413, json!({ "error": "Request body too large" })));
+    }
+    let mut body = vec![0; content_length];
+    stream
+        .read_exact(&mut body)
+        .await
+        .map_err(|_| bad_request("Request body shorter than Content-Length"))?;
+
+    Ok(DripRequest { api_key, body })
+}
+
+/// Reads a line of at most `MAX_LINE_LEN` bytes without the line ending.
+async fn read_line(
+    stream: &mut BufReader<TcpStream>,
+) -> Result<String, Rejection> {
+    let mut line = String::new();
+    match (&mut *stream).take(MAX_LINE_LEN).read_line(&mut line).await {
+        Ok(_) if line.ends_with('\n') => Ok(line.trim_end().to_owned()),
+        Ok(_) => Err(bad_request("Line too long or request cut short")),
+        Err(_) => Err(bad_request("Malformed request")),
+    }
+}
+
+fn bad_request(error: &str) -> Rejection {
+    (400, json!({ "error": error }))
+}
+
+fn reason_phrase(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        404 => "Not Found",
+        408 => "Request Timeout",
+        413 => "Payload Too Large",
+        429 => "Too Many Requests",
+        _ => "Internal Server Error",
+    }
+}
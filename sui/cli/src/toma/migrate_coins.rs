@@ -0,0 +1,212 @@
+use std::collections::BTreeMap;
+
+use move_core_types::language_storage::StructTag;
+use sui_sdk::{
+    rpc_types::{
+        EventFilter, MoveCallParams, Page, RPCTransactionRequestParams,
+        SuiExecutionStatus, SuiObjectDataOptions, SuiParsedData,
+        SuiTransactionBlockEffectsAPI, SuiTypeTag,
+    },
+    types::base_types::ObjectID,
+};
+
+use super::coin_type::normalize;
+use crate::{
+    db::{load_package, parse_u64, type_origin},
+    prelude::*,
+    TOMA_COIN_MODULE_NAME,
+};
+
+const ENDPOINT_NAME: &str = "migrate";
+const MIGRATION_CREATED_EVENT_NAME: &str = "MigrationCreatedEvent";
+/// Each coin is swapped by its own call.
+const MAX_COINS_PER_TX: usize = 100;
+
+/// For every older generation of `TOMA_LEGACY_COIN_TYPES` that the sender
+/// holds coins of:
+/// 1. Find the migration the publisher opened for it, and check that its
+///    reserve covers the coins.
+/// 2. Swap the coins for TOMA, in batches of calls.
+pub(crate) async fn command(context: &mut Context) -> Result<()> {
+    let owner = context.sender()?;
+    let toma_package = context.get_or_load_toma_package_id().await?;
+    let client = context.get_client().await?;
+    let legacy_coin_types = context.conf.toma_legacy_coin_types.clone();
+    if legacy_coin_types.is_empty() {
+        anyhow::bail!("No older TOMA generations, see TOMA_LEGACY_COIN_TYPES");
+    }
+
+    let migrations = load_migrations(context, toma_package).await?;
+    let mut migrated = 0u128;
+    for coin_type in legacy_coin_types {
+        let mut coins = vec![];
+        let mut cursor = None;
+        loop {
+            let Page {
+                data,
+                has_next_page,
+                next_cursor,
+            } = client
+                .coin_read_api()
+                .get_coins(owner, Some(coin_type.clone()), cursor, None)
+                .await?;
+            coins.extend(
+                data.into_iter()
+                    .map(|coin| (coin.coin_object_id, coin.balance)),
+            );
+            if !has_next_page {
+                break;
+            }
+            cursor = next_cursor;
+        }
+        if coins.is_empty() {
+            debug!("No {coin_type} coins to migrate");
+            continue;
+        }
+
+        // 1.
+        let balance: u128 =
+            coins.iter().map(|(_, balance)| u128::from(*balance)).sum();
+        let migration = *migrations.get(&coin_type).ok_or_else(|| {
+            anyhow!("The publisher has not opened a migration for {coin_type}")
+        })?;
+        let reserve = load_reserve(context, migration).await?;
+        if u128::from(reserve) < balance {
+            anyhow::bail!(
+                "Migration {migration} has {} in reserve, too little for \
+                the {} of {coin_type}",
+                context.toma(reserve),
+                context.toma(balance)
+            );
+        }
+
+        // 2.
+        for batch in coins.chunks(MAX_COINS_PER_TX) {
+            let calls = batch
+                .iter()
+                .map(|(coin, _)| {
+                    RPCTransactionRequestParams::MoveCallRequestParams(
+                        MoveCallParams {
+                            package_object_id: toma_package,
+                            module: TOMA_COIN_MODULE_NAME.to_string(),
+                            function: ENDPOINT_NAME.to_string(),
+                            type_arguments: vec![SuiTypeTag::new(
+                                coin_type.clone(),
+                            )],
+                            arguments: vec![
+                                SuiJsonValue::from_object_id(migration),
+                                SuiJsonValue::from_object_id(*coin),
+                            ],
+                        },
+                    )
+                })
+                .collect();
+            let tx = client
+                .transaction_builder()
+                .batch_transaction(owner, calls, None, context.gas_budget())
+                .await?;
+
+            let tx = context.sign_transaction(&tx)?;
+            let resp = context.execute_transaction_may_fail(tx).await?;
+            if let SuiExecutionStatus::Failure { error } =
+                resp.effects.as_ref().unwrap().status()
+            {
+                anyhow::bail!(
+                    "Failed to migrate {} {coin_type} coin(s) in {}: {error}",
+                    batch.len(),
+                    resp.digest
+                );
+            }
+            context.note_toma_coins_created();
+
+            let amount: u128 =
+                batch.iter().map(|(_, balance)| u128::from(*balance)).sum();
+            info!(
+                "Migrated {} of {coin_type} in {}",
+                context.toma(amount),
+                resp.digest
+            );
+            migrated += amount;
+        }
+    }
+
+    println!("Migrated {} to TOMA", context.toma(migrated));
+
+    Ok(())
+}
+
+/// The newest migration for each older coin type.
+async fn load_migrations(
+    context: &Context,
+    toma_package: ObjectID,
+) -> Result<BTreeMap<String, ObjectID>> {
+    // the event type is named after the package version that introduced it
+    let package = load_package(context, toma_package).await?;
+    let origin = type_origin(
+        &package,
+        TOMA_COIN_MODULE_NAME,
+        MIGRATION_CREATED_EVENT_NAME,
+    )?;
+    let filter = EventFilter::MoveEventType(StructTag {
+        address: origin.into(),
+        module: FromStr::from_str(TOMA_COIN_MODULE_NAME)?,
+        name: FromStr::from_str(MIGRATION_CREATED_EVENT_NAME)?,
+        type_params: vec![],
+    });
+
+    let client = context.get_client().await?;
+    let mut migrations = BTreeMap::new();
+    let mut cursor = None;
+    loop {
+        let Page {
+            data,
+            has_next_page,
+            next_cursor,
+        } = client
+            .event_api()
+            .query_events(filter.clone(), cursor, None, true)
+            .await?;
+        for event in data {
+            let (Some(migration), Some(old_coin_type)) = (
+                event.parsed_json["migration"].as_str(),
+                event.parsed_json["old_coin_type"].as_str(),
+            ) else {
+                continue;
+            };
+            migrations
+                .entry(normalize(old_coin_type)?)
+                .or_insert(ObjectID::from_str(migration)?);
+        }
+        if !has_next_page {
+            break;
+        }
+        cursor = next_cursor;
+    }
+
+    Ok(migrations)
+}
+
+async fn load_reserve(context: &Context, migration: ObjectID) -> Result<u64> {
+    let data = context
+        .get_client()
+        .await?
+        .read_api()
+        .get_object_with_options(
+            migration,
+            SuiObjectDataOptions {
+                show_content: true,
+                ..Default::default()
+            },
+        )
+        .await?
+        .data
+        .ok_or_else(|| anyhow!("Migration {migration} not found"))?;
+    let Some(SuiParsedData::MoveObject(migration)) = data.content else {
+        anyhow::bail!("Migration has no content");
+    };
+
+    // balances are usually flattened into their value
+    let fields = migration.fields.to_json_value();
+    parse_u64(&fields["reserve"])
+        .or_else(|_| parse_u64(&fields["reserve"]["value"]))
+}
@@ -0,0 +1,125 @@
+//! zkLogin lets end users sign with an address derived from their OAuth
+//! login instead of a seed phrase, eg. when a gateway submits prompts and
+//! buys stacks on their behalf.
+//!
+//! The gateway runs the OAuth flow with a nonce of the ephemeral key and
+//! gets the proof from a prover service, and the CLI signs with:
+//! - `ZKLOGIN_EPHEMERAL_KEY`, the base64 flagged private key of the
+//!   ephemeral keypair
+//! - `ZKLOGIN_INPUTS_FILE`, the JSON the prover returned with the
+//!   `addressSeed` of the user added to it
+//! - `ZKLOGIN_MAX_EPOCH`, the epoch the ephemeral key was committed to in
+//!   the nonce, after which the proof is no longer valid
+//!
+//! Prompt submission and stack acquisition are sent from the zkLogin
+//! address then, which must own the gas and TOMA coins.
+
+use std::fmt;
+
+use fastcrypto_zkp::bn254::zk_login::ZkLoginInputs;
+use shared_crypto::intent::{Intent, IntentMessage};
+use sui_sdk::types::{
+    base_types::SuiAddress,
+    crypto::{EncodeDecodeBase64, Signature, SuiKeyPair},
+    signature::GenericSignature,
+    transaction::{Transaction, TransactionData},
+    zk_login_authenticator::ZkLoginAuthenticator,
+};
+
+use crate::prelude::*;
+
+pub(crate) const ZKLOGIN_EPHEMERAL_KEY: &str = "ZKLOGIN_EPHEMERAL_KEY";
+pub(crate) const ZKLOGIN_INPUTS_FILE: &str = "ZKLOGIN_INPUTS_FILE";
+pub(crate) const ZKLOGIN_MAX_EPOCH: &str = "ZKLOGIN_MAX_EPOCH";
+const ADDRESS_SEED_FIELD: &str = "addressSeed";
+
+pub(crate) struct ZkLoginConf {
+    ephemeral_key: SuiKeyPair,
+    inputs: ZkLoginInputs,
+    max_epoch: u64,
+    address: SuiAddress,
+}
+
+impl ZkLoginConf {
+    /// Either all of the `ZKLOGIN_*` env vars are set or none.
+    pub(crate) fn from_env() -> Option<Self> {
+        let var =
+            |name: &str| std::env::var(name).ok().filter(|s| !s.is_empty());
+        match (
+            var(ZKLOGIN_EPHEMERAL_KEY),
+            var(ZKLOGIN_INPUTS_FILE),
+            var(ZKLOGIN_MAX_EPOCH),
+        ) {
+            (None, None, None) => None,
+            (Some(ephemeral_key), Some(inputs_file), Some(max_epoch)) => Some(
+                Self::new(&ephemeral_key, &inputs_file, &max_epoch)
+                    .unwrap_or_else(|err| panic!("Invalid zkLogin env: {err}")),
+            ),
+            _ => panic!(
+                "Set all of {ZKLOGIN_EPHEMERAL_KEY}, {ZKLOGIN_INPUTS_FILE} \
+                and {ZKLOGIN_MAX_EPOCH} to sign with zkLogin"
+            ),
+        }
+    }
+
+    fn new(
+        ephemeral_key: &str,
+        inputs_file: &str,
+        max_epoch: &str,
+    ) -> Result<Self> {
+        let ephemeral_key = SuiKeyPair::decode_base64(ephemeral_key)
+            .map_err(|err| anyhow!("Invalid ephemeral key: {err}"))?;
+
+        let mut inputs: serde_json::Value =
+            serde_json::from_slice(&std::fs::read(inputs_file)?)?;
+        let address_seed = inputs
+            .as_object_mut()
+            .and_then(|inputs| inputs.remove(ADDRESS_SEED_FIELD))
+            .and_then(|seed| seed.as_str().map(String::from))
+            .ok_or_else(|| {
+                anyhow!("{inputs_file} has no {ADDRESS_SEED_FIELD}")
+            })?;
+        let inputs =
+            ZkLoginInputs::from_json(&inputs.to_string(), &address_seed)
+                .map_err(|err| anyhow!("Invalid zkLogin inputs: {err}"))?;
+        let address = SuiAddress::try_from_unpadded(&inputs)?;
+
+        Ok(Self {
+            ephemeral_key,
+            inputs,
+            max_epoch: max_epoch.parse()?,
+            address,
+        })
+    }
+
+    pub(crate) fn address(&self) -> SuiAddress {
+        self.address
+    }
+
+    /// The ephemeral key signs the transaction and the proof ties it to the
+    /// zkLogin address.
+    pub(crate) fn sign_transaction(&self, tx: &TransactionData) -> Transaction {
+        let message = IntentMessage::new(Intent::sui_transaction(), tx.clone());
+        let signature = Signature::new_secure(&message, &self.ephemeral_key);
+        let authenticator = ZkLoginAuthenticator::new(
+            self.inputs.clone(),
+            self.max_epoch,
+            signature,
+        );
+
+        Transaction::from_generic_sig_data(
+            tx.clone(),
+            vec![GenericSignature::ZkLoginAuthenticator(authenticator)],
+        )
+    }
+}
+
+/// Keeps the ephemeral key out of the logs.
+impl fmt::Debug for ZkLoginConf {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ZkLoginConf")
+            .field("address", &self.address)
+            .field("max_epoch", &self.max_epoch)
+            .finish_non_exhaustive()
+    }
+}
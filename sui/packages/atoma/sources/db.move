@@ -4,9 +4,12 @@ module atoma::db {
 
     use atoma::atoma::ATOMA;
     use std::ascii;
+    use std::string;
     use sui::balance::{Self, Balance};
+    use sui::clock::Clock;
     use sui::coin::{Self, Coin};
     use sui::dynamic_field;
+    use sui::groth16;
     use sui::object_table::{Self, ObjectTable};
     use sui::package::{Self, Publisher};
     use sui::table_vec::{Self, TableVec};
@@ -14,9 +17,6 @@ module atoma::db {
     use sui::vec_set::{Self, VecSet};
     use usdc::usdc::USDC;
 
-    /// How many epochs after the stack expires during which any disputes must be resolved.
-    const STACK_DISPUTE_SETTLEMENT_DELAY: u64 = 1;
-
     /// Number of bytes per hash commitment
     const BYTES_PER_HASH_COMMITMENT: u64 = 32;
 
@@ -26,6 +26,26 @@ module atoma::db {
     /// One million compute units
     const ONE_MILLION_COMPUTE_UNITS: u64 = 1_000_000;
 
+    /// Prefix of the message that's hashed to identify a price quote.
+    /// Must match the CLI implementation.
+    const QUOTE_DOMAIN: vector<u8> = b"atoma-quote";
+    /// Prefix of the message that's hashed to identify a stack offer.
+    /// Must match the CLI implementation.
+    const OFFER_DOMAIN: vector<u8> = b"atoma-offer";
+    /// Offers are signed with an Ed25519 key that the node registers.
+    const Ed25519PublicKeyLength: u64 = 32;
+    /// Referrers can get at most this share of the fee that the node earns
+    /// for a stack, in basis points.
+    const MaxReferralFeeShareBps: u64 = 2_000;
+
+    /// Curves of the Groth16 verifying keys that tasks can register for
+    /// settling stacks with a succinct proof.
+    const ZkCurveBn254: u8 = 0;
+    const ZkCurveBls12381: u8 = 1;
+    /// Each public input of the settlement proof is a scalar encoded as 32
+    /// little-endian bytes.
+    const BYTES_PER_ZK_SCALAR: u64 = 32;
+
     #[allow(unused)]
     const INTEL_CPU: u16 = 0;
 
@@ -70,6 +90,31 @@ module atoma::db {
     /// settle without them.
     /// This is the initial value and can change.
     const InitialSettlementTimeoutMs: u64 = 60_000;
+    /// Bounds of the settlement timeout the manager can set for an echelon.
+    /// Shorter timeouts would slash nodes for network latency, longer ones
+    /// would lock the user's funds for too long.
+    const MinSettlementTimeoutMs: u64 = 1_000;
+    const MaxSettlementTimeoutMs: u64 = 3_600_000;
+    /// Regions are short labels such as `eu-west`.
+    const MaxNodeRegionLength: u64 = 32;
+    /// Keeps the gas of paying out a node bounded.
+    const MaxPayoutSplitRecipients: u64 = 16;
+    /// Severities of announcements, see `post_announcement`.
+    const AnnouncementInfo: u8 = 0;
+    const AnnouncementWarning: u8 = 1;
+    const AnnouncementCritical: u8 = 2;
+    /// In bytes, announcements are notices rather than documents.
+    const MaxAnnouncementLength: u64 = 1_024;
+    /// Modalities defined by the protocol, the first two as per the gate
+    /// module. Clients rely on these values so they must never change.
+    /// Values below `FirstCustomModality` that aren't listed are reserved for
+    /// future protocol modalities and rejected.
+    const Text2TextModality: u64 = 0;
+    const Text2ImageModality: u64 = 1;
+    const TextEmbeddingModality: u64 = 2;
+    const AudioModality: u64 = 3;
+    /// Modalities from this value on are free for deployments to define.
+    const FirstCustomModality: u64 = 1_000;
     /// If a node does not respond to a prompt within the timeout, it is slashed
     /// by this ‰ amount.
     const InitialPermilleToSlashNodeOnTimeout: u64 = 100;
@@ -93,9 +138,48 @@ module atoma::db {
     /// attestation of the stack settlement ticket
     const InitialSamplingConsensusChargePermille: u64 = 1200; // Additional 20% charge
 
+    /// Nodes that did not send a heartbeat for more than this many epochs
+    /// can be swept from the tasks they are subscribed to.
+    const InitialStaleNodeThresholdEpochs: u64 = 2;
+    /// Paid from the communal treasury to whoever sweeps a stale node, so
+    /// that it pays off to keep the subscriptions healthy.
+    const InitialStaleNodeSweepReward: u64 = 1_000;
+    /// Stacks claimed with `claim_funds_for_stacks` can be collected this
+    /// many epochs after the claim.
+    const InitialStackGcMinAgeEpochs: u64 = 2;
+    /// Paid from the communal treasury for every collected stack, so that
+    /// it pays off to keep the stacks table small.
+    const InitialStackGcBounty: u64 = 100;
+
+    /// How many epochs after a stack is settled during which attestation
+    /// nodes can dispute it, before the node can claim the funds.
+    const InitialStackDisputeWindowEpochs: u64 = 1;
+
+    /// ‰ of an insured node's fee for a stack that goes to the insurance
+    /// pool.
+    const InitialInsurancePremiumPermille: u64 = 10; // 1%
+    /// ‰ of an approved claim that the insurance pool pays out.
+    const InitialInsurancePayoutPermille: u64 = 1000;
+    /// Most the insurance pool pays out for a single claim.
+    const InitialInsuranceMaxPayoutPerClaim: u64 = 1_000_000_000;
+
     /// Start value for reputation scores. It is the same
     /// for every node, in the initial state.
     const REPUTATION_SCORE_START: u8 = 50;
+    const REPUTATION_SCORE_MAX: u8 = 100;
+    /// Reputation scores move one point towards `REPUTATION_SCORE_START`
+    /// every this many epochs, so that neither good nor bad past behavior
+    /// counts forever.
+    const REPUTATION_DECAY_EPOCHS_PER_POINT: u64 = 7;
+    /// Nodes earn a reputation point for every this many consecutive
+    /// settlements without a dispute.
+    const REPUTATION_RECOVERY_STREAK: u64 = 10;
+    /// Reputation points a node loses when it's slashed for not responding
+    /// in time.
+    const REPUTATION_TIMEOUT_PENALTY: u8 = 2;
+    /// Reputation points a node loses when it's slashed for results that
+    /// the oracle disagreed with.
+    const REPUTATION_DISPUTE_PENALTY: u8 = 10;
 
     /// To be able to identify the errors faster in the logs, we start the
     /// counter from a number that's leet for "error_000".
@@ -163,6 +247,47 @@ module atoma::db {
     const EInvalidNumClaimedComputeUnitsPerStack: u64 = EBase + 52;
     const EStackAlreadyClaimed: u64 = EBase + 53;
     const ENodeNotSelectedForClaim: u64 = EBase + 54;
+    const EExceedsTaskMaxComputeUnits: u64 = EBase + 55;
+    const EModalityNotAllowedForTask: u64 = EBase + 56;
+    const EQuoteExpired: u64 = EBase + 57;
+    const EReferralFeeShareTooHigh: u64 = EBase + 58;
+    const ENoReferralFeesToClaim: u64 = EBase + 59;
+    const EInvalidZkCurve: u64 = EBase + 60;
+    const ETaskHasNoZkVerifier: u64 = EBase + 61;
+    const ETaskRequiresZkSettlementProof: u64 = EBase + 62;
+    const EInvalidZkSettlementProof: u64 = EBase + 63;
+    const EInvalidCrossValidationProbability: u64 = EBase + 64;
+    const EInvalidCrossValidationExtraNodesCount: u64 = EBase + 65;
+    const EInvalidSettlementTimeout: u64 = EBase + 66;
+    const EInvalidModality: u64 = EBase + 67;
+    const ENodeNotStale: u64 = EBase + 68;
+    const EInvalidStaleNodeThreshold: u64 = EBase + 69;
+    const ENodeAlreadyInsured: u64 = EBase + 71;
+    const ENodeNotInsured: u64 = EBase + 72;
+    const ENotStackOwner: u64 = EBase + 73;
+    const EInsuranceClaimAlreadyFiled: u64 = EBase + 74;
+    const EInsuranceClaimNotFound: u64 = EBase + 75;
+    const EInvalidInsuranceClaimAmount: u64 = EBase + 76;
+    const EInvalidInsuranceParams: u64 = EBase + 77;
+    const EInvalidStackDisputeWindow: u64 = EBase + 78;
+    const ENodeNotOracle: u64 = EBase + 79;
+    const EInvalidNodeRegion: u64 = EBase + 80;
+    const EOfferExpired: u64 = EBase + 81;
+    const EOfferAlreadyConsumed: u64 = EBase + 82;
+    const ENodeHasNoOfferKey: u64 = EBase + 83;
+    const EInvalidOfferSignature: u64 = EBase + 84;
+    const EInvalidOfferKey: u64 = EBase + 85;
+    const EStackCloseNotProposed: u64 = EBase + 86;
+    const EStackCloseTermsMismatch: u64 = EBase + 87;
+    const EInvalidStackGcMinAge: u64 = EBase + 88;
+    const ENoStacksToCollect: u64 = EBase + 89;
+    const EInvalidPayoutSplit: u64 = EBase + 90;
+    const EPriceBelowTaskMinimum: u64 = EBase + 91;
+    const EInvalidAnnouncement: u64 = EBase + 92;
+    const EAnnouncementNotFound: u64 = EBase + 93;
+    const EInvalidQuoteSignature: u64 = EBase + 94;
+    const ENodeAlreadySwept: u64 = EBase + 95;
+    const ENodeBelowTaskMinVersion: u64 = EBase + 96;
     /// Emitted once upon publishing.
     public struct PublishedEvent has copy, drop {
         /// ID of the AtomaDb object
@@ -198,12 +323,163 @@ module atoma::db {
         evidence_bytes: vector<u8>,
     }
 
+    /// Emitted when the manager changes the settlement timeout of an echelon
+    /// with `set_model_echelon_settlement_timeout_ms`.
+    public struct ModelEchelonSettlementTimeoutUpdatedEvent has copy, drop {
+        model_name: ascii::String,
+        echelon_id: EchelonId,
+        old_timeout_ms: u64,
+        new_timeout_ms: u64,
+    }
+
+    /// Emitted when the manager changes the fees of an echelon with
+    /// `set_model_echelon_fee`.
+    public struct ModelEchelonFeeUpdatedEvent has copy, drop {
+        model_name: ascii::String,
+        echelon_id: EchelonId,
+        old_input_fee_per_token: u64,
+        old_output_fee_per_token: u64,
+        new_input_fee_per_token: u64,
+        new_output_fee_per_token: u64,
+    }
+
     public struct NodeSubscribedToModelEvent has copy, drop {
         node_small_id: NodeSmallId,
         model_name: ascii::String,
         echelon_id: EchelonId,
     }
 
+    /// Emitted when a node earns a reputation point for a streak of
+    /// settlements without a dispute.
+    public struct NodeReputationRecoveredEvent has copy, drop {
+        node_small_id: NodeSmallId,
+        reputation_score: ReputationScore,
+        dispute_free_settlements_streak: u64,
+    }
+
+    /// Emitted when a node loses reputation points for being slashed.
+    public struct NodeReputationPenalizedEvent has copy, drop {
+        node_small_id: NodeSmallId,
+        /// The score after the penalty.
+        reputation_score: ReputationScore,
+        penalty: u8,
+    }
+
+    /// Emitted when a stale node is removed from a task's subscriptions.
+    public struct StaleNodeSweptEvent has copy, drop {
+        node_small_id: NodeSmallId,
+        task_small_id: TaskSmallId,
+        last_heartbeat_epoch: u64,
+        sweeper: address,
+        reward: u64,
+    }
+
+    /// Emitted when claimed stacks are removed by `collect_claimed_stacks`.
+    public struct ClaimedStacksCollectedEvent has copy, drop {
+        stack_small_ids: vector<StackSmallId>,
+        collector: address,
+        bounty: u64,
+    }
+
+
+    public struct NodeJoinedInsurancePoolEvent has copy, drop {
+        node_small_id: NodeSmallId,
+    }
+
+    public struct NodeLeftInsurancePoolEvent has copy, drop {
+        node_small_id: NodeSmallId,
+    }
+
+    public struct InsuranceClaimFiledEvent has copy, drop {
+        stack_small_id: StackSmallId,
+        claimant: address,
+        node_small_id: NodeSmallId,
+        amount: u64,
+    }
+
+    /// Emitted when the manager approves or rejects an insurance claim.
+    /// The payout of an approved claim can be lower than the claimed
+    /// amount, see `pay_insurance_claim`.
+    public struct InsuranceClaimResolvedEvent has copy, drop {
+        stack_small_id: StackSmallId,
+        claimant: address,
+        node_small_id: NodeSmallId,
+        is_approved: bool,
+        payout: u64,
+    }
+
+    /// Emitted when a node reports the version of the software it runs.
+    public struct NodeSoftwareVersionUpdatedEvent has copy, drop {
+        node_small_id: NodeSmallId,
+        version: SoftwareVersion,
+    }
+
+    /// Emitted when the manager changes the minimum version of the software
+    /// that nodes must run to be sampled for the stacks of a task.
+    /// A version of 0.0.0 means there is no minimum.
+    public struct TaskMinNodeVersionUpdatedEvent has copy, drop {
+        task_small_id: TaskSmallId,
+        min_version: SoftwareVersion,
+    }
+
+    /// Emitted when the manager changes the minimum price that nodes must
+    /// ask to subscribe to a task.
+    /// A price of 0 means there is no minimum.
+    public struct TaskMinPriceUpdatedEvent has copy, drop {
+        task_small_id: TaskSmallId,
+        min_price_per_one_million_compute_units: u64,
+    }
+
+    /// Emitted when a node changes how its fees are split among the members
+    /// of its cooperative.
+    /// Empty vectors mean that the node is paid alone again.
+    public struct NodePayoutSplitUpdatedEvent has copy, drop {
+        node_small_id: NodeSmallId,
+        recipients: vector<address>,
+        shares_bps: vector<u64>,
+    }
+
+    /// Emitted when the manager posts a notice for nodes and gateways.
+    public struct AnnouncementPostedEvent has copy, drop {
+        announcement_id: u64,
+        severity: u8,
+        message: string::String,
+        effective_epoch: u64,
+    }
+
+    /// Emitted when the manager removes a notice, eg. once a maintenance is
+    /// over.
+    public struct AnnouncementRemovedEvent has copy, drop {
+        announcement_id: u64,
+    }
+
+    /// Emitted when a node reports the region it runs in.
+    public struct NodeRegionUpdatedEvent has copy, drop {
+        node_small_id: NodeSmallId,
+        region: ascii::String,
+    }
+
+    /// Emitted when an oracle records the latency that gateways measured for
+    /// an echelon.
+    public struct ModelEchelonLatencyRecordedEvent has copy, drop {
+        model_name: ascii::String,
+        echelon_id: EchelonId,
+        oracle_node_id: NodeSmallId,
+        latency_p50_ms: u64,
+    }
+
+    /// Emitted when a node registers the key it signs stack offers with.
+    public struct NodeOfferKeySetEvent has copy, drop {
+        node_small_id: NodeSmallId,
+        public_key: vector<u8>,
+    }
+
+    /// Emitted when a node withdraws an offer before it was accepted.
+    public struct StackOfferCancelledEvent has copy, drop {
+        node_small_id: NodeSmallId,
+        nonce: u64,
+    }
+
     public struct NodeSubscribedToTaskEvent has copy, drop {
         task_small_id: TaskSmallId,
         node_small_id: NodeSmallId,
@@ -233,6 +509,11 @@ module atoma::db {
         minimum_reputation_score: Option<u8>,
     }
 
+    public struct TaskConstraintsSetEvent has copy, drop {
+        task_small_id: TaskSmallId,
+        constraints: TaskConstraints,
+    }
+
     public struct TaskDeprecationEvent has copy, drop {
         /// ID of the Task object
         task_id: ID,
@@ -269,6 +550,86 @@ module atoma::db {
         price_per_one_million_compute_units: u64,
     }
 
+    /// The stack was acquired based on a price quote signed off-chain by a node.
+    public struct StackQuoteAcceptedEvent has copy, drop {
+        stack_small_id: StackSmallId,
+        /// The node that issued the quote and that the stack is assigned to.
+        quote_node_id: NodeSmallId,
+        /// Blake2b-256 hash of the quote, see `quote_hash`.
+        quote_hash: vector<u8>,
+    }
+
+    /// Emitted when a stack is acquired with an offer signed by the node
+    /// that the stack is assigned to.
+    public struct StackOfferAcceptedEvent has copy, drop {
+        stack_small_id: StackSmallId,
+        node_small_id: NodeSmallId,
+        nonce: u64,
+        /// Blake2b-256 hash of the offer, see `offer_hash`.
+        offer_hash: vector<u8>,
+    }
+
+    /// Either the owner or the node of a stack proposes to close it early,
+    /// see `propose_stack_close`.
+    public struct StackCloseProposedEvent has copy, drop {
+        stack_small_id: StackSmallId,
+        selected_node_id: NodeSmallId,
+        num_consumed_compute_units: u64,
+        proposed_by_node: bool,
+    }
+
+    /// The other party accepted the proposal and the stack was settled
+    /// without a settlement ticket.
+    public struct StackClosedEarlyEvent has copy, drop {
+        stack_small_id: StackSmallId,
+        selected_node_id: NodeSmallId,
+        /// Named like in the other claim events.
+        num_claimed_compute_units: u64,
+        /// Paid for the compute units that were not consumed.
+        user_refund_amount: u64,
+    }
+
+    public struct StackReferralEvent has copy, drop {
+        stack_small_id: StackSmallId,
+        /// Gets a share of the fee when the stack is claimed.
+        referrer: address,
+        fee_share_bps: u64,
+    }
+
+    public struct ReferralFeeAccruedEvent has copy, drop {
+        stack_small_id: StackSmallId,
+        referrer: address,
+        amount: u64,
+    }
+
+    /// Emitted when the manager changes the parameters of sampling consensus
+    /// with `set_sampling_consensus_params`.
+    public struct SamplingConsensusParamsUpdatedEvent has copy, drop {
+        cross_validation_probability_permille: u64,
+        cross_validation_extra_nodes_count: u64,
+        sampling_consensus_charge_permille: u64,
+        cross_validation_extra_nodes_charge_permille: u64,
+    }
+
+    public struct TaskZkVerifierSetEvent has copy, drop {
+        task_small_id: TaskSmallId,
+        /// Either 0 for BN254 or 1 for BLS12-381.
+        curve: u8,
+    }
+
+    public struct TaskZkVerifierRemovedEvent has copy, drop {
+        task_small_id: TaskSmallId,
+    }
+
+    /// Emitted along with `StackTrySettleEvent` when the settlement was
+    /// accepted based on a succinct proof.
+    public struct StackZkProofVerifiedEvent has copy, drop {
+        stack_small_id: StackSmallId,
+        selected_node_id: NodeSmallId,
+        num_claimed_compute_units: u64,
+        committed_stack_proof: vector<u8>,
+    }
+
     public struct StackTrySettleEvent has copy, drop {
         stack_small_id: StackSmallId,
         /// The node selected to process the requests in the stack
@@ -500,6 +861,72 @@ module atoma::db {
         inner: u16,
     }
 
+    /// Optional limits a task creator can put on a task to bound the worst-case
+    /// cost of a stack.
+    ///
+    /// Compute units of a stack are the sum of input and output tokens, so a
+    /// stack cannot acquire more compute units than the two limits combined.
+    public struct TaskConstraints has store, copy, drop {
+        /// Maximum number of input tokens a stack can be used for.
+        max_input_tokens: Option<u64>,
+        /// Maximum number of output tokens a stack can be used for.
+        max_output_tokens: Option<u64>,
+        /// If not empty, the task's model must have one of these modalities.
+        /// See the gate module for modality identifiers.
+        allowed_modalities: vector<u64>,
+    }
+
+    /// Stored in AtomaDb under `NodeReputationsKey` once the reputation of
+    /// a node changes, see `decayed_reputation_score`.
+    public struct NodeReputation has store, copy, drop {
+        /// The epoch from which the decay of the node's `reputation_score`
+        /// is counted.
+        updated_at_epoch: u64,
+        /// How many stacks the node settled in a row without a dispute.
+        dispute_free_settlements_streak: u64,
+    }
+
+    /// Stored in AtomaDb under `StaleNodeSweepsKey`, see `sweep_stale_node`.
+    public struct StaleNodeSweeps has store {
+        /// Nodes that did not send a heartbeat for more than this many epochs
+        /// are stale.
+        threshold_epochs: u64,
+        /// Paid from the communal treasury for the first sweep of a node
+        /// since its last heartbeat.
+        reward: u64,
+        /// Nodes that have not sent a heartbeat since the db was migrated
+        /// count as if they sent one in this epoch.
+        migrated_at_epoch: u64,
+        last_heartbeat_epochs: Table<NodeSmallId, u64>,
+        /// Who registered the node or sent its last heartbeat, ie. who held
+        /// its badge, since they are not rewarded for sweeping it.
+        heartbeat_senders: Table<NodeSmallId, address>,
+        /// The last heartbeat epoch of the node when it was swept from the
+        /// task, so that it's swept at most once per heartbeat.
+        swept: Table<SweptSubscription, u64>,
+        /// The last heartbeat epoch of the node when a sweep of it was last
+        /// rewarded, so that it's rewarded at most once per heartbeat rather
+        /// than once per task.
+        /// Kept after the node is destroyed.
+        rewarded: Table<NodeSmallId, u64>,
+    }
+
+    public struct SweptSubscription has copy, drop, store {
+        task_small_id: TaskSmallId,
+        node_small_id: NodeSmallId,
+    }
+
+    /// Stored in AtomaDb under `StackGcKey`, see `collect_claimed_stacks`.
+    public struct StackGc has store {
+        /// See `set_stack_gc_params`.
+        min_age_epochs: u64,
+        bounty: u64,
+        /// The epoch in which a stack was claimed with
+        /// `claim_funds_for_stacks`.
+        /// Stacks claimed before the db was migrated are not collected.
+        claimed_stack_epochs: Table<StackSmallId, u64>,
+    }
+
     /// Reputation score of a node
     public struct ReputationScore has store, copy, drop {
         inner: u8,
@@ -530,6 +957,110 @@ module atoma::db {
         is_claimed: bool,
     }
 
+    /// Semantic version of the node software, eg. 1.4.2.
+    public struct SoftwareVersion has store, copy, drop {
+        major: u16,
+        minor: u16,
+        patch: u16,
+    }
+
+    /// A member of a node cooperative and its share of the node's fees.
+    public struct PayoutShare has store, copy, drop {
+        recipient: address,
+        /// In basis points, the shares of a node add up to 10_000.
+        bps: u64,
+    }
+
+    /// A notice of the manager, see `post_announcement`.
+    public struct Announcement has store, copy, drop {
+        /// One of `AnnouncementInfo`, `AnnouncementWarning` or
+        /// `AnnouncementCritical`.
+        severity: u8,
+        message: string::String,
+        /// The epoch from which the notice applies, eg. when a maintenance
+        /// starts.
+        effective_epoch: u64,
+        posted_at_epoch: u64,
+    }
+
+    /// Stored in AtomaDb under `AnnouncementsKey`, see `post_announcement`.
+    public struct Announcements has store {
+        entries: Table<u64, Announcement>,
+        next_id: u64,
+    }
+
+    /// Stored in AtomaDb for stacks that were acquired through a referrer,
+    /// eg. a gateway that brought the user to the network.
+    public struct StackReferral has store, copy, drop {
+        referrer: address,
+        /// Share of the fee that the node earns for the stack that goes to
+        /// the referrer instead, in basis points.
+        fee_share_bps: u64,
+    }
+
+    /// Stored in AtomaDb under `ReferralsKey`.
+    public struct Referrals has store {
+        /// Stacks that were acquired through a referrer.
+        /// Removed once the stack is claimed.
+        stacks: Table<StackSmallId, StackReferral>,
+        /// Fees accrued by referrers that they have not claimed yet.
+        /// The funds are held in the fee treasury.
+        balances: Table<address, u64>,
+    }
+
+    /// Identifies an offer of a node, it can be accepted or cancelled once.
+    public struct OfferNonce has store, copy, drop {
+        node_small_id: NodeSmallId,
+        nonce: u64,
+    }
+
+    /// Stored in AtomaDb under `StackCloseProposalsKey` while a cooperative
+    /// close of a stack waits for the other party to accept it.
+    /// Removed with the stack.
+    public struct StackCloseProposal has store, drop {
+        num_consumed_compute_units: u64,
+        proposed_by_node: bool,
+    }
+
+    /// Stored in AtomaDb under `InsurancePoolKey`, see `join_insurance_pool`.
+    public struct InsurancePool has store {
+        /// Nodes that opted into the insurance pool.
+        members: Table<NodeSmallId, InsuranceMembership>,
+        /// Premiums paid by insured nodes, they cover the claims of users.
+        balance: Balance<USDC>,
+        /// At most one claim per stack.
+        claims: Table<StackSmallId, InsuranceClaim>,
+        /// See `set_insurance_params`.
+        premium_permille: u64,
+        payout_permille: u64,
+        max_payout_per_claim: u64,
+    }
+
+    /// Stored in the insurance pool for nodes that opted into it.
+    public struct InsuranceMembership has store, drop {
+        joined_in_epoch: u64,
+        /// Total premiums the node paid into the pool.
+        premiums_paid: u64,
+    }
+
+    /// Stored in AtomaDb for every stack that its owner filed an insurance
+    /// claim for.
+    /// Kept once resolved so that a stack is never claimed twice.
+    public struct InsuranceClaim has store, drop {
+        claimant: address,
+        /// The insured node that was selected for the stack.
+        node_small_id: NodeSmallId,
+        amount: u64,
+        is_resolved: bool,
+    }
+
+    /// Stored in AtomaDb for tasks whose stacks are settled with a succinct
+    /// proof instead of attestations, see `try_settle_stack_with_zk_proof`.
+    public struct TaskZkVerifier has store, drop {
+        curve: groth16::Curve,
+        verifying_key: groth16::PreparedVerifyingKey,
+    }
+
     /// Represents a settlement ticket for a completed stack execution in the Atoma network.
     ///
     /// This struct encapsulates all the necessary information for verifying and finalizing
@@ -562,6 +1093,35 @@ module atoma::db {
         is_in_dispute: bool,
     }
 
+    /// Dynamic field keys of the state that was added to `AtomaDb` after it was
+    /// first published.
+    /// Upgrades can't change the layout of the struct, see `migrate`.
+    public struct TaskConstraintsKey has copy, drop, store {}
+    public struct ReferralsKey has copy, drop, store {}
+    public struct TaskZkVerifiersKey has copy, drop, store {}
+    public struct NodeReputationsKey has copy, drop, store {}
+    public struct StaleNodeSweepsKey has copy, drop, store {}
+    public struct InsurancePoolKey has copy, drop, store {}
+    public struct StackDisputeWindowEpochsKey has copy, drop, store {}
+    public struct NodeRegionsKey has copy, drop, store {}
+    public struct ModelEchelonLatenciesKey has copy, drop, store {}
+    public struct NodeOfferKeysKey has copy, drop, store {}
+    public struct ConsumedOffersKey has copy, drop, store {}
+    public struct StackCloseProposalsKey has copy, drop, store {}
+    public struct StackGcKey has copy, drop, store {}
+    public struct NodeSoftwareVersionsKey has copy, drop, store {}
+    public struct TaskMinNodeVersionsKey has copy, drop, store {}
+    public struct NodePayoutSplitsKey has copy, drop, store {}
+    public struct TaskMinPricesKey has copy, drop, store {}
+    public struct AnnouncementsKey has copy, drop, store {}
+
+    /// Identifies an echelon of a model in the table under
+    /// `ModelEchelonLatenciesKey`.
+    public struct ModelEchelonKey has copy, drop, store {
+        model_name: ascii::String,
+        echelon_id: EchelonId,
+    }
+
     /// Shared object.
     ///
     /// Database of the package.
@@ -747,6 +1307,8 @@ module atoma::db {
         /// Using a vector allows for a random access using an index.
         /// The order of nodes will not be preserved because we use
         /// `swap_remove` op on table vec.
+        /// Removing a node thus keeps the vector compact without changing
+        /// any node's small ID, which tickets keep referring to.
         nodes: TableVec<NodeSmallId>,
     }
 
@@ -762,7 +1324,7 @@ module atoma::db {
 
     #[allow(unused_function)]
     fun init(ctx: &mut TxContext) {
-        let atoma_db = AtomaDb {
+        let mut atoma_db = AtomaDb {
             id: object::new(ctx),
             tickets: object::new(ctx),
             nodes: table::new(ctx),
@@ -795,6 +1357,7 @@ module atoma::db {
             key_rotation_counter: 0,
             nonce: ctx.epoch(), // TODO: change to a random number, but for now we use the epoch as a good proxy
         };
+        add_missing_fields(&mut atoma_db, ctx);
 
         // Create a manager badge for the package owner for convenience.
         // More can be created later.
@@ -811,6 +1374,125 @@ module atoma::db {
         transfer::transfer(atoma_manager_badge, ctx.sender());
     }
 
+    /// Adds the state that was introduced after the db was published as
+    /// dynamic fields.
+    /// The manager calls this once after upgrading the package, the features
+    /// that need the state abort until then.
+    /// Calling it again is a no-op.
+    public entry fun migrate(
+        self: &mut AtomaDb,
+        _: &AtomaManagerBadge,
+        ctx: &mut TxContext,
+    ) {
+        add_missing_fields(self, ctx);
+    }
+
+    fun add_missing_fields(self: &mut AtomaDb, ctx: &mut TxContext) {
+        if (!dynamic_field::exists_(&self.id, TaskConstraintsKey {})) {
+            let constraints: Table<TaskSmallId, TaskConstraints> = table::new(ctx);
+            dynamic_field::add(&mut self.id, TaskConstraintsKey {}, constraints);
+        };
+        if (!dynamic_field::exists_(&self.id, ReferralsKey {})) {
+            let referrals = Referrals {
+                stacks: table::new(ctx),
+                balances: table::new(ctx),
+            };
+            dynamic_field::add(&mut self.id, ReferralsKey {}, referrals);
+        };
+        if (!dynamic_field::exists_(&self.id, TaskZkVerifiersKey {})) {
+            let verifiers: Table<TaskSmallId, TaskZkVerifier> = table::new(ctx);
+            dynamic_field::add(&mut self.id, TaskZkVerifiersKey {}, verifiers);
+        };
+        if (!dynamic_field::exists_(&self.id, NodeReputationsKey {})) {
+            let reputations: Table<NodeSmallId, NodeReputation> = table::new(ctx);
+            dynamic_field::add(&mut self.id, NodeReputationsKey {}, reputations);
+        };
+        if (!dynamic_field::exists_(&self.id, StaleNodeSweepsKey {})) {
+            let sweeps = StaleNodeSweeps {
+                threshold_epochs: InitialStaleNodeThresholdEpochs,
+                reward: InitialStaleNodeSweepReward,
+                migrated_at_epoch: ctx.epoch(),
+                last_heartbeat_epochs: table::new(ctx),
+                heartbeat_senders: table::new(ctx),
+                swept: table::new(ctx),
+                rewarded: table::new(ctx),
+            };
+            dynamic_field::add(&mut self.id, StaleNodeSweepsKey {}, sweeps);
+        };
+        if (!dynamic_field::exists_(&self.id, InsurancePoolKey {})) {
+            let pool = InsurancePool {
+                members: table::new(ctx),
+                balance: balance::zero(),
+                claims: table::new(ctx),
+                premium_permille: InitialInsurancePremiumPermille,
+                payout_permille: InitialInsurancePayoutPermille,
+                max_payout_per_claim: InitialInsuranceMaxPayoutPerClaim,
+            };
+            dynamic_field::add(&mut self.id, InsurancePoolKey {}, pool);
+        };
+        if (!dynamic_field::exists_(&self.id, StackDisputeWindowEpochsKey {})) {
+            dynamic_field::add(
+                &mut self.id,
+                StackDisputeWindowEpochsKey {},
+                InitialStackDisputeWindowEpochs,
+            );
+        };
+        if (!dynamic_field::exists_(&self.id, NodeRegionsKey {})) {
+            let regions: Table<NodeSmallId, ascii::String> = table::new(ctx);
+            dynamic_field::add(&mut self.id, NodeRegionsKey {}, regions);
+        };
+        if (!dynamic_field::exists_(&self.id, ModelEchelonLatenciesKey {})) {
+            let latencies: Table<ModelEchelonKey, u64> = table::new(ctx);
+            dynamic_field::add(&mut self.id, ModelEchelonLatenciesKey {}, latencies);
+        };
+        if (!dynamic_field::exists_(&self.id, NodeOfferKeysKey {})) {
+            // Ed25519 public keys that nodes sign quotes and offers with
+            let keys: Table<NodeSmallId, vector<u8>> = table::new(ctx);
+            dynamic_field::add(&mut self.id, NodeOfferKeysKey {}, keys);
+        };
+        if (!dynamic_field::exists_(&self.id, ConsumedOffersKey {})) {
+            // offers that were accepted or cancelled, so that none is used
+            // twice
+            let offers: Table<OfferNonce, bool> = table::new(ctx);
+            dynamic_field::add(&mut self.id, ConsumedOffersKey {}, offers);
+        };
+        if (!dynamic_field::exists_(&self.id, StackCloseProposalsKey {})) {
+            let proposals: Table<StackSmallId, StackCloseProposal> = table::new(ctx);
+            dynamic_field::add(&mut self.id, StackCloseProposalsKey {}, proposals);
+        };
+        if (!dynamic_field::exists_(&self.id, StackGcKey {})) {
+            let gc = StackGc {
+                min_age_epochs: InitialStackGcMinAgeEpochs,
+                bounty: InitialStackGcBounty,
+                claimed_stack_epochs: table::new(ctx),
+            };
+            dynamic_field::add(&mut self.id, StackGcKey {}, gc);
+        };
+        if (!dynamic_field::exists_(&self.id, NodeSoftwareVersionsKey {})) {
+            let versions: Table<NodeSmallId, SoftwareVersion> = table::new(ctx);
+            dynamic_field::add(&mut self.id, NodeSoftwareVersionsKey {}, versions);
+        };
+        if (!dynamic_field::exists_(&self.id, TaskMinNodeVersionsKey {})) {
+            let min_versions: Table<TaskSmallId, SoftwareVersion> = table::new(ctx);
+            dynamic_field::add(&mut self.id, TaskMinNodeVersionsKey {}, min_versions);
+        };
+        if (!dynamic_field::exists_(&self.id, NodePayoutSplitsKey {})) {
+            let splits: Table<NodeSmallId, vector<PayoutShare>> = table::new(ctx);
+            dynamic_field::add(&mut self.id, NodePayoutSplitsKey {}, splits);
+        };
+        if (!dynamic_field::exists_(&self.id, TaskMinPricesKey {})) {
+            let min_prices: Table<TaskSmallId, u64> = table::new(ctx);
+            dynamic_field::add(&mut self.id, TaskMinPricesKey {}, min_prices);
+        };
+        if (!dynamic_field::exists_(&self.id, AnnouncementsKey {})) {
+            let announcements = Announcements {
+                entries: table::new(ctx),
+                next_id: 1,
+            };
+            dynamic_field::add(&mut self.id, AnnouncementsKey {}, announcements);
+        };
+    }
+
     /// Takes collateral from the sender's wallet and transfers them the node
     /// badge.
     public entry fun register_node_entry(
@@ -836,6 +1518,7 @@ module atoma::db {
     /// * `efficiency_time_units` - An optional u16 representing the time units for efficiency metrics.
     /// * `efficiency_value` - An optional u64 representing the value for efficiency metrics.
     /// * `minimum_reputation_score` - An optional u8 representing the minimum reputation score required for a node to subscribe to the task.
+    /// * `is_public` - Whether any node can subscribe to the task or only whitelisted ones.
     /// * `ctx` - A mutable reference to the transaction context.
     ///
     /// # Effects
@@ -868,6 +1551,47 @@ module atoma::db {
         transfer::transfer(badge, ctx.sender());
     }
 
+    /// Like `create_task_entry`, but bounds what a single stack for the task
+    /// can consume.
+    ///
+    /// # Arguments
+    /// * `max_input_tokens` - An optional u64 bounding the input tokens a stack for this task can be used for.
+    /// * `max_output_tokens` - An optional u64 bounding the output tokens a stack for this task can be used for.
+    /// * `allowed_modalities` - Modalities the task's model may have, any if empty.
+    ///
+    /// # Events
+    /// Emits a TaskRegisteredEvent and a TaskConstraintsSetEvent.
+    public entry fun create_task_with_constraints_entry(
+        self: &mut AtomaDb,
+        badge: &AtomaManagerBadge,
+        role: u16,
+        model_name: Option<ascii::String>,
+        security_level: Option<u16>,
+        minimum_reputation_score: Option<u8>,
+        is_public: bool,
+        max_input_tokens: Option<u64>,
+        max_output_tokens: Option<u64>,
+        allowed_modalities: vector<u64>,
+        ctx: &mut TxContext,
+    ) {
+        let badge = create_task_with_constraints(
+            self,
+            badge,
+            role,
+            model_name,
+            option::get_with_default(&security_level, NoSecurity),
+            minimum_reputation_score,
+            is_public,
+            new_task_constraints(
+                max_input_tokens,
+                max_output_tokens,
+                allowed_modalities,
+            ),
+            ctx,
+        );
+        transfer::transfer(badge, ctx.sender());
+    }
+
     /// Creates a new task in the Atoma network and returns a TaskBadge.
     ///
     /// # Arguments
@@ -877,6 +1601,7 @@ module atoma::db {
     /// * `model_name` - An optional ASCII string representing the model name.
     /// * `security_level` - An u16 representing the security level.
     /// * `minimum_reputation_score` - An optional u8 representing the minimum reputation score required for a node to subscribe to the task.
+    /// * `is_public` - Whether any node can subscribe to the task or only whitelisted ones.
     /// * `ctx` - A mutable reference to the transaction context.
     ///
     /// # Returns
@@ -939,6 +1664,63 @@ module atoma::db {
         task_badge
     }
 
+    /// Like `create_task`, but bounds what a single stack for the task can
+    /// consume.
+    /// The constraints are kept next to the task, see `TaskConstraintsKey`.
+    ///
+    /// # Errors
+    /// * `EModalityNotAllowedForTask` - If the task's model has a modality that's not among the allowed ones.
+    public fun create_task_with_constraints(
+        self: &mut AtomaDb,
+        badge: &AtomaManagerBadge,
+        role: u16,
+        model_name: Option<ascii::String>,
+        security_level: u16,
+        minimum_reputation_score: Option<u8>,
+        is_public: bool,
+        constraints: TaskConstraints,
+        ctx: &mut TxContext,
+    ): TaskBadge {
+        if (model_name.is_some() && !constraints.allowed_modalities.is_empty()) {
+            let modality = self.models.borrow(*model_name.borrow()).modality;
+            assert!(constraints.allowed_modalities.contains(&modality), EModalityNotAllowedForTask);
+        };
+
+        let task_badge = create_task(
+            self,
+            badge,
+            role,
+            model_name,
+            security_level,
+            minimum_reputation_score,
+            is_public,
+            ctx,
+        );
+        let task_small_id = task_badge.small_id;
+        let all_constraints: &mut Table<TaskSmallId, TaskConstraints> =
+            dynamic_field::borrow_mut(&mut self.id, TaskConstraintsKey {});
+        all_constraints.add(task_small_id, constraints);
+
+        sui::event::emit(TaskConstraintsSetEvent {
+            task_small_id,
+            constraints,
+        });
+
+        task_badge
+    }
+
+    public fun new_task_constraints(
+        max_input_tokens: Option<u64>,
+        max_output_tokens: Option<u64>,
+        allowed_modalities: vector<u64>,
+    ): TaskConstraints {
+        TaskConstraints {
+            max_input_tokens,
+            max_output_tokens,
+            allowed_modalities,
+        }
+    }
+
     /// Deprecates a task in the Atoma network.
     ///
     /// This function marks a task as deprecated, preventing it from being used for new computations.
@@ -1063,6 +1845,7 @@ module atoma::db {
         task_badge_id.delete();
         subscribed_nodes.drop();
         subscribed_nodes_small_ids.drop();
+        self.remove_task_constraints(task_small_id);
 
         // Emit an event for task removal
         sui::event::emit(TaskRemovedEvent {
@@ -1127,6 +1910,7 @@ module atoma::db {
             confidential_compute_device_types: vector::empty(),
         };
         self.nodes.add(small_id, node_entry);
+        self.record_heartbeat(small_id, ctx.sender(), ctx.epoch());
 
         let badge_id = object::new(ctx);
         sui::event::emit(NodeRegisteredEvent {
@@ -1216,6 +2000,10 @@ module atoma::db {
     /// This function allows a node to subscribe to a task, enabling it to participate in
     /// the execution of that task within the Atoma network.
     ///
+    /// The reputation score of the node is checked as of the epoch it last
+    /// changed in, see `subscribe_node_to_task_entry` to check it with decay
+    /// applied.
+    ///
     /// # Arguments
     /// * `self` - A mutable reference to the AtomaDb object.
     /// * `node_badge` - A mutable reference to the NodeBadge of the subscribing node.
@@ -1225,6 +2013,7 @@ module atoma::db {
     /// * `ETaskNotFound` - If the specified task does not exist in the AtomaDb.
     /// * `ETaskDeprecated` - If the specified task has been deprecated.
     /// * `ENodeAlreadySubscribedToTask` - If the node is already subscribed to the task.
+    /// * `EPriceBelowTaskMinimum` - If the price is below the minimum of the task, see `set_task_min_price`.
     ///
     /// # Events
     /// Emits a `NodeSubscribedToTaskEvent` upon successful subscription.
@@ -1234,18 +2023,52 @@ module atoma::db {
         task_small_id: u64,
         price_per_one_million_compute_units: u64,
     ) {
-        let task_small_id = TaskSmallId { inner: task_small_id };
-
-        // Check if the task exists
-        assert!(self.tasks.contains(task_small_id), ETaskNotFound);
-        // Check if the node's reputation score meets the task's minimum requirement
-        {
-            let task = self.tasks.borrow(task_small_id);
-            assert!(node_meets_task_requirements(self, node_badge, task), ENodeDoesNotMeetTaskRequirements);
-        };
+        let reputation_score = self.nodes.borrow(node_badge.small_id).reputation_score.inner;
+        self.subscribe_node_to_task_with_reputation(
+            node_badge,
+            task_small_id,
+            price_per_one_million_compute_units,
+            reputation_score,
+        );
+    }
 
-        // Check if the task is public
-        let task = self.tasks.borrow_mut(task_small_id);
+    /// Same as `subscribe_node_to_task`, but the reputation score of the
+    /// node is checked with decay applied, see `get_node_reputation_score`.
+    entry fun subscribe_node_to_task_entry(
+        self: &mut AtomaDb,
+        node_badge: &mut NodeBadge,
+        task_small_id: u64,
+        price_per_one_million_compute_units: u64,
+        ctx: &TxContext,
+    ) {
+        let reputation_score = self.decayed_reputation_score(node_badge.small_id, ctx.epoch());
+        self.subscribe_node_to_task_with_reputation(
+            node_badge,
+            task_small_id,
+            price_per_one_million_compute_units,
+            reputation_score,
+        );
+    }
+
+    fun subscribe_node_to_task_with_reputation(
+        self: &mut AtomaDb,
+        node_badge: &mut NodeBadge,
+        task_small_id: u64,
+        price_per_one_million_compute_units: u64,
+        reputation_score: u8,
+    ) {
+        let task_small_id = TaskSmallId { inner: task_small_id };
+
+        // Check if the task exists
+        assert!(self.tasks.contains(task_small_id), ETaskNotFound);
+        // Check if the node's reputation score meets the task's minimum requirement
+        {
+            let task = self.tasks.borrow(task_small_id);
+            assert!(node_meets_task_requirements(task, reputation_score), ENodeDoesNotMeetTaskRequirements);
+        };
+
+        // Check if the task is public
+        let task = self.tasks.borrow_mut(task_small_id);
         if (!task.is_public) {
             assert!(
                 vector::contains(&task.whitelisted_nodes, &node_badge.small_id),
@@ -1255,6 +2078,14 @@ module atoma::db {
         // Check if the task is not deprecated
         assert!(!task.is_deprecated, ETaskDeprecated);
 
+        // The badge still remembers a subscription that was swept
+        if (
+            dynamic_field::exists_(&node_badge.id, task_small_id)
+                && !task.subscribed_nodes.contains(node_badge.small_id)
+        ) {
+            let _: bool = dynamic_field::remove(&mut node_badge.id, task_small_id);
+        };
+
         // Check if the node is already subscribed to the task
         assert!(
             !dynamic_field::exists_(&node_badge.id, task_small_id),
@@ -1263,6 +2094,10 @@ module atoma::db {
         
         // Validate price_per_one_million_compute_units and max_num_compute_units
         assert!(price_per_one_million_compute_units > 0, EInvalidPricePerComputeUnit);
+        assert!(
+            price_per_one_million_compute_units >= task_min_price(&self.id, task_small_id),
+            EPriceBelowTaskMinimum,
+        );
 
         // Add the node to the task's subscribed_nodes list
         let node_price_data = NodePriceData {
@@ -1300,6 +2135,7 @@ module atoma::db {
     /// # Errors
     /// * `ETaskNotFound` - If the specified task does not exist in the AtomaDb.
     /// * `ENodeNotSubscribedToTask` - If the node is not currently subscribed to the specified task.
+    /// * `EPriceBelowTaskMinimum` - If the new price is below the minimum of the task, see `set_task_min_price`.
     ///
     /// # Effects
     /// - Updates the node's price per one million compute units and maximum number of compute units for the specified task.
@@ -1336,6 +2172,10 @@ module atoma::db {
 
         // Check if the task exists
         assert!(self.tasks.contains(task_small_id), ETaskNotFound);
+        assert!(
+            price_per_one_million_compute_units >= task_min_price(&self.id, task_small_id),
+            EPriceBelowTaskMinimum,
+        );
 
         // Check if the node is subscribed to the task
         // NOTE: we do not check if the task is public here, as if it is not, then the node could not have subscribed to it in the first place
@@ -1389,6 +2229,11 @@ module atoma::db {
         let task = self.tasks.borrow_mut(task_small_id);
         assert!(!task.is_deprecated, ETaskDeprecated);
 
+        if (!task.subscribed_nodes.contains(node_badge.small_id)) {
+            // the subscription was swept, see `sweep_stale_node`
+            return
+        };
+
         // Remove the node from the task's subscribed_nodes list and the subscribed_nodes_small_ids list
         // NOTE: we do not need to swap remove from subscribed_nodes_small_ids right now, we can remove it
         // when we iterate over the subscribed_nodes_small_ids table_vec and do not find the corresponding
@@ -1406,6 +2251,378 @@ module atoma::db {
         });
     }
 
+    /// Tells the network that the node is online.
+    ///
+    /// Nodes that don't call this at least once every `threshold_epochs`
+    /// epochs can be swept from the tasks they are subscribed to, see
+    /// `sweep_stale_node`.
+    ///
+    /// # Errors
+    /// * `ENodeAlreadyDisabled` - If the node was disabled, since it can't
+    ///   serve stacks anymore.
+    public entry fun heartbeat(
+        self: &mut AtomaDb,
+        node_badge: &NodeBadge,
+        ctx: &TxContext,
+    ) {
+        assert!(
+            self.nodes.borrow(node_badge.small_id).was_disabled_in_epoch.is_none(),
+            ENodeAlreadyDisabled,
+        );
+
+        let sweeps = self.stale_node_sweeps_mut();
+        let heartbeats = &mut sweeps.last_heartbeat_epochs;
+        if (heartbeats.contains(node_badge.small_id)) {
+            *heartbeats.borrow_mut(node_badge.small_id) = ctx.epoch();
+        } else {
+            heartbeats.add(node_badge.small_id, ctx.epoch());
+        };
+        let senders = &mut sweeps.heartbeat_senders;
+        if (senders.contains(node_badge.small_id)) {
+            *senders.borrow_mut(node_badge.small_id) = ctx.sender();
+        } else {
+            senders.add(node_badge.small_id, ctx.sender());
+        };
+    }
+
+    /// Reports the region the node runs in, eg. `eu-west`, so that gateways
+    /// know where to probe it from.
+    /// Replaces the previously reported region.
+    ///
+    /// # Errors
+    /// * `EInvalidNodeRegion` - If the region is empty or longer than
+    ///   `MaxNodeRegionLength`.
+    public entry fun set_node_region(
+        self: &mut AtomaDb,
+        node_badge: &NodeBadge,
+        region: ascii::String,
+    ) {
+        assert!(
+            region.length() > 0 && region.length() <= MaxNodeRegionLength,
+            EInvalidNodeRegion,
+        );
+        let node_small_id = node_badge.small_id;
+        let regions = self.node_regions_mut();
+        if (regions.contains(node_small_id)) {
+            *regions.borrow_mut(node_small_id) = region;
+        } else {
+            regions.add(node_small_id, region);
+        };
+
+        sui::event::emit(NodeRegionUpdatedEvent { node_small_id, region });
+    }
+
+    /// Reports the version of the software the node runs, so that the
+    /// manager can coordinate protocol upgrades.
+    /// Replaces the previously reported version.
+    ///
+    /// Nodes that never reported a version are treated as 0.0.0 by
+    /// `set_task_min_node_version`.
+    public entry fun set_node_software_version(
+        self: &mut AtomaDb,
+        node_badge: &NodeBadge,
+        major: u16,
+        minor: u16,
+        patch: u16,
+    ) {
+        let node_small_id = node_badge.small_id;
+        let version = SoftwareVersion { major, minor, patch };
+        let versions: &mut Table<NodeSmallId, SoftwareVersion> =
+            dynamic_field::borrow_mut(&mut self.id, NodeSoftwareVersionsKey {});
+        if (versions.contains(node_small_id)) {
+            *versions.borrow_mut(node_small_id) = version;
+        } else {
+            versions.add(node_small_id, version);
+        };
+
+        sui::event::emit(NodeSoftwareVersionUpdatedEvent { node_small_id, version });
+    }
+
+    /// Nodes operated by a cooperative can have their fees split among its
+    /// members whenever they are paid out, ie. by `claim_funds`,
+    /// `claim_funds_for_stacks` and `withdraw_fees`.
+    /// Replaces the previous split.
+    ///
+    /// The shares are in basis points and must add up to 10_000.
+    /// What is lost to rounding goes to the last recipient.
+    /// Empty vectors remove the split, and the node is paid alone again.
+    ///
+    /// # Errors
+    /// * `EInvalidPayoutSplit` - If the vectors have different lengths,
+    ///   there are more than `MaxPayoutSplitRecipients` recipients, a share
+    ///   is zero or the shares don't add up to 10_000.
+    public entry fun set_node_payout_split(
+        self: &mut AtomaDb,
+        node_badge: &NodeBadge,
+        recipients: vector<address>,
+        shares_bps: vector<u64>,
+    ) {
+        let count = recipients.length();
+        assert!(
+            count == shares_bps.length() && count <= MaxPayoutSplitRecipients,
+            EInvalidPayoutSplit,
+        );
+
+        let node_small_id = node_badge.small_id;
+        let splits = self.node_payout_splits_mut();
+        if (splits.contains(node_small_id)) {
+            splits.remove(node_small_id);
+        };
+        if (count > 0) {
+            let mut split = vector::empty();
+            let mut total_bps = 0;
+            let mut index = 0;
+            while (index < count) {
+                let bps = shares_bps[index];
+                assert!(bps > 0, EInvalidPayoutSplit);
+                total_bps = total_bps + bps;
+                split.push_back(PayoutShare { recipient: recipients[index], bps });
+                index = index + 1;
+            };
+            assert!(total_bps == 10_000, EInvalidPayoutSplit);
+            splits.add(node_small_id, split);
+        };
+
+        sui::event::emit(NodePayoutSplitUpdatedEvent {
+            node_small_id,
+            recipients,
+            shares_bps,
+        });
+    }
+
+    /// Registers the Ed25519 public key that the node signs stack quotes and
+    /// offers with, see `acquire_new_stack_with_quote_entry` and
+    /// `acquire_new_stack_with_offer_entry`.
+    /// Replaces the previous key, which invalidates all quotes and offers
+    /// signed with it.
+    ///
+    /// # Errors
+    /// * `EInvalidOfferKey` - If the key is not 32 bytes long.
+    public entry fun set_node_offer_key(
+        self: &mut AtomaDb,
+        node_badge: &NodeBadge,
+        public_key: vector<u8>,
+    ) {
+        assert!(public_key.length() == Ed25519PublicKeyLength, EInvalidOfferKey);
+        let node_small_id = node_badge.small_id;
+        let keys = self.node_offer_keys_mut();
+        if (keys.contains(node_small_id)) {
+            *keys.borrow_mut(node_small_id) = public_key;
+        } else {
+            keys.add(node_small_id, public_key);
+        };
+
+        sui::event::emit(NodeOfferKeySetEvent { node_small_id, public_key });
+    }
+
+    /// Withdraws an offer of the node so that it can no longer be accepted.
+    ///
+    /// # Errors
+    /// * `EOfferAlreadyConsumed` - If the offer was accepted or cancelled
+    ///   already.
+    public entry fun cancel_stack_offer(
+        self: &mut AtomaDb,
+        node_badge: &NodeBadge,
+        nonce: u64,
+    ) {
+        let node_small_id = node_badge.small_id;
+        self.consume_offer(OfferNonce { node_small_id, nonce });
+
+        sui::event::emit(StackOfferCancelledEvent { node_small_id, nonce });
+    }
+
+    /// Gateways probe the nodes of an echelon, and an oracle of the echelon
+    /// records the median latency they measured.
+    /// Replaces the previous measurement.
+    ///
+    /// # Errors
+    /// * `ENodeNotOracle` - If the node is not an oracle of the echelon.
+    public entry fun record_model_echelon_latency(
+        self: &mut AtomaDb,
+        node_badge: &NodeBadge,
+        model_name: ascii::String,
+        echelon: u64,
+        latency_p50_ms: u64,
+    ) {
+        let echelon_id = EchelonId { id: echelon };
+        let oracle_node_id = node_badge.small_id;
+        let model = self.models.borrow(model_name);
+        let echelon = get_echelon(&model.echelons, echelon_id);
+        assert!(echelon.oracles.contains(&oracle_node_id), ENodeNotOracle);
+
+        let key = ModelEchelonKey { model_name, echelon_id };
+        let latencies = self.model_echelon_latencies_mut();
+        if (latencies.contains(key)) {
+            *latencies.borrow_mut(key) = latency_p50_ms;
+        } else {
+            latencies.add(key, latency_p50_ms);
+        };
+
+        sui::event::emit(ModelEchelonLatencyRecordedEvent {
+            model_name,
+            echelon_id,
+            oracle_node_id,
+            latency_p50_ms,
+        });
+    }
+
+    /// Anyone can remove a node from a task it's subscribed to if the node
+    /// has not sent a heartbeat for more than `threshold_epochs` epochs, or
+    /// if the node no longer exists.
+    /// This way offline nodes are not sampled for new stacks.
+    ///
+    /// The sender is paid `reward` from the communal treasury, or whatever
+    /// is left in it, for the first sweep of the node since its last
+    /// heartbeat, unless they registered the node or sent that heartbeat.
+    /// Sweeps of the node from its other tasks are not rewarded, so a node
+    /// earns its sweepers at most one reward per heartbeat however many
+    /// tasks it's subscribed to.
+    ///
+    /// The node can subscribe to the task again once it's back online.
+    /// A node that subscribes again without sending a heartbeat can't be
+    /// swept from the task again.
+    ///
+    /// # Errors
+    /// * `ETaskNotFound` - If the task does not exist.
+    /// * `ENodeNotSubscribedToTask` - If the node is not subscribed to the task.
+    /// * `ENodeNotStale` - If the node sent a heartbeat recently enough.
+    /// * `ENodeAlreadySwept` - If the node was swept from the task and has
+    ///   not sent a heartbeat since.
+    public entry fun sweep_stale_node(
+        self: &mut AtomaDb,
+        task_small_id: u64,
+        node_small_id: u64,
+        ctx: &mut TxContext,
+    ) {
+        let task_small_id = TaskSmallId { inner: task_small_id };
+        let node_small_id = NodeSmallId { inner: node_small_id };
+        assert!(self.tasks.contains(task_small_id), ETaskNotFound);
+
+        let last_heartbeat_epoch = self.node_last_heartbeat_epoch(node_small_id);
+        if (self.nodes.contains(node_small_id)) {
+            assert!(
+                ctx.epoch() > last_heartbeat_epoch + self.stale_node_sweeps().threshold_epochs,
+                ENodeNotStale,
+            );
+        };
+
+        let subscription = SweptSubscription { task_small_id, node_small_id };
+        let swept = &mut self.stale_node_sweeps_mut().swept;
+        if (swept.contains(subscription)) {
+            let swept_at_heartbeat = swept.borrow_mut(subscription);
+            assert!(*swept_at_heartbeat != last_heartbeat_epoch, ENodeAlreadySwept);
+            *swept_at_heartbeat = last_heartbeat_epoch;
+        } else {
+            swept.add(subscription, last_heartbeat_epoch);
+        };
+
+        // the small ID is swap removed lazily when sampling, as with
+        // `unsubscribe_node_from_task`
+        let task = self.tasks.borrow_mut(task_small_id);
+        assert!(task.subscribed_nodes.contains(node_small_id), ENodeNotSubscribedToTask);
+        table::remove(&mut task.subscribed_nodes, node_small_id);
+
+        let reward = {
+            let sweeps = self.stale_node_sweeps();
+            let sent_heartbeat = sweeps.heartbeat_senders.contains(node_small_id)
+                && *sweeps.heartbeat_senders.borrow(node_small_id) == ctx.sender();
+            let was_rewarded = sweeps.rewarded.contains(node_small_id)
+                && *sweeps.rewarded.borrow(node_small_id) == last_heartbeat_epoch;
+            if (sent_heartbeat || was_rewarded) { 0 } else { sweeps.reward }
+        };
+        let reward = if (reward < self.communal_treasury.value()) {
+            reward
+        } else {
+            self.communal_treasury.value()
+        };
+        if (reward > 0) {
+            let rewarded = &mut self.stale_node_sweeps_mut().rewarded;
+            if (rewarded.contains(node_small_id)) {
+                *rewarded.borrow_mut(node_small_id) = last_heartbeat_epoch;
+            } else {
+                rewarded.add(node_small_id, last_heartbeat_epoch);
+            };
+            let wallet = coin::from_balance(self.communal_treasury.split(reward), ctx);
+            transfer::public_transfer(wallet, ctx.sender());
+        };
+
+        sui::event::emit(NodeUnsubscribedFromTaskEvent {
+            node_small_id,
+            task_small_id,
+        });
+        sui::event::emit(StaleNodeSweptEvent {
+            node_small_id,
+            task_small_id,
+            last_heartbeat_epoch,
+            sweeper: ctx.sender(),
+            reward,
+        });
+    }
+
+    /// Confidential compute stacks stay in the db after they are claimed
+    /// with `claim_funds_for_stacks`, which makes every lookup in the stacks
+    /// table slower.
+    /// Anyone can remove the ones that were claimed more than
+    /// `min_age_epochs` epochs ago, see `set_stack_gc_params`.
+    ///
+    /// Stacks that can't be collected, eg. because another collector was
+    /// faster, are skipped.
+    /// The sender is paid `bounty` for every collected stack from
+    /// the communal treasury, or whatever is left in it.
+    ///
+    /// # Errors
+    /// * `ENoStacksToCollect` - If none of the stacks could be collected.
+    public entry fun collect_claimed_stacks(
+        self: &mut AtomaDb,
+        stack_small_ids: vector<u64>,
+        ctx: &mut TxContext,
+    ) {
+        let mut collected = vector::empty();
+        let mut index = 0;
+        while (index < stack_small_ids.length()) {
+            let stack_small_id = StackSmallId { inner: stack_small_ids[index] };
+            index = index + 1;
+            let gc = self.stack_gc();
+            if (!gc.claimed_stack_epochs.contains(stack_small_id)) {
+                continue
+            };
+            let claimed_at_epoch = *gc.claimed_stack_epochs.borrow(stack_small_id);
+            if (ctx.epoch() <= claimed_at_epoch + gc.min_age_epochs) {
+                continue
+            };
+
+            self.stack_gc_mut().claimed_stack_epochs.remove(stack_small_id);
+            let Stack {
+                task_small_id: _,
+                selected_node_id: _,
+                owner: _,
+                price_per_one_million_compute_units: _,
+                num_compute_units: _,
+                is_claimed: _,
+            } = self.stacks.remove(stack_small_id);
+            self.remove_stack_close_proposal(stack_small_id);
+            collected.push_back(stack_small_id);
+        };
+        assert!(!collected.is_empty(), ENoStacksToCollect);
+
+        let bounty = self.stack_gc().bounty * collected.length();
+        let bounty = if (bounty < self.communal_treasury.value()) {
+            bounty
+        } else {
+            self.communal_treasury.value()
+        };
+        if (bounty > 0) {
+            let wallet = coin::from_balance(self.communal_treasury.split(bounty), ctx);
+            transfer::public_transfer(wallet, ctx.sender());
+        };
+
+        sui::event::emit(ClaimedStacksCollectedEvent {
+            stack_small_ids: collected,
+            collector: ctx.sender(),
+            bounty,
+        });
+    }
+
     /// Creates a new stack for a specific task and transfers the resulting StackBadge to the sender.
     ///
     /// This entry function is a wrapper around the `acquire_new_stack` function, handling the creation
@@ -1417,6 +2634,9 @@ module atoma::db {
     /// * `task_small_id` - The SmallId of the task associated with this stack.
     /// * `num_compute_units` - The number of compute units allocated to this stack.
     /// * `price` - The price per one million compute units in USDC tokens.
+    /// * `referrer` - Optional address that brought the user to the network.
+    /// * `referral_fee_share_bps` - Share of the node's fee for this stack
+    ///   that goes to the referrer, in basis points. Ignored without referrer.
     /// * `random` - A reference to a Random object for generating random numbers.
     /// * `ctx` - A mutable reference to the transaction context.
     ///
@@ -1425,21 +2645,26 @@ module atoma::db {
     /// - Generates a StackBadge for the newly created stack.
     /// - Transfers the StackBadge to the transaction sender.
     /// - Deducts the cost of compute units from the provided wallet.
+    /// - Records the referral, if any, to be paid out when the stack is claimed.
     ///
     /// # Aborts
     /// This function may abort if:
     /// - The task specified by `task_small_id` does not exist.
     /// - There are no eligible nodes to process the stack's requests.
     /// - The wallet does not have sufficient balance to pay for the compute units.
+    /// - The referral fee share is above `MaxReferralFeeShareBps`.
     ///
     /// # Events
-    /// Emits a StackCreatedEvent containing details about the newly created stack.
+    /// Emits a StackCreatedEvent containing details about the newly created stack
+    /// and a StackReferralEvent if there is a referrer.
     entry fun acquire_new_stack_entry(
         self: &mut AtomaDb,
         wallet: &mut Coin<USDC>,
         task_small_id: u64,
         num_compute_units: u64,
         price: u64,
+        referrer: Option<address>,
+        referral_fee_share_bps: u64,
         random: &sui::random::Random,
         ctx: &mut TxContext,
     ) {
@@ -1453,85 +2678,379 @@ module atoma::db {
             &mut rng, 
             ctx,
         );
+        if (referrer.is_some()) {
+            self.add_stack_referral(
+                stack_badge.small_id,
+                referrer.destroy_some(),
+                referral_fee_share_bps,
+            );
+        };
         transfer::transfer(stack_badge, ctx.sender());
     }
 
-    /// Creates a new stack for a specific task and returns a StackBadge.
+    /// Acquires a stack on the terms of a price quote that a node signed
+    /// off-chain, giving both the user and the node a non-repudiable record
+    /// of the agreed pricing.
+    /// The stack is assigned to the quoting node at the quoted price.
     ///
-    /// This function creates a new stack associated with a given task, selects a node to process
-    /// the requests, and initializes the stack with the specified parameters.
+    /// The node signs `quote_hash` as a personal message with the key it
+    /// registered with `set_node_offer_key`.
+    /// Unlike an offer, a quote has no nonce and can be accepted any number
+    /// of times until it expires.
     ///
     /// # Arguments
-    /// * `self` - A mutable reference to the AtomaDb object.
-    /// * `task_small_id` - The SmallId of the task associated with this stack.
-    /// * `num_compute_units` - The number of compute units allocated to this stack.
-    /// * `expiration_epoch` - An optional expiration epoch for the stack.
-    /// * `price_per_one_million_compute_units` - The price per one million compute units in USDC tokens.
-    /// * `rng` - A mutable reference to a RandomGenerator for node selection.
-    /// * `ctx` - A mutable reference to the transaction context.
-    ///
-    /// # Returns
-    /// A StackBadge object representing the newly created stack.
+    /// Same as `acquire_new_stack_entry` without referrals, plus:
+    /// * `quote_node_small_id` - The node that signed the quote.
+    /// * `quote_expires_at_ms` - Timestamp after which the quote is no longer valid.
+    /// * `signature` - The 64 byte Ed25519 signature of the quote.
+    /// * `clock` - The shared clock object.
     ///
     /// # Aborts
-    /// * If the expiration epoch is not greater than the current epoch (when provided).
+    /// * `EQuoteExpired` - If the quote has expired.
+    /// * `ENodeHasNoOfferKey` - If the node has not registered a key.
+    /// * `EInvalidQuoteSignature` - If the signature doesn't match the terms.
+    /// * `ENodeNotSubscribedToTask` - If the node no longer serves the task.
+    /// * `ENodeAlreadyDisabled` - If the node was disabled.
+    /// * `ENodeBelowTaskMinVersion` - If the node runs an older version than
+    ///   the task requires, see `set_task_min_node_version`.
+    /// * `EPriceBelowTaskMinimum` - If the price is below the minimum of the
+    ///   task, see `set_task_min_price`.
     ///
     /// # Events
-    /// Emits a StackCreatedEvent containing details about the newly created stack.
-    ///
-    /// # Notes
-    /// - The function selects a node for the stack using the `sample_node_for_stack` method.
-    /// - The stack is initialized as not being in dispute.
-    /// - A new SmallId is assigned to the stack, incrementing the `next_stack_small_id` counter.
-    fun acquire_new_stack(
+    /// Emits a StackCreatedEvent and a StackQuoteAcceptedEvent.
+    entry fun acquire_new_stack_with_quote_entry(
         self: &mut AtomaDb,
-        wallet: &mut Balance<USDC>,
+        wallet: &mut Coin<USDC>,
         task_small_id: u64,
         num_compute_units: u64,
-        price_per_one_million_compute_units: u64,
-        rng: &mut sui::random::RandomGenerator,
+        price: u64,
+        quote_node_small_id: u64,
+        quote_expires_at_ms: u64,
+        signature: vector<u8>,
+        clock: &Clock,
         ctx: &mut TxContext,
-    ): StackBadge {
-        // Input validation
-        assert!(num_compute_units > 0, EInvalidComputeUnits);
-        assert!(price_per_one_million_compute_units > 0, EInvalidPricePerComputeUnit);
-
-        let task_small_id = TaskSmallId { inner: task_small_id };
-        assert!(self.tasks.contains(task_small_id), ETaskNotFound);
-        {
-            // transfer the funds for compute units to the contract
-            let task = self.tasks.borrow(task_small_id);
-            let security_level = task.security_level;
-            let total_cost = (price_per_one_million_compute_units * num_compute_units) / ONE_MILLION_COMPUTE_UNITS;
-            let fee_amount = if (security_level.inner == SamplingConsensus) {
-                let sampling_consensus_charge = self.get_sampling_consensus_charge_permille();
-                let cross_validation_charge = self.get_cross_validation_extra_nodes_charge_permille();
-                (total_cost * (sampling_consensus_charge + cross_validation_charge)) / 1000
-            } else {
-                total_cost
-            };
-            // Check if the wallet has enough balance to pay for the compute units
-            assert!(balance::value(wallet) >= fee_amount, EInsufficientBalance);
-            // Transfer the funds to the contract
-            let funds = wallet.split(fee_amount);
-            self.deposit_to_fee_treasury(funds);
-        };
+    ) {
+        assert!(clock.timestamp_ms() <= quote_expires_at_ms, EQuoteExpired);
 
-        // Sample a node and create the stack
-        let owner = ctx.sender();
-        let selected_node_id = self.sample_node_for_stack(
-            task_small_id, 
-            price_per_one_million_compute_units, 
-            num_compute_units, 
-            rng
+        let quote_hash = self.quote_hash(
+            quote_node_small_id,
+            task_small_id,
+            price,
+            num_compute_units,
+            quote_expires_at_ms,
+        );
+        let quote_node_id = NodeSmallId { inner: quote_node_small_id };
+        assert!(
+            self.is_signed_by_node(quote_node_id, quote_hash, &signature),
+            EInvalidQuoteSignature,
         );
 
-        let stack = Stack { 
-            owner,
-            task_small_id,
+        let stack_badge = self.acquire_new_stack_from_node(
+            wallet.balance_mut(),
+            TaskSmallId { inner: task_small_id },
             num_compute_units,
-            selected_node_id,
-            price_per_one_million_compute_units,
+            price,
+            quote_node_id,
+            ctx,
+        );
+
+        sui::event::emit(StackQuoteAcceptedEvent {
+            stack_small_id: stack_badge.small_id,
+            quote_node_id,
+            quote_hash,
+        });
+        transfer::transfer(stack_badge, ctx.sender());
+    }
+
+    /// Acquires a stack on the terms of an offer that a node signed
+    /// off-chain, eg. one the user picked from an order book.
+    /// Like with quotes, the stack is assigned to the offering node rather
+    /// than to a sampled one, at the price of the offer.
+    ///
+    /// The node signs `offer_hash` as a personal message with the key it
+    /// registered with `set_node_offer_key`.
+    /// Each nonce of a node can be used once, and a node can cancel an offer
+    /// with `cancel_stack_offer` until it's accepted.
+    ///
+    /// # Arguments
+    /// Same as `acquire_new_stack_entry` without referrals, plus:
+    /// * `node_small_id` - The node that signed the offer.
+    /// * `offer_expires_at_ms` - Timestamp after which the offer is no longer
+    ///   valid.
+    /// * `offer_nonce` - Chosen by the node to tell its offers apart.
+    /// * `signature` - The 64 byte Ed25519 signature of the offer.
+    /// * `clock` - The shared clock object.
+    ///
+    /// # Aborts
+    /// * `EOfferExpired` - If the offer has expired.
+    /// * `ENodeHasNoOfferKey` - If the node has not registered an offer key.
+    /// * `EOfferAlreadyConsumed` - If the offer was accepted or cancelled.
+    /// * `EInvalidOfferSignature` - If the signature doesn't match the terms.
+    /// * `ENodeNotSubscribedToTask` - If the node no longer serves the task.
+    /// * `ENodeAlreadyDisabled` - If the node was disabled.
+    /// * `ENodeBelowTaskMinVersion` - If the node runs an older version than
+    ///   the task requires, see `set_task_min_node_version`.
+    /// * `EPriceBelowTaskMinimum` - If the price is below the minimum of the
+    ///   task, see `set_task_min_price`.
+    ///
+    /// # Events
+    /// Emits a StackCreatedEvent and a StackOfferAcceptedEvent.
+    entry fun acquire_new_stack_with_offer_entry(
+        self: &mut AtomaDb,
+        wallet: &mut Coin<USDC>,
+        task_small_id: u64,
+        num_compute_units: u64,
+        price: u64,
+        node_small_id: u64,
+        offer_expires_at_ms: u64,
+        offer_nonce: u64,
+        signature: vector<u8>,
+        clock: &Clock,
+        ctx: &mut TxContext,
+    ) {
+        assert!(clock.timestamp_ms() <= offer_expires_at_ms, EOfferExpired);
+
+        let offer_hash = self.offer_hash(
+            node_small_id,
+            task_small_id,
+            price,
+            num_compute_units,
+            offer_expires_at_ms,
+            offer_nonce,
+        );
+        let node_small_id = NodeSmallId { inner: node_small_id };
+        assert!(
+            self.is_signed_by_node(node_small_id, offer_hash, &signature),
+            EInvalidOfferSignature,
+        );
+        self.consume_offer(OfferNonce { node_small_id, nonce: offer_nonce });
+
+        let stack_badge = self.acquire_new_stack_from_node(
+            wallet.balance_mut(),
+            TaskSmallId { inner: task_small_id },
+            num_compute_units,
+            price,
+            node_small_id,
+            ctx,
+        );
+
+        sui::event::emit(StackOfferAcceptedEvent {
+            stack_small_id: stack_badge.small_id,
+            node_small_id,
+            nonce: offer_nonce,
+            offer_hash,
+        });
+        transfer::transfer(stack_badge, ctx.sender());
+    }
+
+    fun consume_offer(self: &mut AtomaDb, offer: OfferNonce) {
+        let consumed_offers = self.consumed_offers_mut();
+        assert!(!consumed_offers.contains(offer), EOfferAlreadyConsumed);
+        consumed_offers.add(offer, true);
+    }
+
+    /// Whether the node signed the message as a personal message with the
+    /// key it registered with `set_node_offer_key`.
+    ///
+    /// # Aborts
+    /// * `ENodeHasNoOfferKey` - If the node has not registered a key.
+    fun is_signed_by_node(
+        self: &AtomaDb,
+        node_small_id: NodeSmallId,
+        message: vector<u8>,
+        signature: &vector<u8>,
+    ): bool {
+        assert!(
+            dynamic_field::exists_(&self.id, NodeOfferKeysKey {})
+                && self.node_offer_keys().contains(node_small_id),
+            ENodeHasNoOfferKey,
+        );
+        sui::ed25519::ed25519_verify(
+            signature,
+            self.node_offer_keys().borrow(node_small_id),
+            &personal_message_digest(message),
+        )
+    }
+
+    /// Charges for a stack that is assigned to the given node rather than to
+    /// a sampled one, ie. on terms that the node signed.
+    fun acquire_new_stack_from_node(
+        self: &mut AtomaDb,
+        wallet: &mut Balance<USDC>,
+        task_small_id: TaskSmallId,
+        num_compute_units: u64,
+        price_per_one_million_compute_units: u64,
+        node_small_id: NodeSmallId,
+        ctx: &mut TxContext,
+    ): StackBadge {
+        assert!(num_compute_units > 0, EInvalidComputeUnits);
+        assert!(price_per_one_million_compute_units > 0, EInvalidPricePerComputeUnit);
+        assert!(self.tasks.contains(task_small_id), ETaskNotFound);
+        assert!(
+            self.tasks.borrow(task_small_id).subscribed_nodes.contains(node_small_id),
+            ENodeNotSubscribedToTask,
+        );
+        assert!(
+            self.nodes.borrow(node_small_id).was_disabled_in_epoch.is_none(),
+            ENodeAlreadyDisabled,
+        );
+        assert!(
+            node_software_version(&self.id, node_small_id) >= self.task_min_node_version(task_small_id),
+            ENodeBelowTaskMinVersion,
+        );
+        assert!(
+            price_per_one_million_compute_units >= task_min_price(&self.id, task_small_id),
+            EPriceBelowTaskMinimum,
+        );
+
+        self.charge_for_stack(
+            wallet,
+            task_small_id,
+            num_compute_units,
+            price_per_one_million_compute_units,
+        );
+        self.create_stack(
+            task_small_id,
+            num_compute_units,
+            price_per_one_million_compute_units,
+            node_small_id,
+            ctx,
+        )
+    }
+
+    /// Creates a new stack for a specific task and returns a StackBadge.
+    ///
+    /// This function creates a new stack associated with a given task, selects a node to process
+    /// the requests, and initializes the stack with the specified parameters.
+    ///
+    /// # Arguments
+    /// * `self` - A mutable reference to the AtomaDb object.
+    /// * `task_small_id` - The SmallId of the task associated with this stack.
+    /// * `num_compute_units` - The number of compute units allocated to this stack.
+    /// * `expiration_epoch` - An optional expiration epoch for the stack.
+    /// * `price_per_one_million_compute_units` - The price per one million compute units in USDC tokens.
+    /// * `rng` - A mutable reference to a RandomGenerator for node selection.
+    /// * `ctx` - A mutable reference to the transaction context.
+    ///
+    /// # Returns
+    /// A StackBadge object representing the newly created stack.
+    ///
+    /// # Aborts
+    /// * If the expiration epoch is not greater than the current epoch (when provided).
+    ///
+    /// # Events
+    /// Emits a StackCreatedEvent containing details about the newly created stack.
+    ///
+    /// # Notes
+    /// - The function selects a node for the stack using the `sample_node_for_stack` method.
+    /// - The stack is initialized as not being in dispute.
+    /// - A new SmallId is assigned to the stack, incrementing the `next_stack_small_id` counter.
+    fun acquire_new_stack(
+        self: &mut AtomaDb,
+        wallet: &mut Balance<USDC>,
+        task_small_id: u64,
+        num_compute_units: u64,
+        price_per_one_million_compute_units: u64,
+        rng: &mut sui::random::RandomGenerator,
+        ctx: &mut TxContext,
+    ): StackBadge {
+        // Input validation
+        assert!(num_compute_units > 0, EInvalidComputeUnits);
+        assert!(price_per_one_million_compute_units > 0, EInvalidPricePerComputeUnit);
+
+        let task_small_id = TaskSmallId { inner: task_small_id };
+        assert!(self.tasks.contains(task_small_id), ETaskNotFound);
+        // transfer the funds for compute units to the contract
+        self.charge_for_stack(
+            wallet,
+            task_small_id,
+            num_compute_units,
+            price_per_one_million_compute_units,
+        );
+
+        // Sample a node and create the stack
+        let selected_node_id = self.sample_node_for_stack(
+            task_small_id, 
+            price_per_one_million_compute_units, 
+            num_compute_units, 
+            rng
+        );
+
+        self.create_stack(
+            task_small_id,
+            num_compute_units,
+            price_per_one_million_compute_units,
+            selected_node_id,
+            ctx,
+        )
+    }
+
+    /// Takes the payment for a stack, see `stack_cost`, into the fee
+    /// treasury.
+    fun charge_for_stack(
+        self: &mut AtomaDb,
+        wallet: &mut Balance<USDC>,
+        task_small_id: TaskSmallId,
+        num_compute_units: u64,
+        price_per_one_million_compute_units: u64,
+    ) {
+        let max_compute_units = self.get_task_max_compute_units(task_small_id);
+        let task = self.tasks.borrow(task_small_id);
+        assert!(
+            max_compute_units.is_none() || num_compute_units <= *max_compute_units.borrow(),
+            EExceedsTaskMaxComputeUnits,
+        );
+        let security_level = task.security_level;
+        let fee_amount = self.stack_cost(
+            security_level,
+            price_per_one_million_compute_units,
+            num_compute_units,
+        );
+        // Check if the wallet has enough balance to pay for the compute units
+        assert!(balance::value(wallet) >= fee_amount, EInsufficientBalance);
+        // Transfer the funds to the contract
+        let funds = wallet.split(fee_amount);
+        self.deposit_to_fee_treasury(funds);
+    }
+
+    /// What the user pays for the compute units of a stack.
+    ///
+    /// Stacks of tasks with sampling consensus also pay for the attestation
+    /// nodes, ie. the sampling consensus charge and the cross validation
+    /// charge on top of the price.
+    fun stack_cost(
+        self: &AtomaDb,
+        security_level: SecurityLevel,
+        price_per_one_million_compute_units: u64,
+        num_compute_units: u64,
+    ): u64 {
+        let total_cost = (price_per_one_million_compute_units * num_compute_units) / ONE_MILLION_COMPUTE_UNITS;
+        if (security_level.inner == SamplingConsensus) {
+            let sampling_consensus_charge = self.get_sampling_consensus_charge_permille();
+            let cross_validation_charge = self.get_cross_validation_extra_nodes_charge_permille();
+            (total_cost * (sampling_consensus_charge + cross_validation_charge)) / 1000
+        } else {
+            total_cost
+        }
+    }
+
+    /// Stores a paid stack assigned to the given node and emits
+    /// `StackCreatedEvent`.
+    fun create_stack(
+        self: &mut AtomaDb,
+        task_small_id: TaskSmallId,
+        num_compute_units: u64,
+        price_per_one_million_compute_units: u64,
+        selected_node_id: NodeSmallId,
+        ctx: &mut TxContext,
+    ): StackBadge {
+        let owner = ctx.sender();
+        let stack = Stack { 
+            owner,
+            task_small_id,
+            num_compute_units,
+            selected_node_id,
+            price_per_one_million_compute_units,
             is_claimed: false,
         };
 
@@ -1609,23 +3128,21 @@ module atoma::db {
         ctx: &mut TxContext,
     ) {
         let stack_small_id = StackSmallId { inner: stack_small_id };
-
-        let stack = self.stacks.borrow(stack_small_id);
         let node_small_id = node_badge.small_id;
-
-        assert!(node_small_id == stack.selected_node_id, ENodeNotSelectedForStack);
-        assert!(num_claimed_compute_units <= stack.num_compute_units, ETooManyComputedUnits);
-        assert!(!self.stack_settlement_tickets.contains(stack_small_id), EStackInSettlementDispute);
-
-        // Validate input data
-        assert!(vector::length(&committed_stack_proof) == BYTES_PER_HASH_COMMITMENT, EInvalidCommittedStackProof);
-        assert!(vector::length(&stack_merkle_leaf) == BYTES_PER_HASH_COMMITMENT, EInvalidStackMerkleLeaf);
+        self.assert_can_try_settle_stack(
+            node_small_id,
+            stack_small_id,
+            num_claimed_compute_units,
+            &committed_stack_proof,
+            &stack_merkle_leaf,
+        );
 
         // Get all necessary values from self beforehand to avoid mutable borrows
         let cross_validation_probability_permille = self.get_cross_validation_probability_permille();
-        let cross_validation_extra_nodes_count = self.get_cross_validation_extra_nodes_count();
 
+        let stack = self.stacks.borrow(stack_small_id);
         let task_small_id = stack.task_small_id;
+        assert!(!self.task_has_zk_verifier(task_small_id), ETaskRequiresZkSettlementProof);
         let task = self.tasks.borrow(task_small_id);
         let security_level = task.security_level;
         let stack_price_per_one_million_compute_units = stack.price_per_one_million_compute_units;
@@ -1650,71 +3167,124 @@ module atoma::db {
             vector::empty()
         };
 
-        // Populate the stack merkle leaves vector, by copying the stack merkle leaf
-        // for the first BYTES_PER_HASH_COMMITMENT bytes, and then padding with 0s
-        // for the remaining cross_validation_extra_nodes_count * BYTES_PER_HASH_COMMITMENT bytes 
-        let mut stack_merkle_leaves_vector = vector::empty();
-        let mut i = 0;
-        while (i < BYTES_PER_HASH_COMMITMENT) {
-            vector::push_back(&mut stack_merkle_leaves_vector, stack_merkle_leaf[i]);
-            i = i + 1;
-        };
-
-        let mut j = 0;
-        while (j < cross_validation_extra_nodes_count * BYTES_PER_HASH_COMMITMENT) {
-            vector::push_back(&mut stack_merkle_leaves_vector, 0);
-            j = j + 1;
-        };
-
-        let ticket_id = object::new(ctx);
-        let stack_settlement_ticket = StackSettlementTicket {
-            id: ticket_id,
-            stack_small_id: stack_small_id,
-            selected_node_id: node_small_id,
-            num_claimed_compute_units,
-            requested_attestation_nodes: attestation_nodes,
-            dispute_settled_at_epoch: ctx.epoch() + STACK_DISPUTE_SETTLEMENT_DELAY,
-            committed_stack_proof,
-            stack_merkle_leaves_vector,
-            already_attested_nodes: vector::empty(),
-            is_in_dispute: false,
-        };
-
-        self.stack_settlement_tickets.add(stack_small_id, stack_settlement_ticket);
-
-        sui::event::emit(StackTrySettleEvent {
-            stack_small_id: stack_small_id,
-            selected_node_id: node_small_id,
+        self.add_stack_settlement_ticket(
+            node_small_id,
+            stack_small_id,
             num_claimed_compute_units,
-            requested_attestation_nodes: attestation_nodes,
             committed_stack_proof,
             stack_merkle_leaf,
-        });
+            attestation_nodes,
+            ctx,
+        );
     }
 
-    /// Submits an attestation for a stack settlement in the Atoma network.
+    /// Attempts to settle a stack of a task that registered a zk verifier,
+    /// see `set_task_zk_verifier`.
     ///
-    /// This function allows an attestation node to submit its verification of a stack's execution results.
-    /// It's part of the consensus mechanism for tasks with a Sampling Consensus security level.
+    /// Instead of relying on attestation nodes to recompute the stack, the
+    /// selected node submits a Groth16 proof produced by an external prover.
+    /// The proof attests that the claimed number of compute units and the
+    /// committed stack proof were correctly derived from the requests and
+    /// responses of the stack.
+    /// Its public inputs are derived on-chain, see
+    /// `zk_settlement_public_inputs`, so that the proof cannot be replayed for
+    /// a different stack or claim.
+    ///
+    /// No attestation nodes are sampled.
+    /// The ticket can be claimed once the dispute period is over.
     ///
     /// # Arguments
-    /// * `self` - A mutable reference to the AtomaDb object.
-    /// * `node_badge` - A reference to the NodeBadge of the attesting node.
-    /// * `stack_small_id` - The SmallId of the stack being attested.
-    /// * `committed_stack_proof` - A vector<u8> representing the cryptographic proof of the stack's execution.
-    /// * `stack_merkle_leaf` - A vector<u8> representing the Merkle leaf for this attestation.
-    /// * `ctx` - A mutable reference to the transaction context.
+    /// * `proof_points` - The Groth16 proof, as arkworks compressed bytes.
     ///
-    /// # Behavior
-    /// 1. Verifies the existence of the stack and its settlement ticket.
-    /// 2. Checks if the stack's associated task requires Sampling Consensus.
-    /// 3. Ensures the dispute period is still ongoing.
-    /// 4. Verifies that the attesting node was selected for attestation.
-    /// 5. Records the attestation in the stack's Merkle tree.
-    /// 6. If all attestations are received, computes the Merkle root and verifies consensus.
+    /// See `try_settle_stack` for the rest.
     ///
     /// # Errors
-    /// * `EStackNotFound` - If the specified stack does not exist.
+    /// * `ETaskHasNoZkVerifier` - If the stack's task has no zk verifier.
+    /// * `EInvalidZkSettlementProof` - If the proof does not verify.
+    ///
+    /// See `try_settle_stack` for the rest.
+    ///
+    /// # Events
+    /// Emits a `StackZkProofVerifiedEvent` and a `StackTrySettleEvent`.
+    entry fun try_settle_stack_with_zk_proof(
+        self: &mut AtomaDb,
+        node_badge: &NodeBadge,
+        stack_small_id: u64,
+        num_claimed_compute_units: u64,
+        committed_stack_proof: vector<u8>,
+        stack_merkle_leaf: vector<u8>,
+        proof_points: vector<u8>,
+        ctx: &mut TxContext,
+    ) {
+        let public_inputs = zk_settlement_public_inputs(
+            stack_small_id,
+            num_claimed_compute_units,
+            committed_stack_proof,
+        );
+        let stack_small_id = StackSmallId { inner: stack_small_id };
+        let node_small_id = node_badge.small_id;
+        self.assert_can_try_settle_stack(
+            node_small_id,
+            stack_small_id,
+            num_claimed_compute_units,
+            &committed_stack_proof,
+            &stack_merkle_leaf,
+        );
+
+        let task_small_id = self.stacks.borrow(stack_small_id).task_small_id;
+        assert!(self.task_has_zk_verifier(task_small_id), ETaskHasNoZkVerifier);
+        let verifier = self.task_zk_verifiers().borrow(task_small_id);
+        assert!(
+            groth16::verify_groth16_proof(
+                &verifier.curve,
+                &verifier.verifying_key,
+                &groth16::public_proof_inputs_from_bytes(public_inputs),
+                &groth16::proof_points_from_bytes(proof_points),
+            ),
+            EInvalidZkSettlementProof,
+        );
+
+        sui::event::emit(StackZkProofVerifiedEvent {
+            stack_small_id,
+            selected_node_id: node_small_id,
+            num_claimed_compute_units,
+            committed_stack_proof,
+        });
+
+        self.add_stack_settlement_ticket(
+            node_small_id,
+            stack_small_id,
+            num_claimed_compute_units,
+            committed_stack_proof,
+            stack_merkle_leaf,
+            vector::empty(),
+            ctx,
+        );
+    }
+
+    /// Submits an attestation for a stack settlement in the Atoma network.
+    ///
+    /// This function allows an attestation node to submit its verification of a stack's execution results.
+    /// It's part of the consensus mechanism for tasks with a Sampling Consensus security level.
+    ///
+    /// # Arguments
+    /// * `self` - A mutable reference to the AtomaDb object.
+    /// * `node_badge` - A reference to the NodeBadge of the attesting node.
+    /// * `stack_small_id` - The SmallId of the stack being attested.
+    /// * `committed_stack_proof` - A vector<u8> representing the cryptographic proof of the stack's execution.
+    /// * `stack_merkle_leaf` - A vector<u8> representing the Merkle leaf for this attestation.
+    /// * `ctx` - A mutable reference to the transaction context.
+    ///
+    /// # Behavior
+    /// 1. Verifies the existence of the stack and its settlement ticket.
+    /// 2. Checks if the stack's associated task requires Sampling Consensus.
+    /// 3. Ensures the dispute period is still ongoing.
+    /// 4. Verifies that the attesting node was selected for attestation.
+    /// 5. Records the attestation in the stack's Merkle tree.
+    /// 6. If all attestations are received, computes the Merkle root and verifies consensus.
+    ///
+    /// # Errors
+    /// * `EStackNotFound` - If the specified stack does not exist.
     /// * `EStackNotInSettlementDispute` - If there's no ongoing settlement for the stack.
     /// * `EStackDoesNotRequireSamplingConsensus` - If the stack's task doesn't use Sampling Consensus.
     /// * `EStackDisputePeriodOver` - If the dispute period has ended.
@@ -1872,7 +3442,7 @@ module atoma::db {
     /// + ✓ Validates that the node is processing a stack that is being processed through confidential compute
     /// - ⚠️ No explicit check for stack settlement status
     /// - ⚠️ No explicit dispute period verification
-    /// - ⚠️ No cleanup of processed stacks
+    /// + ✓ Processed stacks are removed later with `collect_claimed_stacks`
     ///
     /// # Events
     /// - No events are currently emitted for fund transfers
@@ -1924,8 +3494,15 @@ module atoma::db {
                         stack_num_claimed_compute_units, 
                     sampling_consensus_charge_permille
                 );
+            // Part of the fee might go to the referrer of the stack
+            let referral_fee_amount = self.accrue_referral_fee(stack_small_id, node_fee_amount);
+            // Insured nodes pay a premium into the insurance pool
+            let insurance_premium_amount = self.accrue_insurance_premium(
+                node_badge.small_id,
+                node_fee_amount - referral_fee_amount,
+            );
             // Update the total node fee
-            total_node_fee = total_node_fee + node_fee_amount;
+            total_node_fee = total_node_fee + node_fee_amount - referral_fee_amount - insurance_premium_amount;
             let remaining_compute_units = stack_num_compute_units - stack_num_claimed_compute_units;
             let user_refund_amount = 
                 calculate_stack_fee_amount(
@@ -1939,6 +3516,10 @@ module atoma::db {
                 let stack = self.stacks.borrow_mut(stack_small_id);
                 stack.is_claimed = true;
             };
+            self.record_claimed_stack(stack_small_id, ctx);
+            if (node_fee_amount > 0) {
+                self.record_dispute_free_settlement(node_badge.small_id, ctx.epoch());
+            };
             index = index + 1;
             sui::event::emit(ClaimedStackEvent {
                 stack_small_id,
@@ -1947,7 +3528,7 @@ module atoma::db {
                 user_refund_amount,
             });
         };
-        self.transfer_funds(total_node_fee, ctx.sender(), ctx);
+        self.transfer_node_fee(node_badge.small_id, total_node_fee, ctx);
         self.withdraw_fees(node_badge, ctx);
     }
 
@@ -2028,9 +3609,23 @@ module atoma::db {
                     num_claimed_compute_units, 
                     sampling_consensus_charge_permille
                 );
+
+            // The ticket cannot be claimed while in dispute
+            if (node_fee_amount > 0) {
+                self.record_dispute_free_settlement(selected_node_id, ctx.epoch());
+            };
+
+            // Part of the fee might go to the referrer of the stack
+            let referral_fee_amount = self.accrue_referral_fee(stack_small_id, node_fee_amount);
+
+            // Insured nodes pay a premium into the insurance pool
+            let insurance_premium_amount = self.accrue_insurance_premium(
+                selected_node_id,
+                node_fee_amount - referral_fee_amount,
+            );
             
             // Update the total node fee
-            total_node_fee = total_node_fee + node_fee_amount;
+            total_node_fee = total_node_fee + node_fee_amount - referral_fee_amount - insurance_premium_amount;
             
             // Check if there are remaining funds to be sent back to the user
             let remaining_compute_units = num_compute_units - num_claimed_compute_units;
@@ -2048,6 +3643,7 @@ module atoma::db {
                 &attestation_nodes,
                 node_fee_amount,
                 cross_validation_extra_nodes_count,
+                ctx,
             );
 
             // Refund the remaining funds back to the user
@@ -2070,12 +3666,122 @@ module atoma::db {
         };
 
         // Transfer the total node fee to the node
-        self.transfer_funds(total_node_fee, ctx.sender(), ctx);
+        self.transfer_node_fee(node_badge.small_id, total_node_fee, ctx);
 
         // We distribute the remaining funds to the node
         self.withdraw_fees(node_badge, ctx);
     }
 
+    /// The owner of a stack proposes to close it before it's exhausted, eg.
+    /// because the model is deprecated, with the number of compute units
+    /// that the node served so far.
+    /// The stack is closed once the node accepts with the same number, see
+    /// `accept_stack_close_as_node`.
+    ///
+    /// Replaces any pending proposal, which is how either party counters.
+    ///
+    /// # Aborts
+    /// * `EStackNotFound` - If the stack does not exist.
+    /// * `ENotStackOwner` - If the sender does not own the stack.
+    /// * `EStackAlreadyClaimed` - If the stack was claimed already.
+    /// * `EStackInSettlementDispute` - If the node already tried to settle
+    ///   the stack.
+    /// * `ETooManyComputedUnits` - If more compute units are proposed than
+    ///   the stack has.
+    public entry fun propose_stack_close(
+        self: &mut AtomaDb,
+        stack_small_id: u64,
+        num_consumed_compute_units: u64,
+        ctx: &TxContext,
+    ) {
+        let stack_small_id = StackSmallId { inner: stack_small_id };
+        self.assert_can_close_stack(stack_small_id, num_consumed_compute_units);
+        assert!(self.stacks.borrow(stack_small_id).owner == ctx.sender(), ENotStackOwner);
+
+        self.add_stack_close_proposal(stack_small_id, num_consumed_compute_units, false);
+    }
+
+    /// The node selected for a stack proposes to close it before it's
+    /// exhausted, eg. because the node is leaving the network.
+    /// The stack is closed once its owner accepts, see `accept_stack_close`.
+    ///
+    /// See `propose_stack_close`.
+    ///
+    /// # Aborts
+    /// * `ENodeNotSelectedForStack` - If the node was not selected for the
+    ///   stack.
+    ///
+    /// See `propose_stack_close` for the rest.
+    public entry fun propose_stack_close_as_node(
+        self: &mut AtomaDb,
+        node_badge: &NodeBadge,
+        stack_small_id: u64,
+        num_consumed_compute_units: u64,
+    ) {
+        let stack_small_id = StackSmallId { inner: stack_small_id };
+        self.assert_can_close_stack(stack_small_id, num_consumed_compute_units);
+        assert!(
+            self.stacks.borrow(stack_small_id).selected_node_id == node_badge.small_id,
+            ENodeNotSelectedForStack,
+        );
+
+        self.add_stack_close_proposal(stack_small_id, num_consumed_compute_units, true);
+    }
+
+    /// The owner of a stack accepts the node's proposal to close it.
+    ///
+    /// The number of compute units is repeated so that the node cannot
+    /// replace the proposal with worse terms before this is executed.
+    ///
+    /// # Aborts
+    /// * `EStackCloseNotProposed` - If the node has not proposed to close
+    ///   the stack.
+    /// * `EStackCloseTermsMismatch` - If the node proposed a different number
+    ///   of compute units.
+    ///
+    /// See `propose_stack_close` for the rest.
+    public entry fun accept_stack_close(
+        self: &mut AtomaDb,
+        stack_small_id: u64,
+        num_consumed_compute_units: u64,
+        ctx: &mut TxContext,
+    ) {
+        let stack_small_id = StackSmallId { inner: stack_small_id };
+        self.assert_can_close_stack(stack_small_id, num_consumed_compute_units);
+        assert!(self.stacks.borrow(stack_small_id).owner == ctx.sender(), ENotStackOwner);
+
+        self.close_stack(stack_small_id, num_consumed_compute_units, true, ctx);
+    }
+
+    /// The node selected for a stack accepts its owner's proposal to close
+    /// it.
+    ///
+    /// See `accept_stack_close`.
+    ///
+    /// # Aborts
+    /// * `ENodeNotSelectedForStack` - If the node was not selected for the
+    ///   stack.
+    /// * `EStackCloseNotProposed` - If the owner has not proposed to close
+    ///   the stack.
+    ///
+    /// See `accept_stack_close` for the rest.
+    public entry fun accept_stack_close_as_node(
+        self: &mut AtomaDb,
+        node_badge: &NodeBadge,
+        stack_small_id: u64,
+        num_consumed_compute_units: u64,
+        ctx: &mut TxContext,
+    ) {
+        let stack_small_id = StackSmallId { inner: stack_small_id };
+        self.assert_can_close_stack(stack_small_id, num_consumed_compute_units);
+        assert!(
+            self.stacks.borrow(stack_small_id).selected_node_id == node_badge.small_id,
+            ENodeNotSelectedForStack,
+        );
+
+        self.close_stack(stack_small_id, num_consumed_compute_units, false, ctx);
+    }
+
     /// Initiates an attestation dispute for a stack settlement.
     ///
     /// When an attestation node discovers a discrepancy between their computed result and the original node's 
@@ -2130,15 +3836,18 @@ module atoma::db {
         
         assert!(task.security_level.inner == SamplingConsensus, ETaskSecurityLevelNotSamplingConsensus);
         stack_settlement_ticket.is_in_dispute = true;
+        let original_node_id = stack_settlement_ticket.selected_node_id;
 
 
         sui::event::emit(StackAttestationDisputeEvent {
             stack_small_id,
             attestation_commitment,
             attestation_node_id: node_badge.small_id,
-            original_node_id: stack_settlement_ticket.selected_node_id,
+            original_node_id,
             original_commitment: stack_settlement_ticket.committed_stack_proof,
         });
+
+        self.reset_dispute_free_settlements_streak(original_node_id);
     }
 
     /// The node owner announces that they can serve prompts for the given
@@ -2255,6 +3964,8 @@ module atoma::db {
             confidential_compute_last_rotation_counter: _,
             confidential_compute_device_types: _,
         } = self.nodes.remove(node_badge.small_id);
+        self.remove_node_reputation(node_badge.small_id);
+        self.remove_heartbeat(node_badge.small_id);
 
         let was_disabled_in_epoch = was_disabled_in_epoch.extract();
         assert!(was_disabled_in_epoch + 4 <= ctx.epoch(), ENodeMustWaitBeforeDestroy);
@@ -2266,6 +3977,109 @@ module atoma::db {
         badge_id.delete();
     }
 
+    /// Opts the node into the insurance pool.
+    ///
+    /// From then on, the node pays `premium_permille` of its fee
+    /// for every stack it claims into the pool.
+    /// In exchange, the owners of stacks that the node was selected for can
+    /// file claims that the manager pays out of the pool, see
+    /// `file_insurance_claim`.
+    public entry fun join_insurance_pool(
+        self: &mut AtomaDb,
+        node_badge: &NodeBadge,
+        ctx: &TxContext,
+    ) {
+        let node_small_id = node_badge.small_id;
+        let members = &mut self.insurance_pool_mut().members;
+        assert!(!members.contains(node_small_id), ENodeAlreadyInsured);
+        members.add(node_small_id, InsuranceMembership {
+            joined_in_epoch: ctx.epoch(),
+            premiums_paid: 0,
+        });
+
+        sui::event::emit(NodeJoinedInsurancePoolEvent { node_small_id });
+    }
+
+    /// The premiums the node paid stay in the pool.
+    /// Claims that were already filed for the node's stacks are still
+    /// resolved by the manager.
+    public entry fun leave_insurance_pool(
+        self: &mut AtomaDb,
+        node_badge: &NodeBadge,
+    ) {
+        let node_small_id = node_badge.small_id;
+        let members = &mut self.insurance_pool_mut().members;
+        assert!(members.contains(node_small_id), ENodeNotInsured);
+        members.remove(node_small_id);
+
+        sui::event::emit(NodeLeftInsurancePoolEvent { node_small_id });
+    }
+
+    /// The owner of a stack whose node is insured files a claim for the
+    /// amount it lost, eg. because the node failed and its slashed
+    /// collateral did not make the owner whole.
+    ///
+    /// The claim is at most what the stack cost, see `stack_cost`, and is
+    /// paid out once the manager approves it, see `pay_insurance_claim`.
+    ///
+    /// # Aborts
+    /// * `EStackNotFound` - If the stack does not exist.
+    /// * `ENotStackOwner` - If the sender does not own the stack.
+    /// * `ENodeNotInsured` - If the stack's node is not in the insurance pool.
+    /// * `EInsuranceClaimAlreadyFiled` - If the stack was claimed before.
+    /// * `EInvalidInsuranceClaimAmount` - If the amount is zero or more than
+    ///   the stack cost.
+    public entry fun file_insurance_claim(
+        self: &mut AtomaDb,
+        stack_small_id: u64,
+        amount: u64,
+        ctx: &TxContext,
+    ) {
+        let stack_small_id = StackSmallId { inner: stack_small_id };
+        assert!(self.stacks.contains(stack_small_id), EStackNotFound);
+        let (owner, node_small_id, stack_cost) = {
+            let stack = self.stacks.borrow(stack_small_id);
+            let task = self.tasks.borrow(stack.task_small_id);
+            let stack_cost = self.stack_cost(
+                task.security_level,
+                stack.price_per_one_million_compute_units,
+                stack.num_compute_units,
+            );
+            (stack.owner, stack.selected_node_id, stack_cost)
+        };
+        assert!(owner == ctx.sender(), ENotStackOwner);
+        let pool = self.insurance_pool_mut();
+        assert!(pool.members.contains(node_small_id), ENodeNotInsured);
+        assert!(!pool.claims.contains(stack_small_id), EInsuranceClaimAlreadyFiled);
+        assert!(amount > 0 && amount <= stack_cost, EInvalidInsuranceClaimAmount);
+
+        pool.claims.add(stack_small_id, InsuranceClaim {
+            claimant: owner,
+            node_small_id,
+            amount,
+            is_resolved: false,
+        });
+
+        sui::event::emit(InsuranceClaimFiledEvent {
+            stack_small_id,
+            claimant: owner,
+            node_small_id,
+            amount,
+        });
+    }
+
+    /// Transfers all the fees accrued by the sender as a referrer of stacks.
+    public entry fun claim_referral_fees(
+        self: &mut AtomaDb,
+        ctx: &mut TxContext,
+    ) {
+        let referrer = ctx.sender();
+        let balances = &mut self.referrals_mut().balances;
+        assert!(balances.contains(referrer), ENoReferralFeesToClaim);
+        let amount = balances.remove(referrer);
+        self.transfer_funds(amount, referrer, ctx);
+    }
+
     /// Transfers a coin object to the sender if there are some fees to be
     /// claimed for this node.
     public entry fun withdraw_fees(
@@ -2281,11 +4095,8 @@ module atoma::db {
 
         let node = self.nodes.borrow_mut(node_id);
         let amount = node.available_fee_amount;
-        if (amount > 0) {
-            node.available_fee_amount = 0;
-            let wallet = coin::from_balance(self.fee_treasury.split(amount), ctx);
-            transfer::public_transfer(wallet, ctx.sender());
-        };
+        node.available_fee_amount = 0;
+        self.transfer_node_fee(node_id, amount, ctx);
     }
 
     public fun is_oracle(
@@ -2334,6 +4145,51 @@ module atoma::db {
         &self.nodes
     }
 
+    /// How much the referrer can claim with `claim_referral_fees`.
+    public fun get_referral_balance(self: &AtomaDb, referrer: address): u64 {
+        if (!dynamic_field::exists_(&self.id, ReferralsKey {})) {
+            return 0
+        };
+        let balances = &self.referrals().balances;
+        if (balances.contains(referrer)) {
+            *balances.borrow(referrer)
+        } else {
+            0
+        }
+    }
+
+    /// The reputation score of the node in the current epoch, with decay
+    /// applied.
+    public fun get_node_reputation_score(self: &AtomaDb, node_small_id: u64, ctx: &TxContext): u8 {
+        self.decayed_reputation_score(NodeSmallId { inner: node_small_id }, ctx.epoch())
+    }
+
+    public fun get_node_last_heartbeat_epoch(self: &AtomaDb, node_small_id: u64): u64 {
+        self.node_last_heartbeat_epoch(NodeSmallId { inner: node_small_id })
+    }
+
+    public fun get_node_dispute_free_settlements_streak(self: &AtomaDb, node_small_id: u64): u64 {
+        let node_small_id = NodeSmallId { inner: node_small_id };
+        if (self.has_node_reputation(node_small_id)) {
+            self.node_reputations().borrow(node_small_id).dispute_free_settlements_streak
+        } else {
+            0
+        }
+    }
+
+
+    public fun get_stack_dispute_window_epochs(self: &AtomaDb): u64 {
+        self.stack_dispute_window_epochs()
+    }
+
+    public fun is_node_insured(self: &AtomaDb, node_small_id: u64): bool {
+        self.is_insured(NodeSmallId { inner: node_small_id })
+    }
+
+    public fun get_insurance_pool_balance(self: &AtomaDb): u64 {
+        self.insurance_pool().balance.value()
+    }
+
     public fun get_model_echelon_performance(self: &ModelEchelon): u64 {
         self.relative_performance
     }
@@ -2342,6 +4198,101 @@ module atoma::db {
         self.settlement_timeout_ms
     }
 
+    /// Median latency of the echelon's nodes measured by gateway probes, as
+    /// recorded by one of the oracles, see `record_model_echelon_latency`.
+    public fun get_model_echelon_latency_p50_ms(
+        self: &AtomaDb,
+        model_name: ascii::String,
+        echelon_id: EchelonId,
+    ): Option<u64> {
+        let key = ModelEchelonKey { model_name, echelon_id };
+        if (dynamic_field::exists_(&self.id, ModelEchelonLatenciesKey {})
+            && self.model_echelon_latencies().contains(key)) {
+            option::some(*self.model_echelon_latencies().borrow(key))
+        } else {
+            option::none()
+        }
+    }
+
+    public fun get_node_region(self: &AtomaDb, node_small_id: u64): Option<ascii::String> {
+        let node_small_id = NodeSmallId { inner: node_small_id };
+        if (dynamic_field::exists_(&self.id, NodeRegionsKey {})
+            && self.node_regions().contains(node_small_id)) {
+            option::some(*self.node_regions().borrow(node_small_id))
+        } else {
+            option::none()
+        }
+    }
+
+    /// Major, minor and patch, if the node reported a version.
+    public fun get_node_software_version(
+        self: &AtomaDb, node_small_id: u64,
+    ): Option<vector<u16>> {
+        let node_small_id = NodeSmallId { inner: node_small_id };
+        if (!dynamic_field::exists_(&self.id, NodeSoftwareVersionsKey {})) {
+            return option::none()
+        };
+        let versions: &Table<NodeSmallId, SoftwareVersion> =
+            dynamic_field::borrow(&self.id, NodeSoftwareVersionsKey {});
+        if (versions.contains(node_small_id)) {
+            let version = versions.borrow(node_small_id);
+            option::some(vector[version.major, version.minor, version.patch])
+        } else {
+            option::none()
+        }
+    }
+
+    /// The recipients and their shares in basis points, empty if the node is
+    /// paid alone.
+    public fun get_node_payout_split(
+        self: &AtomaDb, node_small_id: u64,
+    ): (vector<address>, vector<u64>) {
+        let node_small_id = NodeSmallId { inner: node_small_id };
+        let mut recipients = vector::empty();
+        let mut shares_bps = vector::empty();
+        let split = self.node_payout_split(node_small_id);
+        let mut index = 0;
+        while (index < split.length()) {
+            recipients.push_back(split[index].recipient);
+            shares_bps.push_back(split[index].bps);
+            index = index + 1;
+        };
+        (recipients, shares_bps)
+    }
+
+    /// The severity, message and effective epoch of the announcement.
+    public fun get_announcement(
+        self: &AtomaDb, announcement_id: u64,
+    ): (u8, string::String, u64) {
+        assert!(dynamic_field::exists_(&self.id, AnnouncementsKey {}), EAnnouncementNotFound);
+        let entries = &self.announcements().entries;
+        assert!(entries.contains(announcement_id), EAnnouncementNotFound);
+        let announcement = entries.borrow(announcement_id);
+        (announcement.severity, announcement.message, announcement.effective_epoch)
+    }
+
+    /// Zero if the task has no minimum price, see `set_task_min_price`.
+    public fun get_task_min_price(self: &AtomaDb, task_small_id: u64): u64 {
+        task_min_price(&self.id, TaskSmallId { inner: task_small_id })
+    }
+
+    public fun get_node_offer_key(self: &AtomaDb, node_small_id: u64): Option<vector<u8>> {
+        let node_small_id = NodeSmallId { inner: node_small_id };
+        if (dynamic_field::exists_(&self.id, NodeOfferKeysKey {})
+            && self.node_offer_keys().contains(node_small_id)) {
+            option::some(*self.node_offer_keys().borrow(node_small_id))
+        } else {
+            option::none()
+        }
+    }
+
+    /// Whether the offer was accepted or cancelled.
+    public fun is_stack_offer_consumed(self: &AtomaDb, node_small_id: u64, nonce: u64): bool {
+        let node_small_id = NodeSmallId { inner: node_small_id };
+        dynamic_field::exists_(&self.id, ConsumedOffersKey {})
+            && self.consumed_offers().contains(OfferNonce { node_small_id, nonce })
+    }
+
     public fun get_node_id(self: &NodeBadge): NodeSmallId { self.small_id }
 
     public fun get_opaque_inner_id(self: NodeSmallId): u64 { self.inner }
@@ -2350,6 +4301,15 @@ module atoma::db {
         self.models.borrow(model_name).modality
     }
 
+    /// Either a protocol modality or a custom one.
+    public fun is_valid_modality(modality: u64): bool {
+        modality == Text2TextModality
+            || modality == Text2ImageModality
+            || modality == TextEmbeddingModality
+            || modality == AudioModality
+            || modality >= FirstCustomModality
+    }
+
     public fun get_cross_validation_probability_permille(self: &AtomaDb): u64 {
         self.cross_validation_probability_permille
     }
@@ -2425,14 +4385,17 @@ module atoma::db {
     /// slashed by some ‰ amount.
     /// It's possible that the node is slashed to zero balance, in which case it
     /// won't participate in new prompts.
+    ///
+    /// The node also loses `REPUTATION_TIMEOUT_PENALTY` reputation points.
     public(package) fun slash_node_on_timeout(
-        self: &mut AtomaDb, node_id: NodeSmallId,
+        self: &mut AtomaDb, node_id: NodeSmallId, ctx: &TxContext,
     ): Balance<USDC> {
         let has_node = self.nodes.contains(node_id);
         if (!has_node) {
             // this node has already been removed
             return balance::zero()
         };
+        self.penalize_node_reputation(node_id, REPUTATION_TIMEOUT_PENALTY, ctx.epoch());
 
         let node = self.nodes.borrow_mut(node_id);
         let collateral = node.collateral.value();
@@ -2451,13 +4414,16 @@ module atoma::db {
     }
 
     /// Takes away all node's collateral.
+    ///
+    /// The node also loses `REPUTATION_DISPUTE_PENALTY` reputation points.
     public(package) fun slash_node_on_dispute(
-        self: &mut AtomaDb, node_id: NodeSmallId,
+        self: &mut AtomaDb, node_id: NodeSmallId, ctx: &TxContext,
     ): Balance<USDC> {
         if (!self.nodes.contains(node_id)) {
             // this node has already been removed
             balance::zero()
         } else {
+            self.penalize_node_reputation(node_id, REPUTATION_DISPUTE_PENALTY, ctx.epoch());
             self.nodes.borrow_mut(node_id).collateral.withdraw_all()
         }
     }
@@ -2567,6 +4533,7 @@ module atoma::db {
     /// 3. Its maximum number of compute units is greater than or equal to the required amount.
     /// 4. It has a positive collateral balance.
     /// 5. It is not disabled.
+    /// 6. It reported at least the minimum software version of the task.
     ///
     /// # Arguments
     /// * `self` - A mutable reference to the AtomaDb object.
@@ -2600,6 +4567,7 @@ module atoma::db {
         num_compute_units: u64,
         rng: &mut sui::random::RandomGenerator,
     ): NodeSmallId {
+        let min_version = self.task_min_node_version(task_small_id);
         let task = self.tasks.borrow_mut(task_small_id);
         let mut nodes_count = table_vec::length(&task.subscribed_nodes_small_ids);
         if (nodes_count == 0) {
@@ -2626,6 +4594,7 @@ module atoma::db {
             
             if (node_price_per_one_million_compute_units <= price_per_one_million_compute_units_cap 
                 && node_max_num_compute_units >= num_compute_units 
+                && node_software_version(&self.id, node_id) >= min_version
                 && self.nodes.contains(node_id)) 
             {
                 let node = self.nodes.borrow(node_id);
@@ -2674,6 +4643,7 @@ module atoma::db {
     ///   b. They offer at least the required number of compute units.
     ///   c. They have positive collateral.
     ///   d. They are not disabled.
+    ///   e. They reported at least the minimum software version of the task.
     /// - If there are not enough eligible nodes, the function returns all eligible nodes.
     ///   This ensures that stack settlement can proceed even with fewer attestation nodes than ideal.
     ///
@@ -2687,6 +4657,7 @@ module atoma::db {
         num_compute_units: u64,
         rng: &mut sui::random::RandomGenerator,
     ): vector<NodeSmallId> {
+        let min_version = self.task_min_node_version(task_small_id);
         let task = self.tasks.borrow(task_small_id);
         let subscribed_nodes = &task.subscribed_nodes;
         let subscribed_nodes_small_ids = &task.subscribed_nodes_small_ids;
@@ -2704,6 +4675,7 @@ module atoma::db {
             let node_max_num_compute_units = node_price_data.max_num_compute_units; 
             if (node_price_per_one_million_compute_units <= price_per_one_million_compute_units_cap 
                 && node_max_num_compute_units >= num_compute_units 
+                && node_software_version(&self.id, node_id) >= min_version
                 && self.nodes.contains(node_id)) 
             {
                 let node = self.nodes.borrow(node_id);
@@ -2850,6 +4822,7 @@ module atoma::db {
     /// * `attestation_nodes` - A reference to a vector of SmallIds representing the attestation nodes.
     /// * `node_fee_amount` - The total fee amount allocated for attestation nodes.
     /// * `cross_validation_extra_nodes_count` - The number of extra nodes used for cross-validation.
+    /// * `ctx` - The transaction context, slashed nodes lose reputation as of its epoch.
     ///
     /// # Behavior
     /// For each attestation node:
@@ -2875,6 +4848,7 @@ module atoma::db {
         attestation_nodes: &vector<NodeSmallId>,
         node_fee_amount: u64,
         cross_validation_extra_nodes_count: u64,
+        ctx: &TxContext,
     ) {
         let num_attestation_nodes = vector::length(attestation_nodes);
         if (num_attestation_nodes > 0) {
@@ -2896,7 +4870,7 @@ module atoma::db {
                 } else {
                     // The selected attestation node has not submitted its attestation
                     // so we should partially slash its collateral
-                    let conficasted_balance = self.slash_node_on_timeout(attestation_node_id);
+                    let conficasted_balance = self.slash_node_on_timeout(attestation_node_id, ctx);
                     self.deposit_to_communal_treasury(conficasted_balance);
                 };
                 attestation_node_index = attestation_node_index + 1;
@@ -2947,6 +4921,7 @@ module atoma::db {
                 num_compute_units: _,
                 is_claimed: _,
             } = table::remove(&mut self.stacks, stack_small_id);
+        self.remove_stack_close_proposal(stack_small_id);
     }
 
     // =========================================================================
@@ -3062,6 +5037,50 @@ module atoma::db {
         };
     }
 
+    /// Removes nodes from the whitelist of a private task.
+    ///
+    /// A removed node that is subscribed to the task is unsubscribed so that
+    /// it's not selected for new stacks.
+    /// Existing stacks are not affected.
+    /// The small ID is swap removed lazily when sampling, as with
+    /// `unsubscribe_node_from_task`.
+    ///
+    /// # Aborts
+    /// * `ETaskNotFound` - If the specified task does not exist.
+    /// * `ETaskIsPublic` - If the specified task is public.
+    /// * `ENodeNotWhitelistedForTask` - If any of the nodes is not whitelisted.
+    public entry fun remove_nodes_from_task_whitelist(
+        self: &mut AtomaDb,
+        _: &AtomaManagerBadge,
+        task_small_id: u64,
+        nodes: vector<u64>,
+    ) {
+        let task_small_id = TaskSmallId { inner: task_small_id };
+        assert!(object_table::contains(&self.tasks, task_small_id), ETaskNotFound);
+
+        let task = self.tasks.borrow_mut(task_small_id);
+        assert!(!task.is_public, ETaskIsPublic);
+
+        let mut node_index = 0;
+        let num_nodes = vector::length(&nodes);
+        while (node_index < num_nodes) {
+            let node_small_id = NodeSmallId { inner: nodes[node_index] };
+            let (is_whitelisted, whitelist_index) =
+                vector::index_of(&task.whitelisted_nodes, &node_small_id);
+            assert!(is_whitelisted, ENodeNotWhitelistedForTask);
+            task.whitelisted_nodes.remove(whitelist_index);
+
+            if (task.subscribed_nodes.contains(node_small_id)) {
+                table::remove(&mut task.subscribed_nodes, node_small_id);
+                sui::event::emit(NodeUnsubscribedFromTaskEvent {
+                    node_small_id,
+                    task_small_id,
+                });
+            };
+            node_index = node_index + 1;
+        };
+    }
+
     /// Triggers a network-wide rotation of node public keys by emitting a key rotation event.
     ///
     /// This function allows the Atoma network administrator to initiate a coordinated rotation
@@ -3146,11 +5165,16 @@ module atoma::db {
         task_id.delete();
         subscribed_nodes.drop();
         subscribed_nodes_small_ids.drop();
+        self.remove_task_constraints(task_small_id);
     }
 
-    /// As per the gate module:
+    /// Known modalities:
     /// - Text2TextModality = 0
     /// - Text2ImageModality = 1
+    /// - TextEmbeddingModality = 2
+    /// - AudioModality = 3
+    ///
+    /// Values from 1000 on are custom.
     public entry fun add_model_entry(
         self: &mut AtomaDb,
         badge: &AtomaManagerBadge,
@@ -3170,13 +5194,14 @@ module atoma::db {
         self.models.add(model.name, model);
     }
 
-    /// For modality identifiers, see the gate module.
+    /// For modality identifiers, see `add_model_entry`.
     public fun create_model(
         _: &AtomaManagerBadge,
         model_name: ascii::String,
         modality: u64,
         ctx: &mut TxContext,
     ): ModelEntry {
+        assert!(is_valid_modality(modality), EInvalidModality);
         ModelEntry {
             id: object::new(ctx),
             name: model_name,
@@ -3258,7 +5283,7 @@ module atoma::db {
         let len = vector::length(&echelons);
         while (index < len) {
             let ModelEchelon {
-                id: _,
+                id: echelon_id,
                 input_fee_per_token: _,
                 output_fee_per_token: _,
                 relative_performance: _,
@@ -3267,6 +5292,7 @@ module atoma::db {
                 nodes,
             } = vector::pop_back(&mut echelons);
             nodes.drop();
+            self.remove_model_echelon_latency(model_name, echelon_id);
         };
 
         echelons.destroy_empty();
@@ -3290,6 +5316,7 @@ module atoma::db {
             nodes,
         } = remove_echelon(&mut model.echelons, echelon_id);
         nodes.drop();
+        self.remove_model_echelon_latency(model_name, echelon_id);
     }
 
     public entry fun disable_model(
@@ -3326,14 +5353,87 @@ module atoma::db {
         self.registration_collateral_in_protocol_token = new_required_collateral;
     }
 
-    public entry fun set_permille_to_slash_node_on_timeout(
+
+    /// Approves an insurance claim and pays `payout_permille` of the claimed
+    /// amount to the claimant, capped by `max_payout_per_claim` and by
+    /// what's in the pool.
+    public entry fun pay_insurance_claim(
         self: &mut AtomaDb,
         _: &AtomaManagerBadge,
-        new_permille: u64,
+        stack_small_id: u64,
+        ctx: &mut TxContext,
     ) {
-        assert!(new_permille <= 1000, ETotalPermilleMustBeLessThan1000);
-        self.permille_to_slash_node_on_timeout = new_permille;
-    }
+        let stack_small_id = StackSmallId { inner: stack_small_id };
+        let (claimant, node_small_id, amount) = self.resolve_insurance_claim(stack_small_id);
+
+        let pool = self.insurance_pool_mut();
+        let mut payout = amount * pool.payout_permille / 1000;
+        if (payout > pool.max_payout_per_claim) {
+            payout = pool.max_payout_per_claim;
+        };
+        if (payout > pool.balance.value()) {
+            payout = pool.balance.value();
+        };
+        if (payout > 0) {
+            let wallet = coin::from_balance(pool.balance.split(payout), ctx);
+            transfer::public_transfer(wallet, claimant);
+        };
+
+        sui::event::emit(InsuranceClaimResolvedEvent {
+            stack_small_id,
+            claimant,
+            node_small_id,
+            is_approved: true,
+            payout,
+        });
+    }
+
+    /// The stack cannot be claimed again.
+    public entry fun reject_insurance_claim(
+        self: &mut AtomaDb,
+        _: &AtomaManagerBadge,
+        stack_small_id: u64,
+    ) {
+        let stack_small_id = StackSmallId { inner: stack_small_id };
+        let (claimant, node_small_id, _) = self.resolve_insurance_claim(stack_small_id);
+
+        sui::event::emit(InsuranceClaimResolvedEvent {
+            stack_small_id,
+            claimant,
+            node_small_id,
+            is_approved: false,
+            payout: 0,
+        });
+    }
+
+    /// The payout rules of the insurance pool.
+    /// They apply to premiums of stacks claimed from now on and to claims
+    /// paid from now on.
+    public entry fun set_insurance_params(
+        self: &mut AtomaDb,
+        _: &AtomaManagerBadge,
+        premium_permille: u64,
+        payout_permille: u64,
+        max_payout_per_claim: u64,
+    ) {
+        assert!(
+            premium_permille <= 1000 && payout_permille <= 1000,
+            EInvalidInsuranceParams,
+        );
+        let pool = self.insurance_pool_mut();
+        pool.premium_permille = premium_permille;
+        pool.payout_permille = payout_permille;
+        pool.max_payout_per_claim = max_payout_per_claim;
+    }
+
+    public entry fun set_permille_to_slash_node_on_timeout(
+        self: &mut AtomaDb,
+        _: &AtomaManagerBadge,
+        new_permille: u64,
+    ) {
+        assert!(new_permille <= 1000, ETotalPermilleMustBeLessThan1000);
+        self.permille_to_slash_node_on_timeout = new_permille;
+    }
 
     public entry fun set_permille_for_oracle_on_dispute(
         self: &mut AtomaDb,
@@ -3371,8 +5471,19 @@ module atoma::db {
         let model = self.models.borrow_mut(model_name);
         let echelon_id = EchelonId { id: echelon };
         let echelon = get_echelon_mut(&mut model.echelons, echelon_id);
+        let old_input_fee_per_token = echelon.input_fee_per_token;
+        let old_output_fee_per_token = echelon.output_fee_per_token;
         echelon.input_fee_per_token = new_input_fee_per_token;
         echelon.output_fee_per_token = new_output_fee_per_token;
+
+        sui::event::emit(ModelEchelonFeeUpdatedEvent {
+            model_name,
+            echelon_id,
+            old_input_fee_per_token,
+            old_output_fee_per_token,
+            new_input_fee_per_token,
+            new_output_fee_per_token,
+        });
     }
 
     public entry fun add_model_echelon_oracle_node(
@@ -3402,6 +5513,9 @@ module atoma::db {
         echelon.oracles.remove(&NodeSmallId { inner: node_small_id });
     }
 
+    /// The timeout must be between `MinSettlementTimeoutMs` and
+    /// `MaxSettlementTimeoutMs`.
+    /// It only applies to tickets created after the change.
     public entry fun set_model_echelon_settlement_timeout_ms(
         self: &mut AtomaDb,
         _: &AtomaManagerBadge,
@@ -3409,10 +5523,23 @@ module atoma::db {
         echelon: u64,
         new_timeout_ms: u64,
     ) {
+        assert!(
+            new_timeout_ms >= MinSettlementTimeoutMs
+                && new_timeout_ms <= MaxSettlementTimeoutMs,
+            EInvalidSettlementTimeout,
+        );
         let model = self.models.borrow_mut(model_name);
         let echelon_id = EchelonId { id: echelon };
         let echelon = get_echelon_mut(&mut model.echelons, echelon_id);
+        let old_timeout_ms = echelon.settlement_timeout_ms;
         echelon.settlement_timeout_ms = new_timeout_ms;
+
+        sui::event::emit(ModelEchelonSettlementTimeoutUpdatedEvent {
+            model_name,
+            echelon_id,
+            old_timeout_ms,
+            new_timeout_ms,
+        });
     }
 
     public entry fun set_cross_validation_probability_permille(
@@ -3420,6 +5547,7 @@ module atoma::db {
         _: &AtomaManagerBadge,
         new_probability_permille: u64,
     ) {
+        assert!(new_probability_permille <= 1000, EInvalidCrossValidationProbability);
         self.cross_validation_probability_permille = new_probability_permille;
     }
 
@@ -3428,6 +5556,7 @@ module atoma::db {
         _: &AtomaManagerBadge,
         new_extra_nodes_count: u64,
     ) {
+        assert!(new_extra_nodes_count > 0, EInvalidCrossValidationExtraNodesCount);
         self.cross_validation_extra_nodes_count = new_extra_nodes_count;
     }
 
@@ -3447,10 +5576,475 @@ module atoma::db {
         self.cross_validation_extra_nodes_charge_permille = new_charge_permille;
     }
 
+    /// Sets all parameters of sampling consensus at once so that stacks are
+    /// never priced with a mix of old and new parameters.
+    ///
+    /// # Arguments
+    /// * `cross_validation_probability_permille` - The probability that a
+    ///   stack settlement samples attestation nodes, at most 1000.
+    /// * `cross_validation_extra_nodes_count` - How many attestation nodes
+    ///   are sampled, at least one.
+    /// * `sampling_consensus_charge_permille` - What share of the stack price
+    ///   the user pays for the selected node.
+    /// * `cross_validation_extra_nodes_charge_permille` - What share of the
+    ///   stack price the user pays on top for the attestation nodes.
+    ///
+    /// # Errors
+    /// * `EInvalidCrossValidationProbability` - If the probability exceeds 1000.
+    /// * `EInvalidCrossValidationExtraNodesCount` - If the node count is zero.
+    public entry fun set_sampling_consensus_params(
+        self: &mut AtomaDb,
+        badge: &AtomaManagerBadge,
+        cross_validation_probability_permille: u64,
+        cross_validation_extra_nodes_count: u64,
+        sampling_consensus_charge_permille: u64,
+        cross_validation_extra_nodes_charge_permille: u64,
+    ) {
+        self.set_cross_validation_probability_permille(badge, cross_validation_probability_permille);
+        self.set_cross_validation_extra_nodes_count(badge, cross_validation_extra_nodes_count);
+        self.set_sampling_consensus_charge_permille(badge, sampling_consensus_charge_permille);
+        self.set_cross_validation_extra_nodes_charge_permille(badge, cross_validation_extra_nodes_charge_permille);
+
+        sui::event::emit(SamplingConsensusParamsUpdatedEvent {
+            cross_validation_probability_permille,
+            cross_validation_extra_nodes_count,
+            sampling_consensus_charge_permille,
+            cross_validation_extra_nodes_charge_permille,
+        });
+    }
+
+    /// # Arguments
+    /// * `threshold_epochs` - How many epochs without a heartbeat make a
+    ///   node stale, at least one.
+    /// * `reward` - What the sweeper of a stale node is paid from the
+    ///   communal treasury.
+    ///
+    /// # Errors
+    /// * `EInvalidStaleNodeThreshold` - If the threshold is zero.
+    public entry fun set_stale_node_sweep_params(
+        self: &mut AtomaDb,
+        _: &AtomaManagerBadge,
+        threshold_epochs: u64,
+        reward: u64,
+    ) {
+        assert!(threshold_epochs > 0, EInvalidStaleNodeThreshold);
+        let sweeps = self.stale_node_sweeps_mut();
+        sweeps.threshold_epochs = threshold_epochs;
+        sweeps.reward = reward;
+    }
+
+    /// # Arguments
+    /// * `min_age_epochs` - How many epochs after `claim_funds_for_stacks`
+    ///   a stack can be collected, at least one.
+    /// * `bounty` - What the collector is paid per stack from the communal
+    ///   treasury.
+    ///
+    /// # Errors
+    /// * `EInvalidStackGcMinAge` - If the age is zero.
+    public entry fun set_stack_gc_params(
+        self: &mut AtomaDb,
+        _: &AtomaManagerBadge,
+        min_age_epochs: u64,
+        bounty: u64,
+    ) {
+        assert!(min_age_epochs > 0, EInvalidStackGcMinAge);
+        let gc = self.stack_gc_mut();
+        gc.min_age_epochs = min_age_epochs;
+        gc.bounty = bounty;
+    }
+
+    /// How many epochs after `try_settle_stack` the attestation nodes have to
+    /// dispute the settlement.
+    /// Only once the window closed can the node claim the funds of the stack
+    /// with `claim_funds`.
+    /// Applies to stacks settled from now on.
+    ///
+    /// # Errors
+    /// * `EInvalidStackDisputeWindow` - If the window is zero epochs, which
+    ///   would make settlements final right away.
+    public entry fun set_stack_dispute_window(
+        self: &mut AtomaDb,
+        _: &AtomaManagerBadge,
+        epochs: u64,
+    ) {
+        assert!(epochs > 0, EInvalidStackDisputeWindow);
+        *dynamic_field::borrow_mut(&mut self.id, StackDisputeWindowEpochsKey {}) = epochs;
+    }
+
+    /// From now on, only nodes that reported at least the given version with
+    /// `set_node_software_version` are sampled for the stacks of the task,
+    /// including as attestation nodes, or can sell them through quotes and
+    /// offers.
+    /// Nodes that are already processing a stack keep processing it.
+    ///
+    /// Prompts are not affected, echelons group nodes by their software
+    /// already.
+    ///
+    /// A version of 0.0.0 removes the minimum.
+    ///
+    /// # Errors
+    /// * `ETaskNotFound` - If the task does not exist.
+    public entry fun set_task_min_node_version(
+        self: &mut AtomaDb,
+        _: &AtomaManagerBadge,
+        task_small_id: u64,
+        major: u16,
+        minor: u16,
+        patch: u16,
+    ) {
+        let task_small_id = TaskSmallId { inner: task_small_id };
+        assert!(self.tasks.contains(task_small_id), ETaskNotFound);
+
+        let min_version = SoftwareVersion { major, minor, patch };
+        let min_versions: &mut Table<TaskSmallId, SoftwareVersion> =
+            dynamic_field::borrow_mut(&mut self.id, TaskMinNodeVersionsKey {});
+        if (min_versions.contains(task_small_id)) {
+            min_versions.remove(task_small_id);
+        };
+        if (software_version_as_u64(min_version) > 0) {
+            min_versions.add(task_small_id, min_version);
+        };
+
+        sui::event::emit(TaskMinNodeVersionUpdatedEvent { task_small_id, min_version });
+    }
+
+    /// From now on, nodes must ask at least the given price per one million
+    /// compute units to subscribe to the task or to update their
+    /// subscription, so that nodes can't hog the sampling of its stacks with
+    /// a negligible price and then fail to serve them.
+    /// Nodes that are already subscribed below the minimum keep their price
+    /// until they update it, but can't sell stacks below it through quotes
+    /// and offers.
+    ///
+    /// A price of 0 removes the minimum.
+    ///
+    /// # Errors
+    /// * `ETaskNotFound` - If the task does not exist.
+    public entry fun set_task_min_price(
+        self: &mut AtomaDb,
+        _: &AtomaManagerBadge,
+        task_small_id: u64,
+        min_price_per_one_million_compute_units: u64,
+    ) {
+        let task_small_id = TaskSmallId { inner: task_small_id };
+        assert!(self.tasks.contains(task_small_id), ETaskNotFound);
+
+        let min_prices: &mut Table<TaskSmallId, u64> =
+            dynamic_field::borrow_mut(&mut self.id, TaskMinPricesKey {});
+        if (min_prices.contains(task_small_id)) {
+            min_prices.remove(task_small_id);
+        };
+        if (min_price_per_one_million_compute_units > 0) {
+            min_prices.add(task_small_id, min_price_per_one_million_compute_units);
+        };
+
+        sui::event::emit(TaskMinPriceUpdatedEvent {
+            task_small_id,
+            min_price_per_one_million_compute_units,
+        });
+    }
+
+    /// Posts a notice for nodes and gateways, eg. of a maintenance, an
+    /// upgrade or a parameter change.
+    /// They read it with `db announcements`, and `daemon params-watch`
+    /// alerts operators about critical ones.
+    ///
+    /// Announcements stay until removed with `remove_announcement`.
+    ///
+    /// # Arguments
+    /// * `severity` - 0 for info, 1 for warning or 2 for critical.
+    /// * `message` - At most `MaxAnnouncementLength` bytes.
+    /// * `effective_epoch` - The epoch from which the notice applies.
+    ///
+    /// # Errors
+    /// * `EInvalidAnnouncement` - If the severity is unknown, or the message
+    ///   is empty or too long.
+    public entry fun post_announcement(
+        self: &mut AtomaDb,
+        _: &AtomaManagerBadge,
+        severity: u8,
+        message: string::String,
+        effective_epoch: u64,
+        ctx: &TxContext,
+    ) {
+        assert!(
+            severity == AnnouncementInfo
+                || severity == AnnouncementWarning
+                || severity == AnnouncementCritical,
+            EInvalidAnnouncement,
+        );
+        assert!(
+            message.length() > 0 && message.length() <= MaxAnnouncementLength,
+            EInvalidAnnouncement,
+        );
+
+        let announcements = self.announcements_mut();
+        let announcement_id = announcements.next_id;
+        announcements.next_id = announcement_id + 1;
+        announcements.entries.add(announcement_id, Announcement {
+            severity,
+            message,
+            effective_epoch,
+            posted_at_epoch: ctx.epoch(),
+        });
+
+        sui::event::emit(AnnouncementPostedEvent {
+            announcement_id,
+            severity,
+            message,
+            effective_epoch,
+        });
+    }
+
+    /// # Errors
+    /// * `EAnnouncementNotFound` - If there is no announcement with the ID.
+    public entry fun remove_announcement(
+        self: &mut AtomaDb,
+        _: &AtomaManagerBadge,
+        announcement_id: u64,
+    ) {
+        let entries = &mut self.announcements_mut().entries;
+        assert!(entries.contains(announcement_id), EAnnouncementNotFound);
+        entries.remove(announcement_id);
+
+        sui::event::emit(AnnouncementRemovedEvent { announcement_id });
+    }
+
+    /// From now on, stacks of the task can only be settled with
+    /// `try_settle_stack_with_zk_proof`, and their proofs are verified against
+    /// the given key.
+    /// Replaces the previous key if any.
+    ///
+    /// # Arguments
+    /// * `curve` - 0 for BN254 or 1 for BLS12-381.
+    /// * `verifying_key` - The Groth16 verifying key, as arkworks compressed bytes.
+    ///
+    /// # Errors
+    /// * `ETaskNotFound` - If the task does not exist.
+    /// * `EInvalidZkCurve` - If the curve is neither of the above.
+    public entry fun set_task_zk_verifier(
+        self: &mut AtomaDb,
+        _: &AtomaManagerBadge,
+        task_small_id: u64,
+        curve: u8,
+        verifying_key: vector<u8>,
+    ) {
+        let task_small_id = TaskSmallId { inner: task_small_id };
+        assert!(self.tasks.contains(task_small_id), ETaskNotFound);
+        let curve_id = curve;
+        let curve = if (curve_id == ZkCurveBn254) {
+            groth16::bn254()
+        } else if (curve_id == ZkCurveBls12381) {
+            groth16::bls12381()
+        } else {
+            abort EInvalidZkCurve
+        };
+        let verifying_key = groth16::prepare_verifying_key(&curve, &verifying_key);
+
+        let verifiers = self.task_zk_verifiers_mut();
+        if (verifiers.contains(task_small_id)) {
+            verifiers.remove(task_small_id);
+        };
+        verifiers.add(task_small_id, TaskZkVerifier { curve, verifying_key });
+
+        sui::event::emit(TaskZkVerifierSetEvent { task_small_id, curve: curve_id });
+    }
+
+    /// Stacks of the task are settled with `try_settle_stack` again.
+    public entry fun remove_task_zk_verifier(
+        self: &mut AtomaDb,
+        _: &AtomaManagerBadge,
+        task_small_id: u64,
+    ) {
+        let task_small_id = TaskSmallId { inner: task_small_id };
+        assert!(self.task_has_zk_verifier(task_small_id), ETaskHasNoZkVerifier);
+        self.task_zk_verifiers_mut().remove(task_small_id);
+
+        sui::event::emit(TaskZkVerifierRemovedEvent { task_small_id });
+    }
+
     // =========================================================================
     //                          Helpers
     // =========================================================================
 
+    /// Checks shared by `try_settle_stack` and `try_settle_stack_with_zk_proof`.
+    fun assert_can_try_settle_stack(
+        self: &AtomaDb,
+        node_small_id: NodeSmallId,
+        stack_small_id: StackSmallId,
+        num_claimed_compute_units: u64,
+        committed_stack_proof: &vector<u8>,
+        stack_merkle_leaf: &vector<u8>,
+    ) {
+        let stack = self.stacks.borrow(stack_small_id);
+
+        assert!(node_small_id == stack.selected_node_id, ENodeNotSelectedForStack);
+        assert!(num_claimed_compute_units <= stack.num_compute_units, ETooManyComputedUnits);
+        assert!(!self.stack_settlement_tickets.contains(stack_small_id), EStackInSettlementDispute);
+
+        // Validate input data
+        assert!(vector::length(committed_stack_proof) == BYTES_PER_HASH_COMMITMENT, EInvalidCommittedStackProof);
+        assert!(vector::length(stack_merkle_leaf) == BYTES_PER_HASH_COMMITMENT, EInvalidStackMerkleLeaf);
+    }
+
+    fun assert_can_close_stack(
+        self: &AtomaDb,
+        stack_small_id: StackSmallId,
+        num_consumed_compute_units: u64,
+    ) {
+        assert!(self.stacks.contains(stack_small_id), EStackNotFound);
+        let stack = self.stacks.borrow(stack_small_id);
+        assert!(!stack.is_claimed, EStackAlreadyClaimed);
+        assert!(!self.stack_settlement_tickets.contains(stack_small_id), EStackInSettlementDispute);
+        assert!(num_consumed_compute_units <= stack.num_compute_units, ETooManyComputedUnits);
+    }
+
+    fun add_stack_close_proposal(
+        self: &mut AtomaDb,
+        stack_small_id: StackSmallId,
+        num_consumed_compute_units: u64,
+        proposed_by_node: bool,
+    ) {
+        self.remove_stack_close_proposal(stack_small_id);
+        self.stack_close_proposals_mut().add(stack_small_id, StackCloseProposal {
+            num_consumed_compute_units,
+            proposed_by_node,
+        });
+
+        sui::event::emit(StackCloseProposedEvent {
+            stack_small_id,
+            selected_node_id: self.stacks.borrow(stack_small_id).selected_node_id,
+            num_consumed_compute_units,
+            proposed_by_node,
+        });
+    }
+
+    /// Settles the consumed compute units like `claim_funds` would, except
+    /// that no attestation nodes are involved since both parties agreed, and
+    /// refunds the rest to the owner.
+    ///
+    /// The node earns what the consumed compute units cost, see
+    /// `stack_cost`, and the owner gets back the rest of what was paid, so
+    /// that nothing stays in the fee treasury.
+    ///
+    /// The node's fee is attributed to it rather than transferred, because
+    /// the owner might be the sender, see `withdraw_fees`.
+    fun close_stack(
+        self: &mut AtomaDb,
+        stack_small_id: StackSmallId,
+        num_consumed_compute_units: u64,
+        proposed_by_node: bool,
+        ctx: &mut TxContext,
+    ) {
+        assert!(
+            dynamic_field::exists_(&self.id, StackCloseProposalsKey {})
+                && self.stack_close_proposals().contains(stack_small_id),
+            EStackCloseNotProposed,
+        );
+        let proposal = self.stack_close_proposals_mut().remove(stack_small_id);
+        assert!(proposal.proposed_by_node == proposed_by_node, EStackCloseNotProposed);
+        assert!(
+            proposal.num_consumed_compute_units == num_consumed_compute_units,
+            EStackCloseTermsMismatch,
+        );
+
+        let Stack {
+            owner,
+            price_per_one_million_compute_units,
+            num_compute_units,
+            selected_node_id,
+            task_small_id,
+            is_claimed: _,
+        } = self.stacks.remove(stack_small_id);
+        let security_level = self.tasks.borrow(task_small_id).security_level;
+        let stack_cost = self.stack_cost(
+            security_level,
+            price_per_one_million_compute_units,
+            num_compute_units,
+        );
+
+        let node_fee_amount = self.stack_cost(
+            security_level,
+            price_per_one_million_compute_units,
+            num_consumed_compute_units,
+        );
+        let referral_fee_amount = self.accrue_referral_fee(stack_small_id, node_fee_amount);
+        let insurance_premium_amount = self.accrue_insurance_premium(
+            selected_node_id,
+            node_fee_amount - referral_fee_amount,
+        );
+        self.attribute_fee_to_node(
+            selected_node_id,
+            node_fee_amount - referral_fee_amount - insurance_premium_amount,
+            ctx,
+        );
+
+        let user_refund_amount = stack_cost - node_fee_amount;
+        self.transfer_funds(user_refund_amount, owner, ctx);
+
+        sui::event::emit(StackClosedEarlyEvent {
+            stack_small_id,
+            selected_node_id,
+            num_claimed_compute_units: num_consumed_compute_units,
+            user_refund_amount,
+        });
+    }
+
+    /// Creates the settlement ticket for a stack and emits `StackTrySettleEvent`.
+    fun add_stack_settlement_ticket(
+        self: &mut AtomaDb,
+        node_small_id: NodeSmallId,
+        stack_small_id: StackSmallId,
+        num_claimed_compute_units: u64,
+        committed_stack_proof: vector<u8>,
+        stack_merkle_leaf: vector<u8>,
+        attestation_nodes: vector<NodeSmallId>,
+        ctx: &mut TxContext,
+    ) {
+        let cross_validation_extra_nodes_count = self.get_cross_validation_extra_nodes_count();
+
+        // Populate the stack merkle leaves vector, by copying the stack merkle leaf
+        // for the first BYTES_PER_HASH_COMMITMENT bytes, and then padding with 0s
+        // for the remaining cross_validation_extra_nodes_count * BYTES_PER_HASH_COMMITMENT bytes 
+        let mut stack_merkle_leaves_vector = vector::empty();
+        let mut i = 0;
+        while (i < BYTES_PER_HASH_COMMITMENT) {
+            vector::push_back(&mut stack_merkle_leaves_vector, stack_merkle_leaf[i]);
+            i = i + 1;
+        };
+
+        let mut j = 0;
+        while (j < cross_validation_extra_nodes_count * BYTES_PER_HASH_COMMITMENT) {
+            vector::push_back(&mut stack_merkle_leaves_vector, 0);
+            j = j + 1;
+        };
+
+        let ticket_id = object::new(ctx);
+        let stack_settlement_ticket = StackSettlementTicket {
+            id: ticket_id,
+            stack_small_id: stack_small_id,
+            selected_node_id: node_small_id,
+            num_claimed_compute_units,
+            requested_attestation_nodes: attestation_nodes,
+            dispute_settled_at_epoch: ctx.epoch() + self.stack_dispute_window_epochs(),
+            committed_stack_proof,
+            stack_merkle_leaves_vector,
+            already_attested_nodes: vector::empty(),
+            is_in_dispute: false,
+        };
+
+        self.stack_settlement_tickets.add(stack_small_id, stack_settlement_ticket);
+
+        sui::event::emit(StackTrySettleEvent {
+            stack_small_id: stack_small_id,
+            selected_node_id: node_small_id,
+            num_claimed_compute_units,
+            requested_attestation_nodes: attestation_nodes,
+            committed_stack_proof,
+            stack_merkle_leaf,
+        });
+    }
+
     /// Helper function to handle attestation for nodes that have been previously attested
     fun handle_existing_node_attestation(
         node: &mut NodeEntry,
@@ -3497,15 +6091,226 @@ module atoma::db {
         node.confidential_compute_device_types = vector::singleton(device_type);
     }
 
-    // Helper function to check if a node meets the task's requirements
-    fun node_meets_task_requirements(self: &AtomaDb, node_badge: &NodeBadge, task: &Task): bool {
-        let node = self.nodes.borrow(node_badge.small_id);
-        
-        // Check if the node's reputation score meets the task's minimum requirement
-        if (node.reputation_score.inner < task.minimum_reputation_score.inner) {
-            return false
+    // Helper function to check if a node meets the task's requirements
+    fun node_meets_task_requirements(task: &Task, reputation_score: u8): bool {
+        // Check if the node's reputation score meets the task's minimum requirement
+        if (reputation_score < task.minimum_reputation_score.inner) {
+            return false
+        };
+        true
+    }
+
+    /// The reputation score of the node moved towards `REPUTATION_SCORE_START`
+    /// by a point for every `REPUTATION_DECAY_EPOCHS_PER_POINT` epochs since
+    /// it was last updated.
+    fun decayed_reputation_score(self: &AtomaDb, node_small_id: NodeSmallId, epoch: u64): u8 {
+        let reputation = self.node_reputation_or_fresh(node_small_id, epoch);
+        let points = (epoch - reputation.updated_at_epoch) / REPUTATION_DECAY_EPOCHS_PER_POINT;
+        let score = self.nodes.borrow(node_small_id).reputation_score.inner as u64;
+        let baseline = REPUTATION_SCORE_START as u64;
+        let decayed = if (score > baseline) {
+            if (score - baseline > points) { score - points } else { baseline }
+        } else {
+            if (baseline - score > points) { score + points } else { baseline }
+        };
+        decayed as u8
+    }
+
+    /// Stores the decayed reputation score so that further changes apply to
+    /// it, and returns the reputation record to store along with it.
+    /// Epochs that did not yet add up to a full point keep counting.
+    fun apply_reputation_decay(self: &mut AtomaDb, node_small_id: NodeSmallId, epoch: u64): NodeReputation {
+        let score = self.decayed_reputation_score(node_small_id, epoch);
+        self.nodes.borrow_mut(node_small_id).reputation_score = ReputationScore { inner: score };
+
+        let mut reputation = self.node_reputation_or_fresh(node_small_id, epoch);
+        let points = (epoch - reputation.updated_at_epoch) / REPUTATION_DECAY_EPOCHS_PER_POINT;
+        reputation.updated_at_epoch =
+            reputation.updated_at_epoch + points * REPUTATION_DECAY_EPOCHS_PER_POINT;
+        reputation
+    }
+
+    /// Extends the streak of dispute-free settlements of the node, and
+    /// awards a reputation point for every `REPUTATION_RECOVERY_STREAK` of
+    /// them.
+    ///
+    /// Only settlements that earned the node a fee count, otherwise a node
+    /// could recover for free by buying tiny stacks from itself, whose fee
+    /// rounds down to zero.
+    fun record_dispute_free_settlement(self: &mut AtomaDb, node_small_id: NodeSmallId, epoch: u64) {
+        let mut reputation = self.apply_reputation_decay(node_small_id, epoch);
+        reputation.dispute_free_settlements_streak = reputation.dispute_free_settlements_streak + 1;
+
+        let node = self.nodes.borrow_mut(node_small_id);
+        if (
+            reputation.dispute_free_settlements_streak % REPUTATION_RECOVERY_STREAK == 0
+                && node.reputation_score.inner < REPUTATION_SCORE_MAX
+        ) {
+            node.reputation_score.inner = node.reputation_score.inner + 1;
+            sui::event::emit(NodeReputationRecoveredEvent {
+                node_small_id,
+                reputation_score: node.reputation_score,
+                dispute_free_settlements_streak: reputation.dispute_free_settlements_streak,
+            });
+        };
+        self.set_node_reputation(node_small_id, reputation);
+    }
+
+    /// Takes the penalty off the decayed reputation score of the node, down
+    /// to zero, and ends its streak of dispute-free settlements.
+    fun penalize_node_reputation(
+        self: &mut AtomaDb,
+        node_small_id: NodeSmallId,
+        penalty: u8,
+        epoch: u64,
+    ) {
+        let mut reputation = self.apply_reputation_decay(node_small_id, epoch);
+        reputation.dispute_free_settlements_streak = 0;
+
+        let node = self.nodes.borrow_mut(node_small_id);
+        node.reputation_score.inner = if (node.reputation_score.inner > penalty) {
+            node.reputation_score.inner - penalty
+        } else {
+            0
+        };
+        sui::event::emit(NodeReputationPenalizedEvent {
+            node_small_id,
+            reputation_score: node.reputation_score,
+            penalty,
+        });
+        self.set_node_reputation(node_small_id, reputation);
+    }
+
+    /// A node without a streak has nothing to reset.
+    fun reset_dispute_free_settlements_streak(self: &mut AtomaDb, node_small_id: NodeSmallId) {
+        if (self.has_node_reputation(node_small_id)) {
+            self.node_reputations_mut().borrow_mut(node_small_id).dispute_free_settlements_streak = 0;
+        };
+    }
+
+    /// Nodes have no record until their reputation changes after the db was
+    /// migrated, their score counts as updated in the given epoch.
+    fun node_reputation_or_fresh(self: &AtomaDb, node_small_id: NodeSmallId, epoch: u64): NodeReputation {
+        if (self.has_node_reputation(node_small_id)) {
+            *self.node_reputations().borrow(node_small_id)
+        } else {
+            NodeReputation { updated_at_epoch: epoch, dispute_free_settlements_streak: 0 }
+        }
+    }
+
+    fun has_node_reputation(self: &AtomaDb, node_small_id: NodeSmallId): bool {
+        dynamic_field::exists_(&self.id, NodeReputationsKey {})
+            && self.node_reputations().contains(node_small_id)
+    }
+
+    /// Until the db is migrated, the score changes without the decay and
+    /// streak being recorded.
+    fun set_node_reputation(self: &mut AtomaDb, node_small_id: NodeSmallId, reputation: NodeReputation) {
+        if (!dynamic_field::exists_(&self.id, NodeReputationsKey {})) {
+            return
+        };
+        let reputations = self.node_reputations_mut();
+        if (reputations.contains(node_small_id)) {
+            *reputations.borrow_mut(node_small_id) = reputation;
+        } else {
+            reputations.add(node_small_id, reputation);
+        };
+    }
+
+    fun remove_node_reputation(self: &mut AtomaDb, node_small_id: NodeSmallId) {
+        if (self.has_node_reputation(node_small_id)) {
+            self.node_reputations_mut().remove(node_small_id);
+        };
+    }
+
+    fun node_reputations(self: &AtomaDb): &Table<NodeSmallId, NodeReputation> {
+        dynamic_field::borrow(&self.id, NodeReputationsKey {})
+    }
+
+    fun node_reputations_mut(self: &mut AtomaDb): &mut Table<NodeSmallId, NodeReputation> {
+        dynamic_field::borrow_mut(&mut self.id, NodeReputationsKey {})
+    }
+
+    /// Nodes that have not sent a heartbeat since the db was migrated count
+    /// as if they sent one then.
+    fun node_last_heartbeat_epoch(self: &AtomaDb, node_small_id: NodeSmallId): u64 {
+        let sweeps = self.stale_node_sweeps();
+        if (sweeps.last_heartbeat_epochs.contains(node_small_id)) {
+            *sweeps.last_heartbeat_epochs.borrow(node_small_id)
+        } else {
+            sweeps.migrated_at_epoch
+        }
+    }
+
+    /// Until the db is migrated, new nodes count as if they sent a heartbeat
+    /// in the epoch of the migration.
+    fun record_heartbeat(
+        self: &mut AtomaDb,
+        node_small_id: NodeSmallId,
+        sender: address,
+        epoch: u64,
+    ) {
+        if (dynamic_field::exists_(&self.id, StaleNodeSweepsKey {})) {
+            let sweeps = self.stale_node_sweeps_mut();
+            sweeps.last_heartbeat_epochs.add(node_small_id, epoch);
+            sweeps.heartbeat_senders.add(node_small_id, sender);
+        };
+    }
+
+    fun remove_heartbeat(self: &mut AtomaDb, node_small_id: NodeSmallId) {
+        if (dynamic_field::exists_(&self.id, StaleNodeSweepsKey {})) {
+            let sweeps = self.stale_node_sweeps_mut();
+            if (sweeps.last_heartbeat_epochs.contains(node_small_id)) {
+                sweeps.last_heartbeat_epochs.remove(node_small_id);
+            };
+            if (sweeps.heartbeat_senders.contains(node_small_id)) {
+                sweeps.heartbeat_senders.remove(node_small_id);
+            };
+        };
+    }
+
+    fun stale_node_sweeps(self: &AtomaDb): &StaleNodeSweeps {
+        dynamic_field::borrow(&self.id, StaleNodeSweepsKey {})
+    }
+
+    fun stale_node_sweeps_mut(self: &mut AtomaDb): &mut StaleNodeSweeps {
+        dynamic_field::borrow_mut(&mut self.id, StaleNodeSweepsKey {})
+    }
+
+    /// The most compute units a single stack for the task can acquire, none
+    /// if the task is unbounded.
+    fun get_task_max_compute_units(self: &AtomaDb, task_small_id: TaskSmallId): Option<u64> {
+        if (!dynamic_field::exists_(&self.id, TaskConstraintsKey {})) {
+            return option::none()
+        };
+        let all_constraints: &Table<TaskSmallId, TaskConstraints> =
+            dynamic_field::borrow(&self.id, TaskConstraintsKey {});
+        if (!all_constraints.contains(task_small_id)) {
+            return option::none()
+        };
+        all_constraints.borrow(task_small_id).max_compute_units()
+    }
+
+    fun remove_task_constraints(self: &mut AtomaDb, task_small_id: TaskSmallId) {
+        if (!dynamic_field::exists_(&self.id, TaskConstraintsKey {})) {
+            return
+        };
+        let all_constraints: &mut Table<TaskSmallId, TaskConstraints> =
+            dynamic_field::borrow_mut(&mut self.id, TaskConstraintsKey {});
+        if (all_constraints.contains(task_small_id)) {
+            all_constraints.remove(task_small_id);
         };
-        true
+    }
+
+    fun max_compute_units(self: &TaskConstraints): Option<u64> {
+        if (self.max_input_tokens.is_none() && self.max_output_tokens.is_none()) {
+            option::none()
+        } else {
+            option::some(
+                self.max_input_tokens.get_with_default(0)
+                    + self.max_output_tokens.get_with_default(0)
+            )
+        }
     }
 
     /// Helper function to check if a task role is valid.
@@ -3651,43 +6456,21 @@ module atoma::db {
         let mut sampled_nodes = vector::empty();
 
         let total_echelon_nodes = echelon_nodes.length();
-        // how many nodes do we sample from in each chunk
-        let base_nodes_per_chunk = total_echelon_nodes / how_many_nodes_to_sample;
-        // first this many chunks will sample from one extra node
-        let total_chunks_with_extra_node_count = total_echelon_nodes % how_many_nodes_to_sample;
-
-        // when 0 then we no longer sample from extra node
-        let mut extra_node_chunks_remaining = total_chunks_with_extra_node_count;
-        // We keep track of the index of the first node in the chunk.
-        // This also tells us whether we iterated all the chunks or not yet
-        let mut from_node_index = 0;
-        while (from_node_index < total_echelon_nodes) {
-            // sample in interval <from_node_index; from_node_index + nodes_to_pick_from)
-            let nodes_to_pick_from = base_nodes_per_chunk
-            + if (extra_node_chunks_remaining > 0) {
-                extra_node_chunks_remaining = extra_node_chunks_remaining - 1;
-                // add 1 because of the residual from modulo when dividing
-                // total_echelon_nodes by how_many_nodes_to_sample
-                1
-            } else {
-                0
-            };
+        // one chunk per node to sample unless there are fewer nodes
+        let chunks_count = if (total_echelon_nodes < how_many_nodes_to_sample) {
+            total_echelon_nodes
+        } else {
+            how_many_nodes_to_sample
+        };
 
-            let node_index = from_node_index
-                + rng.generate_u64() % nodes_to_pick_from;
-
-            // We want to sample from the end of the echelon for two reasons:
-            // - Primarily, since we remove nodes from the echelon, we want to
-            //   use swap remove and not invalidate the indices.
-            // - The first `total_chunks_with_extra_node_count` chunks have one
-            //   extra node, decreasing the chance of sampling of each node.
-            //   Ever so slightly, it's better NOT to unsubscribe and to
-            //   subscribe early.
-            // Therefore we inverse the index such that 0 becomes the last
-            // index, 1 the second to last and so on.
-            // The rest of the algorithm is simpler if we start from the
-            // beginning, so we do this transformation here.
-            let inverse_node_index = total_echelon_nodes - 1 - node_index;
+        let mut chunk = 0;
+        while (chunk < chunks_count) {
+            let inverse_node_index = unique_sample_node_index(
+                total_echelon_nodes,
+                how_many_nodes_to_sample,
+                chunk,
+                rng.generate_u64(),
+            );
             let mut node_id = get_node_id_if_unslashed_or_swap_remove(
                 nodes, echelon_nodes, inverse_node_index,
             );
@@ -3699,7 +6482,7 @@ module atoma::db {
                 // and deal with the situation later
             };
 
-            from_node_index = from_node_index + nodes_to_pick_from;
+            chunk = chunk + 1;
         };
 
         if (sampled_nodes.length() == how_many_nodes_to_sample) {
@@ -3739,6 +6522,112 @@ module atoma::db {
         }
     }
 
+    /// Which node of the echelon `sample_unique_nodes` picks in the given
+    /// chunk for the given random number, as an index into the echelon's
+    /// nodes before any of them are removed.
+    ///
+    /// The echelon is split into `how_many_nodes_to_sample` chunks, or into
+    /// chunks of a single node if there are fewer nodes, and a node is picked
+    /// from each chunk.
+    /// The first `total_echelon_nodes % how_many_nodes_to_sample` chunks have
+    /// one extra node.
+    ///
+    /// Pure so that clients can predict which nodes random numbers select,
+    /// the CLI mirrors it in its `sampling` module.
+    public fun unique_sample_node_index(
+        total_echelon_nodes: u64,
+        how_many_nodes_to_sample: u64,
+        chunk: u64,
+        random: u64,
+    ): u64 {
+        assert!(how_many_nodes_to_sample > 0, ECannotSampleZeroNodes);
+        // how many nodes do we sample from in each chunk
+        let base_nodes_per_chunk = total_echelon_nodes / how_many_nodes_to_sample;
+        // first this many chunks will sample from one extra node
+        let total_chunks_with_extra_node_count = total_echelon_nodes % how_many_nodes_to_sample;
+
+        // sample in interval <from_node_index; from_node_index + nodes_to_pick_from)
+        let (from_node_index, nodes_to_pick_from) = if (chunk < total_chunks_with_extra_node_count) {
+            // add 1 because of the residual from modulo when dividing
+            // total_echelon_nodes by how_many_nodes_to_sample
+            (chunk * (base_nodes_per_chunk + 1), base_nodes_per_chunk + 1)
+        } else {
+            (
+                chunk * base_nodes_per_chunk + total_chunks_with_extra_node_count,
+                base_nodes_per_chunk,
+            )
+        };
+        let node_index = from_node_index + random % nodes_to_pick_from;
+
+        // We want to sample from the end of the echelon for two reasons:
+        // - Primarily, since we remove nodes from the echelon, we want to
+        //   use swap remove and not invalidate the indices.
+        // - The first `total_chunks_with_extra_node_count` chunks have one
+        //   extra node, decreasing the chance of sampling of each node.
+        //   Ever so slightly, it's better NOT to unsubscribe and to
+        //   subscribe early.
+        // Therefore we inverse the index such that 0 becomes the last
+        // index, 1 the second to last and so on.
+        // The rest of the algorithm is simpler if we start from the
+        // beginning, so we do this transformation here.
+        total_echelon_nodes - 1 - node_index
+    }
+
+    /// The minimum version of the task as `software_version_as_u64`, zero if
+    /// there is none or the db was not migrated yet.
+    fun task_min_node_version(self: &AtomaDb, task_small_id: TaskSmallId): u64 {
+        if (!dynamic_field::exists_(&self.id, TaskMinNodeVersionsKey {})) {
+            return 0
+        };
+        let min_versions: &Table<TaskSmallId, SoftwareVersion> =
+            dynamic_field::borrow(&self.id, TaskMinNodeVersionsKey {});
+        if (min_versions.contains(task_small_id)) {
+            software_version_as_u64(*min_versions.borrow(task_small_id))
+        } else {
+            0
+        }
+    }
+
+    /// The minimum price per one million compute units of the task, zero if
+    /// there is none.
+    ///
+    /// Takes the ID of the db rather than the db so that it can be called
+    /// while a task of the db is borrowed.
+    fun task_min_price(db_id: &UID, task_small_id: TaskSmallId): u64 {
+        if (!dynamic_field::exists_(db_id, TaskMinPricesKey {})) {
+            return 0
+        };
+        let min_prices: &Table<TaskSmallId, u64> =
+            dynamic_field::borrow(db_id, TaskMinPricesKey {});
+        if (min_prices.contains(task_small_id)) {
+            *min_prices.borrow(task_small_id)
+        } else {
+            0
+        }
+    }
+
+    /// As `software_version_as_u64`, zero if the node never reported one.
+    ///
+    /// Takes the ID of the db rather than the db so that it can be called
+    /// while a task of the db is borrowed.
+    fun node_software_version(db_id: &UID, node_small_id: NodeSmallId): u64 {
+        if (!dynamic_field::exists_(db_id, NodeSoftwareVersionsKey {})) {
+            return 0
+        };
+        let versions: &Table<NodeSmallId, SoftwareVersion> =
+            dynamic_field::borrow(db_id, NodeSoftwareVersionsKey {});
+        if (versions.contains(node_small_id)) {
+            software_version_as_u64(*versions.borrow(node_small_id))
+        } else {
+            0
+        }
+    }
+
+    /// Orders versions by major, then minor, then patch.
+    fun software_version_as_u64(version: SoftwareVersion): u64 {
+        ((version.major as u64) << 32) | ((version.minor as u64) << 16) | (version.patch as u64)
+    }
+
     fun get_node_id_if_unslashed_or_swap_remove(
         nodes: &Table<NodeSmallId, NodeEntry>,
         echelon_nodes: &mut TableVec<NodeSmallId>,
@@ -3791,6 +6680,408 @@ module atoma::db {
         }
     }
 
+    /// Pays the sender, who holds the node badge, or the members of the
+    /// node's cooperative, see `set_node_payout_split`.
+    fun transfer_node_fee(
+        self: &mut AtomaDb,
+        node_small_id: NodeSmallId,
+        amount: u64,
+        ctx: &mut TxContext,
+    ) {
+        if (amount == 0) {
+            return
+        };
+        let split = self.node_payout_split(node_small_id);
+        if (split.is_empty()) {
+            self.transfer_funds(amount, ctx.sender(), ctx);
+            return
+        };
+
+        let mut remaining = amount;
+        let mut index = 0;
+        while (index < split.length()) {
+            let share = split[index];
+            let share_amount = if (index + 1 == split.length()) {
+                remaining
+            } else {
+                amount * share.bps / 10_000
+            };
+            remaining = remaining - share_amount;
+            self.transfer_funds(share_amount, share.recipient, ctx);
+            index = index + 1;
+        };
+    }
+
+    /// Empty if the node is paid alone or the db was not migrated yet.
+    fun node_payout_split(self: &AtomaDb, node_small_id: NodeSmallId): vector<PayoutShare> {
+        if (!dynamic_field::exists_(&self.id, NodePayoutSplitsKey {})) {
+            return vector::empty()
+        };
+        let splits: &Table<NodeSmallId, vector<PayoutShare>> =
+            dynamic_field::borrow(&self.id, NodePayoutSplitsKey {});
+        if (splits.contains(node_small_id)) {
+            *splits.borrow(node_small_id)
+        } else {
+            vector::empty()
+        }
+    }
+
+    fun node_payout_splits_mut(
+        self: &mut AtomaDb,
+    ): &mut Table<NodeSmallId, vector<PayoutShare>> {
+        dynamic_field::borrow_mut(&mut self.id, NodePayoutSplitsKey {})
+    }
+
+    fun referrals(self: &AtomaDb): &Referrals {
+        dynamic_field::borrow(&self.id, ReferralsKey {})
+    }
+
+    fun referrals_mut(self: &mut AtomaDb): &mut Referrals {
+        dynamic_field::borrow_mut(&mut self.id, ReferralsKey {})
+    }
+
+    fun add_stack_referral(
+        self: &mut AtomaDb,
+        stack_small_id: StackSmallId,
+        referrer: address,
+        fee_share_bps: u64,
+    ) {
+        assert!(fee_share_bps <= MaxReferralFeeShareBps, EReferralFeeShareTooHigh);
+        self.referrals_mut().stacks.add(stack_small_id, StackReferral { referrer, fee_share_bps });
+
+        sui::event::emit(StackReferralEvent {
+            stack_small_id,
+            referrer,
+            fee_share_bps,
+        });
+    }
+
+    /// If the stack has a referrer, credits them their share of the node fee
+    /// and returns it so that it can be deducted from what the node gets.
+    /// The funds stay in the fee treasury until the referrer claims them.
+    fun accrue_referral_fee(
+        self: &mut AtomaDb,
+        stack_small_id: StackSmallId,
+        node_fee_amount: u64,
+    ): u64 {
+        // stacks acquired before the migration have no referrer
+        if (!dynamic_field::exists_(&self.id, ReferralsKey {})) {
+            return 0
+        };
+        let referrals = self.referrals_mut();
+        if (!referrals.stacks.contains(stack_small_id)) {
+            return 0
+        };
+        let StackReferral { referrer, fee_share_bps } = referrals.stacks.remove(stack_small_id);
+        let amount = node_fee_amount * fee_share_bps / 10_000;
+        if (amount == 0) {
+            return 0
+        };
+
+        if (referrals.balances.contains(referrer)) {
+            let balance = referrals.balances.borrow_mut(referrer);
+            *balance = *balance + amount;
+        } else {
+            referrals.balances.add(referrer, amount);
+        };
+
+        sui::event::emit(ReferralFeeAccruedEvent {
+            stack_small_id,
+            referrer,
+            amount,
+        });
+
+        amount
+    }
+
+    /// Moves the premium of an insured node's fee for a stack from the fee
+    /// treasury to the insurance pool.
+    /// Returns the premium, which the node is not paid.
+    fun accrue_insurance_premium(
+        self: &mut AtomaDb,
+        node_small_id: NodeSmallId,
+        node_fee_amount: u64,
+    ): u64 {
+        if (!self.is_insured(node_small_id)) {
+            return 0
+        };
+        let amount = node_fee_amount * self.insurance_pool().premium_permille / 1000;
+        if (amount == 0) {
+            return 0
+        };
+
+        let premium = self.fee_treasury.split(amount);
+        let pool = self.insurance_pool_mut();
+        pool.balance.join(premium);
+        let membership = pool.members.borrow_mut(node_small_id);
+        membership.premiums_paid = membership.premiums_paid + amount;
+
+        amount
+    }
+
+    /// Marks a pending claim as resolved and returns its claimant, node and
+    /// amount.
+    fun resolve_insurance_claim(
+        self: &mut AtomaDb,
+        stack_small_id: StackSmallId,
+    ): (address, NodeSmallId, u64) {
+        let claims = &mut self.insurance_pool_mut().claims;
+        assert!(claims.contains(stack_small_id), EInsuranceClaimNotFound);
+        let claim = claims.borrow_mut(stack_small_id);
+        assert!(!claim.is_resolved, EInsuranceClaimNotFound);
+        claim.is_resolved = true;
+
+        (claim.claimant, claim.node_small_id, claim.amount)
+    }
+
+    /// Until the db is migrated, no node is insured.
+    fun is_insured(self: &AtomaDb, node_small_id: NodeSmallId): bool {
+        dynamic_field::exists_(&self.id, InsurancePoolKey {})
+            && self.insurance_pool().members.contains(node_small_id)
+    }
+
+    /// Until the db is migrated, settlements can be disputed for
+    /// `InitialStackDisputeWindowEpochs`.
+    fun stack_dispute_window_epochs(self: &AtomaDb): u64 {
+        if (dynamic_field::exists_(&self.id, StackDisputeWindowEpochsKey {})) {
+            *dynamic_field::borrow(&self.id, StackDisputeWindowEpochsKey {})
+        } else {
+            InitialStackDisputeWindowEpochs
+        }
+    }
+
+    /// Removes the latency recorded for an echelon that is being removed.
+    fun remove_model_echelon_latency(
+        self: &mut AtomaDb,
+        model_name: ascii::String,
+        echelon_id: EchelonId,
+    ) {
+        if (!dynamic_field::exists_(&self.id, ModelEchelonLatenciesKey {})) {
+            return
+        };
+        let key = ModelEchelonKey { model_name, echelon_id };
+        let latencies = self.model_echelon_latencies_mut();
+        if (latencies.contains(key)) {
+            latencies.remove(key);
+        };
+    }
+
+    /// Until the db is migrated, claimed stacks are not recorded and can't be
+    /// collected.
+    fun record_claimed_stack(self: &mut AtomaDb, stack_small_id: StackSmallId, ctx: &TxContext) {
+        if (dynamic_field::exists_(&self.id, StackGcKey {})) {
+            self.stack_gc_mut().claimed_stack_epochs.add(stack_small_id, ctx.epoch());
+        };
+    }
+
+    fun stack_gc(self: &AtomaDb): &StackGc {
+        dynamic_field::borrow(&self.id, StackGcKey {})
+    }
+
+    fun stack_gc_mut(self: &mut AtomaDb): &mut StackGc {
+        dynamic_field::borrow_mut(&mut self.id, StackGcKey {})
+    }
+
+    fun announcements(self: &AtomaDb): &Announcements {
+        dynamic_field::borrow(&self.id, AnnouncementsKey {})
+    }
+
+    fun announcements_mut(self: &mut AtomaDb): &mut Announcements {
+        dynamic_field::borrow_mut(&mut self.id, AnnouncementsKey {})
+    }
+
+    /// Until the db is migrated, no close can have been proposed.
+    fun remove_stack_close_proposal(self: &mut AtomaDb, stack_small_id: StackSmallId) {
+        if (!dynamic_field::exists_(&self.id, StackCloseProposalsKey {})) {
+            return
+        };
+        let proposals = self.stack_close_proposals_mut();
+        if (proposals.contains(stack_small_id)) {
+            proposals.remove(stack_small_id);
+        };
+    }
+
+    fun stack_close_proposals(self: &AtomaDb): &Table<StackSmallId, StackCloseProposal> {
+        dynamic_field::borrow(&self.id, StackCloseProposalsKey {})
+    }
+
+    fun stack_close_proposals_mut(
+        self: &mut AtomaDb,
+    ): &mut Table<StackSmallId, StackCloseProposal> {
+        dynamic_field::borrow_mut(&mut self.id, StackCloseProposalsKey {})
+    }
+
+    fun node_offer_keys(self: &AtomaDb): &Table<NodeSmallId, vector<u8>> {
+        dynamic_field::borrow(&self.id, NodeOfferKeysKey {})
+    }
+
+    fun node_offer_keys_mut(self: &mut AtomaDb): &mut Table<NodeSmallId, vector<u8>> {
+        dynamic_field::borrow_mut(&mut self.id, NodeOfferKeysKey {})
+    }
+
+    fun consumed_offers(self: &AtomaDb): &Table<OfferNonce, bool> {
+        dynamic_field::borrow(&self.id, ConsumedOffersKey {})
+    }
+
+    fun consumed_offers_mut(self: &mut AtomaDb): &mut Table<OfferNonce, bool> {
+        dynamic_field::borrow_mut(&mut self.id, ConsumedOffersKey {})
+    }
+
+    fun node_regions(self: &AtomaDb): &Table<NodeSmallId, ascii::String> {
+        dynamic_field::borrow(&self.id, NodeRegionsKey {})
+    }
+
+    fun node_regions_mut(self: &mut AtomaDb): &mut Table<NodeSmallId, ascii::String> {
+        dynamic_field::borrow_mut(&mut self.id, NodeRegionsKey {})
+    }
+
+    fun model_echelon_latencies(self: &AtomaDb): &Table<ModelEchelonKey, u64> {
+        dynamic_field::borrow(&self.id, ModelEchelonLatenciesKey {})
+    }
+
+    fun model_echelon_latencies_mut(
+        self: &mut AtomaDb,
+    ): &mut Table<ModelEchelonKey, u64> {
+        dynamic_field::borrow_mut(&mut self.id, ModelEchelonLatenciesKey {})
+    }
+
+    fun insurance_pool(self: &AtomaDb): &InsurancePool {
+        dynamic_field::borrow(&self.id, InsurancePoolKey {})
+    }
+
+    fun insurance_pool_mut(self: &mut AtomaDb): &mut InsurancePool {
+        dynamic_field::borrow_mut(&mut self.id, InsurancePoolKey {})
+    }
+
+    /// Identifies a price quote.
+    ///
+    /// It's the signed terms hash of the `QUOTE_DOMAIN` and the node small
+    /// ID, task small ID, price per one million compute units, number of
+    /// compute units and expiry timestamp.
+    public fun quote_hash(
+        self: &AtomaDb,
+        node_small_id: u64,
+        task_small_id: u64,
+        price_per_one_million_compute_units: u64,
+        num_compute_units: u64,
+        expires_at_ms: u64,
+    ): vector<u8> {
+        self.signed_terms_hash(QUOTE_DOMAIN, vector[
+            node_small_id,
+            task_small_id,
+            price_per_one_million_compute_units,
+            num_compute_units,
+            expires_at_ms,
+        ])
+    }
+
+    /// Identifies a stack offer.
+    ///
+    /// It's the signed terms hash of the `OFFER_DOMAIN` and the node small
+    /// ID, task small ID, price per one million compute units, number of
+    /// compute units, expiry timestamp and nonce.
+    public fun offer_hash(
+        self: &AtomaDb,
+        node_small_id: u64,
+        task_small_id: u64,
+        price_per_one_million_compute_units: u64,
+        num_compute_units: u64,
+        expires_at_ms: u64,
+        nonce: u64,
+    ): vector<u8> {
+        self.signed_terms_hash(OFFER_DOMAIN, vector[
+            node_small_id,
+            task_small_id,
+            price_per_one_million_compute_units,
+            num_compute_units,
+            expires_at_ms,
+            nonce,
+        ])
+    }
+
+    /// The Blake2b-256 hash of the domain, the ID of the package that first
+    /// defined the db, the db ID and the little-endian encoded terms.
+    ///
+    /// The package ID stays the same across upgrades, and with the db ID it
+    /// keeps terms that a node signed for one deployment from being accepted
+    /// by another.
+    fun signed_terms_hash(
+        self: &AtomaDb,
+        domain: vector<u8>,
+        terms: vector<u64>,
+    ): vector<u8> {
+        let mut message = domain;
+        message.append(std::bcs::to_bytes(&@atoma));
+        message.append(object::id_to_bytes(&object::id(self)));
+        let mut i = 0;
+        while (i < terms.length()) {
+            message.append(std::bcs::to_bytes(&terms[i]));
+            i = i + 1;
+        };
+        sui::hash::blake2b256(&message)
+    }
+
+    /// What a Sui wallet signs when it signs the message as a personal
+    /// message: the Blake2b-256 hash of the personal message intent followed
+    /// by the BCS encoded message.
+    fun personal_message_digest(message: vector<u8>): vector<u8> {
+        let mut intent_message = vector[3, 0, 0];
+        intent_message.append(std::bcs::to_bytes(&message));
+        sui::hash::blake2b256(&intent_message)
+    }
+
+    /// The public inputs of a settlement proof, in this order:
+    /// 1. the stack small ID,
+    /// 2. the number of claimed compute units,
+    /// 3. the first 31 bytes of the committed stack proof, so that it always
+    ///    fits into the scalar field of both curves.
+    ///
+    /// Each is encoded as a 32 byte little-endian scalar.
+    /// Must match the CLI implementation.
+    public fun zk_settlement_public_inputs(
+        stack_small_id: u64,
+        num_claimed_compute_units: u64,
+        committed_stack_proof: vector<u8>,
+    ): vector<u8> {
+        let mut inputs = vector::empty();
+        append_zk_scalar(&mut inputs, std::bcs::to_bytes(&stack_small_id));
+        append_zk_scalar(&mut inputs, std::bcs::to_bytes(&num_claimed_compute_units));
+        let mut commitment = vector::empty();
+        let mut i = 0;
+        while (i < BYTES_PER_ZK_SCALAR - 1 && i < committed_stack_proof.length()) {
+            commitment.push_back(committed_stack_proof[i]);
+            i = i + 1;
+        };
+        append_zk_scalar(&mut inputs, commitment);
+        inputs
+    }
+
+    /// Appends the little-endian bytes zero-padded to a 32 byte scalar.
+    fun append_zk_scalar(inputs: &mut vector<u8>, mut le_bytes: vector<u8>) {
+        while (le_bytes.length() < BYTES_PER_ZK_SCALAR) {
+            le_bytes.push_back(0);
+        };
+        inputs.append(le_bytes);
+    }
+
+    public fun has_task_zk_verifier(self: &AtomaDb, task_small_id: u64): bool {
+        self.task_has_zk_verifier(TaskSmallId { inner: task_small_id })
+    }
+
+    /// Tasks have no verifier until the db is migrated.
+    fun task_has_zk_verifier(self: &AtomaDb, task_small_id: TaskSmallId): bool {
+        dynamic_field::exists_(&self.id, TaskZkVerifiersKey {})
+            && self.task_zk_verifiers().contains(task_small_id)
+    }
+
+    fun task_zk_verifiers(self: &AtomaDb): &Table<TaskSmallId, TaskZkVerifier> {
+        dynamic_field::borrow(&self.id, TaskZkVerifiersKey {})
+    }
+
+    fun task_zk_verifiers_mut(self: &mut AtomaDb): &mut Table<TaskSmallId, TaskZkVerifier> {
+        dynamic_field::borrow_mut(&mut self.id, TaskZkVerifiersKey {})
+    }
+
     /// Returns true if the device type is valid (Intel, AMD, Nvidia GPU, Nvidia NVSwitch)
     public fun is_device_type_valid(device_type: u16): bool {
         device_type <= NVIDIA_NVSWITCH + 5999
@@ -3850,6 +7141,38 @@ module atoma::db {
         db.tasks.contains(TaskSmallId { inner: task_small_id })
     }
 
+    #[test_only]
+    public fun set_node_reputation_score_for_testing(
+        db: &mut AtomaDb,
+        node_small_id: u64,
+        score: u8,
+        ctx: &TxContext,
+    ) {
+        let node_small_id = NodeSmallId { inner: node_small_id };
+        db.nodes.borrow_mut(node_small_id).reputation_score = ReputationScore { inner: score };
+        let mut reputation = db.node_reputation_or_fresh(node_small_id, ctx.epoch());
+        reputation.updated_at_epoch = ctx.epoch();
+        db.set_node_reputation(node_small_id, reputation);
+    }
+
+    #[test_only]
+    public fun record_dispute_free_settlement_for_testing(
+        db: &mut AtomaDb,
+        node_small_id: u64,
+        ctx: &TxContext,
+    ) {
+        db.record_dispute_free_settlement(NodeSmallId { inner: node_small_id }, ctx.epoch());
+    }
+
+    #[test_only]
+    public fun accrue_insurance_premium_for_testing(
+        db: &mut AtomaDb,
+        node_small_id: u64,
+        node_fee_amount: u64,
+    ): u64 {
+        db.accrue_insurance_premium(NodeSmallId { inner: node_small_id }, node_fee_amount)
+    }
+
     #[test_only]
     public fun is_node_subscribed_to_task(db: &AtomaDb, node_badge: &NodeBadge, task_small_id: u64): bool {
         let task = db.tasks.borrow(TaskSmallId { inner: task_small_id });
@@ -3901,6 +7224,7 @@ module atoma::db {
 
         // Add node to DB
         table::add(&mut db.nodes, small_id, node_entry);
+        db.record_heartbeat(small_id, tx_context::sender(ctx), tx_context::epoch(ctx));
 
         // Create and return badge
         let badge_id = object::new(ctx);
@@ -3934,6 +7258,11 @@ module atoma::db {
         db.stack_settlement_tickets.contains(StackSmallId { inner: stack_small_id })
     }
 
+    #[test_only]
+    public fun check_stack_exists(db: &AtomaDb, stack_small_id: u64): bool {
+        db.stacks.contains(StackSmallId { inner: stack_small_id })
+    }
+
     #[test_only]
     public fun get_stack_settlement(db: &AtomaDb, stack_small_id: u64): &StackSettlementTicket {
         db.stack_settlement_tickets.borrow(StackSmallId { inner: stack_small_id })
@@ -14,6 +14,23 @@ module atoma::gate {
     const ENoEligibleEchelons: u64 = EBase + 0;
     const ETooManyNodesToSample: u64 = EBase + 1;
     const EModalityMismatch: u64 = EBase + 2;
+    const EInvalidPriority: u64 = EBase + 3;
+
+    /// Prompts are charged the echelon fees.
+    const PriorityNormal: u8 = 0;
+    /// Prompts are charged 1.5x the echelon fees and are more likely to be
+    /// served by echelons with higher relative performance.
+    const PriorityHigh: u8 = 1;
+    /// Prompts are charged 2x the echelon fees and are even more likely to
+    /// be served by echelons with higher relative performance.
+    const PriorityUrgent: u8 = 2;
+
+    /// Echelons that no oracle measured yet are weighted as if their median
+    /// latency was this, when the submitter prefers low latency.
+    const UnmeasuredLatencyP50Ms: u64 = 1_000;
+    /// Scales the echelon weights before they are divided by the median
+    /// latency, so that they don't round down to zero.
+    const LatencyWeightScaleMs: u256 = 1_000_000;
 
     /// Models that take text as input and return text as output.
     /// Be careful about changing this as clients rely on this value.
@@ -127,6 +144,19 @@ module atoma::gate {
         output_destination: vector<u8>,
     }
 
+    /// Emitted right after `Text2TextPromptEvent` or `Text2ImagePromptEvent`
+    /// with how the prompt was submitted.
+    public struct PromptPreferencesEvent has copy, drop {
+        /// The ID of the settlement object.
+        ticket_id: ID,
+        /// Nodes should serve prompts with higher priority first.
+        /// See `PriorityNormal`, `PriorityHigh` and `PriorityUrgent`.
+        priority: u8,
+        /// Whether the echelon was picked with a preference for low latency,
+        /// see `latency_weighted_performance`.
+        prefer_low_latency: bool,
+    }
+
     #[allow(lint(public_random))]
     /// The fee is per input token.
     ///
@@ -145,6 +175,64 @@ module atoma::gate {
         output_destination: vector<u8>,
         random: &sui::random::Random,
         ctx: &mut TxContext,
+    ): ID {
+        submit_text2text_prompt_with_priority(
+            atoma,
+            wallet,
+            params,
+            max_fee_per_token,
+            nodes_to_sample,
+            output_destination,
+            PriorityNormal,
+            random,
+            ctx,
+        )
+    }
+
+    #[allow(lint(public_random))]
+    /// Same as `submit_text2text_prompt` but the user can pay a premium for
+    /// the prompt to be served faster.
+    /// The max fee is compared against the fee with the premium.
+    public fun submit_text2text_prompt_with_priority(
+        atoma: &mut AtomaDb,
+        wallet: &mut Balance<USDC>,
+        params: Text2TextPromptParams,
+        max_fee_per_token: u64,
+        nodes_to_sample: Option<u64>,
+        output_destination: vector<u8>,
+        priority: u8,
+        random: &sui::random::Random,
+        ctx: &mut TxContext,
+    ): ID {
+        submit_text2text_prompt_with_preferences(
+            atoma,
+            wallet,
+            params,
+            max_fee_per_token,
+            nodes_to_sample,
+            output_destination,
+            priority,
+            false,
+            random,
+            ctx,
+        )
+    }
+
+    #[allow(lint(public_random))]
+    /// Same as `submit_text2text_prompt_with_priority` but the user can also
+    /// prefer echelons with a low measured latency among those within the
+    /// max fee.
+    public fun submit_text2text_prompt_with_preferences(
+        atoma: &mut AtomaDb,
+        wallet: &mut Balance<USDC>,
+        params: Text2TextPromptParams,
+        max_fee_per_token: u64,
+        nodes_to_sample: Option<u64>,
+        output_destination: vector<u8>,
+        priority: u8,
+        prefer_low_latency: bool,
+        random: &sui::random::Random,
+        ctx: &mut TxContext,
     ): ID {
         let mut rng = random.new_generator(ctx);
 
@@ -167,6 +255,8 @@ module atoma::gate {
             max_fee_per_token,
             output_tokens,
             nodes_to_sample,
+            priority,
+            prefer_low_latency,
             &mut rng,
             ctx,
         );
@@ -183,7 +273,12 @@ module atoma::gate {
             ticket_id,
             chunks_count,
             nodes: selected_nodes,
-            output_destination
+            output_destination,
+        });
+        sui::event::emit(PromptPreferencesEvent {
+            ticket_id,
+            priority,
+            prefer_low_latency,
         });
 
         ticket_id
@@ -209,6 +304,68 @@ module atoma::gate {
         output_destination: vector<u8>,
         random: &sui::random::Random,
         ctx: &mut TxContext,
+    ): ID {
+        submit_text2image_prompt_with_priority(
+            atoma,
+            wallet,
+            params,
+            max_fee_per_input_token,
+            max_fee_per_output_token,
+            nodes_to_sample,
+            output_destination,
+            PriorityNormal,
+            random,
+            ctx,
+        )
+    }
+
+    #[allow(lint(public_random))]
+    /// Same as `submit_text2image_prompt` but the user can pay a premium for
+    /// the prompt to be served faster.
+    /// The max fees are compared against the fees with the premium.
+    public fun submit_text2image_prompt_with_priority(
+        atoma: &mut AtomaDb,
+        wallet: &mut Balance<USDC>,
+        params: Text2ImagePromptParams,
+        max_fee_per_input_token: u64,
+        max_fee_per_output_token: u64,
+        nodes_to_sample: Option<u64>,
+        output_destination: vector<u8>,
+        priority: u8,
+        random: &sui::random::Random,
+        ctx: &mut TxContext,
+    ): ID {
+        submit_text2image_prompt_with_preferences(
+            atoma,
+            wallet,
+            params,
+            max_fee_per_input_token,
+            max_fee_per_output_token,
+            nodes_to_sample,
+            output_destination,
+            priority,
+            false,
+            random,
+            ctx,
+        )
+    }
+
+    #[allow(lint(public_random))]
+    /// Same as `submit_text2image_prompt_with_priority` but the user can also
+    /// prefer echelons with a low measured latency among those within the
+    /// max fees.
+    public fun submit_text2image_prompt_with_preferences(
+        atoma: &mut AtomaDb,
+        wallet: &mut Balance<USDC>,
+        params: Text2ImagePromptParams,
+        max_fee_per_input_token: u64,
+        max_fee_per_output_token: u64,
+        nodes_to_sample: Option<u64>,
+        output_destination: vector<u8>,
+        priority: u8,
+        prefer_low_latency: bool,
+        random: &sui::random::Random,
+        ctx: &mut TxContext,
     ): ID {
         let mut rng = random.new_generator(ctx);
 
@@ -227,6 +384,8 @@ module atoma::gate {
             max_fee_per_output_token,
             images,
             nodes_to_sample,
+            priority,
+            prefer_low_latency,
             &mut rng,
             ctx,
         );
@@ -243,7 +402,12 @@ module atoma::gate {
             ticket_id,
             chunks_count,
             nodes: selected_nodes,
-            output_destination
+            output_destination,
+        });
+        sui::event::emit(PromptPreferencesEvent {
+            ticket_id,
+            priority,
+            prefer_low_latency,
         });
 
         ticket_id
@@ -321,6 +485,9 @@ module atoma::gate {
     /// However, the real fee is calculated when the nodes submit the results.
     /// The difference is refunded to the user.
     ///
+    /// The priority multiplies the echelon fees and the premium is stored in
+    /// the ticket so that nodes receive it on settlement.
+    ///
     /// 1. Get the model echelons from the database.
     /// 2. Randomly pick one of the echelons.
     /// 3. Sample the required number of nodes from the echelon.
@@ -341,9 +508,13 @@ module atoma::gate {
         max_fee_per_output_token: u64,
         approx_output_tokens_count: u64,
         requested_nodes_to_sample: Option<u64>,
+        priority: u8,
+        prefer_low_latency: bool,
         rng: &mut sui::random::RandomGenerator,
         ctx: &mut TxContext,
     ): (SettlementTicket, u64, vector<NodeSmallId>) {
+        assert!(priority <= PriorityUrgent, EInvalidPriority);
+
         let expected_model_modality = atoma.get_model_modality(model);
         assert!(expected_model_modality == model_modality, EModalityMismatch);
 
@@ -356,10 +527,14 @@ module atoma::gate {
         let nodes_to_sample = requested_nodes_to_sample.get_with_default(1);
         assert!(nodes_to_sample <= MaxNodesToSample, ETooManyNodesToSample);
         let echelon_index = select_eligible_echelon_at_random(
+            atoma,
+            model,
             echelons,
             nodes_to_sample,
             max_fee_per_input_token,
             max_fee_per_output_token,
+            priority,
+            prefer_low_latency,
             rng,
         );
         let echelon = echelons.borrow(echelon_index);
@@ -367,6 +542,8 @@ module atoma::gate {
         let echelon_settlement_timeout_ms =
             echelon.get_model_echelon_settlement_timeout_ms();
         let (input_fee, output_fee) = echelon.get_model_echelon_fees();
+        let input_fee = apply_priority_premium(input_fee, priority);
+        let output_fee = apply_priority_premium(output_fee, priority);
 
         // 3.
         let sampled_nodes = atoma.sample_unique_nodes_by_echelon_index(
@@ -444,7 +621,12 @@ module atoma::gate {
 
     /// 1. Filter out only appropriate echelons that are below max fee and
     ///   have enough nodes.
+    ///   The fees include the priority premium.
     /// 2. Randomly pick one of the echelons.
+    ///   The priority biases the pick towards echelons with higher relative
+    ///   performance, see `priority_weighted_performance`.
+    ///   The low latency preference biases it towards echelons with a lower
+    ///   measured latency, see `latency_weighted_performance`.
     ///
     /// We return an index into the `echelons` vector.
     ///
@@ -474,17 +656,20 @@ module atoma::gate {
     /// That's how I validated in my mind that the order does not matter,
     /// the probability will be satisfied.
     fun select_eligible_echelon_at_random(
+        atoma: &AtomaDb,
+        model: ascii::String,
         echelons: &vector<ModelEchelon>,
         nodes_to_sample: u64,
         max_fee_per_input_token: u64,
         max_fee_per_output_token: u64,
+        priority: u8,
+        prefer_low_latency: bool,
         rng: &mut sui::random::RandomGenerator,
     ): u64 {
         //
         // 1.
         //
 
-        let mut total_performance: u256 = 0;
         let mut eligible_echelons = vector::empty();
         let echelon_count = echelons.length();
         let mut index = 0;
@@ -492,6 +677,8 @@ module atoma::gate {
             let echelon = echelons.borrow(index);
 
             let (input_fee, output_fee) = echelon.get_model_echelon_fees();
+            let input_fee = apply_priority_premium(input_fee, priority);
+            let output_fee = apply_priority_premium(output_fee, priority);
             if (input_fee > max_fee_per_input_token
                 || output_fee > max_fee_per_output_token) {
                 index = index + 1;
@@ -505,11 +692,22 @@ module atoma::gate {
                 continue
             };
 
-            let performance =
-                (echelon.get_model_echelon_performance() as u256)
+            let mut performance =
+                priority_weighted_performance(
+                    echelon.get_model_echelon_performance(),
+                    priority,
+                )
                 *
                 (node_count as u256);
-            total_performance = total_performance + performance; // A
+            if (prefer_low_latency) {
+                performance = latency_weighted_performance(
+                    performance,
+                    atoma.get_model_echelon_latency_p50_ms(
+                        model,
+                        echelon.get_model_echelon_id(),
+                    ),
+                );
+            };
             eligible_echelons.push_back(EchelonIdAndPerformance {
                 index,
                 performance,
@@ -523,21 +721,96 @@ module atoma::gate {
         // 2.
         //
 
-        // modulo is ok because we don't allow performance to be 0
-        let goal = 1 + rng.generate_u256() % total_performance; // B
+        let mut performances = vector::empty();
+        let mut i = 0;
+        while (i < eligible_echelons.length()) {
+            performances.push_back(eligible_echelons[i].performance);
+            i = i + 1;
+        };
+        let picked = pick_weighted_index(&performances, rng.generate_u256());
+        eligible_echelons[picked].index
+    }
+
+    /// Steps B to D of `select_eligible_echelon_at_random`, given the weights
+    /// of the eligible echelons and the random number.
+    /// Returns an index into `weights`.
+    ///
+    /// Pure so that clients can predict which echelon a random number
+    /// selects, the CLI mirrors it in its `sampling` module.
+    /// Weights must not be zero.
+    public fun pick_weighted_index(weights: &vector<u256>, random: u256): u64 {
+        let mut total_weight = 0;
+        let mut i = 0;
+        while (i < weights.length()) {
+            total_weight = total_weight + weights[i]; // A
+            i = i + 1;
+        };
+        assert!(total_weight > 0, ENoEligibleEchelons);
+
+        let goal = 1 + random % total_weight; // B
 
-        let mut remaining_performance = total_performance;
+        let mut remaining_weight = total_weight;
+        let mut index = weights.length();
         loop {
             // index never out of bounds bcs on last iteration
-            // remaining_performance == 0 while goal > 0
-            let EchelonIdAndPerformance {
-                index, performance
-            } = eligible_echelons.pop_back();
-            remaining_performance = remaining_performance - performance; // C
+            // remaining_weight == 0 while goal > 0
+            index = index - 1;
+            remaining_weight = remaining_weight - weights[index]; // C
 
-            if (goal > remaining_performance) {
+            if (goal > remaining_weight) {
                 return index // D
             };
         }
     }
+
+    /// How much of the echelon fee is charged with the given priority, in
+    /// percent.
+    public fun priority_fee_multiplier_percent(priority: u8): u64 {
+        if (priority == PriorityNormal) {
+            100
+        } else if (priority == PriorityHigh) {
+            150
+        } else if (priority == PriorityUrgent) {
+            200
+        } else {
+            abort EInvalidPriority
+        }
+    }
+
+    fun apply_priority_premium(fee: u64, priority: u8): u64 {
+        fee * priority_fee_multiplier_percent(priority) / 100
+    }
+
+    /// Divides the weight of an echelon by its median latency, so that an
+    /// echelon twice as fast is twice as likely to be picked.
+    /// Echelons that were not measured yet count as `UnmeasuredLatencyP50Ms`.
+    ///
+    /// Pure so that clients can predict the pick, the CLI mirrors it in its
+    /// `sampling` module.
+    public fun latency_weighted_performance(
+        performance: u256,
+        latency_p50_ms: Option<u64>,
+    ): u256 {
+        let latency_p50_ms = latency_p50_ms.get_with_default(UnmeasuredLatencyP50Ms);
+        let latency_p50_ms = if (latency_p50_ms == 0) { 1 } else { latency_p50_ms };
+        let weighted = performance * LatencyWeightScaleMs / (latency_p50_ms as u256);
+        // echelons within the max fee must stay eligible
+        if (weighted == 0) { 1 } else { weighted }
+    }
+
+    /// With normal priority, this is just the relative performance.
+    /// Each priority level above that multiplies it by the relative
+    /// performance once more, which favors faster echelons.
+    fun priority_weighted_performance(
+        relative_performance: u64,
+        priority: u8,
+    ): u256 {
+        let mut weighted = relative_performance as u256;
+        let mut level = 0;
+        while (level < priority) {
+            weighted = weighted * (relative_performance as u256);
+            level = level + 1;
+        };
+        weighted
+    }
 }
@@ -24,6 +24,9 @@ module atoma::prompts {
 
     /// Submits an arbitrary text prompt.
     /// The other alternative is to use programmable txs on client.
+    ///
+    /// See the gate module for the priority levels and the low latency
+    /// preference.
     entry fun send_prompt(
         atoma: &mut AtomaDb,
         wallet: &mut Coin<USDC>,
@@ -41,6 +44,8 @@ module atoma::prompts {
         top_k: u64,
         top_p: u32,
         nodes_to_sample: Option<u64>,
+        priority: u8,
+        prefer_low_latency: bool,
         random: &Random,
         ctx: &mut TxContext,
     ) {
@@ -60,13 +65,15 @@ module atoma::prompts {
             top_k,
             top_p,
         );
-        atoma::gate::submit_text2text_prompt(
+        atoma::gate::submit_text2text_prompt_with_preferences(
             atoma,
             wallet.balance_mut(),
             params,
             max_fee_per_token,
             nodes_to_sample,
             output_destination,
+            priority,
+            prefer_low_latency,
             random,
             ctx,
         );
@@ -83,6 +90,8 @@ module atoma::prompts {
         max_fee_per_output_pixel: u64,
         prompt: vector<u8>,
         uncond_prompt: vector<u8>,
+        priority: u8,
+        prefer_low_latency: bool,
         random: &Random,
         ctx: &mut TxContext,
     ) {
@@ -111,7 +120,7 @@ module atoma::prompts {
             uncond_prompt,
             width,
         );
-        atoma::gate::submit_text2image_prompt(
+        atoma::gate::submit_text2image_prompt_with_preferences(
             atoma,
             wallet.balance_mut(),
             params,
@@ -125,6 +134,8 @@ module atoma::prompts {
             // nodes to sample
             option::some(1),
             output_destination,
+            priority,
+            prefer_low_latency,
             random,
             ctx,
         );
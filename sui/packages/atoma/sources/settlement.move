@@ -21,6 +21,10 @@ module atoma::settlement {
     const ETicketMustHaveNodes: u64 = EBase + 6;
     /// There can only be one node sampled at first for cross validation to work.
     const ECrossValidationSupportedForOneNodeOnly: u64 = EBase + 7;
+    const ENotTicketPayer: u64 = EBase + 8;
+    /// Only tickets that not every node committed to, that are not disputed
+    /// and that timed out can be cancelled.
+    const ETicketNotCancellable: u64 = EBase + 9;
 
     /// Node is the first to submit a commitment for a given ticket
     public struct FirstSubmissionEvent has copy, drop {
@@ -60,6 +64,17 @@ module atoma::settlement {
         oracle_node_id: Option<NodeSmallId>,
     }
 
+    /// The payer cancelled a ticket that not every node committed to in time.
+    /// The nodes that committed were paid, the others were slashed, and the
+    /// rest of the collected fee was refunded.
+    public struct PromptCancelledEvent has copy, drop {
+        ticket_id: ID,
+        paid_nodes: vector<NodeSmallId>,
+        fee_per_node: u64,
+        timed_out_nodes: vector<NodeSmallId>,
+        refunded_amount: u64,
+    }
+
     /// Retry settlement when there are at least this many nodes in the echelon.
     public struct RetrySettlementEvent has copy, drop {
         ticket_id: ID,
@@ -117,6 +132,11 @@ module atoma::settlement {
         /// `H(H(output, 1), H(output, 2), …, H(output, n))`
         /// where `n` is the number of sampled nodes, ie. `all.len()`.
         ///
+        /// If the prompt was submitted with `should_stream_output`, the
+        /// `output` is the stream commitment of the segments as they were
+        /// streamed instead of the final text.
+        /// See `roll_stream_commitment`.
+        ///
         /// If the final hash does not match the root, or if any node does not
         /// agree with the root, the settlement is being disputed.
         merkle_root: vector<u8>,
@@ -433,7 +453,7 @@ module atoma::settlement {
             // b
             else {
                 let new_nodes =
-                    replace_timed_out_nodes(&mut ticket, atoma, &mut rng);
+                    replace_timed_out_nodes(&mut ticket, atoma, &mut rng, ctx);
 
                 if (new_nodes.is_empty()) {
                     // if there are no more nodes to sample in this echelon
@@ -548,7 +568,7 @@ module atoma::settlement {
         // token counts, slash it
         let mut i = if (!merkle_root_match || !token_counts_match) {
             let node_id = completed[0];
-            confiscated_total.join(atoma.slash_node_on_dispute(node_id));
+            confiscated_total.join(atoma.slash_node_on_dispute(node_id, ctx));
             slashed_nodes.push_back(node_id);
 
             // first node is slashed, no need to check its leaves
@@ -565,10 +585,10 @@ module atoma::settlement {
                 let node_id = all[node_index];
                 let confiscated_from_node = if (!completed.contains(&node_id)) {
                     // the node did not submit the commitment in time
-                    atoma.slash_node_on_timeout(node_id)
+                    atoma.slash_node_on_timeout(node_id, ctx)
                 } else {
                     // the node submitted wrong commitment
-                    atoma.slash_node_on_dispute(node_id)
+                    atoma.slash_node_on_dispute(node_id, ctx)
                 };
                 confiscated_total.join(confiscated_from_node);
 
@@ -588,7 +608,7 @@ module atoma::settlement {
             if (token_counts_match && !slashed_nodes.contains(&node_id)) {
                 // the node was wrong about the token counts, we slash it if not
                 // already slashed
-                let confiscated_from_node = atoma.slash_node_on_dispute(node_id);
+                let confiscated_from_node = atoma.slash_node_on_dispute(node_id, ctx);
                 confiscated_total.join(confiscated_from_node);
                 slashed_nodes.push_back(node_id);
             } else {
@@ -664,6 +684,78 @@ module atoma::settlement {
         });
     }
 
+    /// The payer of a prompt can recover their escrow if not every node
+    /// submitted a commitment before the ticket timed out.
+    ///
+    /// This also settles the ticket partially: the nodes that committed are
+    /// paid for the tokens as if the ticket settled, see
+    /// `close_timed_out_ticket`, the nodes that missed the deadline are
+    /// slashed the same way as in `try_to_settle`, and the ticket is
+    /// destroyed.
+    /// The rest of the collected fee is refunded, all of it if no node
+    /// committed.
+    ///
+    /// # Aborts
+    /// - If the sender is not the payer of the ticket.
+    /// - If every node has committed, the ticket is being disputed, or the
+    ///   ticket has not timed out yet.
+    public entry fun cancel_prompt(
+        atoma: &mut AtomaDb,
+        ticket_id: ID,
+        ctx: &mut TxContext,
+    ) {
+        let ticket = remove_settlement_ticket(atoma, ticket_id);
+        assert!(ticket.payer == ctx.sender(), ENotTicketPayer);
+        assert!(
+            ticket.completed.length() < ticket.all.length()
+                && !ticket.is_being_disputed
+                && ticket.did_timeout(ctx),
+            ETicketNotCancellable,
+        );
+
+        let (paid_nodes, fee_per_node, timed_out_nodes, refunded_amount) =
+            close_timed_out_ticket(atoma, ticket, ctx);
+
+        sui::event::emit(PromptCancelledEvent {
+            ticket_id,
+            paid_nodes,
+            fee_per_node,
+            timed_out_nodes,
+            refunded_amount,
+        });
+    }
+
+    /// Streamed outputs are committed to segment by segment with a rolling
+    /// hash:
+    /// `c_0 = [0; 32]` and `c_i = H(c_{i-1}, segment_i)`.
+    ///
+    /// The final `c_n` then stands in place of the output when computing the
+    /// merkle chunks.
+    /// This way a receiver of the stream can verify each prefix of the
+    /// response as it arrives, and a dispute can point at the first segment
+    /// where the commitments diverge.
+    public fun roll_stream_commitment(
+        previous: vector<u8>,
+        segment: vector<u8>,
+    ): vector<u8> {
+        assert!(previous.length() == 32, EBlake2b256HashMustBe32Bytes);
+
+        let mut preimage = previous;
+        preimage.append(segment);
+        sui::hash::blake2b256(&preimage)
+    }
+
+    /// The commitment of a stream before any segment was produced.
+    public fun empty_stream_commitment(): vector<u8> {
+        let mut commitment = vector::empty();
+        let mut i = 0;
+        while (i < 32) {
+            commitment.push_back(0);
+            i = i + 1;
+        };
+        commitment
+    }
+
     // =========================================================================
     //                              Package private functions
     // =========================================================================
@@ -877,6 +969,74 @@ module atoma::settlement {
         self.timeout.timed_out_count >= MaxTicketTimeouts
     }
 
+    /// Pays each node that committed to a timed out ticket for the tokens as if
+    /// the ticket settled, at most an equal share of the collected fee, slashes
+    /// the nodes that did not commit, and refunds the rest to the payer.
+    ///
+    /// Returns the paid nodes, the fee paid to each, the slashed nodes and the
+    /// refunded amount.
+    fun close_timed_out_ticket(
+        atoma: &mut AtomaDb,
+        ticket: SettlementTicket,
+        ctx: &mut TxContext,
+    ): (vector<NodeSmallId>, u64, vector<NodeSmallId>, u64) {
+        let SettlementTicket {
+            id,
+            all,
+            completed,
+            collected_fee_in_protocol_token: collected_fee,
+            payer,
+            input_fee_per_token,
+            input_tokens_count,
+            output_fee_per_token,
+            output_tokens_count,
+
+            model_name: _,
+            echelon_id: _,
+            merkle_root: _,
+            merkle_leaves: _,
+            is_being_disputed: _,
+            token_counts_disputed_by: _,
+            timeout: _,
+            cross_validation: _,
+        } = ticket;
+        id.delete();
+
+        // the first node that committed submitted the counts
+        let fee_per_node = if (completed.is_empty()) {
+            0
+        } else {
+            let exact_fee_per_node =
+                input_fee_per_token * *input_tokens_count.borrow()
+                + output_fee_per_token * *output_tokens_count.borrow();
+            let max_fee_per_node = collected_fee / completed.length();
+            if (exact_fee_per_node < max_fee_per_node) {
+                exact_fee_per_node
+            } else {
+                max_fee_per_node
+            }
+        };
+
+        let mut timed_out_nodes = vector::empty();
+        let mut i = 0;
+        while (i < all.length()) {
+            let node_id = all[i];
+            if (completed.contains(&node_id)) {
+                atoma.attribute_fee_to_node(node_id, fee_per_node, ctx);
+            } else {
+                let confiscated = atoma.slash_node_on_timeout(node_id, ctx);
+                atoma.deposit_to_communal_treasury(confiscated);
+                timed_out_nodes.push_back(node_id);
+            };
+            i = i + 1;
+        };
+
+        let refunded_amount = collected_fee - fee_per_node * completed.length();
+        atoma.refund_to_user(payer, refunded_amount, ctx);
+
+        (completed, fee_per_node, timed_out_nodes, refunded_amount)
+    }
+
     /// # How the timeout works?
     ///
     /// When creating a ticket, we store Sui epoch id and MS since epoch started.
@@ -928,6 +1088,7 @@ module atoma::settlement {
         ticket: &mut SettlementTicket,
         atoma: &mut AtomaDb,
         rng: &mut sui::random::RandomGenerator,
+        ctx: &TxContext,
     ): vector<MapNodeToChunk> {
         let mut new_nodes = vector::empty();
         let mut i = 0;
@@ -935,7 +1096,7 @@ module atoma::settlement {
             let node_id = ticket.all[i];
 
             if (!ticket.completed.contains(&node_id)) {
-                let confiscated = atoma.slash_node_on_timeout(node_id);
+                let confiscated = atoma.slash_node_on_timeout(node_id, ctx);
                 atoma.deposit_to_communal_treasury(confiscated);
 
                 // sample another node to replace the slashed one
@@ -6,7 +6,17 @@ module atoma::db_tests {
         StackBadge, EInvalidComputeUnits, EInsufficientBalance, ENoNodesSubscribedToTask, ENodeNotSelectedForStack, 
         ETooManyComputedUnits, EStackInSettlementDispute, EInvalidCommittedStackProof, EInvalidStackMerkleLeaf, 
         ENoNodesEligibleForTask, ETaskIsPublic, ENodeNotWhitelistedForTask, AtomaManagerBadge,
+        EExceedsTaskMaxComputeUnits, EQuoteExpired, EReferralFeeShareTooHigh, EInvalidZkCurve,
+        ETaskHasNoZkVerifier, EInvalidCrossValidationProbability, EInvalidSettlementTimeout,
+        EInvalidModality, ENodeNotStale, ENodeAlreadySwept, ENodeNotInsured,
+        EInsuranceClaimAlreadyFiled, EInvalidStackDisputeWindow, ENodeNotOracle,
+        EInvalidNodeRegion, EOfferAlreadyConsumed, EInvalidOfferSignature, EInvalidQuoteSignature,
+        EStackCloseNotProposed, EStackCloseTermsMismatch, ENoStacksToCollect,
+        EInvalidPayoutSplit, EPriceBelowTaskMinimum, EInvalidAnnouncement,
+        EAnnouncementNotFound, ENodeAlreadyDisabled, ENodeBelowTaskMinVersion,
     };
+    use atoma::gate::{Self, EInvalidPriority};
+    use atoma::settlement;
     use sui::test_scenario::{Self as test, Scenario};
     use sui::coin::{Self, Coin};
     use sui::random::Random;
@@ -23,6 +33,16 @@ module atoma::db_tests {
     // At the top of the module, add these test-only constants for coin amounts
     const MINT_AMOUNT: u64 = 1_000_000_000; // 1 billion tokens for testing
 
+    /// Ed25519 key of the node 1 that signs `OFFER_SIGNATURE`, the key pair
+    /// is derived from the seed `[7; 32]`.
+    const OFFER_PUBLIC_KEY: vector<u8> = x"ea4a6c63e29c520abef5507b132ec5f9954776aebebe7b92421eea691446d22c";
+    /// Signs the offer of node 1 for task 1 at price 1000 for 100 compute
+    /// units that expires at 10_000 ms with nonce 7, as a personal message,
+    /// for package `@atoma` and the db with `TEST_DB_ID`.
+    const OFFER_SIGNATURE: vector<u8> = x"fc10b389f416dfc5ccf23dc0b98d2041035400a927a3ed237844a74296f746cadddd16c496994129d5aca16a35b55f6730584fe4a7b6fddb818137f151e51d06";
+    /// The db is the first object created in the scenario.
+    const TEST_DB_ID: address = @0x034401905bebdf8c04f3cd5f04f442a39372c8dc321c29edfb4f9cb30b23ab96;
+
     fun setup_test(): Scenario {
         let mut scenario = test::begin(SYSTEM);  // Start scenario with SYSTEM address
         // Initialize the AtomaDb and Random object
@@ -582,6 +602,69 @@ module atoma::db_tests {
         test::end(scenario);
     }
 
+    #[test]
+    fun test_remove_nodes_from_task_whitelist() {
+        let mut scenario = setup_test();
+
+        test::next_tx(&mut scenario, USER);
+        {
+            let mut db = test::take_shared<AtomaDb>(&scenario);
+            let manager_badge = test::take_from_address<AtomaManagerBadge>(&scenario, SYSTEM);
+
+            db::create_task_entry(
+                &mut db,
+                &manager_badge,
+                INFERENCE_ROLE,
+                option::none(),
+                option::none(),
+                option::none(),
+                false, // is_public
+                test::ctx(&mut scenario)
+            );
+            db::whitelist_nodes_for_task(&mut db, &manager_badge, 1, vector[1, 2, 3]);
+
+            db::remove_nodes_from_task_whitelist(&mut db, &manager_badge, 1, vector[2]);
+
+            assert!(db::is_node_whitelisted_for_task(&db, 1, 1), 0);
+            assert!(!db::is_node_whitelisted_for_task(&db, 1, 2), 1);
+            assert!(db::is_node_whitelisted_for_task(&db, 1, 3), 2);
+
+            test::return_shared(db);
+            test::return_to_address(SYSTEM, manager_badge);
+        };
+        test::end(scenario);
+    }
+
+    #[test]
+    #[expected_failure(abort_code = ENodeNotWhitelistedForTask)]
+    fun test_remove_node_not_in_task_whitelist() {
+        let mut scenario = setup_test();
+
+        test::next_tx(&mut scenario, USER);
+        {
+            let mut db = test::take_shared<AtomaDb>(&scenario);
+            let manager_badge = test::take_from_address<AtomaManagerBadge>(&scenario, SYSTEM);
+
+            db::create_task_entry(
+                &mut db,
+                &manager_badge,
+                INFERENCE_ROLE,
+                option::none(),
+                option::none(),
+                option::none(),
+                false, // is_public
+                test::ctx(&mut scenario)
+            );
+            db::whitelist_nodes_for_task(&mut db, &manager_badge, 1, vector[1]);
+
+            db::remove_nodes_from_task_whitelist(&mut db, &manager_badge, 1, vector[2]);
+
+            test::return_shared(db);
+            test::return_to_address(SYSTEM, manager_badge);
+        };
+        test::end(scenario);
+    }
+
     #[test]
     #[expected_failure(abort_code = ETaskNotFound)]
     fun test_whitelist_nodes_for_nonexistent_task() {
@@ -863,7 +946,7 @@ module atoma::db_tests {
                 &mut db,
                 &mut node_badge,
                 999, // nonexistent task_small_id
-                1000 // price_per_one_million_compute_units
+                1000, // price_per_one_million_compute_units
             );
             
             test::return_shared(db);
@@ -928,7 +1011,7 @@ module atoma::db_tests {
                 &mut db,
                 &mut node_badge,
                 1,       // task_small_id
-                1000     // price_per_one_million_compute_units
+                1000,    // price_per_one_million_compute_units
             );
             
             test::return_shared(db);
@@ -982,7 +1065,7 @@ module atoma::db_tests {
                 &mut db,
                 &mut node_badge,
                 1,       // task_small_id
-                1000     // price_per_one_million_compute_units
+                1000,    // price_per_one_million_compute_units
             );
             
             // Try to subscribe again
@@ -1052,6 +1135,159 @@ module atoma::db_tests {
         test::end(scenario);
     }
 
+    /// Creates a public task with the given minimum price and a test node.
+    fun setup_task_with_min_price(scenario: &mut Scenario, min_price: u64) {
+        test::next_tx(scenario, USER);
+        {
+            let mut db = test::take_shared<AtomaDb>(scenario);
+            let manager_badge = test::take_from_address<AtomaManagerBadge>(scenario, SYSTEM);
+
+            db::create_task_entry(
+                &mut db,
+                &manager_badge,
+                INFERENCE_ROLE,
+                option::none(),
+                option::none(),
+                option::none(),
+                true, // is_public
+                test::ctx(scenario)
+            );
+            db::set_task_min_price(&mut db, &manager_badge, 1, min_price);
+            assert!(db::get_task_min_price(&db, 1) == min_price, 0);
+
+            test::return_shared(db);
+            test::return_to_address(SYSTEM, manager_badge);
+        };
+
+        test::next_tx(scenario, USER);
+        {
+            let mut db = test::take_shared<AtomaDb>(scenario);
+            db::create_test_node(&mut db, test::ctx(scenario));
+            test::return_shared(db);
+        };
+    }
+
+    #[test]
+    fun test_subscribe_at_task_min_price() {
+        let mut scenario = setup_test();
+        setup_task_with_min_price(&mut scenario, 1000);
+
+        test::next_tx(&mut scenario, USER);
+        {
+            let mut db = test::take_shared<AtomaDb>(&scenario);
+            let mut node_badge = test::take_from_sender<NodeBadge>(&scenario);
+
+            db::subscribe_node_to_task(&mut db, &mut node_badge, 1, 1000);
+            db::update_node_subscription(&mut db, &mut node_badge, 1, 1500);
+            assert!(db::get_node_subscription_price(&db, 1, db::get_node_badge_small_id(&node_badge)) == 1500, 0);
+
+            test::return_shared(db);
+            test::return_to_sender(&scenario, node_badge);
+        };
+
+        // a minimum of 0 removes it
+        test::next_tx(&mut scenario, USER);
+        {
+            let mut db = test::take_shared<AtomaDb>(&scenario);
+            let manager_badge = test::take_from_address<AtomaManagerBadge>(&scenario, SYSTEM);
+            let mut node_badge = test::take_from_sender<NodeBadge>(&scenario);
+
+            db::set_task_min_price(&mut db, &manager_badge, 1, 0);
+            assert!(db::get_task_min_price(&db, 1) == 0, 1);
+            db::update_node_subscription(&mut db, &mut node_badge, 1, 1);
+
+            test::return_shared(db);
+            test::return_to_address(SYSTEM, manager_badge);
+            test::return_to_sender(&scenario, node_badge);
+        };
+        test::end(scenario);
+    }
+
+    #[test]
+    #[expected_failure(abort_code = EPriceBelowTaskMinimum)]
+    fun test_subscribe_below_task_min_price() {
+        let mut scenario = setup_test();
+        setup_task_with_min_price(&mut scenario, 1000);
+
+        test::next_tx(&mut scenario, USER);
+        {
+            let mut db = test::take_shared<AtomaDb>(&scenario);
+            let mut node_badge = test::take_from_sender<NodeBadge>(&scenario);
+
+            db::subscribe_node_to_task(&mut db, &mut node_badge, 1, 999);
+
+            test::return_shared(db);
+            test::return_to_sender(&scenario, node_badge);
+        };
+        test::end(scenario);
+    }
+
+    #[test]
+    #[expected_failure(abort_code = EPriceBelowTaskMinimum)]
+    fun test_update_node_subscription_below_task_min_price() {
+        let mut scenario = setup_test();
+        setup_task_with_min_price(&mut scenario, 1000);
+
+        test::next_tx(&mut scenario, USER);
+        {
+            let mut db = test::take_shared<AtomaDb>(&scenario);
+            let mut node_badge = test::take_from_sender<NodeBadge>(&scenario);
+
+            db::subscribe_node_to_task(&mut db, &mut node_badge, 1, 1000);
+            db::update_node_subscription(&mut db, &mut node_badge, 1, 999);
+
+            test::return_shared(db);
+            test::return_to_sender(&scenario, node_badge);
+        };
+        test::end(scenario);
+    }
+
+    #[test]
+    #[expected_failure(abort_code = EAnnouncementNotFound)]
+    fun test_post_and_remove_announcement() {
+        let mut scenario = setup_test();
+
+        test::next_tx(&mut scenario, USER);
+        {
+            let mut db = test::take_shared<AtomaDb>(&scenario);
+            let manager_badge = test::take_from_address<AtomaManagerBadge>(&scenario, SYSTEM);
+
+            db::post_announcement(&mut db, &manager_badge, 2, std::string::utf8(b"Upgrade"), 5, test::ctx(&mut scenario));
+            db::post_announcement(&mut db, &manager_badge, 0, std::string::utf8(b"Maintenance"), 7, test::ctx(&mut scenario));
+            let (severity, message, effective_epoch) = db::get_announcement(&db, 1);
+            assert!(severity == 2, 0);
+            assert!(message == std::string::utf8(b"Upgrade"), 1);
+            assert!(effective_epoch == 5, 2);
+            let (severity, _, effective_epoch) = db::get_announcement(&db, 2);
+            assert!(severity == 0 && effective_epoch == 7, 3);
+
+            db::remove_announcement(&mut db, &manager_badge, 1);
+            db::get_announcement(&db, 1);
+
+            test::return_shared(db);
+            test::return_to_address(SYSTEM, manager_badge);
+        };
+        test::end(scenario);
+    }
+
+    #[test]
+    #[expected_failure(abort_code = EInvalidAnnouncement)]
+    fun test_post_announcement_with_invalid_severity() {
+        let mut scenario = setup_test();
+
+        test::next_tx(&mut scenario, USER);
+        {
+            let mut db = test::take_shared<AtomaDb>(&scenario);
+            let manager_badge = test::take_from_address<AtomaManagerBadge>(&scenario, SYSTEM);
+
+            db::post_announcement(&mut db, &manager_badge, 3, std::string::utf8(b"Upgrade"), 5, test::ctx(&mut scenario));
+
+            test::return_shared(db);
+            test::return_to_address(SYSTEM, manager_badge);
+        };
+        test::end(scenario);
+    }
+
     #[test]
     fun test_update_node_subscription_success() {
         let mut scenario = setup_test();
@@ -1093,7 +1329,7 @@ module atoma::db_tests {
                 &mut db,
                 &mut node_badge,
                 1,       // task_small_id
-                1000     // price_per_one_million_compute_units
+                1000,    // price_per_one_million_compute_units
             );
             
             // Update subscription
@@ -1240,7 +1476,7 @@ module atoma::db_tests {
                 &mut db,
                 &mut node_badge,
                 1,       // task_small_id
-                1000     // price
+                1000,    // price
             );
             
             test::return_shared(db);
@@ -1320,7 +1556,7 @@ module atoma::db_tests {
                 &mut db,
                 &mut node_badge,
                 1,       // task_small_id
-                1000     // price_per_one_million_compute_units
+                1000,    // price_per_one_million_compute_units
             );
             
             // Try to update with zero price
@@ -1378,7 +1614,7 @@ module atoma::db_tests {
                 &mut db,
                 &mut node_badge,
                 1,       // task_small_id
-                1000     // price
+                1000,    // price
             );
             
             // Verify subscription before unsubscribing
@@ -1524,7 +1760,7 @@ module atoma::db_tests {
                 &mut db,
                 &mut node_badge,
                 1,       // task_small_id
-                1000     // price
+                1000,    // price
             );
             
             test::return_shared(db);
@@ -1603,7 +1839,7 @@ module atoma::db_tests {
                 &mut db,
                 &mut node_badge,
                 1,       // task_small_id
-                1000     // price
+                1000,    // price
             );
             
             // Unsubscribe
@@ -1618,7 +1854,7 @@ module atoma::db_tests {
                 &mut db,
                 &mut node_badge,
                 1,       // task_small_id
-                2000     // new price
+                2000,    // new price
             );
             
             // Verify new subscription parameters
@@ -1677,7 +1913,7 @@ module atoma::db_tests {
                 &mut db,
                 &mut node_badge,
                 1,       // task_small_id
-                1000     // price_per_one_million_compute_units
+                1000,    // price_per_one_million_compute_units
             );
             test::return_shared(db);
             test::return_to_sender(&scenario, node_badge);
@@ -1697,6 +1933,8 @@ module atoma::db_tests {
                 1,          // task_small_id
                 100,        // num_compute_units
                 1000,         // price
+                option::none(), // referrer
+                0,          // referral_fee_share_bps
                 &random,
                 test::ctx(&mut scenario)
             );
@@ -1737,6 +1975,8 @@ module atoma::db_tests {
                 999,        // nonexistent task_small_id
                 100,        // num_compute_units
                 10,         // price
+                option::none(), // referrer
+                0,          // referral_fee_share_bps
                 &random,
                 test::ctx(&mut scenario)
             );
@@ -1749,20 +1989,20 @@ module atoma::db_tests {
     }
 
     #[test]
-    #[expected_failure(abort_code = EInvalidComputeUnits)]
-    fun test_acquire_new_stack_zero_compute_units() {
+    #[expected_failure(abort_code = EExceedsTaskMaxComputeUnits)]
+    fun test_acquire_new_stack_exceeds_task_constraints() {
         let mut scenario = setup_test();
-        
+
         // Mint tokens to USER for testing
         mint_test_tokens(&mut scenario, USER, MINT_AMOUNT);
 
-        // First tx: Create a task
+        // First tx: Create a task that allows at most 60 compute units per stack
         test::next_tx(&mut scenario, USER);
         {
-            let mut db = test::take_shared<AtomaDb>(&scenario); 
+            let mut db = test::take_shared<AtomaDb>(&scenario);
             let manager_badge = test::take_from_address<AtomaManagerBadge>(&scenario, SYSTEM);
 
-            db::create_task_entry(
+            db::create_task_with_constraints_entry(
                 &mut db,
                 &manager_badge,
                 INFERENCE_ROLE,
@@ -1770,6 +2010,9 @@ module atoma::db_tests {
                 option::none(),
                 option::none(),
                 true, // is_public
+                option::some(50), // max_input_tokens
+                option::some(10), // max_output_tokens
+                vector::empty(), // allowed_modalities
                 test::ctx(&mut scenario)
             );
             test::return_shared(db);
@@ -1789,34 +2032,35 @@ module atoma::db_tests {
         {
             let mut db = test::take_shared<AtomaDb>(&scenario);
             let mut node_badge = test::take_from_sender<NodeBadge>(&scenario);
-            
+
             db::subscribe_node_to_task(
                 &mut db,
                 &mut node_badge,
                 1,       // task_small_id
-                1000     // price_per_one_million_compute_units
+                1000,    // price_per_one_million_compute_units
             );
             test::return_shared(db);
             test::return_to_sender(&scenario, node_badge);
         };
 
-        // Fourth tx: Try to create stack with zero compute units
+        // Fourth tx: Try to create a stack larger than the task allows
         test::next_tx(&mut scenario, USER);
         {
             let mut db = test::take_shared<AtomaDb>(&scenario);
             let mut wallet = test::take_from_sender<Coin<USDC>>(&scenario);
             let random = test::take_shared<Random>(&scenario);
-            
+
             db::acquire_new_stack_entry(
                 &mut db,
                 &mut wallet,
                 1,          // task_small_id
-                0,          // zero compute_units
-                1000,         // price
+                100,        // num_compute_units
+                1000,       // price
+                option::none(), // referrer
+                0,          // referral_fee_share_bps
                 &random,
                 test::ctx(&mut scenario)
             );
-            
             test::return_shared(db);
             test::return_shared(random);
             test::return_to_sender(&scenario, wallet);
@@ -1825,17 +2069,52 @@ module atoma::db_tests {
     }
 
     #[test]
-    #[expected_failure(abort_code = EInvalidPricePerComputeUnit)]
-    fun test_acquire_new_stack_zero_price() {
+    #[expected_failure(abort_code = EQuoteExpired)]
+    fun test_acquire_new_stack_with_expired_quote() {
         let mut scenario = setup_test();
-        
+
+        // Mint tokens to USER for testing
+        mint_test_tokens(&mut scenario, USER, MINT_AMOUNT);
+
+        test::next_tx(&mut scenario, USER);
+        {
+            let mut db = test::take_shared<AtomaDb>(&scenario);
+            let mut wallet = test::take_from_sender<Coin<USDC>>(&scenario);
+            let mut clock = sui::clock::create_for_testing(test::ctx(&mut scenario));
+            clock.set_for_testing(2_000);
+
+            db::acquire_new_stack_with_quote_entry(
+                &mut db,
+                &mut wallet,
+                1,          // task_small_id
+                100,        // num_compute_units
+                1000,       // price
+                1,          // quote_node_small_id
+                1_000,      // quote_expires_at_ms
+                OFFER_SIGNATURE,
+                &clock,
+                test::ctx(&mut scenario)
+            );
+
+            clock.destroy_for_testing();
+            test::return_shared(db);
+            test::return_to_sender(&scenario, wallet);
+        };
+        test::end(scenario);
+    }
+
+    #[test]
+    #[expected_failure(abort_code = EReferralFeeShareTooHigh)]
+    fun test_acquire_new_stack_referral_fee_share_too_high() {
+        let mut scenario = setup_test();
+
         // Mint tokens to USER for testing
         mint_test_tokens(&mut scenario, USER, MINT_AMOUNT);
 
         // First tx: Create a task
         test::next_tx(&mut scenario, USER);
         {
-            let mut db = test::take_shared<AtomaDb>(&scenario); 
+            let mut db = test::take_shared<AtomaDb>(&scenario);
             let manager_badge = test::take_from_address<AtomaManagerBadge>(&scenario, SYSTEM);
 
             db::create_task_entry(
@@ -1865,34 +2144,35 @@ module atoma::db_tests {
         {
             let mut db = test::take_shared<AtomaDb>(&scenario);
             let mut node_badge = test::take_from_sender<NodeBadge>(&scenario);
-            
+
             db::subscribe_node_to_task(
                 &mut db,
                 &mut node_badge,
                 1,       // task_small_id
-                1000     // price_per_one_million_compute_units
+                1000,    // price_per_one_million_compute_units
             );
             test::return_shared(db);
             test::return_to_sender(&scenario, node_badge);
         };
 
-        // Fourth tx: Try to create stack with zero price
+        // Fourth tx: Try to give the referrer more than the allowed share
         test::next_tx(&mut scenario, USER);
         {
             let mut db = test::take_shared<AtomaDb>(&scenario);
             let mut wallet = test::take_from_sender<Coin<USDC>>(&scenario);
             let random = test::take_shared<Random>(&scenario);
-            
+
             db::acquire_new_stack_entry(
                 &mut db,
                 &mut wallet,
                 1,          // task_small_id
                 100,        // num_compute_units
-                0,          // zero price
+                1000,       // price
+                option::some(ADMIN), // referrer
+                2_001,      // referral_fee_share_bps
                 &random,
                 test::ctx(&mut scenario)
             );
-            
             test::return_shared(db);
             test::return_shared(random);
             test::return_to_sender(&scenario, wallet);
@@ -1901,32 +2181,1691 @@ module atoma::db_tests {
     }
 
     #[test]
-    #[expected_failure(abort_code = EInsufficientBalance)]
-    fun test_acquire_new_stack_insufficient_balance() {
+    fun test_referral_balance_is_zero_without_referrals() {
         let mut scenario = setup_test();
-        
-        // First tx: Create a task
         test::next_tx(&mut scenario, USER);
         {
-            let mut db = test::take_shared<AtomaDb>(&scenario); 
-            let manager_badge = test::take_from_address<AtomaManagerBadge>(&scenario, SYSTEM);
-
-            db::create_task_entry(
-                &mut db,
-                &manager_badge,
-                INFERENCE_ROLE,
-                option::none(),
-                option::none(),
-                option::none(),
-                true, // is_public
-                test::ctx(&mut scenario)
-            );
+            let db = test::take_shared<AtomaDb>(&scenario);
+            assert!(db.get_referral_balance(ADMIN) == 0, 0);
             test::return_shared(db);
-            test::return_to_address(SYSTEM, manager_badge);
         };
+        test::end(scenario);
+    }
 
-        // Second tx: Register a node
-        test::next_tx(&mut scenario, NODE);
+    #[test]
+    fun test_quote_hash_binds_all_fields() {
+        let mut scenario = setup_test();
+        test::next_tx(&mut scenario, USER);
+        {
+            let db = test::take_shared<AtomaDb>(&scenario);
+            let hash = db.quote_hash(1, 2, 3, 4, 5);
+            assert!(hash.length() == 32, 0);
+            assert!(hash == db.quote_hash(1, 2, 3, 4, 5), 1);
+            assert!(hash != db.quote_hash(1, 2, 3, 4, 6), 2);
+            assert!(hash != db.quote_hash(2, 1, 3, 4, 5), 3);
+            assert!(hash != db.offer_hash(1, 2, 3, 4, 5, 0), 4);
+            test::return_shared(db);
+        };
+        test::end(scenario);
+    }
+
+    #[test]
+    fun test_offer_hash_vector() {
+        let mut scenario = setup_test();
+        test::next_tx(&mut scenario, USER);
+        {
+            let db = test::take_shared<AtomaDb>(&scenario);
+            assert!(object::id_to_address(&object::id(&db)) == TEST_DB_ID, 0);
+            // pinned so that the CLI can be checked against it
+            assert!(
+                db.offer_hash(1, 1, 1000, 100, 10_000, 7)
+                    == x"c4c058bd32062f86b8963d144fe419552bd0310256d4e4edfbc180b87403d221",
+                1,
+            );
+            assert!(db.offer_hash(1, 1, 1000, 100, 10_000, 8) != db.offer_hash(1, 1, 1000, 100, 10_000, 7), 2);
+            test::return_shared(db);
+        };
+        test::end(scenario);
+    }
+
+    /// Subscribes node 1 to task 1, registers its offer key and mints tokens
+    /// to USER.
+    fun setup_node_with_offer_key(scenario: &mut Scenario) {
+        setup_task_with_subscribed_node(scenario);
+        mint_test_tokens(scenario, USER, MINT_AMOUNT);
+
+        test::next_tx(scenario, NODE);
+        {
+            let mut db = test::take_shared<AtomaDb>(scenario);
+            let node_badge = test::take_from_sender<NodeBadge>(scenario);
+            db::set_node_offer_key(&mut db, &node_badge, OFFER_PUBLIC_KEY);
+            assert!(db.get_node_offer_key(1) == option::some(OFFER_PUBLIC_KEY), 0);
+            test::return_shared(db);
+            test::return_to_sender(scenario, node_badge);
+        };
+    }
+
+    /// Accepts the offer that `OFFER_SIGNATURE` signs, but at the given
+    /// price.
+    fun accept_offer(scenario: &mut Scenario, price: u64) {
+        test::next_tx(scenario, USER);
+        {
+            let mut db = test::take_shared<AtomaDb>(scenario);
+            let mut wallet = test::take_from_sender<Coin<USDC>>(scenario);
+            let mut clock = sui::clock::create_for_testing(test::ctx(scenario));
+            clock.set_for_testing(5_000);
+
+            db::acquire_new_stack_with_offer_entry(
+                &mut db,
+                &mut wallet,
+                1,          // task_small_id
+                100,        // num_compute_units
+                price,
+                1,          // node_small_id
+                10_000,     // offer_expires_at_ms
+                7,          // offer_nonce
+                OFFER_SIGNATURE,
+                &clock,
+                test::ctx(scenario)
+            );
+
+            clock.destroy_for_testing();
+            test::return_shared(db);
+            test::return_to_sender(scenario, wallet);
+        };
+    }
+
+    #[test]
+    fun test_acquire_new_stack_with_offer() {
+        let mut scenario = setup_test();
+        setup_node_with_offer_key(&mut scenario);
+        accept_offer(&mut scenario, 1000);
+
+        test::next_tx(&mut scenario, USER);
+        {
+            let db = test::take_shared<AtomaDb>(&scenario);
+            let stack_badge = test::take_from_sender<StackBadge>(&scenario);
+            assert!(db::get_stack_badge_small_id(&stack_badge) == 1, 0);
+            assert!(db.is_stack_offer_consumed(1, 7), 1);
+            test::return_to_sender(&scenario, stack_badge);
+            test::return_shared(db);
+        };
+        test::end(scenario);
+    }
+
+    #[test]
+    #[expected_failure(abort_code = ENodeBelowTaskMinVersion)]
+    fun test_acquire_new_stack_with_offer_from_outdated_node() {
+        let mut scenario = setup_test();
+        setup_node_with_offer_key(&mut scenario);
+        // node 1 never reported a version
+        set_task_min_node_version(&mut scenario, 1, 2, 0);
+        accept_offer(&mut scenario, 1000);
+        test::end(scenario);
+    }
+
+    #[test]
+    #[expected_failure(abort_code = EPriceBelowTaskMinimum)]
+    fun test_acquire_new_stack_with_offer_below_task_min_price() {
+        let mut scenario = setup_test();
+        setup_node_with_offer_key(&mut scenario);
+
+        test::next_tx(&mut scenario, SYSTEM);
+        {
+            let mut db = test::take_shared<AtomaDb>(&scenario);
+            let manager_badge = test::take_from_sender<AtomaManagerBadge>(&scenario);
+            db::set_task_min_price(&mut db, &manager_badge, 1, 2000);
+            test::return_shared(db);
+            test::return_to_sender(&scenario, manager_badge);
+        };
+
+        accept_offer(&mut scenario, 1000);
+        test::end(scenario);
+    }
+
+    #[test]
+    #[expected_failure(abort_code = EInvalidOfferSignature)]
+    fun test_acquire_new_stack_with_offer_at_other_price() {
+        let mut scenario = setup_test();
+        setup_node_with_offer_key(&mut scenario);
+        accept_offer(&mut scenario, 999);
+        test::end(scenario);
+    }
+
+    #[test]
+    #[expected_failure(abort_code = EOfferAlreadyConsumed)]
+    fun test_acquire_new_stack_with_offer_replayed() {
+        let mut scenario = setup_test();
+        setup_node_with_offer_key(&mut scenario);
+        accept_offer(&mut scenario, 1000);
+        accept_offer(&mut scenario, 1000);
+        test::end(scenario);
+    }
+
+    #[test]
+    #[expected_failure(abort_code = EOfferAlreadyConsumed)]
+    fun test_acquire_new_stack_with_cancelled_offer() {
+        let mut scenario = setup_test();
+        setup_node_with_offer_key(&mut scenario);
+
+        test::next_tx(&mut scenario, NODE);
+        {
+            let mut db = test::take_shared<AtomaDb>(&scenario);
+            let node_badge = test::take_from_sender<NodeBadge>(&scenario);
+            db::cancel_stack_offer(&mut db, &node_badge, 7);
+            test::return_shared(db);
+            test::return_to_sender(&scenario, node_badge);
+        };
+
+        accept_offer(&mut scenario, 1000);
+        test::end(scenario);
+    }
+
+    #[test]
+    #[expected_failure(abort_code = EInvalidQuoteSignature)]
+    fun test_acquire_new_stack_with_quote_signed_as_offer() {
+        let mut scenario = setup_test();
+        setup_node_with_offer_key(&mut scenario);
+
+        test::next_tx(&mut scenario, USER);
+        {
+            let mut db = test::take_shared<AtomaDb>(&scenario);
+            let mut wallet = test::take_from_sender<Coin<USDC>>(&scenario);
+            let mut clock = sui::clock::create_for_testing(test::ctx(&mut scenario));
+            clock.set_for_testing(5_000);
+
+            // the offer signature must not pass for a quote on the same terms
+            db::acquire_new_stack_with_quote_entry(
+                &mut db,
+                &mut wallet,
+                1,          // task_small_id
+                100,        // num_compute_units
+                1000,       // price
+                1,          // quote_node_small_id
+                10_000,     // quote_expires_at_ms
+                OFFER_SIGNATURE,
+                &clock,
+                test::ctx(&mut scenario)
+            );
+
+            clock.destroy_for_testing();
+            test::return_shared(db);
+            test::return_to_sender(&scenario, wallet);
+        };
+        test::end(scenario);
+    }
+
+    /// USER acquires stack 1 of 1M compute units at price 1000 from node 1.
+    fun setup_stack_to_close(scenario: &mut Scenario) {
+        setup_stack_at_security_level_to_close(scenario, option::none());
+    }
+
+    fun setup_stack_at_security_level_to_close(
+        scenario: &mut Scenario,
+        security_level: Option<u16>,
+    ) {
+        setup_task_at_security_level_with_subscribed_node(scenario, security_level);
+        mint_test_tokens(scenario, USER, MINT_AMOUNT);
+
+        test::next_tx(scenario, USER);
+        {
+            let mut db = test::take_shared<AtomaDb>(scenario);
+            let mut wallet = test::take_from_sender<Coin<USDC>>(scenario);
+            let random = test::take_shared<Random>(scenario);
+            db::acquire_new_stack_entry(
+                &mut db,
+                &mut wallet,
+                1,              // task_small_id
+                1_000_000,      // num_compute_units
+                1000,           // price
+                option::none(), // referrer
+                0,              // referral_fee_share_bps
+                &random,
+                test::ctx(scenario)
+            );
+            test::return_shared(db);
+            test::return_shared(random);
+            test::return_to_sender(scenario, wallet);
+        };
+    }
+
+    fun propose_stack_close_as_node(scenario: &mut Scenario, num_consumed_compute_units: u64) {
+        test::next_tx(scenario, NODE);
+        {
+            let mut db = test::take_shared<AtomaDb>(scenario);
+            let node_badge = test::take_from_sender<NodeBadge>(scenario);
+            db::propose_stack_close_as_node(&mut db, &node_badge, 1, num_consumed_compute_units);
+            test::return_shared(db);
+            test::return_to_sender(scenario, node_badge);
+        };
+    }
+
+    fun accept_stack_close(scenario: &mut Scenario, num_consumed_compute_units: u64) {
+        test::next_tx(scenario, USER);
+        {
+            let mut db = test::take_shared<AtomaDb>(scenario);
+            db::accept_stack_close(&mut db, 1, num_consumed_compute_units, test::ctx(scenario));
+            test::return_shared(db);
+        };
+    }
+
+    #[test]
+    fun test_close_stack_early() {
+        let mut scenario = setup_test();
+        setup_stack_to_close(&mut scenario);
+        propose_stack_close_as_node(&mut scenario, 400_000);
+        accept_stack_close(&mut scenario, 400_000);
+
+        // the unconsumed 600k compute units are refunded
+        test::next_tx(&mut scenario, USER);
+        {
+            let db = test::take_shared<AtomaDb>(&scenario);
+            assert!(!db::check_stack_exists(&db, 1), 0);
+            let refund_id = test::most_recent_id_for_address<Coin<USDC>>(USER).destroy_some();
+            let refund = test::take_from_sender_by_id<Coin<USDC>>(&scenario, refund_id);
+            assert!(refund.value() == 600, 1);
+            test::return_to_sender(&scenario, refund);
+            test::return_shared(db);
+        };
+
+        // the node is paid for the consumed 400k once the epoch is over
+        test::next_epoch(&mut scenario, NODE);
+        test::next_tx(&mut scenario, NODE);
+        {
+            let mut db = test::take_shared<AtomaDb>(&scenario);
+            let node_badge = test::take_from_sender<NodeBadge>(&scenario);
+            db::withdraw_fees(&mut db, &node_badge, test::ctx(&mut scenario));
+            test::return_shared(db);
+            test::return_to_sender(&scenario, node_badge);
+        };
+        test::next_tx(&mut scenario, NODE);
+        {
+            let fee = test::take_from_sender<Coin<USDC>>(&scenario);
+            assert!(fee.value() == 400, 2);
+            test::return_to_sender(&scenario, fee);
+        };
+        test::end(scenario);
+    }
+
+    #[test]
+    fun test_close_sampling_consensus_stack_early() {
+        let mut scenario = setup_test();
+        setup_stack_at_security_level_to_close(&mut scenario, option::some(2)); // SamplingConsensus
+        propose_stack_close_as_node(&mut scenario, 400_000);
+        accept_stack_close(&mut scenario, 400_000);
+
+        // the stack cost 1000 plus the 1200‰ and 12‰ charges, ie. 1212, and
+        // the consumed 400k compute units 484 of it
+        test::next_tx(&mut scenario, USER);
+        {
+            let refund_id = test::most_recent_id_for_address<Coin<USDC>>(USER).destroy_some();
+            let refund = test::take_from_sender_by_id<Coin<USDC>>(&scenario, refund_id);
+            let wallet = test::take_from_sender<Coin<USDC>>(&scenario);
+            assert!(refund.value() == 1212 - 484, 0);
+            assert!(MINT_AMOUNT - wallet.value() - refund.value() == 484, 1);
+            test::return_to_sender(&scenario, wallet);
+            test::return_to_sender(&scenario, refund);
+        };
+
+        test::next_epoch(&mut scenario, NODE);
+        test::next_tx(&mut scenario, NODE);
+        {
+            let mut db = test::take_shared<AtomaDb>(&scenario);
+            let node_badge = test::take_from_sender<NodeBadge>(&scenario);
+            db::withdraw_fees(&mut db, &node_badge, test::ctx(&mut scenario));
+            test::return_shared(db);
+            test::return_to_sender(&scenario, node_badge);
+        };
+        test::next_tx(&mut scenario, NODE);
+        {
+            let fee = test::take_from_sender<Coin<USDC>>(&scenario);
+            assert!(fee.value() == 484, 2);
+            test::return_to_sender(&scenario, fee);
+        };
+        test::end(scenario);
+    }
+
+    #[test]
+    #[expected_failure(abort_code = EStackCloseTermsMismatch)]
+    fun test_close_stack_early_with_other_terms() {
+        let mut scenario = setup_test();
+        setup_stack_to_close(&mut scenario);
+        propose_stack_close_as_node(&mut scenario, 400_000);
+        accept_stack_close(&mut scenario, 300_000);
+        test::end(scenario);
+    }
+
+    #[test]
+    #[expected_failure(abort_code = EStackCloseNotProposed)]
+    fun test_close_stack_early_accepting_own_proposal() {
+        let mut scenario = setup_test();
+        setup_stack_to_close(&mut scenario);
+
+        test::next_tx(&mut scenario, USER);
+        {
+            let mut db = test::take_shared<AtomaDb>(&scenario);
+            db::propose_stack_close(&mut db, 1, 400_000, test::ctx(&mut scenario));
+            test::return_shared(db);
+        };
+
+        accept_stack_close(&mut scenario, 400_000);
+        test::end(scenario);
+    }
+
+    /// USER acquires stack 1 of 1M compute units at price 1000 in a
+    /// confidential compute task and node 1 claims 400k of them.
+    fun setup_claimed_confidential_stack(scenario: &mut Scenario) {
+        setup_confidential_stack(scenario);
+        claim_confidential_stack(scenario);
+    }
+
+    fun setup_confidential_stack(scenario: &mut Scenario) {
+        test::next_tx(scenario, NODE);
+        {
+            let mut db = test::take_shared<AtomaDb>(scenario);
+            let manager_badge = test::take_from_address<AtomaManagerBadge>(scenario, SYSTEM);
+            db::create_task_entry(
+                &mut db,
+                &manager_badge,
+                INFERENCE_ROLE,
+                option::none(),
+                option::some(1), // ConfidentialCompute
+                option::none(),
+                true, // is_public
+                test::ctx(scenario)
+            );
+            db::create_test_node(&mut db, test::ctx(scenario));
+            test::return_shared(db);
+            test::return_to_address(SYSTEM, manager_badge);
+        };
+
+        test::next_tx(scenario, NODE);
+        {
+            let mut db = test::take_shared<AtomaDb>(scenario);
+            let mut node_badge = test::take_from_sender<NodeBadge>(scenario);
+            db::subscribe_node_to_task(&mut db, &mut node_badge, 1, 1000);
+            test::return_shared(db);
+            test::return_to_sender(scenario, node_badge);
+        };
+
+        mint_test_tokens(scenario, USER, MINT_AMOUNT);
+        test::next_tx(scenario, USER);
+        {
+            let mut db = test::take_shared<AtomaDb>(scenario);
+            let mut wallet = test::take_from_sender<Coin<USDC>>(scenario);
+            let random = test::take_shared<Random>(scenario);
+            db::acquire_new_stack_entry(
+                &mut db,
+                &mut wallet,
+                1,              // task_small_id
+                1_000_000,      // num_compute_units
+                1000,           // price
+                option::none(), // referrer
+                0,              // referral_fee_share_bps
+                &random,
+                test::ctx(scenario)
+            );
+            test::return_shared(db);
+            test::return_shared(random);
+            test::return_to_sender(scenario, wallet);
+        };
+    }
+
+    /// NODE claims 400 of the stack's fee.
+    fun claim_confidential_stack(scenario: &mut Scenario) {
+        test::next_tx(scenario, NODE);
+        {
+            let mut db = test::take_shared<AtomaDb>(scenario);
+            let node_badge = test::take_from_sender<NodeBadge>(scenario);
+            db::claim_funds_for_stacks(
+                &mut db,
+                &node_badge,
+                vector[1],
+                vector[400_000],
+                test::ctx(scenario),
+            );
+            test::return_shared(db);
+            test::return_to_sender(scenario, node_badge);
+        };
+    }
+
+    #[test]
+    fun test_collect_claimed_stacks() {
+        let mut scenario = setup_test();
+        setup_claimed_confidential_stack(&mut scenario);
+
+        // collectable after more than 2 epochs
+        let mut epoch = 0;
+        while (epoch < 3) {
+            test::next_epoch(&mut scenario, USER);
+            epoch = epoch + 1;
+        };
+
+        test::next_tx(&mut scenario, NODE2);
+        {
+            let mut db = test::take_shared<AtomaDb>(&scenario);
+            let treasury = coin::mint_for_testing<USDC>(1000, test::ctx(&mut scenario));
+            db.deposit_to_communal_treasury(treasury.into_balance());
+            // stack 2 doesn't exist and is skipped
+            db::collect_claimed_stacks(&mut db, vector[1, 2], test::ctx(&mut scenario));
+            assert!(!db::check_stack_exists(&db, 1), 0);
+            test::return_shared(db);
+        };
+
+        test::next_tx(&mut scenario, NODE2);
+        {
+            let bounty = test::take_from_sender<Coin<USDC>>(&scenario);
+            assert!(bounty.value() == 100, 1);
+            test::return_to_sender(&scenario, bounty);
+        };
+        test::end(scenario);
+    }
+
+    #[test]
+    #[expected_failure(abort_code = ENoStacksToCollect)]
+    fun test_collect_recently_claimed_stack() {
+        let mut scenario = setup_test();
+        setup_claimed_confidential_stack(&mut scenario);
+
+        test::next_epoch(&mut scenario, USER);
+        test::next_epoch(&mut scenario, USER);
+        test::next_tx(&mut scenario, NODE2);
+        {
+            let mut db = test::take_shared<AtomaDb>(&scenario);
+            db::collect_claimed_stacks(&mut db, vector[1], test::ctx(&mut scenario));
+            test::return_shared(db);
+        };
+        test::end(scenario);
+    }
+
+    #[test]
+    fun test_claim_funds_with_payout_split() {
+        let mut scenario = setup_test();
+        setup_confidential_stack(&mut scenario);
+
+        test::next_tx(&mut scenario, NODE);
+        {
+            let mut db = test::take_shared<AtomaDb>(&scenario);
+            let node_badge = test::take_from_sender<NodeBadge>(&scenario);
+            db::set_node_payout_split(&mut db, &node_badge, vector[NODE, NODE2], vector[6_000, 4_000]);
+            let (recipients, shares_bps) = db.get_node_payout_split(1);
+            assert!(recipients == vector[NODE, NODE2], 0);
+            assert!(shares_bps == vector[6_000, 4_000], 1);
+            test::return_shared(db);
+            test::return_to_sender(&scenario, node_badge);
+        };
+
+        claim_confidential_stack(&mut scenario);
+
+        test::next_tx(&mut scenario, NODE);
+        {
+            let node_share_id = test::most_recent_id_for_address<Coin<USDC>>(NODE).destroy_some();
+            let node_share = test::take_from_address_by_id<Coin<USDC>>(&scenario, NODE, node_share_id);
+            assert!(node_share.value() == 240, 2);
+            test::return_to_address(NODE, node_share);
+
+            let node2_share_id = test::most_recent_id_for_address<Coin<USDC>>(NODE2).destroy_some();
+            let node2_share = test::take_from_address_by_id<Coin<USDC>>(&scenario, NODE2, node2_share_id);
+            assert!(node2_share.value() == 160, 3);
+            test::return_to_address(NODE2, node2_share);
+        };
+        test::end(scenario);
+    }
+
+    #[test]
+    #[expected_failure(abort_code = EInvalidPayoutSplit)]
+    fun test_payout_split_must_add_up_to_100_percent() {
+        let mut scenario = setup_test();
+        setup_confidential_stack(&mut scenario);
+
+        test::next_tx(&mut scenario, NODE);
+        {
+            let mut db = test::take_shared<AtomaDb>(&scenario);
+            let node_badge = test::take_from_sender<NodeBadge>(&scenario);
+            db::set_node_payout_split(&mut db, &node_badge, vector[NODE, NODE2], vector[6_000, 3_000]);
+            test::return_shared(db);
+            test::return_to_sender(&scenario, node_badge);
+        };
+        test::end(scenario);
+    }
+
+    fun set_task_min_node_version(scenario: &mut Scenario, major: u16, minor: u16, patch: u16) {
+        test::next_tx(scenario, SYSTEM);
+        {
+            let mut db = test::take_shared<AtomaDb>(scenario);
+            let manager_badge = test::take_from_sender<AtomaManagerBadge>(scenario);
+            db::set_task_min_node_version(&mut db, &manager_badge, 1, major, minor, patch);
+            test::return_shared(db);
+            test::return_to_sender(scenario, manager_badge);
+        };
+    }
+
+    fun acquire_stack_of_task_1(scenario: &mut Scenario) {
+        mint_test_tokens(scenario, USER, MINT_AMOUNT);
+        test::next_tx(scenario, USER);
+        {
+            let mut db = test::take_shared<AtomaDb>(scenario);
+            let mut wallet = test::take_from_sender<Coin<USDC>>(scenario);
+            let random = test::take_shared<Random>(scenario);
+            db::acquire_new_stack_entry(
+                &mut db,
+                &mut wallet,
+                1,              // task_small_id
+                1_000_000,      // num_compute_units
+                1000,           // price
+                option::none(), // referrer
+                0,              // referral_fee_share_bps
+                &random,
+                test::ctx(scenario)
+            );
+            test::return_shared(db);
+            test::return_shared(random);
+            test::return_to_sender(scenario, wallet);
+        };
+    }
+
+    #[test]
+    fun test_node_sampled_with_min_version() {
+        let mut scenario = setup_test();
+        setup_task_with_subscribed_node(&mut scenario);
+        set_task_min_node_version(&mut scenario, 1, 2, 0);
+
+        test::next_tx(&mut scenario, NODE);
+        {
+            let mut db = test::take_shared<AtomaDb>(&scenario);
+            let node_badge = test::take_from_sender<NodeBadge>(&scenario);
+            db::set_node_software_version(&mut db, &node_badge, 1, 2, 3);
+            assert!(db.get_node_software_version(1) == option::some(vector[1, 2, 3]), 0);
+            test::return_shared(db);
+            test::return_to_sender(&scenario, node_badge);
+        };
+
+        acquire_stack_of_task_1(&mut scenario);
+        test::next_tx(&mut scenario, USER);
+        {
+            let db = test::take_shared<AtomaDb>(&scenario);
+            assert!(db::check_stack_exists(&db, 1), 1);
+            test::return_shared(db);
+        };
+        test::end(scenario);
+    }
+
+    #[test]
+    #[expected_failure(abort_code = ENoNodesEligibleForTask)]
+    fun test_outdated_node_not_sampled() {
+        let mut scenario = setup_test();
+        setup_task_with_subscribed_node(&mut scenario);
+        set_task_min_node_version(&mut scenario, 1, 2, 0);
+
+        test::next_tx(&mut scenario, NODE);
+        {
+            let mut db = test::take_shared<AtomaDb>(&scenario);
+            let node_badge = test::take_from_sender<NodeBadge>(&scenario);
+            db::set_node_software_version(&mut db, &node_badge, 1, 1, 9);
+            test::return_shared(db);
+            test::return_to_sender(&scenario, node_badge);
+        };
+
+        acquire_stack_of_task_1(&mut scenario);
+        test::end(scenario);
+    }
+
+    #[test]
+    fun test_node_reputation_decays_towards_baseline() {
+        let mut scenario = setup_test();
+        test::next_tx(&mut scenario, NODE);
+        {
+            let mut db = test::take_shared<AtomaDb>(&scenario);
+            db::create_test_node(&mut db, test::ctx(&mut scenario));
+            db::create_test_node(&mut db, test::ctx(&mut scenario));
+            db::set_node_reputation_score_for_testing(&mut db, 1, 80, test::ctx(&mut scenario));
+            db::set_node_reputation_score_for_testing(&mut db, 2, 49, test::ctx(&mut scenario));
+            test::return_shared(db);
+        };
+
+        // a point every 7 epochs
+        let mut epoch = 0;
+        while (epoch < 20) {
+            test::next_epoch(&mut scenario, NODE);
+            epoch = epoch + 1;
+        };
+
+        test::next_tx(&mut scenario, NODE);
+        {
+            let db = test::take_shared<AtomaDb>(&scenario);
+            assert!(db.get_node_reputation_score(1, test::ctx(&mut scenario)) == 78, 0);
+            // never past the baseline
+            assert!(db.get_node_reputation_score(2, test::ctx(&mut scenario)) == 50, 1);
+            test::return_shared(db);
+        };
+        test::end(scenario);
+    }
+
+    fun setup_task_with_subscribed_node(scenario: &mut Scenario) {
+        setup_task_at_security_level_with_subscribed_node(scenario, option::none());
+    }
+
+    fun setup_task_at_security_level_with_subscribed_node(
+        scenario: &mut Scenario,
+        security_level: Option<u16>,
+    ) {
+        test::next_tx(scenario, NODE);
+        {
+            let mut db = test::take_shared<AtomaDb>(scenario);
+            let manager_badge = test::take_from_address<AtomaManagerBadge>(scenario, SYSTEM);
+            db::create_task_entry(
+                &mut db,
+                &manager_badge,
+                INFERENCE_ROLE,
+                option::none(),
+                security_level,
+                option::none(),
+                true, // is_public
+                test::ctx(scenario)
+            );
+            db::create_test_node(&mut db, test::ctx(scenario));
+            test::return_shared(db);
+            test::return_to_address(SYSTEM, manager_badge);
+        };
+
+        test::next_tx(scenario, NODE);
+        {
+            let mut db = test::take_shared<AtomaDb>(scenario);
+            let mut node_badge = test::take_from_sender<NodeBadge>(scenario);
+            db::subscribe_node_to_task(&mut db, &mut node_badge, 1, 1000);
+            test::return_shared(db);
+            test::return_to_sender(scenario, node_badge);
+        };
+    }
+
+    #[test]
+    fun test_sweep_stale_node_and_resubscribe() {
+        let mut scenario = setup_test();
+        setup_task_with_subscribed_node(&mut scenario);
+
+        // stale after more than 2 epochs without a heartbeat
+        let mut epoch = 0;
+        while (epoch < 3) {
+            test::next_epoch(&mut scenario, USER);
+            epoch = epoch + 1;
+        };
+
+        test::next_tx(&mut scenario, USER);
+        {
+            let mut db = test::take_shared<AtomaDb>(&scenario);
+            db::sweep_stale_node(&mut db, 1, 1, test::ctx(&mut scenario));
+            test::return_shared(db);
+        };
+
+        test::next_tx(&mut scenario, NODE);
+        {
+            let mut db = test::take_shared<AtomaDb>(&scenario);
+            let mut node_badge = test::take_from_sender<NodeBadge>(&scenario);
+            assert!(!db::is_node_subscribed_to_task(&db, &node_badge, 1), 0);
+
+            // back online
+            db::heartbeat(&mut db, &node_badge, test::ctx(&mut scenario));
+            assert!(db.get_node_last_heartbeat_epoch(1) == 3, 1);
+            db::subscribe_node_to_task(&mut db, &mut node_badge, 1, 2000);
+            assert!(db::is_node_subscribed_to_task(&db, &node_badge, 1), 2);
+            assert!(db::get_node_subscription_price(&db, 1, 1) == 2000, 3);
+
+            test::return_shared(db);
+            test::return_to_sender(&scenario, node_badge);
+        };
+        test::end(scenario);
+    }
+
+    #[test]
+    #[expected_failure(abort_code = ENodeNotStale)]
+    fun test_sweep_node_with_recent_heartbeat() {
+        let mut scenario = setup_test();
+        setup_task_with_subscribed_node(&mut scenario);
+
+        test::next_epoch(&mut scenario, USER);
+        test::next_epoch(&mut scenario, USER);
+        test::next_tx(&mut scenario, NODE);
+        {
+            let mut db = test::take_shared<AtomaDb>(&scenario);
+            let node_badge = test::take_from_sender<NodeBadge>(&scenario);
+            db::heartbeat(&mut db, &node_badge, test::ctx(&mut scenario));
+            test::return_shared(db);
+            test::return_to_sender(&scenario, node_badge);
+        };
+
+        test::next_epoch(&mut scenario, USER);
+        test::next_epoch(&mut scenario, USER);
+        test::next_tx(&mut scenario, USER);
+        {
+            let mut db = test::take_shared<AtomaDb>(&scenario);
+            db::sweep_stale_node(&mut db, 1, 1, test::ctx(&mut scenario));
+            test::return_shared(db);
+        };
+        test::end(scenario);
+    }
+
+    #[test]
+    #[expected_failure(abort_code = ENodeAlreadySwept)]
+    fun test_sweep_resubscribed_node_without_heartbeat() {
+        let mut scenario = setup_test();
+        setup_task_with_subscribed_node(&mut scenario);
+
+        let mut epoch = 0;
+        while (epoch < 3) {
+            test::next_epoch(&mut scenario, USER);
+            epoch = epoch + 1;
+        };
+
+        test::next_tx(&mut scenario, USER);
+        {
+            let mut db = test::take_shared<AtomaDb>(&scenario);
+            db::sweep_stale_node(&mut db, 1, 1, test::ctx(&mut scenario));
+            test::return_shared(db);
+        };
+
+        test::next_tx(&mut scenario, NODE);
+        {
+            let mut db = test::take_shared<AtomaDb>(&scenario);
+            let mut node_badge = test::take_from_sender<NodeBadge>(&scenario);
+            db::subscribe_node_to_task(&mut db, &mut node_badge, 1, 1000);
+            test::return_shared(db);
+            test::return_to_sender(&scenario, node_badge);
+        };
+
+        test::next_tx(&mut scenario, USER);
+        {
+            let mut db = test::take_shared<AtomaDb>(&scenario);
+            db::sweep_stale_node(&mut db, 1, 1, test::ctx(&mut scenario));
+            test::return_shared(db);
+        };
+        test::end(scenario);
+    }
+
+    /// NODE is subscribed to tasks 1 and 2 and went stale, and the communal
+    /// treasury holds 10 sweep rewards.
+    fun setup_node_stale_in_two_tasks(scenario: &mut Scenario) {
+        setup_task_with_subscribed_node(scenario);
+
+        test::next_tx(scenario, NODE);
+        {
+            let mut db = test::take_shared<AtomaDb>(scenario);
+            let manager_badge = test::take_from_address<AtomaManagerBadge>(scenario, SYSTEM);
+            db::create_task_entry(
+                &mut db,
+                &manager_badge,
+                INFERENCE_ROLE,
+                option::none(),
+                option::none(),
+                option::none(),
+                true, // is_public
+                test::ctx(scenario)
+            );
+            let treasury = coin::mint_for_testing<USDC>(10_000, test::ctx(scenario));
+            db.deposit_to_communal_treasury(treasury.into_balance());
+            test::return_shared(db);
+            test::return_to_address(SYSTEM, manager_badge);
+        };
+
+        test::next_tx(scenario, NODE);
+        {
+            let mut db = test::take_shared<AtomaDb>(scenario);
+            let mut node_badge = test::take_from_sender<NodeBadge>(scenario);
+            db::subscribe_node_to_task(&mut db, &mut node_badge, 2, 1000);
+            test::return_shared(db);
+            test::return_to_sender(scenario, node_badge);
+        };
+
+        let mut epoch = 0;
+        while (epoch < 3) {
+            test::next_epoch(scenario, USER);
+            epoch = epoch + 1;
+        };
+    }
+
+    fun sweep_from_both_tasks(scenario: &mut Scenario, sweeper: address) {
+        test::next_tx(scenario, sweeper);
+        {
+            let mut db = test::take_shared<AtomaDb>(scenario);
+            db::sweep_stale_node(&mut db, 1, 1, test::ctx(scenario));
+            db::sweep_stale_node(&mut db, 2, 1, test::ctx(scenario));
+            test::return_shared(db);
+        };
+    }
+
+    #[test]
+    fun test_sweep_stale_node_rewarded_once_per_heartbeat() {
+        let mut scenario = setup_test();
+        setup_node_stale_in_two_tasks(&mut scenario);
+        sweep_from_both_tasks(&mut scenario, USER);
+
+        test::next_tx(&mut scenario, USER);
+        {
+            assert!(test::ids_for_sender<Coin<USDC>>(&scenario).length() == 1, 0);
+            let reward = test::take_from_sender<Coin<USDC>>(&scenario);
+            assert!(reward.value() == 1000, 1);
+            test::return_to_sender(&scenario, reward);
+        };
+        test::end(scenario);
+    }
+
+    #[test]
+    fun test_sweep_own_stale_node_not_rewarded() {
+        let mut scenario = setup_test();
+        setup_node_stale_in_two_tasks(&mut scenario);
+        sweep_from_both_tasks(&mut scenario, NODE);
+
+        test::next_tx(&mut scenario, NODE);
+        {
+            assert!(test::ids_for_sender<Coin<USDC>>(&scenario).is_empty(), 0);
+        };
+        test::end(scenario);
+    }
+
+    #[test]
+    #[expected_failure(abort_code = ENodeAlreadyDisabled)]
+    fun test_heartbeat_of_disabled_node() {
+        let mut scenario = setup_test();
+        setup_task_with_subscribed_node(&mut scenario);
+
+        test::next_tx(&mut scenario, NODE);
+        {
+            let mut db = test::take_shared<AtomaDb>(&scenario);
+            let node_badge = test::take_from_sender<NodeBadge>(&scenario);
+            db::permanently_disable_node(&mut db, &node_badge, test::ctx(&mut scenario));
+            db::heartbeat(&mut db, &node_badge, test::ctx(&mut scenario));
+            test::return_shared(db);
+            test::return_to_sender(&scenario, node_badge);
+        };
+        test::end(scenario);
+    }
+
+    /// NODE is insured and USER bought a stack of 1M compute units at 1000.
+    fun setup_insured_stack(scenario: &mut Scenario) {
+        setup_task_with_subscribed_node(scenario);
+        mint_test_tokens(scenario, USER, MINT_AMOUNT);
+
+        test::next_tx(scenario, NODE);
+        {
+            let mut db = test::take_shared<AtomaDb>(scenario);
+            let node_badge = test::take_from_sender<NodeBadge>(scenario);
+            db::join_insurance_pool(&mut db, &node_badge, test::ctx(scenario));
+            assert!(db::is_node_insured(&db, 1), 0);
+            test::return_shared(db);
+            test::return_to_sender(scenario, node_badge);
+        };
+
+        test::next_tx(scenario, USER);
+        {
+            let mut db = test::take_shared<AtomaDb>(scenario);
+            let mut wallet = test::take_from_sender<Coin<USDC>>(scenario);
+            let random = test::take_shared<Random>(scenario);
+            db::acquire_new_stack_entry(
+                &mut db,
+                &mut wallet,
+                1,          // task_small_id
+                1_000_000,  // num_compute_units
+                1000,       // price
+                option::none(), // referrer
+                0,          // referral_fee_share_bps
+                &random,
+                test::ctx(scenario)
+            );
+            test::return_shared(db);
+            test::return_shared(random);
+            test::return_to_sender(scenario, wallet);
+        };
+    }
+
+    #[test]
+    fun test_insurance_premium_and_claim_payout() {
+        let mut scenario = setup_test();
+        setup_insured_stack(&mut scenario);
+
+        test::next_tx(&mut scenario, NODE);
+        {
+            let mut db = test::take_shared<AtomaDb>(&scenario);
+            // the default premium is 1%
+            assert!(db::accrue_insurance_premium_for_testing(&mut db, 1, 1000) == 10, 0);
+            assert!(db::get_insurance_pool_balance(&db) == 10, 1);
+            test::return_shared(db);
+        };
+
+        test::next_tx(&mut scenario, USER);
+        {
+            let mut db = test::take_shared<AtomaDb>(&scenario);
+            db::file_insurance_claim(&mut db, 1, 500, test::ctx(&mut scenario));
+            test::return_shared(db);
+        };
+
+        test::next_tx(&mut scenario, SYSTEM);
+        {
+            let mut db = test::take_shared<AtomaDb>(&scenario);
+            let manager_badge = test::take_from_sender<AtomaManagerBadge>(&scenario);
+            // the payout is capped by what's in the pool
+            db::pay_insurance_claim(&mut db, &manager_badge, 1, test::ctx(&mut scenario));
+            assert!(db::get_insurance_pool_balance(&db) == 0, 2);
+            test::return_shared(db);
+            test::return_to_sender(&scenario, manager_badge);
+        };
+        test::end(scenario);
+    }
+
+    #[test]
+    #[expected_failure(abort_code = EInsuranceClaimAlreadyFiled)]
+    fun test_insurance_claim_filed_twice() {
+        let mut scenario = setup_test();
+        setup_insured_stack(&mut scenario);
+
+        test::next_tx(&mut scenario, USER);
+        {
+            let mut db = test::take_shared<AtomaDb>(&scenario);
+            db::file_insurance_claim(&mut db, 1, 500, test::ctx(&mut scenario));
+            db::file_insurance_claim(&mut db, 1, 500, test::ctx(&mut scenario));
+            test::return_shared(db);
+        };
+        test::end(scenario);
+    }
+
+    #[test]
+    #[expected_failure(abort_code = ENodeNotInsured)]
+    fun test_insurance_claim_for_uninsured_node() {
+        let mut scenario = setup_test();
+        setup_insured_stack(&mut scenario);
+
+        test::next_tx(&mut scenario, NODE);
+        {
+            let mut db = test::take_shared<AtomaDb>(&scenario);
+            let node_badge = test::take_from_sender<NodeBadge>(&scenario);
+            db::leave_insurance_pool(&mut db, &node_badge);
+            assert!(db::accrue_insurance_premium_for_testing(&mut db, 1, 1000) == 0, 0);
+            test::return_shared(db);
+            test::return_to_sender(&scenario, node_badge);
+        };
+
+        test::next_tx(&mut scenario, USER);
+        {
+            let mut db = test::take_shared<AtomaDb>(&scenario);
+            db::file_insurance_claim(&mut db, 1, 500, test::ctx(&mut scenario));
+            test::return_shared(db);
+        };
+        test::end(scenario);
+    }
+
+    #[test]
+    fun test_node_reputation_recovers_with_dispute_free_settlements() {
+        let mut scenario = setup_test();
+        test::next_tx(&mut scenario, NODE);
+        {
+            let mut db = test::take_shared<AtomaDb>(&scenario);
+            db::create_test_node(&mut db, test::ctx(&mut scenario));
+
+            let mut i = 0;
+            while (i < 9) {
+                db::record_dispute_free_settlement_for_testing(&mut db, 1, test::ctx(&mut scenario));
+                i = i + 1;
+            };
+            assert!(db.get_node_reputation_score(1, test::ctx(&mut scenario)) == 50, 0);
+
+            db::record_dispute_free_settlement_for_testing(&mut db, 1, test::ctx(&mut scenario));
+            assert!(db.get_node_reputation_score(1, test::ctx(&mut scenario)) == 51, 1);
+            assert!(db.get_node_dispute_free_settlements_streak(1) == 10, 2);
+            test::return_shared(db);
+        };
+        test::end(scenario);
+    }
+
+    #[test]
+    fun test_zero_fee_claim_does_not_extend_dispute_free_streak() {
+        let mut scenario = setup_test();
+        setup_confidential_stack(&mut scenario);
+
+        // 999 compute units at 1000 per million round down to a fee of 0
+        test::next_tx(&mut scenario, NODE);
+        {
+            let mut db = test::take_shared<AtomaDb>(&scenario);
+            let node_badge = test::take_from_sender<NodeBadge>(&scenario);
+            db::claim_funds_for_stacks(
+                &mut db,
+                &node_badge,
+                vector[1],
+                vector[999],
+                test::ctx(&mut scenario),
+            );
+            assert!(db.get_node_dispute_free_settlements_streak(1) == 0, 0);
+            test::return_shared(db);
+            test::return_to_sender(&scenario, node_badge);
+        };
+        test::end(scenario);
+    }
+
+    #[test]
+    fun test_set_sampling_consensus_params() {
+        let mut scenario = setup_test();
+        test::next_tx(&mut scenario, ADMIN);
+        {
+            let mut db = test::take_shared<AtomaDb>(&scenario);
+            let manager_badge = test::take_from_address<AtomaManagerBadge>(&scenario, SYSTEM);
+
+            db::set_sampling_consensus_params(&mut db, &manager_badge, 250, 3, 1100, 30);
+            assert!(db.get_cross_validation_probability_permille() == 250, 0);
+            assert!(db.get_cross_validation_extra_nodes_count() == 3, 1);
+            assert!(db.get_sampling_consensus_charge_permille() == 1100, 2);
+            assert!(db.get_cross_validation_extra_nodes_charge_permille() == 30, 3);
+
+            test::return_shared(db);
+            test::return_to_address(SYSTEM, manager_badge);
+        };
+        test::end(scenario);
+    }
+
+    #[test]
+    #[expected_failure(abort_code = EInvalidCrossValidationProbability)]
+    fun test_set_sampling_consensus_params_probability_too_high() {
+        let mut scenario = setup_test();
+        test::next_tx(&mut scenario, ADMIN);
+        {
+            let mut db = test::take_shared<AtomaDb>(&scenario);
+            let manager_badge = test::take_from_address<AtomaManagerBadge>(&scenario, SYSTEM);
+
+            db::set_sampling_consensus_params(&mut db, &manager_badge, 1001, 1, 1200, 12);
+
+            test::return_shared(db);
+            test::return_to_address(SYSTEM, manager_badge);
+        };
+        test::end(scenario);
+    }
+
+    #[test]
+    fun test_set_model_echelon_settlement_timeout_ms() {
+        let mut scenario = setup_test();
+        test::next_tx(&mut scenario, ADMIN);
+        {
+            let mut db = test::take_shared<AtomaDb>(&scenario);
+            let manager_badge = test::take_from_address<AtomaManagerBadge>(&scenario, SYSTEM);
+
+            let model_name = std::ascii::string(b"llama");
+            db::add_model_entry(&mut db, &manager_badge, model_name, 0, test::ctx(&mut scenario));
+            db::add_model_echelon_entry(&mut db, &manager_badge, model_name, 1, 1, 1, 100, test::ctx(&mut scenario));
+            db::set_model_echelon_settlement_timeout_ms(&mut db, &manager_badge, model_name, 1, 120_000);
+
+            let echelons = db.get_model_echelons_if_enabled(model_name);
+            assert!(echelons[0].get_model_echelon_settlement_timeout_ms() == 120_000, 0);
+
+            test::return_shared(db);
+            test::return_to_address(SYSTEM, manager_badge);
+        };
+        test::end(scenario);
+    }
+
+    /// Registers NODE and NODE2, and makes NODE2 an oracle of echelon 1 of
+    /// llama.
+    fun setup_echelon_with_oracle(scenario: &mut Scenario) {
+        test::next_tx(scenario, NODE);
+        {
+            let mut db = test::take_shared<AtomaDb>(scenario);
+            db::create_test_node(&mut db, test::ctx(scenario));
+            test::return_shared(db);
+        };
+        test::next_tx(scenario, NODE2);
+        {
+            let mut db = test::take_shared<AtomaDb>(scenario);
+            db::create_test_node(&mut db, test::ctx(scenario));
+            test::return_shared(db);
+        };
+
+        test::next_tx(scenario, SYSTEM);
+        {
+            let mut db = test::take_shared<AtomaDb>(scenario);
+            let manager_badge = test::take_from_sender<AtomaManagerBadge>(scenario);
+            let model_name = std::ascii::string(b"llama");
+            db::add_model_entry(&mut db, &manager_badge, model_name, 0, test::ctx(scenario));
+            db::add_model_echelon_entry(&mut db, &manager_badge, model_name, 1, 1, 1, 100, test::ctx(scenario));
+            db::add_model_echelon_oracle_node(&mut db, &manager_badge, model_name, 1, 2);
+            test::return_shared(db);
+            test::return_to_sender(scenario, manager_badge);
+        };
+    }
+
+    #[test]
+    fun test_record_model_echelon_latency() {
+        let mut scenario = setup_test();
+        setup_echelon_with_oracle(&mut scenario);
+
+        test::next_tx(&mut scenario, NODE2);
+        {
+            let mut db = test::take_shared<AtomaDb>(&scenario);
+            let node_badge = test::take_from_sender<NodeBadge>(&scenario);
+            let model_name = std::ascii::string(b"llama");
+            db::record_model_echelon_latency(&mut db, &node_badge, model_name, 1, 250);
+
+            let echelon_id = db.get_model_echelons_if_enabled(model_name)[0].get_model_echelon_id();
+            assert!(db.get_model_echelon_latency_p50_ms(model_name, echelon_id) == option::some(250), 0);
+
+            test::return_shared(db);
+            test::return_to_sender(&scenario, node_badge);
+        };
+        test::end(scenario);
+    }
+
+    #[test]
+    fun test_node_and_echelon_ids_bcs_layout() {
+        // the tickets record the sampled and the completed nodes as vectors
+        // of node IDs, and the prompt events carry them and the echelon ID
+        // the same way, so off-chain decoders rely on the layout: a length
+        // prefix and a little-endian u64 per ID
+        let mut scenario = setup_test();
+        setup_echelon_with_oracle(&mut scenario);
+
+        test::next_tx(&mut scenario, NODE2);
+        {
+            let db = test::take_shared<AtomaDb>(&scenario);
+            let node_badge = test::take_from_sender<NodeBadge>(&scenario);
+            let node_small_id = node_badge.get_node_id();
+            assert!(std::bcs::to_bytes(&node_small_id) == x"0200000000000000", 0);
+            assert!(
+                std::bcs::to_bytes(&vector[node_small_id, node_small_id])
+                    == x"0202000000000000000200000000000000",
+                1,
+            );
+            let empty: vector<db::NodeSmallId> = vector[];
+            assert!(std::bcs::to_bytes(&empty) == x"00", 2);
+
+            let echelon_id = db.get_model_echelons_if_enabled(std::ascii::string(b"llama"))[0].get_model_echelon_id();
+            assert!(std::bcs::to_bytes(&echelon_id) == x"0100000000000000", 3);
+
+            test::return_shared(db);
+            test::return_to_sender(&scenario, node_badge);
+        };
+        test::end(scenario);
+    }
+
+    #[test]
+    #[expected_failure(abort_code = ENodeNotOracle)]
+    fun test_record_model_echelon_latency_not_oracle() {
+        let mut scenario = setup_test();
+        setup_echelon_with_oracle(&mut scenario);
+
+        test::next_tx(&mut scenario, NODE);
+        {
+            let mut db = test::take_shared<AtomaDb>(&scenario);
+            let node_badge = test::take_from_sender<NodeBadge>(&scenario);
+            db::record_model_echelon_latency(&mut db, &node_badge, std::ascii::string(b"llama"), 1, 250);
+            test::return_shared(db);
+            test::return_to_sender(&scenario, node_badge);
+        };
+        test::end(scenario);
+    }
+
+    #[test]
+    fun test_set_node_region() {
+        let mut scenario = setup_test();
+        test::next_tx(&mut scenario, NODE);
+        {
+            let mut db = test::take_shared<AtomaDb>(&scenario);
+            db::create_test_node(&mut db, test::ctx(&mut scenario));
+            test::return_shared(db);
+        };
+
+        test::next_tx(&mut scenario, NODE);
+        {
+            let mut db = test::take_shared<AtomaDb>(&scenario);
+            let node_badge = test::take_from_sender<NodeBadge>(&scenario);
+            assert!(db::get_node_region(&db, 1).is_none(), 0);
+            db::set_node_region(&mut db, &node_badge, std::ascii::string(b"eu-west"));
+            db::set_node_region(&mut db, &node_badge, std::ascii::string(b"us-east"));
+            assert!(db::get_node_region(&db, 1) == option::some(std::ascii::string(b"us-east")), 1);
+            test::return_shared(db);
+            test::return_to_sender(&scenario, node_badge);
+        };
+        test::end(scenario);
+    }
+
+    #[test]
+    #[expected_failure(abort_code = EInvalidNodeRegion)]
+    fun test_set_node_region_empty() {
+        let mut scenario = setup_test();
+        test::next_tx(&mut scenario, NODE);
+        {
+            let mut db = test::take_shared<AtomaDb>(&scenario);
+            db::create_test_node(&mut db, test::ctx(&mut scenario));
+            test::return_shared(db);
+        };
+
+        test::next_tx(&mut scenario, NODE);
+        {
+            let mut db = test::take_shared<AtomaDb>(&scenario);
+            let node_badge = test::take_from_sender<NodeBadge>(&scenario);
+            db::set_node_region(&mut db, &node_badge, std::ascii::string(b""));
+            test::return_shared(db);
+            test::return_to_sender(&scenario, node_badge);
+        };
+        test::end(scenario);
+    }
+
+    #[test]
+    #[expected_failure(abort_code = EInvalidSettlementTimeout)]
+    fun test_set_model_echelon_settlement_timeout_ms_too_short() {
+        let mut scenario = setup_test();
+        test::next_tx(&mut scenario, ADMIN);
+        {
+            let mut db = test::take_shared<AtomaDb>(&scenario);
+            let manager_badge = test::take_from_address<AtomaManagerBadge>(&scenario, SYSTEM);
+
+            let model_name = std::ascii::string(b"llama");
+            db::add_model_entry(&mut db, &manager_badge, model_name, 0, test::ctx(&mut scenario));
+            db::add_model_echelon_entry(&mut db, &manager_badge, model_name, 1, 1, 1, 100, test::ctx(&mut scenario));
+            db::set_model_echelon_settlement_timeout_ms(&mut db, &manager_badge, model_name, 1, 999);
+
+            test::return_shared(db);
+            test::return_to_address(SYSTEM, manager_badge);
+        };
+        test::end(scenario);
+    }
+
+    #[test]
+    fun test_is_valid_modality() {
+        assert!(db::is_valid_modality(0), 0);
+        assert!(db::is_valid_modality(1), 1);
+        assert!(db::is_valid_modality(2), 2);
+        assert!(db::is_valid_modality(3), 3);
+        // reserved for the protocol
+        assert!(!db::is_valid_modality(4), 4);
+        assert!(!db::is_valid_modality(999), 5);
+        // custom
+        assert!(db::is_valid_modality(1_000), 6);
+        assert!(db::is_valid_modality(18446744073709551615), 7);
+    }
+
+    #[test]
+    #[expected_failure(abort_code = EInvalidModality)]
+    fun test_add_model_with_reserved_modality() {
+        let mut scenario = setup_test();
+        test::next_tx(&mut scenario, ADMIN);
+        {
+            let mut db = test::take_shared<AtomaDb>(&scenario);
+            let manager_badge = test::take_from_address<AtomaManagerBadge>(&scenario, SYSTEM);
+
+            let model_name = std::ascii::string(b"llama");
+            db::add_model_entry(&mut db, &manager_badge, model_name, 4, test::ctx(&mut scenario));
+
+            test::return_shared(db);
+            test::return_to_address(SYSTEM, manager_badge);
+        };
+        test::end(scenario);
+    }
+
+    #[test]
+    fun test_zk_settlement_public_inputs_layout() {
+        let commitment = x"0123456789ABCDEF0123456789ABCDEF0123456789ABCDEF0123456789ABCDEF";
+        let inputs = db::zk_settlement_public_inputs(0x0102, 50, commitment);
+        assert!(inputs.length() == 96, 0);
+
+        // little-endian scalars
+        assert!(inputs[0] == 0x02 && inputs[1] == 0x01 && inputs[2] == 0, 1);
+        assert!(inputs[32] == 50 && inputs[33] == 0, 2);
+
+        // the last byte of the commitment is dropped to fit the scalar field
+        let mut i = 0;
+        while (i < 31) {
+            assert!(inputs[64 + i] == commitment[i], 3);
+            i = i + 1;
+        };
+        assert!(inputs[95] == 0, 4);
+
+        assert!(inputs != db::zk_settlement_public_inputs(0x0102, 49, commitment), 5);
+    }
+
+    #[test]
+    #[expected_failure(abort_code = EInvalidZkCurve)]
+    fun test_set_task_zk_verifier_invalid_curve() {
+        let mut scenario = setup_test();
+
+        test::next_tx(&mut scenario, ADMIN);
+        {
+            let mut db = test::take_shared<AtomaDb>(&scenario);
+            let manager_badge = test::take_from_address<AtomaManagerBadge>(&scenario, SYSTEM);
+
+            db::create_task_entry(
+                &mut db,
+                &manager_badge,
+                INFERENCE_ROLE,
+                option::none(),
+                option::none(),
+                option::none(),
+                true, // is_public
+                test::ctx(&mut scenario)
+            );
+            db::set_task_zk_verifier(
+                &mut db,
+                &manager_badge,
+                1,          // task_small_id
+                2,          // curve
+                vector::empty(),
+            );
+            test::return_shared(db);
+            test::return_to_address(SYSTEM, manager_badge);
+        };
+        test::end(scenario);
+    }
+
+    #[test]
+    #[expected_failure(abort_code = ETaskHasNoZkVerifier)]
+    fun test_try_settle_stack_with_zk_proof_without_verifier() {
+        let mut scenario = setup_test();
+
+        // Mint tokens to USER for testing
+        mint_test_tokens(&mut scenario, USER, MINT_AMOUNT);
+
+        // First tx: Create a task
+        test::next_tx(&mut scenario, USER);
+        {
+            let mut db = test::take_shared<AtomaDb>(&scenario);
+            let manager_badge = test::take_from_address<AtomaManagerBadge>(&scenario, SYSTEM);
+
+            db::create_task_entry(
+                &mut db,
+                &manager_badge,
+                INFERENCE_ROLE,
+                option::none(),
+                option::none(),
+                option::none(),
+                true, // is_public
+                test::ctx(&mut scenario)
+            );
+            test::return_shared(db);
+            test::return_to_address(SYSTEM, manager_badge);
+        };
+
+        // Second tx: Register a node
+        test::next_tx(&mut scenario, NODE);
+        {
+            let mut db = test::take_shared<AtomaDb>(&scenario);
+            db::create_test_node(&mut db, test::ctx(&mut scenario));
+            test::return_shared(db);
+        };
+
+        // Third tx: Subscribe node to task
+        test::next_tx(&mut scenario, NODE);
+        {
+            let mut db = test::take_shared<AtomaDb>(&scenario);
+            let mut node_badge = test::take_from_sender<NodeBadge>(&scenario);
+
+            db::subscribe_node_to_task(
+                &mut db,
+                &mut node_badge,
+                1,       // task_small_id
+                1000,    // price_per_one_million_compute_units
+            );
+            test::return_shared(db);
+            test::return_to_sender(&scenario, node_badge);
+        };
+
+        // Fourth tx: Create a stack
+        test::next_tx(&mut scenario, USER);
+        {
+            let mut db = test::take_shared<AtomaDb>(&scenario);
+            let mut wallet = test::take_from_sender<Coin<USDC>>(&scenario);
+            let random = test::take_shared<Random>(&scenario);
+
+            db::acquire_new_stack_entry(
+                &mut db,
+                &mut wallet,
+                1,          // task_small_id
+                50,         // num_compute_units
+                1000,       // price
+                option::none(), // referrer
+                0,          // referral_fee_share_bps
+                &random,
+                test::ctx(&mut scenario)
+            );
+
+            test::return_shared(db);
+            test::return_shared(random);
+            test::return_to_sender(&scenario, wallet);
+        };
+
+        // Fifth tx: Try to settle the stack with a proof
+        test::next_tx(&mut scenario, NODE);
+        {
+            let mut db = test::take_shared<AtomaDb>(&scenario);
+            let node_badge = test::take_from_sender<NodeBadge>(&scenario);
+
+            let proof = x"0123456789ABCDEF0123456789ABCDEF0123456789ABCDEF0123456789ABCDEF";
+            let leaf = x"FEDCBA9876543210FEDCBA9876543210FEDCBA9876543210FEDCBA9876543210";
+
+            db::try_settle_stack_with_zk_proof(
+                &mut db,
+                &node_badge,
+                1,          // stack_small_id
+                50,         // num_claimed_compute_units
+                proof,      // committed_stack_proof
+                leaf,       // stack_merkle_leaf
+                vector::empty(), // proof_points
+                test::ctx(&mut scenario)
+            );
+
+            test::return_shared(db);
+            test::return_to_sender(&scenario, node_badge);
+        };
+        test::end(scenario);
+    }
+
+    #[test]
+    fun test_roll_stream_commitment_chains_segments() {
+        let empty = settlement::empty_stream_commitment();
+        assert!(empty.length() == 32, 0);
+
+        let c1 = settlement::roll_stream_commitment(empty, b"Hello");
+        let c2 = settlement::roll_stream_commitment(c1, b", world");
+
+        let mut preimage = empty;
+        preimage.append(b"Hello");
+        assert!(c1 == sui::hash::blake2b256(&preimage), 1);
+        let mut preimage = c1;
+        preimage.append(b", world");
+        assert!(c2 == sui::hash::blake2b256(&preimage), 2);
+
+        // the same text split differently is a different stream
+        let c = settlement::roll_stream_commitment(empty, b"Hello, world");
+        assert!(c != c2, 3);
+    }
+
+    #[test]
+    fun test_priority_fee_multiplier() {
+        assert!(gate::priority_fee_multiplier_percent(0) == 100, 0);
+        assert!(gate::priority_fee_multiplier_percent(1) == 150, 1);
+        assert!(gate::priority_fee_multiplier_percent(2) == 200, 2);
+    }
+
+    #[test]
+    #[expected_failure(abort_code = EInvalidPriority)]
+    fun test_priority_fee_multiplier_invalid_priority() {
+        gate::priority_fee_multiplier_percent(3);
+    }
+
+    #[test]
+    #[expected_failure(abort_code = EInvalidComputeUnits)]
+    fun test_acquire_new_stack_zero_compute_units() {
+        let mut scenario = setup_test();
+        
+        // Mint tokens to USER for testing
+        mint_test_tokens(&mut scenario, USER, MINT_AMOUNT);
+
+        // First tx: Create a task
+        test::next_tx(&mut scenario, USER);
+        {
+            let mut db = test::take_shared<AtomaDb>(&scenario); 
+            let manager_badge = test::take_from_address<AtomaManagerBadge>(&scenario, SYSTEM);
+
+            db::create_task_entry(
+                &mut db,
+                &manager_badge,
+                INFERENCE_ROLE,
+                option::none(),
+                option::none(),
+                option::none(),
+                true, // is_public
+                test::ctx(&mut scenario)
+            );
+            test::return_shared(db);
+            test::return_to_address(SYSTEM, manager_badge);
+        };
+
+        // Second tx: Register a node
+        test::next_tx(&mut scenario, NODE);
+        {
+            let mut db = test::take_shared<AtomaDb>(&scenario);
+            db::create_test_node(&mut db, test::ctx(&mut scenario));
+            test::return_shared(db);
+        };
+
+        // Third tx: Subscribe node to task
+        test::next_tx(&mut scenario, NODE);
+        {
+            let mut db = test::take_shared<AtomaDb>(&scenario);
+            let mut node_badge = test::take_from_sender<NodeBadge>(&scenario);
+            
+            db::subscribe_node_to_task(
+                &mut db,
+                &mut node_badge,
+                1,       // task_small_id
+                1000,    // price_per_one_million_compute_units
+            );
+            test::return_shared(db);
+            test::return_to_sender(&scenario, node_badge);
+        };
+
+        // Fourth tx: Try to create stack with zero compute units
+        test::next_tx(&mut scenario, USER);
+        {
+            let mut db = test::take_shared<AtomaDb>(&scenario);
+            let mut wallet = test::take_from_sender<Coin<USDC>>(&scenario);
+            let random = test::take_shared<Random>(&scenario);
+            
+            db::acquire_new_stack_entry(
+                &mut db,
+                &mut wallet,
+                1,          // task_small_id
+                0,          // zero compute_units
+                1000,         // price
+                option::none(), // referrer
+                0,          // referral_fee_share_bps
+                &random,
+                test::ctx(&mut scenario)
+            );
+            
+            test::return_shared(db);
+            test::return_shared(random);
+            test::return_to_sender(&scenario, wallet);
+        };
+        test::end(scenario);
+    }
+
+    #[test]
+    #[expected_failure(abort_code = EInvalidPricePerComputeUnit)]
+    fun test_acquire_new_stack_zero_price() {
+        let mut scenario = setup_test();
+        
+        // Mint tokens to USER for testing
+        mint_test_tokens(&mut scenario, USER, MINT_AMOUNT);
+
+        // First tx: Create a task
+        test::next_tx(&mut scenario, USER);
+        {
+            let mut db = test::take_shared<AtomaDb>(&scenario); 
+            let manager_badge = test::take_from_address<AtomaManagerBadge>(&scenario, SYSTEM);
+
+            db::create_task_entry(
+                &mut db,
+                &manager_badge,
+                INFERENCE_ROLE,
+                option::none(),
+                option::none(),
+                option::none(),
+                true, // is_public
+                test::ctx(&mut scenario)
+            );
+            test::return_shared(db);
+            test::return_to_address(SYSTEM, manager_badge);
+        };
+
+        // Second tx: Register a node
+        test::next_tx(&mut scenario, NODE);
+        {
+            let mut db = test::take_shared<AtomaDb>(&scenario);
+            db::create_test_node(&mut db, test::ctx(&mut scenario));
+            test::return_shared(db);
+        };
+
+        // Third tx: Subscribe node to task
+        test::next_tx(&mut scenario, NODE);
+        {
+            let mut db = test::take_shared<AtomaDb>(&scenario);
+            let mut node_badge = test::take_from_sender<NodeBadge>(&scenario);
+            
+            db::subscribe_node_to_task(
+                &mut db,
+                &mut node_badge,
+                1,       // task_small_id
+                1000,    // price_per_one_million_compute_units
+            );
+            test::return_shared(db);
+            test::return_to_sender(&scenario, node_badge);
+        };
+
+        // Fourth tx: Try to create stack with zero price
+        test::next_tx(&mut scenario, USER);
+        {
+            let mut db = test::take_shared<AtomaDb>(&scenario);
+            let mut wallet = test::take_from_sender<Coin<USDC>>(&scenario);
+            let random = test::take_shared<Random>(&scenario);
+            
+            db::acquire_new_stack_entry(
+                &mut db,
+                &mut wallet,
+                1,          // task_small_id
+                100,        // num_compute_units
+                0,          // zero price
+                option::none(), // referrer
+                0,          // referral_fee_share_bps
+                &random,
+                test::ctx(&mut scenario)
+            );
+            
+            test::return_shared(db);
+            test::return_shared(random);
+            test::return_to_sender(&scenario, wallet);
+        };
+        test::end(scenario);
+    }
+
+    #[test]
+    #[expected_failure(abort_code = EInsufficientBalance)]
+    fun test_acquire_new_stack_insufficient_balance() {
+        let mut scenario = setup_test();
+        
+        // First tx: Create a task
+        test::next_tx(&mut scenario, USER);
+        {
+            let mut db = test::take_shared<AtomaDb>(&scenario); 
+            let manager_badge = test::take_from_address<AtomaManagerBadge>(&scenario, SYSTEM);
+
+            db::create_task_entry(
+                &mut db,
+                &manager_badge,
+                INFERENCE_ROLE,
+                option::none(),
+                option::none(),
+                option::none(),
+                true, // is_public
+                test::ctx(&mut scenario)
+            );
+            test::return_shared(db);
+            test::return_to_address(SYSTEM, manager_badge);
+        };
+
+        // Second tx: Register a node
+        test::next_tx(&mut scenario, NODE);
         {
             let mut db = test::take_shared<AtomaDb>(&scenario);
             db::create_test_node(&mut db, test::ctx(&mut scenario));
@@ -1962,6 +3901,8 @@ module atoma::db_tests {
                 1,          // task_small_id
                 100,        // num_compute_units
                 1000000,    // very high price
+                option::none(), // referrer
+                0,          // referral_fee_share_bps
                 &random,
                 test::ctx(&mut scenario)
             );
@@ -2018,7 +3959,7 @@ module atoma::db_tests {
                 &mut db,
                 &mut node_badge,
                 1,       // task_small_id
-                1000     // price_per_one_million_compute_units
+                1000,    // price_per_one_million_compute_units
             );
             test::return_shared(db);
             test::return_to_sender(&scenario, node_badge);
@@ -2037,6 +3978,8 @@ module atoma::db_tests {
                 1,          // task_small_id
                 100,        // num_compute_units
                 1000,         // price
+                option::none(), // referrer
+                0,          // referral_fee_share_bps
                 &random,
                 test::ctx(&mut scenario)
             );
@@ -2067,6 +4010,8 @@ module atoma::db_tests {
                 1,          // task_small_id
                 200,        // different num_compute_units
                 2000,         // different price
+                option::none(), // referrer
+                0,          // referral_fee_share_bps
                 &random,
                 test::ctx(&mut scenario)
             );
@@ -2133,7 +4078,7 @@ module atoma::db_tests {
                 &mut db,
                 &mut node_badge,
                 1,       // task_small_id
-                1000     // price_per_one_million_compute_units (high price)
+                1000,    // price_per_one_million_compute_units (high price)
             );
             test::return_shared(db);
             test::return_to_sender(&scenario, node_badge);
@@ -2153,6 +4098,8 @@ module atoma::db_tests {
                 1,          // task_small_id
                 50,         // num_compute_units
                 50,         // price_per_one_million_compute_units (lower than node's price)
+                option::none(), // referrer
+                0,          // referral_fee_share_bps
                 &random,
                 test::ctx(&mut scenario)
             );
@@ -2164,6 +4111,87 @@ module atoma::db_tests {
         test::end(scenario);
     }
 
+    #[test]
+    fun test_try_settle_stack_with_configured_dispute_window() {
+        let mut scenario = setup_test();
+        setup_task_with_subscribed_node(&mut scenario);
+        mint_test_tokens(&mut scenario, USER, MINT_AMOUNT);
+
+        test::next_tx(&mut scenario, SYSTEM);
+        {
+            let mut db = test::take_shared<AtomaDb>(&scenario);
+            let manager_badge = test::take_from_sender<AtomaManagerBadge>(&scenario);
+            db::set_stack_dispute_window(&mut db, &manager_badge, 3);
+            assert!(db::get_stack_dispute_window_epochs(&db) == 3, 0);
+            test::return_shared(db);
+            test::return_to_sender(&scenario, manager_badge);
+        };
+
+        test::next_tx(&mut scenario, USER);
+        {
+            let mut db = test::take_shared<AtomaDb>(&scenario);
+            let mut wallet = test::take_from_sender<Coin<USDC>>(&scenario);
+            let random = test::take_shared<Random>(&scenario);
+            db::acquire_new_stack_entry(
+                &mut db,
+                &mut wallet,
+                1,          // task_small_id
+                50,         // num_compute_units
+                1000,       // price
+                option::none(), // referrer
+                0,          // referral_fee_share_bps
+                &random,
+                test::ctx(&mut scenario)
+            );
+            test::return_shared(db);
+            test::return_shared(random);
+            test::return_to_sender(&scenario, wallet);
+        };
+
+        test::next_tx(&mut scenario, NODE);
+        {
+            let mut db = test::take_shared<AtomaDb>(&scenario);
+            let node_badge = test::take_from_sender<NodeBadge>(&scenario);
+            let random = test::take_shared<Random>(&scenario);
+
+            let proof = x"0123456789ABCDEF0123456789ABCDEF0123456789ABCDEF0123456789ABCDEF";
+            let leaf = x"FEDCBA9876543210FEDCBA9876543210FEDCBA9876543210FEDCBA9876543210";
+            db::try_settle_stack(
+                &mut db,
+                &node_badge,
+                1,          // stack_small_id
+                50,         // num_claimed_compute_units
+                proof,      // committed_stack_proof
+                leaf,       // stack_merkle_leaf
+                &random,
+                test::ctx(&mut scenario)
+            );
+
+            let settlement = db::get_stack_settlement(&db, 1);
+            assert!(db::confirm_stack_settlement_ticket_dispute_epoch(settlement, 3), 1);
+
+            test::return_shared(db);
+            test::return_shared(random);
+            test::return_to_sender(&scenario, node_badge);
+        };
+        test::end(scenario);
+    }
+
+    #[test]
+    #[expected_failure(abort_code = EInvalidStackDisputeWindow)]
+    fun test_set_stack_dispute_window_to_zero() {
+        let mut scenario = setup_test();
+        test::next_tx(&mut scenario, SYSTEM);
+        {
+            let mut db = test::take_shared<AtomaDb>(&scenario);
+            let manager_badge = test::take_from_sender<AtomaManagerBadge>(&scenario);
+            db::set_stack_dispute_window(&mut db, &manager_badge, 0);
+            test::return_shared(db);
+            test::return_to_sender(&scenario, manager_badge);
+        };
+        test::end(scenario);
+    }
+
     #[test]
     fun test_try_settle_stack_basic_success() {
         let mut scenario = setup_test();
@@ -2209,7 +4237,7 @@ module atoma::db_tests {
                 &mut db,
                 &mut node_badge,
                 1,       // task_small_id
-                1000     // price_per_one_million_compute_units
+                1000,    // price_per_one_million_compute_units
             );
             test::return_shared(db);
             test::return_to_sender(&scenario, node_badge);
@@ -2228,6 +4256,8 @@ module atoma::db_tests {
                 1,          // task_small_id
                 50,         // num_compute_units
                 1000,         // price
+                option::none(), // referrer
+                0,          // referral_fee_share_bps
                 &random,
                 test::ctx(&mut scenario)
             );
@@ -2322,6 +4352,8 @@ module atoma::db_tests {
                 1,          // task_small_id
                 50,         // num_compute_units
                 10,         // price
+                option::none(), // referrer
+                0,          // referral_fee_share_bps
                 &random,
                 test::ctx(&mut scenario)
             );
@@ -2412,7 +4444,7 @@ module atoma::db_tests {
                 &mut db,
                 &mut node_badge,
                 1,       // task_small_id
-                100     // price_per_one_million_compute_units
+                100,    // price_per_one_million_compute_units
             );
             test::return_shared(db);
             test::return_to_sender(&scenario, node_badge);
@@ -2428,7 +4460,7 @@ module atoma::db_tests {
                 &mut db,
                 &mut node_badge,
                 1,       // task_small_id
-                1000     // price_per_one_million_compute_units
+                1000,    // price_per_one_million_compute_units
             );
             test::return_shared(db);
             test::return_to_sender(&scenario, node_badge);
@@ -2447,6 +4479,8 @@ module atoma::db_tests {
                 1,          // task_small_id
                 50,         // num_compute_units
                 100,         // price
+                option::none(), // referrer
+                0,          // referral_fee_share_bps
                 &random,
                 test::ctx(&mut scenario)
             );
@@ -2530,7 +4564,7 @@ module atoma::db_tests {
                 &mut db,
                 &mut node_badge,
                 1,       // task_small_id
-                1000     // price_per_one_million_compute_units
+                1000,    // price_per_one_million_compute_units
             );
             test::return_shared(db);
             test::return_to_sender(&scenario, node_badge);
@@ -2549,6 +4583,8 @@ module atoma::db_tests {
                 1,          // task_small_id
                 50,         // num_compute_units
                 1000,         // price
+                option::none(), // referrer
+                0,          // referral_fee_share_bps
                 &random,
                 test::ctx(&mut scenario)
             );
@@ -2643,6 +4679,8 @@ module atoma::db_tests {
                 1, 
                 50, 
                 1000, 
+                option::none(), // referrer
+                0,          // referral_fee_share_bps
                 &random, 
                 test::ctx(&mut scenario)
             );
@@ -2740,6 +4778,8 @@ module atoma::db_tests {
                 1, 
                 50, 
                 1000, 
+                option::none(), // referrer
+                0,          // referral_fee_share_bps
                 &random, 
                 test::ctx(&mut scenario)
             );
@@ -2821,6 +4861,8 @@ module atoma::db_tests {
                 1, 
                 50, 
                 1000, 
+                option::none(), // referrer
+                0,          // referral_fee_share_bps
                 &random, 
                 test::ctx(&mut scenario)
             );
@@ -2908,6 +4950,8 @@ module atoma::db_tests {
                 1, 
                 50, 
                 1000, 
+                option::none(), // referrer
+                0,          // referral_fee_share_bps
                 &random, 
                 test::ctx(&mut scenario)
             );
@@ -2997,6 +5041,8 @@ module atoma::db_tests {
                     1, 
                     50, 
                     1000, 
+                    option::none(), // referrer
+                    0,          // referral_fee_share_bps
                     &random, 
                     test::ctx(&mut scenario)
                 );
@@ -3094,6 +5140,8 @@ module atoma::db_tests {
                 1, 
                 50, 
                 1000, 
+                option::none(), // referrer
+                0,          // referral_fee_share_bps
                 &random, 
                 test::ctx(&mut scenario)
             );
@@ -3154,4 +5202,49 @@ module atoma::db_tests {
         
         test::end(scenario);
     }
+
+    // The CLI's `sampling` module mirrors these functions, keep the vectors
+    // in sync with its docs.
+
+    #[test]
+    fun test_pick_weighted_index_vectors() {
+        // the ABC echelons from the `select_eligible_echelon_at_random` docs
+        let weights = vector[10, 1, 35];
+        assert!(gate::pick_weighted_index(&weights, 0) == 0, 0);
+        assert!(gate::pick_weighted_index(&weights, 9) == 0, 1);
+        assert!(gate::pick_weighted_index(&weights, 10) == 1, 2);
+        assert!(gate::pick_weighted_index(&weights, 11) == 2, 3);
+        assert!(gate::pick_weighted_index(&weights, 45) == 2, 4);
+        // wraps around
+        assert!(gate::pick_weighted_index(&weights, 46) == 0, 5);
+        assert!(
+            gate::pick_weighted_index(
+                &weights,
+                115792089237316195423570985008687907853269984665640564039457584007913129639935,
+            ) == 0,
+            6,
+        );
+    }
+
+    #[test]
+    fun test_latency_weighted_performance_vectors() {
+        assert!(gate::latency_weighted_performance(1000, option::some(500)) == 2_000_000, 0);
+        // unmeasured echelons count as 1 s
+        assert!(gate::latency_weighted_performance(1000, option::none()) == 1_000_000, 1);
+        assert!(gate::latency_weighted_performance(7, option::some(0)) == 7_000_000, 2);
+        // never rounds down to zero
+        assert!(gate::latency_weighted_performance(1, option::some(2_000_000)) == 1, 3);
+    }
+
+    #[test]
+    fun test_unique_sample_node_index_vectors() {
+        // 10 nodes in chunks of 4, 3 and 3, counted from the end
+        assert!(db::unique_sample_node_index(10, 3, 0, 5) == 8, 0);
+        assert!(db::unique_sample_node_index(10, 3, 1, 5) == 3, 1);
+        assert!(db::unique_sample_node_index(10, 3, 2, 2) == 0, 2);
+        assert!(db::unique_sample_node_index(10, 3, 2, 3) == 2, 3);
+        // fewer nodes than requested, a chunk per node
+        assert!(db::unique_sample_node_index(2, 3, 0, 7) == 1, 4);
+        assert!(db::unique_sample_node_index(2, 3, 1, 7) == 0, 5);
+    }
 }
@@ -1,4 +1,6 @@
 module toma::toma {
+    use std::ascii;
+    use sui::balance::Balance;
     use sui::coin;
     use sui::package::Publisher;
 
@@ -7,6 +9,10 @@ module toma::toma {
     const NAME: vector<u8> = b"TOMA";
     const DESCRIPTION: vector<u8> = b"Atoma network coin";
 
+    const ENotPublisher: u64 = 0;
+    const EDecimalsMismatch: u64 = 1;
+    const ENotEnoughMigrationReserve: u64 = 2;
+
     /// The coin type.
     public struct TOMA has drop {}
 
@@ -17,12 +23,38 @@ module toma::toma {
         metadata: ID,
     }
 
+    /// Emitted when the publisher opens a migration from an older generation
+    /// of the coin.
+    public struct MigrationCreatedEvent has copy, drop {
+        migration: ID,
+        /// Type name of the older coin, eg. `0x...::toma::TOMA` without the
+        /// `0x` prefix.
+        old_coin_type: ascii::String,
+    }
+
+    /// Emitted for every swap of older coins for TOMA.
+    public struct CoinsMigratedEvent has copy, drop {
+        migration: ID,
+        amount: u64,
+    }
+
     /// Used to mint TOMA tokens.
     public struct Faucet has key, store {
         id: UID,
         treasury: Option<coin::TreasuryCap<TOMA>>,
     }
 
+    /// Swaps coins of an older generation of TOMA, ie. of a package that was
+    /// published anew rather than upgraded, for TOMA one to one.
+    ///
+    /// The swapped TOMA is paid out of a reserve that the publisher funds,
+    /// and the older coins are locked in here for good.
+    public struct Migration<phantom Old> has key {
+        id: UID,
+        reserve: Balance<TOMA>,
+        migrated: Balance<Old>,
+    }
+
     /// A treasury cap is sent to the publisher, who then controls minting and
     /// burning of TOMA tokens.
     fun init(otw: TOMA, ctx: &mut TxContext) {
@@ -69,10 +101,74 @@ module toma::toma {
         transfer::public_transfer(treasury, ctx.sender());
     }
 
+    /// Opens a migration from the `Old` coin with `amount` of TOMA split off
+    /// `wallet` as its reserve.
+    /// Both coins must have the same decimals for a one to one swap.
+    entry fun create_migration<Old>(
+        publisher: &Publisher,
+        old_metadata: &coin::CoinMetadata<Old>,
+        wallet: &mut coin::Coin<TOMA>,
+        amount: u64,
+        ctx: &mut TxContext,
+    ) {
+        assert!(sui::package::from_package<TOMA>(publisher), ENotPublisher);
+        assert!(old_metadata.get_decimals() == DECIMALS, EDecimalsMismatch);
+
+        let migration = Migration<Old> {
+            id: object::new(ctx),
+            reserve: wallet.split(amount, ctx).into_balance(),
+            migrated: sui::balance::zero(),
+        };
+        sui::event::emit(MigrationCreatedEvent {
+            migration: object::id(&migration),
+            old_coin_type: std::type_name::get<Old>().into_string(),
+        });
+        transfer::share_object(migration);
+    }
+
+    /// Tops up the reserve of a migration.
+    entry fun fund_migration<Old>(
+        self: &mut Migration<Old>,
+        wallet: &mut coin::Coin<TOMA>,
+        amount: u64,
+        ctx: &mut TxContext,
+    ) {
+        self.reserve.join(wallet.split(amount, ctx).into_balance());
+    }
+
+    /// Swaps the older coin for the same amount of TOMA, which is sent to the
+    /// sender.
+    entry fun migrate<Old>(
+        self: &mut Migration<Old>,
+        old: coin::Coin<Old>,
+        ctx: &mut TxContext,
+    ) {
+        let amount = old.value();
+        assert!(self.reserve.value() >= amount, ENotEnoughMigrationReserve);
+
+        self.migrated.join(old.into_balance());
+        let wallet = coin::from_balance(self.reserve.split(amount), ctx);
+        transfer::public_transfer(wallet, ctx.sender());
+
+        sui::event::emit(CoinsMigratedEvent {
+            migration: object::id(self),
+            amount,
+        });
+    }
+
     /// Mints some TOMA if the faucet is enabled.
     entry fun faucet(faucet: &mut Faucet, amount: u64, ctx: &mut TxContext) {
         let treasury = faucet.treasury.borrow_mut();
         let wallet = treasury.mint(amount, ctx);
         transfer::public_transfer(wallet, ctx.sender());
     }
+
+    /// Mints some TOMA to the recipient if the faucet is enabled.
+    entry fun faucet_to(
+        faucet: &mut Faucet, amount: u64, recipient: address, ctx: &mut TxContext,
+    ) {
+        let treasury = faucet.treasury.borrow_mut();
+        let wallet = treasury.mint(amount, ctx);
+        transfer::public_transfer(wallet, recipient);
+    }
 }